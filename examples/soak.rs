@@ -0,0 +1,409 @@
+//! Soak test for session storages. This runs a sustained workload against a storage backend -
+//! creating, reading, and refreshing sessions from many concurrent workers, along with periodic
+//! invalidation storms that delete a batch of sessions at once - and reports the latency
+//! percentiles and error rates of each operation. Use it to validate the sizing of your session
+//! store (e.g. the connection pool size or the database instance) before going to production.
+//!
+//! ```sh
+//! # in-memory storage (the default)
+//! cargo run --release --example soak -- --duration 30 --concurrency 64
+//!
+//! # Redis
+//! cargo run --release --example soak --features redis_fred -- \
+//!     --backend redis --url redis://localhost:6379
+//!
+//! # Postgres (the data column must be `bytea`) or SQLite (the data column must be `BLOB`)
+//! cargo run --release --example soak --features sqlx_postgres -- \
+//!     --backend postgres --url postgres://localhost/app --table sessions
+//! ```
+//!
+//! Options (all optional):
+//!
+//! | Option | Default | Description |
+//! |--------|---------|-------------|
+//! | `--backend` | `memory` | `memory`, `redis`, `postgres`, or `sqlite` |
+//! | `--url` | | Connection URL of the backend |
+//! | `--table` | `sessions` | Session table of the SQL backends |
+//! | `--duration` | `30` | Duration of the test in seconds |
+//! | `--concurrency` | `16` | Number of concurrent workers |
+//! | `--payload` | `512` | Size of the session data in bytes |
+//! | `--ttl` | `3600` | Session TTL in seconds |
+//! | `--creates` | `10` | Relative weight of session creates |
+//! | `--reads` | `70` | Relative weight of session reads |
+//! | `--refreshes` | `20` | Relative weight of rolling refreshes (reads that reset the TTL) |
+//! | `--storm-interval` | `10` | Seconds between invalidation storms (`0` to disable) |
+//! | `--storm-size` | `500` | Number of sessions deleted in each storm |
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::{
+    distr::{Alphanumeric, SampleString},
+    Rng,
+};
+use rocket::{futures::future::join_all, tokio};
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{erased::ErasedPayload, memory::MemoryStorage, NoopSessionContext, SessionStorage},
+};
+
+type Storage = Arc<dyn SessionStorage<ErasedPayload>>;
+
+/// Maximum number of session IDs kept for reads and refreshes
+const MAX_LIVE_SESSIONS: usize = 100_000;
+
+#[derive(Debug)]
+struct SoakConfig {
+    backend: String,
+    url: Option<String>,
+    #[cfg_attr(
+        not(any(feature = "sqlx_postgres", feature = "sqlx_sqlite")),
+        allow(dead_code)
+    )]
+    table: String,
+    duration: Duration,
+    concurrency: usize,
+    payload: usize,
+    ttl: u32,
+    creates: u32,
+    reads: u32,
+    refreshes: u32,
+    storm_interval: Duration,
+    storm_size: usize,
+}
+
+impl SoakConfig {
+    fn from_args() -> Self {
+        let mut args: HashMap<String, String> = HashMap::new();
+        let mut iter = std::env::args().skip(1);
+        while let Some(key) = iter.next() {
+            let Some(key) = key.strip_prefix("--") else {
+                panic!("Unexpected argument '{key}'");
+            };
+            let value = iter
+                .next()
+                .unwrap_or_else(|| panic!("Missing value for --{key}"));
+            args.insert(key.to_owned(), value);
+        }
+        let mut take = |key: &str, default: &str| args.remove(key).unwrap_or(default.to_owned());
+        let mut number = |key: &str, default: u64| -> u64 {
+            let value = take(key, &default.to_string());
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid number for --{key}: '{value}'"))
+        };
+
+        let config = Self {
+            duration: Duration::from_secs(number("duration", 30)),
+            concurrency: number("concurrency", 16) as usize,
+            payload: number("payload", 512) as usize,
+            ttl: number("ttl", 3600) as u32,
+            creates: number("creates", 10) as u32,
+            reads: number("reads", 70) as u32,
+            refreshes: number("refreshes", 20) as u32,
+            storm_interval: Duration::from_secs(number("storm-interval", 10)),
+            storm_size: number("storm-size", 500) as usize,
+            backend: take("backend", "memory"),
+            url: args.remove("url"),
+            table: args.remove("table").unwrap_or("sessions".to_owned()),
+        };
+        if let Some(key) = args.keys().next() {
+            panic!("Unknown option --{key}");
+        }
+        assert!(
+            config.creates + config.reads + config.refreshes > 0,
+            "At least one operation weight must be positive"
+        );
+        config
+    }
+}
+
+/// Create the storage of the chosen backend
+async fn create_storage(config: &SoakConfig) -> Storage {
+    let url = || {
+        config
+            .url
+            .clone()
+            .expect("--url is required for this backend")
+    };
+    match config.backend.as_str() {
+        "memory" => Arc::new(MemoryStorage::<ErasedPayload>::default()),
+        #[cfg(feature = "redis_fred")]
+        "redis" => {
+            use fred::prelude::*;
+            let config = Config::from_url(&url()).expect("should parse Redis URL");
+            let pool = Builder::from_config(config)
+                .build_pool(8)
+                .expect("should build Redis pool");
+            pool.init().await.expect("should connect to Redis");
+            Arc::new(
+                rocket_flex_session::storage::redis::RedisFredStorage::builder()
+                    .pool(pool)
+                    .prefix("soak:")
+                    .build(),
+            )
+        }
+        #[cfg(feature = "sqlx_postgres")]
+        "postgres" => {
+            let pool = sqlx::PgPool::connect(&url())
+                .await
+                .expect("should connect to Postgres");
+            Arc::new(
+                rocket_flex_session::storage::sqlx::SqlxPostgresStorage::builder()
+                    .pool(pool)
+                    .table_name(config.table.as_str())
+                    .build(),
+            )
+        }
+        #[cfg(feature = "sqlx_sqlite")]
+        "sqlite" => {
+            use std::str::FromStr;
+
+            let options = sqlx::sqlite::SqliteConnectOptions::from_str(&url())
+                .expect("should parse the SQLite URL")
+                .busy_timeout(Duration::from_secs(5));
+            let pool = sqlx::SqlitePool::connect_with(options)
+                .await
+                .expect("should connect to SQLite");
+            Arc::new(
+                rocket_flex_session::storage::sqlx::SqlxSqliteStorage::builder()
+                    .pool(pool)
+                    .table_name(config.table.as_str())
+                    .wal(true)
+                    .build(),
+            )
+        }
+        backend => {
+            let _ = url;
+            panic!("Unknown backend '{backend}', or its feature isn't enabled")
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Operation {
+    Create,
+    Read,
+    Refresh,
+    Invalidate,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Create => "create",
+            Self::Read => "read",
+            Self::Refresh => "refresh",
+            Self::Invalidate => "invalidate",
+        };
+        f.pad(name)
+    }
+}
+
+/// Results of an operation
+#[derive(Default)]
+struct OperationStats {
+    latencies: Vec<Duration>,
+    /// Reads of sessions that were already invalidated
+    misses: u64,
+    errors: u64,
+}
+
+#[derive(Default)]
+struct Stats(HashMap<Operation, OperationStats>);
+
+impl Stats {
+    fn record<R>(
+        &mut self,
+        operation: Operation,
+        started: Instant,
+        result: &Result<R, SessionError>,
+    ) {
+        let stats = self.0.entry(operation).or_default();
+        stats.latencies.push(started.elapsed());
+        match result {
+            Ok(_) => {}
+            Err(SessionError::NotFound | SessionError::Expired) => stats.misses += 1,
+            Err(e) => {
+                stats.errors += 1;
+                if stats.errors == 1 {
+                    eprintln!("First {operation} error: {e}");
+                }
+            }
+        }
+    }
+
+    fn merge(&mut self, other: Stats) {
+        for (operation, other) in other.0 {
+            let stats = self.0.entry(operation).or_default();
+            stats.latencies.extend(other.latencies);
+            stats.misses += other.misses;
+            stats.errors += other.errors;
+        }
+    }
+
+    fn report(mut self, elapsed: Duration) {
+        println!(
+            "\n{:<10} {:>9} {:>9} {:>8} {:>8} {:>9} {:>9} {:>9} {:>9}",
+            "operation", "count", "ops/s", "errors", "misses", "p50", "p90", "p99", "max"
+        );
+        let mut operations: Vec<_> = self.0.keys().copied().collect();
+        operations.sort();
+        for operation in operations {
+            let stats = self.0.get_mut(&operation).expect("should exist");
+            stats.latencies.sort();
+            let count = stats.latencies.len();
+            let percentile = |p: f64| {
+                let index = ((count as f64 * p).ceil() as usize).clamp(1, count) - 1;
+                format!("{:.2?}", stats.latencies[index])
+            };
+            println!(
+                "{:<10} {:>9} {:>9.0} {:>7.2}% {:>7.2}% {:>9} {:>9} {:>9} {:>9}",
+                operation,
+                count,
+                count as f64 / elapsed.as_secs_f64(),
+                stats.errors as f64 * 100.0 / count as f64,
+                stats.misses as f64 * 100.0 / count as f64,
+                percentile(0.5),
+                percentile(0.9),
+                percentile(0.99),
+                percentile(1.0),
+            );
+        }
+    }
+}
+
+/// IDs of the sessions that were created and not invalidated yet
+#[derive(Default)]
+struct LiveSessions(Mutex<Vec<String>>);
+
+impl LiveSessions {
+    fn add(&self, id: String) {
+        let mut ids = self.0.lock().unwrap();
+        if ids.len() < MAX_LIVE_SESSIONS {
+            ids.push(id);
+        } else {
+            let index = rand::rng().random_range(0..ids.len());
+            ids[index] = id;
+        }
+    }
+
+    fn random(&self) -> Option<String> {
+        let ids = self.0.lock().unwrap();
+        (!ids.is_empty()).then(|| ids[rand::rng().random_range(0..ids.len())].clone())
+    }
+
+    fn take(&self, count: usize) -> Vec<String> {
+        let mut ids = self.0.lock().unwrap();
+        let count = count.min(ids.len());
+        let start = rand::rng().random_range(0..=ids.len() - count);
+        ids.drain(start..start + count).collect()
+    }
+}
+
+async fn worker(
+    storage: Storage,
+    config: Arc<SoakConfig>,
+    live: Arc<LiveSessions>,
+    until: Instant,
+) -> Stats {
+    let mut stats = Stats::default();
+    let total_weight = config.creates + config.reads + config.refreshes;
+    let payload = ErasedPayload(vec![b'x'; config.payload]);
+    while Instant::now() < until {
+        let roll = rand::rng().random_range(0..total_weight);
+        let existing = live.random();
+        let started = Instant::now();
+        match existing {
+            Some(id) if roll >= config.creates + config.reads => {
+                let result = storage
+                    .load(&id, Some(config.ttl), &NoopSessionContext)
+                    .await;
+                stats.record(Operation::Refresh, started, &result);
+            }
+            Some(id) if roll >= config.creates => {
+                let result = storage.load(&id, None, &NoopSessionContext).await;
+                stats.record(Operation::Read, started, &result);
+            }
+            _ => {
+                let id = Alphanumeric.sample_string(&mut rand::rng(), 20);
+                let result = storage.save(&id, payload.clone(), config.ttl).await;
+                stats.record(Operation::Create, started, &result);
+                if result.is_ok() {
+                    live.add(id);
+                }
+            }
+        }
+        // Let the invalidation storms run, even if the storage never yields (e.g. memory)
+        tokio::task::yield_now().await;
+    }
+    stats
+}
+
+/// Periodically delete a batch of sessions at once
+async fn invalidation_storms(
+    storage: Storage,
+    config: Arc<SoakConfig>,
+    live: Arc<LiveSessions>,
+    until: Instant,
+) -> Stats {
+    let mut stats = Stats::default();
+    if config.storm_interval.is_zero() {
+        return stats;
+    }
+    loop {
+        tokio::time::sleep(config.storm_interval).await;
+        if Instant::now() >= until {
+            return stats;
+        }
+        let ids = live.take(config.storm_size);
+        println!("Invalidation storm: deleting {} sessions", ids.len());
+        let results = join_all(ids.iter().map(|id| {
+            let storage = storage.clone();
+            async move {
+                let started = Instant::now();
+                let result = storage.delete_unreadable(id).await;
+                (started, result)
+            }
+        }))
+        .await;
+        for (started, result) in results {
+            stats.record(Operation::Invalidate, started, &result);
+        }
+    }
+}
+
+#[rocket::main]
+async fn main() {
+    let config = Arc::new(SoakConfig::from_args());
+    println!("Running soak test: {config:?}");
+
+    let storage = create_storage(&config).await;
+    storage.setup().await.expect("should set up storage");
+
+    let live = Arc::new(LiveSessions::default());
+    let started = Instant::now();
+    let until = started + config.duration;
+    let workers: Vec<_> = (0..config.concurrency)
+        .map(|_| tokio::spawn(worker(storage.clone(), config.clone(), live.clone(), until)))
+        .collect();
+    let storms = tokio::spawn(invalidation_storms(
+        storage.clone(),
+        config.clone(),
+        live,
+        until,
+    ));
+
+    let mut stats = Stats::default();
+    for result in join_all(workers).await {
+        stats.merge(result.expect("worker should not panic"));
+    }
+    stats.merge(storms.await.expect("storms should not panic"));
+    stats.report(started.elapsed());
+
+    storage.shutdown().await.expect("should shut down storage");
+}
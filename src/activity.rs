@@ -0,0 +1,57 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{error::SessionError, Session};
+
+/// An entry in a session's recent activity log. Entries are recorded at the end
+/// of each request that used the session, if the
+/// [`activity_log_size`](crate::RocketFlexSessionOptions::activity_log_size) option is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActivityEntry {
+    /// Time of the request
+    pub timestamp: OffsetDateTime,
+    /// Path of the request
+    pub path: String,
+    /// Response status code
+    pub status: u16,
+}
+
+impl ActivityEntry {
+    /// Encode the entry as a string, in the format `<unix_timestamp>|<status>|<path>`
+    pub fn encode(&self) -> String {
+        format!(
+            "{}|{}|{}",
+            self.timestamp.unix_timestamp(),
+            self.status,
+            self.path
+        )
+    }
+
+    /// Decode an entry that was encoded with [`ActivityEntry::encode`]
+    pub fn decode(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, '|');
+        let timestamp = parts.next()?.parse().ok()?;
+        let status = parts.next()?.parse().ok()?;
+        let path = parts.next()?.to_owned();
+
+        Some(Self {
+            timestamp: OffsetDateTime::from_unix_timestamp(timestamp).ok()?,
+            path,
+            status,
+        })
+    }
+}
+
+impl<T> Session<'_, T>
+where
+    T: Send + Sync + Clone,
+{
+    /// Get the recent activity of the current session, most recent first. Returns an
+    /// empty list if there's no active session. The storage provider must support
+    /// activity logs (check the docs for the provider you're using).
+    pub async fn recent_activity(&self) -> Result<Vec<ActivityEntry>, SessionError> {
+        let Some(id) = self.id() else {
+            return Ok(Vec::new());
+        };
+        self.storage.recent_activity(&id).await
+    }
+}
@@ -0,0 +1,188 @@
+//! Admin helpers for data subject requests
+//!
+//! The [`SessionAdmin`] trait is implemented for all storage providers that support indexing,
+//! and can be used in subject-access request (data export) and right-to-erasure workflows,
+//! such as those required by the GDPR. The same helpers are also available on the
+//! [fairing](crate::RocketFlexSession), which will apply its `redact` setting to exports.
+//!
+//! # Example
+//! ```rust
+//! use rocket::serde::Serialize;
+//! use rocket_flex_session::{error::SessionResult, RocketFlexSession, SessionIdentifier};
+//!
+//! #[derive(Clone, Serialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct MySession {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for MySession {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! // Exports from the fairing only include the redacted description of the session data
+//! fn create_fairing() -> RocketFlexSession<MySession> {
+//!     RocketFlexSession::<MySession>::builder()
+//!         .redact(|_: &MySession| "user session".to_owned())
+//!         .build()
+//! }
+//!
+//! async fn handle_erasure_request(
+//!     fairing: &RocketFlexSession<MySession>,
+//!     user_id: String,
+//! ) -> SessionResult<()> {
+//!     let export = fairing.export_sessions_for_identifier(&user_id).await?;
+//!     println!("Sessions: {export}");
+//!     fairing.erase_identifier(&user_id).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use rocket::{
+    async_trait,
+    serde::{
+        json::serde_json::{self, json, Value},
+        Serialize,
+    },
+    time::OffsetDateTime,
+};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    storage::SessionStorageIndexed,
+    RocketFlexSession, SessionIdentifier,
+};
+
+/// Number of characters of the session ID to include in exports. The full session ID is
+/// a secret that could be used to hijack the session, so it isn't exported.
+const EXPORTED_ID_LENGTH: usize = 8;
+
+/// Admin operations on all sessions belonging to an identifier (e.g. a user ID).
+#[async_trait]
+pub trait SessionAdmin<T>: Send + Sync
+where
+    T: SessionIdentifier + Serialize + 'static,
+{
+    /// Export the data and metadata of all active sessions belonging to the identifier as JSON,
+    /// in the following format:
+    /// ```json
+    /// {
+    ///   "exported_at": 1700000000,
+    ///   "sessions": [
+    ///     {
+    ///       "id": "abcd1234",
+    ///       "ttl": 3600,
+    ///       "expires_at": 1700003600,
+    ///       "data": { ... },
+    ///       "activity": [{ "timestamp": 1699999000, "path": "/", "status": 200 }]
+    ///     }
+    ///   ]
+    /// }
+    /// ```
+    /// Only a prefix of each session ID is included. The `activity` field is only included
+    /// if the storage supports the activity log.
+    async fn export_sessions_for_identifier(&self, id: &T::Id) -> SessionResult<Value>;
+
+    /// Erase all sessions belonging to the identifier. Returns the number of sessions erased.
+    async fn erase_identifier(&self, id: &T::Id) -> SessionResult<u64>;
+}
+
+#[async_trait]
+impl<T, S> SessionAdmin<T> for S
+where
+    T: SessionIdentifier + Serialize + 'static,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    async fn export_sessions_for_identifier(&self, id: &T::Id) -> SessionResult<Value> {
+        export_sessions(self, id, OffsetDateTime::now_utc(), serialize_data).await
+    }
+
+    async fn erase_identifier(&self, id: &T::Id) -> SessionResult<u64> {
+        self.invalidate_sessions_by_identifier(id, None).await
+    }
+}
+
+impl<T> RocketFlexSession<T>
+where
+    T: SessionIdentifier + Serialize + 'static,
+{
+    /// Export all active sessions belonging to the identifier, in the same format as
+    /// [`SessionAdmin::export_sessions_for_identifier`]. If the `redact` setting of the
+    /// fairing is set, the session data is replaced with its redacted description. Timestamps
+    /// are based on the fairing's [clock](crate::clock).
+    pub async fn export_sessions_for_identifier(&self, id: &T::Id) -> SessionResult<Value> {
+        let storage = self
+            .indexed_storage()
+            .ok_or(SessionError::NonIndexedStorage)?;
+        let now = self.clock.now();
+        match &self.redact {
+            Some(redact) => {
+                export_sessions(storage, id, now, |data| Ok(Value::from(redact(data)))).await
+            }
+            None => export_sessions(storage, id, now, serialize_data).await,
+        }
+    }
+
+    /// Erase all sessions belonging to the identifier. Returns the number of sessions erased.
+    pub async fn erase_identifier(&self, id: &T::Id) -> SessionResult<u64> {
+        let storage = self
+            .indexed_storage()
+            .ok_or(SessionError::NonIndexedStorage)?;
+        storage.erase_identifier(id).await
+    }
+}
+
+/// Export the sessions belonging to the identifier at the given time, converting the session data with the given function
+async fn export_sessions<T, S>(
+    storage: &S,
+    id: &T::Id,
+    now: OffsetDateTime,
+    data_to_value: impl Fn(&T) -> SessionResult<Value> + Send + Sync,
+) -> SessionResult<Value>
+where
+    T: SessionIdentifier + 'static,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    let now = now.unix_timestamp();
+    let sessions = storage.get_sessions_by_identifier(id).await?;
+
+    let mut exported_sessions = Vec::with_capacity(sessions.len());
+    for (session_id, data, ttl) in sessions {
+        let mut session = json!({
+            "id": session_id.chars().take(EXPORTED_ID_LENGTH).collect::<String>(),
+            "ttl": ttl,
+            "expires_at": now + i64::from(ttl),
+            "data": data_to_value(&data)?,
+        });
+        match storage.recent_activity(&session_id).await {
+            Ok(entries) => {
+                session["activity"] = entries
+                    .into_iter()
+                    .map(|entry| {
+                        json!({
+                            "timestamp": entry.timestamp.unix_timestamp(),
+                            "path": entry.path,
+                            "status": entry.status,
+                        })
+                    })
+                    .collect();
+            }
+            Err(SessionError::Unsupported(_)) => {}
+            Err(e) => return Err(e),
+        }
+        exported_sessions.push(session);
+    }
+
+    Ok(json!({
+        "exported_at": now,
+        "sessions": exported_sessions,
+    }))
+}
+
+/// Serialize the session data as is
+fn serialize_data<T: Serialize>(data: &T) -> SessionResult<Value> {
+    serde_json::to_value(data).map_err(|e| SessionError::Serialization(Box::new(e)))
+}
@@ -0,0 +1,215 @@
+//! Session lifetime and churn analytics
+//!
+//! Set the `lifetime_metrics` of the [fairing](crate::RocketFlexSession) to count the sessions
+//! that are created and terminated (with the [reason](TerminationReason)), along with a
+//! histogram of how long the terminated sessions lasted. The lifetime of a session is only known
+//! if the storage provider tracks [creation times](crate::Session::created_at).
+//!
+//! For capacity planning, storages that support indexing can also compute [aggregates of the
+//! stored sessions](SessionAnalytics) with
+//! [`session_analytics`](crate::storage::SessionStorageIndexed::session_analytics), such as the
+//! average session age and the number of sessions per identifier:
+//!
+//! | Storage | Feature Flag |
+//! |---------|-------------|
+//! | [`storage::memory::MemoryStorageIndexed`](crate::storage::memory::MemoryStorageIndexed) | Built-in |
+//!
+//! # Example
+//! ```rust
+//! use rocket::time::{Duration, OffsetDateTime};
+//! use rocket_flex_session::{
+//!     analytics::{SessionLifetimeMetrics, TerminationReason},
+//!     storage::memory::MemoryStorageIndexed,
+//!     RocketFlexSession, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for User {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! let metrics = SessionLifetimeMetrics::default();
+//! let fairing = RocketFlexSession::<User>::builder()
+//!     .storage(MemoryStorageIndexed::default())
+//!     .lifetime_metrics(metrics.clone())
+//!     .build();
+//!
+//! // e.g. in a metrics endpoint
+//! println!(
+//!     "{} created, {} logged out, average lifetime {:?}",
+//!     metrics.created(),
+//!     metrics.terminated(TerminationReason::Deleted),
+//!     metrics.average_lifetime()
+//! );
+//! # rocket::async_test(async move {
+//! let storage = fairing.indexed_storage().unwrap();
+//! let since = OffsetDateTime::now_utc() - Duration::days(1);
+//! let analytics = storage.session_analytics(since).await.unwrap();
+//! println!(
+//!     "{} sessions for {} users, {} users active today",
+//!     analytics.sessions, analytics.identifiers, analytics.active_identifiers
+//! );
+//! # });
+//! ```
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Upper bounds of the buckets of the [lifetime histogram](SessionLifetimeMetrics::lifetime_histogram).
+/// Longer lifetimes are counted in a final bucket without an upper bound.
+pub const LIFETIME_BUCKETS: [Duration; 7] = [
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(4 * 60 * 60),
+    Duration::from_secs(24 * 60 * 60),
+    Duration::from_secs(7 * 24 * 60 * 60),
+];
+
+/// Why a session was terminated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TerminationReason {
+    /// The session was deleted during a request, e.g. when the user logged out
+    Deleted = 0,
+    /// The session was invalidated through the fairing, e.g. when its identifier was
+    /// [deprovisioned](crate::RocketFlexSession::deprovision_identifier)
+    Invalidated = 1,
+    /// A request had the cookie of a session that had expired (or was otherwise missing from
+    /// storage)
+    Expired = 2,
+    /// The session was deleted because its data failed [validation](crate::RocketFlexSession::builder)
+    /// or couldn't be deserialized
+    Rejected = 3,
+}
+
+impl TerminationReason {
+    /// All of the termination reasons
+    pub const ALL: [TerminationReason; 4] = [
+        Self::Deleted,
+        Self::Invalidated,
+        Self::Expired,
+        Self::Rejected,
+    ];
+}
+
+/// Counters of created and terminated sessions, and a histogram of session lifetimes.
+/// Clones share the same counters. See the [module docs](self).
+#[derive(Clone, Debug, Default)]
+pub struct SessionLifetimeMetrics {
+    counters: Arc<LifetimeCounters>,
+}
+
+#[derive(Debug, Default)]
+struct LifetimeCounters {
+    created: AtomicU64,
+    terminated: [AtomicU64; TerminationReason::ALL.len()],
+    buckets: [AtomicU64; LIFETIME_BUCKETS.len() + 1],
+    total_lifetime_secs: AtomicU64,
+}
+
+impl SessionLifetimeMetrics {
+    /// Number of new sessions that were saved
+    pub fn created(&self) -> u64 {
+        self.counters.created.load(Ordering::Relaxed)
+    }
+
+    /// Number of sessions terminated for the given reason
+    pub fn terminated(&self, reason: TerminationReason) -> u64 {
+        self.counters.terminated[reason as usize].load(Ordering::Relaxed)
+    }
+
+    /// Number of sessions terminated for any reason
+    pub fn total_terminated(&self) -> u64 {
+        TerminationReason::ALL
+            .into_iter()
+            .map(|reason| self.terminated(reason))
+            .sum()
+    }
+
+    /// Ratio of terminated to created sessions. A value above `1.0` means the number of
+    /// sessions is shrinking (or that sessions created before the metrics were set up are ending).
+    pub fn churn_rate(&self) -> f64 {
+        match self.created() {
+            0 => 0.0,
+            created => self.total_terminated() as f64 / created as f64,
+        }
+    }
+
+    /// Number of terminated sessions in each lifetime bucket, with the upper bound of the bucket.
+    /// The last bucket has no upper bound. See [`LIFETIME_BUCKETS`].
+    pub fn lifetime_histogram(&self) -> Vec<(Option<Duration>, u64)> {
+        let bounds = LIFETIME_BUCKETS.into_iter().map(Some).chain([None]);
+        bounds
+            .zip(&self.counters.buckets)
+            .map(|(bound, count)| (bound, count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Average lifetime of the terminated sessions with a known creation time
+    pub fn average_lifetime(&self) -> Option<Duration> {
+        let count: u64 = (self.counters.buckets.iter())
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum();
+        let total = self.counters.total_lifetime_secs.load(Ordering::Relaxed);
+        (count > 0).then(|| Duration::from_secs(total / count))
+    }
+
+    pub(crate) fn record_created(&self) {
+        self.counters.created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_terminated(&self, reason: TerminationReason, lifetime: Option<Duration>) {
+        self.record_terminated_count(reason, 1);
+        if let Some(lifetime) = lifetime {
+            let bucket = LIFETIME_BUCKETS
+                .iter()
+                .position(|bound| lifetime <= *bound)
+                .unwrap_or(LIFETIME_BUCKETS.len());
+            self.counters.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+            (self.counters.total_lifetime_secs).fetch_add(lifetime.as_secs(), Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn record_terminated_count(&self, reason: TerminationReason, count: u64) {
+        self.counters.terminated[reason as usize].fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+/// Aggregates of the stored sessions, computed by the storage with
+/// [`session_analytics`](crate::storage::SessionStorageIndexed::session_analytics)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionAnalytics {
+    /// Number of stored sessions with an identifier
+    pub sessions: u64,
+    /// Number of distinct identifiers with at least one stored session
+    pub identifiers: u64,
+    /// Number of identifiers that were [last seen](crate::presence) since the given time, if
+    /// the storage supports presence tracking
+    pub active_identifiers: u64,
+    /// Average age of the stored sessions with a known creation time
+    pub average_age: Option<Duration>,
+}
+
+impl SessionAnalytics {
+    /// Average number of sessions per identifier (e.g. devices per user)
+    pub fn sessions_per_identifier(&self) -> f64 {
+        match self.identifiers {
+            0 => 0.0,
+            identifiers => self.sessions as f64 / identifiers as f64,
+        }
+    }
+}
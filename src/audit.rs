@@ -0,0 +1,18 @@
+//! Audit events
+//!
+//! Set the `audit` hook of the [fairing](crate::RocketFlexSession) to forward these events to
+//! your audit log or SIEM.
+
+/// A security-relevant event performed through the fairing
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuditEvent {
+    /// An identifier (e.g. a user) was deprovisioned via
+    /// [`deprovision_identifier`](crate::RocketFlexSession::deprovision_identifier)
+    Deprovisioned {
+        /// The deprovisioned identifier
+        identifier: String,
+        /// Number of sessions invalidated
+        sessions_invalidated: u64,
+    },
+}
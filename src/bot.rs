@@ -0,0 +1,52 @@
+//! Bot and crawler detection
+//!
+//! Helpers that can be used with the `skip_request` setting of the [fairing](crate::RocketFlexSession),
+//! to avoid creating sessions for bots and crawlers.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{bot::is_likely_bot, RocketFlexSession};
+//!
+//! #[derive(Clone)]
+//! struct MySession {
+//!     user_id: String,
+//! }
+//!
+//! let fairing = RocketFlexSession::<MySession>::builder()
+//!     .skip_request(is_likely_bot)
+//!     .build();
+//! ```
+
+use rocket::Request;
+
+/// Common substrings found in the user agents of bots, crawlers, and HTTP tools
+const BOT_USER_AGENT_PATTERNS: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "facebookexternalhit",
+    "preview",
+    "headless",
+    "curl/",
+    "wget/",
+    "python-requests",
+    "go-http-client",
+];
+
+/// Heuristic check of whether the request is from a bot or crawler, based on the `User-Agent`
+/// header. Requests without a user agent are also considered bots.
+pub fn is_likely_bot(req: &Request<'_>) -> bool {
+    match req.headers().get_one("User-Agent") {
+        Some(user_agent) => is_bot_user_agent(user_agent),
+        None => true,
+    }
+}
+
+/// Heuristic check of whether the user agent belongs to a bot or crawler.
+pub fn is_bot_user_agent(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    BOT_USER_AGENT_PATTERNS
+        .iter()
+        .any(|pattern| user_agent.contains(pattern))
+}
@@ -0,0 +1,99 @@
+use crate::{error::SessionError, session_inner::generate_id, Session};
+
+/// A capability token derived from a session, e.g. for a file download link or a websocket
+/// auth ticket. See [`Session::create_capability`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capability {
+    /// ID of the session the token is bound to
+    pub session_id: String,
+    /// Scope of the token, e.g. `download:report-123`
+    pub scope: String,
+}
+
+impl Capability {
+    /// Encode the capability as a string, in the format `<session_id>|<scope>`
+    pub fn encode(&self) -> String {
+        format!("{}|{}", self.session_id, self.scope)
+    }
+
+    /// Decode a capability that was encoded with [`Capability::encode`]
+    pub fn decode(value: &str) -> Option<Self> {
+        let (session_id, scope) = value.split_once('|')?;
+        Some(Self {
+            session_id: session_id.to_owned(),
+            scope: scope.to_owned(),
+        })
+    }
+}
+
+impl<T> Session<'_, T>
+where
+    T: Send + Sync + Clone,
+{
+    /// Create a short-lived capability token bound to the current session, with the given scope and
+    /// TTL in seconds. The token can be shared where the session cookie isn't available (e.g. in a
+    /// download link or a websocket URL) without exposing the session ID, and is only valid while the
+    /// session is active. Returns `None` if there's no active session. The storage provider must
+    /// support capability tokens (check the docs for the provider you're using).
+    pub async fn create_capability(
+        &self,
+        scope: &str,
+        ttl: u32,
+    ) -> Result<Option<String>, SessionError> {
+        let Some(session_id) = self.id() else {
+            return Ok(None);
+        };
+        let token = format!("{}{}", generate_id(), generate_id());
+        let capability = Capability {
+            session_id,
+            scope: scope.to_owned(),
+        };
+        self.storage
+            .save_capability(&token, capability, ttl)
+            .await?;
+        Ok(Some(token))
+    }
+
+    /// Verify a capability token against storage. Returns the capability if the token exists, has
+    /// the given scope, and its session is still active. This doesn't require the session cookie
+    /// to be present in the request. Tokens of sessions that have ended are deleted.
+    pub async fn verify_capability(
+        &self,
+        token: &str,
+        scope: &str,
+    ) -> Result<Option<Capability>, SessionError> {
+        let capability = match self.storage.load_capability(token).await {
+            Ok(capability) => capability,
+            Err(SessionError::NotFound | SessionError::Expired) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if capability.scope != scope {
+            return Ok(None);
+        }
+        if !self
+            .storage
+            .exists(&capability.session_id, self.cookie_jar)
+            .await?
+        {
+            self.storage.delete_capability(token).await?;
+            return Ok(None);
+        }
+        Ok(Some(capability))
+    }
+
+    /// Verify a single-use capability token (e.g. a websocket auth ticket), and delete it.
+    /// The token is taken from storage in one atomic operation, so it can only be redeemed once,
+    /// even by concurrent requests. The storage provider must support single-use tokens.
+    /// See [`verify_capability`](Session::verify_capability).
+    pub async fn redeem_capability(
+        &self,
+        token: &str,
+        scope: &str,
+    ) -> Result<Option<Capability>, SessionError> {
+        if self.verify_capability(token, scope).await?.is_none() {
+            return Ok(None);
+        }
+        // Only one request takes the token, the others get `None`
+        self.storage.take_capability(token).await
+    }
+}
@@ -0,0 +1,92 @@
+use rocket::time::Duration;
+
+use crate::{
+    error::SessionError,
+    session::create_session_cookie,
+    session_inner::{generate_id, is_valid_id},
+    Session, SessionIdentifier,
+};
+
+/// Separator between the parent session ID and the child's own ID
+const CHILD_ID_SEPARATOR: char = '-';
+
+/// Get the ID of the parent session, if the session ID belongs to a child session
+/// spawned with [`Session::spawn_child`].
+pub fn parent_session_id(id: &str) -> Option<&str> {
+    id.rsplit_once(CHILD_ID_SEPARATOR).map(|(parent, _)| parent)
+}
+
+/// Implementation block for child sessions
+impl<T> Session<'_, T>
+where
+    T: SessionIdentifier,
+{
+    /// Spawn a child session linked to the current session, e.g. a limited-scope support or checkout
+    /// session. The child is saved to storage right away with the given data and TTL, and its ID is
+    /// added to the response in a separate cookie with the given name. Returns the child session ID,
+    /// or `None` if there's no current session.
+    ///
+    /// The child session ID contains the ID of the parent (see [`parent_session_id`]), so that children
+    /// can be [revoked](Session::revoke_children) along with the parent. If the child data has the
+    /// same identifier as the parent, the child is also invalidated along with all other sessions of the
+    /// identifier. Child sessions can't be used with cookie-based storage.
+    pub async fn spawn_child(
+        &self,
+        data: T,
+        cookie_name: &str,
+        ttl: u32,
+    ) -> Result<Option<String>, SessionError> {
+        let Some(parent_id) = self.id() else {
+            return Ok(None);
+        };
+        let child_id = format!("{parent_id}{CHILD_ID_SEPARATOR}{}", generate_id());
+        self.storage.save(&child_id, data, ttl).await?;
+
+        let mut cookie = create_session_cookie(&child_id, self.options, None);
+        cookie.set_name(cookie_name.to_owned());
+        cookie.set_max_age(Duration::seconds(ttl.into()));
+        self.options.cookie_key.add(self.cookie_jar, cookie);
+
+        Ok(Some(child_id))
+    }
+
+    /// Get the ID and data of the child session from the cookie with the given name, if
+    /// there is an active child session. This doesn't require the parent session to be present
+    /// in the request.
+    pub async fn get_child(&self, cookie_name: &str) -> Result<Option<(String, T)>, SessionError> {
+        let Some(cookie) = self.options.cookie_key.get(self.cookie_jar, cookie_name) else {
+            return Ok(None);
+        };
+        let child_id = cookie.value();
+        if !is_valid_id(child_id) || parent_session_id(child_id).is_none() {
+            return Err(SessionError::InvalidId);
+        }
+        match self.storage.load(child_id, None, self.cookie_jar).await {
+            Ok((data, _ttl)) => Ok(Some((child_id.to_owned(), data))),
+            Err(SessionError::NotFound | SessionError::Expired) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Revoke all child sessions of the current session (and their children), using the
+    /// identifier index. Returns the number of sessions revoked, or `None` if there's no
+    /// current session or it isn't indexed.
+    pub async fn revoke_children(&self) -> Result<Option<u64>, SessionError> {
+        let Some(parent_id) = self.id() else {
+            return Ok(None);
+        };
+        let Some(sessions) = self.get_all_sessions().await? else {
+            return Ok(None);
+        };
+
+        let child_prefix = format!("{parent_id}{CHILD_ID_SEPARATOR}");
+        let mut revoked = 0;
+        for (session_id, data, _ttl) in sessions {
+            if session_id.starts_with(&child_prefix) {
+                self.storage.delete(&session_id, data).await?;
+                revoked += 1;
+            }
+        }
+        Ok(Some(revoked))
+    }
+}
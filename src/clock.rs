@@ -0,0 +1,87 @@
+//! Clock used for session expiration
+//!
+//! By default, sessions use the system time. You can inject a different [`Clock`] via the
+//! fairing builder and the [cookie storage](crate::storage::cookie::CookieStorage) builder,
+//! which makes expiration logic testable without waiting for real time to pass.
+//!
+//! The clock only applies to the fairing (session expiration, activity timestamps and
+//! admin exports) and the cookie storage. Other storage providers still expire sessions
+//! using the system time or the TTL of the backing store, so a mock clock won't expire
+//! sessions stored in them.
+//!
+//! # Example
+//! ```rust
+//! use rocket::time::Duration;
+//! use rocket_flex_session::{
+//!     clock::MockClock, storage::cookie::CookieStorage, RocketFlexSession,
+//! };
+//!
+//! let clock = MockClock::default();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(CookieStorage::builder().clock(clock.clone()).build())
+//!     .clock(clock.clone())
+//!     .build();
+//!
+//! // ...later in a test, travel forward in time to expire the session
+//! clock.advance(Duration::hours(1));
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use rocket::time::{Duration, OffsetDateTime};
+
+/// A source of the current time
+pub trait Clock: Send + Sync {
+    /// Get the current time (in UTC)
+    fn now(&self) -> OffsetDateTime;
+}
+
+/// Clock using the system time. This is the default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// Clock that only moves when told to, for deterministic tests. Clones share the same time,
+/// so the same clock can be given to the fairing and the storage provider.
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    now: Arc<Mutex<OffsetDateTime>>,
+}
+
+impl MockClock {
+    /// Create a clock frozen at the given time
+    pub fn new(now: OffsetDateTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward (or backward, with a negative duration)
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now = now.saturating_add(duration);
+    }
+
+    /// Set the clock to the given time
+    pub fn set(&self, now: OffsetDateTime) {
+        *self.now.lock().unwrap() = now;
+    }
+}
+
+impl Default for MockClock {
+    /// Create a clock frozen at the current system time
+    fn default() -> Self {
+        Self::new(OffsetDateTime::now_utc())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.lock().unwrap()
+    }
+}
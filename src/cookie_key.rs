@@ -0,0 +1,120 @@
+use std::fmt;
+
+use cookie::Key;
+use rocket::http::{Cookie, CookieJar};
+
+/**
+The key used to protect the session cookie, set with the `cookie_key` option.
+
+By default, the session cookie is encrypted with Rocket's `secret_key`, like all of Rocket's
+private cookies. Use a dedicated key to rotate Rocket's secret key without logging out all
+users, or to rotate the session key without invalidating your other private cookies.
+
+# Example
+```rust
+use rocket_flex_session::{RocketFlexSession, SessionCookieKey};
+
+// e.g. loaded from an environment variable
+let secret = [7u8; 64];
+let fairing = RocketFlexSession::<String>::builder()
+    .with_options(|opt| {
+        opt.cookie_key = SessionCookieKey::private(&secret).expect("key should be valid");
+    })
+    .build();
+```
+*/
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum SessionCookieKey {
+    /// Encrypt the cookie with Rocket's `secret_key`, as a Rocket private cookie (the default)
+    #[default]
+    RocketSecretKey,
+    /// Encrypt and authenticate the cookie with a dedicated key, using AES-256-GCM
+    Private(Key),
+    /// Sign the cookie with a dedicated key, using HMAC-SHA256. The session ID is readable by
+    /// the client, but can't be tampered with.
+    Signed(Key),
+}
+
+impl SessionCookieKey {
+    /// Encrypt the cookie with a dedicated secret. As with Rocket's `secret_key`, secrets of 64
+    /// bytes or more are used as the master key, and shorter secrets are used to derive it.
+    /// Returns `None` if the secret is shorter than 32 bytes.
+    pub fn private(secret: &[u8]) -> Option<Self> {
+        derive_key(secret).map(Self::Private)
+    }
+
+    /// Sign the cookie with a dedicated secret. See [`SessionCookieKey::private`] for
+    /// the length of the secret.
+    pub fn signed(secret: &[u8]) -> Option<Self> {
+        derive_key(secret).map(Self::Signed)
+    }
+
+    /// Get the session cookie (including a cookie added during this request), if
+    /// it's present and was protected with this key
+    pub(crate) fn get(&self, jar: &CookieJar<'_>, name: &str) -> Option<Cookie<'static>> {
+        match self {
+            Self::RocketSecretKey => jar.get_private(name),
+            _ => self.verify(jar.get_pending(name)?),
+        }
+    }
+
+    /// Verify (and decrypt) a cookie protected with a dedicated key. Always returns `None` for
+    /// Rocket's secret key, as it isn't available outside of Rocket's cookie jar.
+    pub(crate) fn verify(&self, cookie: Cookie<'static>) -> Option<Cookie<'static>> {
+        let name = cookie.name().to_owned();
+        let mut cookies = cookie::CookieJar::new();
+        cookies.add_original(cookie);
+        match self {
+            Self::RocketSecretKey => None,
+            Self::Private(key) => cookies.private(key).get(&name),
+            Self::Signed(key) => cookies.signed(key).get(&name),
+        }
+    }
+
+    /// Add the session cookie, protected with this key
+    pub(crate) fn add(&self, jar: &CookieJar<'_>, cookie: Cookie<'static>) {
+        let key = match self {
+            Self::RocketSecretKey => return jar.add_private(cookie),
+            Self::Private(key) | Self::Signed(key) => key,
+        };
+        let name = cookie.name().to_owned();
+        let mut cookies = cookie::CookieJar::new();
+        match self {
+            Self::Signed(_) => cookies.signed_mut(key).add(cookie),
+            _ => cookies.private_mut(key).add(cookie),
+        }
+        if let Some(protected) = cookies.get(&name) {
+            jar.add(protected.clone());
+        }
+    }
+
+    /// Remove the session cookie
+    pub(crate) fn remove(&self, jar: &CookieJar<'_>, cookie: impl Into<Cookie<'static>>) {
+        match self {
+            Self::RocketSecretKey => jar.remove_private(cookie),
+            _ => jar.remove(cookie),
+        }
+    }
+}
+
+impl fmt::Debug for SessionCookieKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the key
+        let name = match self {
+            Self::RocketSecretKey => "RocketSecretKey",
+            Self::Private(_) => "Private",
+            Self::Signed(_) => "Signed",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Create the master key from a secret, as Rocket does with its `secret_key`
+pub(crate) fn derive_key(secret: &[u8]) -> Option<Key> {
+    match secret.len() {
+        0..=31 => None,
+        32..=63 => Some(Key::derive_from(secret)),
+        _ => Some(Key::from(secret)),
+    }
+}
@@ -0,0 +1,80 @@
+use std::{fmt, sync::Mutex};
+
+use rocket::Request;
+
+use crate::LogIdFormat;
+
+/**
+A hash of the session ID, to correlate requests to sessions (e.g. in reverse proxies and log
+pipelines) without exposing the session ID itself. This is the same hash that's written in the
+crate's logs with [`LogIdFormat::Hashed`], e.g. `"#3f2a9c1d0b4e5f67"`.
+
+Enable the `correlation_header` [option](crate::RocketFlexSessionOptions::correlation_header) to
+add the hash to the response, or the `correlation_local_cache` option to get it in your own code
+(e.g. a logging fairing) with [`SessionCorrelationId::get`]. The hash is set when the session is
+loaded by the request guard, and updated when the response is sent. If several session fairings
+are attached, the hash is from the last one that set it.
+
+# Example
+```rust
+use rocket::fairing::AdHoc;
+use rocket_flex_session::{RocketFlexSession, SessionCorrelationId};
+
+let session_fairing = RocketFlexSession::<String>::builder()
+    .with_options(|opt| {
+        opt.correlation_header = Some("X-Session-Hash".to_owned());
+        opt.correlation_local_cache = true;
+    })
+    .build();
+// Attach after the session fairing, so the hash is updated first
+let access_log = AdHoc::on_response("Access log", |req, res| {
+    Box::pin(async move {
+        let session = SessionCorrelationId::get(req);
+        println!("{} {} {:?}", req.uri(), res.status(), session);
+    })
+});
+let rocket = rocket::build()
+    .attach(session_fairing)
+    .attach(access_log);
+```
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SessionCorrelationId(String);
+
+impl SessionCorrelationId {
+    /// Hash a session ID
+    pub fn new(id: &str) -> Self {
+        Self(LogIdFormat::Hashed.format(id).into_owned())
+    }
+
+    /// The hash as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Get the hash of the request's session from Rocket's request local cache. This returns
+    /// `None` if there's no active session, or if the `correlation_local_cache` option isn't enabled.
+    pub fn get(req: &Request<'_>) -> Option<Self> {
+        req.local_cache(CorrelationSlot::default)
+            .0
+            .lock()
+            .unwrap()
+            .clone()
+    }
+}
+
+impl fmt::Display for SessionCorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Slot for the correlation ID in Rocket's request local cache
+#[derive(Default)]
+struct CorrelationSlot(Mutex<Option<SessionCorrelationId>>);
+
+/// Set the correlation ID of the request's session in Rocket's request local cache
+pub(crate) fn set_correlation_id(req: &Request<'_>, id: Option<&str>) {
+    let slot = req.local_cache(CorrelationSlot::default);
+    *slot.0.lock().unwrap() = id.map(SessionCorrelationId::new);
+}
@@ -0,0 +1,149 @@
+//! Deferred actions
+//!
+//! [`DeferredSession`] stores a queue of deferred actions (e.g. sending a verification email, or
+//! syncing the user's profile) alongside your session data. Actions are [deferred](Session::defer)
+//! during a request, saved to storage with the session, and drained later by a consumer:
+//! - In a later request of the same session, with [`Session::take_deferred`]
+//! - In a background task, with [`drain_deferred`] for all sessions of an identifier (requires an
+//!   [indexing](crate::SessionIdentifier) storage provider)
+//!
+//! Use a serializable action type (e.g. an enum) if your storage provider serializes the session data.
+//! This is a light-weight outbox - actions are only as durable as the session, and are lost if the
+//! session expires or is deleted before they're drained.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{deferred::DeferredSession, RocketFlexSession, Session};
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     email: String,
+//! }
+//!
+//! #[derive(Clone, Debug)]
+//! enum Action {
+//!     SendVerificationEmail(String),
+//! }
+//!
+//! #[rocket::post("/signup")]
+//! fn signup(mut session: Session<DeferredSession<User, Action>>) {
+//!     let user = User { email: "user@example.com".to_owned() };
+//!     session.set(DeferredSession::new(user.clone()));
+//!     session.defer(Action::SendVerificationEmail(user.email));
+//! }
+//!
+//! #[rocket::get("/")]
+//! fn index(mut session: Session<DeferredSession<User, Action>>) {
+//!     for action in session.take_deferred() {
+//!         println!("Running deferred action: {action:?}");
+//!     }
+//! }
+//!
+//! let fairing = RocketFlexSession::<DeferredSession<User, Action>>::default();
+//! ```
+
+use rocket::serde::{Deserialize, Serialize};
+
+use crate::{error::SessionResult, storage::SessionStorageIndexed, Session, SessionIdentifier};
+
+/// Session data wrapper that stores a queue of deferred actions alongside your session data.
+/// If your data implements [`SessionIdentifier`], the sessions will be indexed by the same identifier.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DeferredSession<T, A> {
+    /// Your session data
+    pub data: T,
+    /// Deferred actions, in the order they were deferred
+    #[serde(default = "Vec::new")]
+    pub actions: Vec<A>,
+}
+
+impl<T, A> DeferredSession<T, A> {
+    /// Create the session data with no deferred actions
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            actions: Vec::new(),
+        }
+    }
+}
+
+impl<T, A> SessionIdentifier for DeferredSession<T, A>
+where
+    T: SessionIdentifier,
+    A: Send + Sync + Clone,
+{
+    type Id = T::Id;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.data.identifier()
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.data.tags()
+    }
+}
+
+/// Implementation block for sessions with deferred actions
+impl<T, A> Session<'_, DeferredSession<T, A>>
+where
+    T: Send + Sync + Clone,
+    A: Send + Sync + Clone,
+{
+    /// Defer an action, which will be saved with the session. Returns `false` and has no effect
+    /// if there's no active session.
+    pub fn defer(&mut self, action: A) -> bool {
+        if self.tap(|session| session.is_none()) {
+            return false;
+        }
+        self.tap_mut(|session| {
+            if let Some(session) = session {
+                session.actions.push(action);
+            }
+        });
+        true
+    }
+
+    /// Get the deferred actions of the current session via cloning, without removing them.
+    pub fn deferred(&self) -> Vec<A> {
+        self.tap(|session| session.map(|s| s.actions.clone()).unwrap_or_default())
+    }
+
+    /// Take the deferred actions of the current session, removing them from the session.
+    /// The session is only updated if there were any actions.
+    pub fn take_deferred(&mut self) -> Vec<A> {
+        if self.tap(|session| session.map_or(true, |s| s.actions.is_empty())) {
+            return Vec::new();
+        }
+        self.tap_mut(|session| {
+            session
+                .as_mut()
+                .map(|s| std::mem::take(&mut s.actions))
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Drain the deferred actions of all active sessions of the identifier, e.g. in a background
+/// task. Returns the session ID and action of each deferred action. Sessions with actions are
+/// saved without them, keeping their TTL. Note that changes made by a concurrent request to
+/// one of these sessions may be overwritten.
+pub async fn drain_deferred<T, A>(
+    storage: &dyn SessionStorageIndexed<DeferredSession<T, A>>,
+    identifier: &T::Id,
+) -> SessionResult<Vec<(String, A)>>
+where
+    T: SessionIdentifier,
+    A: Send + Sync + Clone,
+{
+    let mut drained = Vec::new();
+    for (id, mut session, ttl) in storage.get_sessions_by_identifier(identifier).await? {
+        if session.actions.is_empty() {
+            continue;
+        }
+        let actions = std::mem::take(&mut session.actions);
+        storage.save(&id, session, ttl).await?;
+        drained.extend(actions.into_iter().map(|action| (id.clone(), action)));
+    }
+    Ok(drained)
+}
@@ -0,0 +1,102 @@
+use std::{collections::HashMap, future::Future, marker::PhantomData, sync::Mutex};
+
+use rocket::Request;
+
+use crate::Session;
+
+/**
+Cache for an object derived from the session, such as a `CurrentUser` built from the session data
+and a database lookup. The object is cached in Rocket's request local cache, keyed by the session
+ID, so several request guards (or a guard and a handler) that need it in the same request only
+derive it once.
+
+The cache only lasts for the request. If the object is derived from session data that changes
+during the request, [invalidate](SessionDerived::invalidate) it after the change.
+
+# Example
+```rust
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+};
+use rocket_flex_session::{Session, SessionDerived};
+
+#[derive(Clone)]
+struct CurrentUser {
+    id: String,
+    name: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CurrentUser {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let session = req.guard::<Session<String>>().await.expect("should not fail");
+        let user = SessionDerived::get_or_derive(req, &session, |user_id| async move {
+            // e.g. a database lookup
+            Ok::<_, &'static str>(CurrentUser { name: format!("User {user_id}"), id: user_id })
+        });
+        match user.await {
+            Ok(Some(user)) => Outcome::Success(user),
+            Ok(None) => Outcome::Error((Status::Unauthorized, "No active session")),
+            Err(e) => Outcome::Error((Status::InternalServerError, e)),
+        }
+    }
+}
+```
+*/
+pub struct SessionDerived<D> {
+    _derived: PhantomData<D>,
+}
+
+/// Derived objects of type `D` in Rocket's request local cache, keyed by session ID
+struct DerivedSlot<D>(Mutex<HashMap<String, D>>);
+
+impl<D> SessionDerived<D>
+where
+    D: Clone + Send + Sync + 'static,
+{
+    /// Get the object derived from the current session, deriving it from the session data with
+    /// the given function if it isn't cached yet. Errors aren't cached, so the object is derived
+    /// again in the next call. Returns `None` if there's no active session.
+    pub async fn get_or_derive<T, E, F, Fut>(
+        req: &Request<'_>,
+        session: &Session<'_, T>,
+        derive: F,
+    ) -> Result<Option<D>, E>
+    where
+        T: Send + Sync + Clone,
+        F: FnOnce(T) -> Fut,
+        Fut: Future<Output = Result<D, E>>,
+    {
+        let (Some(id), Some(data)) = (session.id(), session.get()) else {
+            return Ok(None);
+        };
+        if let Some(derived) = Self::get(req, &id) {
+            return Ok(Some(derived));
+        }
+        let derived = derive(data).await?;
+        Self::slot(req)
+            .0
+            .lock()
+            .unwrap()
+            .insert(id, derived.clone());
+        Ok(Some(derived))
+    }
+
+    /// Get the cached object derived from the session with the given ID, if any
+    pub fn get(req: &Request<'_>, session_id: &str) -> Option<D> {
+        Self::slot(req).0.lock().unwrap().get(session_id).cloned()
+    }
+
+    /// Remove the cached object derived from the session with the given ID, so that it's
+    /// derived again in the next call to [`get_or_derive`](Self::get_or_derive)
+    pub fn invalidate(req: &Request<'_>, session_id: &str) {
+        Self::slot(req).0.lock().unwrap().remove(session_id);
+    }
+
+    fn slot<'r>(req: &'r Request<'_>) -> &'r DerivedSlot<D> {
+        req.local_cache(|| DerivedSlot(Mutex::default()))
+    }
+}
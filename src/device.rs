@@ -0,0 +1,294 @@
+//! Device management helpers
+//!
+//! [`DeviceInfo`] describes the device, browser, and operating system of a request, parsed from
+//! its user agent. Store it alongside your session data with [`DeviceSession`], and with an
+//! [indexing](crate::SessionIdentifier) storage provider you can list the user's
+//! [active devices](Session::get_active_devices) for an "active devices" page.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     device::{DeviceInfo, DeviceSession},
+//!     storage::memory::MemoryStorageIndexed,
+//!     RocketFlexSession, Session, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for User {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! #[rocket::post("/login")]
+//! fn login(device: DeviceInfo, mut session: Session<DeviceSession<User>>) {
+//!     let user = User { user_id: "123".to_owned() };
+//!     session.set(DeviceSession::new(device, user));
+//! }
+//!
+//! #[rocket::get("/devices")]
+//! async fn devices(session: Session<'_, DeviceSession<User>>) -> String {
+//!     match session.get_active_devices().await {
+//!         Ok(Some(devices)) => devices
+//!             .iter()
+//!             .map(|device| device.device.label())
+//!             .collect::<Vec<_>>()
+//!             .join(", "),
+//!         Ok(None) => "Not logged in".to_owned(),
+//!         Err(e) => format!("Error: {e}"),
+//!     }
+//! }
+//!
+//! let fairing = RocketFlexSession::<DeviceSession<User>>::builder()
+//!     .storage(MemoryStorageIndexed::default())
+//!     .build();
+//! ```
+
+use std::convert::Infallible;
+
+use rocket::{
+    request::{FromRequest, Outcome},
+    serde::{Deserialize, Serialize},
+    Request,
+};
+
+use crate::{bot::is_bot_user_agent, error::SessionError, Session, SessionIdentifier};
+
+/// Type of device
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum DeviceType {
+    /// Desktop or laptop computer
+    Desktop,
+    /// Mobile phone
+    Mobile,
+    /// Tablet
+    Tablet,
+    /// Bot, crawler, or HTTP tool
+    Bot,
+    /// Unknown device, e.g. if there's no user agent
+    Unknown,
+}
+
+impl DeviceType {
+    /// Lowercase name of the device type, e.g. `mobile`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Desktop => "desktop",
+            Self::Mobile => "mobile",
+            Self::Tablet => "tablet",
+            Self::Bot => "bot",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Browser (in order of precedence, as most user agents mention several browsers) and the
+/// user agent substrings that identify it
+const BROWSERS: &[(&str, &[&str])] = &[
+    ("Edge", &["edg/", "edga/", "edgios/"]),
+    ("Opera", &["opr/", "opera"]),
+    ("Samsung Internet", &["samsungbrowser/"]),
+    ("Firefox", &["firefox/", "fxios/"]),
+    ("Chrome", &["chrome/", "crios/"]),
+    ("Safari", &["safari/"]),
+];
+
+/// Operating systems (in order of precedence) and the user agent substrings that identify them
+const OPERATING_SYSTEMS: &[(&str, &[&str])] = &[
+    ("iOS", &["iphone", "ipad", "ipod"]),
+    ("Android", &["android"]),
+    ("Windows", &["windows"]),
+    ("ChromeOS", &["cros "]),
+    ("macOS", &["mac os x", "macintosh"]),
+    ("Linux", &["linux"]),
+];
+
+/// Device, browser, and operating system of a request, parsed from its user agent. Parsing is
+/// heuristic and meant for display purposes, e.g. "Firefox on Windows".
+///
+/// Can also be used as a request guard, which always succeeds.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DeviceInfo {
+    /// Type of device
+    pub device_type: DeviceType,
+    /// Name of the browser, if recognized
+    pub browser: Option<String>,
+    /// Name of the operating system, if recognized
+    pub os: Option<String>,
+}
+
+impl DeviceInfo {
+    /// Parse the device info from a user agent
+    pub fn parse(user_agent: &str) -> Self {
+        let user_agent = user_agent.to_ascii_lowercase();
+        let find = |candidates: &[(&str, &[&str])]| {
+            candidates
+                .iter()
+                .find(|(_, patterns)| patterns.iter().any(|p| user_agent.contains(p)))
+                .map(|(name, _)| name.to_string())
+        };
+        let browser = find(BROWSERS);
+        let os = find(OPERATING_SYSTEMS);
+
+        let device_type = if is_bot_user_agent(&user_agent) {
+            DeviceType::Bot
+        } else if user_agent.contains("ipad")
+            || user_agent.contains("tablet")
+            || (user_agent.contains("android") && !user_agent.contains("mobile"))
+        {
+            DeviceType::Tablet
+        } else if user_agent.contains("mobi")
+            || user_agent.contains("iphone")
+            || user_agent.contains("ipod")
+        {
+            DeviceType::Mobile
+        } else if os.is_some() {
+            DeviceType::Desktop
+        } else {
+            DeviceType::Unknown
+        };
+
+        Self {
+            device_type,
+            browser,
+            os,
+        }
+    }
+
+    /// Get the device info of the request from its `User-Agent` header
+    pub fn from_request(req: &Request<'_>) -> Self {
+        match req.headers().get_one("User-Agent") {
+            Some(user_agent) => Self::parse(user_agent),
+            None => Self::unknown(),
+        }
+    }
+
+    /// Device info of an unknown device
+    pub fn unknown() -> Self {
+        Self {
+            device_type: DeviceType::Unknown,
+            browser: None,
+            os: None,
+        }
+    }
+
+    /// Human-readable label of the device, e.g. "Firefox on Windows"
+    pub fn label(&self) -> String {
+        match (&self.browser, &self.os) {
+            (Some(browser), Some(os)) => format!("{browser} on {os}"),
+            (Some(browser), None) => browser.to_owned(),
+            (None, Some(os)) => os.to_owned(),
+            (None, None) => "Unknown device".to_owned(),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for DeviceInfo {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self::from_request(req))
+    }
+}
+
+/// Session data wrapper that stores the [`DeviceInfo`] alongside your session data. If your
+/// data implements [`SessionIdentifier`], the sessions will be indexed by the same identifier,
+/// and tagged with the device type (e.g. `device-type=mobile`).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DeviceSession<T> {
+    /// The device of the session
+    pub device: DeviceInfo,
+    /// Your session data
+    pub data: T,
+}
+
+impl<T> DeviceSession<T> {
+    /// Create the session data with the given device
+    pub fn new(device: DeviceInfo, data: T) -> Self {
+        Self { device, data }
+    }
+}
+
+impl<T> SessionIdentifier for DeviceSession<T>
+where
+    T: SessionIdentifier,
+{
+    type Id = T::Id;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.data.identifier()
+    }
+
+    fn tags(&self) -> Vec<String> {
+        let mut tags = self.data.tags();
+        tags.push(format!("device-type={}", self.device.device_type.as_str()));
+        tags
+    }
+}
+
+/// A device with active sessions. See [`Session::get_active_devices`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ActiveDevice {
+    /// The device
+    pub device: DeviceInfo,
+    /// IDs of the active sessions on this device
+    pub session_ids: Vec<String>,
+    /// Whether the current session is on this device
+    pub is_current: bool,
+}
+
+/// Group sessions by their device. Devices are listed in the order they're first found.
+pub fn group_by_device<T>(
+    sessions: impl IntoIterator<Item = (String, DeviceSession<T>, u32)>,
+    current_session_id: Option<&str>,
+) -> Vec<ActiveDevice> {
+    let mut devices: Vec<ActiveDevice> = Vec::new();
+    for (session_id, session, _ttl) in sessions {
+        let is_current = current_session_id == Some(session_id.as_str());
+        match devices.iter_mut().find(|d| d.device == session.device) {
+            Some(device) => {
+                device.session_ids.push(session_id);
+                device.is_current |= is_current;
+            }
+            None => devices.push(ActiveDevice {
+                device: session.device,
+                session_ids: vec![session_id],
+                is_current,
+            }),
+        }
+    }
+    devices
+}
+
+/// Implementation block for sessions with device info
+impl<T> Session<'_, DeviceSession<T>>
+where
+    T: SessionIdentifier,
+{
+    /// Get the device of the current session, if there is one.
+    pub fn device(&self) -> Option<DeviceInfo> {
+        self.tap(|session| session.map(|s| s.device.clone()))
+    }
+
+    /// Get the active devices for the same user/identifier as the current session, with the
+    /// current device listed first. Returns `None` if there's no current session or the
+    /// session isn't indexed.
+    pub async fn get_active_devices(&self) -> Result<Option<Vec<ActiveDevice>>, SessionError> {
+        let Some(sessions) = self.get_all_sessions().await? else {
+            return Ok(None);
+        };
+        let current_id = self.id();
+        let mut devices = group_by_device(sessions, current_id.as_deref());
+        devices.sort_by_key(|device| !device.is_current);
+        Ok(Some(devices))
+    }
+}
@@ -0,0 +1,89 @@
+//! Session cookie verification outside Rocket
+//!
+//! [`EdgeVerifier`] validates the encrypted session cookie with the server's secret key, without
+//! a running Rocket server - e.g. in an edge worker, CDN function, or another service that
+//! shares the secret key. This can be used to gate authenticated content before the request
+//! reaches the server.
+//!
+//! If the fairing uses a dedicated [`cookie_key`](crate::RocketFlexSessionOptions::cookie_key),
+//! create the verifier with the same key using [`EdgeVerifier::with_cookie_key`], or with
+//! [`EdgeVerifier::from_key_ring`] if the fairing uses a [key ring](crate::keys). Both
+//! encrypted and signed session cookies are supported.
+//!
+//! The verifier only checks that the cookie was created by the server and holds a valid session
+//! ID - it doesn't check storage, so the session may have since expired or been deleted. Use it
+//! for coarse gating, and keep checking the [`Session`](crate::Session) on the server.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::edge::EdgeVerifier;
+//!
+//! // The same secret key as the `secret_key` of the Rocket config
+//! let secret_key = [7u8; 64];
+//! let verifier = EdgeVerifier::new(&secret_key, "rocket").expect("key should be valid");
+//!
+//! let cookie_header = "theme=dark; rocket=not-a-valid-cookie";
+//! assert_eq!(verifier.verify_cookie_header(cookie_header), None);
+//! ```
+
+use cookie::Cookie;
+
+use crate::{keys::KeyRing, session_inner::is_valid_id, SessionCookieKey};
+
+/// Verifies session cookies with the key used by the server. See the [module docs](self).
+#[derive(Clone)]
+pub struct EdgeVerifier {
+    cookie_key: SessionCookieKey,
+    cookie_name: String,
+}
+
+impl EdgeVerifier {
+    /// Create the verifier with the raw bytes of Rocket's `secret_key`, and the name of the
+    /// session cookie. As in Rocket, keys of 64 bytes or more are used as the master key, and
+    /// shorter keys are used to derive it. Returns `None` if the key is shorter than 32 bytes.
+    pub fn new(secret_key: &[u8], cookie_name: impl Into<String>) -> Option<Self> {
+        Self::with_cookie_key(SessionCookieKey::private(secret_key)?, cookie_name)
+    }
+
+    /// Create the verifier with the dedicated `cookie_key` configured in the session options,
+    /// and the name of the session cookie. Returns `None` for
+    /// [`SessionCookieKey::RocketSecretKey`], as the key itself is needed: use
+    /// [`EdgeVerifier::new`] with Rocket's `secret_key` instead.
+    pub fn with_cookie_key(
+        cookie_key: SessionCookieKey,
+        cookie_name: impl Into<String>,
+    ) -> Option<Self> {
+        if let SessionCookieKey::RocketSecretKey = cookie_key {
+            return None;
+        }
+        Some(Self {
+            cookie_key,
+            cookie_name: cookie_name.into(),
+        })
+    }
+
+    /// Create the verifier with the [`KeyRing`] passed to the fairing builder, and the name of
+    /// the session cookie. The cookie key is derived from the key ring, as in the fairing.
+    pub fn from_key_ring(key_ring: &KeyRing, cookie_name: impl Into<String>) -> Option<Self> {
+        Self::with_cookie_key(key_ring.cookie_key(), cookie_name)
+    }
+
+    /// Verify the value of the session cookie. Returns the session ID if the cookie was
+    /// encrypted or signed with the key, and holds a valid session ID.
+    pub fn verify(&self, cookie_value: &str) -> Option<String> {
+        let cookie = Cookie::new(self.cookie_name.clone(), cookie_value.to_owned());
+        let cookie = self.cookie_key.verify(cookie)?;
+        let id = cookie.value();
+        is_valid_id(id).then(|| id.to_owned())
+    }
+
+    /// Find and [verify](EdgeVerifier::verify) the session cookie in the value of a `Cookie`
+    /// request header, where cookie values are percent-encoded. Returns the session ID if the
+    /// cookie is valid.
+    pub fn verify_cookie_header(&self, cookie_header: &str) -> Option<String> {
+        Cookie::split_parse_encoded(cookie_header)
+            .filter_map(Result::ok)
+            .filter(|cookie| cookie.name() == self.cookie_name)
+            .find_map(|cookie| self.verify(cookie.value()))
+    }
+}
@@ -9,9 +9,15 @@ pub enum SessionError {
     /// There was no session cookie, or decryption of the cookie failed
     #[error("No session cookie")]
     NoSessionCookie,
+    /// The session ID in the cookie failed validation, so the session wasn't looked up in storage
+    #[error("Invalid session ID")]
+    InvalidId,
     /// Session wasn't found in storage
     #[error("Session not found")]
     NotFound,
+    /// Session was skipped for this request, due to the `skip_request` setting of the fairing
+    #[error("Session skipped for this request")]
+    Skipped,
     /// Session was found but it was expired
     #[error("Session expired")]
     Expired,
@@ -21,6 +27,9 @@ pub enum SessionError {
     /// Error parsing the session data
     #[error("Failed to parse session: {0}")]
     Parsing(Box<dyn std::error::Error + Send + Sync>),
+    /// The session data failed the `validate` check of the fairing
+    #[error("Invalid session data: {0}")]
+    Validation(Box<dyn std::error::Error + Send + Sync>),
     /// Invalid data when trying to read the session data
     #[error("Invalid data")]
     InvalidData,
@@ -28,10 +37,35 @@ pub enum SessionError {
     /// implement [SessionStorageIndexed](crate::storage::SessionStorageIndexed)
     #[error("Storage doesn't support indexing")]
     NonIndexedStorage,
+    /// The storage provider doesn't support this operation
+    #[error("Operation not supported by storage: {0}")]
+    Unsupported(&'static str),
     /// A generic error from the storage backend. This error type can be
-    /// used when implementing a custom session storage.
+    /// used when implementing a custom session storage. Wrap a [`std::io::Error`] for connection
+    /// errors, so that they're [retried](Self::is_transient).
     #[error("Storage backend error: {0}")]
     Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// A storage operation took too long, e.g. when using a
+    /// [`TimeoutLayer`](crate::storage::layer::TimeoutLayer)
+    #[error("Storage operation timed out")]
+    Timeout,
+    /// A storage operation was shed because too many operations were waiting, e.g. when using a
+    /// [`ConcurrencyLimitLayer`](crate::storage::layer::ConcurrencyLimitLayer), or dropped from a
+    /// full [persistence queue](crate::queue)
+    #[error("Storage operation shed due to overload")]
+    Overloaded,
+    /// A new session was rejected by the [quota policy](crate::quota::QuotaPolicy)
+    #[error("Session quota exceeded: {active_sessions} of {limit} sessions in use")]
+    QuotaExceeded {
+        /// The maximum number of sessions
+        limit: u64,
+        /// The number of active sessions
+        active_sessions: u64,
+    },
+    /// The session was modified after the response started, while using the
+    /// [`strict`](crate::RocketFlexSessionOptions::strict) option
+    #[error("Session modified after the response started")]
+    ResponseStarted,
     /// Error occurred while setting up or tearing down the session storage
     #[error("Error during storage setup or teardown: {0}")]
     SetupTeardown(String),
@@ -43,4 +77,112 @@ pub enum SessionError {
     #[cfg(feature = "sqlx_postgres")]
     #[error("Sqlx error: {0}")]
     SqlxError(#[from] sqlx::Error),
+
+    #[cfg(feature = "etcd")]
+    #[error("etcd error: {0}")]
+    EtcdError(#[from] etcd_client::Error),
+
+    #[cfg(feature = "rest_kv")]
+    #[error("HTTP KV error: {0}")]
+    RestKvError(#[from] reqwest::Error),
+
+    #[cfg(feature = "tower_sessions")]
+    #[error("tower-sessions store error: {0}")]
+    TowerSessionsError(#[from] tower_sessions_core::session_store::Error),
+
+    #[cfg(feature = "libsql")]
+    #[error("libSQL error: {0}")]
+    LibsqlError(#[from] libsql::Error),
+}
+
+/// Invalid [session options](crate::RocketFlexSessionOptions)
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OptionsError {
+    /// The `max_age` option is 0
+    #[error("max_age must be greater than 0")]
+    ZeroMaxAge,
+    /// A TTL option is 0
+    #[error("{0} must be greater than 0")]
+    ZeroTtl(&'static str),
+    /// A TTL option is longer than the `max_age` option
+    #[error("{0} ({1}) must not be greater than max_age ({2})")]
+    TtlExceedsMaxAge(&'static str, u32, u32),
+    /// The `path` option doesn't start with `/`
+    #[error("path must start with '/': {0}")]
+    InvalidPath(String),
+    /// A sample rate option isn't between 0.0 and 1.0
+    #[error("{0} must be between 0.0 and 1.0: {1}")]
+    InvalidSampleRate(&'static str, f64),
+    /// The `activity_log_size` option is 0
+    #[error("activity_log_size must be greater than 0")]
+    ZeroActivityLogSize,
+}
+
+impl SessionError {
+    /// The error message, without details that could contain session data
+    /// (e.g. the source of parsing or serialization errors).
+    pub fn redacted(&self) -> String {
+        match self {
+            Self::Serialization(_) => "Failed to serialize session".to_owned(),
+            Self::Parsing(_) => "Failed to parse session".to_owned(),
+            Self::Validation(_) => "Invalid session data".to_owned(),
+            Self::Backend(_) => "Storage backend error".to_owned(),
+            e => e.to_string(),
+        }
+    }
+
+    /// Whether the error may be temporary (e.g. a connection error from the storage backend),
+    /// so that the operation could succeed if retried. Only I/O and connection errors and
+    /// timeouts are transient - errors that would happen again (e.g. a constraint violation,
+    /// a decoding error, or a rejected request) aren't. A [`Backend`](Self::Backend) error is
+    /// transient if it's a connection-related [`std::io::Error`].
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::Backend(e) => e
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(is_transient_io),
+            #[cfg(feature = "redis_fred")]
+            Self::RedisFredError(e) => {
+                use fred::error::ErrorKind;
+                matches!(e.kind(), ErrorKind::IO | ErrorKind::Timeout)
+            }
+            #[cfg(feature = "sqlx_postgres")]
+            Self::SqlxError(e) => matches!(e, sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut),
+            #[cfg(feature = "etcd")]
+            Self::EtcdError(e) => matches!(
+                e,
+                etcd_client::Error::IoError(_) | etcd_client::Error::TransportError(_)
+            ),
+            #[cfg(feature = "rest_kv")]
+            Self::RestKvError(e) => e.is_connect() || e.is_timeout(),
+            #[cfg(feature = "libsql")]
+            Self::LibsqlError(e) => matches!(e, libsql::Error::ConnectionFailed(_)),
+            _ => false,
+        }
+    }
+
+    /// The error message to use in logs
+    pub(crate) fn log_message(&self, redact: bool) -> String {
+        match redact {
+            true => self.redacted(),
+            false => self.to_string(),
+        }
+    }
+}
+
+/// Whether the I/O error is a connection problem or timeout, which may not happen again
+fn is_transient_io(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+    matches!(
+        e.kind(),
+        ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+            | ErrorKind::Interrupted
+            | ErrorKind::UnexpectedEof
+    )
 }
@@ -0,0 +1,96 @@
+use std::{convert::Infallible, marker::PhantomData};
+
+use rocket::{
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+use crate::{error::SessionError, guard::get_fairing, logging::session_log, Session};
+
+/// Request guard that checks whether the request has an existing session, without loading or
+/// deserializing the session data. This is useful for lightweight "am I logged in" endpoints
+/// that are polled frequently by frontends. The guard always succeeds.
+///
+/// # Example
+/// ```rust
+/// use rocket_flex_session::SessionExists;
+///
+/// #[derive(Clone)]
+/// struct MySession {
+///     user_id: String,
+/// }
+///
+/// #[rocket::get("/logged-in")]
+/// fn logged_in(session: SessionExists<MySession>) -> &'static str {
+///     match session.exists() {
+///         true => "yes",
+///         false => "no",
+///     }
+/// }
+/// ```
+pub struct SessionExists<T> {
+    exists: bool,
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T> SessionExists<T> {
+    /// Whether the session exists in storage
+    pub fn exists(&self) -> bool {
+        self.exists
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T> FromRequest<'r> for SessionExists<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let fairing = get_fairing::<T>(req.rocket());
+        let options = &fairing.options;
+        let cookie_jar = req.cookies();
+        let session_id = options
+            .cookie_key
+            .get(cookie_jar, &options.cookie_name)
+            .filter(|cookie| fairing.check_valid_id(cookie.value()))
+            .filter(|_| !fairing.check_skip_request(req));
+
+        let exists = match session_id {
+            Some(cookie) => match fairing.storage.exists(cookie.value(), cookie_jar).await {
+                Ok(exists) => exists,
+                Err(e) => {
+                    let message = e.log_message(options.redact_errors);
+                    session_log!(
+                        options,
+                        warn,
+                        "Error while checking if session exists: {message}"
+                    );
+                    false
+                }
+            },
+            None => false,
+        };
+        Outcome::Success(Self {
+            exists,
+            _data: PhantomData,
+        })
+    }
+}
+
+impl<T> Session<'_, T>
+where
+    T: Send + Sync + Clone,
+{
+    /// Check whether the current session still exists in storage, without loading its data.
+    /// This can detect a session that was deleted or invalidated after it was loaded for this
+    /// request. Returns `false` if there's no active session, or if the session is new and
+    /// hasn't been saved yet.
+    pub async fn exists(&self) -> Result<bool, SessionError> {
+        let Some(id) = self.id() else {
+            return Ok(false);
+        };
+        self.storage.exists(&id, self.cookie_jar).await
+    }
+}
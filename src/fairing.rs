@@ -1,15 +1,44 @@
 use std::{
+    any::type_name,
+    collections::HashMap,
     marker::{Send, Sync},
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use bon::Builder;
-use rocket::{fairing::Fairing, Build, Orbit, Request, Response, Rocket};
+use rocket::{
+    fairing::Fairing,
+    futures::future::BoxFuture,
+    http::{Cookie, Method, Status},
+    time::OffsetDateTime,
+    Build, Data, Orbit, Request, Response, Rocket,
+};
 
 use crate::{
+    analytics::{SessionLifetimeMetrics, TerminationReason},
+    audit::AuditEvent,
+    clock::{Clock, SystemClock},
+    correlation::set_correlation_id,
+    error::SessionError,
     guard::LocalCachedSession,
-    storage::{memory::MemoryStorage, SessionStorage},
-    RocketFlexSessionOptions,
+    keys::KeyRing,
+    logging::session_log,
+    merge::MergeStrategy,
+    options::{resolve_cookie_name, PreflightMode},
+    policy::{ParseErrorMetrics, ParseErrorPolicy, SerializationErrorPolicy},
+    queue::{PersistOp, PersistQueue, PersistQueueState, PersistQueueStats, PushResult},
+    quota::QuotaPolicy,
+    security::fnv1a,
+    session_inner::{is_valid_id, PersistReport},
+    storage::{
+        memory::MemoryStorage, CookieStore, CookieStoreAdapter, SessionStorage,
+        SessionStorageIndexed, SessionStore, StoreAdapter,
+    },
+    ActivityEntry, RocketFlexSessionOptions, SessionCookieKey, SessionCorrelationId,
+    SessionIdentifier,
 };
 
 /**
@@ -17,8 +46,8 @@ A Rocket fairing that enables sessions.
 
 # Type Parameters
 * `T` - The type of your session data. Must be thread-safe and
-   implement Clone. The storage provider you use may have additional
-   trait bounds as well.
+  implement Clone. The storage provider you use may have additional
+  trait bounds as well.
 
 # Example
 ```rust
@@ -54,7 +83,7 @@ fn rocket() -> _ {
 }
 ```
 */
-#[derive(Builder)]
+#[derive(Builder, Clone)]
 pub struct RocketFlexSession<T: Send + Sync + Clone + 'static> {
     /// Set the options directly. Alternatively, use `with_options` to customize the default options via a closure.
     #[builder(default)]
@@ -62,28 +91,524 @@ pub struct RocketFlexSession<T: Send + Sync + Clone + 'static> {
     #[builder(default = Arc::new(MemoryStorage::default()), with = |storage: impl SessionStorage<T> + 'static| Arc::new(storage))]
     /// Set the session storage provider. The default is an in-memory storage.
     pub(crate) storage: Arc<dyn SessionStorage<T>>,
+    /// Determine whether the session data is anonymous (e.g. a guest session without a user ID).
+    /// This is used by options such as [`persist_anonymous`](RocketFlexSessionOptions::persist_anonymous).
+    /// If your session data implements [`SessionIdentifier`], you can use `anonymous_without_identifier()` instead.
+    #[builder(with = |f: impl Fn(&T) -> bool + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) is_anonymous: Option<DataPredicate<T>>,
+    /// Skip sessions entirely for requests matching this predicate (e.g. bots and crawlers). For
+    /// these requests, the session won't be loaded, and any changes to the session won't be saved.
+    /// See the [`bot`](crate::bot) module for some helpers.
+    #[builder(with = |f: impl Fn(&Request<'_>) -> bool + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) skip_request: Option<RequestPredicate>,
+    /// Validate the session ID from the cookie before it's looked up in storage. If the ID
+    /// is invalid, the session will be treated as empty. By default, IDs must be 1-128 characters
+    /// long and contain only ASCII letters, digits, `-` and `_` (generated IDs are 20 alphanumeric
+    /// characters). You may need to change this if you're migrating sessions with a different ID format.
+    #[builder(with = |f: impl Fn(&str) -> bool + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) validate_id: Option<IdValidator>,
+    /// Validate the session data after it's loaded from storage, e.g. to reject data written by
+    /// an older version of your app, or data that would otherwise cause a panic in your routes.
+    /// Invalid sessions are deleted from storage and treated as empty (with a
+    /// [`SessionError::Validation`] error), and are counted in
+    /// [`invalid_session_count`](RocketFlexSession::invalid_session_count).
+    ///
+    /// ```rust
+    /// # use rocket_flex_session::RocketFlexSession;
+    /// #[derive(Clone)]
+    /// struct MySession {
+    ///     user_id: String,
+    ///     roles: Vec<String>,
+    /// }
+    ///
+    /// let fairing = RocketFlexSession::<MySession>::builder()
+    ///     .validate(|data| match data.roles.is_empty() {
+    ///         true => Err("session has no roles".into()),
+    ///         false => Ok(()),
+    ///     })
+    ///     .build();
+    /// ```
+    #[builder(with = |f: impl Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) validate: Option<DataValidator<T>>,
+    /// Number of sessions that failed validation
+    #[builder(skip)]
+    pub(crate) invalid_sessions: Arc<AtomicU64>,
+    /// Describe the session data without any personal information. When set, the redacted
+    /// description is included in the crate's debug logs, and replaces the session data in
+    /// exports from the [`admin`](crate::admin) helpers of the fairing.
+    #[builder(with = |f: impl Fn(&T) -> String + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) redact: Option<DataRedactor<T>>,
+    /// Set the clock used to calculate session expiration and activity timestamps. The default
+    /// is the system time. Storage providers other than the cookie storage don't use this clock.
+    /// See the [`clock`](crate::clock) module for a mock clock to use in tests.
+    #[builder(default = Arc::new(SystemClock), with = |clock: impl Clock + 'static| Arc::new(clock))]
+    pub(crate) clock: Arc<dyn Clock>,
+    /// Enrich the session data from the request before it's saved, e.g. to attach the geolocation
+    /// or organization of the request for later auditing. This is called at the end of each request
+    /// that updates the session, just before saving it to storage. Changes aren't included in
+    /// cookie-based storage, as the session cookie has already been set by then.
+    ///
+    /// ```rust
+    /// # use rocket_flex_session::RocketFlexSession;
+    /// #[derive(Clone)]
+    /// struct MySession {
+    ///     user_id: String,
+    ///     last_ip: Option<String>,
+    /// }
+    ///
+    /// let fairing = RocketFlexSession::<MySession>::builder()
+    ///     .enrich(|req, data| Box::pin(async move {
+    ///         data.last_ip = req.client_ip().map(|ip| ip.to_string());
+    ///     }))
+    ///     .build();
+    /// ```
+    #[builder(with = |f: impl for<'a> Fn(&'a Request<'_>, &'a mut T) -> BoxFuture<'a, ()> + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) enrich: Option<DataEnricher<T>>,
+    /// Clean up artifacts derived from a session (e.g. locks or presence records) when the session is
+    /// deleted during a request. This is called with the session ID and data after the session is deleted
+    /// from storage. Sessions invalidated through the storage (e.g. by identifier) don't call this hook.
+    /// The built-in storages already delete the session's activity log and
+    /// [capability tokens](crate::Session::create_capability) along with the session.
+    ///
+    /// ```rust
+    /// # use rocket_flex_session::RocketFlexSession;
+    /// #[derive(Clone)]
+    /// struct MySession {
+    ///     user_id: String,
+    /// }
+    ///
+    /// let fairing = RocketFlexSession::<MySession>::builder()
+    ///     .on_delete(|session_id, _data| Box::pin(async move {
+    ///         println!("Releasing locks held by session {session_id}");
+    ///     }))
+    ///     .build();
+    /// ```
+    #[builder(with = |f: impl for<'a> Fn(&'a str, &'a T) -> BoxFuture<'a, ()> + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) on_delete: Option<DeleteHook<T>>,
+    /// Limit the number of sessions that can be created for an identifier, e.g. to enforce seat limits.
+    /// The policy is checked when creating a session with [`Session::try_set`](crate::Session::try_set).
+    /// See the [`quota`](crate::quota) module for more info.
+    #[builder(with = |policy: impl QuotaPolicy<T> + 'static| Arc::new(policy))]
+    pub(crate) quota: Option<Arc<dyn QuotaPolicy<T>>>,
+    /// Receive [audit events](AuditEvent), e.g. to forward them to your audit log.
+    #[builder(with = |f: impl Fn(AuditEvent) + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) audit: Option<AuditSink>,
+    /// Derive the session keys from one master secret. The session cookie is encrypted with the
+    /// derived cookie key, unless a dedicated [`cookie_key`](RocketFlexSessionOptions::cookie_key)
+    /// is set in the options. See the [`keys`](crate::keys) module for more info.
+    pub(crate) key_ring: Option<KeyRing>,
+    /// What to do when the session data fails to serialize while it's being saved at the end of
+    /// a request. See the [`policy`](crate::policy) module for more info.
+    #[builder(default)]
+    pub(crate) serialization_error_policy: SerializationErrorPolicy<T>,
+    /// What to do when the stored session data fails to deserialize while it's being loaded.
+    /// See the [`policy`](crate::policy) module for more info.
+    #[builder(default)]
+    pub(crate) parse_error_policy: ParseErrorPolicy<T>,
+    /// Count the sessions that failed to deserialize, e.g. to monitor the corruption rate
+    pub(crate) parse_error_metrics: Option<ParseErrorMetrics>,
+    /// Count the sessions that are created and terminated, and how long they lasted. See the
+    /// [`analytics`](crate::analytics) module for more info.
+    pub(crate) lifetime_metrics: Option<SessionLifetimeMetrics>,
+    /// Report errors that happen while saving the session at the end of a request (e.g. to your
+    /// error tracker), as they can't be handled in your routes. This is called with the session ID
+    /// and the error, which is also logged.
+    #[builder(with = |f: impl Fn(&str, &SessionError) + Send + Sync + 'static| Arc::new(f))]
+    pub(crate) on_save_error: Option<SaveErrorHook>,
+    /// Save and delete sessions in a bounded background queue, instead of before completing
+    /// each response. See the [`queue`](crate::queue) module for more info.
+    #[builder(with = |queue: PersistQueue| Arc::new(PersistQueueState::new(queue)))]
+    pub(crate) persist_queue: Option<Arc<PersistQueueState<T>>>,
+    /// Merge the changes of concurrent requests to the same session when it's saved, instead of
+    /// overwriting the changes of the other requests. See the [`merge`](crate::merge) module for more info.
+    pub(crate) merge: Option<MergeStrategy<T>>,
 }
 
+/// An async function that cleans up after a deleted session
+pub(crate) type DeleteHook<T> =
+    Arc<dyn for<'a> Fn(&'a str, &'a T) -> BoxFuture<'a, ()> + Send + Sync>;
+
+/// A function that reports errors while saving the session
+pub(crate) type SaveErrorHook = Arc<dyn Fn(&str, &SessionError) + Send + Sync>;
+
+/// A function that receives audit events
+pub(crate) type AuditSink = Arc<dyn Fn(AuditEvent) + Send + Sync>;
+
+/// Method of the request before routing, as Rocket handles `HEAD` requests with `GET` routes
+/// by changing the request method
+struct RequestMethod(Method);
+
+/// Cookie names used by the attached session fairings, along with their data type
+#[derive(Default)]
+struct SessionCookieNames(Mutex<HashMap<String, &'static str>>);
+
+/// A predicate on the session data
+pub(crate) type DataPredicate<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// A predicate on the request
+pub(crate) type RequestPredicate = Arc<dyn Fn(&Request<'_>) -> bool + Send + Sync>;
+
+/// A function that validates a session ID
+pub(crate) type IdValidator = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// A function that validates the session data
+pub(crate) type DataValidator<T> =
+    Arc<dyn Fn(&T) -> Result<(), Box<dyn std::error::Error + Send + Sync>> + Send + Sync>;
+
+/// A function that describes the session data without personal information
+pub(crate) type DataRedactor<T> = Arc<dyn Fn(&T) -> String + Send + Sync>;
+
+/// An async function that updates the session data from the request before it's saved
+pub(crate) type DataEnricher<T> =
+    Arc<dyn for<'a> Fn(&'a Request<'_>, &'a mut T) -> BoxFuture<'a, ()> + Send + Sync>;
+
 impl<T> Default for RocketFlexSession<T>
 where
     T: Send + Sync + Clone + 'static,
 {
     /// Create a new instance with default options and an in-memory storage.
     fn default() -> Self {
-        Self {
-            options: Default::default(),
-            storage: Arc::new(MemoryStorage::default()),
+        Self::builder().build()
+    }
+}
+
+impl<T> RocketFlexSession<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    /// Get the key ring, if configured, e.g. to derive keys for other purposes
+    pub fn key_ring(&self) -> Option<&KeyRing> {
+        self.key_ring.as_ref()
+    }
+
+    /// Whether sessions should be skipped for this request
+    pub(crate) fn check_skip_request(&self, req: &Request<'_>) -> bool {
+        self.skip_request.as_ref().is_some_and(|f| f(req))
+            || self.preflight_mode(req) == Some(PreflightMode::Skip)
+    }
+
+    /// Whether changes to the session should be discarded for this request
+    pub(crate) fn check_read_only_request(&self, req: &Request<'_>) -> bool {
+        self.preflight_mode(req) == Some(PreflightMode::ReadOnly)
+    }
+
+    /// How the session is handled for this request, if it's an `OPTIONS` or `HEAD` request
+    fn preflight_mode(&self, req: &Request<'_>) -> Option<PreflightMode> {
+        let RequestMethod(method) = req.local_cache(|| RequestMethod(req.method()));
+        matches!(method, Method::Options | Method::Head).then_some(self.options.preflight)
+    }
+
+    /// Whether the session ID from the cookie is valid
+    pub(crate) fn check_valid_id(&self, id: &str) -> bool {
+        match &self.validate_id {
+            Some(validate) => validate(id),
+            None => is_valid_id(id),
+        }
+    }
+
+    /// Validate the session data loaded from storage, counting invalid sessions
+    pub(crate) fn check_valid_data(&self, data: &T) -> Result<(), SessionError> {
+        let Some(validate) = &self.validate else {
+            return Ok(());
+        };
+        validate(data).map_err(|e| {
+            self.invalid_sessions.fetch_add(1, Ordering::Relaxed);
+            SessionError::Validation(e)
+        })
+    }
+
+    /// The number of sessions that failed the `validate` check of the fairing, since the server
+    /// started. Get the fairing from Rocket's managed state to read the count.
+    pub fn invalid_session_count(&self) -> u64 {
+        self.invalid_sessions.load(Ordering::Relaxed)
+    }
+
+    /// Whether the session data is considered anonymous
+    pub(crate) fn check_anonymous(&self, data: &T) -> bool {
+        self.is_anonymous.as_ref().is_some_and(|f| f(data))
+    }
+
+    /// Redacted description of the session data for logs, if enabled
+    fn describe(&self, data: &T) -> String {
+        match &self.redact {
+            Some(redact) => format!(" ({})", redact(data)),
+            None => String::new(),
+        }
+    }
+
+    /// Save the session, retrying with the fallback data of the serialization error policy
+    async fn save_with_policy(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+    ) -> Result<(), SessionError> {
+        let SerializationErrorPolicy::Fallback(fallback) = &self.serialization_error_policy else {
+            return self.storage.save_with_duration(id, data, ttl).await;
+        };
+        let fallback_data = data.clone();
+        match self.storage.save_with_duration(id, data, ttl).await {
+            Err(SessionError::Serialization(e)) => match fallback(&fallback_data) {
+                Some(fallback_data) => {
+                    let (log_id, message) = (
+                        self.options.log_ids.format(id),
+                        SessionError::Serialization(e).log_message(self.options.redact_errors),
+                    );
+                    session_log!(
+                        self.options,
+                        warn,
+                        "Failed to serialize session '{log_id}', saving fallback data: {message}"
+                    );
+                    self.storage
+                        .save_with_duration(id, fallback_data, ttl)
+                        .await
+                }
+                None => Err(SessionError::Serialization(e)),
+            },
+            result => result,
+        }
+    }
+
+    /// Merge the session data with the stored data, if another request changed it since it was loaded
+    async fn merge_concurrent(&self, req: &Request<'_>, id: &str, loaded: &T, data: T) -> T {
+        let Some(merge) = &self.merge else {
+            return data;
+        };
+        let log_id = self.options.log_ids.format(id);
+        match self.storage.load(id, None, req.cookies()).await {
+            Ok((stored, _)) => {
+                let (data, merged) = merge.apply(loaded, stored, data);
+                if merged {
+                    session_log!(
+                        self.options,
+                        debug,
+                        "Session '{log_id}' was changed by another request, merged the changes"
+                    );
+                }
+                data
+            }
+            Err(e) => {
+                let message = e.log_message(self.options.redact_errors);
+                session_log!(
+                    self.options,
+                    debug,
+                    "Couldn't reload session '{log_id}' to merge changes: {message}"
+                );
+                data
+            }
+        }
+    }
+
+    /// Whether the session should be saved to storage
+    fn should_persist(&self, id: &str, data: &T) -> bool {
+        if !self.check_anonymous(data) {
+            return true;
+        }
+        self.options.persist_anonymous
+            && self
+                .options
+                .anonymous_sample_rate
+                .map_or(true, |rate| is_sampled(id, rate))
+    }
+
+    /// Expire the legacy session cookies sent with the request. The cookie jar's changes
+    /// are already applied to the response at this point, so the removal cookies are
+    /// added to the response headers directly.
+    fn expire_legacy_cookies(&self, req: &Request<'_>, res: &mut Response<'_>) {
+        let options = &self.options;
+        for name in &options.legacy_cookies {
+            if req.cookies().get(name).is_none() {
+                continue;
+            }
+            session_log!(options, debug, "Expiring legacy session cookie '{name}'");
+            let mut cookie = Cookie::build(name.to_owned()).path(options.path.to_owned());
+            if let Some(domain) = &options.domain {
+                cookie = cookie.domain(domain.to_owned());
+            }
+            let mut cookie = cookie.build();
+            cookie.make_removal();
+            res.adjoin_header(cookie);
+        }
+    }
+
+    /// Statistics of the background persistence queue, if enabled
+    pub fn persist_queue_stats(&self) -> Option<PersistQueueStats> {
+        self.persist_queue.as_ref().map(|queue| queue.stats())
+    }
+
+    /// Delete the session from storage, and call the `on_delete` hook
+    async fn delete_session(&self, id: &str, data: T) -> Result<(), SessionError> {
+        let log_id = self.options.log_ids.format(id);
+        let data_for_hook = self.on_delete.as_ref().map(|_| data.clone());
+        let result = self.storage.delete(id, data).await;
+        if let Err(e) = &result {
+            let message = e.log_message(self.options.redact_errors);
+            session_log!(
+                self.options,
+                warn,
+                "Error while deleting session '{log_id}': {message}"
+            );
+        } else {
+            session_log!(
+                self.options,
+                debug,
+                "Deleted session '{log_id}' successfully"
+            );
+            if let Some((on_delete, data)) = self.on_delete.as_ref().zip(data_for_hook) {
+                on_delete(id, &data).await;
+            }
+        }
+        result
+    }
+
+    /// Save the session to storage, reporting any error to the `on_save_error` hook
+    async fn save_session(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+    ) -> Result<(), SessionError> {
+        let log_id = self.options.log_ids.format(id);
+        let result = self.save_with_policy(id, data, ttl).await;
+        match &result {
+            Ok(()) => session_log!(self.options, debug, "Saved session '{log_id}' successfully"),
+            Err(e) => {
+                let message = e.log_message(self.options.redact_errors);
+                session_log!(
+                    self.options,
+                    error,
+                    "Error while saving session '{log_id}': {message}"
+                );
+                if let Some(on_save_error) = &self.on_save_error {
+                    on_save_error(id, e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Run a storage operation from the persistence queue
+    async fn run_persist_op(&self, op: PersistOp<T>) {
+        let (result, report) = match op {
+            PersistOp::Save {
+                id,
+                data,
+                ttl,
+                report,
+            } => (self.save_session(&id, data, ttl).await, report),
+            PersistOp::Delete { id, data, report } => {
+                (self.delete_session(&id, data).await, report)
+            }
+        };
+        if let Some(report) = report {
+            report.record(result);
+        }
+    }
+
+    /// Delete the session at the end of a request, via the persistence queue if enabled, and
+    /// record its lifetime
+    async fn persist_delete(
+        &self,
+        id: String,
+        data: T,
+        created_at: Option<OffsetDateTime>,
+        report: Option<Arc<PersistReport>>,
+    ) {
+        match &self.persist_queue {
+            Some(queue) => {
+                self.queue_persist_op(queue, PersistOp::Delete { id, data, report })
+                    .await
+            }
+            None => {
+                let result = self.delete_session(&id, data).await;
+                if let Some(report) = &report {
+                    report.record(result);
+                }
+            }
+        }
+        if let Some(metrics) = &self.lifetime_metrics {
+            let lifetime =
+                created_at.and_then(|created_at| (self.clock.now() - created_at).try_into().ok());
+            metrics.record_terminated(TerminationReason::Deleted, lifetime);
+        }
+    }
+
+    /// Queue a storage operation in the persistence queue, logging any dropped operation
+    async fn queue_persist_op(&self, queue: &PersistQueueState<T>, op: PersistOp<T>) {
+        let (dropped, reason) = match queue.push(op).await {
+            PushResult::Queued => return,
+            PushResult::DroppedOldest(op) => (op, "dropped the oldest operation"),
+            PushResult::Rejected(op) => (op, "dropped the operation"),
+        };
+        let log_id = self.options.log_ids.format(dropped.id());
+        session_log!(
+            self.options,
+            error,
+            "Session persistence queue is full, {reason} for session '{log_id}'"
+        );
+        if let Some(report) = dropped.report() {
+            report.record(Err(SessionError::Overloaded));
         }
     }
 }
 
-use rocket_flex_session_builder::{IsUnset, SetOptions, State};
+impl<T> RocketFlexSession<T>
+where
+    T: SessionIdentifier + 'static,
+{
+    /// Get the session storage, if it supports indexing. This can be used for
+    /// administrative tasks outside of a request, e.g. with the [`admin`](crate::admin) helpers.
+    pub fn indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+        self.storage.as_indexed_storage()
+    }
+
+    /// Deprovision an identifier, e.g. from a SCIM user deactivation handler. This invalidates all
+    /// of its sessions, purges its index entries from storage, and sends an
+    /// [`AuditEvent::Deprovisioned`] to the `audit` hook. Returns the number of sessions invalidated.
+    pub async fn deprovision_identifier(&self, id: &T::Id) -> Result<u64, SessionError>
+    where
+        T::Id: ToString,
+    {
+        let storage = self
+            .indexed_storage()
+            .ok_or(SessionError::NonIndexedStorage)?;
+        let sessions_invalidated = storage.invalidate_sessions_by_identifier(id, None).await?;
+        storage.purge_identifier_index(id).await?;
+        if let Some(metrics) = &self.lifetime_metrics {
+            metrics.record_terminated_count(TerminationReason::Invalidated, sessions_invalidated);
+        }
+
+        let identifier = id.to_string();
+        session_log!(
+            self.options,
+            info,
+            "Deprovisioned '{identifier}', invalidating {sessions_invalidated} session(s)"
+        );
+        if let Some(audit) = &self.audit {
+            audit(AuditEvent::Deprovisioned {
+                identifier,
+                sessions_invalidated,
+            });
+        }
+        Ok(sessions_invalidated)
+    }
+}
+
+/// Deterministically sample a session ID at the given rate (0.0 - 1.0), using an FNV-1a hash
+/// so that the same session is always either sampled or not.
+fn is_sampled(id: &str, rate: f64) -> bool {
+    (fnv1a(id.as_bytes()) as f64 / u64::MAX as f64) < rate
+}
+
+use rocket_flex_session_builder::{IsUnset, SetIsAnonymous, SetOptions, SetStorage, State};
 impl<T, S> RocketFlexSessionBuilder<T, S>
 where
     T: Send + Sync + Clone + 'static,
     S: State,
 {
     /// Customize the [options](RocketFlexSessionOptions) via a closure. Any options that are not set will retain their default values.
+    /// The options aren't validated - use [`RocketFlexSessionOptions::builder`] with the `options` method instead
+    /// to catch invalid options early.
     pub fn with_options<OptionsFn>(
         self,
         options_fn: OptionsFn,
@@ -96,6 +621,61 @@ where
         options_fn(&mut options);
         self.options(options)
     }
+
+    /// Set a [`SessionStore`] as the session storage provider. Use `storage` instead for
+    /// storages implementing the full [`SessionStorage`] trait.
+    pub fn store(
+        self,
+        store: impl SessionStore<T> + 'static,
+    ) -> RocketFlexSessionBuilder<T, SetStorage<S>>
+    where
+        S::Storage: IsUnset,
+    {
+        self.storage(StoreAdapter(store))
+    }
+
+    /// Set a [`CookieStore`] as the session storage provider.
+    pub fn cookie_store(
+        self,
+        store: impl CookieStore<T> + 'static,
+    ) -> RocketFlexSessionBuilder<T, SetStorage<S>>
+    where
+        S::Storage: IsUnset,
+    {
+        self.storage(CookieStoreAdapter(store))
+    }
+
+    /// Use the store and settings of a rocket_session_store fairing, to migrate an existing app.
+    /// The cookie name, duration, and cookie attributes are converted to the
+    /// [options](RocketFlexSessionOptions). See the
+    /// [migration guide](crate::storage::rocket_session_store).
+    #[cfg(feature = "rocket_session_store")]
+    pub fn with_rocket_session_store(
+        self,
+        session_store: rocket_session_store::SessionStore<T>,
+    ) -> RocketFlexSessionBuilder<T, SetStorage<SetOptions<S>>>
+    where
+        S::Options: IsUnset,
+        S::Storage: IsUnset,
+    {
+        let (storage, options) =
+            crate::storage::rocket_session_store::convert_session_store(session_store);
+        self.options(options).store(storage)
+    }
+}
+
+impl<T, S> RocketFlexSessionBuilder<T, S>
+where
+    T: SessionIdentifier + 'static,
+    S: State,
+{
+    /// Consider sessions without an [identifier](SessionIdentifier::identifier) as anonymous.
+    pub fn anonymous_without_identifier(self) -> RocketFlexSessionBuilder<T, SetIsAnonymous<S>>
+    where
+        S::IsAnonymous: IsUnset,
+    {
+        self.is_anonymous(|data: &T| data.identifier().is_none())
+    }
 }
 
 #[rocket::async_trait]
@@ -107,55 +687,294 @@ where
         use rocket::fairing::Kind;
         rocket::fairing::Info {
             name: "Rocket Flex Session",
-            kind: Kind::Ignite | Kind::Response | Kind::Shutdown | Kind::Singleton,
+            kind: Kind::Ignite | Kind::Request | Kind::Response | Kind::Shutdown | Kind::Singleton,
         }
     }
 
     async fn on_ignite(&self, rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
-        rocket::debug!("Setting up session resources...");
+        let mut fairing = self.clone();
+        fairing.options.cookie_name = resolve_cookie_name(
+            &self.options.cookie_name,
+            type_name::<T>(),
+            rocket.figment().profile().as_str().as_str(),
+        );
+        if let (Some(key_ring), SessionCookieKey::RocketSecretKey) =
+            (&self.key_ring, &self.options.cookie_key)
+        {
+            fairing.options.cookie_key = key_ring.cookie_key();
+        }
+        let options = &fairing.options;
+
+        // The TTLs for anonymous and authenticated sessions depend on the `is_anonymous` setting
+        if self.is_anonymous.is_none()
+            && (options.anonymous_ttl.is_some() || options.authenticated_ttl.is_some())
+        {
+            session_log!(
+                options,
+                error,
+                "The anonymous_ttl and authenticated_ttl options of RocketFlexSession<{}> require the is_anonymous setting of the fairing (e.g. anonymous_without_identifier()).",
+                type_name::<T>()
+            );
+            return Err(rocket);
+        }
+
+        // Make sure the cookie name isn't used by another session fairing
+        let rocket = match rocket.state::<SessionCookieNames>() {
+            Some(_) => rocket,
+            None => rocket.manage(SessionCookieNames::default()),
+        };
+        let conflicting_type = rocket.state::<SessionCookieNames>().and_then(|names| {
+            let mut names = names.0.lock().unwrap();
+            match names.get(&options.cookie_name) {
+                Some(other_type) => Some(*other_type),
+                None => {
+                    names.insert(options.cookie_name.clone(), type_name::<T>());
+                    None
+                }
+            }
+        });
+        if let Some(other_type) = conflicting_type {
+            session_log!(
+                options,
+                error,
+                "The session cookie name '{}' of RocketFlexSession<{}> is already used by RocketFlexSession<{other_type}>. Please configure a different cookie name.",
+                options.cookie_name,
+                type_name::<T>()
+            );
+            return Err(rocket);
+        }
+
+        session_log!(options, debug, "Setting up session resources...");
         if let Err(e) = self.storage.setup().await {
-            rocket::warn!("Error during session storage setup: {}", e);
+            let message = e.log_message(options.redact_errors);
+            session_log!(
+                options,
+                warn,
+                "Error during session storage setup: {message}"
+            );
+        }
+
+        if let Some(queue) = &self.persist_queue {
+            let fairing = Arc::new(fairing.clone());
+            queue.start(move |op| {
+                let fairing = fairing.clone();
+                async move { fairing.run_persist_op(op).await }
+            });
         }
 
-        Ok(rocket.manage::<RocketFlexSession<T>>(RocketFlexSession {
-            options: self.options.clone(),
-            storage: self.storage.clone(),
-        }))
+        Ok(rocket.manage::<RocketFlexSession<T>>(fairing))
     }
 
-    async fn on_response<'r>(&self, req: &'r Request<'_>, _res: &mut Response<'r>) {
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        req.local_cache(|| RequestMethod(req.method()));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        self.expire_legacy_cookies(req, res);
+
         // Get session data from request local cache, or generate a default empty one
-        let (session_inner, _): &LocalCachedSession<T> =
+        let (session_inner, session_error): &LocalCachedSession<T> =
             req.local_cache(|| (Mutex::default(), None));
+        if let Some(SessionError::Skipped) = session_error {
+            return;
+        }
+        if session_inner.lock().unwrap().is_read_only() {
+            session_log!(
+                self.options,
+                debug,
+                "Discarding session changes for {} request",
+                req.method()
+            );
+            return;
+        }
+
+        let options = &self.options;
 
         // Take inner session data
-        let (updated, deleted) = session_inner.lock().unwrap().take_for_storage();
+        let (
+            active_id,
+            is_new,
+            active_ttl,
+            anchor,
+            presence_data,
+            created_at,
+            deleted_created_at,
+            (updated, deleted),
+            report,
+            loaded,
+        ) = {
+            let mut inner = session_inner.lock().unwrap();
+            (
+                inner.get_id().map(str::to_owned),
+                inner.is_new(),
+                inner.get_current_ttl(),
+                inner.get_anchor(|| self.clock.now()),
+                options
+                    .presence_sample_rate
+                    .filter(|rate| rand::random::<f64>() < *rate)
+                    .and_then(|_| inner.get_current_data().cloned()),
+                inner.get_created_at(|| self.clock.now()),
+                inner.get_deleted_created_at(),
+                inner.take_for_storage(),
+                inner.take_persist_report(),
+                inner.take_loaded_snapshot(),
+            )
+        };
+
+        // Add the expiry header for rolling sessions
+        if let Some((header, ttl)) = options
+            .expiry_header
+            .as_ref()
+            .filter(|_| options.rolling)
+            .zip(active_ttl)
+        {
+            // Same as `Session::expires_at`, calculated from the request's reference time
+            let expires = anchor.unix_timestamp() + i64::from(ttl);
+            res.set_raw_header(header.clone(), expires.to_string());
+        }
+
+        // Add the correlation ID of the active session
+        if options.correlation_local_cache {
+            set_correlation_id(req, active_id.as_deref());
+        }
+        if let Some((header, id)) = options
+            .correlation_header
+            .as_ref()
+            .zip(active_id.as_deref())
+        {
+            let correlation_id = SessionCorrelationId::new(id);
+            res.set_raw_header(header.clone(), correlation_id.to_string());
+        }
 
         // Handle deleted session
         if let Some((id, data)) = deleted {
-            rocket::debug!("Found deleted session. Deleting session '{id}'...");
-            if let Err(e) = self.storage.delete(&id, data).await {
-                rocket::warn!("Error while deleting session '{id}': {e}");
-            } else {
-                rocket::debug!("Deleted session '{id}' successfully");
-            }
+            let (log_id, description) = (options.log_ids.format(&id), self.describe(&data));
+            session_log!(
+                options,
+                debug,
+                "Found deleted session. Deleting session '{log_id}'{description}..."
+            );
+            self.persist_delete(id, data, deleted_created_at, report.clone())
+                .await;
         }
 
         // Handle updated session
-        if let Some((id, data, ttl)) = updated {
-            rocket::debug!("Found updated session. Saving session '{id}'...");
-            if let Err(e) = self.storage.save(&id, data, ttl).await {
-                rocket::error!("Error while saving session '{id}': {e}");
+        if let Some((id, mut data, ttl)) = updated {
+            let (log_id, description) = (options.log_ids.format(&id), self.describe(&data));
+            session_log!(
+                options,
+                debug,
+                "Found updated session. Saving session '{log_id}'{description}..."
+            );
+            let persist = self.should_persist(&id, &data);
+            if !persist && is_new {
+                session_log!(
+                    options,
+                    debug,
+                    "Skipped saving anonymous session '{log_id}'"
+                );
+            } else if !persist {
+                // An existing session that became anonymous (e.g. after clearing the user ID)
+                // is deleted, so the cookie doesn't load the stored session again
+                session_log!(
+                    options,
+                    debug,
+                    "Session '{log_id}' became anonymous. Deleting stored session..."
+                );
+                self.persist_delete(id, data, created_at, report.clone())
+                    .await;
             } else {
-                rocket::debug!("Saved session '{id}' successfully");
+                if let Some(metrics) = self.lifetime_metrics.as_ref().filter(|_| is_new) {
+                    metrics.record_created();
+                }
+                if let Some(loaded) = loaded.filter(|_| !is_new) {
+                    data = self.merge_concurrent(req, &id, &loaded, data).await;
+                }
+                if let Some(enrich) = &self.enrich {
+                    enrich(req, &mut data).await;
+                }
+                match &self.persist_queue {
+                    Some(queue) => {
+                        let report = report.clone();
+                        let op = PersistOp::Save {
+                            id,
+                            data,
+                            ttl,
+                            report,
+                        };
+                        self.queue_persist_op(queue, op).await
+                    }
+                    None => {
+                        let result = self.save_session(&id, data, ttl).await;
+                        if let (
+                            Err(SessionError::Serialization(_)),
+                            SerializationErrorPolicy::Fail,
+                        ) = (&result, &self.serialization_error_policy)
+                        {
+                            *res = Response::build()
+                                .status(Status::InternalServerError)
+                                .finalize();
+                        }
+                        if let Some(report) = &report {
+                            report.record(result);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Call the request's `on_persisted` callbacks, unless its operations are still queued
+        drop(report);
+
+        // Record session activity
+        if let Some((id, limit)) = active_id.zip(options.activity_log_size) {
+            let entry = ActivityEntry {
+                timestamp: self.clock.now(),
+                path: req.uri().path().to_string(),
+                status: res.status().code,
+            };
+            if let Err(e) = self.storage.record_activity(&id, entry, limit).await {
+                let (log_id, message) = (
+                    options.log_ids.format(&id),
+                    e.log_message(options.redact_errors),
+                );
+                session_log!(
+                    options,
+                    warn,
+                    "Error while recording activity for session '{log_id}': {message}"
+                );
+            }
+        }
+
+        // Record presence of the session's identifier
+        if let Some(data) = presence_data {
+            if let Err(e) = self.storage.record_presence(&data, self.clock.now()).await {
+                let message = e.log_message(options.redact_errors);
+                session_log!(options, warn, "Error while recording presence: {message}");
             }
         }
     }
 
     async fn on_shutdown(&self, _rocket: &Rocket<Orbit>) {
-        rocket::debug!("Shutting down session resources...");
+        let options = &self.options;
+        session_log!(options, debug, "Shutting down session resources...");
+        if let Some(queue) = &self.persist_queue {
+            if let Err(e) = queue.shutdown().await {
+                let message = e.log_message(options.redact_errors);
+                session_log!(
+                    options,
+                    warn,
+                    "Error while completing queued session operations: {message}"
+                );
+            }
+        }
         if let Err(e) = self.storage.shutdown().await {
-            rocket::warn!("Error during session storage shutdown: {e}");
+            let message = e.log_message(options.redact_errors);
+            session_log!(
+                options,
+                warn,
+                "Error during session storage shutdown: {message}"
+            );
         }
     }
 }
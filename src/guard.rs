@@ -1,14 +1,19 @@
 use std::{any::type_name, sync::Mutex};
 
 use rocket::{
-    http::CookieJar,
+    http::{Cookie, CookieJar},
     request::{FromRequest, Outcome},
     Request,
 };
 
 use crate::{
-    error::SessionError, session_inner::SessionInner, storage::SessionStorage, RocketFlexSession,
-    Session,
+    analytics::TerminationReason,
+    correlation::set_correlation_id,
+    error::SessionError,
+    logging::session_log,
+    policy::{ParseErrorOutcome, ParseErrorPolicy},
+    session_inner::SessionInner,
+    RocketFlexSession, Session,
 };
 
 /// Type of the cached inner session data in Rocket's request local cache
@@ -29,18 +34,22 @@ where
         // Use rocket's local cache so that the session data is only fetched once per request
         let (cached_inner, session_error): &LocalCachedSession<T> = req
             .local_cache_async(async {
-                fetch_session_data(
-                    cookie_jar,
-                    &fairing.options.cookie_name,
-                    fairing
-                        .options
-                        .rolling
-                        .then(|| fairing.options.ttl.unwrap_or(fairing.options.max_age)),
-                    fairing.storage.as_ref(),
-                )
-                .await
+                if fairing.check_skip_request(req) {
+                    session_log!(fairing.options, debug, "Skipping session for this request");
+                    return (Mutex::default(), Some(SessionError::Skipped));
+                }
+                let read_only = fairing.check_read_only_request(req);
+                let (inner, error) = fetch_session_data(cookie_jar, fairing, read_only).await;
+                if read_only {
+                    inner.lock().unwrap().set_read_only();
+                }
+                (inner, error)
             })
             .await;
+        if fairing.options.correlation_local_cache {
+            let inner = cached_inner.lock().unwrap();
+            set_correlation_id(req, inner.get_id());
+        }
 
         Outcome::Success(Session::new(
             cached_inner,
@@ -48,13 +57,47 @@ where
             cookie_jar,
             &fairing.options,
             fairing.storage.as_ref(),
+            fairing.clock.as_ref(),
+            fairing.is_anonymous.as_deref(),
+            fairing.quota.as_deref(),
         ))
     }
 }
 
+/// Get the session of the request from Rocket's local cache, without fetching it from storage.
+/// If the session wasn't fetched by the request guard, this is an empty session.
+pub(crate) fn cached_session<'r, T>(req: &'r Request<'_>) -> Session<'r, T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    let fairing = get_fairing::<T>(req.rocket());
+    let (cached_inner, session_error): &LocalCachedSession<T> =
+        req.local_cache(|| match fairing.check_skip_request(req) {
+            true => (Mutex::default(), Some(SessionError::Skipped)),
+            false => {
+                let mut inner = SessionInner::default();
+                if fairing.check_read_only_request(req) {
+                    inner.set_read_only();
+                }
+                (Mutex::new(inner), None)
+            }
+        });
+
+    Session::new(
+        cached_inner,
+        session_error.as_ref(),
+        req.cookies(),
+        &fairing.options,
+        fairing.storage.as_ref(),
+        fairing.clock.as_ref(),
+        fairing.is_anonymous.as_deref(),
+        fairing.quota.as_deref(),
+    )
+}
+
 /// Get session configuration from Rocket state
 #[inline(always)]
-fn get_fairing<T>(rocket: &rocket::Rocket<rocket::Orbit>) -> &RocketFlexSession<T>
+pub(crate) fn get_fairing<T>(rocket: &rocket::Rocket<rocket::Orbit>) -> &RocketFlexSession<T>
 where
     T: Send + Sync + Clone + 'static,
 {
@@ -66,35 +109,204 @@ where
     })
 }
 
-/// Fetch session data from storage
+/// Fetch session data from storage. Rolling sessions aren't extended if the request is read-only.
 #[inline(always)]
-async fn fetch_session_data<'r, T: Send + Sync + Clone>(
+async fn fetch_session_data<'r, T>(
     cookie_jar: &'r CookieJar<'_>,
-    cookie_name: &str,
-    rolling_ttl: Option<u32>,
-    storage: &'r dyn SessionStorage<T>,
-) -> LocalCachedSession<T> {
-    let session_cookie = cookie_jar.get_private(cookie_name);
+    fairing: &'r RocketFlexSession<T>,
+    read_only: bool,
+) -> LocalCachedSession<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    let options = &fairing.options;
+    let storage = fairing.storage.as_ref();
+    let session_cookie = options.cookie_key.get(cookie_jar, &options.cookie_name);
     if let Some(cookie) = session_cookie {
         let id = cookie.value();
-        rocket::debug!("Got session id '{id}' from cookie. Retrieving session...");
-        match storage.load(id, rolling_ttl, cookie_jar).await {
-            Ok((data, ttl)) => {
-                rocket::debug!("Session found. Creating existing session...");
-                let session_inner = SessionInner::new_existing(id, data, ttl);
+        if !fairing.check_valid_id(id) {
+            session_log!(
+                options,
+                info,
+                "Invalid session ID in cookie, creating empty session"
+            );
+            return (Mutex::default(), Some(SessionError::InvalidId));
+        }
+        let log_id = options.log_ids.format(id);
+        session_log!(
+            options,
+            debug,
+            "Got session id '{log_id}' from cookie. Retrieving session..."
+        );
+        let rolling_ttl =
+            (options.rolling && !read_only).then(|| options.ttl.unwrap_or(options.max_age));
+        match storage
+            .load_with_metadata(id, rolling_ttl, cookie_jar)
+            .await
+        {
+            Ok((data, ttl, metadata)) => {
+                if let Some(metrics) = &fairing.parse_error_metrics {
+                    metrics.record(ParseErrorOutcome::Loaded);
+                }
+                if let Err(e) = fairing.check_valid_data(&data) {
+                    let message = e.log_message(options.redact_errors);
+                    session_log!(
+                        options,
+                        warn,
+                        "Session '{log_id}' failed validation, deleting session: {message}"
+                    );
+                    if let Some(metrics) = &fairing.lifetime_metrics {
+                        let lifetime = (metadata.created_at).and_then(|created_at| {
+                            (fairing.clock.now() - created_at).try_into().ok()
+                        });
+                        metrics.record_terminated(TerminationReason::Rejected, lifetime);
+                    }
+                    if let Err(e) = storage.delete(id, data).await {
+                        let message = e.log_message(options.redact_errors);
+                        session_log!(
+                            options,
+                            warn,
+                            "Error while deleting invalid session '{log_id}': {message}"
+                        );
+                    }
+                    return (Mutex::default(), Some(e));
+                }
+                session_log!(
+                    options,
+                    debug,
+                    "Session found. Creating existing session..."
+                );
+                // Rolling sessions are extended by the anonymous or authenticated TTL, if set
+                let class_ttl = rolling_ttl
+                    .and(fairing.is_anonymous.as_deref())
+                    .and_then(|is_anonymous| options.ttl_for(is_anonymous(&data)))
+                    .filter(|class_ttl| Some(*class_ttl) != rolling_ttl);
+                let mut session_inner = SessionInner::new_existing(
+                    id,
+                    data,
+                    ttl,
+                    metadata.created_at,
+                    fairing.clock.now(),
+                );
+                if let Some(class_ttl) = class_ttl {
+                    session_inner.set_ttl(class_ttl);
+                }
+                if fairing.merge.is_some() {
+                    session_inner.keep_loaded_snapshot();
+                }
                 (Mutex::new(session_inner), None)
             }
+            Err(e @ SessionError::Parsing(_)) => handle_parse_error(id, e, fairing).await,
             Err(e) => {
-                rocket::info!("Error from session storage, creating empty session: {e}");
+                let message = e.log_message(options.redact_errors);
+                session_log!(
+                    options,
+                    info,
+                    "Error from session storage, creating empty session: {message}"
+                );
+                if matches!(e, SessionError::Expired | SessionError::NotFound) {
+                    // Remove the stale cookie, so the expiry is only recorded once
+                    let mut remove_cookie =
+                        Cookie::build(options.cookie_name.to_owned()).path(options.path.to_owned());
+                    if let Some(domain) = &options.domain {
+                        remove_cookie = remove_cookie.domain(domain.to_owned());
+                    }
+                    options.cookie_key.remove(cookie_jar, remove_cookie);
+                    if let Some(metrics) = &fairing.lifetime_metrics {
+                        metrics.record_terminated(TerminationReason::Expired, None);
+                    }
+                }
                 (Mutex::default(), Some(e))
             }
         }
     } else {
-        rocket::debug!("No valid session cookie found. Creating empty session...");
+        session_log!(
+            options,
+            debug,
+            "No valid session cookie found. Creating empty session..."
+        );
         (Mutex::default(), Some(SessionError::NoSessionCookie))
     }
 }
 
+/// Handle stored session data that failed to deserialize, according to the parse error policy
+async fn handle_parse_error<T>(
+    id: &str,
+    error: SessionError,
+    fairing: &RocketFlexSession<T>,
+) -> LocalCachedSession<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    let options = &fairing.options;
+    let log_id = options.log_ids.format(id);
+    let message = error.log_message(options.redact_errors);
+    let metrics = fairing.parse_error_metrics.as_ref();
+    if let Some(metrics) = metrics {
+        metrics.record(ParseErrorOutcome::Corrupted);
+    }
+
+    match &fairing.parse_error_policy {
+        ParseErrorPolicy::Keep => {
+            session_log!(
+                options,
+                warn,
+                "Failed to parse session '{log_id}', creating empty session: {message}"
+            );
+        }
+        ParseErrorPolicy::Delete => {
+            session_log!(
+                options,
+                warn,
+                "Failed to parse session '{log_id}', deleting session: {message}"
+            );
+            match fairing.storage.delete_unreadable(id).await {
+                Ok(()) => {
+                    if let Some(metrics) = metrics {
+                        metrics.record(ParseErrorOutcome::Deleted);
+                    }
+                    if let Some(metrics) = &fairing.lifetime_metrics {
+                        metrics.record_terminated(TerminationReason::Rejected, None);
+                    }
+                }
+                Err(e) => {
+                    let message = e.log_message(options.redact_errors);
+                    session_log!(
+                        options,
+                        warn,
+                        "Error while deleting unreadable session '{log_id}': {message}"
+                    );
+                }
+            }
+        }
+        ParseErrorPolicy::Recover(recover) => match recover(id, &error) {
+            Some(data) => {
+                session_log!(
+                    options,
+                    warn,
+                    "Failed to parse session '{log_id}', continuing with recovered data: {message}"
+                );
+                if let Some(metrics) = metrics {
+                    metrics.record(ParseErrorOutcome::Recovered);
+                }
+                let ttl = options.ttl.unwrap_or(options.max_age);
+                let now = fairing.clock.now();
+                let mut session_inner = SessionInner::new_existing(id, data, ttl, None, now);
+                session_inner.mark_updated();
+                return (Mutex::new(session_inner), None);
+            }
+            None => {
+                session_log!(
+                    options,
+                    warn,
+                    "Failed to parse or recover session '{log_id}', creating empty session: {message}"
+                );
+            }
+        },
+    }
+    (Mutex::default(), Some(error))
+}
+
 /// If using rocket-okapi, this implements OpenApiFromRequest for Session to ignore the request guard
 #[cfg(feature = "rocket_okapi")]
 impl<'r, T> rocket_okapi::request::OpenApiFromRequest<'r> for Session<'r, T>
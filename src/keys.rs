@@ -0,0 +1,187 @@
+//! Key management
+//!
+//! A [`KeyRing`] derives a key for each purpose (e.g. cookie encryption, at-rest encryption, and
+//! token signing) from one master secret, using HKDF-SHA256. Each purpose gets an independent key,
+//! so a leaked key can't be used for another purpose, and new features don't need a new secret in
+//! your configuration.
+//!
+//! Pass the key ring to the [fairing builder](crate::RocketFlexSession::builder) to encrypt the
+//! session cookie with the derived cookie key. Derived keys are stable: the same master secret
+//! always derives the same keys, so they can also be derived in other services.
+//!
+//! # Key providers
+//! To avoid keeping raw secrets in your configuration, a [`KeyProvider`] fetches encryption keys
+//! from an external key management service (KMS or HSM), and rotates them. Keys have an ID that
+//! should be stored alongside the encrypted data, so older keys can be fetched to decrypt it after
+//! a rotation. The key ring is also a key provider, with a single key derived for
+//! [`KeyPurpose::AtRestEncryption`].
+//!
+//! | Provider | Feature Flag |
+//! |----------|-------------|
+//! | [`KeyRing`] | Built-in |
+//! | [`AwsKmsKeyProvider`](aws_kms::AwsKmsKeyProvider) | `aws_kms` |
+//!
+//! Other services (e.g. GCP Cloud KMS or Vault) can be supported by implementing the trait:
+//! generate a random data key, encrypt it with the service, and use the encrypted key as its ID.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{keys::{KeyPurpose, KeyRing}, RocketFlexSession};
+//!
+//! // e.g. loaded from an environment variable
+//! let master_secret = [3u8; 64];
+//! let key_ring = KeyRing::new(&master_secret).expect("secret should be long enough");
+//!
+//! // Key for your own use, e.g. to encrypt session data before it's stored
+//! let at_rest_key = key_ring.derive(KeyPurpose::AtRestEncryption);
+//!
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .key_ring(key_ring)
+//!     .build();
+//! ```
+
+#[cfg(feature = "aws_kms")]
+pub mod aws_kms;
+
+use std::{borrow::Cow, fmt};
+
+use cookie::Key;
+use hkdf::Hkdf;
+use rocket::async_trait;
+use sha2::Sha256;
+
+use crate::{
+    error::{SessionError, SessionResult},
+    SessionCookieKey,
+};
+
+/// The HKDF salt of all derived keys
+const KEY_RING_SALT: &[u8] = b"rocket-flex-session key ring";
+
+/// The minimum length of the master secret, in bytes
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// The length of derived keys, in bytes
+pub const DERIVED_KEY_LENGTH: usize = 64;
+
+/// The purpose of a derived key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum KeyPurpose {
+    /// Encryption of the session cookie
+    CookieEncryption,
+    /// Encryption of session data in storage
+    AtRestEncryption,
+    /// Signing of tokens (e.g. CSRF tokens)
+    TokenSigning,
+    /// A purpose of your own, identified by a unique label. Custom labels are namespaced, so
+    /// they can't collide with the built-in purposes.
+    Custom(&'static str),
+}
+
+impl KeyPurpose {
+    /// The HKDF info of the purpose, which must never change for a purpose
+    fn info(&self) -> Cow<'static, str> {
+        match self {
+            Self::CookieEncryption => Cow::Borrowed("cookie-encryption"),
+            Self::AtRestEncryption => Cow::Borrowed("at-rest-encryption"),
+            Self::TokenSigning => Cow::Borrowed("token-signing"),
+            Self::Custom(label) => Cow::Owned(format!("custom:{label}")),
+        }
+    }
+}
+
+/// Derives purpose-specific keys from a master secret. See the [module docs](self).
+#[derive(Clone)]
+pub struct KeyRing {
+    hkdf: Hkdf<Sha256>,
+}
+
+impl KeyRing {
+    /// Create the key ring from the master secret. Returns `None` if the secret is
+    /// shorter than 32 bytes.
+    pub fn new(master_secret: &[u8]) -> Option<Self> {
+        if master_secret.len() < MIN_SECRET_LENGTH {
+            return None;
+        }
+        Some(Self {
+            hkdf: Hkdf::new(Some(KEY_RING_SALT), master_secret),
+        })
+    }
+
+    /// Derive the key for the given purpose
+    pub fn derive(&self, purpose: KeyPurpose) -> [u8; DERIVED_KEY_LENGTH] {
+        let mut key = [0; DERIVED_KEY_LENGTH];
+        let info = format!("rocket-flex-session {}", purpose.info());
+        self.hkdf
+            .expand(info.as_bytes(), &mut key)
+            .expect("derived key length should be valid for HKDF-SHA256");
+        key
+    }
+
+    /// The key used to encrypt the session cookie, derived for [`KeyPurpose::CookieEncryption`]
+    pub fn cookie_key(&self) -> SessionCookieKey {
+        SessionCookieKey::Private(Key::from(&self.derive(KeyPurpose::CookieEncryption)))
+    }
+}
+
+impl fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the secret
+        f.write_str("KeyRing")
+    }
+}
+
+/// The ID of the key provided by the [`KeyRing`]
+const KEY_RING_KEY_ID: &str = "key-ring";
+
+/// An encryption key fetched from a [`KeyProvider`]
+#[derive(Clone)]
+pub struct ProvidedKey {
+    /// The ID of the key, which should be stored alongside the encrypted data
+    pub id: String,
+    /// The key material
+    pub material: Vec<u8>,
+}
+
+impl fmt::Debug for ProvidedKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Don't print the key material
+        f.debug_struct("ProvidedKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fetches encryption keys from a key management service. See the [module docs](self).
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// Get the current key, which should be used to encrypt new data
+    async fn current_key(&self) -> SessionResult<ProvidedKey>;
+
+    /// Get a key by its ID, to decrypt data that was encrypted with it
+    async fn key(&self, id: &str) -> SessionResult<ProvidedKey>;
+
+    /// Optional: Rotate the current key, and return the new key. Older keys must still be
+    /// available by their ID.
+    async fn rotate(&self) -> SessionResult<ProvidedKey> {
+        Err(SessionError::Unsupported("key rotation"))
+    }
+}
+
+#[async_trait]
+impl KeyProvider for KeyRing {
+    async fn current_key(&self) -> SessionResult<ProvidedKey> {
+        Ok(ProvidedKey {
+            id: KEY_RING_KEY_ID.to_owned(),
+            material: self.derive(KeyPurpose::AtRestEncryption).to_vec(),
+        })
+    }
+
+    async fn key(&self, id: &str) -> SessionResult<ProvidedKey> {
+        match id {
+            KEY_RING_KEY_ID => self.current_key().await,
+            _ => Err(SessionError::NotFound),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Key provider using AWS KMS
+
+use std::{collections::HashMap, sync::Mutex};
+
+use aws_sdk_kms::{primitives::Blob, types::DataKeySpec, Client};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bon::Builder;
+use rocket::async_trait;
+
+use crate::error::{SessionError, SessionResult};
+
+use super::{KeyProvider, ProvidedKey};
+
+/**
+Key provider using [AWS KMS](https://aws.amazon.com/kms/) envelope encryption, with the
+[aws-sdk-kms](https://docs.rs/crate/aws-sdk-kms) crate.
+
+Keys are 256-bit data keys generated by KMS with your KMS key, so the raw key never needs to be
+in your configuration. The ID of each data key is its encrypted form (base64-encoded), which is
+decrypted by KMS when the key is fetched by ID. Decrypted keys are cached in memory, so KMS is
+only called once per key. [Rotating](KeyProvider::rotate) generates a new data key.
+
+# Example
+```rust,no_run
+use rocket_flex_session::keys::{aws_kms::AwsKmsKeyProvider, KeyProvider};
+
+# async fn example(client: aws_sdk_kms::Client) {
+// e.g. with a client created from `aws_config::load_from_env()`
+let provider = AwsKmsKeyProvider::builder()
+    .client(client)
+    .key_id("alias/session-data")
+    .build();
+let key = provider.current_key().await.expect("KMS should generate a data key");
+# }
+```
+*/
+#[derive(Builder)]
+pub struct AwsKmsKeyProvider {
+    /// The KMS client
+    client: Client,
+    /// The ID, ARN, or alias of the KMS key used to generate and decrypt the data keys
+    #[builder(into)]
+    key_id: String,
+    /// Decrypted data keys by ID
+    #[builder(skip)]
+    keys: Mutex<HashMap<String, ProvidedKey>>,
+    /// The ID of the current data key
+    #[builder(skip)]
+    current_id: Mutex<Option<String>>,
+}
+
+impl AwsKmsKeyProvider {
+    fn cache_key(&self, key: ProvidedKey) -> ProvidedKey {
+        let mut keys = self.keys.lock().unwrap();
+        keys.insert(key.id.clone(), key.clone());
+        key
+    }
+
+    fn cached_key(&self, id: &str) -> Option<ProvidedKey> {
+        let keys = self.keys.lock().unwrap();
+        keys.get(id).cloned()
+    }
+}
+
+#[async_trait]
+impl KeyProvider for AwsKmsKeyProvider {
+    async fn current_key(&self) -> SessionResult<ProvidedKey> {
+        let current_id = self.current_id.lock().unwrap().clone();
+        match current_id {
+            Some(id) => self.key(&id).await,
+            None => self.rotate().await,
+        }
+    }
+
+    async fn key(&self, id: &str) -> SessionResult<ProvidedKey> {
+        if let Some(key) = self.cached_key(id) {
+            return Ok(key);
+        }
+        let encrypted_key = URL_SAFE_NO_PAD
+            .decode(id)
+            .map_err(|_| SessionError::NotFound)?;
+        let output = self
+            .client
+            .decrypt()
+            .key_id(&self.key_id)
+            .ciphertext_blob(Blob::new(encrypted_key))
+            .send()
+            .await
+            .map_err(|e| SessionError::Backend(Box::new(e)))?;
+        let material = output.plaintext().ok_or(SessionError::InvalidData)?;
+        Ok(self.cache_key(ProvidedKey {
+            id: id.to_owned(),
+            material: material.as_ref().to_vec(),
+        }))
+    }
+
+    async fn rotate(&self) -> SessionResult<ProvidedKey> {
+        let output = self
+            .client
+            .generate_data_key()
+            .key_id(&self.key_id)
+            .key_spec(DataKeySpec::Aes256)
+            .send()
+            .await
+            .map_err(|e| SessionError::Backend(Box::new(e)))?;
+        let (Some(material), Some(encrypted_key)) = (output.plaintext(), output.ciphertext_blob())
+        else {
+            return Err(SessionError::InvalidData);
+        };
+
+        let key = self.cache_key(ProvidedKey {
+            id: URL_SAFE_NO_PAD.encode(encrypted_key.as_ref()),
+            material: material.as_ref().to_vec(),
+        });
+        *self.current_id.lock().unwrap() = Some(key.id.clone());
+        Ok(key)
+    }
+}
@@ -0,0 +1,186 @@
+//! Session fields that are loaded on demand
+//!
+//! Some session data is large but rarely needed (e.g. a cached avatar, or a draft). A
+//! [`LazyField`] keeps such a value out of the main session payload: the session data only stores
+//! the field name, and the value is saved separately in the storage (e.g. in another key or
+//! column) with [`Session::set_lazy`]. It's only loaded when accessed with [`Session::load_lazy`],
+//! so the common path of loading the session stays small and fast.
+//!
+//! The value is stored as JSON, expires along with the session, and is deleted with the session.
+//! Once loaded, it's kept in the field for the rest of the request.
+//!
+//! # Supported storages
+//! Lazy fields are supported by the [memory](crate::storage::memory) and
+//! [Redis](crate::storage::redis) storages, and by the wrapping storages (e.g.
+//! [layers](crate::storage::layer)) when the wrapped storage supports them. The SQL, libSQL,
+//! etcd, and cookie-based storages don't support lazy fields, and return
+//! [`SessionError::Unsupported`](crate::error::SessionError::Unsupported).
+//!
+//! # Example
+//! ```rust
+//! use rocket::{
+//!     http::Status,
+//!     serde::{Deserialize, Serialize},
+//! };
+//! use rocket_flex_session::{lazy::LazyField, RocketFlexSession, Session};
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct MySession {
+//!     user_id: String,
+//!     draft: LazyField<String>,
+//! }
+//!
+//! #[rocket::post("/login/<user_id>")]
+//! fn login(mut session: Session<MySession>, user_id: &str) {
+//!     let draft = LazyField::new("draft");
+//!     session.set(MySession { user_id: user_id.to_owned(), draft });
+//! }
+//!
+//! #[rocket::put("/draft", data = "<draft>")]
+//! async fn save_draft(session: Session<'_, MySession>, draft: String) -> Result<(), Status> {
+//!     session
+//!         .set_lazy(|data| &data.draft, draft)
+//!         .await
+//!         .map_err(|_| Status::InternalServerError)?;
+//!     Ok(())
+//! }
+//!
+//! #[rocket::get("/draft")]
+//! async fn get_draft(session: Session<'_, MySession>) -> Result<Option<String>, Status> {
+//!     session
+//!         .load_lazy(|data| &data.draft)
+//!         .await
+//!         .map_err(|_| Status::InternalServerError)
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<MySession>::default())
+//!     .mount("/", rocket::routes![login, save_draft, get_draft]);
+//! ```
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use rocket::serde::{de::DeserializeOwned, json::serde_json, Deserialize, Serialize};
+
+use crate::{error::SessionError, Session};
+
+/// The value of a lazy field, once loaded or set during the request
+pub(crate) type LoadedValue<U> = Arc<Mutex<Option<U>>>;
+
+/// A field of the session data that's stored separately and loaded on demand. Only the field name
+/// is serialized with the session data. See the [module docs](self).
+///
+/// Clones don't share the loaded value, so a clone loads the value from storage again.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", transparent, bound = "")]
+pub struct LazyField<U> {
+    name: String,
+    /// The value, once loaded or set during the request
+    #[serde(skip)]
+    loaded: LoadedValue<U>,
+}
+
+impl<U> LazyField<U> {
+    /// Create a lazy field with the given name. The name must be unique among the lazy fields of
+    /// the session data.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            loaded: Arc::default(),
+        }
+    }
+
+    /// The name of the field
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<U> Clone for LazyField<U> {
+    fn clone(&self) -> Self {
+        Self::new(self.name.clone())
+    }
+}
+
+impl<U> Debug for LazyField<U> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LazyField").field(&self.name).finish()
+    }
+}
+
+impl<U> PartialEq for LazyField<U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<U> Eq for LazyField<U> {}
+
+impl<T> Session<'_, T>
+where
+    T: Send + Sync + Clone,
+{
+    /// Get the value of a [lazy field](crate::lazy) of the session data, loading it from storage
+    /// if it hasn't been loaded yet during the request. Returns `None` if there's no active
+    /// session, or if the value hasn't been set or has expired.
+    pub async fn load_lazy<U>(
+        &self,
+        field: impl FnOnce(&T) -> &LazyField<U>,
+    ) -> Result<Option<U>, SessionError>
+    where
+        U: DeserializeOwned + Clone,
+    {
+        let Some((session_id, name, loaded)) = self.lazy_field(field) else {
+            return Ok(None);
+        };
+        if let Some(value) = loaded.lock().unwrap().clone() {
+            return Ok(Some(value));
+        }
+        let Some(value) = self.storage.load_lazy_field(&session_id, &name).await? else {
+            return Ok(None);
+        };
+        let value: U =
+            serde_json::from_str(&value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+        *loaded.lock().unwrap() = Some(value.clone());
+        Ok(Some(value))
+    }
+
+    /// Set the value of a [lazy field](crate::lazy) of the session data. The value is saved to
+    /// storage immediately, separately from the session data, and expires with the session.
+    /// Returns `false` if there's no active session.
+    pub async fn set_lazy<U>(
+        &self,
+        field: impl FnOnce(&T) -> &LazyField<U>,
+        value: U,
+    ) -> Result<bool, SessionError>
+    where
+        U: Serialize,
+    {
+        let Some((session_id, name, loaded)) = self.lazy_field(field) else {
+            return Ok(false);
+        };
+        let json =
+            serde_json::to_string(&value).map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        self.storage
+            .save_lazy_field(&session_id, &name, json, self.ttl())
+            .await?;
+        *loaded.lock().unwrap() = Some(value);
+        Ok(true)
+    }
+
+    /// Get the session ID, and the name and loaded value of the lazy field
+    fn lazy_field<U>(
+        &self,
+        field: impl FnOnce(&T) -> &LazyField<U>,
+    ) -> Option<(String, String, LoadedValue<U>)> {
+        let session_id = self.id()?;
+        self.tap(|data| {
+            let field = field(data?);
+            Some((session_id, field.name.clone(), field.loaded.clone()))
+        })
+    }
+}
@@ -206,14 +206,55 @@ This crate supports multiple storage backends with different capabilities:
 | [`storage::redis::RedisFredStorage`] | `redis_fred` | ✅ | Production, distributed systems |
 | [`storage::sqlx::SqlxPostgresStorage`] | `sqlx_postgres` | ✅ | Production, existing database |
 | [`storage::sqlx::SqlxSqliteStorage`] | `sqlx_sqlite` | ✅ | Development and small-scale deployments |
+| [`storage::etcd::EtcdStorage`] | `etcd` | ✅ | Existing etcd clusters |
+| [`storage::libsql::LibsqlStorage`] | `libsql` | ✅ | Edge deployments, Turso |
+| [`storage::rest_kv::RestKvStorage`] | `rest_kv` | ❌ | HTTP KV services (Cloudflare KV, Upstash) |
+| [`storage::tower_sessions::TowerSessionsStorage`] | `tower_sessions` | ❌ | Sharing a tower-sessions store with Axum services |
+| [`storage::rocket_session_store::RocketSessionStoreAdapter`] | `rocket_session_store` | ❌ | Migrating from rocket_session_store |
 
 ## Custom Storage
 
-To implement a custom storage provider, implement the [`SessionStorage`](crate::storage::SessionStorage) trait:
+To implement a custom storage provider, implement the minimal [`SessionStore`](crate::storage::SessionStore)
+trait, and set it with the `store` method of the fairing builder:
 
 ```rust
-use rocket_flex_session::{error::SessionResult, storage::SessionStorage};
-use rocket::{async_trait, http::CookieJar};
+use rocket_flex_session::{error::SessionResult, storage::SessionStore, RocketFlexSession};
+use rocket::async_trait;
+
+pub struct MyCustomStore {}
+
+#[async_trait]
+impl<T> SessionStore<T> for MyCustomStore
+where
+    T: Send + Sync + Clone + 'static,
+{
+    async fn load(&self, id: &str, ttl: Option<u32>) -> SessionResult<(T, u32)> {
+        // Load session from your storage
+        todo!()
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        // Save session to your storage
+        todo!()
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        // Delete session from your storage
+        todo!()
+    }
+}
+
+let fairing = RocketFlexSession::<String>::builder()
+    .store(MyCustomStore {})
+    .build();
+```
+
+For optional capabilities such as indexing or activity logs, implement the full
+[`SessionStorage`](crate::storage::SessionStorage) trait instead, and set it with the `storage` method:
+
+```rust
+use rocket_flex_session::{error::SessionResult, storage::{SessionContext, SessionStorage}};
+use rocket::async_trait;
 
 pub struct MyCustomStorage {}
 
@@ -222,7 +263,7 @@ impl<T> SessionStorage<T> for MyCustomStorage
 where
     T: Send + Sync + Clone + 'static,
 {
-    async fn load(&self, id: &str, ttl: Option<u32>, cookie_jar: &CookieJar) -> SessionResult<(T, u32)> {
+    async fn load(&self, id: &str, ttl: Option<u32>, cookie_jar: &dyn SessionContext) -> SessionResult<(T, u32)> {
         // Load session from your storage
         todo!()
     }
@@ -292,25 +333,94 @@ These features can be enabled as shown
 
 | Name    | Description    |
 |---------|----------------|
+| `admin` | Admin helpers for exporting and erasing a user's sessions (e.g. for GDPR requests). |
 | `cookie` | A cookie-based session store. Data is serialized using serde_json and then encrypted into the value of a cookie. |
 | `redis_fred`  | A session store for Redis (and Redis-compatible databases), using the [fred.rs](https://docs.rs/crate/fred) crate. |
 | `sqlx_postgres`  | A session store using PostgreSQL via the [sqlx](https://docs.rs/crate/sqlx) crate. |
 | `sqlx_sqlite`  | A session store using SQLite via the [sqlx](https://docs.rs/crate/sqlx) crate. |
+| `etcd`  | A session store for [etcd](https://etcd.io/), using the [etcd-client](https://docs.rs/crate/etcd-client) crate. |
+| `libsql`  | A session store for remote SQLite databases (e.g. Turso) using the [libsql](https://docs.rs/crate/libsql) crate. |
+| `rest_kv`  | A session store for HTTP key-value services (e.g. Cloudflare KV), using the [reqwest](https://docs.rs/crate/reqwest) crate. |
+| `tower_sessions`  | A session store over any [tower-sessions](https://docs.rs/crate/tower-sessions) store, e.g. to share sessions with Axum services. |
+| `rocket_session_store`  | A session store over any [rocket_session_store](https://docs.rs/crate/rocket_session_store) store, and a [migration path](crate::storage::rocket_session_store) for apps using that crate. |
+| `moka`  | An in-process session cache for the [tiered storage layer](crate::storage::tiered), using the [moka](https://docs.rs/crate/moka) crate. |
+| `rails`  | [Decoding](crate::storage::rails) of Rails session payloads (Marshal or JSON, optionally signed), for hybrid deployments during a migration. |
+| `django`  | [Decoding](crate::storage::django) of signed Django session payloads, for hybrid deployments during a migration. |
+| `oidc`  | An [OpenID Connect back-channel logout](crate::oidc) handler for SSO integrations. |
+| `edge`  | [Verification](crate::edge) of session cookies outside Rocket, e.g. in an edge worker, using the [cookie](https://docs.rs/crate/cookie) crate. |
+| `json`  | A [responder](crate::responder) for JSON responses that update the session, a [versioned session format](crate::storage::envelope) for sharing sessions with other services, the [express-session format](crate::storage::express), [session scopes](crate::scope) with independent TTLs, and [lazy fields](crate::lazy) that are loaded on demand. |
+| `routes`  | Ready-made [routes](crate::routes) for checking the session expiry, renewing the session, and syncing session-derived state to offline-capable clients. |
+| `zstd`  | [Compression](crate::storage::compression) of stored payloads with Zstandard, using the [zstd](https://docs.rs/crate/zstd) crate. |
+| `lz4`  | [Compression](crate::storage::compression) of stored payloads with LZ4, using the [lz4_flex](https://docs.rs/crate/lz4_flex) crate. |
+| `aws_kms`  | A [key provider](crate::keys::aws_kms) using AWS KMS, with the [aws-sdk-kms](https://docs.rs/crate/aws-sdk-kms) crate. |
+| `test-util`  | Exposes a [conformance test suite](crate::storage::conformance) for custom storage providers, and a [fault injection layer](crate::storage::chaos) for testing. |
 | `rocket_okapi`  | Enables support for the [rocket_okapi](https://docs.rs/crate/rocket_okapi) crate if needed. |
 */
 
+mod activity;
+mod capability;
+mod child;
+mod cookie_key;
+mod correlation;
+mod derived;
+mod exists;
 mod fairing;
 mod guard;
+mod logging;
+mod once;
 mod options;
+mod origin;
+mod recent_auth;
 mod session;
 mod session_hash;
 mod session_index;
 mod session_inner;
 
+#[cfg(feature = "admin")]
+pub mod admin;
+pub mod analytics;
+pub mod audit;
+pub mod bot;
+pub mod clock;
+pub mod deferred;
+pub mod device;
+#[cfg(feature = "edge")]
+pub mod edge;
 pub mod error;
+pub mod keys;
+#[cfg(feature = "json")]
+pub mod lazy;
+pub mod lifecycle;
+pub mod merge;
+#[cfg(feature = "oidc")]
+pub mod oidc;
+pub mod policy;
+pub mod prefs;
+pub mod presence;
+pub mod principal;
+pub mod queue;
+pub mod quota;
+pub mod responder;
+#[cfg(feature = "routes")]
+pub mod routes;
+#[cfg(feature = "json")]
+pub mod scope;
+pub mod security;
 pub mod storage;
+pub mod throttle;
+pub use activity::ActivityEntry;
+pub use capability::Capability;
+pub use child::parent_session_id;
+pub use cookie_key::SessionCookieKey;
+pub use correlation::SessionCorrelationId;
+pub use derived::SessionDerived;
+pub use exists::SessionExists;
 pub use fairing::RocketFlexSession;
-pub use options::RocketFlexSessionOptions;
+pub use options::{
+    CookieOverrides, LogIdFormat, PreflightMode, RocketFlexSessionOptions, SessionLogLevel,
+};
+pub use origin::SameOrigin;
+pub use recent_auth::{RecentlyAuthenticated, SessionAuthTime};
 pub use session::Session;
 pub use session_hash::SessionHashMap;
 pub use session_index::SessionIdentifier;
@@ -0,0 +1,322 @@
+//! Session lifecycle states
+//!
+//! Use [`SessionState`] as your session data type (or wrap it in your own type that implements
+//! [`SessionLifecycle`]) to model the lifecycle of a session explicitly:
+//!
+//! ```text
+//! Anonymous → Authenticated ⇄ Elevated
+//!                   ↓            ↓
+//!                Revoked ←───────┘
+//! ```
+//!
+//! [`Session::lifecycle`] returns the current state with a [`Transition`] handle, which only has
+//! the transition methods that are valid for that state. For example, there's no way to update the
+//! data of a revoked session, or to elevate an anonymous session - these flows won't compile.
+//!
+//! If your storage provider requires a trait implementation for the session data (e.g.
+//! [`SessionSqlx`](crate::storage::sqlx::SessionSqlx)), wrap the state in your own type and
+//! implement [`SessionLifecycle`] for it.
+//!
+//! # Example
+//! ```rust
+//! use rocket::http::Status;
+//! use rocket_flex_session::{
+//!     lifecycle::{Lifecycle, SessionState},
+//!     Session,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! #[rocket::post("/login")]
+//! fn login(mut session: Session<SessionState<User>>) -> Status {
+//!     match session.lifecycle() {
+//!         Lifecycle::Anonymous(anonymous) => {
+//!             anonymous.authenticate(User { user_id: "123".to_owned() });
+//!             Status::Ok
+//!         }
+//!         Lifecycle::Revoked(_) => Status::Forbidden,
+//!         _ => Status::Ok,
+//!     }
+//! }
+//!
+//! #[rocket::post("/sudo")]
+//! fn sudo(mut session: Session<SessionState<User>>) -> Status {
+//!     match session.lifecycle() {
+//!         Lifecycle::Authenticated(authenticated) => {
+//!             authenticated.elevate();
+//!             Status::Ok
+//!         }
+//!         Lifecycle::Elevated(_) => Status::Ok,
+//!         _ => Status::Unauthorized,
+//!     }
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::{Session, SessionIdentifier};
+
+/// The state of a session, with the authenticated data (e.g. the user).
+/// See the [`lifecycle`](crate::lifecycle) module for more info.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SessionState<T> {
+    /// The session isn't authenticated
+    #[default]
+    Anonymous,
+    /// The session is authenticated
+    Authenticated(T),
+    /// The session is authenticated, with elevated privileges (e.g. after re-entering a password)
+    Elevated(T),
+    /// The session was revoked, and can't be used anymore
+    Revoked,
+}
+
+impl<T> SessionState<T> {
+    /// Get the authenticated data, if the session is authenticated or elevated
+    pub fn data(&self) -> Option<&T> {
+        match self {
+            SessionState::Authenticated(data) | SessionState::Elevated(data) => Some(data),
+            SessionState::Anonymous | SessionState::Revoked => None,
+        }
+    }
+}
+
+impl<T> SessionIdentifier for SessionState<T>
+where
+    T: SessionIdentifier,
+{
+    type Id = T::Id;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.data().and_then(|data| data.identifier())
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.data().map(|data| data.tags()).unwrap_or_default()
+    }
+}
+
+/// Trait for session data types that hold a [`SessionState`]. This is implemented for
+/// `SessionState` itself, and can be implemented for your own wrapper type.
+pub trait SessionLifecycle: Send + Sync + Clone {
+    /// The authenticated data type
+    type Data: Send + Sync + Clone;
+
+    /// Get a reference to the session state
+    fn state(&self) -> &SessionState<Self::Data>;
+
+    /// Get a mutable reference to the session state
+    fn state_mut(&mut self) -> &mut SessionState<Self::Data>;
+
+    /// Create the session data from a session state
+    fn from_state(state: SessionState<Self::Data>) -> Self;
+}
+
+impl<T> SessionLifecycle for SessionState<T>
+where
+    T: Send + Sync + Clone,
+{
+    type Data = T;
+
+    fn state(&self) -> &SessionState<T> {
+        self
+    }
+
+    fn state_mut(&mut self) -> &mut SessionState<T> {
+        self
+    }
+
+    fn from_state(state: SessionState<T>) -> Self {
+        state
+    }
+}
+
+/// Marker for the anonymous state. A session without data is also anonymous.
+#[derive(Debug)]
+pub struct Anonymous;
+/// Marker for the authenticated state
+#[derive(Debug)]
+pub struct Authenticated;
+/// Marker for the elevated state
+#[derive(Debug)]
+pub struct Elevated;
+/// Marker for the revoked state
+#[derive(Debug)]
+pub struct Revoked;
+
+/// The current state of a session, as returned by [`Session::lifecycle`]
+pub enum Lifecycle<'s, 'a, S>
+where
+    S: SessionLifecycle,
+{
+    /// The session isn't authenticated
+    Anonymous(Transition<'s, 'a, S, Anonymous>),
+    /// The session is authenticated
+    Authenticated(Transition<'s, 'a, S, Authenticated>),
+    /// The session is authenticated, with elevated privileges
+    Elevated(Transition<'s, 'a, S, Elevated>),
+    /// The session was revoked
+    Revoked(Transition<'s, 'a, S, Revoked>),
+}
+
+/// Handle to a session in a known state, with the transitions that are valid from that state
+pub struct Transition<'s, 'a, S, State>
+where
+    S: SessionLifecycle,
+{
+    session: &'s mut Session<'a, S>,
+    /// The authenticated data, if the state has data
+    data: Option<S::Data>,
+    _state: PhantomData<State>,
+}
+
+impl<'s, 'a, S, State> Transition<'s, 'a, S, State>
+where
+    S: SessionLifecycle,
+{
+    fn new(session: &'s mut Session<'a, S>, data: Option<S::Data>) -> Self {
+        Self {
+            session,
+            data,
+            _state: PhantomData,
+        }
+    }
+
+    /// Set the session state. Will create a new session if there isn't one.
+    fn set_state(&mut self, state: SessionState<S::Data>) {
+        self.session.tap_mut(|data| match data {
+            Some(data) => *data.state_mut() = state,
+            None => *data = Some(S::from_state(state)),
+        });
+    }
+
+    /// Set the session state, and move to the new state
+    fn transition<NewState>(
+        mut self,
+        state: SessionState<S::Data>,
+    ) -> Transition<'s, 'a, S, NewState> {
+        let data = state.data().cloned();
+        self.set_state(state);
+        Transition::new(self.session, data)
+    }
+
+    /// Update the authenticated data, keeping the current state
+    fn update_data<R>(
+        &mut self,
+        f: impl FnOnce(&mut S::Data) -> R,
+        state: fn(S::Data) -> SessionState<S::Data>,
+    ) -> R {
+        let data = self.data.as_mut().expect("state should have data");
+        let response = f(data);
+        let updated_state = state(data.clone());
+        self.set_state(updated_state);
+        response
+    }
+
+    /// Get the authenticated data via cloning
+    fn get_data(&self) -> S::Data {
+        self.data.clone().expect("state should have data")
+    }
+}
+
+impl<'s, 'a, S> Transition<'s, 'a, S, Anonymous>
+where
+    S: SessionLifecycle,
+{
+    /// Authenticate the session with the given data. Will create a new session if there isn't one.
+    pub fn authenticate(self, data: S::Data) -> Transition<'s, 'a, S, Authenticated> {
+        self.transition(SessionState::Authenticated(data))
+    }
+}
+
+impl<'s, 'a, S> Transition<'s, 'a, S, Authenticated>
+where
+    S: SessionLifecycle,
+{
+    /// Get the authenticated data via cloning
+    pub fn data(&self) -> S::Data {
+        self.get_data()
+    }
+
+    /// Update the authenticated data
+    pub fn update<R>(&mut self, f: impl FnOnce(&mut S::Data) -> R) -> R {
+        self.update_data(f, SessionState::Authenticated)
+    }
+
+    /// Elevate the privileges of the session, e.g. after the user re-entered their password
+    pub fn elevate(self) -> Transition<'s, 'a, S, Elevated> {
+        let data = self.get_data();
+        self.transition(SessionState::Elevated(data))
+    }
+
+    /// Revoke the session. The authenticated data is removed, and the session can't be
+    /// authenticated again.
+    pub fn revoke(self) -> Transition<'s, 'a, S, Revoked> {
+        self.transition(SessionState::Revoked)
+    }
+}
+
+impl<'s, 'a, S> Transition<'s, 'a, S, Elevated>
+where
+    S: SessionLifecycle,
+{
+    /// Get the authenticated data via cloning
+    pub fn data(&self) -> S::Data {
+        self.get_data()
+    }
+
+    /// Update the authenticated data
+    pub fn update<R>(&mut self, f: impl FnOnce(&mut S::Data) -> R) -> R {
+        self.update_data(f, SessionState::Elevated)
+    }
+
+    /// Drop the elevated privileges of the session
+    pub fn drop_privileges(self) -> Transition<'s, 'a, S, Authenticated> {
+        let data = self.get_data();
+        self.transition(SessionState::Authenticated(data))
+    }
+
+    /// Revoke the session. The authenticated data is removed, and the session can't be
+    /// authenticated again.
+    pub fn revoke(self) -> Transition<'s, 'a, S, Revoked> {
+        self.transition(SessionState::Revoked)
+    }
+}
+
+impl<S> Transition<'_, '_, S, Revoked>
+where
+    S: SessionLifecycle,
+{
+    /// Delete the revoked session. A new session can be created afterwards.
+    pub fn delete(self) {
+        self.session.delete();
+    }
+}
+
+/// Implementation block for session lifecycle states
+impl<'a, S> Session<'a, S>
+where
+    S: SessionLifecycle,
+{
+    /// Get the current lifecycle state of the session, with the transitions that are valid from
+    /// that state. A session without data is anonymous. See the [`lifecycle`](crate::lifecycle)
+    /// module for more info.
+    pub fn lifecycle(&mut self) -> Lifecycle<'_, 'a, S> {
+        let state = self.tap(|data| data.map(|data| data.state().clone()));
+        match state {
+            None | Some(SessionState::Anonymous) => {
+                Lifecycle::Anonymous(Transition::new(self, None))
+            }
+            Some(SessionState::Authenticated(data)) => {
+                Lifecycle::Authenticated(Transition::new(self, Some(data)))
+            }
+            Some(SessionState::Elevated(data)) => {
+                Lifecycle::Elevated(Transition::new(self, Some(data)))
+            }
+            Some(SessionState::Revoked) => Lifecycle::Revoked(Transition::new(self, None)),
+        }
+    }
+}
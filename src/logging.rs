@@ -0,0 +1,26 @@
+//! Logging helpers that respect the logging options
+
+/// Log a message with Rocket's logging macros, if the level is enabled by the
+/// [`log_level`](crate::RocketFlexSessionOptions::log_level) option.
+///
+/// Usage: `session_log!(options, debug, "message {arg}")`
+macro_rules! session_log {
+    ($options:expr, error, $($arg:tt)+) => {
+        $crate::logging::session_log!(@ $options, Error, error, $($arg)+)
+    };
+    ($options:expr, warn, $($arg:tt)+) => {
+        $crate::logging::session_log!(@ $options, Warn, warn, $($arg)+)
+    };
+    ($options:expr, info, $($arg:tt)+) => {
+        $crate::logging::session_log!(@ $options, Info, info, $($arg)+)
+    };
+    ($options:expr, debug, $($arg:tt)+) => {
+        $crate::logging::session_log!(@ $options, Debug, debug, $($arg)+)
+    };
+    (@ $options:expr, $level:ident, $macro:ident, $($arg:tt)+) => {
+        if $options.log_level >= $crate::SessionLogLevel::$level {
+            rocket::$macro!($($arg)+)
+        }
+    };
+}
+pub(crate) use session_log;
@@ -0,0 +1,85 @@
+//! Merging concurrent changes to the same session
+//!
+//! When two in-flight requests of the same session both modify the session, the request that
+//! finishes last overwrites the changes of the other one. A [`MergeStrategy`], set with the
+//! `merge` setting of the [fairing](crate::RocketFlexSession), reduces these lost updates without
+//! locking the session: before an existing session is saved at the end of a request, the stored
+//! data is loaded again and compared with the data that was loaded at the start of the request.
+//! If it changed in the meantime, the merge function is called with the stored data and the data
+//! of this request, and the merged data is saved instead.
+//!
+//! This costs an additional load from storage for each request that updates an existing session,
+//! and doesn't prevent lost updates between the reload and the save. New and deleted sessions
+//! aren't merged.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{merge::MergeStrategy, RocketFlexSession};
+//! use std::collections::BTreeSet;
+//!
+//! #[derive(Clone, PartialEq)]
+//! struct MySession {
+//!     user_id: String,
+//!     viewed_items: BTreeSet<u32>,
+//! }
+//!
+//! let fairing = RocketFlexSession::<MySession>::builder()
+//!     .merge(MergeStrategy::new(|stored: MySession, mut mine: MySession| {
+//!         mine.viewed_items.extend(stored.viewed_items);
+//!         mine
+//!     }))
+//!     .build();
+//! ```
+
+use std::{fmt, sync::Arc};
+
+/// Function that merges the stored session data (first argument) with the data of the current
+/// request (second argument)
+pub type MergeFn<T> = Arc<dyn Fn(T, T) -> T + Send + Sync>;
+
+/// Function that checks whether the stored session data (second argument) changed since it was
+/// loaded (first argument)
+pub type ChangedFn<T> = Arc<dyn Fn(&T, &T) -> bool + Send + Sync>;
+
+/// Strategy for merging concurrent changes to the same session. See the [module docs](self).
+pub struct MergeStrategy<T> {
+    merge: MergeFn<T>,
+    changed: ChangedFn<T>,
+}
+
+impl<T: PartialEq + 'static> MergeStrategy<T> {
+    /// Create a merge strategy with the given function, which is called with the stored session
+    /// data and the data of the current request if the stored data changed during the request.
+    pub fn new(merge: impl Fn(T, T) -> T + Send + Sync + 'static) -> Self {
+        Self {
+            merge: Arc::new(merge),
+            changed: Arc::new(|loaded: &T, stored: &T| loaded != stored),
+        }
+    }
+}
+
+impl<T> MergeStrategy<T> {
+    /// Merge the data of the current request with the stored data, if it changed since it was
+    /// loaded. Returns the data to save, and whether it was merged.
+    pub(crate) fn apply(&self, loaded: &T, stored: T, mine: T) -> (T, bool) {
+        match (self.changed)(loaded, &stored) {
+            true => ((self.merge)(stored, mine), true),
+            false => (mine, false),
+        }
+    }
+}
+
+impl<T> Clone for MergeStrategy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            merge: self.merge.clone(),
+            changed: self.changed.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for MergeStrategy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeStrategy").finish_non_exhaustive()
+    }
+}
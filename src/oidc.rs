@@ -0,0 +1,267 @@
+//! OpenID Connect back-channel logout
+//!
+//! [`BackChannelLogout`] implements the relying party side of
+//! [OpenID Connect Back-Channel Logout](https://openid.net/specs/openid-connect-backchannel-1_0.html).
+//! Your identity provider sends a logout token to the mounted endpoint when a user logs out
+//! (or is logged out) at the provider, and all sessions with the identifier mapped from the
+//! token's `sub` claim are invalidated. The session storage must support
+//! [indexing](crate::storage::SessionStorageIndexed).
+//!
+//! The crate doesn't verify the signature of the logout token (a JWT) itself - you provide the
+//! `verify` function, e.g. using the `jsonwebtoken` crate and your provider's JWKS, which returns
+//! the token's claims. The crate then validates the claims as required by the spec, and rejects
+//! tokens that have expired (`exp` claim) or were issued too long ago (`iat` claim), using the
+//! fairing's [clock](crate::clock). Replay protection via the `jti` claim can also be done in the
+//! `verify` function if needed.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{oidc::BackChannelLogout, RocketFlexSession, SessionIdentifier};
+//! use rocket_flex_session::storage::memory::MemoryStorageIndexed;
+//!
+//! #[derive(Clone)]
+//! struct MySession {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for MySession {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! let logout = BackChannelLogout::<MySession>::builder()
+//!     .issuer("https://idp.example.com")
+//!     .audience("my-client-id")
+//!     .verify(|_token| Box::pin(async move {
+//!         // Verify the JWT signature and return the claims
+//!         Err("not implemented".to_owned())
+//!     }))
+//!     .map_subject(|sub| Some(sub.to_owned()))
+//!     .build();
+//!
+//! let rocket = rocket::build()
+//!     .attach(
+//!         RocketFlexSession::<MySession>::builder()
+//!             .storage(MemoryStorageIndexed::default())
+//!             .build(),
+//!     )
+//!     .mount("/oidc/backchannel-logout", logout);
+//! ```
+
+use std::sync::Arc;
+
+use bon::Builder;
+use rocket::{
+    data::FromData,
+    form::{Form, FromForm},
+    futures::future::BoxFuture,
+    http::{Header, Method},
+    outcome::Outcome,
+    route::{self, Handler, Route},
+    serde::json::{json, Json, Value},
+    Data, Request, Responder,
+};
+
+use crate::{error::SessionError, guard::get_fairing, logging::session_log, SessionIdentifier};
+
+/// The event type of back-channel logout tokens
+pub const BACKCHANNEL_LOGOUT_EVENT: &str = "http://schemas.openid.net/event/backchannel-logout";
+
+/// Allowed clock skew between the identity provider and this server, in seconds
+const CLOCK_LEEWAY: i64 = 30;
+
+/// Handler for OpenID Connect back-channel logout requests. Mount it at the back-channel
+/// logout URI registered with your identity provider. See the [module docs](self).
+#[derive(Builder, Clone)]
+pub struct BackChannelLogout<T>
+where
+    T: SessionIdentifier + 'static,
+{
+    /// The expected issuer (`iss` claim) of logout tokens, i.e. your identity provider
+    #[builder(into)]
+    issuer: String,
+    /// The expected audience (`aud` claim) of logout tokens, i.e. your client ID
+    #[builder(into)]
+    audience: String,
+    /// Verify the signature of the logout token and return its claims. Return an error if
+    /// the token couldn't be verified.
+    #[builder(with = |f: impl Fn(String) -> BoxFuture<'static, Result<Value, String>> + Send + Sync + 'static| Arc::new(f))]
+    verify: TokenVerifier,
+    /// Map the subject (`sub` claim) of the logout token to the session identifier. Return `None`
+    /// if the subject is unknown.
+    #[builder(with = |f: impl Fn(&str) -> Option<T::Id> + Send + Sync + 'static| Arc::new(f))]
+    map_subject: SubjectMapper<T>,
+    /// Maximum age of logout tokens in seconds, based on their `iat` claim (default: 5 minutes)
+    #[builder(default = 300)]
+    max_age: u32,
+}
+
+/// An async function that verifies a logout token and returns its claims
+type TokenVerifier = Arc<dyn Fn(String) -> BoxFuture<'static, Result<Value, String>> + Send + Sync>;
+
+/// A function that maps a subject to the session identifier
+type SubjectMapper<T> = Arc<dyn Fn(&str) -> Option<<T as SessionIdentifier>::Id> + Send + Sync>;
+
+/// Errors during back-channel logout
+#[derive(Debug, thiserror::Error)]
+pub enum BackChannelLogoutError {
+    /// The logout token failed verification or validation
+    #[error("Invalid logout token: {0}")]
+    InvalidToken(String),
+    /// The sessions couldn't be invalidated
+    #[error(transparent)]
+    Session(#[from] SessionError),
+}
+
+impl<T> BackChannelLogout<T>
+where
+    T: SessionIdentifier + 'static,
+{
+    /// Verify and validate the logout token, and invalidate all sessions of the subject using
+    /// the fairing's storage. Returns the number of sessions invalidated.
+    pub async fn logout(
+        &self,
+        fairing: &crate::RocketFlexSession<T>,
+        logout_token: &str,
+    ) -> Result<u64, BackChannelLogoutError> {
+        let claims = (self.verify)(logout_token.to_owned())
+            .await
+            .map_err(BackChannelLogoutError::InvalidToken)?;
+        let now = fairing.clock.now().unix_timestamp();
+        let subject = self
+            .validate_claims(&claims, now)
+            .map_err(BackChannelLogoutError::InvalidToken)?;
+        let Some(id) = (self.map_subject)(subject) else {
+            return Ok(0);
+        };
+        let storage = fairing
+            .indexed_storage()
+            .ok_or(SessionError::NonIndexedStorage)?;
+        Ok(storage.invalidate_sessions_by_identifier(&id, None).await?)
+    }
+
+    /// Validate the claims of the logout token at the given time (Unix timestamp), and return
+    /// the subject
+    fn validate_claims<'a>(&self, claims: &'a Value, now: i64) -> Result<&'a str, String> {
+        if claims["iss"].as_str() != Some(self.issuer.as_str()) {
+            return Err("unexpected issuer".to_owned());
+        }
+        let audience_matches = match &claims["aud"] {
+            Value::String(aud) => *aud == self.audience,
+            Value::Array(auds) => auds.iter().any(|aud| *aud == *self.audience),
+            _ => false,
+        };
+        if !audience_matches {
+            return Err("unexpected audience".to_owned());
+        }
+        let Some(issued_at) = claims["iat"].as_i64() else {
+            return Err("missing iat claim".to_owned());
+        };
+        if issued_at > now + CLOCK_LEEWAY {
+            return Err("token issued in the future".to_owned());
+        }
+        if issued_at + i64::from(self.max_age) + CLOCK_LEEWAY < now {
+            return Err("token issued too long ago".to_owned());
+        }
+        let Some(expires_at) = claims["exp"].as_i64() else {
+            return Err("missing exp claim".to_owned());
+        };
+        if expires_at + CLOCK_LEEWAY < now {
+            return Err("token has expired".to_owned());
+        }
+        if claims["events"].get(BACKCHANNEL_LOGOUT_EVENT).is_none() {
+            return Err("missing back-channel logout event".to_owned());
+        }
+        if claims.get("nonce").is_some() {
+            return Err("logout tokens must not contain a nonce".to_owned());
+        }
+        match claims["sub"].as_str() {
+            Some(subject) => Ok(subject),
+            None if claims["sid"].is_string() => {
+                Err("logout by sid without sub is not supported".to_owned())
+            }
+            None => Err("missing sub claim".to_owned()),
+        }
+    }
+}
+
+impl<T> From<BackChannelLogout<T>> for Vec<Route>
+where
+    T: SessionIdentifier + 'static,
+{
+    fn from(logout: BackChannelLogout<T>) -> Self {
+        vec![Route::new(Method::Post, "/", logout)]
+    }
+}
+
+/// Form body of a back-channel logout request
+#[derive(FromForm)]
+struct LogoutRequest {
+    logout_token: String,
+}
+
+/// Response to a back-channel logout request
+#[derive(Responder)]
+enum LogoutResponse {
+    #[response(status = 200)]
+    Ok((), Header<'static>),
+    #[response(status = 400)]
+    BadRequest(Json<Value>, Header<'static>),
+    /// Logout failures are also a bad request, as required by the spec
+    #[response(status = 400)]
+    Failed(Json<Value>, Header<'static>),
+}
+
+fn no_store() -> Header<'static> {
+    Header::new("Cache-Control", "no-store")
+}
+
+#[rocket::async_trait]
+impl<T> Handler for BackChannelLogout<T>
+where
+    T: SessionIdentifier + 'static,
+{
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> route::Outcome<'r> {
+        let fairing = get_fairing::<T>(req.rocket());
+        let options = &fairing.options;
+        let logout_token = match Form::<LogoutRequest>::from_data(req, data).await {
+            Outcome::Success(form) => form.into_inner().logout_token,
+            _ => {
+                let error = json!({ "error": "invalid_request" });
+                return route::Outcome::from(
+                    req,
+                    LogoutResponse::BadRequest(Json(error), no_store()),
+                );
+            }
+        };
+
+        let response = match self.logout(fairing, &logout_token).await {
+            Ok(count) => {
+                session_log!(
+                    options,
+                    info,
+                    "Back-channel logout invalidated {count} session(s)"
+                );
+                LogoutResponse::Ok((), no_store())
+            }
+            Err(BackChannelLogoutError::InvalidToken(message)) => {
+                session_log!(options, warn, "Rejected back-channel logout: {message}");
+                let error = json!({ "error": "invalid_request", "error_description": message });
+                LogoutResponse::BadRequest(Json(error), no_store())
+            }
+            Err(BackChannelLogoutError::Session(e)) => {
+                let message = e.log_message(options.redact_errors);
+                session_log!(
+                    options,
+                    error,
+                    "Error during back-channel logout: {message}"
+                );
+                let error = json!({ "error": "logout_failed" });
+                LogoutResponse::Failed(Json(error), no_store())
+            }
+        };
+        route::Outcome::from(req, response)
+    }
+}
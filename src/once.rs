@@ -0,0 +1,45 @@
+use crate::{error::SessionError, Session};
+
+impl<T> Session<'_, T>
+where
+    T: Send + Sync + Clone,
+{
+    /// Store a single-use value for the current session under the given key (e.g. an email
+    /// verification nonce), expiring after the given TTL in seconds. The value is kept in storage
+    /// separately from the session data, and can be consumed with [`take_once`](Session::take_once).
+    /// Setting a value again replaces it. Returns `false` if there's no active session.
+    /// The storage provider must support single-use values (check the docs for the provider you're using).
+    pub async fn set_once(&self, key: &str, value: &str, ttl: u32) -> Result<bool, SessionError> {
+        let Some(session_id) = self.id() else {
+            return Ok(false);
+        };
+        self.storage
+            .save_once_value(&session_id, key, value.to_owned(), ttl)
+            .await?;
+        Ok(true)
+    }
+
+    /// Take a single-use value of the current session, deleting it from storage. The value is
+    /// taken atomically, so if several requests try to take it at the same time, only one of them
+    /// gets it. Returns `None` if there's no active session, or if the value doesn't exist or
+    /// has expired.
+    ///
+    /// # Example
+    /// ```rust
+    /// use rocket_flex_session::Session;
+    ///
+    /// #[rocket::post("/verify-email/<nonce>")]
+    /// async fn verify_email(session: Session<'_, String>, nonce: &str) -> &'static str {
+    ///     match session.take_once("email_nonce").await {
+    ///         Ok(Some(expected)) if expected == nonce => "Email verified",
+    ///         _ => "Invalid or expired link",
+    ///     }
+    /// }
+    /// ```
+    pub async fn take_once(&self, key: &str) -> Result<Option<String>, SessionError> {
+        let Some(session_id) = self.id() else {
+            return Ok(None);
+        };
+        self.storage.take_once_value(&session_id, key).await
+    }
+}
@@ -1,43 +1,388 @@
-/// Options for configuring the session.
-#[derive(Clone, Debug)]
+use std::borrow::Cow;
+
+use bon::Builder;
+use rocket::{
+    figment::Figment,
+    http::SameSite,
+    serde::{de, Deserialize, Deserializer},
+};
+
+use crate::{error::OptionsError, security::fnv1a, SessionCookieKey};
+
+/**
+Options for configuring the session.
+
+The options can be customized with the `with_options` method of the
+[fairing builder](crate::RocketFlexSession::builder), or created with [`RocketFlexSessionOptions::builder`],
+which validates the options when they're built. The builder also fails to compile if an
+option is set more than once.
+
+The options can also be deserialized, e.g. from a config file or environment variables with
+[`from_figment`](RocketFlexSessionOptions::from_figment). Options that are missing will retain
+their default values, and `same_site` accepts `"strict"`, `"lax"`, or `"none"`.
+
+# Example
+```rust
+use rocket_flex_session::{RocketFlexSession, RocketFlexSessionOptions};
+
+let options = RocketFlexSessionOptions::builder()
+    .cookie_name("my_session")
+    .max_age(7 * 24 * 60 * 60)
+    .ttl(60 * 60)
+    .rolling(true)
+    .build()
+    .expect("options should be valid");
+
+let fairing = RocketFlexSession::<String>::builder().options(options).build();
+```
+*/
+#[derive(Builder, Clone, Debug, Deserialize)]
+#[builder(finish_fn(name = build_unvalidated, vis = ""))]
+#[serde(crate = "rocket::serde", default)]
 pub struct RocketFlexSessionOptions {
-    /// The name of the cookie used to store the session ID (default: `"rocket"`)
+    /// The name of the cookie used to store the session ID. The name can include these placeholders,
+    /// which are replaced when the server launches:
+    /// - `{type}`: the session data type in snake case, e.g. `my_session` for `MySession`
+    /// - `{profile}`: the Rocket [profile](rocket::Config::profile), e.g. `debug` or `release`
+    ///
+    /// Each session fairing attached to the server must use a different cookie name, or the
+    /// server will fail to launch. (default: `"rocket"`)
+    #[builder(into, default = "rocket".to_owned())]
     pub cookie_name: String,
     /// The session cookie's `Domain` attribute (default: `None`)
+    #[builder(into)]
     pub domain: Option<String>,
     /// The session cookie's `HttpOnly` attribute (default: `true`)
+    #[builder(default = true)]
     pub http_only: bool,
     /// The session cookie's `Max-Age` attribute, in seconds. This also determines
     /// the session storage TTL, unless you specify a different `ttl` setting. (default: 2 weeks)
+    #[builder(default = 14 * 24 * 60 * 60)]
     pub max_age: u32,
     /// The session cookie's `Path` attribute (default: `"/"`)
+    #[builder(into, default = "/".to_owned())]
     pub path: String,
+    /// Add the session cookie to the response again every time a new session is modified during
+    /// a request, even if the cookie hasn't changed. Set this to `false` to only add the cookie when
+    /// its ID, max-age, or other attributes have changed, saving the cost of re-encrypting it. (default: `true`)
+    #[builder(default = true)]
+    pub rewrite_unchanged_cookie: bool,
     /// Enable 'rolling' sessions where the TTL is extended every time the session is accessed.
     /// This should be used in combination with a shorter `ttl` setting to enable short-lived
     /// sessions that are automatically extended for active users. (default: `false`)
+    #[builder(default)]
     pub rolling: bool,
+    /// When rolling sessions are enabled, add a response header with this name (e.g. `"X-Session-Expires"`)
+    /// containing the new expiration of the active session as a Unix timestamp in seconds. This lets
+    /// single-page apps schedule refresh or logout timers without polling an endpoint. (default: `None`)
+    #[builder(into)]
+    pub expiry_header: Option<String>,
+    /// Add a response header with this name (e.g. `"X-Session-Hash"`) containing a
+    /// [hash of the session ID](crate::SessionCorrelationId), so reverse proxies and log pipelines
+    /// can correlate requests to sessions without seeing the session ID. The header isn't added
+    /// if there's no active session. Make sure to strip it before the response leaves your
+    /// infrastructure if the hash shouldn't be visible to clients. (default: `None`)
+    #[builder(into)]
+    pub correlation_header: Option<String>,
+    /// Store the [hash of the session ID](crate::SessionCorrelationId) in Rocket's request local
+    /// cache, to correlate your own logs with the session. (default: `false`)
+    #[builder(default)]
+    pub correlation_local_cache: bool,
     /// The session cookie's `SameSite` attribute (default: `SameSite::Lax`)
-    pub same_site: rocket::http::SameSite,
+    #[builder(default = SameSite::Lax)]
+    #[serde(deserialize_with = "deserialize_same_site")]
+    pub same_site: SameSite,
     /// The session cookie's `Secure` attribute (default: `true`).
     /// When developing on localhost, you may need to set this to `false` on some browsers.
+    #[builder(default = true)]
     pub secure: bool,
     /// The default TTL (time-to-live) for sessions, in seconds. This value is passed to the
     /// configured session storage. If not set, this defaults to the `max_age` setting.
     pub ttl: Option<u32>,
+    /// The TTL for anonymous sessions, in seconds, e.g. to expire guest sessions quickly. This
+    /// overrides the `ttl` setting when an anonymous session is created, or when a session becomes
+    /// anonymous. Anonymous sessions are determined by the `is_anonymous` setting of the
+    /// [fairing](crate::RocketFlexSession), which is required for this option: Rocket fails to
+    /// launch if it isn't set. (default: `None`)
+    pub anonymous_ttl: Option<u32>,
+    /// The TTL for authenticated (i.e. non-anonymous) sessions, in seconds, e.g. to keep logins for
+    /// weeks. This overrides the `ttl` setting when an authenticated session is created, or when an
+    /// anonymous session becomes authenticated. This should not be longer than `max_age`, as the
+    /// session cookie would expire first. Like `anonymous_ttl`, this requires the `is_anonymous`
+    /// setting of the fairing. (default: `None`)
+    pub authenticated_ttl: Option<u32>,
+    /// Origins (e.g. `"https://app.example.com"`) that are trusted by the [`SameOrigin`](crate::SameOrigin)
+    /// request guard, in addition to the request's own scheme and host. (default: empty)
+    #[builder(default)]
+    pub trusted_origins: Vec<String>,
+    /// Names of other cookies tied to the session, such as a CSRF token or a JS-readable expiry
+    /// cookie set by your app, which are removed by [`Session::logout`](crate::Session::logout).
+    /// They're removed with the session cookie's `path` and `domain`. (default: empty)
+    #[builder(default)]
+    pub companion_cookies: Vec<String>,
+    /// Names of legacy session cookies (e.g. from a previous session library) to expire whenever
+    /// a request sends them, to clean up after a migration. They're removed with the session
+    /// cookie's `path` and `domain`. (default: empty)
+    #[builder(default)]
+    pub legacy_cookies: Vec<String>,
+    /// Record the given number of most recent requests (timestamp, path, and status) for
+    /// each session, which can be retrieved via [`Session::recent_activity`](crate::Session::recent_activity).
+    /// The storage provider must support activity logs. (default: `None`)
+    pub activity_log_size: Option<usize>,
+    /// Whether to save anonymous sessions to the storage provider. Setting this to `false`
+    /// will keep anonymous visitors (e.g. bots and guests) out of your storage backend - their
+    /// sessions will only last for the current request, unless you're using a cookie-based storage.
+    /// Anonymous sessions are determined by the `is_anonymous` setting of the
+    /// [fairing](crate::RocketFlexSession). (default: `true`)
+    #[builder(default = true)]
+    pub persist_anonymous: bool,
+    /// Only save a fraction (`0.0` - `1.0`) of anonymous sessions to the storage provider, trading
+    /// completeness for storage cost. Sessions are sampled by their ID, so a given session is either always or
+    /// never saved. This has no effect if `persist_anonymous` is `false`. (default: `None`)
+    pub anonymous_sample_rate: Option<f64>,
+    /// Record the time that the identifier of an active session was last seen, for a random fraction
+    /// (`0.0` - `1.0`) of requests. The identifiers that were seen recently can be queried for
+    /// [presence tracking](crate::presence). The storage provider must support presence tracking. (default: `None`)
+    pub presence_sample_rate: Option<f64>,
+    /// Omit error details that could contain session data (e.g. parsing errors) from the
+    /// crate's logs. See also the `redact` setting of the [fairing](crate::RocketFlexSession). (default: `false`)
+    #[builder(default)]
+    pub redact_errors: bool,
+    /// How session IDs are written in the crate's logs. Session IDs are secrets that could be
+    /// used to hijack a session, so you may want to use `Truncated` or `Hashed` in production.
+    /// (default: `LogIdFormat::Full`)
+    #[builder(default)]
+    pub log_ids: LogIdFormat,
+    /// The most verbose level of the crate's log messages. This can only make the crate's logs
+    /// quieter than Rocket's configured log level. (default: `SessionLogLevel::Debug`)
+    #[builder(default)]
+    pub log_level: SessionLogLevel,
+    /// Deny changes to the session after the response has started (e.g. in a streaming responder),
+    /// as the session has already been saved and the changes would be lost. Denied changes are
+    /// logged as errors, and [`Session::try_set`](crate::Session::try_set) returns
+    /// [`SessionError::ResponseStarted`](crate::error::SessionError::ResponseStarted). (default: `false`)
+    #[builder(default)]
+    pub strict: bool,
+    /// How sessions are handled for `OPTIONS` and `HEAD` requests, such as CORS preflights and
+    /// link checkers, which shouldn't extend or modify the session. (default: `PreflightMode::ReadOnly`)
+    #[builder(default)]
+    pub preflight: PreflightMode,
+    /// The key used to encrypt or sign the session cookie. Use a dedicated key to rotate Rocket's
+    /// `secret_key` and the session key independently. This option can't be deserialized.
+    /// (default: `SessionCookieKey::RocketSecretKey`)
+    #[builder(default)]
+    #[serde(skip)]
+    pub cookie_key: SessionCookieKey,
+}
+
+impl RocketFlexSessionOptions {
+    /// Validate the options: `max_age` and all TTLs must be greater than 0, the TTLs can't be longer
+    /// than `max_age` (as the session cookie would expire first), the `path` must start with `/`,
+    /// the `activity_log_size` must be greater than 0, and the sample rates must be between `0.0`
+    /// and `1.0`. This is done automatically when using the
+    /// [builder](RocketFlexSessionOptions::builder).
+    pub fn validate(&self) -> Result<(), OptionsError> {
+        if self.max_age == 0 {
+            return Err(OptionsError::ZeroMaxAge);
+        }
+        let ttls = [
+            ("ttl", self.ttl),
+            ("anonymous_ttl", self.anonymous_ttl),
+            ("authenticated_ttl", self.authenticated_ttl),
+        ];
+        for (name, ttl) in ttls {
+            match ttl {
+                Some(0) => return Err(OptionsError::ZeroTtl(name)),
+                Some(ttl) if ttl > self.max_age => {
+                    return Err(OptionsError::TtlExceedsMaxAge(name, ttl, self.max_age))
+                }
+                _ => {}
+            }
+        }
+        if !self.path.starts_with('/') {
+            return Err(OptionsError::InvalidPath(self.path.clone()));
+        }
+        if self.activity_log_size == Some(0) {
+            return Err(OptionsError::ZeroActivityLogSize);
+        }
+        let sample_rates = [
+            ("anonymous_sample_rate", self.anonymous_sample_rate),
+            ("presence_sample_rate", self.presence_sample_rate),
+        ];
+        for (name, rate) in sample_rates {
+            match rate {
+                Some(rate) if !(0.0..=1.0).contains(&rate) => {
+                    return Err(OptionsError::InvalidSampleRate(name, rate))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract and [validate](RocketFlexSessionOptions::validate) the options from the given key of
+    /// a [`Figment`]. You can use Rocket's figment to load the options from `Rocket.toml` and
+    /// `ROCKET_` environment variables.
+    ///
+    /// ```rust,no_run
+    /// use rocket_flex_session::{RocketFlexSession, RocketFlexSessionOptions};
+    ///
+    /// // e.g. [default.session] in Rocket.toml, or ROCKET_SESSION={cookie_name="my_session"}
+    /// let options = RocketFlexSessionOptions::from_figment(&rocket::Config::figment(), "session")
+    ///     .expect("session options should be valid");
+    /// let fairing = RocketFlexSession::<String>::builder().options(options).build();
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn from_figment(figment: &Figment, key: &str) -> Result<Self, rocket::figment::Error> {
+        let options: Self = figment.extract_inner(key)?;
+        options
+            .validate()
+            .map_err(|e| rocket::figment::Error::from(e.to_string()))?;
+        Ok(options)
+    }
+
+    /// The configured TTL for anonymous or authenticated sessions, if any
+    pub(crate) fn ttl_for(&self, anonymous: bool) -> Option<u32> {
+        match anonymous {
+            true => self.anonymous_ttl,
+            false => self.authenticated_ttl,
+        }
+    }
+}
+
+/// Overrides of the session cookie attributes for the current response only, set with
+/// [`Session::with_cookie_overrides`](crate::Session::with_cookie_overrides). Attributes that
+/// aren't overridden use the configured [options](RocketFlexSessionOptions).
+#[derive(Clone, Debug, Default, PartialEq)]
+#[non_exhaustive]
+pub struct CookieOverrides {
+    /// The session cookie's `Max-Age` attribute, in seconds
+    pub max_age: Option<u32>,
+    /// The session cookie's `Path` attribute
+    pub path: Option<String>,
+    /// The session cookie's `SameSite` attribute
+    pub same_site: Option<SameSite>,
+    /// The session cookie's `Secure` attribute
+    pub secure: Option<bool>,
+}
+
+/// Replace the `{type}` and `{profile}` placeholders in the cookie name
+pub(crate) fn resolve_cookie_name(cookie_name: &str, type_name: &str, profile: &str) -> String {
+    if !cookie_name.contains('{') {
+        return cookie_name.to_owned();
+    }
+    cookie_name
+        .replace("{type}", &snake_case_type_name(type_name))
+        .replace("{profile}", profile)
+}
+
+/// Convert the name of the type (without its path and generics) to snake case,
+/// e.g. `my_app::MySession<T>` to `my_session`
+fn snake_case_type_name(type_name: &str) -> String {
+    let name = type_name.split('<').next().unwrap_or(type_name);
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let mut snake_case = String::with_capacity(name.len() + 4);
+    let mut prev_lowercase = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && prev_lowercase {
+            snake_case.push('_');
+        }
+        prev_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        snake_case.push(c.to_ascii_lowercase());
+    }
+    snake_case
+}
+
+/// Deserialize the `SameSite` attribute from a case-insensitive string
+fn deserialize_same_site<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SameSite, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    match value.to_ascii_lowercase().as_str() {
+        "strict" => Ok(SameSite::Strict),
+        "lax" => Ok(SameSite::Lax),
+        "none" => Ok(SameSite::None),
+        _ => Err(de::Error::unknown_variant(
+            &value,
+            &["strict", "lax", "none"],
+        )),
+    }
+}
+
+/// How session IDs are written in log messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum LogIdFormat {
+    /// The full session ID
+    #[default]
+    Full,
+    /// The first few characters of the session ID (e.g. `"aB3x…"`)
+    Truncated,
+    /// A hash of the session ID (e.g. `"#3f2a9c1d0b4e5f67"`), which can still be used to
+    /// correlate log messages of the same session
+    Hashed,
+}
+
+impl LogIdFormat {
+    /// Format the session ID for a log message. You can use this to log session IDs
+    /// consistently in your own code.
+    pub fn format<'a>(&self, id: &'a str) -> Cow<'a, str> {
+        match self {
+            Self::Full => Cow::Borrowed(id),
+            Self::Truncated => Cow::Owned(format!(
+                "{}…",
+                id.chars().take(TRUNCATED_ID_LENGTH).collect::<String>()
+            )),
+            Self::Hashed => Cow::Owned(format!("#{:016x}", fnv1a(id.as_bytes()))),
+        }
+    }
+}
+
+/// Number of characters of the session ID to include in logs with `LogIdFormat::Truncated`
+const TRUNCATED_ID_LENGTH: usize = 4;
+
+/// Verbosity of the crate's log messages
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum SessionLogLevel {
+    /// No log messages
+    Off,
+    /// Only errors
+    Error,
+    /// Errors and warnings
+    Warn,
+    /// Errors, warnings, and info messages
+    Info,
+    /// All messages
+    #[default]
+    Debug,
+}
+
+/// How sessions are handled for `OPTIONS` and `HEAD` requests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum PreflightMode {
+    /// Handle the session like any other request
+    Normal,
+    /// Load the session without extending rolling sessions, and discard any changes
+    /// made during the request instead of saving them
+    #[default]
+    ReadOnly,
+    /// Skip the session entirely, like the `skip_request` setting of the
+    /// [fairing](crate::RocketFlexSession). The session cookie isn't decrypted, and the session
+    /// isn't loaded.
+    Skip,
 }
 
 impl Default for RocketFlexSessionOptions {
     fn default() -> Self {
-        Self {
-            cookie_name: "rocket".to_owned(),
-            domain: None,
-            http_only: true,
-            max_age: 14 * 24 * 60 * 60, // 14 days
-            path: "/".to_owned(),
-            rolling: false,
-            same_site: rocket::http::SameSite::Lax,
-            secure: true,
-            ttl: None,
-        }
+        Self::builder().build_unvalidated()
+    }
+}
+
+impl<S: rocket_flex_session_options_builder::IsComplete> RocketFlexSessionOptionsBuilder<S> {
+    /// Build and [validate](RocketFlexSessionOptions::validate) the options.
+    pub fn build(self) -> Result<RocketFlexSessionOptions, OptionsError> {
+        let options = self.build_unvalidated();
+        options.validate()?;
+        Ok(options)
     }
 }
@@ -0,0 +1,141 @@
+use std::marker::PhantomData;
+
+use rocket::{
+    http::{Method, Status},
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+use crate::{guard::get_fairing, logging::session_log, Session};
+
+/**
+Request guard that verifies the `Origin` / `Sec-Fetch-Site` headers of state-changing
+requests when there's an active session. This is a lightweight CSRF defense layer that
+complements the session cookie's `SameSite` attribute.
+
+The guard always succeeds for safe methods (`GET`, `HEAD`, `OPTIONS`) and for requests
+without an active session. Otherwise, the request is accepted if:
+- the `Sec-Fetch-Site` header is `same-origin` or `none`, or
+- the `Origin` header matches the request's scheme and `Host`, or one of the
+  [trusted origins](crate::RocketFlexSessionOptions::trusted_origins)
+
+The request's scheme is taken from the `X-Forwarded-Proto` header if present, and is otherwise
+`https` if TLS is enabled in Rocket. If your app runs behind a proxy that terminates TLS without
+setting this header, add the site's own origin to the trusted origins.
+
+Requests that send neither header (e.g. non-browser clients) are accepted. Rejected
+requests fail with a `403 Forbidden` status.
+
+# Type Parameters
+* `T` - The session data type
+
+# Example
+```rust
+use rocket_flex_session::{SameOrigin, Session};
+
+#[derive(Clone)]
+struct MySession {
+    user_id: String,
+}
+
+#[rocket::post("/change-email")]
+fn change_email(_origin: SameOrigin<MySession>, session: Session<MySession>) -> &'static str {
+    // The request came from our own site
+    "Email changed"
+}
+```
+*/
+pub struct SameOrigin<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+#[rocket::async_trait]
+impl<'r, T> FromRequest<'r> for SameOrigin<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    type Error = &'r str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let verified = Outcome::Success(SameOrigin {
+            _marker: PhantomData,
+        });
+        if is_safe_method(req.method()) {
+            return verified;
+        }
+
+        let session = req
+            .guard::<Session<'r, T>>()
+            .await
+            .expect("should not fail");
+        if session.id().is_none() {
+            return verified;
+        }
+
+        let fairing = get_fairing::<T>(req.rocket());
+        match verify_origin(req, &fairing.options.trusted_origins) {
+            Ok(()) => verified,
+            Err(reason) => {
+                session_log!(
+                    fairing.options,
+                    warn,
+                    "Rejected request with active session: {reason}"
+                );
+                Outcome::Error((Status::Forbidden, reason))
+            }
+        }
+    }
+}
+
+fn is_safe_method(method: Method) -> bool {
+    matches!(method, Method::Get | Method::Head | Method::Options)
+}
+
+/// Verify the origin of the request using the fetch metadata and `Origin` headers
+fn verify_origin(req: &Request<'_>, trusted_origins: &[String]) -> Result<(), &'static str> {
+    let headers = req.headers();
+    let fetch_site = headers.get_one("Sec-Fetch-Site");
+    if matches!(fetch_site, Some("same-origin" | "none")) {
+        return Ok(());
+    }
+
+    let Some(origin) = headers.get_one("Origin") else {
+        return match fetch_site {
+            None => Ok(()), // no browser metadata to verify
+            Some(_) => Err("Missing Origin header on cross-site request"),
+        };
+    };
+    if origin == "null" {
+        return Err("Opaque request origin");
+    }
+    if trusted_origins
+        .iter()
+        .any(|trusted| trusted.trim_end_matches('/').eq_ignore_ascii_case(origin))
+    {
+        return Ok(());
+    }
+
+    let Some((origin_scheme, origin_host)) = origin.split_once("://") else {
+        return Err("Invalid Origin header");
+    };
+    if !origin_scheme.eq_ignore_ascii_case(request_scheme(req)) {
+        return Err("Origin doesn't match the site's scheme");
+    }
+    match headers.get_one("Host") {
+        Some(host) if host.eq_ignore_ascii_case(origin_host) => Ok(()),
+        _ => Err("Origin doesn't match the site"),
+    }
+}
+
+/// The scheme the request was made with, from the proxy's `X-Forwarded-Proto` header or
+/// Rocket's TLS config
+fn request_scheme<'r>(req: &'r Request<'_>) -> &'r str {
+    if let Some(proto) = req.headers().get_one("X-Forwarded-Proto") {
+        // Proxies may append their own protocol, so use the first one
+        return proto.split(',').next().unwrap_or(proto).trim();
+    }
+    match req.rocket().config().tls_enabled() {
+        true => "https",
+        false => "http",
+    }
+}
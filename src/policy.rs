@@ -0,0 +1,185 @@
+//! Policies for handling session errors
+//!
+//! Some errors can't be handled inside your routes, because they happen after the response was
+//! created - e.g. when the session data fails to serialize while it's saved at the end of the
+//! request. By default, these errors are only logged, which can go unnoticed. These policies,
+//! set with the [fairing builder](crate::RocketFlexSession::builder), configure what happens
+//! instead. Use the `on_save_error` hook of the fairing to also report the errors, e.g. to your
+//! error tracker.
+//!
+//! Sessions that fail to deserialize when they're loaded (e.g. after an incompatible change to
+//! the session data type) are handled by the [`ParseErrorPolicy`], and can be counted with
+//! [`ParseErrorMetrics`] to monitor the corruption rate.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{policy::SerializationErrorPolicy, RocketFlexSession};
+//!
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .serialization_error_policy(SerializationErrorPolicy::Fail)
+//!     .on_save_error(|_session_id, error| {
+//!         eprintln!("Failed to save session: {error}");
+//!     })
+//!     .build();
+//! ```
+//!
+//! ```rust
+//! use rocket_flex_session::policy::{ParseErrorMetrics, ParseErrorPolicy};
+//! use rocket_flex_session::RocketFlexSession;
+//!
+//! let metrics = ParseErrorMetrics::default();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .parse_error_policy(ParseErrorPolicy::Delete)
+//!     .parse_error_metrics(metrics.clone())
+//!     .build();
+//!
+//! // e.g. in a metrics route
+//! println!("Corrupt sessions: {}", metrics.corrupted());
+//! ```
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use crate::error::SessionError;
+
+/// Function that returns the data to save instead of data that failed to serialize
+pub type FallbackFn<T> = Arc<dyn Fn(&T) -> Option<T> + Send + Sync>;
+
+/// What to do when the session data fails to serialize while it's being saved, i.e. when the
+/// storage provider returns [`SessionError::Serialization`](crate::error::SessionError::Serialization).
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum SerializationErrorPolicy<T> {
+    /// Log the error, and keep the data that was previously stored for the session, if any. A new
+    /// session won't be stored, so it will be missing in the next request. (default)
+    #[default]
+    KeepPrevious,
+    /// Retry the save once with the data returned by the function (e.g. without a cached field
+    /// that failed to serialize). If the function returns `None`, the previous data is kept.
+    Fallback(FallbackFn<T>),
+    /// Replace the response with an empty `500 Internal Server Error` response, so the client
+    /// doesn't assume the session was saved.
+    Fail,
+}
+
+impl<T> SerializationErrorPolicy<T> {
+    /// Retry with the data returned by the function. See [`SerializationErrorPolicy::Fallback`].
+    pub fn fallback(f: impl Fn(&T) -> Option<T> + Send + Sync + 'static) -> Self {
+        Self::Fallback(Arc::new(f))
+    }
+}
+
+impl<T> fmt::Debug for SerializationErrorPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::KeepPrevious => "KeepPrevious",
+            Self::Fallback(_) => "Fallback",
+            Self::Fail => "Fail",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Function that salvages the data of a session that failed to deserialize
+pub type RecoverFn<T> = Arc<dyn Fn(&str, &SessionError) -> Option<T> + Send + Sync>;
+
+/// What to do when the stored session data fails to deserialize while it's being loaded, i.e.
+/// when the storage provider returns [`SessionError::Parsing`]. In all cases, the request starts
+/// with an empty session unless the data is recovered.
+#[derive(Clone, Default)]
+#[non_exhaustive]
+pub enum ParseErrorPolicy<T> {
+    /// Log the error, and keep the stored data in case it can be read later (e.g. after rolling
+    /// back a deployment). The error is available from [`Session::error`](crate::Session::error).
+    /// (default)
+    #[default]
+    Keep,
+    /// Delete the stored session with [`delete_unreadable`](crate::storage::SessionStorage::delete_unreadable),
+    /// so it isn't loaded again. The error is available from
+    /// [`Session::error`](crate::Session::error).
+    Delete,
+    /// Call the function with the session ID and the error, to salvage the session data (e.g.
+    /// from a backup, or by re-reading the stored value with a previous data type). If it returns
+    /// data, the session continues with it and is saved over the stored value. Otherwise, the
+    /// stored data is kept.
+    Recover(RecoverFn<T>),
+}
+
+impl<T> ParseErrorPolicy<T> {
+    /// Salvage the data with the given function. See [`ParseErrorPolicy::Recover`].
+    pub fn recover(f: impl Fn(&str, &SessionError) -> Option<T> + Send + Sync + 'static) -> Self {
+        Self::Recover(Arc::new(f))
+    }
+}
+
+impl<T> fmt::Debug for ParseErrorPolicy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Keep => "Keep",
+            Self::Delete => "Delete",
+            Self::Recover(_) => "Recover",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Counters of sessions that failed to deserialize, and how they were handled by the
+/// [`ParseErrorPolicy`]. Clones share the same counters.
+#[derive(Clone, Debug, Default)]
+pub struct ParseErrorMetrics {
+    counters: Arc<[AtomicU64; 4]>,
+}
+
+/// Index of each outcome in the parse error counters
+#[derive(Clone, Copy)]
+pub(crate) enum ParseErrorOutcome {
+    Corrupted = 0,
+    Deleted = 1,
+    Recovered = 2,
+    Loaded = 3,
+}
+
+impl ParseErrorMetrics {
+    /// Number of sessions that failed to deserialize
+    pub fn corrupted(&self) -> u64 {
+        self.count(ParseErrorOutcome::Corrupted)
+    }
+
+    /// Number of sessions that were deleted after failing to deserialize
+    pub fn deleted(&self) -> u64 {
+        self.count(ParseErrorOutcome::Deleted)
+    }
+
+    /// Number of sessions whose data was recovered after failing to deserialize
+    pub fn recovered(&self) -> u64 {
+        self.count(ParseErrorOutcome::Recovered)
+    }
+
+    /// Number of sessions that were loaded successfully
+    pub fn loaded(&self) -> u64 {
+        self.count(ParseErrorOutcome::Loaded)
+    }
+
+    /// Fraction of the loaded sessions that failed to deserialize, from `0.0` to `1.0`
+    pub fn corruption_rate(&self) -> f64 {
+        let corrupted = self.corrupted();
+        let total = corrupted + self.loaded();
+        match total {
+            0 => 0.0,
+            total => corrupted as f64 / total as f64,
+        }
+    }
+
+    fn count(&self, outcome: ParseErrorOutcome) -> u64 {
+        self.counters[outcome as usize].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record(&self, outcome: ParseErrorOutcome) {
+        self.counters[outcome as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
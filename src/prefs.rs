@@ -0,0 +1,263 @@
+//! User preferences (locale, timezone, and theme)
+//!
+//! [`PrefsSession`] stores the user's [`Prefs`] alongside your session data. Attach the
+//! [`prefs::fairing`](fairing) for your session data type, and the [`Preferred`] request guard
+//! can then get a preference from the session, with a fallback to the request headers:
+//! - [`Locale`]: the `Accept-Language` header
+//! - [`Theme`]: the `Sec-CH-Prefers-Color-Scheme` client hint
+//! - [`Timezone`]: no fallback
+//!
+//! The guard forwards if the preference isn't set and there's no fallback - use
+//! `Option<Preferred<_>>` to make it optional.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     prefs::{self, Locale, Preferred, PrefsSession},
+//!     RocketFlexSession, Session,
+//! };
+//!
+//! #[derive(Clone, Default)]
+//! struct User {
+//!     user_id: Option<String>,
+//! }
+//!
+//! #[rocket::post("/locale/<locale>")]
+//! fn set_locale(mut session: Session<PrefsSession<User>>, locale: &str) {
+//!     session.set_prefs(|prefs| prefs.locale = Some(locale.to_owned()));
+//! }
+//!
+//! #[rocket::get("/")]
+//! fn index(locale: Option<Preferred<Locale>>) -> String {
+//!     match locale {
+//!         Some(Preferred(Locale(locale))) => format!("Locale: {locale}"),
+//!         None => "No locale".to_owned(),
+//!     }
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<PrefsSession<User>>::default())
+//!     .attach(prefs::fairing::<User>())
+//!     .mount("/", rocket::routes![set_locale, index]);
+//! ```
+
+use rocket::{
+    fairing::AdHoc,
+    futures::future::BoxFuture,
+    http::Status,
+    request::{FromRequest, Outcome},
+    serde::{Deserialize, Serialize},
+    Request,
+};
+
+use crate::{Session, SessionIdentifier};
+
+/// The user's preferences. Unset preferences are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct Prefs {
+    /// Locale, as a language tag (e.g. `en-US`)
+    pub locale: Option<String>,
+    /// Timezone, as an IANA timezone name (e.g. `Europe/Paris`)
+    pub timezone: Option<String>,
+    /// Theme (e.g. `light` or `dark`)
+    pub theme: Option<String>,
+}
+
+/// Session data wrapper that stores the user's [`Prefs`] alongside your session data. If your
+/// data implements [`SessionIdentifier`], the sessions will be indexed by the same identifier.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PrefsSession<T> {
+    /// The user's preferences
+    pub prefs: Prefs,
+    /// Your session data
+    pub data: T,
+}
+
+impl<T> PrefsSession<T> {
+    /// Create the session data with default preferences
+    pub fn new(data: T) -> Self {
+        Self {
+            prefs: Prefs::default(),
+            data,
+        }
+    }
+}
+
+impl<T> SessionIdentifier for PrefsSession<T>
+where
+    T: SessionIdentifier,
+{
+    type Id = T::Id;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.data.identifier()
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.data.tags()
+    }
+}
+
+/// Implementation block for sessions with preferences
+impl<T> Session<'_, PrefsSession<T>>
+where
+    T: Send + Sync + Clone,
+{
+    /// Get the preferences of the current session. Will be the default preferences if there's
+    /// no active session.
+    pub fn prefs(&self) -> Prefs {
+        self.tap(|session| session.map(|s| s.prefs.clone()).unwrap_or_default())
+    }
+
+    /// Update the preferences of the current session. Will create a new session with the default
+    /// data if there isn't one, so that anonymous visitors can also set their preferences.
+    pub fn set_prefs(&mut self, f: impl FnOnce(&mut Prefs))
+    where
+        T: Default,
+    {
+        self.tap_mut(|session| f(&mut session.get_or_insert_with(Default::default).prefs));
+    }
+}
+
+/// Function that loads the preferences from the session of the request
+type LoadPrefs = for<'r> fn(&'r Request<'_>) -> BoxFuture<'r, Option<Prefs>>;
+
+/// Managed state for the [`Preferred`] request guard
+struct PrefsSource {
+    load: LoadPrefs,
+}
+
+/// Create the fairing for the [`Preferred`] request guard, which gets the preferences from
+/// sessions of type [`PrefsSession<T>`]. The [session fairing](crate::RocketFlexSession) for
+/// `PrefsSession<T>` must also be attached.
+pub fn fairing<T>() -> AdHoc
+where
+    T: Send + Sync + Clone + 'static,
+{
+    AdHoc::on_ignite("Session preferences", |rocket| async {
+        rocket.manage(PrefsSource {
+            load: load_prefs::<T>,
+        })
+    })
+}
+
+/// Load the preferences from the session of the request
+fn load_prefs<'r, T>(req: &'r Request<'_>) -> BoxFuture<'r, Option<Prefs>>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    Box::pin(async move {
+        let session = req
+            .guard::<Session<'r, PrefsSession<T>>>()
+            .await
+            .succeeded()?;
+        session.tap(|session| session.map(|s| s.prefs.clone()))
+    })
+}
+
+/// A preference that can be requested with the [`Preferred`] request guard
+pub trait Preference: Sized + Send {
+    /// Get the preference from the user's preferences
+    fn from_prefs(prefs: &Prefs) -> Option<Self>;
+
+    /// Optional: get the preference from the request headers, if it isn't set in the preferences
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    fn from_headers(req: &Request<'_>) -> Option<Self> {
+        None
+    }
+}
+
+/// Locale preference, as a language tag (e.g. `en-US`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Locale(pub String);
+
+impl Preference for Locale {
+    fn from_prefs(prefs: &Prefs) -> Option<Self> {
+        prefs.locale.clone().map(Self)
+    }
+
+    fn from_headers(req: &Request<'_>) -> Option<Self> {
+        let accept_language = req.headers().get_one("Accept-Language")?;
+        preferred_language(accept_language).map(|language| Self(language.to_owned()))
+    }
+}
+
+/// Timezone preference, as an IANA timezone name (e.g. `Europe/Paris`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timezone(pub String);
+
+impl Preference for Timezone {
+    fn from_prefs(prefs: &Prefs) -> Option<Self> {
+        prefs.timezone.clone().map(Self)
+    }
+}
+
+/// Theme preference (e.g. `light` or `dark`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme(pub String);
+
+impl Preference for Theme {
+    fn from_prefs(prefs: &Prefs) -> Option<Self> {
+        prefs.theme.clone().map(Self)
+    }
+
+    fn from_headers(req: &Request<'_>) -> Option<Self> {
+        let color_scheme = req.headers().get_one("Sec-CH-Prefers-Color-Scheme")?;
+        let color_scheme = color_scheme.trim().trim_matches('"');
+        (!color_scheme.is_empty()).then(|| Self(color_scheme.to_owned()))
+    }
+}
+
+/// Get the language with the highest quality value from an `Accept-Language` header.
+/// Languages with the same quality are preferred in the order they're listed.
+pub fn preferred_language(accept_language: &str) -> Option<&str> {
+    let mut preferred: Option<(&str, f32)> = None;
+    for entry in accept_language.split(',') {
+        let mut parts = entry.split(';');
+        let language = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok());
+        let Some(quality) = quality.filter(|q| *q > 0.0) else {
+            continue;
+        };
+        if language.is_empty() || language == "*" {
+            continue;
+        }
+        if preferred.map_or(true, |(_, best)| quality > best) {
+            preferred = Some((language, quality));
+        }
+    }
+    preferred.map(|(language, _)| language)
+}
+
+/// Request guard for a [`Preference`] (e.g. `Preferred<Locale>`), from the preferences in the
+/// session or the request headers. Forwards with `404 Not Found` if the preference isn't found.
+/// See the [module docs](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preferred<P>(pub P);
+
+#[rocket::async_trait]
+impl<'r, P> FromRequest<'r> for Preferred<P>
+where
+    P: Preference,
+{
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let source = req.rocket().state::<PrefsSource>().unwrap_or_else(|| {
+            panic!("The preferences fairing (prefs::fairing) should be attached to the server")
+        });
+        let prefs = (source.load)(req).await;
+        match prefs
+            .as_ref()
+            .and_then(P::from_prefs)
+            .or_else(|| P::from_headers(req))
+        {
+            Some(preference) => Outcome::Success(Preferred(preference)),
+            None => Outcome::Forward(Status::NotFound),
+        }
+    }
+}
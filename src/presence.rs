@@ -0,0 +1,69 @@
+//! Presence tracking ("who's online")
+//!
+//! If the [`presence_sample_rate`](crate::RocketFlexSessionOptions::presence_sample_rate) option
+//! is set, the fairing records the time that the identifier of an active session was last seen,
+//! for a sampled fraction of requests. You can then query the identifiers that were
+//! [seen recently](Session::online_identifiers), e.g. to show which users are online.
+//!
+//! The storage provider must support indexing and presence tracking:
+//!
+//! | Storage | Feature Flag |
+//! |---------|-------------|
+//! | [`storage::memory::MemoryStorageIndexed`](crate::storage::memory::MemoryStorageIndexed) | Built-in |
+//! | `storage::redis::RedisFredStorage` | `redis_fred` |
+//!
+//! As last-seen times are only recorded for sampled requests, they can lag behind by a few
+//! requests. Use a lower sample rate for busy apps, and a longer window when querying.
+//!
+//! # Example
+//! ```rust
+//! use rocket::time::{Duration, OffsetDateTime};
+//! use rocket_flex_session::{
+//!     storage::memory::MemoryStorageIndexed, RocketFlexSession, Session, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for User {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! #[rocket::get("/online")]
+//! async fn online(session: Session<'_, User>) -> String {
+//!     let since = OffsetDateTime::now_utc() - Duration::minutes(5);
+//!     match session.online_identifiers(since).await {
+//!         Ok(user_ids) => user_ids.join(", "),
+//!         Err(e) => format!("Error: {e}"),
+//!     }
+//! }
+//!
+//! let fairing = RocketFlexSession::<User>::builder()
+//!     .storage(MemoryStorageIndexed::default())
+//!     .with_options(|opt| opt.presence_sample_rate = Some(0.1))
+//!     .build();
+//! ```
+
+use rocket::time::OffsetDateTime;
+
+use crate::{error::SessionError, Session, SessionIdentifier};
+
+/// Implementation block for presence tracking
+impl<T> Session<'_, T>
+where
+    T: SessionIdentifier,
+{
+    /// Get the identifiers (as strings) that were seen since the given time, most recently
+    /// seen first. See the [`presence`](crate::presence) module for more info.
+    pub async fn online_identifiers(
+        &self,
+        since: OffsetDateTime,
+    ) -> Result<Vec<String>, SessionError> {
+        self.get_indexed_storage()?.online_identifiers(since).await
+    }
+}
@@ -0,0 +1,185 @@
+//! Request guard for the principal of the session (e.g. the user record)
+//!
+//! Most apps store an identifier (e.g. a user ID) in the session, and then load the full user
+//! record from a database in each request that needs it. The [`Principal`] guard does both in one
+//! step: it gets the session, and resolves the [identifier](SessionIdentifier) of the session data
+//! to a principal with a [`Loader`]. The principal is cached for the request (see
+//! [`SessionDerived`]), so several guards and handlers can use it while only loading it once.
+//!
+//! The guard fails with a `401 Unauthorized` status if there's no active session, the session
+//! data has no identifier, or the loader doesn't find the principal (e.g. the user was deleted),
+//! and with a `500 Internal Server Error` status if the loader fails.
+//!
+//! # Example
+//! ```rust
+//! use std::collections::HashMap;
+//! use rocket::{Request, State};
+//! use rocket_flex_session::{
+//!     principal::{Loader, Principal},
+//!     RocketFlexSession, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct UserSession {
+//!     user_id: u32,
+//! }
+//!
+//! impl SessionIdentifier for UserSession {
+//!     type Id = u32;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id)
+//!     }
+//! }
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! /// e.g. a database connection pool instead
+//! type Users = HashMap<u32, User>;
+//!
+//! struct UserLoader;
+//!
+//! #[rocket::async_trait]
+//! impl Loader<UserSession> for UserLoader {
+//!     type Output = User;
+//!     type Error = &'static str;
+//!
+//!     async fn load(req: &Request<'_>, user_id: &u32) -> Result<Option<User>, Self::Error> {
+//!         let users = req.guard::<&State<Users>>().await.succeeded().ok_or("No users")?;
+//!         Ok(users.get(user_id).cloned())
+//!     }
+//! }
+//!
+//! #[rocket::get("/profile")]
+//! fn profile(user: Principal<UserSession, UserLoader>) -> String {
+//!     format!("Hello {} (user {})", user.principal.name, user.data.user_id)
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<UserSession>::default())
+//!     .manage(Users::new())
+//!     .mount("/", rocket::routes![profile]);
+//! ```
+
+use std::{fmt::Debug, marker::PhantomData, ops::Deref};
+
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    Request,
+};
+
+use crate::{Session, SessionDerived, SessionIdentifier};
+
+/// Loads the principal of a session from its identifier, for the [`Principal`] guard
+#[rocket::async_trait]
+pub trait Loader<T: SessionIdentifier>: Send + Sync + 'static {
+    /// The principal, e.g. a user record
+    type Output: Clone + Send + Sync + 'static;
+    /// The error when the principal can't be loaded
+    type Error: Debug + Send + Sync + 'static;
+
+    /// Load the principal with the given identifier. Other request guards (e.g. a database
+    /// connection) can be retrieved from the request. Should return `None` if the principal
+    /// doesn't exist.
+    async fn load(req: &Request<'_>, id: &T::Id) -> Result<Option<Self::Output>, Self::Error>;
+}
+
+/// Request guard with the session data and its principal, as resolved by the loader `L`.
+/// See the [module docs](self).
+pub struct Principal<T, L>
+where
+    T: SessionIdentifier,
+    L: Loader<T>,
+{
+    /// The session data
+    pub data: T,
+    /// The principal of the session
+    pub principal: L::Output,
+}
+
+impl<T, L> Deref for Principal<T, L>
+where
+    T: SessionIdentifier,
+    L: Loader<T>,
+{
+    type Target = L::Output;
+
+    fn deref(&self) -> &Self::Target {
+        &self.principal
+    }
+}
+
+/// Why the [`Principal`] guard failed
+#[derive(Debug)]
+pub enum PrincipalError<E> {
+    /// There's no active session, or the session data has no identifier
+    NoSession,
+    /// The loader didn't find the principal
+    NotFound,
+    /// The loader failed
+    Loader(E),
+}
+
+/// The principal loaded for a session, cached in the request by loader type
+struct Loaded<T, L: Loader<T>>
+where
+    T: SessionIdentifier,
+{
+    principal: Option<L::Output>,
+    _loader: PhantomData<fn() -> (T, L)>,
+}
+
+impl<T, L> Clone for Loaded<T, L>
+where
+    T: SessionIdentifier,
+    L: Loader<T>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            principal: self.principal.clone(),
+            _loader: PhantomData,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T, L> FromRequest<'r> for Principal<T, L>
+where
+    T: SessionIdentifier + 'static,
+    L: Loader<T>,
+{
+    type Error = PrincipalError<L::Error>;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let session = req
+            .guard::<Session<'r, T>>()
+            .await
+            .expect("should not fail");
+        let Some(data) = session.get() else {
+            return Outcome::Error((Status::Unauthorized, PrincipalError::NoSession));
+        };
+        let Some(id) = data.identifier() else {
+            return Outcome::Error((Status::Unauthorized, PrincipalError::NoSession));
+        };
+
+        let loaded = SessionDerived::get_or_derive(req, &session, |_| async move {
+            let principal = L::load(req, &id).await;
+            principal.map(|principal| Loaded::<T, L> {
+                principal,
+                _loader: PhantomData,
+            })
+        });
+        match loaded.await {
+            Ok(Some(Loaded {
+                principal: Some(principal),
+                ..
+            })) => Outcome::Success(Principal { data, principal }),
+            Ok(Some(_)) => Outcome::Error((Status::Unauthorized, PrincipalError::NotFound)),
+            Ok(None) => Outcome::Error((Status::Unauthorized, PrincipalError::NoSession)),
+            Err(e) => Outcome::Error((Status::InternalServerError, PrincipalError::Loader(e))),
+        }
+    }
+}
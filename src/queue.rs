@@ -0,0 +1,242 @@
+//! Background persistence queue
+//!
+//! By default, updated sessions are saved (and deleted sessions deleted) at the end of each
+//! request, so the response isn't complete until the storage operation is. With a slow storage
+//! backend, this adds to the latency of every request that changes the session. A
+//! [`PersistQueue`], configured in the [fairing](crate::RocketFlexSession), instead hands these
+//! operations to a background task through a bounded queue, so requests only wait for the
+//! storage if the queue is full and the [overflow policy](OverflowPolicy) is to block.
+//!
+//! This trades durability for latency: a queued session isn't saved yet when the response is
+//! sent, so the next request of the client may still see the previous session data, and queued
+//! operations are lost if the process crashes. Pending operations are completed when Rocket
+//! shuts down. Save errors are still logged and reported to the `on_save_error` hook, but the
+//! [`Fail`](crate::policy::SerializationErrorPolicy::Fail) policy can't change the response.
+//! Callbacks registered with [`Session::on_persisted`](crate::Session::on_persisted) are called
+//! from the background task once the request's operations complete, and receive a
+//! [`SessionError::Overloaded`](crate::error::SessionError::Overloaded) error if an operation
+//! was dropped from the queue.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     queue::{OverflowPolicy, PersistQueue},
+//!     RocketFlexSession,
+//! };
+//!
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .persist_queue(
+//!         PersistQueue::builder()
+//!             .capacity(1000)
+//!             .overflow(OverflowPolicy::DropOldest)
+//!             .build(),
+//!     )
+//!     .build();
+//! ```
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bon::Builder;
+use rocket::tokio::{select, sync::Notify};
+
+use crate::{error::SessionResult, session_inner::PersistReport, storage::TaskGuard};
+
+/// Default capacity of the persistence queue
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// What to do with a storage operation when the persistence queue is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Wait for space in the queue before completing the response, so no operations are lost (default)
+    #[default]
+    Block,
+    /// Drop the oldest queued operation to make room for the new one
+    DropOldest,
+    /// Drop the new operation, and log an error
+    LogError,
+}
+
+/// Configuration of the background persistence queue. See the [module docs](self).
+#[derive(Builder, Clone, Debug)]
+pub struct PersistQueue {
+    /// Maximum number of pending storage operations (default: 1024)
+    #[builder(default = DEFAULT_CAPACITY)]
+    capacity: usize,
+    /// What to do when the queue is full (default: [`OverflowPolicy::Block`])
+    #[builder(default)]
+    overflow: OverflowPolicy,
+}
+
+impl Default for PersistQueue {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Statistics of the persistence queue
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PersistQueueStats {
+    /// Number of storage operations waiting in the queue
+    pub pending: usize,
+    /// Number of storage operations dropped because the queue was full
+    pub dropped: u64,
+}
+
+/// A queued storage operation
+pub(crate) enum PersistOp<T> {
+    Save {
+        id: String,
+        data: T,
+        ttl: std::time::Duration,
+        report: Option<Arc<PersistReport>>,
+    },
+    Delete {
+        id: String,
+        data: T,
+        report: Option<Arc<PersistReport>>,
+    },
+}
+
+impl<T> PersistOp<T> {
+    /// The ID of the session
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Save { id, .. } | Self::Delete { id, .. } => id,
+        }
+    }
+
+    /// The report of the request's storage operations, if it has `on_persisted` callbacks
+    pub fn report(&self) -> Option<&PersistReport> {
+        match self {
+            Self::Save { report, .. } | Self::Delete { report, .. } => report.as_deref(),
+        }
+    }
+}
+
+/// Result of queueing a storage operation
+pub(crate) enum PushResult<T> {
+    /// The operation was queued
+    Queued,
+    /// The operation was queued, and this older operation was dropped to make room
+    DroppedOldest(PersistOp<T>),
+    /// The queue was full, so the operation was dropped
+    Rejected(PersistOp<T>),
+}
+
+/// Shared state of the persistence queue and its worker task
+pub(crate) struct PersistQueueState<T> {
+    config: PersistQueue,
+    ops: Mutex<VecDeque<PersistOp<T>>>,
+    dropped: AtomicU64,
+    /// Notified when an operation is queued
+    queued: Notify,
+    /// Notified when an operation is taken from the queue
+    taken: Notify,
+    worker: Mutex<Option<TaskGuard>>,
+}
+
+impl<T> PersistQueueState<T>
+where
+    T: Send + 'static,
+{
+    pub fn new(config: PersistQueue) -> Self {
+        Self {
+            config,
+            ops: Mutex::default(),
+            dropped: AtomicU64::default(),
+            queued: Notify::new(),
+            taken: Notify::new(),
+            worker: Mutex::default(),
+        }
+    }
+
+    /// Queue a storage operation, applying the overflow policy if the queue is full
+    pub async fn push(&self, op: PersistOp<T>) -> PushResult<T> {
+        loop {
+            let taken = {
+                let mut ops = self.ops.lock().unwrap();
+                if ops.len() < self.config.capacity.max(1) {
+                    ops.push_back(op);
+                    self.queued.notify_one();
+                    return PushResult::Queued;
+                }
+                match self.config.overflow {
+                    OverflowPolicy::DropOldest => {
+                        let oldest = ops.pop_front();
+                        ops.push_back(op);
+                        self.queued.notify_one();
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return match oldest {
+                            Some(oldest) => PushResult::DroppedOldest(oldest),
+                            None => PushResult::Queued,
+                        };
+                    }
+                    OverflowPolicy::LogError => {
+                        self.dropped.fetch_add(1, Ordering::Relaxed);
+                        return PushResult::Rejected(op);
+                    }
+                    OverflowPolicy::Block => self.taken.notified(),
+                }
+            };
+            taken.await;
+        }
+    }
+
+    fn pop(&self) -> Option<PersistOp<T>> {
+        let op = self.ops.lock().unwrap().pop_front();
+        if op.is_some() {
+            self.taken.notify_one();
+        }
+        op
+    }
+
+    pub fn stats(&self) -> PersistQueueStats {
+        PersistQueueStats {
+            pending: self.ops.lock().unwrap().len(),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Start the worker task, which runs the queued operations in order with the given function
+    pub fn start<F, Fut>(self: &Arc<Self>, run: F)
+    where
+        F: Fn(PersistOp<T>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let state = self.clone();
+        let worker = TaskGuard::spawn(|mut shutdown_rx| async move {
+            loop {
+                if let Some(op) = state.pop() {
+                    run(op).await;
+                    continue;
+                }
+                select! {
+                    _ = state.queued.notified() => {}
+                    _ = &mut shutdown_rx => break,
+                }
+            }
+            // Complete the pending operations before shutting down
+            while let Some(op) = state.pop() {
+                run(op).await;
+            }
+        });
+        self.worker.lock().unwrap().replace(worker);
+    }
+
+    /// Stop the worker task, after it completes the pending operations
+    pub async fn shutdown(&self) -> SessionResult<()> {
+        let worker = self.worker.lock().unwrap().take();
+        match worker {
+            Some(worker) => worker.shutdown().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Session quotas
+//!
+//! A [`QuotaPolicy`] limits the number of sessions that can be created for an identifier, e.g. to
+//! enforce the seat limit of a license. The policy is configured in the
+//! [fairing](crate::RocketFlexSession), and checked when using [`Session::try_set`] to create a new
+//! session. The storage provider must support [indexing](crate::SessionIdentifier), so that the
+//! identifier's active sessions can be counted.
+//!
+//! # Example
+//! ```rust
+//! use rocket::http::Status;
+//! use rocket_flex_session::{
+//!     error::SessionError, quota::MaxSessions, storage::memory::MemoryStorageIndexed,
+//!     RocketFlexSession, Session, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for User {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! #[rocket::post("/login")]
+//! async fn login(mut session: Session<'_, User>) -> Result<&'static str, Status> {
+//!     let user = User { user_id: "123".to_owned() };
+//!     match session.try_set(user).await {
+//!         Ok(()) => Ok("Logged in"),
+//!         Err(SessionError::QuotaExceeded { .. }) => Err(Status::Conflict),
+//!         Err(_) => Err(Status::InternalServerError),
+//!     }
+//! }
+//!
+//! let fairing = RocketFlexSession::<User>::builder()
+//!     .storage(MemoryStorageIndexed::default())
+//!     .quota(MaxSessions(3))
+//!     .build();
+//! ```
+
+use rocket::async_trait;
+
+use crate::{
+    error::{SessionError, SessionResult},
+    Session, SessionIdentifier,
+};
+
+/// Policy that decides whether a new session can be created for an identifier.
+/// See the [`quota`](crate::quota) module for more info.
+#[async_trait]
+pub trait QuotaPolicy<T>: Send + Sync {
+    /// Check whether a session can be created with the given data, given the number of other
+    /// active sessions of its identifier (i.e. not counting the current session). Return
+    /// [`SessionError::QuotaExceeded`] to reject the session.
+    async fn check(&self, data: &T, active_sessions: u64) -> SessionResult<()>;
+}
+
+/// Quota policy that allows a maximum number of active sessions per identifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxSessions(pub u64);
+
+#[async_trait]
+impl<T> QuotaPolicy<T> for MaxSessions
+where
+    T: Send + Sync,
+{
+    async fn check(&self, _data: &T, active_sessions: u64) -> SessionResult<()> {
+        match active_sessions < self.0 {
+            true => Ok(()),
+            false => Err(SessionError::QuotaExceeded {
+                limit: self.0,
+                active_sessions,
+            }),
+        }
+    }
+}
+
+/// Implementation block for session quotas
+impl<T> Session<'_, T>
+where
+    T: SessionIdentifier,
+{
+    /// Set/replace the session data, like [`Session::set`]. If the data has an identifier, the
+    /// [quota policy](QuotaPolicy) of the fairing is checked first with the number of the identifier's
+    /// other active sessions, and the session is left unchanged if the policy rejects it.
+    pub async fn try_set(&mut self, new_data: T) -> Result<(), SessionError> {
+        self.check_writable()?;
+        if let Some((quota, identifier)) = self.quota.zip(new_data.identifier()) {
+            let storage = self.get_indexed_storage()?;
+            let current_id = self.id();
+            let active_sessions = storage
+                .count_sessions_by_identifier(&identifier, current_id.as_deref())
+                .await?;
+            quota.check(&new_data, active_sessions).await?;
+        }
+        self.set(new_data);
+        Ok(())
+    }
+}
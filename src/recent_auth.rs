@@ -0,0 +1,92 @@
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    time::{Duration, OffsetDateTime},
+    Request,
+};
+
+use crate::{guard::get_fairing, logging::session_log, Session};
+
+/// Optional trait for session data that records when the user last authenticated (e.g. logged
+/// in or re-entered their password). This is used by the [`RecentlyAuthenticated`] guard.
+pub trait SessionAuthTime {
+    /// The time the user last authenticated, or `None` if the session isn't authenticated
+    fn authenticated_at(&self) -> Option<OffsetDateTime>;
+}
+
+/**
+Request guard that requires a recent login, e.g. for sensitive operations like changing the
+account email or password. The guard succeeds with the session data if the user
+[authenticated](SessionAuthTime::authenticated_at) within the last `SECS` seconds.
+
+Otherwise, the guard fails with a `401 Unauthorized` status, so you can catch it and ask the user
+to log in again (step-up authentication). Update the authentication time in the session data
+after they do.
+
+# Type Parameters
+* `T` - The session data type
+* `SECS` - The maximum age of the login in seconds
+
+# Example
+```rust
+use rocket::time::OffsetDateTime;
+use rocket_flex_session::{RecentlyAuthenticated, SessionAuthTime};
+
+#[derive(Clone)]
+struct MySession {
+    user_id: String,
+    logged_in_at: OffsetDateTime,
+}
+
+impl SessionAuthTime for MySession {
+    fn authenticated_at(&self) -> Option<OffsetDateTime> {
+        Some(self.logged_in_at)
+    }
+}
+
+#[rocket::post("/change-email")]
+fn change_email(auth: RecentlyAuthenticated<MySession, 300>) -> String {
+    // The user logged in within the last 5 minutes
+    format!("Changing email of user {}", auth.data.user_id)
+}
+```
+*/
+pub struct RecentlyAuthenticated<T, const SECS: u64> {
+    /// The session data
+    pub data: T,
+}
+
+#[rocket::async_trait]
+impl<'r, T, const SECS: u64> FromRequest<'r> for RecentlyAuthenticated<T, SECS>
+where
+    T: SessionAuthTime + Send + Sync + Clone + 'static,
+{
+    type Error = &'r str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let session = req
+            .guard::<Session<'r, T>>()
+            .await
+            .expect("should not fail");
+        let Some(data) = session.get() else {
+            return Outcome::Error((Status::Unauthorized, "No active session"));
+        };
+
+        let max_age = Duration::seconds(SECS.try_into().unwrap_or(i64::MAX));
+        let now = session.clock.now();
+        match data.authenticated_at() {
+            Some(authenticated_at) if now - authenticated_at <= max_age => {
+                Outcome::Success(RecentlyAuthenticated { data })
+            }
+            _ => {
+                let options = &get_fairing::<T>(req.rocket()).options;
+                session_log!(
+                    options,
+                    debug,
+                    "Rejected request that requires a login within {SECS} seconds"
+                );
+                Outcome::Error((Status::Unauthorized, "Recent authentication required"))
+            }
+        }
+    }
+}
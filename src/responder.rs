@@ -0,0 +1,165 @@
+//! Responders that update the session
+//!
+//! These responders combine a response with changes to the session, which are applied when the
+//! response is built. They're useful when it isn't convenient to add the [`Session`] request guard
+//! to a handler, e.g. when the response is created in a helper function.
+//!
+//! | Responder | Feature Flag |
+//! |-----------|-------------|
+//! | [`SessionRedirect`] | Built-in |
+//! | `SessionJson` | `json` |
+//!
+//! The session is only loaded from storage if the handler (or another request guard) used the
+//! [`Session`] request guard. Otherwise, the update starts with an empty session - which is fine
+//! for setting the data of a new session after logging in, but use the request guard if you need
+//! the existing session (e.g. to delete it when logging out).
+//!
+//! # Example
+//! ```rust
+//! use rocket::form::Form;
+//! use rocket_flex_session::{responder::SessionRedirect, RocketFlexSession};
+//!
+//! #[derive(Clone)]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! #[derive(rocket::FromForm)]
+//! struct Login {
+//!     username: String,
+//! }
+//!
+//! #[rocket::post("/login", data = "<login>")]
+//! fn login(login: Form<Login>) -> SessionRedirect<User> {
+//!     let user = User { user_id: login.username.clone() };
+//!     SessionRedirect::to("/dashboard").with(move |session| session.set(user))
+//! }
+//! ```
+
+use rocket::{
+    http::uri::Reference,
+    response::{self, Redirect, Responder},
+    Request,
+};
+
+use crate::{guard::cached_session, Session};
+
+/// Update of the session, applied when responding
+type SessionUpdate<T> = Box<dyn FnOnce(&mut Session<'_, T>) + Send>;
+
+/// Apply the session update (if any) to the session of the request
+fn apply_update<T>(req: &Request<'_>, update: Option<SessionUpdate<T>>)
+where
+    T: Send + Sync + Clone + 'static,
+{
+    if let Some(update) = update {
+        update(&mut cached_session(req));
+    }
+}
+
+/// Redirect that updates the session. See the [module docs](self).
+pub struct SessionRedirect<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    redirect: Redirect,
+    update: Option<SessionUpdate<T>>,
+}
+
+impl<T> SessionRedirect<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    /// Redirect to the given URI with a `303 See Other` status, like [`Redirect::to`]
+    pub fn to<U: TryInto<Reference<'static>>>(uri: U) -> Self {
+        Self::from(Redirect::to(uri))
+    }
+
+    /// Update the session when responding. Replaces any previous update.
+    pub fn with(mut self, update: impl FnOnce(&mut Session<'_, T>) + Send + 'static) -> Self {
+        self.update = Some(Box::new(update));
+        self
+    }
+}
+
+impl<T> From<Redirect> for SessionRedirect<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    fn from(redirect: Redirect) -> Self {
+        Self {
+            redirect,
+            update: None,
+        }
+    }
+}
+
+impl<'r, 'o: 'r, T> Responder<'r, 'o> for SessionRedirect<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        apply_update(req, self.update);
+        self.redirect.respond_to(req)
+    }
+}
+
+#[cfg(feature = "json")]
+pub use json::SessionJson;
+
+#[cfg(feature = "json")]
+mod json {
+    use rocket::{
+        response::{self, Responder},
+        serde::{json::Json, Serialize},
+        Request,
+    };
+
+    use super::{apply_update, SessionUpdate};
+    use crate::Session;
+
+    /// JSON response that updates the session. See the [module docs](super).
+    pub struct SessionJson<T, J>
+    where
+        T: Send + Sync + Clone + 'static,
+    {
+        json: Json<J>,
+        update: Option<SessionUpdate<T>>,
+    }
+
+    impl<T, J> SessionJson<T, J>
+    where
+        T: Send + Sync + Clone + 'static,
+    {
+        /// Respond with the value serialized as JSON
+        pub fn new(value: J) -> Self {
+            Self::from(Json(value))
+        }
+
+        /// Update the session when responding. Replaces any previous update.
+        pub fn with(mut self, update: impl FnOnce(&mut Session<'_, T>) + Send + 'static) -> Self {
+            self.update = Some(Box::new(update));
+            self
+        }
+    }
+
+    impl<T, J> From<Json<J>> for SessionJson<T, J>
+    where
+        T: Send + Sync + Clone + 'static,
+    {
+        fn from(json: Json<J>) -> Self {
+            Self { json, update: None }
+        }
+    }
+
+    impl<'r, 'o: 'r, T, J> Responder<'r, 'o> for SessionJson<T, J>
+    where
+        T: Send + Sync + Clone + 'static,
+        J: Serialize,
+    {
+        fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+            apply_update(req, self.update);
+            self.json.respond_to(req)
+        }
+    }
+}
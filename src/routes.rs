@@ -0,0 +1,236 @@
+//! Ready-made session routes
+//!
+//! [`session_routes`] provides keep-alive endpoints for the session, e.g. for single-page apps
+//! that warn users before their session expires:
+//! - `GET /expiry`: Get the [expiration](SessionExpiry) of the current session
+//! - `POST /renew`: [Renew](Session::renew) the current session, and get its new expiration
+//!
+//! Both routes respond with `401 Unauthorized` if there's no active session.
+//!
+//! [`sync_routes`] provides a differential sync endpoint for offline-capable clients (e.g. PWAs)
+//! that cache state derived from the session, such as the user's profile:
+//! - `GET /sync`: Get a [projection](SessionSync) of the session data, along with its version
+//!
+//! The version is a fingerprint of the projected data, which is also sent as the `ETag` header.
+//! Clients can pass their cached version as the `since` query parameter (or the `If-None-Match`
+//! header), and get a `304 Not Modified` response without a body if it's still current. The route
+//! responds with `401 Unauthorized` if there's no active session.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{routes::session_routes, RocketFlexSession};
+//!
+//! #[derive(Clone)]
+//! struct MySession {
+//!     user_id: String,
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<MySession>::default())
+//!     // Mounts `GET /session/expiry` and `POST /session/renew`
+//!     .mount("/session", session_routes::<MySession>());
+//! ```
+//!
+//! ```rust
+//! use rocket::serde::Serialize;
+//! use rocket_flex_session::{routes::sync_routes, RocketFlexSession};
+//!
+//! #[derive(Clone)]
+//! struct MySession {
+//!     user_id: String,
+//!     display_name: String,
+//!     csrf_secret: String,
+//! }
+//!
+//! #[derive(Serialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct Profile {
+//!     user_id: String,
+//!     display_name: String,
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<MySession>::default())
+//!     // Mounts `GET /session/sync`, which only exposes the profile fields
+//!     .mount(
+//!         "/session",
+//!         sync_routes(|data: &MySession| Profile {
+//!             user_id: data.user_id.clone(),
+//!             display_name: data.display_name.clone(),
+//!         }),
+//!     );
+//! ```
+
+use std::{fmt::Write as _, marker::PhantomData, sync::Arc};
+
+use rocket::{
+    http::{Header, Method, Status},
+    response::Responder,
+    route::{self, Handler, Route},
+    serde::{
+        json::{serde_json, Json},
+        Serialize,
+    },
+    Data, Request, Response,
+};
+use sha2::{Digest, Sha256};
+
+use crate::Session;
+
+/// Expiration of the current session
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SessionExpiry {
+    /// Expiration of the session as a Unix timestamp in seconds
+    pub expires: i64,
+    /// Remaining time-to-live of the session in seconds
+    pub ttl: u32,
+}
+
+/// Create the session routes for the session data type `T`. See the [module docs](self).
+pub fn session_routes<T>() -> Vec<Route>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    vec![
+        Route::new(Method::Get, "/expiry", SessionRoute::<T>::new(false)),
+        Route::new(Method::Post, "/renew", SessionRoute::<T>::new(true)),
+    ]
+}
+
+/// Handler for the session routes
+struct SessionRoute<T> {
+    /// Whether to renew the session
+    renew: bool,
+    _data: PhantomData<fn() -> T>,
+}
+
+impl<T> SessionRoute<T> {
+    fn new(renew: bool) -> Self {
+        Self {
+            renew,
+            _data: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for SessionRoute<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.renew)
+    }
+}
+
+#[rocket::async_trait]
+impl<T> Handler for SessionRoute<T>
+where
+    T: Send + Sync + Clone + 'static,
+{
+    async fn handle<'r>(&self, req: &'r Request<'_>, _data: Data<'r>) -> route::Outcome<'r> {
+        let Some(mut session) = req.guard::<Session<'r, T>>().await.succeeded() else {
+            return route::Outcome::Error(Status::InternalServerError);
+        };
+        if session.id().is_none() {
+            return route::Outcome::Error(Status::Unauthorized);
+        }
+        if self.renew {
+            session.renew();
+        }
+        let expiry = SessionExpiry {
+            expires: session.expires().unix_timestamp(),
+            ttl: session.ttl(),
+        };
+        route::Outcome::from(req, Json(expiry))
+    }
+}
+
+/// Projection of the session data returned by the [sync route](sync_routes)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SessionSync<P> {
+    /// Fingerprint of the projected data, which changes whenever the data changes
+    pub version: String,
+    /// The projected session data
+    pub data: P,
+}
+
+/// Create the differential sync route for the session data type `T`, which returns the session
+/// data converted with the `project` function. Only include data that the client is allowed to
+/// see. See the [module docs](self).
+pub fn sync_routes<T, P>(project: impl Fn(&T) -> P + Send + Sync + 'static) -> Vec<Route>
+where
+    T: Send + Sync + Clone + 'static,
+    P: Serialize + 'static,
+{
+    let handler = SyncRoute {
+        project: Arc::new(project),
+    };
+    vec![Route::new(Method::Get, "/sync", handler)]
+}
+
+/// Handler for the sync route
+struct SyncRoute<T, P> {
+    project: Arc<dyn Fn(&T) -> P + Send + Sync>,
+}
+
+impl<T, P> Clone for SyncRoute<T, P> {
+    fn clone(&self) -> Self {
+        Self {
+            project: self.project.clone(),
+        }
+    }
+}
+
+/// Fingerprint of the serialized data: the first 16 bytes of its SHA-256 hash, hex-encoded
+fn fingerprint(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)[..16]
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// The version the client already has, from the `since` query parameter or `If-None-Match` header
+fn client_version<'r>(req: &'r Request<'_>) -> Option<&'r str> {
+    let since = req.query_value::<&str>("since").and_then(Result::ok);
+    since.or_else(|| {
+        let etag = req.headers().get_one("If-None-Match")?;
+        Some(etag.trim_start_matches("W/").trim_matches('"'))
+    })
+}
+
+#[rocket::async_trait]
+impl<T, P> Handler for SyncRoute<T, P>
+where
+    T: Send + Sync + Clone + 'static,
+    P: Serialize + 'static,
+{
+    async fn handle<'r>(&self, req: &'r Request<'_>, _data: Data<'r>) -> route::Outcome<'r> {
+        let Some(session) = req.guard::<Session<'r, T>>().await.succeeded() else {
+            return route::Outcome::Error(Status::InternalServerError);
+        };
+        let Some(data) = session.tap(|data| data.map(|data| (self.project)(data))) else {
+            return route::Outcome::Error(Status::Unauthorized);
+        };
+        let Ok(bytes) = serde_json::to_vec(&data) else {
+            return route::Outcome::Error(Status::InternalServerError);
+        };
+        let version = fingerprint(&bytes);
+        let etag = Header::new("ETag", format!("\"{version}\""));
+
+        if client_version(req) == Some(version.as_str()) {
+            let response = Response::build()
+                .status(Status::NotModified)
+                .header(etag)
+                .finalize();
+            return route::Outcome::Success(response);
+        }
+        match Json(SessionSync { version, data }).respond_to(req) {
+            Ok(mut response) => {
+                response.set_header(etag);
+                route::Outcome::Success(response)
+            }
+            Err(status) => route::Outcome::Error(status),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Session scopes with independent TTLs
+//!
+//! [`ScopedSession`] partitions the session data into named scopes (e.g. `auth`, `cart`, and
+//! `prefs`), each with its own TTL. An expired scope is ignored and removed on the next update,
+//! without affecting the other scopes - so an expired cart doesn't end the login. The session
+//! itself lasts as long as its longest-lived scope, and is deleted when its last scope is removed.
+//! Make sure the cookie [`max_age`](crate::RocketFlexSessionOptions::max_age) is at least the
+//! longest scope TTL, so the cookie doesn't expire before the session.
+//!
+//! The scope values are stored as JSON. With the Redis storage, each scope is a separate field of
+//! the session hash, and only the scopes that changed during the request are written. Other
+//! storages save the whole session as usual.
+//!
+//! # Example
+//! ```rust
+//! use rocket::{
+//!     http::Status,
+//!     serde::{Deserialize, Serialize},
+//! };
+//! use rocket_flex_session::{scope::ScopedSession, RocketFlexSession, Session};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct Cart {
+//!     items: Vec<String>,
+//! }
+//!
+//! #[rocket::post("/login/<user_id>")]
+//! fn login(mut session: Session<ScopedSession>, user_id: &str) -> Result<(), Status> {
+//!     session
+//!         .set_scope("auth", user_id, 7 * 24 * 60 * 60)
+//!         .map_err(|_| Status::InternalServerError)
+//! }
+//!
+//! #[rocket::post("/cart/<item>")]
+//! fn add_to_cart(mut session: Session<ScopedSession>, item: &str) -> Result<(), Status> {
+//!     let mut cart = session.scope("cart").unwrap_or(Cart { items: Vec::new() });
+//!     cart.items.push(item.to_owned());
+//!     session
+//!         .set_scope("cart", &cart, 30 * 60)
+//!         .map_err(|_| Status::InternalServerError)
+//! }
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<ScopedSession>::default())
+//!     .mount("/", rocket::routes![login, add_to_cart]);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use rocket::{
+    serde::{
+        de::DeserializeOwned,
+        json::{serde_json, Value},
+        Deserialize, Serialize,
+    },
+    time::{Duration, OffsetDateTime},
+};
+
+use crate::{error::SessionError, Session, SessionIdentifier};
+
+/// A scope of the session data, with its own expiration
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Scope {
+    /// Expiration of the scope as a Unix timestamp in seconds
+    expires: i64,
+    value: Value,
+}
+
+impl Scope {
+    fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires <= now.unix_timestamp()
+    }
+}
+
+/// Session data partitioned into named scopes with independent TTLs. See the [module docs](self).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ScopedSession {
+    scopes: BTreeMap<String, Scope>,
+    /// Names of the scopes that were set or removed since the data was loaded
+    #[serde(skip)]
+    changed: BTreeSet<String>,
+}
+
+impl ScopedSession {
+    /// Get the value of a scope, if it's set and hasn't expired at the given time
+    pub fn get(&self, name: &str, now: OffsetDateTime) -> Option<&Value> {
+        let scope = self.scopes.get(name)?;
+        (!scope.is_expired(now)).then_some(&scope.value)
+    }
+
+    /// Get the expiration of a scope, if it's set
+    pub fn expires(&self, name: &str) -> Option<OffsetDateTime> {
+        let scope = self.scopes.get(name)?;
+        OffsetDateTime::from_unix_timestamp(scope.expires).ok()
+    }
+
+    /// Set the value of a scope, expiring after the TTL (in seconds) from the given time
+    pub fn insert(&mut self, name: &str, value: Value, ttl: u32, now: OffsetDateTime) {
+        let expires = now.unix_timestamp() + i64::from(ttl);
+        self.scopes
+            .insert(name.to_owned(), Scope { expires, value });
+        self.changed.insert(name.to_owned());
+    }
+
+    /// Remove a scope
+    pub fn remove(&mut self, name: &str) {
+        if self.scopes.remove(name).is_some() {
+            self.changed.insert(name.to_owned());
+        }
+    }
+
+    /// Remove all scopes that have expired at the given time
+    pub fn remove_expired(&mut self, now: OffsetDateTime) {
+        let expired: Vec<_> = (self.scopes.iter())
+            .filter(|(_, scope)| scope.is_expired(now))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in expired {
+            self.remove(&name);
+        }
+    }
+
+    /// Whether there are no scopes
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    /// Expiration of the longest-lived scope
+    fn latest_expiry(&self) -> Option<OffsetDateTime> {
+        let expires = self.scopes.values().map(|scope| scope.expires).max()?;
+        OffsetDateTime::from_unix_timestamp(expires).ok()
+    }
+}
+
+impl SessionIdentifier for ScopedSession {
+    type Id = String;
+
+    /// Scoped sessions aren't indexed
+    fn identifier(&self) -> Option<Self::Id> {
+        None
+    }
+}
+
+/// Implementation block for sessions with scopes
+impl Session<'_, ScopedSession> {
+    /// Get the value of a scope, if it's set, hasn't expired, and can be deserialized
+    pub fn scope<V: DeserializeOwned>(&self, name: &str) -> Option<V> {
+        let now = self.clock.now();
+        let value = self.tap(|data| data?.get(name, now).cloned())?;
+        serde_json::from_value(value).ok()
+    }
+
+    /// Get the expiration of a scope, if it's set
+    pub fn scope_expires(&self, name: &str) -> Option<OffsetDateTime> {
+        self.tap(|data| data?.expires(name))
+    }
+
+    /// Set the value of a scope with the given TTL (in seconds). Will create a new session if
+    /// there isn't one. The session TTL is updated to the longest-lived scope.
+    pub fn set_scope(
+        &mut self,
+        name: &str,
+        value: impl Serialize,
+        ttl: u32,
+    ) -> Result<(), SessionError> {
+        let value =
+            serde_json::to_value(value).map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        let now = self.clock.now();
+        self.tap_mut(|data| {
+            let data = data.get_or_insert_with(ScopedSession::default);
+            data.remove_expired(now);
+            data.insert(name, value, ttl, now);
+        });
+        self.extend_to_scopes();
+        Ok(())
+    }
+
+    /// Remove a scope. The session is deleted if it has no other scopes left, and otherwise its
+    /// TTL is updated to the longest-lived remaining scope.
+    pub fn remove_scope(&mut self, name: &str) {
+        let now = self.clock.now();
+        self.tap_mut(|data| {
+            if let Some(scopes) = data {
+                scopes.remove_expired(now);
+                scopes.remove(name);
+                if scopes.is_empty() {
+                    *data = None;
+                }
+            }
+        });
+        self.extend_to_scopes();
+    }
+
+    /// Update the session TTL, so that the session expires along with its longest-lived scope
+    fn extend_to_scopes(&mut self) {
+        let Some(latest) = self.tap(|data| data?.latest_expiry()) else {
+            return;
+        };
+        let ttl = Duration::try_from(self.ttl_duration()).unwrap_or(Duration::ZERO);
+        let new_ttl = ttl + (latest - self.expires_at());
+        if let Ok(new_ttl) = std::time::Duration::try_from(new_ttl) {
+            self.set_ttl_duration(new_ttl);
+        }
+    }
+}
+
+#[cfg(feature = "redis_fred")]
+impl crate::storage::redis::SessionRedis for ScopedSession {
+    const REDIS_FORMAT: crate::storage::redis::RedisFormat =
+        crate::storage::redis::RedisFormat::Map;
+    type Error = SessionError;
+
+    /// Each scope is a field of the session hash, containing the scope as JSON. Only the changed
+    /// scopes are written, and removed scopes are written as empty fields.
+    fn into_redis(self) -> Result<crate::storage::redis::RedisValue, Self::Error> {
+        let names = match self.changed.is_empty() {
+            true => self.scopes.keys().cloned().collect(),
+            false => self.changed,
+        };
+        let fields = names
+            .into_iter()
+            .map(|name| {
+                let value = match self.scopes.get(&name) {
+                    Some(scope) => serde_json::to_string(scope)
+                        .map_err(|e| SessionError::Serialization(Box::new(e)))?,
+                    None => String::new(),
+                };
+                Ok((name, value))
+            })
+            .collect::<Result<_, SessionError>>()?;
+        Ok(crate::storage::redis::RedisValue::Map(fields))
+    }
+
+    fn from_redis(value: crate::storage::redis::RedisValue) -> Result<Self, Self::Error> {
+        let fields = value.into_map().map_err(|_| SessionError::InvalidData)?;
+        let scopes = fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| {
+                let scope =
+                    serde_json::from_str(&value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+                Ok((name, scope))
+            })
+            .collect::<Result<_, SessionError>>()?;
+        Ok(Self {
+            scopes,
+            changed: BTreeSet::new(),
+        })
+    }
+}
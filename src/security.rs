@@ -0,0 +1,33 @@
+//! Security helpers
+//!
+//! Session IDs are secrets, so comparing them with `==` could leak information through timing
+//! side channels (a comparison returns as soon as a byte differs). The built-in storage providers use
+//! these helpers wherever a session ID is compared, and you can use them in a custom storage provider.
+
+use std::hint::black_box;
+
+/// Compare two byte slices in constant time (with respect to their contents). Slices with different
+/// lengths are unequal, and the lengths themselves aren't considered secret.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a
+        .iter()
+        .zip(b)
+        .fold(0u8, |diff, (x, y)| black_box(diff | (x ^ y)));
+    diff == 0
+}
+
+/// Compare two session IDs (or other secret tokens) in constant time
+pub fn session_id_eq(a: &str, b: &str) -> bool {
+    constant_time_eq(a.as_bytes(), b.as_bytes())
+}
+
+/// 64-bit FNV-1a hash. This is **not** a cryptographic hash, but it's fast and stable
+/// across platforms and releases, so it can be used for sampling and log correlation.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
@@ -4,11 +4,16 @@ use rocket::{
 };
 use std::{
     marker::{Send, Sync},
-    sync::{Mutex, MutexGuard},
+    sync::{Arc, Mutex, MutexGuard},
 };
 
 use crate::{
-    error::SessionError, options::RocketFlexSessionOptions, session_inner::SessionInner,
+    clock::Clock,
+    error::SessionError,
+    logging::session_log,
+    options::{CookieOverrides, RocketFlexSessionOptions},
+    quota::QuotaPolicy,
+    session_inner::SessionInner,
     storage::SessionStorage,
 };
 
@@ -50,24 +55,37 @@ where
     /// Error (if any) when retrieving from storage
     error: Option<&'a SessionError>,
     /// Rocket's cookie jar for managing cookies
-    cookie_jar: &'a CookieJar<'a>,
+    pub(crate) cookie_jar: &'a CookieJar<'a>,
     /// User's session options
-    options: &'a RocketFlexSessionOptions,
+    pub(crate) options: &'a RocketFlexSessionOptions,
     /// Configured storage provider for sessions
     pub(crate) storage: &'a dyn SessionStorage<T>,
+    /// Configured clock for calculating expiration
+    pub(crate) clock: &'a dyn Clock,
+    /// Configured predicate for anonymous session data
+    is_anonymous: Option<&'a AnonymousPredicate<T>>,
+    /// Configured quota policy for new sessions
+    pub(crate) quota: Option<&'a dyn QuotaPolicy<T>>,
 }
 
+/// Predicate for anonymous session data, as configured in the fairing
+pub(crate) type AnonymousPredicate<T> = dyn Fn(&T) -> bool + Send + Sync;
+
 impl<'a, T> Session<'a, T>
 where
     T: Send + Sync + Clone,
 {
     /// Create a new session instance to keep track of the session state in a request
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         inner: &'a Mutex<SessionInner<T>>,
         error: Option<&'a SessionError>,
         cookie_jar: &'a CookieJar<'a>,
         options: &'a RocketFlexSessionOptions,
         storage: &'a dyn SessionStorage<T>,
+        clock: &'a dyn Clock,
+        is_anonymous: Option<&'a AnonymousPredicate<T>>,
+        quota: Option<&'a dyn QuotaPolicy<T>>,
     ) -> Self {
         Self {
             inner,
@@ -75,6 +93,9 @@ where
             cookie_jar,
             options,
             storage,
+            clock,
+            is_anonymous,
+            quota,
         }
     }
 
@@ -90,6 +111,14 @@ where
             .map(|d| d.to_owned())
     }
 
+    /// Get a shared pointer to the current session data. Unlike [`get`](Session::get), this
+    /// doesn't clone the data, so it's cheap for large session structs that are mostly read.
+    /// If the data is modified later in the request, it's cloned first, so the pointer still
+    /// refers to the data at the time it was taken. Will be `None` if there's no active session.
+    pub fn get_arc(&self) -> Option<Arc<T>> {
+        self.get_inner_lock().get_current_arc()
+    }
+
     /// Get a reference to the current session data via a closure.
     /// Data will be `None` if there's no active session.
     ///
@@ -128,9 +157,11 @@ where
     where
         UpdateFn: FnOnce(&mut Option<T>) -> R,
     {
-        let (response, is_deleted) = self
-            .get_inner_lock()
-            .tap_data_mut(f, self.get_default_ttl());
+        if self.check_writable().is_err() {
+            return f(&mut None);
+        }
+        let (response, is_deleted) =
+            self.update_inner(|inner, default_ttl| inner.tap_data_mut(f, default_ttl));
         if is_deleted {
             self.delete();
         } else {
@@ -141,9 +172,14 @@ where
     }
 
     /// Set/replace the session data. Will create a new active session if there isn't one.
-    pub fn set(&mut self, new_data: T) {
-        self.get_inner_lock()
-            .set_data(new_data, self.get_default_ttl());
+    /// This accepts the data itself or an `Arc` of the data (e.g. from
+    /// [`get_arc`](Session::get_arc)), which is stored without cloning.
+    pub fn set(&mut self, new_data: impl Into<Arc<T>>) {
+        if self.check_writable().is_err() {
+            return;
+        }
+        let new_data = new_data.into();
+        self.update_inner(|inner, default_ttl| inner.set_data(new_data, default_ttl));
         self.update_cookies();
     }
 
@@ -151,24 +187,134 @@ where
     /// of the session if needed. This has no effect if there is no active session, or
     /// if you have enabled "rolling" sessions in the [`options`](RocketFlexSessionOptions::rolling).
     pub fn set_ttl(&mut self, new_ttl: u32) {
+        if self.check_writable().is_err() {
+            return;
+        }
         self.get_inner_lock().set_ttl(new_ttl);
         self.update_cookies();
     }
 
-    /// Get the session TTL in seconds.
+    /// Set the TTL of the session with sub-second precision, e.g. for short-lived verification
+    /// sessions. Storages that don't support sub-second expiration round the TTL up to whole
+    /// seconds. Like [`set_ttl`](Session::set_ttl), this has no effect if there is no active session.
+    pub fn set_ttl_duration(&mut self, new_ttl: std::time::Duration) {
+        if self.check_writable().is_err() {
+            return;
+        }
+        self.get_inner_lock().set_ttl_duration(new_ttl);
+        self.update_cookies();
+    }
+
+    /// Reset the TTL of the session to the configured TTL (or the anonymous/authenticated TTL,
+    /// if set), extending the session. This has no effect if there is no active session.
+    pub fn renew(&mut self) {
+        let ttl = {
+            let inner = self.get_inner_lock();
+            self.check_anonymous(&inner)
+                .and_then(|anonymous| self.options.ttl_for(anonymous))
+                .unwrap_or(self.get_default_ttl())
+        };
+        self.set_ttl(ttl);
+    }
+
+    /// Get the session TTL in seconds. A TTL set with sub-second precision is rounded up.
     pub fn ttl(&self) -> u32 {
         self.get_inner_lock()
             .get_current_ttl()
             .unwrap_or(self.get_default_ttl())
     }
 
-    /// Get the session expiration.
+    /// Get the precise session TTL.
+    pub fn ttl_duration(&self) -> std::time::Duration {
+        self.get_inner_lock()
+            .get_current_ttl_duration()
+            .unwrap_or(std::time::Duration::from_secs(
+                self.get_default_ttl().into(),
+            ))
+    }
+
+    /// Get the session expiration. Same as [`expires_at`](Session::expires_at).
     pub fn expires(&self) -> OffsetDateTime {
-        OffsetDateTime::now_utc().saturating_add(Duration::seconds(self.ttl().into()))
+        self.expires_at()
+    }
+
+    /// Get the session expiration. This is calculated from the time the session was loaded from
+    /// storage (or first accessed during this request), so it doesn't drift during the request
+    /// unless the TTL is changed.
+    pub fn expires_at(&self) -> OffsetDateTime {
+        let mut inner = self.get_inner_lock();
+        let ttl = inner
+            .get_current_ttl_duration()
+            .and_then(|ttl| Duration::try_from(ttl).ok())
+            .unwrap_or(Duration::seconds(self.get_default_ttl().into()));
+        inner.get_anchor(|| self.clock.now()).saturating_add(ttl)
+    }
+
+    /// Get the time the session was created. This is `None` if there's no active session, or if the
+    /// storage provider doesn't track the creation time (see
+    /// [`SessionStorage::load_with_metadata`]). For a session created during this request, this is
+    /// the request's reference time, which may be slightly earlier than the creation time
+    /// recorded by the storage when the session is saved.
+    pub fn created_at(&self) -> Option<OffsetDateTime> {
+        self.get_inner_lock().get_created_at(|| self.clock.now())
+    }
+
+    /// Get the remaining time in seconds until the session [expires](Session::expires_at).
+    pub fn remaining_ttl(&self) -> u32 {
+        (self.expires_at() - self.clock.now())
+            .whole_seconds()
+            .try_into()
+            .unwrap_or(0)
+    }
+
+    /// Whether the current session was created or modified during this request, and will be
+    /// saved to storage at the end of the request.
+    pub fn is_dirty(&self) -> bool {
+        self.get_inner_lock().is_dirty()
+    }
+
+    /// Whether the current session was created during this request.
+    pub fn is_new(&self) -> bool {
+        self.get_inner_lock().is_new()
+    }
+
+    /// Whether a session was deleted during this request. A new session may have been
+    /// created afterwards.
+    pub fn was_deleted(&self) -> bool {
+        self.get_inner_lock().was_deleted()
+    }
+
+    /// Override the attributes of the session cookie (e.g. a longer `max_age` for a "remember me"
+    /// login) for this response only, without changing the configured options. The cookie is
+    /// added to the response again if there's an active session. This doesn't change the session
+    /// TTL in storage - use [`set_ttl`](Session::set_ttl) to extend the session as well.
+    ///
+    /// If you override the `path`, the session cookie may not be removed when the session is deleted
+    /// in a later request, as that uses the configured `path`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// session.set(data);
+    /// if remember_me {
+    ///     session.set_ttl(30 * 24 * 60 * 60);
+    ///     session.with_cookie_overrides(|cookie| cookie.max_age = Some(30 * 24 * 60 * 60));
+    /// }
+    /// ```
+    pub fn with_cookie_overrides(&mut self, f: impl FnOnce(&mut CookieOverrides)) {
+        if self.check_writable().is_err() {
+            return;
+        }
+        f(self.get_inner_lock().cookie_overrides_mut());
+        if self.get_inner_lock().get_id().is_some() {
+            self.update_cookies();
+        }
     }
 
     /// Delete the current session.
     pub fn delete(&mut self) {
+        if self.check_writable().is_err() {
+            return;
+        }
         // Delete inner session data
         let mut inner = self.get_inner_lock();
         inner.delete();
@@ -179,7 +325,9 @@ where
         if let Some(domain) = &self.options.domain {
             remove_cookie = remove_cookie.domain(domain.to_owned());
         }
-        self.cookie_jar.remove_private(remove_cookie);
+        self.options
+            .cookie_key
+            .remove(self.cookie_jar, remove_cookie);
 
         // Notify any cookie-based storage
         if let Some(deleted_id) = inner.get_deleted_id() {
@@ -187,11 +335,72 @@ where
                 .storage
                 .save_cookie(deleted_id, None, 0, self.cookie_jar);
             if let Err(e) = delete_result {
-                rocket::error!("Error while deleting session {:?}: {}", deleted_id, e);
+                let options = self.options;
+                let log_id = options.log_ids.format(deleted_id);
+                let message = e.log_message(options.redact_errors);
+                session_log!(
+                    options,
+                    error,
+                    "Error while deleting session '{log_id}': {message}"
+                );
             }
         }
     }
 
+    /// Register a callback for the result of persisting the session at the end of this request,
+    /// e.g. to record failed saves against the request ID or user. The callback is called once
+    /// the session has been saved and/or deleted in storage, with the first error if any operation
+    /// failed (errors are also logged and reported to the fairing's `on_save_error` hook). It isn't
+    /// called if the session didn't need to be saved or deleted.
+    ///
+    /// With a [persistence queue](crate::queue), the callback is called from the background task
+    /// after the response has been sent.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// session.set(data);
+    /// let request_id = request_id.to_string();
+    /// session.on_persisted(move |result| {
+    ///     if let Err(e) = result {
+    ///         eprintln!("Failed to save session for request {request_id}: {e}");
+    ///     }
+    /// });
+    /// ```
+    pub fn on_persisted(&self, f: impl FnOnce(Result<(), &SessionError>) + Send + 'static) {
+        self.get_inner_lock().add_persisted_hook(Box::new(f));
+    }
+
+    /// Log out: delete the current session, and remove all cookies related to it. In addition to
+    /// [`delete`](Session::delete), this removes the data cookie of a cookie-based storage even if
+    /// no session was loaded (e.g. if the session cookie was missing or invalid), and the
+    /// configured [companion cookies](RocketFlexSessionOptions::companion_cookies).
+    pub fn logout(&mut self) {
+        if self.check_writable().is_err() {
+            return;
+        }
+        self.delete();
+
+        // Remove an orphaned cookie of a cookie-based storage
+        if self.get_inner_lock().get_deleted_id().is_none() {
+            if let Err(e) = self.storage.save_cookie("", None, 0, self.cookie_jar) {
+                let message = e.log_message(self.options.redact_errors);
+                session_log!(
+                    self.options,
+                    error,
+                    "Error while removing session storage cookie: {message}"
+                );
+            }
+        }
+
+        for name in &self.options.companion_cookies {
+            let mut cookie = Cookie::build(name.to_owned()).path(self.options.path.to_owned());
+            if let Some(domain) = &self.options.domain {
+                cookie = cookie.domain(domain.to_owned());
+            }
+            self.cookie_jar.remove(cookie);
+        }
+    }
+
     /// Get the error (if any) during session retrieval.
     /// Note that this 'error' could be completely expected - e.g. a
     /// `SessionError::NoSessionCookie` if the user hasn't authenticated.
@@ -203,21 +412,69 @@ where
         self.inner.lock().expect("Failed to get session data lock")
     }
 
+    /// In [strict](RocketFlexSessionOptions::strict) mode, check that the session can still be modified,
+    /// i.e. the session data hasn't been taken for storage at the end of the request. Logs an error if not.
+    pub(crate) fn check_writable(&self) -> Result<(), SessionError> {
+        if !self.options.strict || !self.get_inner_lock().is_finalized() {
+            return Ok(());
+        }
+        session_log!(
+            self.options,
+            error,
+            "Session was modified after the response started. The change was discarded."
+        );
+        Err(SessionError::ResponseStarted)
+    }
+
     pub(super) fn get_default_ttl(&self) -> u32 {
         self.options.ttl.unwrap_or(self.options.max_age)
     }
 
+    /// Update the inner session state. If the session was just created, or switched between
+    /// anonymous and authenticated, the corresponding TTL setting is applied.
+    pub(super) fn update_inner<R>(&self, update: impl FnOnce(&mut SessionInner<T>, u32) -> R) -> R {
+        let mut inner = self.get_inner_lock();
+        let was_anonymous = self.check_anonymous(&inner);
+        let response = update(&mut inner, self.get_default_ttl());
+        let class_ttl = self
+            .check_anonymous(&inner)
+            .filter(|anonymous| was_anonymous != Some(*anonymous))
+            .and_then(|anonymous| self.options.ttl_for(anonymous));
+        if let Some(ttl) = class_ttl {
+            inner.set_ttl(ttl);
+        }
+        response
+    }
+
+    /// Whether the current session data is anonymous, if there's an active session
+    /// and the fairing's `is_anonymous` setting is configured
+    fn check_anonymous(&self, inner: &SessionInner<T>) -> Option<bool> {
+        let is_anonymous = self.is_anonymous?;
+        inner.get_current_data().map(is_anonymous)
+    }
+
     pub(super) fn update_cookies(&self) {
-        let inner = self.get_inner_lock();
-        let Some(id) = inner.get_id() else {
-            rocket::warn!("Cookies not updated: no active session");
+        if let Some(SessionError::Skipped) = self.error {
+            return;
+        }
+        let mut inner = self.get_inner_lock();
+        if inner.is_read_only() {
+            return;
+        }
+        let Some(id) = inner.get_id().map(str::to_owned) else {
+            session_log!(self.options, warn, "Cookies not updated: no active session");
             return;
         };
+        let id = id.as_str();
 
         // Generate new session cookie if needed
-        if inner.is_new() {
-            let session_cookie = create_session_cookie(id, self.options);
-            self.cookie_jar.add_private(session_cookie);
+        if inner.is_new() || inner.get_cookie_overrides().is_some() {
+            let session_cookie =
+                create_session_cookie(id, self.options, inner.get_cookie_overrides());
+            let changed = inner.record_cookie(session_cookie.to_string());
+            if changed || self.options.rewrite_unchanged_cookie {
+                self.options.cookie_key.add(self.cookie_jar, session_cookie);
+            }
         }
 
         // Notify any cookie-based storage
@@ -228,19 +485,32 @@ where
             self.cookie_jar,
         );
         if let Err(e) = save_result {
-            rocket::error!("Error while saving session {:?}: {}", id, e);
+            let options = self.options;
+            let log_id = options.log_ids.format(id);
+            let message = e.log_message(options.redact_errors);
+            session_log!(
+                options,
+                error,
+                "Error while saving session '{log_id}': {message}"
+            );
         };
     }
 }
 
-/// Create the session cookie
-fn create_session_cookie(id: &str, options: &RocketFlexSessionOptions) -> Cookie<'static> {
+/// Create the session cookie, applying any overrides for this request
+pub(crate) fn create_session_cookie(
+    id: &str,
+    options: &RocketFlexSessionOptions,
+    overrides: Option<&CookieOverrides>,
+) -> Cookie<'static> {
+    let overrides = overrides.cloned().unwrap_or_default();
+    let max_age = overrides.max_age.unwrap_or(options.max_age);
     let mut cookie = Cookie::build((options.cookie_name.to_owned(), id.to_owned()))
         .http_only(options.http_only)
-        .max_age(Duration::seconds(options.max_age.into()))
-        .path(options.path.clone())
-        .same_site(options.same_site)
-        .secure(options.secure);
+        .max_age(Duration::seconds(max_age.into()))
+        .path(overrides.path.unwrap_or_else(|| options.path.clone()))
+        .same_site(overrides.same_site.unwrap_or(options.same_site))
+        .secure(overrides.secure.unwrap_or(options.secure));
 
     if let Some(domain) = &options.domain {
         cookie = cookie.domain(domain.clone());
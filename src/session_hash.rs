@@ -49,23 +49,33 @@ where
 
     /// Set the value of a key in the session data. Will create a new session if there isn't one.
     pub fn set_key(&mut self, key: String, value: T::Value) {
-        self.get_inner_lock().tap_data_mut(
-            |data| data.get_or_insert_default().insert(key, value),
-            self.get_default_ttl(),
-        );
+        if self.check_writable().is_err() {
+            return;
+        }
+        self.update_inner(|inner, default_ttl| {
+            inner.tap_data_mut(
+                |data| data.get_or_insert_with(Default::default).insert(key, value),
+                default_ttl,
+            )
+        });
         self.update_cookies();
     }
 
     /// Remove a key from the session data.
     pub fn remove_key(&mut self, key: &str) {
-        self.get_inner_lock().tap_data_mut(
-            |data| {
-                if let Some(data) = data {
-                    data.remove(key);
-                }
-            },
-            self.get_default_ttl(),
-        );
+        if self.check_writable().is_err() {
+            return;
+        }
+        self.update_inner(|inner, default_ttl| {
+            inner.tap_data_mut(
+                |data| {
+                    if let Some(data) = data {
+                        data.remove(key);
+                    }
+                },
+                default_ttl,
+            )
+        });
         self.update_cookies();
     }
 }
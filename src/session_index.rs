@@ -1,4 +1,8 @@
-use crate::{error::SessionError, storage::SessionStorageIndexed, Session};
+use crate::{
+    error::SessionError,
+    storage::{SessionMetadata, SessionStorageIndexed},
+    Session,
+};
 
 /// Trait for session data types that allows grouping sessions by an identifier.
 /// This enables features like retrieving all sessions for a user or invalidating
@@ -34,6 +38,13 @@ pub trait SessionIdentifier: Send + Sync + Clone {
     /// Can return `None` if a session doesn't have an identifier and/or
     /// shouldn't be indexed.
     fn identifier(&self) -> Option<Self::Id>;
+
+    /// Optional: tags of the session, such as `device-type=mobile` or `scope=support-console`,
+    /// which are indexed so that sessions can be [invalidated by tag](SessionStorageIndexed::invalidate_sessions_by_tag).
+    /// Like the identifier, tags should not change for the lifetime of the session.
+    fn tags(&self) -> Vec<String> {
+        Vec::new()
+    }
 }
 
 /// Session implementation block for indexing operations
@@ -86,6 +97,53 @@ where
         Ok(Some(num_sessions))
     }
 
+    /// Invalidate all sessions with the same user/identifier as the current session, optionally keeping the
+    /// current session active, like [`invalidate_all_sessions`](Session::invalidate_all_sessions). Returns the
+    /// ID, data, and [metadata](SessionMetadata) of each session invalidated, so you can tell the user which
+    /// devices were signed out (e.g. with a [`DeviceSession`](crate::device::DeviceSession)), or `None` if
+    /// there's no current session or the session isn't indexed.
+    pub async fn invalidate_all_sessions_with_details(
+        &self,
+        keep_current: bool,
+    ) -> Result<Option<Vec<(String, T, SessionMetadata)>>, SessionError> {
+        let Some((session_id, identifier)) = self.id().zip(self.get_identifier()) else {
+            return Ok(None);
+        };
+        let storage = self.get_indexed_storage()?;
+        let sessions = storage
+            .invalidate_sessions_by_identifier_with_details(
+                &identifier,
+                keep_current.then_some(session_id.as_str()),
+            )
+            .await?;
+
+        Ok(Some(sessions))
+    }
+
+    /// Update the data of all sessions with the same user/identifier as the current session, e.g. to
+    /// propagate a role change to the user's other devices without waiting for them to sign in again.
+    /// The current session is updated as well. Returns the number of other sessions updated, or `None`
+    /// if there's no current session or the session isn't indexed.
+    pub async fn update_all_sessions<F>(&mut self, update: F) -> Result<Option<u64>, SessionError>
+    where
+        F: Fn(T) -> T + Send + Sync,
+    {
+        let Some((session_id, identifier)) = self.id().zip(self.get_identifier()) else {
+            return Ok(None);
+        };
+        let storage = self.get_indexed_storage()?;
+        let num_sessions = storage
+            .update_sessions_by_identifier(&identifier, Some(&session_id), &update)
+            .await?;
+        self.tap_mut(|data| {
+            if let Some(current) = data.take() {
+                *data = Some(update(current));
+            }
+        });
+
+        Ok(Some(num_sessions))
+    }
+
     /// Get all session IDs, data, and TTL (in seconds) for a specific user/identifier.
     pub async fn get_sessions_by_identifier(
         &self,
@@ -115,13 +173,38 @@ where
             .await
     }
 
+    /// Update the data of all sessions for a specific user/identifier, returning the number of sessions updated.
+    /// If the current session belongs to the identifier, changes made to it during this request will take precedence.
+    pub async fn update_sessions_by_identifier<F>(
+        &self,
+        identifier: &T::Id,
+        update: F,
+    ) -> Result<u64, SessionError>
+    where
+        F: Fn(T) -> T + Send + Sync,
+    {
+        let storage = self.get_indexed_storage()?;
+        storage
+            .update_sessions_by_identifier(identifier, None, &update)
+            .await
+    }
+
+    /// Invalidate all sessions with the given tag, e.g. to revoke the sessions of a vulnerable
+    /// client app. Returns the number of sessions invalidated.
+    pub async fn invalidate_sessions_by_tag(&self, tag: &str) -> Result<u64, SessionError> {
+        let storage = self.get_indexed_storage()?;
+        storage.invalidate_sessions_by_tag(tag).await
+    }
+
     /// Get the current session's identifier, if there is one.
     fn get_identifier(&self) -> Option<T::Id> {
         self.get_inner_lock().get_current_identifier()
     }
 
     /// Try to cast the storage as an indexed storage
-    fn get_indexed_storage(&self) -> Result<&dyn SessionStorageIndexed<T>, SessionError> {
+    pub(crate) fn get_indexed_storage(
+        &self,
+    ) -> Result<&dyn SessionStorageIndexed<T>, SessionError> {
         let indexed_storage = self
             .storage
             .as_indexed_storage()
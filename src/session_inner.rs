@@ -1,6 +1,33 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use rand::distr::{Alphanumeric, SampleString};
+use rocket::time::OffsetDateTime;
+
+use crate::{error::SessionError, options::CookieOverrides, storage::ttl_secs, SessionIdentifier};
+
+/// Length of generated session IDs
+const ID_LENGTH: usize = 20;
 
-use crate::SessionIdentifier;
+/// Maximum length of a session ID accepted from the cookie by the default validation
+const MAX_ID_LENGTH: usize = 128;
+
+/// Default validation of a session ID from the cookie: 1-128 characters, containing only
+/// ASCII letters, digits, `-` and `_`. Generated IDs are always valid.
+pub(crate) fn is_valid_id(id: &str) -> bool {
+    (1..=MAX_ID_LENGTH).contains(&id.len())
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Generate a new random session ID
+pub(crate) fn generate_id() -> String {
+    Alphanumeric.sample_string(&mut rand::rng(), ID_LENGTH)
+}
 
 /** Mutable session state, stored in Rocket's request local cache */
 #[derive(Debug)]
@@ -9,6 +36,21 @@ pub(crate) struct SessionInner<T> {
     current: Option<ActiveSession<T>>,
     /// The original session if deleted during the request
     deleted: Option<ActiveSession<T>>,
+    /// The last session cookie added to the cookie jar during the request
+    last_cookie: Option<String>,
+    /// Overrides of the session cookie attributes for this request
+    cookie_overrides: Option<CookieOverrides>,
+    /// Whether the session data was taken for storage at the end of the request
+    finalized: bool,
+    /// The time the session was loaded (or first accessed during the request), used as
+    /// the reference time for expiration so that it doesn't drift during the request
+    anchor: Option<OffsetDateTime>,
+    /// Callbacks to call with the result of persisting the session
+    persisted_hooks: PersistedHooks,
+    /// Whether changes to the session are discarded instead of saved (e.g. for preflight requests)
+    read_only: bool,
+    /// The session data as it was loaded from storage, kept to merge concurrent changes
+    loaded: Option<Arc<T>>,
 }
 impl<T> Default for SessionInner<T> {
     fn default() -> Self {
@@ -21,10 +63,13 @@ impl<T> Default for SessionInner<T> {
 struct ActiveSession<T> {
     /// Session ID (20-character alphanumeric string)
     id: String,
-    /// Session data
-    data: T,
-    /// Time-to-live in seconds
-    ttl: u32,
+    /// Session data, shared with the route handlers that called
+    /// [`Session::get_arc`](crate::Session::get_arc)
+    data: Arc<T>,
+    /// Time-to-live
+    ttl: Duration,
+    /// When the session was created, if tracked by the storage
+    created_at: Option<OffsetDateTime>,
     /// Status of the active session
     status: ActiveSessionStatus,
 }
@@ -42,20 +87,27 @@ enum ActiveSessionStatus {
 
 impl<T> ActiveSession<T> {
     /// Create a new active session with a generated ID, to be saved in storage
-    fn new(new_data: T, ttl: u32) -> Self {
+    fn new(new_data: Arc<T>, ttl: u32) -> Self {
         Self {
-            id: Alphanumeric.sample_string(&mut rand::rng(), 20),
+            id: generate_id(),
             data: new_data,
-            ttl,
+            ttl: Duration::from_secs(ttl.into()),
+            created_at: None,
             status: ActiveSessionStatus::New,
         }
     }
     /// Active session that already exists in storage
-    fn existing(id: &str, data: T, ttl: u32) -> ActiveSession<T> {
+    fn existing(
+        id: &str,
+        data: T,
+        ttl: u32,
+        created_at: Option<OffsetDateTime>,
+    ) -> ActiveSession<T> {
         Self {
             id: id.to_owned(),
-            data,
-            ttl,
+            data: Arc::new(data),
+            ttl: Duration::from_secs(ttl.into()),
+            created_at,
             status: ActiveSessionStatus::Existing,
         }
     }
@@ -67,13 +119,33 @@ impl<T> SessionInner<T> {
         Self {
             current: None,
             deleted: None,
+            last_cookie: None,
+            cookie_overrides: None,
+            finalized: false,
+            anchor: None,
+            persisted_hooks: PersistedHooks::default(),
+            read_only: false,
+            loaded: None,
         }
     }
-    /// New inner session with an existing active session
-    pub(crate) fn new_existing(id: &str, data: T, ttl: u32) -> Self {
+    /// New inner session with an existing active session, loaded at the given time
+    pub(crate) fn new_existing(
+        id: &str,
+        data: T,
+        ttl: u32,
+        created_at: Option<OffsetDateTime>,
+        loaded_at: OffsetDateTime,
+    ) -> Self {
         Self {
-            current: Some(ActiveSession::existing(id, data, ttl)),
+            current: Some(ActiveSession::existing(id, data, ttl, created_at)),
             deleted: None,
+            last_cookie: None,
+            cookie_overrides: None,
+            finalized: false,
+            anchor: Some(loaded_at),
+            persisted_hooks: PersistedHooks::default(),
+            read_only: false,
+            loaded: None,
         }
     }
 
@@ -82,20 +154,80 @@ impl<T> SessionInner<T> {
     }
 
     pub(crate) fn get_current_data(&self) -> Option<&T> {
-        self.current.as_ref().map(|s| &s.data)
+        self.current.as_ref().map(|s| s.data.as_ref())
+    }
+
+    /// Get a shared pointer to the current data, without cloning the data
+    pub(crate) fn get_current_arc(&self) -> Option<Arc<T>> {
+        self.current.as_ref().map(|s| s.data.clone())
     }
 
+    /// Get the TTL in whole seconds (rounded up)
     pub(crate) fn get_current_ttl(&self) -> Option<u32> {
+        self.current.as_ref().map(|s| ttl_secs(s.ttl))
+    }
+
+    pub(crate) fn get_current_ttl_duration(&self) -> Option<Duration> {
         self.current.as_ref().map(|s| s.ttl)
     }
 
+    /// Get the reference time for expiration, setting it to the current time if it's the first access
+    pub(crate) fn get_anchor(&mut self, now: impl FnOnce() -> OffsetDateTime) -> OffsetDateTime {
+        *self.anchor.get_or_insert_with(now)
+    }
+
+    /// Get the creation time of the current session. New sessions are created at the reference time.
+    pub(crate) fn get_created_at(
+        &mut self,
+        now: impl FnOnce() -> OffsetDateTime,
+    ) -> Option<OffsetDateTime> {
+        let current = self.current.as_ref()?;
+        if current.status != ActiveSessionStatus::New {
+            return current.created_at;
+        }
+        Some(self.get_anchor(now))
+    }
+
     pub(crate) fn is_new(&self) -> bool {
         self.current
             .as_ref()
-            .map_or(false, |s| s.status == ActiveSessionStatus::New)
+            .is_some_and(|s| s.status == ActiveSessionStatus::New)
     }
 
-    pub(crate) fn set_data(&mut self, new_data: T, default_ttl: u32) {
+    pub(crate) fn is_dirty(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|s| should_save_session(&s.status))
+    }
+
+    pub(crate) fn was_deleted(&self) -> bool {
+        self.deleted.is_some()
+    }
+
+    pub(crate) fn is_finalized(&self) -> bool {
+        self.finalized
+    }
+
+    pub(crate) fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Discard any changes to the session instead of saving them at the end of the request
+    pub(crate) fn set_read_only(&mut self) {
+        self.read_only = true;
+    }
+
+    /// Keep the data of the loaded session, to detect concurrent changes when it's saved
+    pub(crate) fn keep_loaded_snapshot(&mut self) {
+        self.loaded = self.get_current_arc();
+    }
+
+    /// Take the data of the session as it was loaded, if it was kept
+    pub(crate) fn take_loaded_snapshot(&mut self) -> Option<Arc<T>> {
+        self.loaded.take()
+    }
+
+    pub(crate) fn set_data(&mut self, new_data: Arc<T>, default_ttl: u32) {
         match &mut self.current {
             Some(current) => {
                 current.data = new_data;
@@ -106,25 +238,33 @@ impl<T> SessionInner<T> {
     }
 
     pub(crate) fn set_ttl(&mut self, new_ttl: u32) {
+        self.set_ttl_duration(Duration::from_secs(new_ttl.into()));
+    }
+
+    pub(crate) fn set_ttl_duration(&mut self, new_ttl: Duration) {
         if let Some(current) = &mut self.current {
             current.ttl = new_ttl;
             self.mark_updated();
         }
     }
 
+    /// Update the data via a callback. The data is only cloned if it's still shared with a
+    /// route handler (copy-on-write).
     pub(crate) fn tap_data_mut<UpdateFn, R>(
         &mut self,
         callback: UpdateFn,
         default_ttl: u32,
     ) -> (R, bool)
     where
+        T: Clone,
         UpdateFn: FnOnce(&mut Option<T>) -> R,
     {
         match self.current.take() {
             Some(current) => {
-                let mut updated_data = Some(current.data);
+                let mut updated_data = Some(Arc::unwrap_or_clone(current.data));
                 let response = callback(&mut updated_data);
                 if let Some(data) = updated_data {
+                    let data = Arc::new(data);
                     self.current = Some(ActiveSession { data, ..current });
                     self.mark_updated();
                     (response, false)
@@ -137,7 +277,7 @@ impl<T> SessionInner<T> {
                 let mut new_data: Option<T> = None;
                 let response = callback(&mut new_data);
                 if let Some(data) = new_data {
-                    self.current = Some(ActiveSession::new(data, default_ttl));
+                    self.current = Some(ActiveSession::new(Arc::new(data), default_ttl));
                     (response, false)
                 } else {
                     self.delete();
@@ -164,20 +304,116 @@ impl<T> SessionInner<T> {
         }
     }
 
+    /// Record the session cookie added to the cookie jar. Returns `false` if it's the same
+    /// as the last recorded cookie.
+    pub(crate) fn record_cookie(&mut self, cookie: String) -> bool {
+        if self.last_cookie.as_ref() == Some(&cookie) {
+            return false;
+        }
+        self.last_cookie = Some(cookie);
+        true
+    }
+
+    pub(crate) fn get_cookie_overrides(&self) -> Option<&CookieOverrides> {
+        self.cookie_overrides.as_ref()
+    }
+
+    pub(crate) fn cookie_overrides_mut(&mut self) -> &mut CookieOverrides {
+        self.cookie_overrides
+            .get_or_insert_with(CookieOverrides::default)
+    }
+
+    /// Add a callback for the result of persisting the session at the end of the request
+    pub(crate) fn add_persisted_hook(&mut self, hook: PersistedHook) {
+        self.persisted_hooks.0.push(hook);
+    }
+
+    /// Take the callbacks to report the result of persisting the session, if any were added
+    pub(crate) fn take_persist_report(&mut self) -> Option<Arc<PersistReport>> {
+        let hooks = std::mem::take(&mut self.persisted_hooks.0);
+        (!hooks.is_empty()).then(|| {
+            Arc::new(PersistReport {
+                hooks: Mutex::new(hooks),
+                outcome: Mutex::default(),
+            })
+        })
+    }
+
     pub(crate) fn get_deleted_id(&self) -> Option<&str> {
         self.deleted.as_ref().map(|s| s.id.as_str())
     }
 
+    /// Get the creation time of the session deleted during the request, if tracked by the storage
+    pub(crate) fn get_deleted_created_at(&self) -> Option<OffsetDateTime> {
+        self.deleted.as_ref().and_then(|s| s.created_at)
+    }
+
     /// Get all data for storage if the session needs to be saved or deleted. Returns a tuple of Options
     /// representing an updated session along with a deleted session. This should only be
     /// called once at the end of the request, as it takes ownership of all data.
-    pub(crate) fn take_for_storage(&mut self) -> (Option<(String, T, u32)>, Option<(String, T)>) {
+    /// The data is only cloned if it's still shared with a route handler.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn take_for_storage(
+        &mut self,
+    ) -> (Option<(String, T, Duration)>, Option<(String, T)>)
+    where
+        T: Clone,
+    {
+        self.finalized = true;
         let updated_session = self
             .current
             .take()
             .filter(|c| should_save_session(&c.status))
-            .map(|c| (c.id, c.data, c.ttl));
-        (updated_session, self.deleted.take().map(|s| (s.id, s.data)))
+            .map(|c| (c.id, Arc::unwrap_or_clone(c.data), c.ttl));
+        let deleted_session = (self.deleted.take()).map(|s| (s.id, Arc::unwrap_or_clone(s.data)));
+        (updated_session, deleted_session)
+    }
+}
+
+/// A callback for the result of persisting the session at the end of a request
+pub(crate) type PersistedHook = Box<dyn FnOnce(Result<(), &SessionError>) + Send>;
+
+#[derive(Default)]
+struct PersistedHooks(Vec<PersistedHook>);
+
+impl fmt::Debug for PersistedHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PersistedHooks({})", self.0.len())
+    }
+}
+
+/// Collects the results of the storage operations of a request, and calls the request's
+/// `on_persisted` callbacks once all of them have completed (i.e. when the last reference
+/// is dropped, which may be in the background persistence queue).
+pub(crate) struct PersistReport {
+    /// Only taken on drop, but in a mutex so the report can be shared between threads
+    hooks: Mutex<Vec<PersistedHook>>,
+    /// Whether any operation was run, and the first error
+    outcome: Mutex<(bool, Option<SessionError>)>,
+}
+
+impl PersistReport {
+    /// Record the result of a storage operation. Only the first error is kept.
+    pub(crate) fn record(&self, result: Result<(), SessionError>) {
+        let mut outcome = self.outcome.lock().unwrap();
+        outcome.0 = true;
+        if let Err(e) = result {
+            outcome.1.get_or_insert(e);
+        }
+    }
+}
+
+impl Drop for PersistReport {
+    fn drop(&mut self) {
+        let (ran, error) =
+            std::mem::take(self.outcome.get_mut().unwrap_or_else(|e| e.into_inner()));
+        if !ran {
+            return;
+        }
+        let hooks = self.hooks.get_mut().unwrap_or_else(|e| e.into_inner());
+        for hook in hooks.drain(..) {
+            hook(error.as_ref().map_or(Ok(()), Err));
+        }
     }
 }
 
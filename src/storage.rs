@@ -16,19 +16,106 @@
 //!
 //! ## Custom Storage
 //!
-//! Implement [`SessionStorage`] to create custom storage backends. For indexing
-//! support, also implement [`SessionStorageIndexed`].
+//! Implement [`SessionStore`] to create a custom storage backend with the minimal set of
+//! operations (load, save, and delete), or [`SessionStorage`] for optional capabilities such as
+//! activity logs. For indexing support, also implement [`SessionStorageIndexed`]. Backends that
+//! keep the session in a cookie should implement [`CookieStore`] as well. The `conformance` module
+//! (behind the `test-util` feature) has a test suite you can run against your implementation.
+//!
+//! Storage operations receive the [`SessionContext`] of the request, which gives access to its
+//! cookies. To use a storage outside of a request (e.g. in background jobs or tests), pass a
+//! [`NoopSessionContext`].
+//!
+//! ## Layers
+//!
+//! Cross-cutting behavior such as retries, timeouts, and metrics can be added to any storage
+//! provider with the [`layer`] module. The [`wal`] module has a layer that records session
+//! mutations in a write-ahead log, which can be replayed after restoring the storage from a backup.
+//! To serialize the session data once for all of the layers, use the [`serialized`] module.
+//! In tests, the `chaos` module (behind the `test-util` feature) has a layer that injects faults.
+//!
+//! ## Migrations
+//!
+//! To gradually move sessions to a new storage provider, route a percentage of them to it with
+//! the [`split`] module. To verify that a new storage has the same sessions as the current one
+//! before switching to it, compare them with the [`shadow`] module.
+//!
+//! ## Multiple session types
+//!
+//! To use one storage instance and connection pool for several fairings, create them from a
+//! [`SharedBackend`](shared::SharedBackend) handle (see the [`shared`] module). If the storage
+//! can't store all of the session types directly, use the [`erased`] module instead.
 
+mod change;
+mod context;
+mod forward;
 mod interface;
+mod store;
+mod task;
+pub use change::{SessionChange, SessionChangeStream};
+pub use context::{NoopSessionContext, SessionContext};
+pub(crate) use forward::forward_storage_impl;
 pub use interface::*;
+pub use store::{CookieStore, SessionStore};
+pub(crate) use store::{CookieStoreAdapter, StoreAdapter};
+pub use task::TaskGuard;
 
+pub mod erased;
+pub mod layer;
 pub mod memory;
+pub mod serialized;
+pub mod shadow;
+pub mod shared;
+pub mod split;
+pub mod tiered;
+pub mod wal;
 
-#[cfg(any(feature = "cookie"))]
+#[cfg(feature = "cookie")]
 pub mod cookie;
 
-#[cfg(any(feature = "redis_fred"))]
+#[cfg(feature = "redis_fred")]
 pub mod redis;
 
 #[cfg(any(feature = "sqlx_postgres", feature = "sqlx_sqlite"))]
 pub mod sqlx;
+
+#[cfg(feature = "etcd")]
+pub mod etcd;
+
+#[cfg(feature = "libsql")]
+pub mod libsql;
+
+#[cfg(feature = "rest_kv")]
+pub mod rest_kv;
+
+#[cfg(feature = "tower_sessions")]
+pub mod tower_sessions;
+
+#[cfg(feature = "rocket_session_store")]
+pub mod rocket_session_store;
+
+#[cfg(feature = "test-util")]
+pub mod conformance;
+
+#[cfg(feature = "test-util")]
+pub mod chaos;
+
+#[cfg(any(feature = "zstd", feature = "lz4"))]
+pub mod compression;
+
+#[cfg(feature = "json")]
+pub mod envelope;
+
+#[cfg(feature = "json")]
+pub mod express;
+
+#[cfg(feature = "rails")]
+pub mod rails;
+
+#[cfg(feature = "django")]
+pub mod django;
+
+#[cfg(any(feature = "sqlx_postgres", feature = "sqlx_sqlite", feature = "libsql"))]
+mod sql;
+#[cfg(any(feature = "sqlx_postgres", feature = "sqlx_sqlite", feature = "libsql"))]
+pub mod sql_index;
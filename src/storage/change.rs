@@ -0,0 +1,78 @@
+//! Notifications of session changes
+
+use std::pin::Pin;
+
+use rocket::{
+    futures::{stream, Stream},
+    tokio::sync::broadcast::{self, error::RecvError},
+};
+
+/// A change to one of the sessions belonging to an identifier. See
+/// [`SessionStorageIndexed::subscribe_identifier`](super::SessionStorageIndexed::subscribe_identifier).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SessionChange {
+    /// The session with this ID was created or updated
+    Saved(String),
+    /// The session with this ID was deleted or invalidated
+    Deleted(String),
+}
+
+/// Stream of changes to the sessions of an identifier
+pub type SessionChangeStream = Pin<Box<dyn Stream<Item = SessionChange> + Send>>;
+
+impl SessionChange {
+    /// The ID of the changed session
+    pub fn session_id(&self) -> &str {
+        match self {
+            Self::Saved(id) | Self::Deleted(id) => id,
+        }
+    }
+
+    /// Encode the change as a message for a pub/sub channel, e.g. `saved:<session_id>`
+    #[cfg(any(feature = "redis_fred", feature = "sqlx_postgres"))]
+    pub(crate) fn encode(&self) -> String {
+        match self {
+            Self::Saved(id) => format!("saved:{id}"),
+            Self::Deleted(id) => format!("deleted:{id}"),
+        }
+    }
+
+    /// Decode a change from a pub/sub message
+    #[cfg(any(feature = "redis_fred", feature = "sqlx_postgres"))]
+    pub(crate) fn decode(message: &str) -> Option<Self> {
+        match message.split_once(':')? {
+            ("saved", id) => Some(Self::Saved(id.to_owned())),
+            ("deleted", id) => Some(Self::Deleted(id.to_owned())),
+            _ => None,
+        }
+    }
+}
+
+/// Stream the messages of a broadcast receiver that can be converted to a change, skipping
+/// any messages missed by a lagging receiver. The `state` is kept alive along with the stream.
+pub(crate) fn broadcast_stream<M, S>(
+    receiver: broadcast::Receiver<M>,
+    state: S,
+    to_change: impl Fn(M) -> Option<SessionChange> + Send + 'static,
+) -> SessionChangeStream
+where
+    M: Clone + Send + 'static,
+    S: Send + 'static,
+{
+    Box::pin(stream::unfold(
+        (receiver, state, to_change),
+        |(mut receiver, state, to_change)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => {
+                        if let Some(change) = to_change(message) {
+                            return Some((change, (receiver, state, to_change)));
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    ))
+}
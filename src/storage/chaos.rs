@@ -0,0 +1,295 @@
+//! Fault injection for testing
+//!
+//! [`ChaosLayer`] wraps a storage provider and randomly injects latency, errors, and reordered
+//! writes into its session operations, so you can verify how your app behaves when the session
+//! store is slow or unreliable (e.g. that users see a helpful error page instead of being logged
+//! out). Enable the `test-util` feature (e.g. in your dev-dependencies) to use this module.
+//!
+//! Only session loads, saves, and deletions are affected - other operations are forwarded to the
+//! inner storage unchanged. Injected errors are [`SessionError::Backend`] errors, which
+//! [are transient](SessionError::is_transient). Reordered writes are reported as successful
+//! immediately, and applied to the inner storage after a delay, so a later write to the same
+//! session may be overwritten by an earlier one.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rocket_flex_session::{
+//!     storage::{chaos::ChaosLayer, layer::StorageStack, memory::MemoryStorage},
+//!     RocketFlexSession,
+//! };
+//!
+//! let chaos = ChaosLayer::builder()
+//!     .error_rate(0.1)
+//!     .latency_rate(0.5)
+//!     .max_latency(Duration::from_millis(200))
+//!     .seed(42)
+//!     .build();
+//! let storage = StorageStack::new(MemoryStorage::default())
+//!     .layer(chaos.clone())
+//!     .build();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(storage)
+//!     .build();
+//!
+//! // e.g. after running requests against the app
+//! let stats = chaos.stats();
+//! println!("{} errors injected", stats.errors);
+//! ```
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use bon::Builder;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rocket::tokio::{self, time};
+
+use crate::error::{SessionError, SessionResult};
+
+use super::{
+    forward_storage_impl, layer::StorageLayer, SessionContext, SessionMetadata, SessionStorage,
+    SessionStorageIndexed,
+};
+
+/// Layer that randomly injects faults into session operations. See the [module docs](self).
+/// Clones share the same state, so a clone can be used to read the [stats](ChaosLayer::stats)
+/// or [disable](ChaosLayer::set_enabled) the faults after the storage is created.
+#[derive(Builder, Clone, Debug)]
+pub struct ChaosLayer {
+    /// Probability of failing an operation, from `0.0` to `1.0` (default: `0.0`)
+    #[builder(default)]
+    error_rate: f64,
+    /// Probability of delaying an operation, from `0.0` to `1.0` (default: `0.0`)
+    #[builder(default)]
+    latency_rate: f64,
+    /// Maximum delay of an operation - each delay is random up to this duration (default: 100ms)
+    #[builder(default = Duration::from_millis(100))]
+    max_latency: Duration,
+    /// Probability of deferring a write, so later writes can overtake it, from `0.0` to `1.0`
+    /// (default: `0.0`)
+    #[builder(default)]
+    reorder_rate: f64,
+    /// How long deferred writes are held before they're applied (default: 50ms)
+    #[builder(default = Duration::from_millis(50))]
+    reorder_delay: Duration,
+    /// Seed for the random number generator, to make the injected faults repeatable
+    seed: Option<u64>,
+    #[builder(skip)]
+    state: Arc<ChaosState>,
+}
+
+impl ChaosLayer {
+    /// Get the number of faults injected so far
+    pub fn stats(&self) -> ChaosStats {
+        ChaosStats {
+            errors: self.state.errors.load(Ordering::Relaxed),
+            delays: self.state.delays.load(Ordering::Relaxed),
+            reorders: self.state.reorders.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Enable or disable the injected faults, e.g. to simulate an outage during part of a test
+    /// (default: enabled)
+    pub fn set_enabled(&self, enabled: bool) {
+        self.state.disabled.store(!enabled, Ordering::Relaxed);
+    }
+}
+
+/// Number of faults injected by a [`ChaosLayer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChaosStats {
+    /// Number of operations that failed with an injected error
+    pub errors: u64,
+    /// Number of operations that were delayed
+    pub delays: u64,
+    /// Number of writes that were deferred
+    pub reorders: u64,
+}
+
+#[derive(Debug, Default)]
+struct ChaosState {
+    disabled: AtomicBool,
+    errors: AtomicU64,
+    delays: AtomicU64,
+    reorders: AtomicU64,
+}
+
+impl<S> StorageLayer<S> for ChaosLayer {
+    type Storage = ChaosStorage<S>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        let rng = match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        ChaosStorage {
+            inner: Arc::new(inner),
+            rng: Mutex::new(rng),
+            config: self,
+        }
+    }
+}
+
+/// Storage wrapped by a [`ChaosLayer`]
+pub struct ChaosStorage<S> {
+    inner: Arc<S>,
+    rng: Mutex<StdRng>,
+    config: ChaosLayer,
+}
+
+impl<S> ChaosStorage<S> {
+    /// Randomly decide whether to inject a fault with the given probability
+    fn roll(&self, rate: f64) -> bool {
+        if rate <= 0.0 || self.config.state.disabled.load(Ordering::Relaxed) {
+            return false;
+        }
+        self.rng.lock().unwrap().random_bool(rate.min(1.0))
+    }
+
+    /// Inject a random delay and/or an error before an operation
+    async fn disrupt(&self) -> SessionResult<()> {
+        if self.roll(self.config.latency_rate) {
+            let factor: f64 = self.rng.lock().unwrap().random();
+            self.config.state.delays.fetch_add(1, Ordering::Relaxed);
+            time::sleep(self.config.max_latency.mul_f64(factor)).await;
+        }
+        if self.roll(self.config.error_rate) {
+            self.config.state.errors.fetch_add(1, Ordering::Relaxed);
+            let error = std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "fault injected by ChaosStorage",
+            );
+            return Err(SessionError::Backend(Box::new(error)));
+        }
+        Ok(())
+    }
+
+    /// Randomly decide whether to defer a write
+    fn should_defer(&self) -> bool {
+        let defer = self.roll(self.config.reorder_rate);
+        if defer {
+            self.config.state.reorders.fetch_add(1, Ordering::Relaxed);
+        }
+        defer
+    }
+
+    /// Apply a deferred write in the background, after the reorder delay
+    fn spawn_deferred(&self, write: impl Future<Output = SessionResult<()>> + Send + 'static) {
+        let delay = self.config.reorder_delay;
+        tokio::spawn(async move {
+            time::sleep(delay).await;
+            if let Err(e) = write.await {
+                rocket::debug!("Deferred session write failed: {e}");
+            }
+        });
+    }
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for ChaosStorage<S>
+    where
+        T: Send + Sync + 'static,
+        S: SessionStorage<T> + 'static,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            self.disrupt().await?;
+            self.inner.load(id, ttl, cookie_jar).await
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            self.disrupt().await?;
+            self.inner.load_with_metadata(id, ttl, cookie_jar).await
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.disrupt().await?;
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            self.disrupt().await?;
+            if self.should_defer() {
+                let (inner, id) = (self.inner.clone(), id.to_owned());
+                self.spawn_deferred(async move { inner.save(&id, data, ttl).await });
+                return Ok(());
+            }
+            self.inner.save(id, data, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+                .await
+        }
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            self.disrupt().await?;
+            if self.should_defer() {
+                let (inner, id) = (self.inner.clone(), id.to_owned());
+                self.spawn_deferred(async move {
+                    inner.save_with_metadata(&id, data, ttl, metadata).await
+                });
+                return Ok(());
+            }
+            self.inner.save_with_metadata(id, data, ttl, metadata).await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.disrupt().await?;
+            if self.should_defer() {
+                let (inner, id) = (self.inner.clone(), id.to_owned());
+                self.spawn_deferred(async move { inner.delete(&id, data).await });
+                return Ok(());
+            }
+            self.inner.delete(id, data).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.disrupt().await?;
+            self.inner.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Payload compression for storage providers
+//!
+//! Large sessions (e.g. JSON with cached user data) can take up a lot of memory in Redis or
+//! space in a SQL table. Use [`compress`] and [`decompress`] when converting your session data
+//! to the stored value (e.g. in [`SessionRedis`](crate::storage::redis::SessionRedis) or
+//! [`SessionSqlx`](crate::storage::sqlx::SessionSqlx)) to store it compressed.
+//!
+//! Compressed payloads start with a 2-byte header: the byte `0xFF` (which can't appear in UTF-8
+//! text), followed by the algorithm. Payloads without the header are returned unchanged by
+//! [`decompress`], so sessions that were stored before enabling compression can still be loaded.
+//!
+//! | Algorithm | Feature Flag |
+//! |-----------|-------------|
+//! | [Zstandard](https://facebook.github.io/zstd/) | `zstd` |
+//! | [LZ4](https://lz4.org/) | `lz4` |
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(all(feature = "redis_fred", feature = "zstd"))]
+//! # {
+//! use rocket_flex_session::{
+//!     error::SessionError,
+//!     storage::{
+//!         compression::{compress, decompress, Compression},
+//!         redis::{RedisFormat, RedisValue, SessionRedis},
+//!     },
+//!     SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone)]
+//! struct MySession {
+//!     user_id: String,
+//!     profile_json: String,
+//! }
+//!
+//! impl SessionIdentifier for MySession {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! impl SessionRedis for MySession {
+//!     const REDIS_FORMAT: RedisFormat = RedisFormat::Bytes;
+//!     type Error = SessionError;
+//!
+//!     fn into_redis(self) -> Result<RedisValue, Self::Error> {
+//!         let value = format!("{}\n{}", self.user_id, self.profile_json);
+//!         let compressed = compress(value.as_bytes(), Compression::Zstd { level: 3 })?;
+//!         Ok(RedisValue::Bytes(compressed))
+//!     }
+//!
+//!     fn from_redis(value: RedisValue) -> Result<Self, Self::Error> {
+//!         let bytes = value.into_bytes().map_err(|_| SessionError::InvalidData)?;
+//!         let value = String::from_utf8(decompress(&bytes)?.into_owned())
+//!             .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+//!         let (user_id, profile_json) = value.split_once('\n').ok_or(SessionError::InvalidData)?;
+//!         Ok(Self {
+//!             user_id: user_id.to_owned(),
+//!             profile_json: profile_json.to_owned(),
+//!         })
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::borrow::Cow;
+
+use crate::error::{SessionError, SessionResult};
+
+/// First byte of the header of compressed payloads
+const MAGIC_BYTE: u8 = 0xFF;
+#[cfg(feature = "zstd")]
+const ZSTD_TAG: u8 = b'z';
+#[cfg(feature = "lz4")]
+const LZ4_TAG: u8 = b'4';
+
+/// Compression algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Zstandard, with the given compression level (1 - 22, where 3 is the zstd default)
+    #[cfg(feature = "zstd")]
+    Zstd {
+        /// Compression level
+        level: i32,
+    },
+    /// LZ4, which is faster but compresses less than Zstandard
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+/// Compress the payload with the given algorithm, and add the compression header
+pub fn compress(payload: &[u8], compression: Compression) -> SessionResult<Vec<u8>> {
+    let (tag, compressed) = match compression {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd { level } => (
+            ZSTD_TAG,
+            zstd::bulk::compress(payload, level)
+                .map_err(|e| SessionError::Serialization(Box::new(e)))?,
+        ),
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => (LZ4_TAG, lz4_flex::compress_prepend_size(payload)),
+    };
+    let mut value = Vec::with_capacity(compressed.len() + 2);
+    value.extend_from_slice(&[MAGIC_BYTE, tag]);
+    value.extend_from_slice(&compressed);
+    Ok(value)
+}
+
+/// Decompress a payload that was compressed with [`compress`]. Payloads without
+/// the compression header are returned unchanged.
+pub fn decompress(value: &[u8]) -> SessionResult<Cow<'_, [u8]>> {
+    let [MAGIC_BYTE, tag, compressed @ ..] = value else {
+        return Ok(Cow::Borrowed(value));
+    };
+    match *tag {
+        #[cfg(feature = "zstd")]
+        ZSTD_TAG => {
+            let mut payload = Vec::new();
+            zstd::stream::copy_decode(compressed, &mut payload)
+                .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+            Ok(Cow::Owned(payload))
+        }
+        #[cfg(feature = "lz4")]
+        LZ4_TAG => lz4_flex::decompress_size_prepended(compressed)
+            .map(Cow::Owned)
+            .map_err(|e| SessionError::Parsing(Box::new(e))),
+        _ => Err(SessionError::Unsupported(
+            "compression algorithm of the payload",
+        )),
+    }
+}
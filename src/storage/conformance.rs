@@ -0,0 +1,401 @@
+//! Conformance test suite for storage providers
+//!
+//! If you're writing your own storage provider, you can run these tests against it to verify
+//! that it follows the contract of the [`SessionStorage`] and [`SessionStorageIndexed`] traits.
+//! Each test runs a number of cases with randomly generated session IDs and TTLs, and panics
+//! with a descriptive message on failure. Enable the `test-util` feature (e.g. in your
+//! dev-dependencies) to use this module.
+//!
+//! The tests assume that sessions are persisted by [`SessionStorage::save`], so they don't apply
+//! to cookie-based providers that only save sessions in [`SessionStorage::save_cookie`].
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     storage::{conformance, memory::MemoryStorageIndexed},
+//!     SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct MySession {
+//!     user_id: String,
+//!     counter: usize,
+//! }
+//!
+//! impl SessionIdentifier for MySession {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! rocket::async_test(async {
+//!     let storage = MemoryStorageIndexed::<MySession>::default();
+//!     conformance::run_storage_tests(&storage, |i| MySession {
+//!         user_id: "user".to_owned(),
+//!         counter: i,
+//!     })
+//!     .await;
+//!     conformance::run_indexed_storage_tests(
+//!         &storage,
+//!         |user_id, i| MySession {
+//!             user_id: user_id.clone(),
+//!             counter: i,
+//!         },
+//!         ["alice".to_owned(), "bob".to_owned()],
+//!     )
+//!     .await;
+//! });
+//! ```
+
+use std::fmt::Debug;
+
+use rand::{
+    distr::{Alphanumeric, SampleString},
+    Rng,
+};
+use rocket::tokio::time::sleep;
+
+use crate::SessionIdentifier;
+
+use super::{NoopSessionContext, SessionStorage, SessionStorageIndexed};
+
+/// Number of random cases to run in each test
+const CASES: usize = 16;
+
+/// Allowed difference (in seconds) between the expected and returned TTL, to allow for slower storages
+const TTL_TOLERANCE: u32 = 5;
+
+/// Run all tests for the [`SessionStorage`] trait. The `sample` function should
+/// return different session data for each number.
+pub async fn run_storage_tests<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    test_load_missing(storage).await;
+    test_save_and_load(storage, &sample).await;
+    test_load_with_ttl(storage, &sample).await;
+    test_overwrite(storage, &sample).await;
+    test_delete(storage, &sample).await;
+    test_exists(storage, &sample).await;
+    test_expiration(storage, &sample).await;
+}
+
+/// Run all tests for the [`SessionStorageIndexed`] trait. The `sample` function should return
+/// session data with the given identifier, and different data for each number. Sessions
+/// belonging to the two given identifiers will be invalidated during the tests.
+pub async fn run_indexed_storage_tests<T, S>(
+    storage: &S,
+    sample: impl Fn(&T::Id, usize) -> T,
+    identifiers: [T::Id; 2],
+) where
+    T: SessionIdentifier + PartialEq + Debug,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    test_index_by_identifier(storage, &sample, &identifiers).await;
+    test_invalidate_by_identifier(storage, &sample, &identifiers).await;
+}
+
+/// Loading a session that doesn't exist should fail
+pub async fn test_load_missing<T, S>(storage: &S)
+where
+    T: Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for _ in 0..CASES {
+        let id = random_id();
+        let result = storage.load(&id, None, &NoopSessionContext).await;
+        assert!(
+            result.is_err(),
+            "loading missing session '{id}' should fail"
+        );
+    }
+}
+
+/// A saved session should be loaded with the same data and TTL
+pub async fn test_save_and_load<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for i in 0..CASES {
+        let (id, ttl, data) = (random_id(), random_ttl(), sample(i));
+        storage.save(&id, data.clone(), ttl).await.unwrap();
+
+        let (loaded, loaded_ttl) = storage
+            .load(&id, None, &NoopSessionContext)
+            .await
+            .unwrap_or_else(|e| panic!("should load saved session '{id}': {e}"));
+        assert_eq!(loaded, data, "loaded data of session '{id}'");
+        assert_ttl(loaded_ttl, ttl, &id);
+    }
+}
+
+/// Loading a session with a TTL should update the session's TTL
+pub async fn test_load_with_ttl<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for i in 0..CASES {
+        let (id, ttl, new_ttl, data) = (random_id(), random_ttl(), random_ttl(), sample(i));
+        storage.save(&id, data.clone(), ttl).await.unwrap();
+
+        let (loaded, loaded_ttl) = storage
+            .load(&id, Some(new_ttl), &NoopSessionContext)
+            .await
+            .unwrap_or_else(|e| panic!("should load saved session '{id}': {e}"));
+        assert_eq!(loaded, data, "loaded data of session '{id}'");
+        assert_ttl(loaded_ttl, new_ttl, &id);
+
+        let (_, reloaded_ttl) = storage
+            .load(&id, None, &NoopSessionContext)
+            .await
+            .unwrap_or_else(|e| panic!("should reload session '{id}': {e}"));
+        assert_ttl(reloaded_ttl, new_ttl, &id);
+    }
+}
+
+/// Saving an existing session should replace its data and TTL
+pub async fn test_overwrite<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for i in 0..CASES {
+        let (id, new_ttl, new_data) = (random_id(), random_ttl(), sample(i + 1));
+        storage.save(&id, sample(i), random_ttl()).await.unwrap();
+        storage.save(&id, new_data.clone(), new_ttl).await.unwrap();
+
+        let (loaded, loaded_ttl) = storage
+            .load(&id, None, &NoopSessionContext)
+            .await
+            .unwrap_or_else(|e| panic!("should load saved session '{id}': {e}"));
+        assert_eq!(
+            loaded, new_data,
+            "loaded data of overwritten session '{id}'"
+        );
+        assert_ttl(loaded_ttl, new_ttl, &id);
+    }
+}
+
+/// A deleted session should no longer be loaded, without affecting other sessions
+pub async fn test_delete<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for i in 0..CASES {
+        let (id, other_id, data) = (random_id(), random_id(), sample(i));
+        storage.save(&id, data.clone(), random_ttl()).await.unwrap();
+        storage
+            .save(&other_id, sample(i + 1), random_ttl())
+            .await
+            .unwrap();
+
+        storage.delete(&id, data).await.unwrap();
+        let result = storage.load(&id, None, &NoopSessionContext).await;
+        assert!(
+            result.is_err(),
+            "loading deleted session '{id}' should fail"
+        );
+
+        let result = storage.load(&other_id, None, &NoopSessionContext).await;
+        assert!(result.is_ok(), "other session '{other_id}' should remain");
+    }
+}
+
+/// Only saved sessions should exist
+pub async fn test_exists<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    for i in 0..CASES {
+        let (id, data) = (random_id(), sample(i));
+        let id_ref = &id;
+        let exists = || async move {
+            storage
+                .exists(id_ref, &NoopSessionContext)
+                .await
+                .unwrap_or_else(|e| panic!("should check if session '{id_ref}' exists: {e}"))
+        };
+        assert!(!exists().await, "missing session '{id}' shouldn't exist");
+
+        storage.save(&id, data.clone(), random_ttl()).await.unwrap();
+        assert!(exists().await, "saved session '{id}' should exist");
+
+        storage.delete(&id, data).await.unwrap();
+        assert!(!exists().await, "deleted session '{id}' shouldn't exist");
+    }
+}
+
+/// A session should no longer be loaded after its TTL has passed. This test takes a couple of seconds.
+pub async fn test_expiration<T, S>(storage: &S, sample: impl Fn(usize) -> T)
+where
+    T: PartialEq + Debug + Clone + Send + Sync,
+    S: SessionStorage<T> + ?Sized,
+{
+    let ids: Vec<_> = (0..CASES).map(|_| random_id()).collect();
+    for (i, id) in ids.iter().enumerate() {
+        storage.save(id, sample(i), 1).await.unwrap();
+    }
+
+    sleep(std::time::Duration::from_millis(2100)).await;
+    for id in &ids {
+        let result = storage.load(id, None, &NoopSessionContext).await;
+        assert!(
+            result.is_err(),
+            "loading expired session '{id}' should fail"
+        );
+    }
+}
+
+/// Sessions should be retrievable by their identifier
+pub async fn test_index_by_identifier<T, S>(
+    storage: &S,
+    sample: impl Fn(&T::Id, usize) -> T,
+    identifiers: &[T::Id; 2],
+) where
+    T: SessionIdentifier + PartialEq + Debug,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    clear_identifiers(storage, identifiers).await;
+
+    let mut expected: [Vec<(String, T, u32)>; 2] = Default::default();
+    for i in 0..CASES {
+        let (id, ttl, data) = (random_id(), random_ttl(), sample(&identifiers[i % 2], i));
+        storage.save(&id, data.clone(), ttl).await.unwrap();
+        expected[i % 2].push((id, data, ttl));
+    }
+
+    for (identifier, expected) in identifiers.iter().zip(expected) {
+        let mut ids = storage
+            .get_session_ids_by_identifier(identifier)
+            .await
+            .unwrap();
+        ids.sort();
+        let mut expected_ids: Vec<_> = expected.iter().map(|(id, ..)| id.clone()).collect();
+        expected_ids.sort();
+        assert_eq!(ids, expected_ids, "session IDs by identifier");
+
+        let sessions = storage
+            .get_sessions_by_identifier(identifier)
+            .await
+            .unwrap();
+        assert_eq!(sessions.len(), expected.len(), "sessions by identifier");
+        for (id, data, ttl) in expected {
+            let (_, loaded, loaded_ttl) = sessions
+                .iter()
+                .find(|(session_id, ..)| *session_id == id)
+                .unwrap_or_else(|| panic!("session '{id}' should be found by identifier"));
+            assert_eq!(*loaded, data, "data of session '{id}' by identifier");
+            assert_ttl(*loaded_ttl, ttl, &id);
+        }
+    }
+
+    clear_identifiers(storage, identifiers).await;
+}
+
+/// Invalidating sessions by identifier should only remove the sessions of that identifier,
+/// except for the excluded session
+pub async fn test_invalidate_by_identifier<T, S>(
+    storage: &S,
+    sample: impl Fn(&T::Id, usize) -> T,
+    identifiers: &[T::Id; 2],
+) where
+    T: SessionIdentifier + PartialEq + Debug,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    clear_identifiers(storage, identifiers).await;
+
+    let mut ids: [Vec<String>; 2] = Default::default();
+    for i in 0..CASES {
+        let id = random_id();
+        let data = sample(&identifiers[i % 2], i);
+        storage.save(&id, data, random_ttl()).await.unwrap();
+        ids[i % 2].push(id);
+    }
+
+    let [invalidated_ids, other_ids] = ids;
+    let excluded_id = &invalidated_ids[0];
+    let count = storage
+        .invalidate_sessions_by_identifier(&identifiers[0], Some(excluded_id))
+        .await
+        .unwrap();
+    assert_eq!(
+        count,
+        invalidated_ids.len() as u64 - 1,
+        "number of invalidated sessions"
+    );
+    for id in &invalidated_ids[1..] {
+        let result = storage.load(id, None, &NoopSessionContext).await;
+        assert!(result.is_err(), "invalidated session '{id}' should fail");
+    }
+    let result = storage.load(excluded_id, None, &NoopSessionContext).await;
+    assert!(
+        result.is_ok(),
+        "excluded session '{excluded_id}' should remain"
+    );
+    assert_eq!(
+        storage
+            .get_session_ids_by_identifier(&identifiers[0])
+            .await
+            .unwrap(),
+        vec![excluded_id.clone()],
+        "session IDs after invalidation"
+    );
+    for id in &other_ids {
+        let result = storage.load(id, None, &NoopSessionContext).await;
+        assert!(
+            result.is_ok(),
+            "session '{id}' of other identifier should remain"
+        );
+    }
+
+    let count = storage
+        .invalidate_sessions_by_identifier(&identifiers[1], None)
+        .await
+        .unwrap();
+    assert_eq!(
+        count,
+        other_ids.len() as u64,
+        "number of invalidated sessions"
+    );
+
+    clear_identifiers(storage, identifiers).await;
+}
+
+/// Invalidate any sessions left over from previous tests
+async fn clear_identifiers<T, S>(storage: &S, identifiers: &[T::Id; 2])
+where
+    T: SessionIdentifier,
+    S: SessionStorageIndexed<T> + ?Sized,
+{
+    for identifier in identifiers {
+        storage
+            .invalidate_sessions_by_identifier(identifier, None)
+            .await
+            .unwrap();
+    }
+}
+
+/// Assert that the TTL returned by the storage matches the expected TTL
+fn assert_ttl(ttl: u32, expected: u32, id: &str) {
+    assert!(
+        ttl <= expected && ttl + TTL_TOLERANCE >= expected,
+        "TTL of session '{id}' should be close to {expected}, got {ttl}"
+    );
+}
+
+/// Random session ID with a length between 8 and 64
+fn random_id() -> String {
+    let len = rand::rng().random_range(8..=64);
+    Alphanumeric.sample_string(&mut rand::rng(), len)
+}
+
+/// Random TTL between 1 minute and 1 week
+fn random_ttl() -> u32 {
+    rand::rng().random_range(60..=7 * 24 * 60 * 60)
+}
@@ -0,0 +1,64 @@
+//! Request context of storage operations
+
+use rocket::http::{Cookie, CookieJar};
+
+/**
+The request context passed to storage operations, giving access to the cookies of the request.
+
+During a request, this is Rocket's [`CookieJar`]. Most storages ignore it, but cookie-based
+storages read and write the session through it. To use a storage outside of a request (e.g. in
+background jobs or tests), pass a [`NoopSessionContext`] instead of building a Rocket instance to
+get a cookie jar.
+
+# Example
+```rust
+use rocket_flex_session::storage::{memory::MemoryStorage, NoopSessionContext, SessionStorage};
+
+# rocket::async_test(async {
+let storage = MemoryStorage::<String>::default();
+storage.save("session-id", "data".to_owned(), 60).await.unwrap();
+
+let (data, _ttl) = storage.load("session-id", None, &NoopSessionContext).await.unwrap();
+assert_eq!(data, "data");
+# });
+```
+*/
+pub trait SessionContext: Send + Sync {
+    /// Get a private (encrypted) cookie, including a cookie added during this request
+    fn get_private(&self, name: &str) -> Option<Cookie<'static>>;
+
+    /// Add a private (encrypted) cookie
+    fn add_private(&self, cookie: Cookie<'static>);
+
+    /// Remove a private (encrypted) cookie
+    fn remove_private(&self, cookie: Cookie<'static>);
+}
+
+impl SessionContext for CookieJar<'_> {
+    fn get_private(&self, name: &str) -> Option<Cookie<'static>> {
+        CookieJar::get_private(self, name)
+    }
+
+    fn add_private(&self, cookie: Cookie<'static>) {
+        CookieJar::add_private(self, cookie)
+    }
+
+    fn remove_private(&self, cookie: Cookie<'static>) {
+        CookieJar::remove_private(self, cookie)
+    }
+}
+
+/// A context without cookies, for using storages outside of a request. No cookies are found,
+/// and added or removed cookies are discarded, so cookie-based storages can't load sessions.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopSessionContext;
+
+impl SessionContext for NoopSessionContext {
+    fn get_private(&self, _name: &str) -> Option<Cookie<'static>> {
+        None
+    }
+
+    fn add_private(&self, _cookie: Cookie<'static>) {}
+
+    fn remove_private(&self, _cookie: Cookie<'static>) {}
+}
@@ -1,15 +1,24 @@
 //! Cookie-based session storage implementation
 
+use std::sync::Arc;
+
 use rocket::{
     async_trait,
-    http::{Cookie, CookieJar},
+    http::Cookie,
     serde::{de::DeserializeOwned, json::serde_json, Deserialize, Serialize},
     time::{Duration, OffsetDateTime},
 };
 
-use crate::error::{SessionError, SessionResult};
+use crate::{
+    clock::{Clock, SystemClock},
+    error::{SessionError, SessionResult},
+    security::session_id_eq,
+};
 
-use super::interface::SessionStorage;
+use super::{
+    interface::{SessionMetadata, SessionStorage},
+    SessionContext,
+};
 
 /**
 Storage provider for sessions backed by cookies. All session data is serialized to JSON
@@ -39,19 +48,32 @@ let custom_storage = CookieStorage::builder()
     .build();
 ```
 */
-#[derive(Default)]
 pub struct CookieStorage {
     options: CookieStorageOptions,
+    clock: Arc<dyn Clock>,
 }
 impl CookieStorage {
     pub fn builder() -> CookieStorageBuilder {
         CookieStorageBuilder::default()
     }
 }
+impl Default for CookieStorage {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
 
-#[derive(Default)]
 pub struct CookieStorageBuilder {
     options: CookieStorageOptions,
+    clock: Arc<dyn Clock>,
+}
+impl Default for CookieStorageBuilder {
+    fn default() -> Self {
+        Self {
+            options: CookieStorageOptions::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
 }
 impl CookieStorageBuilder {
     /// Set the cookie options via a closure
@@ -63,10 +85,18 @@ impl CookieStorageBuilder {
         self
     }
 
+    /// Set the clock used to calculate the session expiration stored in the cookie.
+    /// The default is the system time.
+    pub fn clock(&mut self, clock: impl Clock + 'static) -> &mut Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
     /// Build the cookie storage provider
     pub fn build(&self) -> CookieStorage {
         CookieStorage {
             options: self.options.clone(),
+            clock: self.clock.clone(),
         }
     }
 }
@@ -111,14 +141,25 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
         let cookie = cookie_jar
             .get_private(&self.options.cookie_name)
             .ok_or(SessionError::NotFound)?;
         let cookie_data = serde_json::from_str::<DeserializedCookieSession<T>>(cookie.value())
             .map_err(|e| SessionError::Serialization(Box::new(e)))?;
-        if cookie_data.id != id || cookie_data.expires <= OffsetDateTime::now_utc() {
+        let now = self.clock.now();
+        if !session_id_eq(&cookie_data.id, id) || cookie_data.expires <= now {
             return Err(SessionError::Expired);
         }
 
@@ -127,16 +168,22 @@ where
                 SerializedCookieSession::<T> {
                     id,
                     data: &cookie_data.data,
-                    expires: OffsetDateTime::now_utc() + Duration::seconds(new_ttl.into()),
+                    expires: now + Duration::seconds(new_ttl.into()),
+                    created: cookie_data.created,
                 },
                 &self.options,
             )?;
             cookie_jar.add_private(new_cookie);
         }
 
+        let remaining_ttl = (cookie_data.expires - now)
+            .whole_seconds()
+            .try_into()
+            .unwrap_or(0);
         Ok((
             cookie_data.data,
-            ttl.unwrap_or((OffsetDateTime::now_utc() - cookie_data.expires).whole_seconds() as u32),
+            ttl.unwrap_or(remaining_ttl),
+            SessionMetadata::new(cookie_data.created),
         ))
     }
 
@@ -145,15 +192,25 @@ where
         id: &str,
         data: Option<&T>,
         ttl: u32,
-        cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<()> {
         if let Some(data) = data {
-            // Save new data on cookie
+            // Save new data on cookie, keeping the creation time of an existing session
+            let now = self.clock.now();
+            let created = cookie_jar
+                .get_private(&self.options.cookie_name)
+                .and_then(|cookie| {
+                    serde_json::from_str::<CookieSessionCreated>(cookie.value()).ok()
+                })
+                .filter(|existing| session_id_eq(&existing.id, id))
+                .and_then(|existing| existing.created)
+                .unwrap_or(now);
             let new_cookie = create_storage_cookie(
                 SerializedCookieSession {
                     id,
                     data,
-                    expires: OffsetDateTime::now_utc() + Duration::seconds(ttl.into()),
+                    expires: now + Duration::seconds(ttl.into()),
+                    created: Some(created),
                 },
                 &self.options,
             )?;
@@ -161,9 +218,12 @@ where
             Ok(())
         } else {
             // Delete cookie
-            cookie_jar.remove_private(
-                Cookie::build(self.options.cookie_name.clone()).path(self.options.path.clone()),
-            );
+            let mut cookie =
+                Cookie::build(self.options.cookie_name.clone()).path(self.options.path.clone());
+            if let Some(domain) = &self.options.domain {
+                cookie = cookie.domain(domain.clone());
+            }
+            cookie_jar.remove_private(cookie.build());
             Ok(())
         }
     }
@@ -184,6 +244,18 @@ struct DeserializedCookieSession<T> {
     pub id: String,
     pub data: T,
     pub expires: OffsetDateTime,
+    /// Creation time of the session (missing in cookies written by older versions)
+    #[serde(default)]
+    pub created: Option<OffsetDateTime>,
+}
+
+/// The ID and creation time of the session in the cookie, without parsing the data
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct CookieSessionCreated {
+    pub id: String,
+    #[serde(default)]
+    pub created: Option<OffsetDateTime>,
 }
 
 /// Represents data saved to the cookie. Structure should match [DeserializedCookieSession] - just
@@ -194,6 +266,7 @@ struct SerializedCookieSession<'a, T> {
     pub id: &'a str,
     pub data: &'a T,
     pub expires: OffsetDateTime,
+    pub created: Option<OffsetDateTime>,
 }
 
 fn create_storage_cookie<'a, T>(
@@ -0,0 +1,106 @@
+//! Read-through support for Django session payloads
+//!
+//! [`DjangoCodec`] decodes the session data written by a Django app (3.1 or later) into your
+//! session data type, for hybrid deployments where Django and Rocket serve the same users during
+//! a migration. This is the value of the `session_data` column of the `django_session` table
+//! (the database session backend), or the value of the signed cookie session backend.
+//!
+//! Session data is signed with Django's `signing` module, in the format
+//! `<base64 JSON>:<timestamp>:<signature>`, where the JSON may be compressed with zlib (shown
+//! by a leading `.`). The signature is verified with the `SECRET_KEY` of the Django app. The
+//! timestamp isn't checked, as the expiration of the session is handled by the storage
+//! provider. Sessions serialized with pickle aren't supported.
+//!
+//! # Example
+//! ```rust
+//! use rocket::serde::Deserialize;
+//! use rocket_flex_session::storage::django::DjangoCodec;
+//!
+//! #[derive(Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct DjangoSession {
+//!     #[serde(rename = "_auth_user_id")]
+//!     user_id: String,
+//! }
+//!
+//! let codec = DjangoCodec::builder().secret_key("django-secret-key").build();
+//! let result = codec.decode::<DjangoSession>("eyJfYXV0aF91c2VyX2lkIjoiMSJ9:1tUvXo:invalid");
+//! assert!(result.is_err());
+//! ```
+
+use std::io::Read;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bon::Builder;
+use hmac::{Hmac, Mac};
+use rocket::serde::{
+    de::DeserializeOwned,
+    json::serde_json::{self, Value},
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::{SessionError, SessionResult};
+
+/// The salt used by Django's session backends
+const DEFAULT_SALT: &str = "django.contrib.sessions.SessionStore";
+
+/// Decodes Django session payloads. See the [module docs](self).
+#[derive(Builder, Clone, Debug)]
+pub struct DjangoCodec {
+    /// The `SECRET_KEY` of the Django app
+    #[builder(into)]
+    secret_key: String,
+    /// The salt of the signature (default: the salt of Django's session backends, `django.contrib.sessions.SessionStore`)
+    #[builder(into, default = DEFAULT_SALT)]
+    salt: String,
+}
+
+impl DjangoCodec {
+    /// Decode the session data into your session data type
+    pub fn decode<T: DeserializeOwned>(&self, session_data: &str) -> SessionResult<T> {
+        let value = self.decode_value(session_data)?;
+        serde_json::from_value(value).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+
+    /// Decode the session data into a JSON value
+    pub fn decode_value(&self, session_data: &str) -> SessionResult<Value> {
+        let (signed, signature) = session_data
+            .rsplit_once(':')
+            .ok_or(SessionError::InvalidData)?;
+        self.verify(signed, signature)?;
+        let (payload, _timestamp) = signed.rsplit_once(':').ok_or(SessionError::InvalidData)?;
+
+        let (payload, compressed) = match payload.strip_prefix('.') {
+            Some(payload) => (payload, true),
+            None => (payload, false),
+        };
+        let mut json = URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+        if compressed {
+            let mut decompressed = Vec::new();
+            flate2::read::ZlibDecoder::new(json.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+            json = decompressed;
+        }
+        serde_json::from_slice(&json).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+
+    /// Verify the signature, which is a salted HMAC-SHA256 of the signed value
+    fn verify(&self, signed: &str, signature: &str) -> SessionResult<()> {
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SessionError::InvalidData)?;
+        let key = Sha256::new()
+            .chain_update(&self.salt)
+            .chain_update("signer")
+            .chain_update(&self.secret_key)
+            .finalize();
+        Hmac::<Sha256>::new_from_slice(&key)
+            .expect("HMAC should accept any key length")
+            .chain_update(signed)
+            .verify_slice(&signature)
+            .map_err(|_| SessionError::InvalidData)
+    }
+}
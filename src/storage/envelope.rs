@@ -0,0 +1,346 @@
+//! Versioned session format for sharing sessions with other services
+//!
+//! Sessions stored in Redis or a SQL database can be read and written by other services (e.g.
+//! written in Node or Go), as long as they agree on where the sessions are stored and how the
+//! data is serialized. This module documents that contract, and provides [`EnvelopeCodec`] to
+//! produce and consume the session data in a stable, versioned format.
+//!
+//! # Envelope format
+//! The stored value is a single version byte, followed by the session data serialized as UTF-8
+//! JSON:
+//!
+//! | Offset | Size | Content |
+//! |--------|------|---------|
+//! | 0 | 1 byte | Envelope version (currently `0x01`) |
+//! | 1 | rest | Session data as UTF-8 JSON |
+//!
+//! Version bytes are in the range `0x01` - `0x08`, which can't start a JSON document, so values
+//! without a version byte (e.g. plain JSON written before adopting the envelope) can be told
+//! apart and are accepted by default. The version byte is valid UTF-8, so the envelope can be
+//! stored as bytes (Redis strings, `bytea` or `BLOB` columns) or as text (`text` or `TEXT`
+//! columns). If you also use [compression](crate::storage::compression), compress the encoded
+//! envelope and decompress it before decoding.
+//!
+//! In another service, check the first byte of the value (`value[0] === 1` in Node or
+//! `value[0] == 1` in Go), and parse the rest as JSON. New versions of the format will use a new
+//! version byte.
+//!
+//! ## Tagged envelope
+//! To change how the data is serialized (e.g. to MessagePack) or to compress it, configure the
+//! codec with a [`format`](EnvelopeCodecBuilder::format) or
+//! [`compression`](EnvelopeCodecBuilder::compression). The data is then stored in a version
+//! `0x02` envelope, which tags each record with the format it was written in:
+//!
+//! | Offset | Size | Content |
+//! |--------|------|---------|
+//! | 0 | 1 byte | Envelope version (`0x02`) |
+//! | 1 | 1 byte | [Format ID](EnvelopeFormat::id) (`0x01` for JSON) |
+//! | 2 | 1 byte | Flags: bit 0 is set if the payload is [compressed](crate::storage::compression) |
+//! | 3 | rest | Session data in the tagged format |
+//!
+//! Records are always written with the current settings, and records in older formats (plain
+//! JSON, version `0x01`, or one of the [`legacy_formats`](EnvelopeCodecBuilder::legacy_formats))
+//! can still be read. So the stored sessions are upgraded as they're saved, and the
+//! [`ErasedSessionStorage`](super::erased::ErasedSessionStorage) also rewrites outdated records
+//! when they're loaded. Use [`EnvelopeCodec::needs_upgrade`] to check a record yourself.
+//!
+//! # Key naming
+//! ## Redis
+//! With the default [`RedisFredStorage`](crate::storage::redis::RedisFredStorage) prefixes:
+//!
+//! | Key | Type | Content |
+//! |-----|------|---------|
+//! | `sess:<session ID>` | string | The session envelope. The key TTL is the session TTL. |
+//! | `sess:user:<identifier>` | set | IDs of the sessions of the identifier (if indexed) |
+//! | `sess:tag:<tag>` | set | IDs of the sessions with the tag (if tagged) |
+//!
+//! ## SQL
+//! The session table has these columns:
+//!
+//! | Column | Content |
+//! |--------|---------|
+//! | `id` | The session ID |
+//! | `data` | The session envelope |
+//! | `expires` | Expiration time of the session (UTC) |
+//! | Index column (e.g. `user_id`) | Identifier of the session (if indexed) |
+//!
+//! Session IDs are 20 random alphanumeric characters. Other services that create sessions must
+//! also set the session cookie, as a private cookie encrypted with Rocket's secret key.
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(feature = "redis_fred")]
+//! # {
+//! use rocket::serde::{Deserialize, Serialize};
+//! use rocket_flex_session::{
+//!     error::SessionError,
+//!     storage::{
+//!         envelope::EnvelopeCodec,
+//!         redis::{RedisFormat, RedisValue, SessionRedis},
+//!     },
+//!     SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct MySession {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for MySession {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! impl SessionRedis for MySession {
+//!     const REDIS_FORMAT: RedisFormat = RedisFormat::Bytes;
+//!     type Error = SessionError;
+//!
+//!     fn into_redis(self) -> Result<RedisValue, Self::Error> {
+//!         Ok(RedisValue::Bytes(EnvelopeCodec::default().encode(&self)?))
+//!     }
+//!
+//!     fn from_redis(value: RedisValue) -> Result<Self, Self::Error> {
+//!         let bytes = value.into_bytes().map_err(|_| SessionError::InvalidData)?;
+//!         EnvelopeCodec::default().decode(&bytes)
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use bon::Builder;
+use rocket::serde::{
+    de::DeserializeOwned,
+    json::{serde_json, Value},
+    Serialize,
+};
+
+use crate::error::{SessionError, SessionResult};
+
+use super::erased::SessionCodec;
+
+/// The version of the plain JSON envelope format
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// The version of the tagged envelope format, which records the format of the data
+pub const TAGGED_ENVELOPE_VERSION: u8 = 2;
+
+/// Format ID of [`JsonFormat`]. IDs `0x00` - `0x7F` are reserved for formats of this crate.
+pub const JSON_FORMAT_ID: u8 = 1;
+
+/// Range of bytes reserved for envelope versions, which can't start a JSON document
+const VERSION_RANGE: std::ops::RangeInclusive<u8> = 0x01..=0x08;
+
+/// Flag of the tagged envelope for compressed payloads
+const COMPRESSED_FLAG: u8 = 0b1;
+
+/// Serialization format of the data in a [tagged envelope](self#tagged-envelope), e.g.
+/// MessagePack or CBOR. The data is converted to and from a JSON [`Value`] by the codec.
+pub trait EnvelopeFormat: std::fmt::Debug + Send + Sync {
+    /// ID of the format, stored in each record. Use an ID from `0x80` to `0xFF` for your own
+    /// formats, and never reuse the ID of a format that may still be stored.
+    fn id(&self) -> u8;
+
+    /// Serialize the data into bytes
+    fn serialize(&self, value: &Value) -> SessionResult<Vec<u8>>;
+
+    /// Deserialize the data from bytes
+    fn deserialize(&self, bytes: &[u8]) -> SessionResult<Value>;
+}
+
+/// The JSON format, which is the default format of the envelope
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonFormat;
+
+impl EnvelopeFormat for JsonFormat {
+    fn id(&self) -> u8 {
+        JSON_FORMAT_ID
+    }
+
+    fn serialize(&self, value: &Value) -> SessionResult<Vec<u8>> {
+        serde_json::to_vec(value).map_err(|e| SessionError::Serialization(Box::new(e)))
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> SessionResult<Value> {
+        serde_json::from_slice(bytes).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+}
+
+/// Encodes and decodes session data in the versioned [envelope format](self).
+#[derive(Builder, Clone, Debug)]
+pub struct EnvelopeCodec {
+    /// Accept values without a version byte when decoding, and parse them as plain JSON
+    /// (default: `true`)
+    #[builder(default = true)]
+    accept_unversioned: bool,
+    /// Serialize the data with this format, in a [tagged envelope](self#tagged-envelope)
+    /// (default: JSON in the plain envelope)
+    #[builder(with = |format: impl EnvelopeFormat + 'static| Arc::new(format))]
+    format: Option<Arc<dyn EnvelopeFormat>>,
+    /// Formats that were previously used to store the data. Records in these formats can still
+    /// be read, and are written in the current format when saved.
+    #[builder(default)]
+    legacy_formats: Vec<Arc<dyn EnvelopeFormat>>,
+    /// Compress the data, in a [tagged envelope](self#tagged-envelope) (default: no compression)
+    #[cfg(any(feature = "zstd", feature = "lz4"))]
+    compression: Option<super::compression::Compression>,
+}
+
+impl Default for EnvelopeCodec {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl EnvelopeCodec {
+    /// Encode the session data into the envelope bytes
+    pub fn encode<T: Serialize>(&self, data: &T) -> SessionResult<Vec<u8>> {
+        let Some(header) = self.tagged_header() else {
+            let mut value = vec![ENVELOPE_VERSION];
+            serde_json::to_writer(&mut value, data)
+                .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+            return Ok(value);
+        };
+
+        let payload = match &self.format {
+            Some(format) => {
+                let value = serde_json::to_value(data)
+                    .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+                format.serialize(&value)?
+            }
+            None => {
+                serde_json::to_vec(data).map_err(|e| SessionError::Serialization(Box::new(e)))?
+            }
+        };
+        #[cfg(any(feature = "zstd", feature = "lz4"))]
+        let payload = match self.compression {
+            Some(compression) => super::compression::compress(&payload, compression)?,
+            None => payload,
+        };
+
+        let mut value = Vec::with_capacity(payload.len() + header.len());
+        value.extend_from_slice(&header);
+        value.extend_from_slice(&payload);
+        Ok(value)
+    }
+
+    /// Encode the session data into the envelope, for storage in a text column. Not supported
+    /// with a custom format or compression, since the tagged envelope isn't valid UTF-8.
+    pub fn encode_str<T: Serialize>(&self, data: &T) -> SessionResult<String> {
+        if self.tagged_header().is_some() {
+            return Err(SessionError::Unsupported(
+                "text encoding of the tagged session envelope",
+            ));
+        }
+        let json =
+            serde_json::to_string(data).map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        let mut value = String::with_capacity(json.len() + 1);
+        value.push(char::from(ENVELOPE_VERSION));
+        value.push_str(&json);
+        Ok(value)
+    }
+
+    /// Decode the session data from the envelope bytes
+    pub fn decode<T: DeserializeOwned>(&self, value: &[u8]) -> SessionResult<T> {
+        let payload = match envelope_version(value) {
+            Some(ENVELOPE_VERSION) => &value[1..],
+            Some(TAGGED_ENVELOPE_VERSION) => return self.decode_tagged(&value[1..]),
+            Some(_) => return Err(SessionError::Unsupported("version of the session envelope")),
+            None if self.accept_unversioned => value,
+            None => return Err(SessionError::InvalidData),
+        };
+        serde_json::from_slice(payload).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+
+    /// Decode the session data from the envelope stored in a text column
+    pub fn decode_str<T: DeserializeOwned>(&self, value: &str) -> SessionResult<T> {
+        self.decode(value.as_bytes())
+    }
+
+    /// Whether a stored value is in an older format than the one this codec writes, and should
+    /// be saved again to upgrade it
+    pub fn needs_upgrade(&self, value: &[u8]) -> bool {
+        match self.tagged_header() {
+            Some(header) => !value.starts_with(&header),
+            None => envelope_version(value) != Some(ENVELOPE_VERSION),
+        }
+    }
+
+    /// Header of the tagged envelope written by this codec, or `None` if it writes the plain
+    /// envelope
+    fn tagged_header(&self) -> Option<[u8; 3]> {
+        #[cfg(any(feature = "zstd", feature = "lz4"))]
+        let flags = match self.compression {
+            Some(_) => COMPRESSED_FLAG,
+            None => 0,
+        };
+        #[cfg(not(any(feature = "zstd", feature = "lz4")))]
+        let flags = 0;
+
+        if self.format.is_none() && flags == 0 {
+            return None;
+        }
+        let format_id = self.format.as_ref().map_or(JSON_FORMAT_ID, |f| f.id());
+        Some([TAGGED_ENVELOPE_VERSION, format_id, flags])
+    }
+
+    /// Decode the data from a tagged envelope, after the version byte
+    fn decode_tagged<T: DeserializeOwned>(&self, value: &[u8]) -> SessionResult<T> {
+        let [format_id, flags, payload @ ..] = value else {
+            return Err(SessionError::InvalidData);
+        };
+        if flags & !COMPRESSED_FLAG != 0 {
+            return Err(SessionError::Unsupported("flags of the session envelope"));
+        }
+
+        let payload = match flags & COMPRESSED_FLAG != 0 {
+            #[cfg(any(feature = "zstd", feature = "lz4"))]
+            true => super::compression::decompress(payload)?,
+            #[cfg(not(any(feature = "zstd", feature = "lz4")))]
+            true => {
+                return Err(SessionError::Unsupported(
+                    "compression of the session envelope",
+                ))
+            }
+            false => std::borrow::Cow::Borrowed(payload),
+        };
+
+        let format = (self.format.iter())
+            .chain(&self.legacy_formats)
+            .find(|format| format.id() == *format_id);
+        match format {
+            Some(format) => serde_json::from_value(format.deserialize(&payload)?)
+                .map_err(|e| SessionError::Parsing(Box::new(e))),
+            None if *format_id == JSON_FORMAT_ID => {
+                serde_json::from_slice(&payload).map_err(|e| SessionError::Parsing(Box::new(e)))
+            }
+            None => Err(SessionError::Unsupported("format of the session envelope")),
+        }
+    }
+}
+
+/// Get the envelope version of a stored value, or `None` if the value doesn't have a version byte
+pub fn envelope_version(value: &[u8]) -> Option<u8> {
+    value
+        .first()
+        .copied()
+        .filter(|byte| VERSION_RANGE.contains(byte))
+}
+
+impl<T: Serialize + DeserializeOwned> SessionCodec<T> for EnvelopeCodec {
+    fn encode(&self, data: &T) -> SessionResult<Vec<u8>> {
+        EnvelopeCodec::encode(self, data)
+    }
+
+    fn decode(&self, value: &[u8]) -> SessionResult<T> {
+        EnvelopeCodec::decode(self, value)
+    }
+
+    fn needs_upgrade(&self, value: &[u8]) -> bool {
+        EnvelopeCodec::needs_upgrade(self, value)
+    }
+}
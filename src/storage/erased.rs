@@ -0,0 +1,290 @@
+//! Type-erased storage shared by multiple session types
+//!
+//! Each fairing needs a storage for its own session data type, so an app with several session
+//! types (e.g. user and admin sessions) would usually create a storage, along with its connection
+//! pool and cleanup task, for each type. An [`ErasedSessionStorage`] instead stores the sessions as
+//! [serialized bytes](ErasedPayload) in one shared storage, and converts them to and from the
+//! session data type with a [`SessionCodec`]. Use [`ErasedSessionStorage::with_codec`] to create the
+//! storage for another session type, backed by the same storage instance.
+//!
+//! Like a [`SharedBackend`], the shared storage is set up when the first fairing using it is
+//! launched, and shut down along with the last one. Session indexing isn't supported, since the identifier of the session data
+//! isn't known to the shared storage.
+//!
+//! The built-in storages that can store the payload are:
+//!
+//! | Storage | Requirements |
+//! |---------|-------------|
+//! | [`MemoryStorage`](super::memory::MemoryStorage) | |
+//! | [`RedisFredStorage`](super::redis::RedisFredStorage) | The payload is stored as a Redis string |
+//! | [`SqlxPostgresStorage`](super::sqlx::SqlxPostgresStorage) | The data column must be `bytea` |
+//! | [`SqlxSqliteStorage`](super::sqlx::SqlxSqliteStorage) | The data column must be `BLOB` |
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(feature = "json")]
+//! # {
+//! use rocket::serde::{Deserialize, Serialize};
+//! use rocket_flex_session::{
+//!     storage::{envelope::EnvelopeCodec, erased::ErasedSessionStorage, memory::MemoryStorage},
+//!     RocketFlexSession,
+//! };
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct UserSession {
+//!     user_id: String,
+//! }
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct AdminSession {
+//!     admin_id: String,
+//! }
+//!
+//! // e.g. a Redis or Postgres storage instead
+//! let user_storage = ErasedSessionStorage::new(MemoryStorage::default(), EnvelopeCodec::default());
+//! let admin_storage = user_storage.with_codec(EnvelopeCodec::default());
+//!
+//! let rocket = rocket::build()
+//!     .attach(RocketFlexSession::<UserSession>::builder().storage(user_storage).build())
+//!     .attach(RocketFlexSession::<AdminSession>::builder().storage(admin_storage).build());
+//! # }
+//! ```
+
+use std::{sync::Arc, time::Duration};
+
+use crate::{error::SessionResult, SessionIdentifier};
+
+use super::{
+    forward_storage_impl, shared::SharedBackend, SessionContext, SessionMetadata, SessionStorage,
+};
+
+/// Converts the session data to and from the bytes stored by an [`ErasedSessionStorage`].
+/// With the `json` feature, [`EnvelopeCodec`](super::envelope::EnvelopeCodec) implements this
+/// for all serializable types.
+pub trait SessionCodec<T>: Send + Sync {
+    /// Encode the session data into bytes
+    fn encode(&self, data: &T) -> SessionResult<Vec<u8>>;
+
+    /// Decode the session data from bytes
+    fn decode(&self, value: &[u8]) -> SessionResult<T>;
+
+    /// Whether the stored bytes are in an outdated format, and should be encoded and saved
+    /// again when loaded (default: `false`)
+    fn needs_upgrade(&self, _value: &[u8]) -> bool {
+        false
+    }
+}
+
+/// Serialized session data, as stored by the shared storage of an [`ErasedSessionStorage`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErasedPayload(pub Vec<u8>);
+
+impl SessionIdentifier for ErasedPayload {
+    type Id = String;
+
+    /// Erased sessions aren't indexed
+    fn identifier(&self) -> Option<Self::Id> {
+        None
+    }
+}
+
+#[cfg(feature = "redis_fred")]
+impl super::redis::SessionRedis for ErasedPayload {
+    const REDIS_FORMAT: super::redis::RedisFormat = super::redis::RedisFormat::Bytes;
+    type Error = crate::error::SessionError;
+
+    fn into_redis(self) -> Result<super::redis::RedisValue, Self::Error> {
+        Ok(super::redis::RedisValue::Bytes(self.0))
+    }
+
+    fn from_redis(value: super::redis::RedisValue) -> Result<Self, Self::Error> {
+        let bytes = value
+            .into_bytes()
+            .map_err(|_| crate::error::SessionError::InvalidData)?;
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(feature = "sqlx_postgres")]
+impl super::sqlx::SessionSqlx<sqlx::Postgres> for ErasedPayload {
+    type Error = std::convert::Infallible;
+    type Data = Vec<u8>;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}
+
+#[cfg(feature = "sqlx_sqlite")]
+impl super::sqlx::SessionSqlx<sqlx::Sqlite> for ErasedPayload {
+    type Error = std::convert::Infallible;
+    type Data = Vec<u8>;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}
+
+/// Storage for a session data type, backed by a storage of serialized bytes that can be shared
+/// with other session types. See the [module docs](self).
+pub struct ErasedSessionStorage<T> {
+    shared: SharedBackend<dyn SessionStorage<ErasedPayload>>,
+    codec: Arc<dyn SessionCodec<T>>,
+}
+
+impl<T> ErasedSessionStorage<T> {
+    /// Create the storage for a session data type, backed by the given storage
+    pub fn new(
+        storage: impl SessionStorage<ErasedPayload> + 'static,
+        codec: impl SessionCodec<T> + 'static,
+    ) -> Self {
+        Self {
+            shared: SharedBackend::new_dyn(storage),
+            codec: Arc::new(codec),
+        }
+    }
+
+    /// Create the storage for another session data type, backed by the same storage
+    pub fn with_codec<U>(&self, codec: impl SessionCodec<U> + 'static) -> ErasedSessionStorage<U> {
+        ErasedSessionStorage {
+            shared: self.shared.clone(),
+            codec: Arc::new(codec),
+        }
+    }
+
+    fn encode(&self, data: &T) -> SessionResult<ErasedPayload> {
+        self.codec.encode(data).map(ErasedPayload)
+    }
+
+    fn decode(&self, payload: &ErasedPayload) -> SessionResult<T> {
+        self.codec.decode(&payload.0)
+    }
+
+    /// Save the session again if it was stored in an outdated format. Failing to upgrade
+    /// doesn't fail the load, since the stored session can still be read.
+    async fn upgrade(&self, id: &str, payload: &ErasedPayload, data: &T, ttl: u32) {
+        if !self.codec.needs_upgrade(&payload.0) {
+            return;
+        }
+        if let Ok(payload) = self.encode(data) {
+            let _ = self.shared.save(id, payload, ttl).await;
+        }
+    }
+}
+
+impl<T> Clone for ErasedSessionStorage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+            codec: self.codec.clone(),
+        }
+    }
+}
+
+forward_storage_impl! {
+    forward(shared): activity, capabilities, once_values, lazy_fields;
+    impl<T> SessionStorage<T> for ErasedSessionStorage<T>
+    where
+        T: Send + Sync + 'static,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            let (payload, ttl) = self.shared.load(id, ttl, cookie_jar).await?;
+            let data = self.decode(&payload)?;
+            self.upgrade(id, &payload, &data, ttl).await;
+            Ok((data, ttl))
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            let (payload, ttl, metadata) =
+                self.shared.load_with_metadata(id, ttl, cookie_jar).await?;
+            let data = self.decode(&payload)?;
+            self.upgrade(id, &payload, &data, ttl).await;
+            Ok((data, ttl, metadata))
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.shared.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            let payload = self.encode(&data)?;
+            self.shared.save(id, payload, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            let payload = self.encode(&data)?;
+            self.shared.save_with_duration(id, payload, ttl).await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            let payload = self.encode(&data)?;
+            self.shared
+                .save_with_metadata(id, payload, ttl, metadata)
+                .await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            let payload = self.encode(&data)?;
+            self.shared.delete(id, payload).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.shared.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            let payload = data.map(|data| self.encode(data)).transpose()?;
+            self.shared
+                .save_cookie(id, payload.as_ref(), ttl, cookie_jar)
+        }
+
+        async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+            // Sessions of other types in the shared storage fail to decode, and are skipped
+            let sessions = self.shared.recently_active(limit).await?;
+            Ok(sessions
+                .into_iter()
+                .filter_map(|(id, payload, ttl)| Some((id, self.decode(&payload).ok()?, ttl)))
+                .collect())
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.shared.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.shared.shutdown().await
+        }
+    }
+}
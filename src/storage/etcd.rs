@@ -0,0 +1,336 @@
+//! Session storage via etcd
+
+use bon::Builder;
+use etcd_client::{Client, DeleteOptions, GetOptions, PutOptions, Txn, TxnOp, TxnOpResponse};
+use rocket::async_trait;
+
+use crate::{
+    error::{SessionError, SessionResult},
+    security::session_id_eq,
+    storage::{SessionContext, SessionStorage, SessionStorageIndexed},
+    SessionIdentifier,
+};
+
+/// Max number of sessions to delete in one transaction (etcd allows 128 operations per transaction by default)
+const MAX_TXN_SESSIONS: usize = 64;
+
+/**
+Trait for session data types that can be stored in etcd.
+
+# Example
+
+```
+use rocket_flex_session::storage::etcd::SessionEtcd;
+use rocket_flex_session::SessionIdentifier;
+
+#[derive(Clone)]
+struct SessionData {
+    user_id: String,
+}
+
+impl SessionIdentifier for SessionData {
+    type Id = String; // must be a string for etcd storage
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+impl SessionEtcd for SessionData {
+    type Error = std::string::FromUtf8Error;
+
+    fn into_etcd(self) -> Result<Vec<u8>, Self::Error> {
+        Ok(self.user_id.into_bytes())
+    }
+
+    fn from_etcd(value: Vec<u8>) -> Result<Self, Self::Error> {
+        Ok(SessionData { user_id: String::from_utf8(value)? })
+    }
+}
+```
+*/
+pub trait SessionEtcd: SessionIdentifier + Sized {
+    /// The error that can occur when converting to/from the stored value.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Convert to the value that will be stored in etcd.
+    fn into_etcd(self) -> Result<Vec<u8>, Self::Error>;
+
+    /// Convert from the value stored in etcd.
+    fn from_etcd(value: Vec<u8>) -> Result<Self, Self::Error>;
+}
+
+/// Session storage using [etcd](https://etcd.io/) via the [etcd-client](https://docs.rs/crate/etcd-client) crate.
+///
+/// # Requirements
+/// - You must pass in a connected etcd client.
+/// - Your session data type must implement [`SessionEtcd`] to configure how to convert & store session data.
+/// - Your session data type must implement [`SessionIdentifier`]. The [Id](`SessionIdentifier::Id`) type must be a string.
+///
+/// # Storage
+/// ## Session keys and expiration
+/// Sessions are stored with a key of `<prefix><id>` (e.g.: `sess:abcdef...`). Each time a session is
+/// saved, a new etcd lease is granted with the session's TTL and attached to its keys, so that etcd
+/// deletes expired sessions automatically. The previous lease of the session is then revoked, so
+/// that leases don't accumulate until they expire.
+///
+/// ## Indexing sessions
+/// Sessions are indexed with the identifier retrieved from your [`SessionIdentifier`] implementation.
+/// An empty key of `<index_prefix><identifier>/<id>` (e.g.: `sess:user:1/abcdef...`) is stored under the
+/// same lease as the session, and sessions are found with a prefix-range scan.
+///
+/// # Example
+/// ```
+/// use rocket_flex_session::storage::etcd::EtcdStorage;
+///
+/// async fn create_storage() -> EtcdStorage {
+///     let client = etcd_client::Client::connect(["localhost:2379"], None).await.unwrap();
+///     EtcdStorage::builder()
+///         .client(client)
+///         .prefix("sess:")
+///         .build()
+/// }
+/// ```
+#[derive(Builder)]
+pub struct EtcdStorage {
+    /// The connected etcd client.
+    client: Client,
+    /// The prefix to use for session keys.
+    #[builder(into, default = "sess:")]
+    prefix: String,
+    /// The prefix to use for session index keys (e.g. to group sessions by user ID)
+    #[builder(into, default = "sess:user:")]
+    index_prefix: String,
+}
+
+impl EtcdStorage {
+    fn session_key(&self, id: &str) -> String {
+        format!("{}{id}", self.prefix)
+    }
+
+    fn session_index_prefix(&self, identifier: &str) -> String {
+        format!("{}{identifier}/", self.index_prefix)
+    }
+
+    fn session_index_key(&self, identifier: &str, id: &str) -> String {
+        format!("{}{id}", self.session_index_prefix(identifier))
+    }
+
+    /// Store the session data and index key under a new lease, and revoke the previous lease
+    /// of the session. Revoking the previous lease also deletes a stale index key, if the
+    /// identifier of the session changed.
+    async fn put_session(
+        &self,
+        id: &str,
+        value: Vec<u8>,
+        identifier: Option<&str>,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let mut client = self.client.clone();
+        let lease = client.lease_grant(ttl.into(), None).await?;
+        let options = PutOptions::new().with_lease(lease.id());
+
+        let mut operations = vec![TxnOp::put(
+            self.session_key(id),
+            value,
+            Some(options.clone().with_prev_key()),
+        )];
+        if let Some(identifier) = identifier {
+            operations.push(TxnOp::put(
+                self.session_index_key(identifier, id),
+                "",
+                Some(options),
+            ));
+        }
+        let response = client.txn(Txn::new().and_then(operations)).await?;
+
+        let prev_lease = response.op_responses().into_iter().find_map(|op| match op {
+            TxnOpResponse::Put(put) => put.prev_key().map(|kv| kv.lease()),
+            _ => None,
+        });
+        if let Some(prev_lease) = prev_lease.filter(|&l| l != 0 && l != lease.id()) {
+            // The previous lease may have already expired
+            if let Err(e) = client.lease_revoke(prev_lease).await {
+                rocket::debug!("Couldn't revoke previous lease of session: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load the raw session data and remaining TTL
+    async fn get_session(&self, id: &str) -> SessionResult<(Vec<u8>, u32)> {
+        let mut client = self.client.clone();
+        let response = client.get(self.session_key(id), None).await?;
+        let kv = response.kvs().first().ok_or(SessionError::NotFound)?;
+        let lease = client.lease_time_to_live(kv.lease(), None).await?;
+        let ttl: u32 = lease.ttl().try_into().unwrap_or(0);
+        if ttl == 0 {
+            return Err(SessionError::Expired);
+        }
+
+        Ok((kv.value().to_vec(), ttl))
+    }
+
+    async fn fetch_session_ids(&self, identifier: &str) -> SessionResult<Vec<String>> {
+        let index_prefix = self.session_index_prefix(identifier);
+        let options = GetOptions::new().with_prefix().with_keys_only();
+        let response = self
+            .client
+            .clone()
+            .get(index_prefix.as_str(), Some(options))
+            .await?;
+
+        let session_ids = response
+            .kvs()
+            .iter()
+            .filter_map(|kv| kv.key_str().ok()?.strip_prefix(&index_prefix))
+            .map(|id| id.to_owned())
+            .collect();
+        Ok(session_ids)
+    }
+}
+
+#[async_trait]
+impl<T> SessionStorage<T> for EtcdStorage
+where
+    T: SessionEtcd,
+    <T as SessionIdentifier>::Id: AsRef<str>,
+{
+    fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+        Some(self)
+    }
+
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let (value, orig_ttl) = self.get_session(id).await?;
+        let data = T::from_etcd(value.clone()).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+
+        match ttl {
+            Some(new_ttl) => {
+                // Leases can't be shortened or extended to a different TTL, so re-attach the keys to a new lease
+                let identifier = data.identifier();
+                self.put_session(id, value, identifier.as_ref().map(|i| i.as_ref()), new_ttl)
+                    .await?;
+                Ok((data, new_ttl))
+            }
+            None => Ok((data, orig_ttl)),
+        }
+    }
+
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        // Keys are removed when their lease expires
+        let options = GetOptions::new().with_count_only();
+        let response = self
+            .client
+            .clone()
+            .get(self.session_key(id), Some(options))
+            .await?;
+        Ok(response.count() > 0)
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let identifier = data.identifier();
+        let value = data
+            .into_etcd()
+            .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        self.put_session(id, value, identifier.as_ref().map(|i| i.as_ref()), ttl)
+            .await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        let mut operations = vec![TxnOp::delete(self.session_key(id), None)];
+        if let Some(identifier) = data.identifier() {
+            operations.push(TxnOp::delete(
+                self.session_index_key(identifier.as_ref(), id),
+                None,
+            ));
+        }
+        self.client
+            .clone()
+            .txn(Txn::new().and_then(operations))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.client
+            .clone()
+            .delete(self.session_key(id), None)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T> SessionStorageIndexed<T> for EtcdStorage
+where
+    T: SessionEtcd,
+    <T as SessionIdentifier>::Id: AsRef<str>,
+{
+    async fn get_session_ids_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<String>> {
+        self.fetch_session_ids(id.as_ref()).await
+    }
+
+    async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
+        let session_ids = self.fetch_session_ids(id.as_ref()).await?;
+
+        let mut sessions = Vec::with_capacity(session_ids.len());
+        for session_id in session_ids {
+            let Ok((value, ttl)) = self.get_session(&session_id).await else {
+                continue;
+            };
+            if let Ok(data) = T::from_etcd(value) {
+                sessions.push((session_id, data, ttl));
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    async fn invalidate_sessions_by_identifier(
+        &self,
+        id: &T::Id,
+        excluded_session_id: Option<&str>,
+    ) -> SessionResult<u64> {
+        let session_ids = self.fetch_session_ids(id.as_ref()).await?;
+        let to_delete: Vec<_> = session_ids
+            .into_iter()
+            .filter(|session_id| {
+                !excluded_session_id.is_some_and(|excluded| session_id_eq(session_id, excluded))
+            })
+            .collect();
+        if to_delete.is_empty() {
+            return Ok(0);
+        }
+
+        let mut client = self.client.clone();
+        for chunk in to_delete.chunks(MAX_TXN_SESSIONS) {
+            let operations = chunk
+                .iter()
+                .flat_map(|session_id| {
+                    [
+                        TxnOp::delete(self.session_key(session_id), None),
+                        TxnOp::delete(self.session_index_key(id.as_ref(), session_id), None),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            client.txn(Txn::new().and_then(operations)).await?;
+        }
+
+        Ok(to_delete.len() as u64)
+    }
+
+    async fn purge_identifier_index(&self, id: &T::Id) -> SessionResult<()> {
+        let index_prefix = self.session_index_prefix(id.as_ref());
+        let options = DeleteOptions::new().with_prefix();
+        self.client
+            .clone()
+            .delete(index_prefix.as_str(), Some(options))
+            .await?;
+        Ok(())
+    }
+}
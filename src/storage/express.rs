@@ -0,0 +1,159 @@
+//! Compatibility with the express-session / connect-redis session format
+//!
+//! [`ExpressSession`] reads and writes sessions in the JSON format used by Node.js apps with
+//! [express-session](https://github.com/expressjs/session) and
+//! [connect-redis](https://github.com/tj/connect-redis), so a Rocket server and a Node.js app
+//! can share the same sessions while migrating routes incrementally.
+//!
+//! connect-redis stores each session as a JSON string under the key `sess:<session ID>`, which
+//! matches the default prefix of [`RedisFredStorage`](crate::storage::redis::RedisFredStorage).
+//! The JSON object has a `cookie` field with the cookie settings used by express-session, and
+//! your session data in the remaining fields:
+//!
+//! ```json
+//! {"cookie":{"originalMaxAge":86400000,"expires":"2025-01-01T00:00:00.000Z","httpOnly":true,"path":"/"},"userId":1}
+//! ```
+//!
+//! The `cookie` field is kept as-is when the session is saved by Rocket, so the Node.js app
+//! sees the same cookie settings. Note that the session cookie itself isn't shared -
+//! express-session signs the session ID into the `connect.sid` cookie, while Rocket uses its
+//! own private cookie - so both apps need to set their cookie to the same session ID (e.g. when
+//! the user first visits a route served by the other app).
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(feature = "redis_fred")]
+//! # {
+//! use rocket::serde::{Deserialize, Serialize};
+//! use rocket_flex_session::{
+//!     storage::{express::ExpressSession, redis::RedisFredStorage},
+//!     RocketFlexSession, Session, SessionIdentifier,
+//! };
+//!
+//! #[derive(Clone, Serialize, Deserialize)]
+//! #[serde(crate = "rocket::serde", rename_all = "camelCase")]
+//! struct User {
+//!     user_id: String,
+//! }
+//!
+//! impl SessionIdentifier for User {
+//!     type Id = String;
+//!     fn identifier(&self) -> Option<Self::Id> {
+//!         Some(self.user_id.clone())
+//!     }
+//! }
+//!
+//! #[rocket::get("/user")]
+//! fn user(session: Session<ExpressSession<User>>) -> Option<String> {
+//!     session.tap(|session| session.map(|s| s.data.user_id.clone()))
+//! }
+//!
+//! fn session_fairing(storage: RedisFredStorage) -> RocketFlexSession<ExpressSession<User>> {
+//!     RocketFlexSession::builder().storage(storage).build()
+//! }
+//! # }
+//! ```
+
+use rocket::serde::{de::DeserializeOwned, json::serde_json, Deserialize, Serialize};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    SessionIdentifier,
+};
+
+/// The cookie settings that express-session stores with each session
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct ExpressCookie {
+    /// The original max age of the cookie, in milliseconds
+    #[serde(default)]
+    pub original_max_age: Option<u64>,
+    /// The expiration time of the cookie, as an ISO 8601 string
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<String>,
+    /// Whether the cookie is HTTP-only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_only: Option<bool>,
+    /// The path of the cookie
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// The domain of the cookie
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// Whether the cookie is secure
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secure: Option<bool>,
+    /// The SameSite setting of the cookie (a string or boolean in express-session)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<serde_json::Value>,
+}
+
+/// Session data wrapper in the express-session format. Your session data is stored in the
+/// top-level fields alongside the `cookie` field, so it must serialize to a JSON object.
+/// If your data implements [`SessionIdentifier`], the sessions will be indexed by the same identifier.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ExpressSession<T> {
+    /// The cookie settings of express-session
+    #[serde(default)]
+    pub cookie: ExpressCookie,
+    /// Your session data
+    #[serde(flatten)]
+    pub data: T,
+}
+
+impl<T> ExpressSession<T> {
+    /// Create the session data with default cookie settings
+    pub fn new(data: T) -> Self {
+        Self {
+            cookie: ExpressCookie::default(),
+            data,
+        }
+    }
+}
+
+impl<T: Serialize> ExpressSession<T> {
+    /// Encode the session into the express-session JSON format
+    pub fn encode(&self) -> SessionResult<String> {
+        serde_json::to_string(self).map_err(|e| SessionError::Serialization(Box::new(e)))
+    }
+}
+
+impl<T: DeserializeOwned> ExpressSession<T> {
+    /// Decode a session saved in the express-session JSON format
+    pub fn decode(value: &str) -> SessionResult<Self> {
+        serde_json::from_str(value).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+}
+
+impl<T: SessionIdentifier> SessionIdentifier for ExpressSession<T> {
+    type Id = T::Id;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.data.identifier()
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.data.tags()
+    }
+}
+
+#[cfg(feature = "redis_fred")]
+impl<T> super::redis::SessionRedis for ExpressSession<T>
+where
+    T: SessionIdentifier + Serialize + DeserializeOwned + 'static,
+    T::Id: AsRef<str>,
+{
+    const REDIS_FORMAT: super::redis::RedisFormat = super::redis::RedisFormat::String;
+
+    type Error = SessionError;
+
+    fn into_redis(self) -> Result<super::redis::RedisValue, Self::Error> {
+        Ok(super::redis::RedisValue::String(self.encode()?))
+    }
+
+    fn from_redis(value: super::redis::RedisValue) -> Result<Self, Self::Error> {
+        let value = value.into_string().map_err(|_| SessionError::InvalidData)?;
+        Self::decode(&value)
+    }
+}
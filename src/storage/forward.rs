@@ -0,0 +1,156 @@
+//! Forwarding of the optional storage methods in wrapper storages
+
+/// Implement [`SessionStorage`](super::SessionStorage) for a storage that wraps another storage,
+/// forwarding groups of optional methods to the inner storage. The methods of a group are all
+/// forwarded together, so a method added to a group is forwarded by every wrapper that uses the
+/// group, instead of falling back to [`SessionError::Unsupported`](crate::error::SessionError::Unsupported).
+/// Groups that the wrapper handles differently (e.g. to convert the session data) are implemented
+/// in the impl block instead, as usual.
+///
+/// The groups are `activity`, `presence`, `capabilities`, `once_values`, and `lazy_fields`. The
+/// inner storage is given as a field or method of `self`, e.g. `forward(inner)` or `forward(storage())`.
+///
+/// Usage:
+/// ```ignore
+/// forward_storage_impl! {
+///     forward(inner): activity, capabilities;
+///     impl<T, S> SessionStorage<T> for MyWrapper<S>
+///     where
+///         T: Send + Sync + 'static,
+///         S: SessionStorage<T>,
+///     {
+///         // required methods, and the optional methods that aren't forwarded
+///     }
+/// }
+/// ```
+macro_rules! forward_storage_impl {
+    (forward($($inner:tt)+): $($group:ident),+ $(,)?; $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [] [$($group),+] $($impl)+);
+    };
+
+    // Append the forwarded methods of each group
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [activity $(, $group:ident)*] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [
+            $($methods)*
+            async fn record_activity(
+                &self,
+                id: &str,
+                entry: $crate::ActivityEntry,
+                limit: usize,
+            ) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.record_activity(id, entry, limit).await
+            }
+            async fn recent_activity(
+                &self,
+                id: &str,
+            ) -> $crate::error::SessionResult<Vec<$crate::ActivityEntry>> {
+                self.$($inner)+.recent_activity(id).await
+            }
+        ] [$($group),*] $($impl)+);
+    };
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [presence $(, $group:ident)*] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [
+            $($methods)*
+            async fn record_presence(
+                &self,
+                data: &T,
+                last_seen: ::rocket::time::OffsetDateTime,
+            ) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.record_presence(data, last_seen).await
+            }
+            async fn recently_active(
+                &self,
+                limit: usize,
+            ) -> $crate::error::SessionResult<Vec<(String, T, u32)>> {
+                self.$($inner)+.recently_active(limit).await
+            }
+        ] [$($group),*] $($impl)+);
+    };
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [capabilities $(, $group:ident)*] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [
+            $($methods)*
+            async fn save_capability(
+                &self,
+                token: &str,
+                capability: $crate::Capability,
+                ttl: u32,
+            ) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.save_capability(token, capability, ttl).await
+            }
+            async fn load_capability(
+                &self,
+                token: &str,
+            ) -> $crate::error::SessionResult<$crate::Capability> {
+                self.$($inner)+.load_capability(token).await
+            }
+            async fn delete_capability(&self, token: &str) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.delete_capability(token).await
+            }
+            async fn take_capability(
+                &self,
+                token: &str,
+            ) -> $crate::error::SessionResult<Option<$crate::Capability>> {
+                self.$($inner)+.take_capability(token).await
+            }
+        ] [$($group),*] $($impl)+);
+    };
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [once_values $(, $group:ident)*] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [
+            $($methods)*
+            async fn save_once_value(
+                &self,
+                id: &str,
+                key: &str,
+                value: String,
+                ttl: u32,
+            ) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.save_once_value(id, key, value, ttl).await
+            }
+            async fn take_once_value(
+                &self,
+                id: &str,
+                key: &str,
+            ) -> $crate::error::SessionResult<Option<String>> {
+                self.$($inner)+.take_once_value(id, key).await
+            }
+        ] [$($group),*] $($impl)+);
+    };
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [lazy_fields $(, $group:ident)*] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@groups [$($inner)+] [
+            $($methods)*
+            async fn save_lazy_field(
+                &self,
+                id: &str,
+                name: &str,
+                value: String,
+                ttl: u32,
+            ) -> $crate::error::SessionResult<()> {
+                self.$($inner)+.save_lazy_field(id, name, value, ttl).await
+            }
+            async fn load_lazy_field(
+                &self,
+                id: &str,
+                name: &str,
+            ) -> $crate::error::SessionResult<Option<String>> {
+                self.$($inner)+.load_lazy_field(id, name).await
+            }
+        ] [$($group),*] $($impl)+);
+    };
+    (@groups [$($inner:tt)+] [$($methods:tt)*] [] $($impl:tt)+) => {
+        $crate::storage::forward_storage_impl!(@impl [$($methods)*] [] $($impl)+);
+    };
+
+    // Find the body of the impl block, and add the forwarded methods to it. The whole impl block
+    // is passed to `async_trait`, as it doesn't expand macros inside the block.
+    (@impl [$($methods:tt)*] [$($header:tt)*] { $($body:tt)* }) => {
+        #[::rocket::async_trait]
+        $($header)* {
+            $($body)*
+            $($methods)*
+        }
+    };
+    (@impl [$($methods:tt)*] [$($header:tt)*] $next:tt $($rest:tt)+) => {
+        $crate::storage::forward_storage_impl!(@impl [$($methods)*] [$($header)* $next] $($rest)+);
+    };
+}
+pub(crate) use forward_storage_impl;
@@ -1,8 +1,32 @@
 //! Shared interface for session storage
 
-use rocket::{async_trait, http::CookieJar};
+use std::{collections::HashMap, time::Duration};
 
-use crate::{error::SessionResult, SessionIdentifier};
+use rocket::{async_trait, time::OffsetDateTime};
+
+use crate::{
+    analytics::SessionAnalytics,
+    error::{SessionError, SessionResult},
+    security::session_id_eq,
+    ActivityEntry, Capability, SessionIdentifier,
+};
+
+use super::{SessionChangeStream, SessionContext};
+
+/// Metadata of a stored session, tracked by the storage provider
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionMetadata {
+    /// When the session was first saved, if the storage tracks it
+    pub created_at: Option<OffsetDateTime>,
+}
+
+impl SessionMetadata {
+    /// Create the metadata of a session, with its creation time if tracked
+    pub fn new(created_at: Option<OffsetDateTime>) -> Self {
+        Self { created_at }
+    }
+}
 
 /// Trait representing a session backend storage. You can use your own session storage
 /// by implementing this trait.
@@ -18,15 +42,77 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)>;
 
+    /// Load session data, TTL, and [metadata](SessionMetadata) from storage, in the same way as
+    /// [`load`](SessionStorage::load). Storages that track the creation time of sessions should
+    /// override this. The default implementation calls `load`, and returns empty metadata.
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        let (data, ttl) = self.load(id, ttl, cookie_jar).await?;
+        Ok((data, ttl, SessionMetadata::default()))
+    }
+
     /// Save or update a session in storage. This will be performed at the end of the request lifecycle.
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()>;
 
+    /// Save or update a session in storage with a precise TTL, as set with
+    /// [`Session::set_ttl_duration`](crate::Session::set_ttl_duration). Storages that support
+    /// sub-second expiration should override this. The default implementation calls
+    /// [`save`](SessionStorage::save) with the TTL rounded up to whole seconds.
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()>
+    where
+        T: 'async_trait,
+    {
+        self.save(id, data, ttl_secs(ttl)).await
+    }
+
+    /// Save a session along with its [metadata](SessionMetadata), e.g. when moving it from another
+    /// storage. Storages that track the creation time of sessions should override this, and use the
+    /// given creation time if the session isn't stored yet. The default implementation calls
+    /// [`save_with_duration`](SessionStorage::save_with_duration), ignoring the metadata.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()>
+    where
+        T: 'async_trait,
+    {
+        self.save_with_duration(id, data, ttl).await
+    }
+
     /// Delete a session in storage. This will be performed at the end of the request lifecycle.
     async fn delete(&self, id: &str, data: T) -> SessionResult<()>;
 
+    /// Delete a session whose data can't be read (e.g. it failed to deserialize), by its ID. This
+    /// is used by [`ParseErrorPolicy::Delete`](crate::policy::ParseErrorPolicy::Delete). Since the
+    /// data is unknown, index entries of the session can't be removed, and are left to expire.
+    /// The default implementation returns [`SessionError::Unsupported`].
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        Err(SessionError::Unsupported("deleting unreadable sessions"))
+    }
+
+    /// Check whether a session exists, without deserializing its data or changing its TTL.
+    /// The default implementation loads the session, so storages should override this with
+    /// a cheaper check if possible.
+    async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        match self.load(id, None, cookie_jar).await {
+            Ok(_) => Ok(true),
+            Err(SessionError::NotFound | SessionError::Expired) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Optional callback when there's a pending change to the session data. A `data` value
     /// of `None` indicates a deleted session. This callback can be used by cookie-based
     /// session stores to update the cookie jar during the request.
@@ -36,7 +122,7 @@ where
         id: &str,
         data: Option<&T>,
         ttl: u32,
-        cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<()> {
         Ok(()) // Default no-op
     }
@@ -47,6 +133,120 @@ where
         None // Default not supported
     }
 
+    /// Optional: record an entry in the session's activity log, keeping only the most
+    /// recent `limit` entries. This will be performed at the end of the request if the
+    /// [`activity_log_size`](crate::RocketFlexSessionOptions::activity_log_size) option is set.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        Err(SessionError::Unsupported("activity log"))
+    }
+
+    /// Optional: retrieve the session's activity log, most recent first.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        Err(SessionError::Unsupported("activity log"))
+    }
+
+    /// Optional: record that the identifier of the session data was last seen at the given time, for
+    /// [presence tracking](crate::presence). This will be performed at the end of a sampled request if the
+    /// [`presence_sample_rate`](crate::RocketFlexSessionOptions::presence_sample_rate) option is set.
+    /// Storages that support this should also support indexing, to query the
+    /// [online identifiers](SessionStorageIndexed::online_identifiers).
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        Err(SessionError::Unsupported("presence tracking"))
+    }
+
+    /// Optional: retrieve up to `limit` of the most recently active sessions, as session ID, data,
+    /// and TTL (in seconds). This is used to [warm up](crate::storage::tiered::TieredLayer::warmup)
+    /// a cache at startup. Storages may approximate recent activity, e.g. by the latest expiration.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        Err(SessionError::Unsupported("recently active sessions"))
+    }
+
+    /// Optional: save a [capability token](crate::Session::create_capability) bound to a session,
+    /// expiring after the given TTL in seconds.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        Err(SessionError::Unsupported("capability tokens"))
+    }
+
+    /// Optional: load a capability token. Should return [`SessionError::NotFound`] if the
+    /// token doesn't exist or has expired.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        Err(SessionError::Unsupported("capability tokens"))
+    }
+
+    /// Optional: delete a capability token.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        Err(SessionError::Unsupported("capability tokens"))
+    }
+
+    /// Optional: load and delete a capability token in one atomic operation (e.g. `GETDEL` or
+    /// `DELETE ... RETURNING`), so that concurrent requests can't both redeem a
+    /// [single-use token](crate::Session::redeem_capability). Should return `None` if the token
+    /// doesn't exist or has expired.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        Err(SessionError::Unsupported("single-use capability tokens"))
+    }
+
+    /// Optional: save a [single-use value](crate::Session::take_once) for the session under the
+    /// given key, replacing any existing value and expiring after the given TTL in seconds.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        Err(SessionError::Unsupported("single-use values"))
+    }
+
+    /// Optional: get and delete a single-use value of the session in one atomic operation
+    /// (e.g. `GETDEL` or `DELETE ... RETURNING`), so that concurrent requests can't both take
+    /// the value. Should return `None` if the value doesn't exist or has expired.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        Err(SessionError::Unsupported("single-use values"))
+    }
+
+    /// Optional: save a [lazy field](crate::lazy) of the session, stored separately from the
+    /// session data (e.g. in another key or column). The field should expire with the given TTL
+    /// in seconds, and afterwards with the session when its TTL is extended. It should be deleted
+    /// along with the session.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        Err(SessionError::Unsupported("lazy fields"))
+    }
+
+    /// Optional: load a lazy field of the session. Should return `None` if the field hasn't been
+    /// saved, or has expired.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        Err(SessionError::Unsupported("lazy fields"))
+    }
+
     /// Optional setup of resources that will be called on server startup
     async fn setup(&self) -> SessionResult<()> {
         Ok(()) // Default no-op
@@ -74,6 +274,38 @@ where
     /// Retrieve all tracked session IDs, data, and TTL for the given identifier.
     async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>>;
 
+    /// Retrieve the [metadata](SessionMetadata) of all tracked sessions for the given identifier,
+    /// along with their session IDs. Storages that track the creation time of sessions should override
+    /// this. The default implementation returns empty metadata for each [session ID](SessionStorageIndexed::get_session_ids_by_identifier).
+    async fn get_session_metadata_by_identifier(
+        &self,
+        id: &T::Id,
+    ) -> SessionResult<Vec<(String, SessionMetadata)>> {
+        let session_ids = self.get_session_ids_by_identifier(id).await?;
+        Ok(session_ids
+            .into_iter()
+            .map(|session_id| (session_id, SessionMetadata::default()))
+            .collect())
+    }
+
+    /// Count the tracked sessions associated with the given identifier, optionally excluding one session ID.
+    /// The default implementation counts the [session IDs](SessionStorageIndexed::get_session_ids_by_identifier),
+    /// so storages may override this with a cheaper count.
+    async fn count_sessions_by_identifier(
+        &self,
+        id: &T::Id,
+        excluded_session_id: Option<&str>,
+    ) -> SessionResult<u64> {
+        let session_ids = self.get_session_ids_by_identifier(id).await?;
+        let count = session_ids
+            .iter()
+            .filter(|session_id| {
+                !excluded_session_id.is_some_and(|excluded| session_id_eq(session_id, excluded))
+            })
+            .count();
+        Ok(count as u64)
+    }
+
     /// Invalidate all tracked sessions associated with the given identifier, optionally excluding one session ID.
     /// Returns the number of sessions invalidated.
     async fn invalidate_sessions_by_identifier(
@@ -81,4 +313,109 @@ where
         id: &T::Id,
         excluded_session_id: Option<&str>,
     ) -> SessionResult<u64>;
+
+    /// Invalidate all tracked sessions associated with the given identifier, optionally excluding one session ID,
+    /// in the same way as [`invalidate_sessions_by_identifier`](SessionStorageIndexed::invalidate_sessions_by_identifier).
+    /// Returns the ID, data, and [metadata](SessionMetadata) of each session invalidated, e.g. to show
+    /// the user which devices were signed out.
+    ///
+    /// The default implementation retrieves the sessions and deletes them one by one, so sessions
+    /// created in the meantime aren't invalidated.
+    async fn invalidate_sessions_by_identifier_with_details(
+        &self,
+        id: &T::Id,
+        excluded_session_id: Option<&str>,
+    ) -> SessionResult<Vec<(String, T, SessionMetadata)>> {
+        let mut metadata: HashMap<String, SessionMetadata> = self
+            .get_session_metadata_by_identifier(id)
+            .await?
+            .into_iter()
+            .collect();
+        let mut invalidated = Vec::new();
+        for (session_id, data, _) in self.get_sessions_by_identifier(id).await? {
+            if excluded_session_id.is_some_and(|excluded| session_id_eq(&session_id, excluded)) {
+                continue;
+            }
+            self.delete(&session_id, data.clone()).await?;
+            let session_metadata = metadata.remove(&session_id).unwrap_or_default();
+            invalidated.push((session_id, data, session_metadata));
+        }
+        Ok(invalidated)
+    }
+
+    /// Update the data of all tracked sessions associated with the given identifier, optionally excluding
+    /// one session ID, keeping their remaining TTL. The update shouldn't change the identifier. Returns the
+    /// number of sessions updated.
+    ///
+    /// The default implementation loads and re-saves each session, so a concurrent request to one of
+    /// those sessions may overwrite the update. Storages may override this with an atomic update.
+    async fn update_sessions_by_identifier(
+        &self,
+        id: &T::Id,
+        excluded_session_id: Option<&str>,
+        update: &(dyn Fn(T) -> T + Send + Sync),
+    ) -> SessionResult<u64> {
+        let mut updated = 0;
+        for (session_id, data, ttl) in self.get_sessions_by_identifier(id).await? {
+            if excluded_session_id.is_some_and(|excluded| session_id_eq(&session_id, excluded)) {
+                continue;
+            }
+            self.save(&session_id, update(data), ttl).await?;
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// Optional: remove all index entries of the given identifier, including any stale entries of
+    /// expired sessions. This should be called after invalidating the identifier's sessions, e.g.
+    /// when the user is deprovisioned. The default implementation does nothing, for storages that
+    /// don't keep a separate index.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn purge_identifier_index(&self, id: &T::Id) -> SessionResult<()> {
+        Ok(())
+    }
+
+    /// Optional: invalidate all tracked sessions with the given [tag](SessionIdentifier::tags).
+    /// Returns the number of sessions invalidated. Implementations should delete the sessions
+    /// like [`SessionStorage::delete`], i.e. also remove them from the identifier index and
+    /// publish the [changes](SessionStorageIndexed::subscribe_identifier).
+    ///
+    /// Of the built-in storages, only the memory and Redis storages index tags. The SQL, libSQL,
+    /// and etcd storages return [`SessionError::Unsupported`].
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn invalidate_sessions_by_tag(&self, tag: &str) -> SessionResult<u64> {
+        Err(SessionError::Unsupported("session tags"))
+    }
+
+    /// Optional: retrieve the identifiers (as strings) that were [last seen](SessionStorage::record_presence)
+    /// since the given time, most recently seen first.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn online_identifiers(&self, since: OffsetDateTime) -> SessionResult<Vec<String>> {
+        Err(SessionError::Unsupported("presence tracking"))
+    }
+
+    /// Optional: compute [aggregates](SessionAnalytics) of the stored sessions for capacity
+    /// planning, counting the identifiers [last seen](SessionStorage::record_presence) since the
+    /// given time as active.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn session_analytics(
+        &self,
+        active_since: OffsetDateTime,
+    ) -> SessionResult<SessionAnalytics> {
+        Err(SessionError::Unsupported("session analytics"))
+    }
+
+    /// Optional: subscribe to changes of the sessions belonging to the identifier, e.g. to notify
+    /// connected clients when they've been signed out. Only changes made through the storage
+    /// (saves, deletions, and invalidations) are published - sessions that expire aren't notified.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn subscribe_identifier(&self, id: &T::Id) -> SessionResult<SessionChangeStream> {
+        Err(SessionError::Unsupported("session change notifications"))
+    }
+}
+
+/// Convert a precise TTL to whole seconds, rounding up so that sessions never expire early
+pub(crate) fn ttl_secs(ttl: Duration) -> u32 {
+    let secs = ttl.as_secs() + u64::from(ttl.subsec_nanos() > 0);
+    secs.try_into().unwrap_or(u32::MAX)
 }
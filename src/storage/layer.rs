@@ -0,0 +1,930 @@
+//! Composable storage layers
+//!
+//! A [`StorageLayer`] wraps a storage provider with cross-cutting behavior, such as retries,
+//! timeouts, concurrency limits, or metrics, and produces another storage provider. Use
+//! [`StorageStack`] to apply layers to a base storage in order - the last layer added is the
+//! outermost one, and sees each operation first.
+//!
+//! # Example
+//! ```rust
+//! use std::time::Duration;
+//! use rocket_flex_session::{
+//!     storage::{
+//!         layer::{MetricsLayer, RetryLayer, StorageMetrics, StorageStack, TimeoutLayer},
+//!         memory::MemoryStorage,
+//!     },
+//!     RocketFlexSession,
+//! };
+//!
+//! let metrics = StorageMetrics::default();
+//! let storage = StorageStack::new(MemoryStorage::default())
+//!     .layer(RetryLayer::builder().max_retries(2).build())
+//!     .layer(TimeoutLayer::new(Duration::from_secs(1)))
+//!     .layer(MetricsLayer::new(metrics.clone()))
+//!     .build();
+//!
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(storage)
+//!     .build();
+//!
+//! // later, e.g. in a metrics endpoint
+//! let loads = metrics.load();
+//! println!("{} session loads, {} errors", loads.calls, loads.errors);
+//! ```
+//!
+//! # Writing a layer
+//! Implement [`StorageLayer`] to create the wrapper, and [`SessionStorage`] for the wrapper, forwarding
+//! to the inner storage. All trait methods with default implementations should be forwarded as well, so
+//! that the inner storage's behavior is preserved. Indexed operations are not wrapped by the built-in layers:
+//! [`SessionStorage::as_indexed_storage`] returns the inner storage directly.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bon::Builder;
+use rocket::{
+    async_trait,
+    time::OffsetDateTime,
+    tokio::{
+        sync::{Semaphore, SemaphorePermit},
+        time,
+    },
+};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    ActivityEntry, Capability,
+};
+
+use super::{
+    forward_storage_impl, SessionContext, SessionMetadata, SessionStorage, SessionStorageIndexed,
+};
+
+/// A layer that wraps a storage provider of type `S`
+pub trait StorageLayer<S> {
+    /// The wrapped storage provider
+    type Storage;
+
+    /// Wrap the inner storage provider
+    fn layer(self, inner: S) -> Self::Storage;
+}
+
+/// Builder for a storage provider composed of layers around a base storage
+pub struct StorageStack<S> {
+    storage: S,
+}
+
+impl<S> StorageStack<S> {
+    /// Start a new stack with the base storage provider
+    pub fn new(base: S) -> Self {
+        Self { storage: base }
+    }
+
+    /// Wrap the current stack with the given layer
+    pub fn layer<L: StorageLayer<S>>(self, layer: L) -> StorageStack<L::Storage> {
+        StorageStack {
+            storage: layer.layer(self.storage),
+        }
+    }
+
+    /// Get the composed storage provider
+    pub fn build(self) -> S {
+        self.storage
+    }
+}
+
+/// Layer that retries failed operations if the error [is transient](SessionError::is_transient),
+/// waiting a bit longer between each attempt. Saving a session requires cloning the data for
+/// each attempt. Operations that can't safely run twice are only attempted once: if a
+/// [capability token](SessionStorage::take_capability) or [single-use value](SessionStorage::take_once_value)
+/// was taken but the reply was lost, a retry wouldn't find it anymore, and an
+/// [activity entry](SessionStorage::record_activity) would be appended twice.
+#[derive(Builder, Clone, Debug)]
+pub struct RetryLayer {
+    /// Maximum number of retries (default: `3`)
+    #[builder(default = 3)]
+    max_retries: u32,
+    /// Delay before the first retry, multiplied by the attempt number for later retries (default: 50ms)
+    #[builder(default = Duration::from_millis(50))]
+    backoff: Duration,
+}
+
+impl Default for RetryLayer {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl<S> StorageLayer<S> for RetryLayer {
+    type Storage = RetryStorage<S>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        RetryStorage {
+            inner,
+            config: self,
+        }
+    }
+}
+
+/// Storage wrapped by a [`RetryLayer`]
+pub struct RetryStorage<S> {
+    inner: S,
+    config: RetryLayer,
+}
+
+impl<S> RetryStorage<S> {
+    /// Run the operation, retrying on transient errors
+    async fn retry<F, Fut, R>(&self, mut operation: F) -> SessionResult<R>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = SessionResult<R>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match operation().await {
+                Err(e) if attempt < self.config.max_retries && e.is_transient() => {
+                    attempt += 1;
+                    rocket::debug!("Retrying session storage operation (attempt {attempt}): {e}");
+                    time::sleep(self.config.backoff * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T, S> SessionStorage<T> for RetryStorage<S>
+where
+    T: Clone + Send + Sync + 'static,
+    S: SessionStorage<T>,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        self.retry(|| self.inner.load(id, ttl, cookie_jar)).await
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        self.retry(|| self.inner.load_with_metadata(id, ttl, cookie_jar))
+            .await
+    }
+
+    async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        self.retry(|| self.inner.exists(id, cookie_jar)).await
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.retry(|| self.inner.save(id, data.clone(), ttl)).await
+    }
+
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        self.retry(|| self.inner.save_with_duration(id, data.clone(), ttl))
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        self.retry(|| {
+            self.inner
+                .save_with_metadata(id, data.clone(), ttl, metadata.clone())
+        })
+        .await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        self.retry(|| self.inner.delete(id, data.clone())).await
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.retry(|| self.inner.delete_unreadable(id)).await
+    }
+
+    fn save_cookie(
+        &self,
+        id: &str,
+        data: Option<&T>,
+        ttl: u32,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<()> {
+        self.inner.save_cookie(id, data, ttl, cookie_jar)
+    }
+
+    fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+        self.inner.as_indexed_storage()
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        // Not retried: if the entry was appended but the reply was lost, a retry would append it again
+        self.inner.record_activity(id, entry, limit).await
+    }
+
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        self.retry(|| self.inner.recent_activity(id)).await
+    }
+
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        self.retry(|| self.inner.record_presence(data, last_seen))
+            .await
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        self.retry(|| self.inner.recently_active(limit)).await
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.retry(|| self.inner.save_capability(token, capability.clone(), ttl))
+            .await
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        self.retry(|| self.inner.load_capability(token)).await
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        self.retry(|| self.inner.delete_capability(token)).await
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        // Not retried: if the token was taken but the reply was lost, a retry would get `None`
+        self.inner.take_capability(token).await
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.retry(|| self.inner.save_once_value(id, key, value.clone(), ttl))
+            .await
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        // Not retried: if the value was taken but the reply was lost, a retry would get `None`
+        self.inner.take_once_value(id, key).await
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.retry(|| self.inner.save_lazy_field(id, name, value.clone(), ttl))
+            .await
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        self.retry(|| self.inner.load_lazy_field(id, name)).await
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.inner.setup().await
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.inner.shutdown().await
+    }
+}
+
+/// Layer that fails operations with [`SessionError::Timeout`] if they take longer than the given duration
+/// (except for taking a [capability token](SessionStorage::take_capability) or
+/// [single-use value](SessionStorage::take_once_value), which would be lost if it was already
+/// taken when the operation is cancelled)
+#[derive(Clone, Copy, Debug)]
+pub struct TimeoutLayer {
+    timeout: Duration,
+}
+
+impl TimeoutLayer {
+    /// Create a timeout layer with the given duration
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl<S> StorageLayer<S> for TimeoutLayer {
+    type Storage = TimeoutStorage<S>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        TimeoutStorage {
+            inner,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Storage wrapped by a [`TimeoutLayer`]
+pub struct TimeoutStorage<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<S> TimeoutStorage<S> {
+    /// Run the operation with the timeout
+    async fn with_timeout<R>(
+        &self,
+        operation: impl Future<Output = SessionResult<R>>,
+    ) -> SessionResult<R> {
+        time::timeout(self.timeout, operation)
+            .await
+            .unwrap_or(Err(SessionError::Timeout))
+    }
+}
+
+#[async_trait]
+impl<T, S> SessionStorage<T> for TimeoutStorage<S>
+where
+    T: Send + Sync + 'static,
+    S: SessionStorage<T>,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        self.with_timeout(self.inner.load(id, ttl, cookie_jar))
+            .await
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        self.with_timeout(self.inner.load_with_metadata(id, ttl, cookie_jar))
+            .await
+    }
+
+    async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        self.with_timeout(self.inner.exists(id, cookie_jar)).await
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.with_timeout(self.inner.save(id, data, ttl)).await
+    }
+
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        self.with_timeout(self.inner.save_with_duration(id, data, ttl))
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        self.with_timeout(self.inner.save_with_metadata(id, data, ttl, metadata))
+            .await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        self.with_timeout(self.inner.delete(id, data)).await
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.with_timeout(self.inner.delete_unreadable(id)).await
+    }
+
+    fn save_cookie(
+        &self,
+        id: &str,
+        data: Option<&T>,
+        ttl: u32,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<()> {
+        self.inner.save_cookie(id, data, ttl, cookie_jar)
+    }
+
+    fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+        self.inner.as_indexed_storage()
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        self.with_timeout(self.inner.record_activity(id, entry, limit))
+            .await
+    }
+
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        self.with_timeout(self.inner.recent_activity(id)).await
+    }
+
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        self.with_timeout(self.inner.record_presence(data, last_seen))
+            .await
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        self.with_timeout(self.inner.recently_active(limit)).await
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.with_timeout(self.inner.save_capability(token, capability, ttl))
+            .await
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        self.with_timeout(self.inner.load_capability(token)).await
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        self.with_timeout(self.inner.delete_capability(token)).await
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        // Not cancelled: the token may already be taken when the timeout elapses
+        self.inner.take_capability(token).await
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.with_timeout(self.inner.save_once_value(id, key, value, ttl))
+            .await
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        // Not cancelled: the value may already be taken when the timeout elapses
+        self.inner.take_once_value(id, key).await
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.with_timeout(self.inner.save_lazy_field(id, name, value, ttl))
+            .await
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        self.with_timeout(self.inner.load_lazy_field(id, name))
+            .await
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.inner.setup().await
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.inner.shutdown().await
+    }
+}
+
+/// Number of calls and errors of a storage operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OperationCounts {
+    /// Total number of calls
+    pub calls: u64,
+    /// Number of calls that returned an error
+    pub errors: u64,
+}
+
+/// Counters of storage operations, recorded by a [`MetricsLayer`]. Clones share the same counters.
+#[derive(Clone, Debug, Default)]
+pub struct StorageMetrics {
+    counters: Arc<[Counter; 3]>,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    calls: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// Index of each operation in the metrics counters
+#[derive(Clone, Copy)]
+enum Operation {
+    Load = 0,
+    Save = 1,
+    Delete = 2,
+}
+
+impl StorageMetrics {
+    /// Counts of session loads
+    pub fn load(&self) -> OperationCounts {
+        self.counts(Operation::Load)
+    }
+
+    /// Counts of session saves
+    pub fn save(&self) -> OperationCounts {
+        self.counts(Operation::Save)
+    }
+
+    /// Counts of session deletions
+    pub fn delete(&self) -> OperationCounts {
+        self.counts(Operation::Delete)
+    }
+
+    fn counts(&self, operation: Operation) -> OperationCounts {
+        let counter = &self.counters[operation as usize];
+        OperationCounts {
+            calls: counter.calls.load(Ordering::Relaxed),
+            errors: counter.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record<R>(&self, operation: Operation, result: &SessionResult<R>) {
+        let counter = &self.counters[operation as usize];
+        counter.calls.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            counter.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Layer that counts session loads, saves, and deletions in the given [`StorageMetrics`]
+#[derive(Clone, Debug)]
+pub struct MetricsLayer {
+    metrics: StorageMetrics,
+}
+
+impl MetricsLayer {
+    /// Create a metrics layer that records to the given metrics
+    pub fn new(metrics: StorageMetrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> StorageLayer<S> for MetricsLayer {
+    type Storage = MetricsStorage<S>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        MetricsStorage {
+            inner,
+            metrics: self.metrics,
+        }
+    }
+}
+
+/// Storage wrapped by a [`MetricsLayer`]
+pub struct MetricsStorage<S> {
+    inner: S,
+    metrics: StorageMetrics,
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for MetricsStorage<S>
+    where
+        T: Send + Sync + 'static,
+        S: SessionStorage<T>,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            let result = self.inner.load(id, ttl, cookie_jar).await;
+            self.metrics.record(Operation::Load, &result);
+            result
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            let result = self.inner.load_with_metadata(id, ttl, cookie_jar).await;
+            self.metrics.record(Operation::Load, &result);
+            result
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            let result = self.inner.save(id, data, ttl).await;
+            self.metrics.record(Operation::Save, &result);
+            result
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            let result = self.inner.save_with_duration(id, data, ttl).await;
+            self.metrics.record(Operation::Save, &result);
+            result
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            let result = self.inner.save_with_metadata(id, data, ttl, metadata).await;
+            self.metrics.record(Operation::Save, &result);
+            result
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            let result = self.inner.delete(id, data).await;
+            self.metrics.record(Operation::Delete, &result);
+            result
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            let result = self.inner.delete_unreadable(id).await;
+            self.metrics.record(Operation::Delete, &result);
+            result
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
+
+/// What to do with a save or deletion that has to wait for a [`ConcurrencyLimitLayer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShedPolicy {
+    /// Wait until the operation can run (default)
+    #[default]
+    Wait,
+    /// Shed the operation if it can't start within the given duration
+    After(Duration),
+    /// Shed the operation right away if this many operations are already waiting
+    MaxWaiting(u64),
+}
+
+/// Current state of a [`ConcurrencyLimitLayer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConcurrencyStats {
+    /// Number of saves and deletions running
+    pub running: u64,
+    /// Number of saves and deletions waiting to run
+    pub waiting: u64,
+    /// Total number of saves and deletions shed by the [`ShedPolicy`]
+    pub shed: u64,
+}
+
+/// Layer that limits the number of concurrent session saves and deletions, e.g. to protect the
+/// connection pool of the storage during traffic spikes. Operations beyond the limit wait for
+/// their turn, or fail with [`SessionError::Overloaded`] according to the [`ShedPolicy`].
+/// Loads and other operations aren't limited.
+///
+/// Clones of the layer share the same limit, so apply clones of one layer to the storages of all
+/// your session fairings to limit their operations together.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimitLayer {
+    limiter: Arc<Limiter>,
+}
+
+#[derive(Debug)]
+struct Limiter {
+    max_concurrent: usize,
+    permits: Semaphore,
+    shed_policy: ShedPolicy,
+    waiting: AtomicU64,
+    shed: AtomicU64,
+}
+
+impl ConcurrencyLimitLayer {
+    /// Allow at most `max_concurrent` saves and deletions at a time, handling the rest
+    /// according to the shed policy
+    pub fn new(max_concurrent: usize, shed_policy: ShedPolicy) -> Self {
+        let max_concurrent = max_concurrent.max(1);
+        Self {
+            limiter: Arc::new(Limiter {
+                max_concurrent,
+                permits: Semaphore::new(max_concurrent),
+                shed_policy,
+                waiting: AtomicU64::default(),
+                shed: AtomicU64::default(),
+            }),
+        }
+    }
+
+    /// Current number of running and waiting operations, and the number of shed operations
+    pub fn stats(&self) -> ConcurrencyStats {
+        let limiter = &self.limiter;
+        let available = limiter.permits.available_permits();
+        ConcurrencyStats {
+            running: (limiter.max_concurrent.saturating_sub(available)) as u64,
+            waiting: limiter.waiting.load(Ordering::Relaxed),
+            shed: limiter.shed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Limiter {
+    /// Wait for a permit to run an operation, or shed it according to the policy
+    async fn acquire(&self) -> SessionResult<SemaphorePermit<'_>> {
+        if let Ok(permit) = self.permits.try_acquire() {
+            return Ok(permit);
+        }
+        if let ShedPolicy::MaxWaiting(max) = self.shed_policy {
+            if self.waiting.load(Ordering::Relaxed) >= max {
+                return Err(self.shed());
+            }
+        }
+
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = WaitingGuard(&self.waiting);
+        let permit = match self.shed_policy {
+            ShedPolicy::After(timeout) => time::timeout(timeout, self.permits.acquire())
+                .await
+                .map_err(|_| self.shed())?,
+            _ => self.permits.acquire().await,
+        };
+        // The semaphore is never closed
+        permit.map_err(|_| SessionError::Overloaded)
+    }
+
+    fn shed(&self) -> SessionError {
+        self.shed.fetch_add(1, Ordering::Relaxed);
+        SessionError::Overloaded
+    }
+}
+
+/// Decrements the number of waiting operations, even if the operation is cancelled
+struct WaitingGuard<'a>(&'a AtomicU64);
+
+impl Drop for WaitingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> StorageLayer<S> for ConcurrencyLimitLayer {
+    type Storage = ConcurrencyLimitStorage<S>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        ConcurrencyLimitStorage {
+            inner,
+            limiter: self.limiter,
+        }
+    }
+}
+
+/// Storage wrapped by a [`ConcurrencyLimitLayer`]
+pub struct ConcurrencyLimitStorage<S> {
+    inner: S,
+    limiter: Arc<Limiter>,
+}
+
+impl<S> ConcurrencyLimitStorage<S> {
+    /// Run the operation once the limit allows it
+    async fn limited<R>(
+        &self,
+        operation: impl Future<Output = SessionResult<R>>,
+    ) -> SessionResult<R> {
+        let _permit = self.limiter.acquire().await?;
+        operation.await
+    }
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for ConcurrencyLimitStorage<S>
+    where
+        T: Send + Sync + 'static,
+        S: SessionStorage<T>,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            self.inner.load(id, ttl, cookie_jar).await
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            self.inner.load_with_metadata(id, ttl, cookie_jar).await
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            self.limited(self.inner.save(id, data, ttl)).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.limited(self.inner.save_with_duration(id, data, ttl))
+                .await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            self.limited(self.inner.save_with_metadata(id, data, ttl, metadata))
+                .await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.limited(self.inner.delete(id, data)).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.limited(self.inner.delete_unreadable(id)).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Session storage via libSQL (Turso)
+
+use bon::bon;
+use libsql::{Connection, Row, Value};
+use rocket::{
+    async_trait,
+    time::OffsetDateTime,
+    tokio::{sync::Mutex, time::interval},
+};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    storage::{
+        sql::{self, SqlDialect},
+        SessionContext, SessionStorage, SessionStorageIndexed, TaskGuard,
+    },
+    SessionIdentifier,
+};
+
+/**
+Trait for session data types that can be stored using libSQL. Session data is stored
+as text, so you'll typically serialize it to JSON or a similar format.
+
+# Example
+
+```
+use rocket_flex_session::error::SessionError;
+use rocket_flex_session::storage::libsql::SessionLibsql;
+use rocket_flex_session::SessionIdentifier;
+
+#[derive(Clone)]
+struct SessionData {
+    user_id: String,
+    data: String,
+}
+
+impl SessionIdentifier for SessionData {
+    type Id = String; // must be convertible to a libSQL value
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+impl SessionLibsql for SessionData {
+    type Error = SessionError; // or a custom error
+
+    fn into_sql(self) -> Result<String, Self::Error> {
+        Ok(format!("{}:{}", self.user_id, self.data))
+    }
+
+    fn from_sql(value: String) -> Result<Self, Self::Error> {
+        let (user_id, data) = value.split_once(':').ok_or(SessionError::InvalidData)?;
+        Ok(SessionData {
+            user_id: user_id.to_owned(),
+            data: data.to_owned(),
+        })
+    }
+}
+```
+*/
+pub trait SessionLibsql: SessionIdentifier + Sized {
+    /// The error that can occur when converting to/from the stored value.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Convert to the value that will be stored in the database.
+    fn into_sql(self) -> Result<String, Self::Error>;
+
+    /// Convert from the value stored in the database.
+    fn from_sql(value: String) -> Result<Self, Self::Error>;
+}
+
+/**
+Session store using [libSQL](https://docs.rs/crate/libsql), e.g. a remote [Turso](https://turso.tech/) database
+or an embedded replica. This is a good fit for edge deployments that need a shared session store.
+
+# Requirements
+- You must pass in an initialized libSQL connection.
+- Your session data type must implement [`SessionLibsql`] to configure how to convert & store session data.
+- Your session data type must implement [`SessionIdentifier`]. The SessionIdentifier's
+  [Id](`SessionIdentifier::Id`) type must be convertible to a libSQL [`Value`].
+- Expects a table to already exist with the following columns:
+
+| Name | Type |
+|------|---------|
+| id   | TEXT NOT NULL PRIMARY KEY |
+| data | TEXT NOT NULL  |
+| user_id | TEXT (or the SQL type of `SessionIdentifier::Id`) |
+| expires | INTEGER NOT NULL |
+
+The `expires` column stores the expiration as a Unix timestamp (in seconds). The name of
+the session index column ("user_id") can be customized when building the storage.
+
+# Example
+```
+use rocket_flex_session::storage::libsql::LibsqlStorage;
+use std::time::Duration;
+
+async fn create_storage() -> LibsqlStorage {
+    let db = libsql::Builder::new_remote("libsql://...".to_owned(), "token".to_owned())
+        .build()
+        .await
+        .unwrap();
+    LibsqlStorage::builder()
+        .connection(db.connect().unwrap())
+        .table_name("sessions")
+        // optional auto-deletion of expired sessions
+        .cleanup_interval(Duration::from_secs(600))
+        .build()
+}
+```
+*/
+pub struct LibsqlStorage {
+    connection: Connection,
+    table_name: String,
+    index_column: String,
+    cleanup_interval: Option<std::time::Duration>,
+    cleanup_task: Mutex<Option<TaskGuard>>,
+}
+
+#[bon]
+impl LibsqlStorage {
+    #[builder]
+    pub fn new(
+        /// An initialized libSQL connection.
+        connection: Connection,
+        /// The name of the table to use for storing sessions.
+        #[builder(into)]
+        table_name: String,
+        /// The name of the column used to index/group sessions (default: `"user_id"`)
+        #[builder(into, default = "user_id")]
+        index_column: String,
+        /// Interval to check for and delete expired sessions. If not set,
+        /// expired sessions will not be cleaned up automatically.
+        cleanup_interval: Option<std::time::Duration>,
+    ) -> Self {
+        Self {
+            connection,
+            table_name,
+            index_column,
+            cleanup_interval,
+            cleanup_task: Mutex::default(),
+        }
+    }
+
+    /// Whether the cleanup task is running. It's started by [`setup`](SessionStorage::setup)
+    /// if `cleanup_interval` is set, and stopped by [`shutdown`](SessionStorage::shutdown).
+    pub async fn is_cleanup_running(&self) -> bool {
+        let task = self.cleanup_task.lock().await;
+        task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+}
+
+/// Current time as a Unix timestamp
+fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+/// Convert the stored expiration timestamp to TTL
+fn expires_to_ttl(expires: i64) -> u32 {
+    (expires - now()).try_into().unwrap_or(0)
+}
+
+/// Convert an optional session identifier to a libSQL value
+fn identifier_value<I: Into<Value>>(identifier: Option<I>) -> Value {
+    identifier.map(Into::into).unwrap_or(Value::Null)
+}
+
+/// Parse a row containing the session data and expiration
+fn parse_session_row<T: SessionLibsql>(row: &Row, offset: i32) -> SessionResult<(T, u32)> {
+    let value: String = row.get(offset)?;
+    let data = T::from_sql(value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+    let expires: i64 = row.get(offset + 1)?;
+    Ok((data, expires_to_ttl(expires)))
+}
+
+#[async_trait]
+impl<T> SessionStorage<T> for LibsqlStorage
+where
+    T: SessionLibsql + 'static,
+    <T as SessionIdentifier>::Id: Into<Value> + Clone,
+{
+    fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+        Some(self)
+    }
+
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let mut rows = match ttl {
+            Some(new_ttl) => {
+                self.connection
+                    .query(
+                        &sql::load_and_update_ttl(&self.table_name, None),
+                        vec![
+                            Value::Integer(now() + i64::from(new_ttl)),
+                            Value::from(id.to_owned()),
+                            Value::Integer(now()),
+                        ],
+                    )
+                    .await?
+            }
+            None => {
+                self.connection
+                    .query(
+                        &sql::load(&self.table_name, None),
+                        vec![Value::from(id.to_owned()), Value::Integer(now())],
+                    )
+                    .await?
+            }
+        };
+        let row = rows.next().await?.ok_or(SessionError::NotFound)?;
+
+        parse_session_row(&row, 0)
+    }
+
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        let mut rows = self
+            .connection
+            .query(
+                &sql::exists(&self.table_name),
+                vec![Value::from(id.to_owned()), Value::Integer(now())],
+            )
+            .await?;
+        Ok(rows.next().await?.is_some())
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let identifier = identifier_value(data.identifier());
+        let value = data
+            .into_sql()
+            .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        self.connection
+            .execute(
+                &sql::save(
+                    &self.table_name,
+                    &self.index_column,
+                    SqlDialect::Standard,
+                    None,
+                ),
+                vec![
+                    Value::from(id.to_owned()),
+                    identifier,
+                    Value::from(value),
+                    Value::Integer(now() + i64::from(ttl)),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        self.connection
+            .execute(
+                &sql::delete(&self.table_name),
+                vec![Value::from(id.to_owned())],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.connection
+            .execute(
+                &sql::delete(&self.table_name),
+                vec![Value::from(id.to_owned())],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        let Some(cleanup_interval) = self.cleanup_interval else {
+            return Ok(());
+        };
+
+        let connection = self.connection.clone();
+        let delete_sql = sql::delete_expired(&self.table_name, false);
+        let task = TaskGuard::spawn(|mut shutdown_rx| async move {
+            rocket::info!("Starting session cleanup monitor");
+            let mut interval = interval(cleanup_interval);
+            loop {
+                rocket::tokio::select! {
+                    _ = interval.tick() => {
+                        rocket::debug!("Cleaning up expired sessions");
+                        if let Err(e) = connection.execute(&delete_sql, vec![Value::Integer(now())]).await {
+                            rocket::error!("Error deleting expired sessions: {e}");
+                        }
+                    }
+                    _ = &mut shutdown_rx => {
+                        rocket::info!("Session cleanup monitor shutdown");
+                        break;
+                    }
+                }
+            }
+        });
+        // Replacing a previous task stops it
+        self.cleanup_task.lock().await.replace(task);
+
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        let task = self.cleanup_task.lock().await.take();
+        match task {
+            Some(task) => task.shutdown().await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> SessionStorageIndexed<T> for LibsqlStorage
+where
+    T: SessionLibsql + 'static,
+    <T as SessionIdentifier>::Id: Into<Value> + Clone,
+{
+    async fn get_session_ids_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<String>> {
+        let mut rows = self
+            .connection
+            .query(
+                &sql::all_session_ids(
+                    &self.table_name,
+                    &self.index_column,
+                    SqlDialect::Standard,
+                    None,
+                ),
+                vec![id.clone().into(), Value::Integer(now())],
+            )
+            .await?;
+
+        let mut session_ids = Vec::new();
+        while let Some(row) = rows.next().await? {
+            if let Ok(session_id) = row.get::<String>(0) {
+                session_ids.push(session_id);
+            }
+        }
+
+        Ok(session_ids)
+    }
+
+    async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
+        let mut rows = self
+            .connection
+            .query(
+                &sql::all_session_data(&self.table_name, &self.index_column, SqlDialect::Standard),
+                vec![id.clone().into(), Value::Integer(now())],
+            )
+            .await?;
+
+        let mut sessions = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let Ok(session_id) = row.get::<String>(0) else {
+                continue;
+            };
+            if let Ok((data, ttl)) = parse_session_row(&row, 1) {
+                sessions.push((session_id, data, ttl));
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    async fn invalidate_sessions_by_identifier(
+        &self,
+        id: &T::Id,
+        excluded_session_id: Option<&str>,
+    ) -> SessionResult<u64> {
+        let sql = sql::invalidate_all(
+            &self.table_name,
+            &self.index_column,
+            excluded_session_id.is_some(),
+        );
+        let mut params: Vec<Value> = vec![id.clone().into()];
+        if let Some(session_id) = excluded_session_id {
+            params.push(Value::from(session_id.to_owned()));
+        }
+        let rows = self.connection.execute(&sql, params).await?;
+
+        Ok(rows)
+    }
+}
@@ -1,24 +1,40 @@
 //! In-memory session storage implementation
 
 use std::{
-    collections::{HashMap, HashSet},
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use bon::Builder;
 use retainer::Cache;
 use rocket::{
     async_trait,
-    http::CookieJar,
-    tokio::{select, spawn, sync::oneshot},
+    time::OffsetDateTime,
+    tokio::{select, sync::broadcast},
 };
 
 use crate::{
+    analytics::SessionAnalytics,
     error::{SessionError, SessionResult},
-    SessionIdentifier,
+    security::session_id_eq,
+    throttle::{LoginThrottle, DEFAULT_MAX_ATTEMPTS, DEFAULT_WINDOW},
+    ActivityEntry, Capability, SessionIdentifier,
 };
 
-use super::interface::{SessionStorage, SessionStorageIndexed};
+use super::{
+    change::broadcast_stream,
+    interface::{SessionMetadata, SessionStorage, SessionStorageIndexed},
+    SessionChange, SessionChangeStream, SessionContext, TaskGuard,
+};
+
+/// Capacity of the session change channel. Subscribers that fall behind will miss changes.
+const CHANGES_CAPACITY: usize = 256;
+
+/// Lazy fields of a session, which are updated in place so that concurrent saves of different
+/// fields don't overwrite each other
+type LazyFields = Arc<Mutex<HashMap<String, String>>>;
 
 /// In-memory storage provider for sessions. This is designed mostly for local
 /// development, and not for production use. It uses the [retainer] crate to
@@ -26,15 +42,76 @@ use super::interface::{SessionStorage, SessionStorageIndexed};
 ///
 /// For session indexing support, see [`MemoryStorageIndexed`].
 pub struct MemoryStorage<T> {
-    shutdown_tx: Mutex<Option<oneshot::Sender<()>>>,
+    monitor: Mutex<Option<TaskGuard>>,
     cache: Arc<Cache<String, T>>,
+    activity: Arc<Cache<String, VecDeque<ActivityEntry>>>,
+    capabilities: Arc<Cache<String, Capability>>,
+    session_capabilities: Arc<Cache<String, Vec<String>>>,
+    once_values: Arc<Cache<(String, String), String>>,
+    session_once_keys: Arc<Cache<String, Vec<String>>>,
+    lazy_fields: Arc<Cache<String, LazyFields>>,
+    /// Lock for creating the lazy fields of a session
+    lazy_fields_lock: rocket::tokio::sync::Mutex<()>,
+    created: Arc<Cache<String, OffsetDateTime>>,
 }
 
 impl<T> Default for MemoryStorage<T> {
     fn default() -> Self {
         Self {
-            shutdown_tx: Mutex::default(),
+            monitor: Mutex::default(),
             cache: Default::default(),
+            created: Default::default(),
+            activity: Default::default(),
+            capabilities: Default::default(),
+            session_capabilities: Default::default(),
+            once_values: Default::default(),
+            session_once_keys: Default::default(),
+            lazy_fields: Default::default(),
+            lazy_fields_lock: Default::default(),
+        }
+    }
+}
+
+impl<T> MemoryStorage<T> {
+    /// Whether the cache monitor, which evicts expired sessions, is running. It's started by
+    /// [`setup`](SessionStorage::setup) and stopped by [`shutdown`](SessionStorage::shutdown).
+    pub fn is_monitor_running(&self) -> bool {
+        let monitor = self.monitor.lock().unwrap();
+        monitor
+            .as_ref()
+            .is_some_and(|monitor| !monitor.is_finished())
+    }
+
+    /// Get the creation time of the session
+    async fn created_at(&self, id: &str) -> Option<OffsetDateTime> {
+        let created = self.created.get(&id.to_owned()).await;
+        created.map(|created| *created)
+    }
+
+    /// Extend the expiration of the session's lazy fields to the new TTL of the session
+    async fn extend_lazy_fields(&self, id: &str, ttl: Duration) {
+        let fields = self.lazy_fields.get(&id.to_owned()).await;
+        if let Some(fields) = fields.map(|fields| Arc::clone(&fields)) {
+            self.lazy_fields.insert(id.to_owned(), fields, ttl).await;
+        }
+    }
+
+    /// Remove the session along with its activity log, lazy fields, capability tokens, and
+    /// single-use values
+    async fn remove_session(&self, id: &str) {
+        self.cache.remove(&id.to_owned()).await;
+        self.created.remove(&id.to_owned()).await;
+        self.activity.remove(&id.to_owned()).await;
+        self.lazy_fields.remove(&id.to_owned()).await;
+        if let Some(tokens) = self.session_capabilities.remove(&id.to_owned()).await {
+            for token in tokens {
+                self.capabilities.remove(&token).await;
+            }
+        }
+        if let Some(keys) = self.session_once_keys.remove(&id.to_owned()).await {
+            for key in keys {
+                self.once_values.remove(&(id.to_owned(), key)).await;
+            }
         }
     }
 }
@@ -48,56 +125,273 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        _cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
         let Some(data) = self.cache.get(&id.to_owned()).await else {
             return Err(SessionError::NotFound);
         };
+        let created_at = self.created_at(id).await;
         if let Some(new_ttl) = ttl {
+            let new_ttl = Duration::from_secs(new_ttl.into());
             self.cache
-                .insert(
-                    id.to_owned(),
-                    data.to_owned(),
-                    Duration::from_secs(new_ttl.into()),
-                )
+                .insert(id.to_owned(), data.to_owned(), new_ttl)
                 .await;
+            if let Some(created_at) = created_at {
+                self.created
+                    .insert(id.to_owned(), created_at, new_ttl)
+                    .await;
+            }
+            self.extend_lazy_fields(id, new_ttl).await;
         }
         let ttl = ttl.unwrap_or(data.expiration().remaining().unwrap().as_secs() as u32);
-        Ok((data.to_owned(), ttl))
+        Ok((data.to_owned(), ttl, SessionMetadata::new(created_at)))
+    }
+
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        Ok(self.cache.get(&id.to_owned()).await.is_some())
     }
 
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
-        self.cache
-            .insert(id.to_owned(), data, Duration::from_secs(ttl.into()))
-            .await;
+        self.save_with_duration(id, data, Duration::from_secs(ttl.into()))
+            .await
+    }
+
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        // The creation time and lazy fields expire along with the session
+        let created_at = self.created_at(id).await.or(metadata.created_at);
+        let created_at = created_at.unwrap_or_else(OffsetDateTime::now_utc);
+        self.cache.insert(id.to_owned(), data, ttl).await;
+        self.created.insert(id.to_owned(), created_at, ttl).await;
+        self.extend_lazy_fields(id, ttl).await;
         Ok(())
     }
 
     async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
-        self.cache.remove(&id.to_owned()).await;
+        self.remove_session(id).await;
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.remove_session(id).await;
+        Ok(())
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        // Activity log expires along with the session
+        let Some(remaining) = self
+            .cache
+            .get(&id.to_owned())
+            .await
+            .and_then(|data| data.expiration().remaining())
+        else {
+            return Ok(());
+        };
+        let mut entries = self
+            .activity
+            .get(&id.to_owned())
+            .await
+            .map(|entries| entries.to_owned())
+            .unwrap_or_default();
+        entries.push_front(entry);
+        entries.truncate(limit);
+        self.activity
+            .insert(id.to_owned(), entries, remaining)
+            .await;
         Ok(())
     }
 
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        let entries = self.activity.get(&id.to_owned()).await;
+        Ok(entries.map_or(Vec::new(), |entries| entries.iter().cloned().collect()))
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        // Track the tokens of the session, so they're deleted along with the session
+        let ttl = Duration::from_secs(ttl.into());
+        let session_id = capability.session_id.clone();
+        let existing = self.session_capabilities.get(&session_id).await;
+        let (mut tokens, remaining) = existing
+            .map(|tokens| (tokens.to_owned(), tokens.expiration().remaining()))
+            .unwrap_or_default();
+        tokens.push(token.to_owned());
+        let tokens_ttl = remaining.map_or(ttl, |remaining| remaining.max(ttl));
+        self.session_capabilities
+            .insert(session_id, tokens, tokens_ttl)
+            .await;
+
+        self.capabilities
+            .insert(token.to_owned(), capability, ttl)
+            .await;
+        Ok(())
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        let capability = self.capabilities.get(&token.to_owned()).await;
+        capability
+            .map(|capability| capability.to_owned())
+            .ok_or(SessionError::NotFound)
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        self.capabilities.remove(&token.to_owned()).await;
+        Ok(())
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        // Same as single-use values: only one caller gets the removed token
+        let token = token.to_owned();
+        if self.capabilities.get(&token).await.is_none() {
+            return Ok(None);
+        }
+        Ok(self.capabilities.remove(&token).await)
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        // Track the keys of the session's values, so they're deleted along with the session
+        let ttl = Duration::from_secs(ttl.into());
+        let existing = self.session_once_keys.get(&id.to_owned()).await;
+        let (mut keys, remaining) = existing
+            .map(|keys| (keys.to_owned(), keys.expiration().remaining()))
+            .unwrap_or_default();
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_owned());
+        }
+        let keys_ttl = remaining.map_or(ttl, |remaining| remaining.max(ttl));
+        self.session_once_keys
+            .insert(id.to_owned(), keys, keys_ttl)
+            .await;
+
+        self.once_values
+            .insert((id.to_owned(), key.to_owned()), value, ttl)
+            .await;
+        Ok(())
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        // Removing the value holds the cache's write lock, so only one caller gets it. Removal
+        // doesn't check the expiration, so check it first.
+        let key = (id.to_owned(), key.to_owned());
+        if self.once_values.get(&key).await.is_none() {
+            return Ok(None);
+        }
+        Ok(self.once_values.remove(&key).await)
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let ttl = Duration::from_secs(ttl.into());
+        let existing = self.lazy_fields.get(&id.to_owned()).await;
+        let fields = match existing.map(|fields| Arc::clone(&fields)) {
+            Some(fields) => fields,
+            None => {
+                // Only one caller creates the fields of the session
+                let _guard = self.lazy_fields_lock.lock().await;
+                let existing = self.lazy_fields.get(&id.to_owned()).await;
+                match existing.map(|fields| Arc::clone(&fields)) {
+                    Some(fields) => fields,
+                    None => {
+                        let fields = LazyFields::default();
+                        let new_fields = Arc::clone(&fields);
+                        self.lazy_fields
+                            .insert(id.to_owned(), new_fields, ttl)
+                            .await;
+                        fields
+                    }
+                }
+            }
+        };
+        fields.lock().unwrap().insert(name.to_owned(), value);
+        // Extend the expiration of the fields to the session's TTL
+        self.lazy_fields.insert(id.to_owned(), fields, ttl).await;
+        Ok(())
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        let fields = self.lazy_fields.get(&id.to_owned()).await;
+        let Some(fields) = fields.map(|fields| Arc::clone(&fields)) else {
+            return Ok(None);
+        };
+        let value = fields.lock().unwrap().get(name).cloned();
+        Ok(value)
+    }
+
     async fn setup(&self) -> SessionResult<()> {
         let cache = self.cache.clone();
-        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-        spawn(async move {
+        let created = self.created.clone();
+        let activity = self.activity.clone();
+        let capabilities = self.capabilities.clone();
+        let session_capabilities = self.session_capabilities.clone();
+        let once_values = self.once_values.clone();
+        let session_once_keys = self.session_once_keys.clone();
+        let lazy_fields = self.lazy_fields.clone();
+        let monitor = TaskGuard::spawn(|shutdown_rx| async move {
             select! {
                 _ = cache.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = created.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = activity.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = capabilities.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = session_capabilities.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = once_values.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = session_once_keys.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
+                _ = lazy_fields.monitor(10, 0.25, Duration::from_secs(5 * 60)) => (),
                 _ = shutdown_rx => {
                     rocket::debug!("Session cache monitor shutdown");
                 }
             }
         });
-        self.shutdown_tx.lock().unwrap().replace(shutdown_tx);
+        // Replacing a previous monitor stops it
+        self.monitor.lock().unwrap().replace(monitor);
         Ok(())
     }
 
     async fn shutdown(&self) -> SessionResult<()> {
-        if let Some(tx) = self.shutdown_tx.lock().unwrap().take() {
-            let _ = tx.send(());
+        let monitor = self.monitor.lock().unwrap().take();
+        match monitor {
+            Some(monitor) => monitor.shutdown().await,
+            None => Ok(()),
         }
-        Ok(())
     }
 }
 
@@ -108,6 +402,9 @@ where
 /// You must implement the [`SessionIdentifier`] trait for your session type,
 /// and the [`SessionIdentifier::Id`] type must implement [`ToString`].
 ///
+/// Session [tags](SessionIdentifier::tags) are indexed as well, and session changes can be
+/// subscribed to with [`SessionStorageIndexed::subscribe_identifier`].
+///
 /// # Example
 /// ```rust
 /// use rocket_flex_session::storage::memory::MemoryStorageIndexed;
@@ -138,6 +435,12 @@ where
     base_storage: MemoryStorage<T>,
     // Index from identifier to set of session IDs
     identifier_index: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+    // Index from tag to set of session IDs
+    tag_index: Arc<Mutex<HashMap<String, HashSet<String>>>>,
+    // Last seen time of each identifier, for presence tracking
+    presence: Arc<Mutex<HashMap<String, OffsetDateTime>>>,
+    // Channel of session changes, along with their identifier
+    changes: broadcast::Sender<(String, SessionChange)>,
 }
 
 impl<T> Default for MemoryStorageIndexed<T>
@@ -149,6 +452,9 @@ where
         Self {
             base_storage: MemoryStorage::default(),
             identifier_index: Arc::default(),
+            tag_index: Arc::default(),
+            presence: Arc::default(),
+            changes: broadcast::channel(CHANGES_CAPACITY).0,
         }
     }
 }
@@ -158,19 +464,43 @@ where
     T: SessionIdentifier,
     T::Id: ToString,
 {
-    /// Update the identifier index when session data is saved
+    /// Update the identifier and tag indexes when session data is saved
     fn update_identifier_index(&self, session_id: &str, data: &T) {
         if let Some(id) = data.identifier() {
             let mut index = self.identifier_index.lock().unwrap();
             index
                 .entry(id.to_string())
-                .or_insert_with(HashSet::new)
+                .or_default()
                 .insert(session_id.to_owned());
         }
+        let tags = data.tags();
+        if !tags.is_empty() {
+            let mut index = self.tag_index.lock().unwrap();
+            for tag in tags {
+                index.entry(tag).or_default().insert(session_id.to_owned());
+            }
+        }
     }
 
-    /// Remove from identifier index when session is deleted
+    /// Publish a change to any subscribers of the identifier
+    fn publish_change(&self, identifier: String, change: SessionChange) {
+        let _ = self.changes.send((identifier, change)); // errors if there are no subscribers
+    }
+
+    /// Remove from identifier and tag indexes when session is deleted
     fn remove_from_identifier_index(&self, session_id: &str, data: &T) {
+        let tags = data.tags();
+        if !tags.is_empty() {
+            let mut index = self.tag_index.lock().unwrap();
+            for tag in tags {
+                if let Some(session_ids) = index.get_mut(&tag) {
+                    session_ids.remove(session_id);
+                    if session_ids.is_empty() {
+                        index.remove(&tag);
+                    }
+                }
+            }
+        }
         if let Some(id) = data.identifier() {
             let mut index = self.identifier_index.lock().unwrap();
             let key = id.to_string();
@@ -198,22 +528,147 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
         self.base_storage.load(id, ttl, cookie_jar).await
     }
 
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        self.base_storage
+            .load_with_metadata(id, ttl, cookie_jar)
+            .await
+    }
+
+    async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        self.base_storage.exists(id, cookie_jar).await
+    }
+
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.save_with_duration(id, data, Duration::from_secs(ttl.into()))
+            .await
+    }
+
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
         // Update identifier index before saving
         self.update_identifier_index(id, &data);
+        let identifier = data.identifier();
 
         // Save using base storage
-        self.base_storage.save(id, data, ttl).await
+        self.base_storage
+            .save_with_metadata(id, data, ttl, metadata)
+            .await?;
+        if let Some(identifier) = identifier {
+            self.publish_change(identifier.to_string(), SessionChange::Saved(id.to_owned()));
+        }
+        Ok(())
     }
 
     async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
         self.remove_from_identifier_index(id, &data);
-        self.base_storage.delete(id, data).await
+        let identifier = data.identifier();
+        self.base_storage.delete(id, data).await?;
+        if let Some(identifier) = identifier {
+            self.publish_change(
+                identifier.to_string(),
+                SessionChange::Deleted(id.to_owned()),
+            );
+        }
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.base_storage.delete_unreadable(id).await
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        self.base_storage.record_activity(id, entry, limit).await
+    }
+
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        self.base_storage.recent_activity(id).await
+    }
+
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        if let Some(identifier) = data.identifier() {
+            let mut presence = self.presence.lock().unwrap();
+            let entry = presence.entry(identifier.to_string()).or_insert(last_seen);
+            *entry = (*entry).max(last_seen);
+        }
+        Ok(())
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.base_storage
+            .save_capability(token, capability, ttl)
+            .await
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        self.base_storage.load_capability(token).await
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        self.base_storage.delete_capability(token).await
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        self.base_storage.take_capability(token).await
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.base_storage.save_once_value(id, key, value, ttl).await
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        self.base_storage.take_once_value(id, key).await
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        self.base_storage
+            .save_lazy_field(id, name, value, ttl)
+            .await
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        self.base_storage.load_lazy_field(id, name).await
     }
 
     async fn setup(&self) -> SessionResult<()> {
@@ -259,6 +714,26 @@ where
         Ok(session_ids.into_iter().collect())
     }
 
+    async fn get_session_metadata_by_identifier(
+        &self,
+        id: &T::Id,
+    ) -> SessionResult<Vec<(String, SessionMetadata)>> {
+        let session_ids = {
+            let index = self.identifier_index.lock().unwrap();
+            index.get(&id.to_string()).cloned().unwrap_or_default()
+        };
+
+        let mut sessions = Vec::new();
+        for session_id in session_ids {
+            if self.base_storage.cache.get(&session_id).await.is_some() {
+                let created_at = self.base_storage.created_at(&session_id).await;
+                sessions.push((session_id, SessionMetadata::new(created_at)));
+            }
+        }
+
+        Ok(sessions)
+    }
+
     async fn invalidate_sessions_by_identifier(
         &self,
         id: &T::Id,
@@ -270,12 +745,12 @@ where
             index.get(&id_str).cloned().unwrap_or_default()
         };
         if let Some(session_id) = excluded_session_id {
-            session_ids_to_remove.retain(|id| id != session_id);
+            session_ids_to_remove.retain(|id| !session_id_eq(id, session_id));
         }
 
         // Remove all sessions from cache
         for session_id in &session_ids_to_remove {
-            self.base_storage.cache.remove(session_id).await;
+            self.base_storage.remove_session(session_id).await;
         }
 
         // Remove all sessions from index
@@ -291,6 +766,160 @@ where
             }
         }
 
-        Ok(session_ids_to_remove.len() as u64)
+        let count = session_ids_to_remove.len() as u64;
+        for session_id in session_ids_to_remove {
+            self.publish_change(id_str.clone(), SessionChange::Deleted(session_id));
+        }
+        Ok(count)
+    }
+
+    async fn purge_identifier_index(&self, id: &T::Id) -> SessionResult<()> {
+        self.identifier_index
+            .lock()
+            .unwrap()
+            .remove(&id.to_string());
+        self.presence.lock().unwrap().remove(&id.to_string());
+        Ok(())
+    }
+
+    async fn invalidate_sessions_by_tag(&self, tag: &str) -> SessionResult<u64> {
+        let session_ids = {
+            let mut index = self.tag_index.lock().unwrap();
+            index.remove(tag).unwrap_or_default()
+        };
+
+        let mut count = 0;
+        for session_id in session_ids {
+            let cached = self.base_storage.cache.get(&session_id).await;
+            let Some(data) = cached.map(|data| data.value().to_owned()) else {
+                continue; // already expired
+            };
+            self.delete(&session_id, data).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn online_identifiers(&self, since: OffsetDateTime) -> SessionResult<Vec<String>> {
+        let mut online: Vec<(String, OffsetDateTime)> = {
+            let presence = self.presence.lock().unwrap();
+            presence
+                .iter()
+                .filter(|(_, last_seen)| **last_seen >= since)
+                .map(|(identifier, last_seen)| (identifier.clone(), *last_seen))
+                .collect()
+        };
+        online.sort_by_key(|(_, last_seen)| Reverse(*last_seen));
+        Ok(online
+            .into_iter()
+            .map(|(identifier, _)| identifier)
+            .collect())
+    }
+
+    async fn session_analytics(
+        &self,
+        active_since: OffsetDateTime,
+    ) -> SessionResult<SessionAnalytics> {
+        let index = self.identifier_index.lock().unwrap().clone();
+        let mut analytics = SessionAnalytics::default();
+        let (mut total_age, mut aged_sessions) = (Duration::ZERO, 0u32);
+        let now = OffsetDateTime::now_utc();
+        for session_ids in index.values() {
+            let mut has_sessions = false;
+            for session_id in session_ids {
+                if self.base_storage.cache.get(session_id).await.is_none() {
+                    continue; // already expired
+                }
+                has_sessions = true;
+                analytics.sessions += 1;
+                if let Some(created_at) = self.base_storage.created_at(session_id).await {
+                    total_age += Duration::try_from(now - created_at).unwrap_or_default();
+                    aged_sessions += 1;
+                }
+            }
+            analytics.identifiers += u64::from(has_sessions);
+        }
+        analytics.average_age = (aged_sessions > 0).then(|| total_age / aged_sessions);
+        analytics.active_identifiers = {
+            let presence = self.presence.lock().unwrap();
+            let active = presence
+                .values()
+                .filter(|last_seen| **last_seen >= active_since);
+            active.count() as u64
+        };
+        Ok(analytics)
+    }
+
+    async fn subscribe_identifier(&self, id: &T::Id) -> SessionResult<SessionChangeStream> {
+        let id_str = id.to_string();
+        let receiver = self.changes.subscribe();
+        Ok(broadcast_stream(
+            receiver,
+            (),
+            move |(identifier, change)| (identifier == id_str).then_some(change),
+        ))
+    }
+}
+
+/// In-memory implementation of [`LoginThrottle`]. This is designed mostly for local
+/// development and single-instance deployments, as attempts aren't shared between processes.
+///
+/// # Example
+/// ```rust
+/// use rocket_flex_session::storage::memory::MemoryThrottle;
+///
+/// let throttle = MemoryThrottle::builder()
+///     .max_attempts(5)
+///     .window(15 * 60)
+///     .build();
+/// ```
+#[derive(Builder)]
+pub struct MemoryThrottle {
+    /// The maximum number of failed attempts before an identifier is locked out (default: 5)
+    #[builder(default = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u32,
+    /// The window in seconds in which failed attempts are counted (default: 15 minutes)
+    #[builder(default = DEFAULT_WINDOW)]
+    window: u32,
+    #[builder(skip)]
+    attempts: Mutex<HashMap<String, (u32, Instant)>>,
+}
+
+impl Default for MemoryThrottle {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[async_trait]
+impl LoginThrottle for MemoryThrottle {
+    fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    async fn record_failed_login(&self, identifier: &str) -> SessionResult<u32> {
+        let now = Instant::now();
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.retain(|_, (_, expires)| *expires > now);
+
+        let (count, _) = attempts
+            .entry(identifier.to_owned())
+            .or_insert((0, now + Duration::from_secs(self.window.into())));
+        *count += 1;
+        Ok(*count)
+    }
+
+    async fn failed_attempts(&self, identifier: &str) -> SessionResult<u32> {
+        let attempts = self.attempts.lock().unwrap();
+        let count = attempts
+            .get(identifier)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map_or(0, |(count, _)| *count);
+        Ok(count)
+    }
+
+    async fn reset_failed_logins(&self, identifier: &str) -> SessionResult<()> {
+        self.attempts.lock().unwrap().remove(identifier);
+        Ok(())
     }
 }
@@ -0,0 +1,382 @@
+//! Read-through support for Rails session payloads
+//!
+//! [`RailsCodec`] decodes session payloads written by a Rails app into your session data type,
+//! for hybrid deployments where Rails and Rocket serve the same users during a migration. This
+//! covers:
+//! - Sessions in server-side stores (e.g. redis-session-store or activerecord-session_store),
+//!   serialized with Marshal or JSON
+//! - Payloads signed with `ActiveSupport::MessageVerifier` (`<base64 data>--<hex digest>`),
+//!   including the metadata envelope (`_rails`) used since Rails 5.2
+//!
+//! Marshal payloads are converted to JSON values before deserializing your data type: symbols
+//! and strings become JSON strings, hash keys become strings, and Ruby objects become maps of
+//! their instance variables (without the `@`). Encrypted cookies (the default Rails cookie
+//! store) aren't supported. The `exp` field of the metadata envelope isn't checked, as the
+//! expiration of the session is handled by the storage provider.
+//!
+//! # Example
+//! ```rust
+//! use rocket::serde::Deserialize;
+//! use rocket_flex_session::storage::rails::{RailsCodec, RailsSerializer};
+//!
+//! #[derive(Deserialize)]
+//! #[serde(crate = "rocket::serde")]
+//! struct RailsSession {
+//!     user_id: u64,
+//! }
+//!
+//! let codec = RailsCodec::builder()
+//!     .serializer(RailsSerializer::Json)
+//!     .build();
+//! let session: RailsSession = codec.decode(br#"{"user_id":1}"#).unwrap();
+//! assert_eq!(session.user_id, 1);
+//! ```
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bon::Builder;
+use hmac::{Hmac, Mac};
+use rocket::serde::{
+    de::DeserializeOwned,
+    json::serde_json::{self, Map, Number, Value},
+};
+
+use crate::error::{SessionError, SessionResult};
+
+/// The serializer used by the Rails app for the session data
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RailsSerializer {
+    /// JSON (the default since Rails 7)
+    #[default]
+    Json,
+    /// Ruby's Marshal format
+    Marshal,
+}
+
+/// The digest used to sign payloads with `ActiveSupport::MessageVerifier`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RailsDigest {
+    /// SHA1 (the default of `MessageVerifier`)
+    #[default]
+    Sha1,
+    /// SHA256
+    Sha256,
+}
+
+/// Decodes Rails session payloads. See the [module docs](self).
+#[derive(Builder, Clone, Debug)]
+pub struct RailsCodec {
+    /// The secret of the message verifier, if the payloads are signed. This is the derived
+    /// secret, which can be generated in a Rails console with e.g.
+    /// `Rails.application.key_generator.generate_key("signed cookie")`.
+    #[builder(into)]
+    secret: Option<Vec<u8>>,
+    /// The serializer of the session data (default: JSON)
+    #[builder(default)]
+    serializer: RailsSerializer,
+    /// The digest of the signed payloads (default: SHA1)
+    #[builder(default)]
+    digest: RailsDigest,
+}
+
+impl RailsCodec {
+    /// Decode the payload into your session data type
+    pub fn decode<T: DeserializeOwned>(&self, payload: &[u8]) -> SessionResult<T> {
+        let value = self.decode_value(payload)?;
+        serde_json::from_value(value).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+
+    /// Decode the payload into a JSON value
+    pub fn decode_value(&self, payload: &[u8]) -> SessionResult<Value> {
+        let Some(secret) = &self.secret else {
+            return self.deserialize(payload);
+        };
+        let data = self.verify(secret, payload)?;
+        let data = STANDARD
+            .decode(data)
+            .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+
+        // Unwrap the metadata envelope of Rails 5.2+, which is always JSON
+        if let Ok(Value::Object(mut envelope)) = serde_json::from_slice::<Value>(&data) {
+            if let Some(Value::Object(mut metadata)) = envelope.remove("_rails") {
+                if let Some(data) = metadata.remove("data") {
+                    return Ok(data);
+                }
+                let Some(Value::String(message)) = metadata.remove("message") else {
+                    return Err(SessionError::InvalidData);
+                };
+                let message = STANDARD
+                    .decode(message)
+                    .map_err(|e| SessionError::Parsing(Box::new(e)))?;
+                return self.deserialize(&message);
+            }
+        }
+        self.deserialize(&data)
+    }
+
+    /// Verify the signature of a `<data>--<hex digest>` payload, and return the data
+    fn verify<'p>(&self, secret: &[u8], payload: &'p [u8]) -> SessionResult<&'p [u8]> {
+        let separator = payload
+            .windows(2)
+            .rposition(|window| window == b"--")
+            .ok_or(SessionError::InvalidData)?;
+        let (data, digest) = (&payload[..separator], &payload[separator + 2..]);
+        let digest = decode_hex(digest).ok_or(SessionError::InvalidData)?;
+
+        let verified = match self.digest {
+            RailsDigest::Sha1 => Hmac::<sha1::Sha1>::new_from_slice(secret)
+                .map(|mac| mac.chain_update(data).verify_slice(&digest).is_ok()),
+            RailsDigest::Sha256 => Hmac::<sha2::Sha256>::new_from_slice(secret)
+                .map(|mac| mac.chain_update(data).verify_slice(&digest).is_ok()),
+        };
+        match verified {
+            Ok(true) => Ok(data),
+            _ => Err(SessionError::InvalidData),
+        }
+    }
+
+    fn deserialize(&self, data: &[u8]) -> SessionResult<Value> {
+        match self.serializer {
+            RailsSerializer::Json => {
+                serde_json::from_slice(data).map_err(|e| SessionError::Parsing(Box::new(e)))
+            }
+            RailsSerializer::Marshal => marshal_to_json(data),
+        }
+    }
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+/// Convert a Ruby Marshal (version 4.8) payload into a JSON value. See the [module docs](self)
+/// for how Ruby values are converted.
+pub fn marshal_to_json(data: &[u8]) -> SessionResult<Value> {
+    let [4, 8, data @ ..] = data else {
+        return Err(SessionError::Unsupported("version of the Marshal payload"));
+    };
+    let mut reader = MarshalReader {
+        data,
+        symbols: Vec::new(),
+        objects: Vec::new(),
+    };
+    reader.read_value().ok_or(SessionError::InvalidData)
+}
+
+/// Reader of Marshal payloads, tracking the symbol and object tables for links
+struct MarshalReader<'a> {
+    data: &'a [u8],
+    symbols: Vec<String>,
+    objects: Vec<Value>,
+}
+
+impl<'a> MarshalReader<'a> {
+    fn read_byte(&mut self) -> Option<u8> {
+        let (byte, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(*byte)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn read_int(&mut self) -> Option<i64> {
+        let c = self.read_byte()? as i8;
+        match c {
+            0 => Some(0),
+            1..=4 => {
+                let mut value = 0i64;
+                for i in 0..c {
+                    value |= i64::from(self.read_byte()?) << (8 * i);
+                }
+                Some(value)
+            }
+            -4..=-1 => {
+                let mut value = -1i64;
+                for i in 0..-c {
+                    value &= !(0xff << (8 * i));
+                    value |= i64::from(self.read_byte()?) << (8 * i);
+                }
+                Some(value)
+            }
+            5.. => Some(i64::from(c) - 5),
+            _ => Some(i64::from(c) + 5),
+        }
+    }
+
+    fn read_len(&mut self) -> Option<usize> {
+        self.read_int()?.try_into().ok()
+    }
+
+    fn read_string(&mut self) -> Option<String> {
+        let len = self.read_len()?;
+        let bytes = self.read_bytes(len)?;
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn read_symbol(&mut self) -> Option<String> {
+        let tag = self.read_byte()?;
+        self.read_symbol_tagged(tag)
+    }
+
+    fn read_symbol_tagged(&mut self, tag: u8) -> Option<String> {
+        match tag {
+            b':' => {
+                let symbol = self.read_string()?;
+                self.symbols.push(symbol.clone());
+                Some(symbol)
+            }
+            b';' => {
+                let index = self.read_len()?;
+                self.symbols.get(index).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    /// Reserve a slot in the object table for links, to be filled once the object is read
+    fn reserve_object(&mut self) -> usize {
+        self.objects.push(Value::Null);
+        self.objects.len() - 1
+    }
+
+    fn store_object(&mut self, index: usize, value: Value) -> Option<Value> {
+        self.objects[index] = value.clone();
+        Some(value)
+    }
+
+    fn read_value(&mut self) -> Option<Value> {
+        let tag = self.read_byte()?;
+        match tag {
+            b'0' => Some(Value::Null),
+            b'T' => Some(Value::Bool(true)),
+            b'F' => Some(Value::Bool(false)),
+            b'i' => Some(Value::from(self.read_int()?)),
+            b':' | b';' => self.read_symbol_tagged(tag).map(Value::String),
+            b'@' => {
+                let index = self.read_len()?;
+                self.objects.get(index).cloned()
+            }
+            // Instance variables (e.g. the encoding of a string), which are skipped
+            b'I' => {
+                let value = self.read_value()?;
+                for _ in 0..self.read_len()? {
+                    self.read_symbol()?;
+                    self.read_value()?;
+                }
+                Some(value)
+            }
+            // Objects extended with a module, and subclasses of String, Array, or Hash
+            // (e.g. `HashWithIndifferentAccess`)
+            b'e' | b'C' => {
+                self.read_symbol()?;
+                self.read_value()
+            }
+            b'"' => {
+                let index = self.reserve_object();
+                let string = self.read_string()?;
+                self.store_object(index, Value::String(string))
+            }
+            b'f' => {
+                let index = self.reserve_object();
+                let float = match self.read_string()?.as_str() {
+                    "inf" | "-inf" | "nan" => Value::Null,
+                    float => Value::Number(Number::from_f64(float.parse().ok()?)?),
+                };
+                self.store_object(index, float)
+            }
+            b'l' => {
+                let index = self.reserve_object();
+                let sign = self.read_byte()?;
+                let len = self.read_len()? * 2;
+                let bytes = self.read_bytes(len)?;
+                let magnitude = bytes.iter().rev().try_fold(0u128, |value, byte| {
+                    value.checked_mul(256)?.checked_add(u128::from(*byte))
+                })?;
+                let bignum = match sign {
+                    b'-' => i64::try_from(magnitude)
+                        .map(|n| Value::from(-n))
+                        .unwrap_or_else(|_| Value::String(format!("-{magnitude}"))),
+                    _ => u64::try_from(magnitude)
+                        .map(Value::from)
+                        .unwrap_or_else(|_| Value::String(magnitude.to_string())),
+                };
+                self.store_object(index, bignum)
+            }
+            b'[' => {
+                let index = self.reserve_object();
+                let array = (0..self.read_len()?)
+                    .map(|_| self.read_value())
+                    .collect::<Option<Vec<_>>>()?;
+                self.store_object(index, Value::Array(array))
+            }
+            b'{' | b'}' => {
+                let index = self.reserve_object();
+                let mut map = Map::new();
+                for _ in 0..self.read_len()? {
+                    let key = match self.read_value()? {
+                        Value::String(key) => key,
+                        key => key.to_string(),
+                    };
+                    map.insert(key, self.read_value()?);
+                }
+                if tag == b'}' {
+                    // Default value of the hash
+                    self.read_value()?;
+                }
+                self.store_object(index, Value::Object(map))
+            }
+            // Objects and structs, which are converted to a map of their fields
+            b'o' | b'S' => {
+                let index = self.reserve_object();
+                self.read_symbol()?;
+                let mut map = Map::new();
+                for _ in 0..self.read_len()? {
+                    let field = self.read_symbol()?;
+                    let field = field.strip_prefix('@').unwrap_or(&field).to_owned();
+                    map.insert(field, self.read_value()?);
+                }
+                self.store_object(index, Value::Object(map))
+            }
+            // Objects with custom serialization (`_dump` and `marshal_dump`)
+            b'u' => {
+                let index = self.reserve_object();
+                self.read_symbol()?;
+                let dumped = self.read_string()?;
+                self.store_object(index, Value::String(dumped))
+            }
+            b'U' => {
+                let index = self.reserve_object();
+                self.read_symbol()?;
+                let dumped = self.read_value()?;
+                self.store_object(index, dumped)
+            }
+            // Regular expressions, classes, and modules, which are converted to their source or name
+            b'/' => {
+                let index = self.reserve_object();
+                let source = self.read_string()?;
+                self.read_byte()?;
+                self.store_object(index, Value::String(source))
+            }
+            b'c' | b'm' | b'M' => {
+                let index = self.reserve_object();
+                let name = self.read_string()?;
+                self.store_object(index, Value::String(name))
+            }
+            _ => None,
+        }
+    }
+}
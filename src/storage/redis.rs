@@ -3,7 +3,7 @@
 #[cfg(feature = "redis_fred")]
 mod fred;
 #[cfg(feature = "redis_fred")]
-pub use fred::RedisFredStorage;
+pub use fred::{RedisFredStorage, RedisFredThrottle};
 
 use crate::SessionIdentifier;
 
@@ -1,11 +1,21 @@
 use bon::Builder;
-use fred::prelude::{HashesInterface, KeysInterface, SetsInterface, Value};
-use rocket::http::CookieJar;
+use fred::prelude::{
+    ClientLike, EventInterface, HashesInterface, KeysInterface, ListInterface, PubsubInterface,
+    SetsInterface, SortedSetsInterface, Value,
+};
+use std::time::Duration;
+
+use rocket::time::OffsetDateTime;
 
 use crate::{
     error::{SessionError, SessionResult},
-    storage::{SessionStorage, SessionStorageIndexed},
-    SessionIdentifier,
+    security::session_id_eq,
+    storage::{
+        change::broadcast_stream, SessionChange, SessionChangeStream, SessionContext,
+        SessionMetadata, SessionStorage, SessionStorageIndexed,
+    },
+    throttle::{LoginThrottle, DEFAULT_MAX_ATTEMPTS, DEFAULT_WINDOW},
+    ActivityEntry, Capability, SessionIdentifier,
 };
 
 use super::{RedisFormat, RedisValue, SessionRedis};
@@ -30,6 +40,46 @@ const TWO_WEEKS_TTL: u32 = 60 * 60 * 24 * 7 * 2;
 ///
 /// `<index_prefix>:<id>` (e.g.: `sess:user:1`)
 ///
+/// ## Tags
+/// Session [tags](SessionIdentifier::tags) are indexed using Redis sets as well, with a key format of:
+///
+/// `<tag_prefix>:<tag>` (e.g.: `sess:tag:device-type=mobile`)
+///
+/// ## Activity log
+/// If enabled, the session activity log is stored in a Redis list with a key of
+/// `<prefix>:<id>:activity`, and expires along with the session.
+///
+/// ## Creation time
+/// The creation time of each session is stored as a Unix timestamp in a Redis string with a key
+/// of `<prefix>:<id>:created`, and expires along with the session.
+///
+/// ## Capability tokens
+/// [Capability tokens](crate::Session::create_capability) are stored in Redis strings with a key
+/// of `<prefix>:cap:<token>`, and expire after their TTL. The tokens of each session are tracked
+/// in a Redis set with a key of `<prefix>:<id>:caps`, and are deleted along with the session.
+///
+/// ## Single-use values
+/// [Single-use values](crate::Session::take_once) are stored in Redis strings with a key of
+/// `<prefix>:<id>:once:<key>`, expire after their TTL, and are taken with `GETDEL`. The keys of
+/// each session's values are tracked in a Redis set with a key of `<prefix>:<id>:onces`, and are
+/// deleted along with the session.
+///
+/// ## Lazy fields
+/// [Lazy fields](crate::lazy) are stored in a Redis hash with a key of `<prefix>:<id>:fields`,
+/// and expire along with the session.
+///
+/// ## Presence
+/// If [presence tracking](crate::presence) is enabled, the last seen time of each identifier is
+/// stored in a Redis sorted set with a key of `<prefix>:presence`, scored by Unix timestamp.
+/// Identifiers that haven't been seen within the `index_ttl` are removed from the set.
+///
+/// ## Session changes
+/// If `notify_changes` is enabled, changes to indexed sessions are published to a Redis
+/// pub/sub channel with the same name as the index key (e.g.: `sess:user:1`), and can be
+/// subscribed to with [`SessionStorageIndexed::subscribe_identifier`]. Each subscription
+/// opens a new connection to Redis. Changes are published after they're stored, so a failure
+/// to publish is logged, without failing the operation.
+///
 /// # Example
 /// A full Redis example can be found in the crate's examples directory.
 #[derive(Builder)]
@@ -45,6 +95,12 @@ pub struct RedisFredStorage {
     /// The TTL in seconds for the session index keys - should match your longest expected session duration (default: 2 weeks).
     #[builder(default = TWO_WEEKS_TTL)]
     index_ttl: u32,
+    /// The prefix to use for session tag keys
+    #[builder(into, default = "sess:tag:")]
+    tag_prefix: String,
+    /// Publish changes to indexed sessions via Redis pub/sub (default: `false`)
+    #[builder(default)]
+    notify_changes: bool,
 }
 
 impl RedisFredStorage {
@@ -52,10 +108,75 @@ impl RedisFredStorage {
         format!("{}{id}", self.prefix)
     }
 
+    fn session_activity_key(&self, id: &str) -> String {
+        format!("{}{id}:activity", self.prefix)
+    }
+
+    fn session_created_key(&self, id: &str) -> String {
+        format!("{}{id}:created", self.prefix)
+    }
+
+    fn capability_key(&self, token: &str) -> String {
+        format!("{}cap:{token}", self.prefix)
+    }
+
+    fn session_capabilities_key(&self, id: &str) -> String {
+        format!("{}{id}:caps", self.prefix)
+    }
+
+    fn once_value_key(&self, id: &str, key: &str) -> String {
+        format!("{}{id}:once:{key}", self.prefix)
+    }
+
+    fn session_once_keys_key(&self, id: &str) -> String {
+        format!("{}{id}:onces", self.prefix)
+    }
+
+    fn lazy_fields_key(&self, id: &str) -> String {
+        format!("{}{id}:fields", self.prefix)
+    }
+
+    /// Keys of the artifacts derived from the sessions (creation times, activity logs, lazy
+    /// fields, capability tokens, and single-use values), which are deleted along with the sessions
+    async fn session_artifact_keys(&self, session_ids: &[String]) -> SessionResult<Vec<String>> {
+        let capabilities_keys: Vec<_> = session_ids
+            .iter()
+            .map(|id| self.session_capabilities_key(id))
+            .collect();
+        let once_keys_keys: Vec<_> = session_ids
+            .iter()
+            .map(|id| self.session_once_keys_key(id))
+            .collect();
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.sunion(capabilities_keys.clone()).await?;
+        let _: () = pipeline.sunion(once_keys_keys.clone()).await?;
+        let (tokens, once_keys): (Vec<String>, Vec<String>) = pipeline.all().await?;
+
+        let mut keys: Vec<_> = session_ids
+            .iter()
+            .map(|id| self.session_activity_key(id))
+            .collect();
+        keys.extend(session_ids.iter().map(|id| self.session_created_key(id)));
+        keys.extend(session_ids.iter().map(|id| self.lazy_fields_key(id)));
+        keys.extend(capabilities_keys);
+        keys.extend(tokens.iter().map(|token| self.capability_key(token)));
+        keys.extend(once_keys_keys);
+        keys.extend(once_keys);
+        Ok(keys)
+    }
+
+    fn presence_key(&self) -> String {
+        format!("{}presence", self.prefix)
+    }
+
     fn session_index_key(&self, identifier: &str) -> String {
         format!("{}{identifier}", self.index_prefix)
     }
 
+    fn session_tag_key(&self, tag: &str) -> String {
+        format!("{}{tag}", self.tag_prefix)
+    }
+
     async fn fetch_session_index(&self, identifier: &str) -> SessionResult<(Vec<String>, String)> {
         let index_key = self.session_index_key(identifier);
         let session_ids = self.pool.smembers(&index_key).await?;
@@ -71,6 +192,37 @@ impl RedisFredStorage {
         .ok_or(SessionError::InvalidData)
     }
 
+    /// Fetch and parse the data of a session, or `None` if it doesn't exist
+    async fn fetch_session_data<T>(&self, id: &str) -> SessionResult<Option<T>>
+    where
+        T: SessionRedis,
+        <T as SessionIdentifier>::Id: AsRef<str>,
+    {
+        let key = self.session_key(id);
+        let value: Option<Value> = match T::REDIS_FORMAT {
+            RedisFormat::String | RedisFormat::Bytes => self.pool.get(&key).await?,
+            RedisFormat::Map => self.pool.hgetall(&key).await?,
+        };
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let typed_value = self.to_typed_value(T::REDIS_FORMAT, value)?;
+        let data = T::from_redis(typed_value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+        Ok(Some(data))
+    }
+
+    /// Publish a change to a session belonging to the identifier, if enabled. This is done after
+    /// the change is stored, so a failure is logged instead of failing the operation.
+    async fn publish_change(&self, identifier: &str, change: SessionChange) {
+        if self.notify_changes {
+            let channel = self.session_index_key(identifier);
+            let result: Result<(), _> = self.pool.next().publish(channel, change.encode()).await;
+            if let Err(e) = result {
+                rocket::warn!("Error publishing session change: {e}");
+            }
+        }
+    }
+
     async fn cleanup_session_index(
         &self,
         index_key: &str,
@@ -94,37 +246,87 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        _cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
-        let key = self.session_key(id);
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        let (key, created_key) = (self.session_key(id), self.session_created_key(id));
         let pipeline = self.pool.next().pipeline();
         let _: () = match T::REDIS_FORMAT {
             RedisFormat::String | RedisFormat::Bytes => pipeline.get(&key).await?,
             RedisFormat::Map => pipeline.hgetall(&key).await?,
         };
         let _: () = pipeline.ttl(&key).await?;
+        let _: () = pipeline.get(&created_key).await?;
 
-        let (value, orig_ttl): (Option<Value>, i64) = match ttl {
+        let (value, orig_ttl, created): (Option<Value>, i64, Option<i64>) = match ttl {
             None => pipeline.all().await?,
             Some(new_ttl) => {
                 let _: () = pipeline.expire(&key, new_ttl.into(), None).await?;
-                let (value, orig_ttl, _expire_result): (Option<Value>, i64, Option<u8>) =
-                    pipeline.all().await?;
-                (value, orig_ttl)
+                let _: () = pipeline.expire(&created_key, new_ttl.into(), None).await?;
+                let fields_key = self.lazy_fields_key(id);
+                let _: () = pipeline.expire(&fields_key, new_ttl.into(), None).await?;
+                let (value, orig_ttl, created, _, _, _): (
+                    Option<Value>,
+                    i64,
+                    Option<i64>,
+                    Option<u8>,
+                    Option<u8>,
+                    Option<u8>,
+                ) = pipeline.all().await?;
+                (value, orig_ttl, created)
             }
         };
 
         let value = value.ok_or(SessionError::NotFound)?;
         let typed_value = self.to_typed_value(T::REDIS_FORMAT, value)?;
         let data = T::from_redis(typed_value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+        let created_at = created.and_then(|ts| OffsetDateTime::from_unix_timestamp(ts).ok());
+
+        Ok((
+            data,
+            ttl.unwrap_or(orig_ttl.try_into().unwrap_or(0)),
+            SessionMetadata::new(created_at),
+        ))
+    }
 
-        Ok((data, ttl.unwrap_or(orig_ttl.try_into().unwrap_or(0))))
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        let count: u32 = self.pool.exists(self.session_key(id)).await?;
+        Ok(count > 0)
     }
 
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
-        use fred::types::Expiration;
+        self.save_with_duration(id, data, Duration::from_secs(ttl.into()))
+            .await
+    }
 
-        if let Some(identifier) = data.identifier() {
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        use fred::types::{Expiration, SetOptions};
+
+        // Expire with millisecond precision (rounded up), via PX and PEXPIRE
+        let ttl_ms = i64::try_from(ttl.as_nanos().div_ceil(1_000_000)).unwrap_or(i64::MAX);
+
+        let identifier = data.identifier();
+        if let Some(identifier) = &identifier {
             let index_key = self.session_index_key(identifier.as_ref());
             let pipeline = self.pool.next().pipeline();
             let _: () = pipeline.sadd(&index_key, id).await?;
@@ -133,6 +335,18 @@ where
                 .await?;
             let _: () = pipeline.all().await?;
         }
+        let tags = data.tags();
+        if !tags.is_empty() {
+            let pipeline = self.pool.next().pipeline();
+            for tag in tags {
+                let tag_key = self.session_tag_key(&tag);
+                let _: () = pipeline.sadd(&tag_key, id).await?;
+                let _: () = pipeline
+                    .expire(&tag_key, self.index_ttl.into(), None)
+                    .await?;
+            }
+            let _: () = pipeline.all().await?;
+        }
 
         let key = self.session_key(id);
         let value = data
@@ -141,33 +355,235 @@ where
         let _: () = match value {
             RedisValue::String(val) => {
                 self.pool
-                    .set(&key, val, Some(Expiration::EX(ttl.into())), None, false)
+                    .set(&key, val, Some(Expiration::PX(ttl_ms)), None, false)
                     .await?
             }
             RedisValue::Bytes(val) => {
                 self.pool
-                    .set(&key, val, Some(Expiration::EX(ttl.into())), None, false)
+                    .set(&key, val, Some(Expiration::PX(ttl_ms)), None, false)
                     .await?
             }
             RedisValue::Map(map) => {
                 let pipeline = self.pool.next().pipeline();
                 let _: () = pipeline.hset(&key, map).await?;
-                let _: () = pipeline.expire(&key, ttl.into(), None).await?;
+                let _: () = pipeline.pexpire(&key, ttl_ms, None).await?;
                 pipeline.all().await?
             }
         };
+
+        // Set the creation time if it's a new session, and expire it and the lazy fields along
+        // with the session
+        let created_key = self.session_created_key(id);
+        let pipeline = self.pool.next().pipeline();
+        let created_at = metadata.created_at.unwrap_or_else(OffsetDateTime::now_utc);
+        let _: () = pipeline
+            .set(
+                &created_key,
+                created_at.unix_timestamp(),
+                None,
+                Some(SetOptions::NX),
+                false,
+            )
+            .await?;
+        let _: () = pipeline.pexpire(&created_key, ttl_ms, None).await?;
+        let _: () = pipeline
+            .pexpire(self.lazy_fields_key(id), ttl_ms, None)
+            .await?;
+        let _: () = pipeline.all().await?;
+        if let Some(identifier) = identifier {
+            self.publish_change(identifier.as_ref(), SessionChange::Saved(id.to_owned()))
+                .await;
+        }
         Ok(())
     }
 
     async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        let mut keys = self.session_artifact_keys(&[id.to_owned()]).await?;
+        keys.push(self.session_key(id));
         let pipeline = self.pool.next().pipeline();
-        let _: () = pipeline.del(self.session_key(id)).await?;
+        let _: () = pipeline.del(keys).await?;
         if let Some(identifier) = data.identifier() {
             let session_idx_key = self.session_index_key(identifier.as_ref());
             let _: () = pipeline.srem(&session_idx_key, id).await?;
+            if self.notify_changes {
+                let message = SessionChange::Deleted(id.to_owned()).encode();
+                let _: () = pipeline.publish(&session_idx_key, message).await?;
+            }
+        }
+        for tag in data.tags() {
+            let _: () = pipeline.srem(self.session_tag_key(&tag), id).await?;
+        }
+        Ok(pipeline.all().await?)
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        let mut keys = self.session_artifact_keys(&[id.to_owned()]).await?;
+        keys.push(self.session_key(id));
+        let _: () = self.pool.del(keys).await?;
+        Ok(())
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        let session_ttl: i64 = self.pool.ttl(self.session_key(id)).await?;
+        if session_ttl <= 0 {
+            return Ok(());
+        }
+        let key = self.session_activity_key(id);
+        if limit == 0 {
+            // `LTRIM 0 -1` would keep the whole list
+            let _: () = self.pool.del(&key).await?;
+            return Ok(());
+        }
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.lpush(&key, entry.encode()).await?;
+        let _: () = pipeline.ltrim(&key, 0, limit as i64 - 1).await?;
+        let _: () = pipeline.expire(&key, session_ttl, None).await?;
+        Ok(pipeline.all().await?)
+    }
+
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        let entries: Vec<String> = self
+            .pool
+            .lrange(self.session_activity_key(id), 0, -1)
+            .await?;
+        Ok(entries
+            .iter()
+            .filter_map(|entry| ActivityEntry::decode(entry))
+            .collect())
+    }
+
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        let Some(identifier) = data.identifier() else {
+            return Ok(());
+        };
+        let key = self.presence_key();
+        let timestamp = last_seen.unix_timestamp();
+        let stale_before = timestamp - i64::from(self.index_ttl);
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline
+            .zadd(
+                &key,
+                None,
+                None,
+                false,
+                false,
+                (timestamp as f64, identifier.as_ref()),
+            )
+            .await?;
+        let _: () = pipeline
+            .zremrangebyscore(&key, "-inf", stale_before as f64)
+            .await?;
+        let _: () = pipeline.expire(&key, self.index_ttl.into(), None).await?;
+        Ok(pipeline.all().await?)
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        use fred::types::Expiration;
+
+        // Track the tokens of the session, so they're deleted along with the session
+        let capabilities_key = self.session_capabilities_key(&capability.session_id);
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline
+            .set(
+                self.capability_key(token),
+                capability.encode(),
+                Some(Expiration::EX(ttl.into())),
+                None,
+                false,
+            )
+            .await?;
+        let _: () = pipeline.sadd(&capabilities_key, token).await?;
+        let _: () = pipeline
+            .expire(&capabilities_key, self.index_ttl.into(), None)
+            .await?;
+        Ok(pipeline.all().await?)
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        let value: Option<String> = self.pool.get(self.capability_key(token)).await?;
+        let value = value.ok_or(SessionError::NotFound)?;
+        Capability::decode(&value).ok_or(SessionError::InvalidData)
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        let _: () = self.pool.del(self.capability_key(token)).await?;
+        Ok(())
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        let value: Option<String> = self.pool.getdel(self.capability_key(token)).await?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let capability = Capability::decode(&value).ok_or(SessionError::InvalidData)?;
+        let capabilities_key = self.session_capabilities_key(&capability.session_id);
+        let _: () = self.pool.srem(capabilities_key, token).await?;
+        Ok(Some(capability))
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        use fred::types::{Expiration, ExpireOptions};
+
+        // Track the keys of the session's values, so they're deleted along with the session.
+        // The set lives as long as its longest-lived value: its TTL is set if it's new (`NX`),
+        // and only extended otherwise (`GT`). This requires Redis 7.0 or later.
+        let key = self.once_value_key(id, key);
+        let once_keys_key = self.session_once_keys_key(id);
+        let expiration = Some(Expiration::EX(ttl.into()));
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.set(&key, value, expiration, None, false).await?;
+        let _: () = pipeline.sadd(&once_keys_key, &key).await?;
+        for option in [ExpireOptions::NX, ExpireOptions::GT] {
+            let _: () = pipeline
+                .expire(&once_keys_key, ttl.into(), Some(option))
+                .await?;
         }
         Ok(pipeline.all().await?)
     }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        let key = self.once_value_key(id, key);
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.getdel(&key).await?;
+        let _: () = pipeline.srem(self.session_once_keys_key(id), &key).await?;
+        let (value, _): (Option<String>, u8) = pipeline.all().await?;
+        Ok(value)
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let key = self.lazy_fields_key(id);
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.hset(&key, (name, value)).await?;
+        let _: () = pipeline.expire(&key, ttl.into(), None).await?;
+        let _: () = pipeline.all().await?;
+        Ok(())
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        Ok(self.pool.hget(self.lazy_fields_key(id), name).await?)
+    }
 }
 
 #[rocket::async_trait]
@@ -181,7 +597,7 @@ where
 
         let session_exist_pipeline = self.pool.next().pipeline();
         for session_id in &session_ids {
-            let session_key = self.session_key(&session_id);
+            let session_key = self.session_key(session_id);
             let _: () = session_exist_pipeline.exists(&session_key).await?;
         }
         let session_exist_results: Vec<bool> = session_exist_pipeline.all().await?;
@@ -199,12 +615,42 @@ where
         Ok(sessions)
     }
 
+    async fn get_session_metadata_by_identifier(
+        &self,
+        id: &T::Id,
+    ) -> SessionResult<Vec<(String, SessionMetadata)>> {
+        let session_ids =
+            SessionStorageIndexed::<T>::get_session_ids_by_identifier(self, id).await?;
+        if session_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let created_pipeline = self.pool.next().pipeline();
+        for session_id in &session_ids {
+            let _: () = created_pipeline
+                .get(self.session_created_key(session_id))
+                .await?;
+        }
+        let created: Vec<Option<i64>> = created_pipeline.all().await?;
+
+        let sessions = session_ids
+            .into_iter()
+            .zip(created)
+            .map(|(session_id, created)| {
+                let created_at =
+                    created.and_then(|ts| OffsetDateTime::from_unix_timestamp(ts).ok());
+                (session_id, SessionMetadata::new(created_at))
+            })
+            .collect();
+        Ok(sessions)
+    }
+
     async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
         let (session_ids, index_key) = self.fetch_session_index(id.as_ref()).await?;
 
         let session_value_pipeline = self.pool.next().pipeline();
         for session_id in &session_ids {
-            let session_key = self.session_key(&session_id);
+            let session_key = self.session_key(session_id);
             let _: () = match T::REDIS_FORMAT {
                 RedisFormat::String | RedisFormat::Bytes => {
                     session_value_pipeline.get(&session_key).await?
@@ -250,18 +696,156 @@ where
     ) -> SessionResult<u64> {
         let (mut session_ids, index_key) = self.fetch_session_index(id.as_ref()).await?;
         if let Some(excluded_id) = excluded_session_id {
-            session_ids.retain(|id| id != excluded_id);
+            session_ids.retain(|id| !session_id_eq(id, excluded_id));
         }
         if session_ids.is_empty() {
             return Ok(0);
         }
 
         let session_keys: Vec<_> = session_ids.iter().map(|id| self.session_key(id)).collect();
+        let artifact_keys = self.session_artifact_keys(&session_ids).await?;
         let delete_pipeline = self.pool.next().pipeline();
         let _: () = delete_pipeline.del(session_keys).await?;
-        let _: () = delete_pipeline.srem(index_key, session_ids).await?;
-        let (del_num, _srem_num): (u64, u64) = delete_pipeline.all().await?;
+        let _: () = delete_pipeline.srem(index_key, session_ids.clone()).await?;
+        let _: () = delete_pipeline.del(artifact_keys).await?;
+        let (del_num, _srem_num, _artifacts_num): (u64, u64, u64) = delete_pipeline.all().await?;
 
+        for session_id in session_ids {
+            self.publish_change(id.as_ref(), SessionChange::Deleted(session_id))
+                .await;
+        }
         Ok(del_num)
     }
+
+    async fn purge_identifier_index(&self, id: &T::Id) -> SessionResult<()> {
+        let _: () = self.pool.del(self.session_index_key(id.as_ref())).await?;
+        Ok(())
+    }
+
+    async fn invalidate_sessions_by_tag(&self, tag: &str) -> SessionResult<u64> {
+        let tag_key = self.session_tag_key(tag);
+        let session_ids: Vec<String> = self.pool.smembers(&tag_key).await?;
+        if session_ids.is_empty() {
+            return Ok(0);
+        }
+
+        // Delete each session with its data, so that it's also removed from its identifier
+        // index and other tag indexes, and the deletion is published
+        let mut count = 0;
+        for session_id in &session_ids {
+            match self.fetch_session_data::<T>(session_id).await {
+                Ok(Some(data)) => SessionStorage::<T>::delete(self, session_id, data).await?,
+                Ok(None) => continue, // already expired
+                Err(_) => {
+                    let mut keys = self
+                        .session_artifact_keys(std::slice::from_ref(session_id))
+                        .await?;
+                    keys.push(self.session_key(session_id));
+                    let _: () = self.pool.del(keys).await?;
+                }
+            }
+            count += 1;
+        }
+        let _: () = self.pool.srem(tag_key, session_ids).await?;
+
+        Ok(count)
+    }
+
+    async fn online_identifiers(&self, since: OffsetDateTime) -> SessionResult<Vec<String>> {
+        let identifiers: Vec<String> = self
+            .pool
+            .zrevrangebyscore(
+                self.presence_key(),
+                "+inf",
+                since.unix_timestamp() as f64,
+                false,
+                None,
+            )
+            .await?;
+        Ok(identifiers)
+    }
+
+    async fn subscribe_identifier(&self, id: &T::Id) -> SessionResult<SessionChangeStream> {
+        let channel = self.session_index_key(id.as_ref());
+        let subscriber = self.pool.next().clone_new();
+        subscriber.init().await?;
+        subscriber.subscribe(channel.clone()).await?;
+        let receiver = subscriber.message_rx();
+
+        Ok(broadcast_stream(receiver, subscriber, move |message| {
+            if *message.channel != *channel {
+                return None;
+            }
+            SessionChange::decode(&message.value.as_string()?)
+        }))
+    }
+}
+
+/// Redis implementation of [`LoginThrottle`] using the [fred.rs](https://docs.rs/fred) crate.
+/// Failed attempts are counted with `INCR`, and the key expires at the end of the window. The
+/// expiration is set with `EXPIRE ... NX` along with every increment, so a counter can't be left
+/// without a TTL. This requires Redis 7.0 or later.
+///
+/// The key will be `<prefix>:<identifier>` (e.g.: `throttle:alice@example.com`)
+///
+/// # Example
+/// ```rust
+/// use rocket_flex_session::storage::redis::RedisFredThrottle;
+///
+/// fn create_throttle(pool: fred::prelude::Pool) -> RedisFredThrottle {
+///     RedisFredThrottle::builder()
+///         .pool(pool)
+///         .max_attempts(5)
+///         .window(15 * 60)
+///         .build()
+/// }
+/// ```
+#[derive(Builder)]
+pub struct RedisFredThrottle {
+    /// The initialized fred.rs connection pool.
+    pool: fred::prelude::Pool,
+    /// The prefix to use for the attempt counter keys.
+    #[builder(into, default = "throttle:")]
+    prefix: String,
+    /// The maximum number of failed attempts before an identifier is locked out (default: 5)
+    #[builder(default = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u32,
+    /// The window in seconds in which failed attempts are counted (default: 15 minutes)
+    #[builder(default = DEFAULT_WINDOW)]
+    window: u32,
+}
+
+impl RedisFredThrottle {
+    fn throttle_key(&self, identifier: &str) -> String {
+        format!("{}{identifier}", self.prefix)
+    }
+}
+
+#[rocket::async_trait]
+impl LoginThrottle for RedisFredThrottle {
+    fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    async fn record_failed_login(&self, identifier: &str) -> SessionResult<u32> {
+        use fred::types::ExpireOptions;
+
+        let key = self.throttle_key(identifier);
+        let pipeline = self.pool.next().pipeline();
+        let _: () = pipeline.incr(&key).await?;
+        let _: () = pipeline
+            .expire(&key, self.window.into(), Some(ExpireOptions::NX))
+            .await?;
+        let (attempts, _): (u32, u8) = pipeline.all().await?;
+        Ok(attempts)
+    }
+
+    async fn failed_attempts(&self, identifier: &str) -> SessionResult<u32> {
+        let attempts: Option<u32> = self.pool.get(self.throttle_key(identifier)).await?;
+        Ok(attempts.unwrap_or(0))
+    }
+
+    async fn reset_failed_logins(&self, identifier: &str) -> SessionResult<()> {
+        Ok(self.pool.del(self.throttle_key(identifier)).await?)
+    }
 }
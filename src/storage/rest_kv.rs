@@ -0,0 +1,263 @@
+//! Session storage for HTTP key-value services
+
+use bon::Builder;
+use reqwest::{header::HeaderMap, Client, Method, RequestBuilder, StatusCode};
+use rocket::{async_trait, time::OffsetDateTime};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    storage::{SessionContext, SessionStorage},
+};
+
+/**
+Trait for session data types that can be stored in an HTTP key-value service.
+Session data is stored as text, so you'll typically serialize it to JSON or a similar format.
+
+# Example
+
+```
+use rocket_flex_session::storage::rest_kv::SessionRestKv;
+
+#[derive(Clone)]
+struct SessionData {
+    user_id: String,
+}
+
+impl SessionRestKv for SessionData {
+    type Error = std::convert::Infallible;
+
+    fn into_kv(self) -> Result<String, Self::Error> {
+        Ok(self.user_id)
+    }
+
+    fn from_kv(value: String) -> Result<Self, Self::Error> {
+        Ok(SessionData { user_id: value })
+    }
+}
+```
+*/
+pub trait SessionRestKv: Sized {
+    /// The error that can occur when converting to/from the stored value.
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Convert to the value that will be stored in the KV service.
+    fn into_kv(self) -> Result<String, Self::Error>;
+
+    /// Convert from the value stored in the KV service.
+    fn from_kv(value: String) -> Result<Self, Self::Error>;
+}
+
+/// How the session TTL is sent to the KV service when saving a session.
+#[derive(Clone, Debug, Default)]
+pub enum RestKvTtl {
+    /// Don't send the TTL. Expired sessions will still be rejected when loading, but
+    /// won't be deleted from the service.
+    #[default]
+    None,
+    /// Send the TTL in seconds as a query parameter (e.g. `expiration_ttl` for Cloudflare KV)
+    QueryParam(String),
+    /// Send the TTL in seconds as a request header
+    Header(String),
+}
+
+/**
+Session storage for HTTP key-value services such as [Cloudflare KV](https://developers.cloudflare.com/kv/)
+or [Upstash](https://upstash.com/), using the [reqwest](https://docs.rs/crate/reqwest) crate.
+This storage doesn't support indexing.
+
+# Requests
+URLs are configured as templates, where `{key}` is replaced with the session key (`<prefix><id>`)
+and `{ttl}` is replaced with the session TTL in seconds.
+- **Load:** `GET <get_url>`. The service should respond with the stored value as the response
+  body, or a 404 if the key doesn't exist.
+- **Save:** `<put_method> <put_url>` (default: `PUT <get_url>`), with the value as the request body.
+  The TTL can also be sent via a query parameter or header - see [`RestKvTtl`].
+- **Delete:** `DELETE <delete_url>` (default: `DELETE <get_url>`)
+
+Since many KV services don't return the remaining TTL of a key, the expiration time is stored
+along with the session data, in the format `<unix_timestamp>\n<data>`. Services like Cloudflare KV
+are also eventually consistent and have a minimum TTL, so the storage will verify the expiration
+when loading a session, and you can use `min_ttl` to send a higher TTL to the service.
+
+With [rolling sessions](crate::RocketFlexSessionOptions::rolling), extending the expiration
+requires saving the whole value again. To avoid a write on every request, the value is only saved
+again once the expiration would be extended by at least `refresh_interval` seconds.
+
+# Example
+```
+use rocket_flex_session::storage::rest_kv::{RestKvStorage, RestKvTtl};
+
+fn create_storage() -> RestKvStorage {
+    let account = "...";
+    let namespace = "...";
+    RestKvStorage::builder()
+        .get_url(format!(
+            "https://api.cloudflare.com/client/v4/accounts/{account}/storage/kv/namespaces/{namespace}/values/{{key}}"
+        ))
+        .bearer_token("api-token")
+        .ttl_mode(RestKvTtl::QueryParam("expiration_ttl".to_owned()))
+        .min_ttl(60)
+        .build()
+}
+```
+*/
+#[derive(Builder)]
+pub struct RestKvStorage {
+    /// The HTTP client to use (default: a new `reqwest::Client`)
+    #[builder(default)]
+    client: Client,
+    /// URL template used to get a session
+    #[builder(into)]
+    get_url: String,
+    /// URL template used to save a session (default: the `get_url`)
+    #[builder(into)]
+    put_url: Option<String>,
+    /// HTTP method used to save a session (default: `PUT`)
+    #[builder(default = Method::PUT)]
+    put_method: Method,
+    /// URL template used to delete a session (default: the `get_url`)
+    #[builder(into)]
+    delete_url: Option<String>,
+    /// The prefix to use for session keys.
+    #[builder(into, default = "sess:")]
+    prefix: String,
+    /// Bearer token sent in the `Authorization` header
+    #[builder(into)]
+    bearer_token: Option<String>,
+    /// Additional headers to send with every request
+    #[builder(default)]
+    headers: HeaderMap,
+    /// How to send the session TTL when saving a session (default: not sent)
+    #[builder(default)]
+    ttl_mode: RestKvTtl,
+    /// The minimum TTL in seconds to send to the service (default: 0)
+    #[builder(default)]
+    min_ttl: u32,
+    /// When loading a session with a new TTL, only save it again if its expiration is extended
+    /// by at least this many seconds (default: 60)
+    #[builder(default = 60)]
+    refresh_interval: u32,
+}
+
+impl RestKvStorage {
+    fn session_key(&self, id: &str) -> String {
+        format!("{}{id}", self.prefix)
+    }
+
+    fn url(&self, template: &str, id: &str, ttl: u32) -> String {
+        template
+            .replace("{key}", &self.session_key(id))
+            .replace("{ttl}", &ttl.to_string())
+    }
+
+    fn request(&self, method: Method, url: String) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, url)
+            .headers(self.headers.clone());
+        match &self.bearer_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn put(&self, id: &str, value: String, ttl: u32) -> SessionResult<()> {
+        let expires = OffsetDateTime::now_utc().unix_timestamp() + i64::from(ttl);
+        let service_ttl = ttl.max(self.min_ttl);
+        let url = self.url(
+            self.put_url.as_ref().unwrap_or(&self.get_url),
+            id,
+            service_ttl,
+        );
+
+        let mut request = self
+            .request(self.put_method.clone(), url)
+            .body(format!("{expires}\n{value}"));
+        match &self.ttl_mode {
+            RestKvTtl::None => {}
+            RestKvTtl::QueryParam(param) => {
+                request = request.query(&[(param, service_ttl)]);
+            }
+            RestKvTtl::Header(header) => {
+                request = request.header(header, service_ttl);
+            }
+        }
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn delete_key(&self, id: &str) -> SessionResult<()> {
+        let url = self.url(self.delete_url.as_ref().unwrap_or(&self.get_url), id, 0);
+        let response = self.request(Method::DELETE, url).send().await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+}
+
+/// Split the stored value into the expiration timestamp and session data
+fn parse_stored_value(stored: &str) -> SessionResult<(i64, &str)> {
+    let (expires, value) = stored.split_once('\n').ok_or(SessionError::InvalidData)?;
+    let expires = expires.parse().map_err(|_| SessionError::InvalidData)?;
+    Ok((expires, value))
+}
+
+#[async_trait]
+impl<T> SessionStorage<T> for RestKvStorage
+where
+    T: SessionRestKv + Send + Sync + 'static,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let response = self
+            .request(Method::GET, self.url(&self.get_url, id, 0))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(SessionError::NotFound);
+        }
+        let stored = response.error_for_status()?.text().await?;
+
+        let (expires, value) = parse_stored_value(&stored)?;
+        let remaining_ttl: u32 = (expires - OffsetDateTime::now_utc().unix_timestamp())
+            .try_into()
+            .unwrap_or(0);
+        if remaining_ttl == 0 {
+            return Err(SessionError::Expired);
+        }
+        let data = T::from_kv(value.to_owned()).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+
+        match ttl {
+            // Skip small extensions, to avoid rewriting the value on every request
+            Some(new_ttl)
+                if new_ttl < remaining_ttl || new_ttl - remaining_ttl >= self.refresh_interval =>
+            {
+                // Rewrite the value to update the expiration
+                self.put(id, value.to_owned(), new_ttl).await?;
+                Ok((data, new_ttl))
+            }
+            _ => Ok((data, remaining_ttl)),
+        }
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let value = data
+            .into_kv()
+            .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        self.put(id, value, ttl).await
+    }
+
+    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        self.delete_key(id).await
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.delete_key(id).await
+    }
+}
@@ -0,0 +1,172 @@
+//! Migration from rocket_session_store
+//!
+//! Apps using the [rocket_session_store](https://docs.rs/crate/rocket_session_store) crate can
+//! switch to this crate while keeping their existing [`Store`] implementations (e.g. the Redis
+//! store, or a custom one) and session data. [`RocketSessionStoreAdapter`] uses any [`Store`]
+//! as the session storage provider, and the `with_rocket_session_store` method of the
+//! [fairing builder](crate::RocketFlexSession::builder) converts a whole [`SessionStore`]
+//! fairing, including its cookie name, duration, and cookie attributes.
+//!
+//! # Migration guide
+//! 1. Replace the [`SessionStore`] fairing with a [`RocketFlexSession`](crate::RocketFlexSession)
+//!    fairing, created with `with_rocket_session_store`:
+//!     ```rust
+//!     use std::time::Duration;
+//!     use rocket::http::Cookie;
+//!     use rocket_flex_session::RocketFlexSession;
+//!     use rocket_session_store::{memory::MemoryStore, SessionStore};
+//!
+//!     #[rocket::launch]
+//!     fn rocket() -> _ {
+//!         let session_store: SessionStore<String> = SessionStore {
+//!             store: Box::new(MemoryStore::new()),
+//!             name: "token".into(),
+//!             duration: Duration::from_secs(3600),
+//!             cookie_builder: Cookie::build(("", "")).path("/"),
+//!         };
+//!         let fairing = RocketFlexSession::<String>::builder()
+//!             .with_rocket_session_store(session_store)
+//!             .build();
+//!         rocket::build().attach(fairing)
+//!     }
+//!     ```
+//! 2. Replace the `rocket_session_store::Session<'_, T>` guard with
+//!    [`Session<T>`](crate::Session). Its methods are synchronous - changes are saved at the
+//!    end of the request:
+//!
+//!     | rocket_session_store | rocket_flex_session |
+//!     |----------------------|---------------------|
+//!     | `session.get().await?` | `session.get()` |
+//!     | `session.set(value).await?` | `session.set(value)` |
+//!     | `session.touch().await?` | `session.renew()` |
+//!     | `session.remove().await?` | `session.delete()` |
+//!
+//! 3. Once the app is migrated, you can move to one of the storage providers of this crate for
+//!    more features (e.g. session indexing).
+//!
+//! This crate stores the session ID in a private (encrypted) cookie. If your sessions used a
+//! plain cookie, existing users will get a new session once after switching.
+//!
+//! # TTL
+//! [`Store`] doesn't expose the remaining TTL of a session, so the adapter reports the full
+//! [`ttl`](RocketSessionStoreAdapter::ttl) when a session is loaded.
+
+use std::{fmt::Debug, time::Duration};
+
+use rocket::async_trait;
+use rocket_session_store::{SessionStore, Store};
+
+use crate::error::{SessionError, SessionResult};
+
+use super::SessionStore as FlexSessionStore;
+
+/// Session storage over a rocket_session_store [`Store`]. See the [module docs](self).
+pub struct RocketSessionStoreAdapter<T> {
+    store: Box<dyn Store<Value = T>>,
+    ttl: u32,
+}
+
+impl<T> RocketSessionStoreAdapter<T> {
+    /// Create the storage from a [`Store`]
+    pub fn new(store: impl Store<Value = T> + 'static) -> Self {
+        Self::from_boxed(Box::new(store))
+    }
+
+    /// Create the storage from a boxed [`Store`], e.g. the `store` field of a [`SessionStore`]
+    pub fn from_boxed(store: Box<dyn Store<Value = T>>) -> Self {
+        Self {
+            store,
+            ttl: 14 * 24 * 60 * 60,
+        }
+    }
+
+    /// Set the TTL (in seconds) reported when a session is loaded without a new TTL. This should
+    /// be the session TTL of the fairing. (default: 2 weeks, same as the default `max_age`)
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+}
+
+/// Wrap a rocket_session_store [`Store`] as a storage provider for this crate. This is the same
+/// as [`RocketSessionStoreAdapter::new`].
+pub fn from_rocket_session_store<T>(
+    store: impl Store<Value = T> + 'static,
+) -> RocketSessionStoreAdapter<T> {
+    RocketSessionStoreAdapter::new(store)
+}
+
+/// Convert a rocket_session_store fairing into its store and the equivalent session options
+pub(crate) fn convert_session_store<T>(
+    session_store: SessionStore<T>,
+) -> (
+    RocketSessionStoreAdapter<T>,
+    crate::RocketFlexSessionOptions,
+) {
+    let SessionStore {
+        store,
+        name,
+        duration,
+        cookie_builder,
+    } = session_store;
+    let cookie = cookie_builder.build();
+    let max_age = duration.as_secs().try_into().unwrap_or(u32::MAX);
+
+    let mut options = crate::RocketFlexSessionOptions {
+        cookie_name: name,
+        max_age,
+        domain: cookie.domain().map(str::to_owned),
+        ..Default::default()
+    };
+    if let Some(path) = cookie.path() {
+        options.path = path.to_owned();
+    }
+    if let Some(http_only) = cookie.http_only() {
+        options.http_only = http_only;
+    }
+    if let Some(secure) = cookie.secure() {
+        options.secure = secure;
+    }
+    if let Some(same_site) = cookie.same_site() {
+        options.same_site = same_site;
+    }
+
+    let storage = RocketSessionStoreAdapter::from_boxed(store).ttl(max_age);
+    (storage, options)
+}
+
+fn store_error(e: impl Debug) -> SessionError {
+    SessionError::Backend(format!("rocket_session_store error: {e:?}").into())
+}
+
+#[async_trait]
+impl<T> FlexSessionStore<T> for RocketSessionStoreAdapter<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn load(&self, id: &str, ttl: Option<u32>) -> SessionResult<(T, u32)> {
+        let data = (self.store.get(id).await)
+            .map_err(store_error)?
+            .ok_or(SessionError::NotFound)?;
+        match ttl {
+            Some(new_ttl) => {
+                let duration = Duration::from_secs(new_ttl.into());
+                self.store.touch(id, duration).await.map_err(store_error)?;
+                Ok((data, new_ttl))
+            }
+            None => Ok((data, self.ttl)),
+        }
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let duration = Duration::from_secs(ttl.into());
+        self.store
+            .set(id, data, duration)
+            .await
+            .map_err(store_error)
+    }
+
+    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        self.store.remove(id).await.map_err(store_error)
+    }
+}
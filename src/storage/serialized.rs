@@ -0,0 +1,331 @@
+//! Serialize the session data once for all layers
+//!
+//! When several parts of a [layer stack](super::layer) need the serialized session data (e.g. a
+//! [write-ahead log](super::wal) and a Redis or SQL storage), each of them would serialize the data
+//! again. Instead, add a [`SerializeOnceLayer`] on top of the stack: it serializes the data once
+//! with a [`SessionCodec`], and passes a [`SerializedSession`] - the data along with its bytes -
+//! through the layers below it. Layers and storages read the bytes with
+//! [`SerializedSession::bytes`], and a [tiered cache](super::tiered) below the layer keeps the
+//! decoded data, so cache hits don't need to be deserialized either.
+//!
+//! The base storage must store a [`SerializedSession`]. The built-in conversions are:
+//!
+//! | Storage | Requirements |
+//! |---------|-------------|
+//! | [`MemoryStorage`](super::memory::MemoryStorage) | |
+//! | [`RedisFredStorage`](super::redis::RedisFredStorage) | The bytes are stored as a Redis string |
+//! | [`SqlxPostgresStorage`](super::sqlx::SqlxPostgresStorage) | The data column must be `bytea` |
+//! | [`SqlxSqliteStorage`](super::sqlx::SqlxSqliteStorage) | The data column must be `BLOB` |
+//!
+//! Use [`PassthroughCodec`] for the layers below that take a codec (e.g. the
+//! [`WalLayer`](super::wal::WalLayer)), to reuse the bytes. Like the
+//! [erased storage](super::erased), session indexing isn't supported.
+//!
+//! # Example
+//! ```rust,no_run
+//! # #[cfg(feature = "json")]
+//! # {
+//! use rocket_flex_session::{
+//!     storage::{
+//!         envelope::EnvelopeCodec,
+//!         layer::StorageStack,
+//!         memory::MemoryStorage,
+//!         serialized::{PassthroughCodec, SerializeOnceLayer, SerializedSession},
+//!         wal::{FileWal, WalLayer},
+//!     },
+//!     RocketFlexSession,
+//! };
+//!
+//! // e.g. a Redis or Postgres storage instead
+//! let storage = StorageStack::new(MemoryStorage::<SerializedSession<String>>::default())
+//!     .layer(WalLayer::new(FileWal::new("sessions.wal"), PassthroughCodec))
+//!     .layer(SerializeOnceLayer::new(EnvelopeCodec::default()))
+//!     .build();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(storage)
+//!     .build();
+//! # }
+//! ```
+
+use std::{fmt, sync::Arc, time::Duration};
+
+use rocket::time::OffsetDateTime;
+
+use crate::{error::SessionResult, SessionIdentifier};
+
+use super::{
+    erased::SessionCodec, forward_storage_impl, layer::StorageLayer, SessionContext,
+    SessionMetadata, SessionStorage,
+};
+
+/// Session data along with its serialized bytes, passed through the layers below a
+/// [`SerializeOnceLayer`]. Clones are cheap, as the data and bytes are shared.
+pub struct SerializedSession<T> {
+    bytes: Arc<[u8]>,
+    /// The data, if this wasn't loaded from the bytes
+    data: Option<Arc<T>>,
+}
+
+impl<T> SerializedSession<T> {
+    /// Create the session from its serialized bytes, e.g. when loading it from a storage
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self {
+            bytes: bytes.into(),
+            data: None,
+        }
+    }
+
+    /// The serialized session data
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The session data, if the session was created from the data rather than loaded from the
+    /// bytes. The [`SerializeOnceLayer`] deserializes the bytes otherwise.
+    pub fn data(&self) -> Option<&T> {
+        self.data.as_deref()
+    }
+}
+
+impl<T> Clone for SerializedSession<T> {
+    fn clone(&self) -> Self {
+        Self {
+            bytes: self.bytes.clone(),
+            data: self.data.clone(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SerializedSession<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerializedSession")
+            .field("bytes", &self.bytes.len())
+            .field("decoded", &self.data.is_some())
+            .finish()
+    }
+}
+
+impl<T: Send + Sync> SessionIdentifier for SerializedSession<T> {
+    type Id = String;
+
+    /// Serialized sessions aren't indexed
+    fn identifier(&self) -> Option<Self::Id> {
+        None
+    }
+}
+
+#[cfg(feature = "redis_fred")]
+impl<T: Send + Sync + 'static> super::redis::SessionRedis for SerializedSession<T> {
+    const REDIS_FORMAT: super::redis::RedisFormat = super::redis::RedisFormat::Bytes;
+    type Error = crate::error::SessionError;
+
+    fn into_redis(self) -> Result<super::redis::RedisValue, Self::Error> {
+        Ok(super::redis::RedisValue::Bytes(self.bytes.to_vec()))
+    }
+
+    fn from_redis(value: super::redis::RedisValue) -> Result<Self, Self::Error> {
+        let bytes = value
+            .into_bytes()
+            .map_err(|_| crate::error::SessionError::InvalidData)?;
+        Ok(Self::from_bytes(bytes))
+    }
+}
+
+#[cfg(feature = "sqlx_postgres")]
+impl<T: Send + Sync + 'static> super::sqlx::SessionSqlx<sqlx::Postgres> for SerializedSession<T> {
+    type Error = std::convert::Infallible;
+    type Data = Vec<u8>;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.bytes.to_vec())
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(Self::from_bytes(value))
+    }
+}
+
+#[cfg(feature = "sqlx_sqlite")]
+impl<T: Send + Sync + 'static> super::sqlx::SessionSqlx<sqlx::Sqlite> for SerializedSession<T> {
+    type Error = std::convert::Infallible;
+    type Data = Vec<u8>;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.bytes.to_vec())
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(Self::from_bytes(value))
+    }
+}
+
+/// Codec for layers below a [`SerializeOnceLayer`], which reuses the serialized bytes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PassthroughCodec;
+
+impl<T: Send + Sync> SessionCodec<SerializedSession<T>> for PassthroughCodec {
+    fn encode(&self, data: &SerializedSession<T>) -> SessionResult<Vec<u8>> {
+        Ok(data.bytes.to_vec())
+    }
+
+    fn decode(&self, value: &[u8]) -> SessionResult<SerializedSession<T>> {
+        Ok(SerializedSession::from_bytes(value))
+    }
+}
+
+/// Layer that serializes the session data once, and passes a [`SerializedSession`] to the
+/// layers below. See the [module docs](self).
+pub struct SerializeOnceLayer<T> {
+    codec: Arc<dyn SessionCodec<T>>,
+}
+
+impl<T> Clone for SerializeOnceLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            codec: self.codec.clone(),
+        }
+    }
+}
+
+impl<T> SerializeOnceLayer<T> {
+    /// Serialize the session data with the given codec
+    pub fn new(codec: impl SessionCodec<T> + 'static) -> Self {
+        Self {
+            codec: Arc::new(codec),
+        }
+    }
+}
+
+impl<S, T> StorageLayer<S> for SerializeOnceLayer<T> {
+    type Storage = SerializeOnceStorage<S, T>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        SerializeOnceStorage {
+            inner,
+            codec: self.codec,
+        }
+    }
+}
+
+/// Storage wrapped by a [`SerializeOnceLayer`]
+pub struct SerializeOnceStorage<S, T> {
+    inner: S,
+    codec: Arc<dyn SessionCodec<T>>,
+}
+
+impl<S, T: Clone> SerializeOnceStorage<S, T> {
+    fn encode(&self, data: T) -> SessionResult<SerializedSession<T>> {
+        let bytes = self.codec.encode(&data)?;
+        Ok(SerializedSession {
+            bytes: bytes.into(),
+            data: Some(Arc::new(data)),
+        })
+    }
+
+    /// Get the data of the session, deserializing it if it was loaded from the bytes
+    fn decode(&self, session: SerializedSession<T>) -> SessionResult<T> {
+        match session.data {
+            Some(data) => Ok(Arc::unwrap_or_clone(data)),
+            None => self.codec.decode(&session.bytes),
+        }
+    }
+}
+
+forward_storage_impl! {
+    forward(inner): activity, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for SerializeOnceStorage<S, T>
+    where
+        T: Clone + Send + Sync + 'static,
+        S: SessionStorage<SerializedSession<T>>,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            let (session, ttl) = self.inner.load(id, ttl, cookie_jar).await?;
+            Ok((self.decode(session)?, ttl))
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            let (session, ttl, metadata) =
+                self.inner.load_with_metadata(id, ttl, cookie_jar).await?;
+            Ok((self.decode(session)?, ttl, metadata))
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            let session = self.encode(data)?;
+            self.inner.save(id, session, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            let session = self.encode(data)?;
+            self.inner.save_with_duration(id, session, ttl).await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            let session = self.encode(data)?;
+            self.inner
+                .save_with_metadata(id, session, ttl, metadata)
+                .await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            let session = self.encode(data)?;
+            self.inner.delete(id, session).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.inner.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            let session = data.map(|data| self.encode(data.clone())).transpose()?;
+            self.inner
+                .save_cookie(id, session.as_ref(), ttl, cookie_jar)
+        }
+
+        async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+            let session = self.encode(data.clone())?;
+            self.inner.record_presence(&session, last_seen).await
+        }
+
+        async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+            let sessions = self.inner.recently_active(limit).await?;
+            sessions
+                .into_iter()
+                .map(|(id, session, ttl)| Ok((id, self.decode(session)?, ttl)))
+                .collect()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
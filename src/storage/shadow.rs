@@ -0,0 +1,323 @@
+//! Shadow reads to verify a migration target
+//!
+//! Before switching to a new storage provider, [`ShadowReadLayer`] can check that it has the same
+//! sessions as the current one. Sessions are still loaded from (and saved to) the wrapped primary
+//! storage, but each load also reads the same session from the secondary storage in the
+//! background, and compares the results. Mismatches are logged as warnings (with a
+//! [hashed](crate::LogIdFormat::Hashed) session ID) and counted in the [`ShadowReadMetrics`].
+//!
+//! Writes aren't mirrored to the secondary storage, so it should be kept in sync separately (e.g.
+//! by replication, or by replaying a [write-ahead log](super::wal)). The shadow read doesn't
+//! extend the TTL of the session in the secondary storage, and doesn't have access to the cookies
+//! of the request, so cookie-based storages can't be used as the secondary storage. Set a
+//! `sample_rate` to only shadow a fraction of the sessions.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     storage::{
+//!         layer::StorageStack,
+//!         memory::MemoryStorage,
+//!         shadow::{ShadowReadLayer, ShadowReadMetrics},
+//!     },
+//!     RocketFlexSession,
+//! };
+//!
+//! let metrics = ShadowReadMetrics::default();
+//! // e.g. Redis as the primary storage and Postgres as the secondary
+//! let storage = StorageStack::new(MemoryStorage::default())
+//!     .layer(
+//!         ShadowReadLayer::new(MemoryStorage::default())
+//!             .sample_rate(0.1)
+//!             .metrics(metrics.clone()),
+//!     )
+//!     .build();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(storage)
+//!     .build();
+//!
+//! // later, e.g. in a metrics endpoint
+//! println!(
+//!     "{} matched, {} mismatched, {} failed",
+//!     metrics.matched(),
+//!     metrics.mismatched(),
+//!     metrics.errors()
+//! );
+//! ```
+
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rocket::tokio;
+
+use crate::{
+    error::{SessionError, SessionResult},
+    security::fnv1a,
+    LogIdFormat,
+};
+
+use super::{
+    forward_storage_impl, layer::StorageLayer, NoopSessionContext, SessionContext, SessionMetadata,
+    SessionStorage, SessionStorageIndexed,
+};
+
+/// Layer that compares the sessions loaded from the wrapped storage with a secondary storage.
+/// See the [module docs](self).
+pub struct ShadowReadLayer<S> {
+    secondary: Arc<S>,
+    sample_rate: f64,
+    metrics: ShadowReadMetrics,
+}
+
+impl<S> ShadowReadLayer<S> {
+    /// Create a shadow read layer with the secondary storage to compare against
+    pub fn new(secondary: S) -> Self {
+        Self {
+            secondary: Arc::new(secondary),
+            sample_rate: 1.0,
+            metrics: ShadowReadMetrics::default(),
+        }
+    }
+
+    /// Only shadow this fraction of the sessions (0.0 - 1.0, default: 1.0). The same sessions
+    /// are always sampled.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Record the comparisons in the given metrics
+    pub fn metrics(mut self, metrics: ShadowReadMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+}
+
+impl<P, S> StorageLayer<P> for ShadowReadLayer<S> {
+    type Storage = ShadowReadStorage<P, S>;
+
+    fn layer(self, inner: P) -> Self::Storage {
+        ShadowReadStorage {
+            inner,
+            secondary: self.secondary,
+            sample_rate: self.sample_rate,
+            metrics: self.metrics,
+        }
+    }
+}
+
+/// Storage wrapped by a [`ShadowReadLayer`]
+pub struct ShadowReadStorage<P, S> {
+    inner: P,
+    secondary: Arc<S>,
+    sample_rate: f64,
+    metrics: ShadowReadMetrics,
+}
+
+impl<P, S> ShadowReadStorage<P, S> {
+    /// Compare the session loaded from the primary storage (`None` if it wasn't found) with the
+    /// secondary storage in the background
+    fn shadow<T>(&self, id: &str, primary: Option<&T>)
+    where
+        T: PartialEq + Clone + Send + Sync + 'static,
+        S: SessionStorage<T> + 'static,
+    {
+        if (fnv1a(id.as_bytes()) as f64 / u64::MAX as f64) >= self.sample_rate {
+            return;
+        }
+        let id = id.to_owned();
+        let primary = primary.cloned();
+        let secondary = self.secondary.clone();
+        let metrics = self.metrics.clone();
+        tokio::spawn(async move {
+            let mismatch = match secondary.load(&id, None, &NoopSessionContext).await {
+                Ok((data, _)) => match primary {
+                    Some(primary) if primary == data => None,
+                    Some(_) => Some(Mismatch::Data),
+                    None => Some(Mismatch::MissingInPrimary),
+                },
+                Err(SessionError::NotFound | SessionError::Expired) => {
+                    primary.map(|_| Mismatch::MissingInSecondary)
+                }
+                Err(e) => {
+                    rocket::warn!(
+                        "Shadow read of session {} failed: {e}",
+                        LogIdFormat::Hashed.format(&id)
+                    );
+                    metrics.counters.errors.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+            };
+            match mismatch {
+                Some(mismatch) => {
+                    rocket::warn!(
+                        "Shadow read mismatch for session {}: {mismatch}",
+                        LogIdFormat::Hashed.format(&id)
+                    );
+                    metrics.counters.mismatched.fetch_add(1, Ordering::Relaxed);
+                }
+                None => {
+                    metrics.counters.matched.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+    }
+
+    /// Shadow the result of a load from the primary storage
+    fn shadow_result<T, R>(&self, id: &str, result: &SessionResult<R>, data: impl Fn(&R) -> &T)
+    where
+        T: PartialEq + Clone + Send + Sync + 'static,
+        S: SessionStorage<T> + 'static,
+    {
+        match result {
+            Ok(loaded) => self.shadow(id, Some(data(loaded))),
+            Err(SessionError::NotFound | SessionError::Expired) => self.shadow::<T>(id, None),
+            Err(_) => {}
+        }
+    }
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, P, S> SessionStorage<T> for ShadowReadStorage<P, S>
+    where
+        T: PartialEq + Clone + Send + Sync + 'static,
+        P: SessionStorage<T>,
+        S: SessionStorage<T> + 'static,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            let result = self.inner.load(id, ttl, cookie_jar).await;
+            self.shadow_result(id, &result, |(data, _)| data);
+            result
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            let result = self.inner.load_with_metadata(id, ttl, cookie_jar).await;
+            self.shadow_result(id, &result, |(data, _, _)| data);
+            result
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            self.inner.save(id, data, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.inner.save_with_duration(id, data, ttl).await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            self.inner.save_with_metadata(id, data, ttl, metadata).await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.inner.delete(id, data).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.inner.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await?;
+            self.secondary.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            let (inner, secondary) = (self.inner.shutdown().await, self.secondary.shutdown().await);
+            inner.and(secondary)
+        }
+    }
+}
+
+/// How the secondary storage differs from the primary storage
+enum Mismatch {
+    Data,
+    MissingInPrimary,
+    MissingInSecondary,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Data => "session data differs",
+            Self::MissingInPrimary => "session only found in secondary storage",
+            Self::MissingInSecondary => "session not found in secondary storage",
+        })
+    }
+}
+
+/// Counters of the comparisons made by a [`ShadowReadLayer`]. Clones share the same counters.
+#[derive(Clone, Debug, Default)]
+pub struct ShadowReadMetrics {
+    counters: Arc<ShadowCounters>,
+}
+
+#[derive(Debug, Default)]
+struct ShadowCounters {
+    matched: AtomicU64,
+    mismatched: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl ShadowReadMetrics {
+    /// Number of shadow reads that matched the primary storage, including sessions that
+    /// weren't found in either storage
+    pub fn matched(&self) -> u64 {
+        self.counters.matched.load(Ordering::Relaxed)
+    }
+
+    /// Number of shadow reads that didn't match the primary storage
+    pub fn mismatched(&self) -> u64 {
+        self.counters.mismatched.load(Ordering::Relaxed)
+    }
+
+    /// Number of shadow reads that failed with an error
+    pub fn errors(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
+    }
+
+    /// Total number of shadow reads that have completed
+    pub fn total(&self) -> u64 {
+        self.matched() + self.mismatched() + self.errors()
+    }
+}
@@ -0,0 +1,202 @@
+//! Storage backend shared by multiple fairings
+//!
+//! Each fairing takes ownership of its storage, so two fairings for different session types would
+//! usually each build a storage - and with it, a connection pool and cleanup task. The built-in
+//! backends such as [`RedisFredStorage`](super::redis::RedisFredStorage) and
+//! [`SqlxPostgresStorage`](super::sqlx::SqlxPostgresStorage) can already store any session type
+//! that implements their conversion trait, so a [`SharedBackend`] handle lets you configure the
+//! backend once and cheaply create a storage for each fairing by cloning the handle.
+//!
+//! The backend is set up when the first fairing using it is launched, and shut down along with the
+//! last one, so e.g. only one cleanup task runs for a sqlx storage. Sessions of all types are kept
+//! in the same place (e.g. the same table or key prefix), so each fairing should use a distinct
+//! cookie name. To share a backend between session types that it can't store directly, use the
+//! [`erased`](super::erased) module instead.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     storage::{memory::MemoryStorage, shared::SharedBackend},
+//!     RocketFlexSession,
+//! };
+//!
+//! // e.g. a Redis or Postgres storage instead, which can also be shared by different session types
+//! let backend = SharedBackend::new(MemoryStorage::<String>::default());
+//!
+//! let rocket = rocket::build()
+//!     .attach(
+//!         RocketFlexSession::<String>::builder()
+//!             .storage(backend.clone())
+//!             .with_options(|opt| opt.cookie_name = "user_session".to_owned())
+//!             .build(),
+//!     )
+//!     .attach(
+//!         RocketFlexSession::<String>::builder()
+//!             .storage(backend)
+//!             .with_options(|opt| opt.cookie_name = "admin_session".to_owned())
+//!             .build(),
+//!     );
+//! ```
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::error::SessionResult;
+
+use super::{
+    forward_storage_impl, SessionContext, SessionMetadata, SessionStorage, SessionStorageIndexed,
+};
+
+struct Shared<S: ?Sized> {
+    /// Number of handles that have been set up
+    active: AtomicUsize,
+    storage: S,
+}
+
+/// Handle to a storage backend that can be used by multiple fairings. Cloning the handle is cheap,
+/// and all clones use the same backend instance. See the [module docs](self).
+pub struct SharedBackend<S: ?Sized> {
+    shared: Arc<Shared<S>>,
+}
+
+impl<S> SharedBackend<S> {
+    /// Create a shared handle to the given storage backend
+    pub fn new(storage: S) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                active: AtomicUsize::new(0),
+                storage,
+            }),
+        }
+    }
+}
+
+impl<T: Send + Sync> SharedBackend<dyn SessionStorage<T>> {
+    /// Create a shared handle to a type-erased storage backend
+    pub(crate) fn new_dyn(storage: impl SessionStorage<T> + 'static) -> Self {
+        let shared: Arc<Shared<dyn SessionStorage<T>>> = Arc::new(Shared {
+            active: AtomicUsize::new(0),
+            storage,
+        });
+        Self { shared }
+    }
+}
+
+impl<S: ?Sized> SharedBackend<S> {
+    /// Get a reference to the storage backend, e.g. to call storage-specific methods
+    pub fn storage(&self) -> &S {
+        &self.shared.storage
+    }
+
+    /// Register a fairing using the backend, returning whether it's the first one
+    fn activate(&self) -> bool {
+        self.shared.active.fetch_add(1, Ordering::AcqRel) == 0
+    }
+
+    /// Unregister a fairing using the backend, returning whether it was the last one
+    fn deactivate(&self) -> bool {
+        let active = (self.shared.active)
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1));
+        active == Ok(1)
+    }
+}
+
+impl<S: ?Sized> Clone for SharedBackend<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+forward_storage_impl! {
+    forward(storage()): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for SharedBackend<S>
+    where
+        T: Send + Sync + 'static,
+        S: SessionStorage<T> + ?Sized,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            self.storage().load(id, ttl, cookie_jar).await
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            self.storage().load_with_metadata(id, ttl, cookie_jar).await
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.storage().exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            self.storage().save(id, data, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.storage().save_with_duration(id, data, ttl).await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            self.storage()
+                .save_with_metadata(id, data, ttl, metadata)
+                .await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.storage().delete(id, data).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.storage().delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.storage().save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.storage().as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            if self.activate() {
+                self.storage().setup().await?;
+            }
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            if self.deactivate() {
+                self.storage().shutdown().await?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Gradual rollout of a new storage provider
+//!
+//! [`SplitTrafficStorage`] routes a percentage of sessions to a candidate storage (e.g. a new
+//! database, or the same database with a new codec), while the rest keep using the current storage.
+//! Sessions are routed by a hash of their ID, so each session sticks to the same storage as long as
+//! the percentage doesn't change. This can be used to de-risk an infrastructure migration: start
+//! with a small percentage, compare the [metrics](SplitTrafficMetrics) of both storages, and
+//! increase the percentage until all sessions use the candidate storage.
+//!
+//! When the percentage changes, some sessions are routed to the other storage than the one that
+//! has them. If a session isn't found in the storage it's routed to, it's loaded from the other
+//! storage and moved. Deletions are applied to both storages, so a moved session can't come back.
+//!
+//! To roll out a new codec, use [erased storages](super::erased) with the current and new codec on
+//! each side, backed by separate storages or key prefixes.
+//!
+//! Session indexing isn't supported, since the sessions of an identifier may be split between the
+//! two storages. Presence tracking and [recently active sessions](SessionStorage::recently_active)
+//! use the current storage.
+//!
+//! # Example
+//! ```rust
+//! use rocket_flex_session::{
+//!     storage::{
+//!         memory::MemoryStorage,
+//!         split::{SplitBackend, SplitTrafficMetrics, SplitTrafficStorage},
+//!     },
+//!     RocketFlexSession,
+//! };
+//!
+//! let metrics = SplitTrafficMetrics::default();
+//! // e.g. Redis as the current storage and Postgres as the candidate
+//! let storage = SplitTrafficStorage::builder()
+//!     .current(MemoryStorage::default())
+//!     .candidate(MemoryStorage::default())
+//!     .percentage(10)
+//!     .metrics(metrics.clone())
+//!     .build();
+//! let fairing = RocketFlexSession::<String>::builder()
+//!     .storage(storage)
+//!     .build();
+//!
+//! // later, e.g. in a metrics endpoint
+//! for backend in [SplitBackend::Current, SplitBackend::Candidate] {
+//!     let loads = metrics.load(backend);
+//!     println!(
+//!         "{backend:?}: {} loads, {} errors, average latency {:?}",
+//!         loads.calls,
+//!         loads.errors,
+//!         metrics.average_latency(backend)
+//!     );
+//! }
+//! ```
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use bon::Builder;
+use rocket::{async_trait, time::OffsetDateTime};
+
+use crate::{
+    error::{SessionError, SessionResult},
+    security::fnv1a,
+    ActivityEntry, Capability,
+};
+
+use super::{layer::OperationCounts, SessionContext, SessionMetadata, SessionStorage};
+
+/// One of the storages of a [`SplitTrafficStorage`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SplitBackend {
+    /// The storage used by most sessions
+    Current = 0,
+    /// The storage that sessions are being rolled out to
+    Candidate = 1,
+}
+
+/// Storage that routes a percentage of sessions to a candidate storage. See the [module docs](self).
+///
+/// A moved session keeps its creation time, but its activity log, lazy fields, single-use values,
+/// and capability tokens aren't moved along with it: they're deleted from the other storage when
+/// the session is.
+#[derive(Builder)]
+pub struct SplitTrafficStorage<C, N> {
+    /// The storage used by sessions that aren't routed to the candidate
+    current: C,
+    /// The storage that sessions are being rolled out to
+    candidate: N,
+    /// Percentage of sessions routed to the candidate storage, from `0` to `100`
+    percentage: u8,
+    /// Metrics to compare the storages
+    #[builder(default)]
+    metrics: SplitTrafficMetrics,
+}
+
+impl<C, N> SplitTrafficStorage<C, N> {
+    /// The storage that the session is routed to
+    pub fn route(&self, id: &str) -> SplitBackend {
+        match fnv1a(id.as_bytes()) % 100 < u64::from(self.percentage) {
+            true => SplitBackend::Candidate,
+            false => SplitBackend::Current,
+        }
+    }
+
+    /// The metrics of the storages
+    pub fn metrics(&self) -> &SplitTrafficMetrics {
+        &self.metrics
+    }
+
+    /// The storages in order of preference for the session: the one it's routed to first
+    fn backends<T>(&self, id: &str) -> [(SplitBackend, &dyn SessionStorage<T>); 2]
+    where
+        T: Send + Sync,
+        C: SessionStorage<T>,
+        N: SessionStorage<T>,
+    {
+        let current = (
+            SplitBackend::Current,
+            &self.current as &dyn SessionStorage<T>,
+        );
+        let candidate = (
+            SplitBackend::Candidate,
+            &self.candidate as &dyn SessionStorage<T>,
+        );
+        match self.route(id) {
+            SplitBackend::Current => [current, candidate],
+            SplitBackend::Candidate => [candidate, current],
+        }
+    }
+
+    /// Run the operation on a storage, recording it in the metrics
+    async fn measure<R>(
+        &self,
+        backend: SplitBackend,
+        operation: Operation,
+        future: impl Future<Output = SessionResult<R>>,
+    ) -> SessionResult<R> {
+        let start = Instant::now();
+        let result = future.await;
+        self.metrics
+            .record(backend, operation, start.elapsed(), &result);
+        result
+    }
+}
+
+#[async_trait]
+impl<T, C, N> SessionStorage<T> for SplitTrafficStorage<C, N>
+where
+    T: Clone + Send + Sync + 'static,
+    C: SessionStorage<T>,
+    N: SessionStorage<T>,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        let [(routed, routed_storage), (other, other_storage)] = self.backends::<T>(id);
+        let load = routed_storage.load_with_metadata(id, ttl, cookie_jar);
+        match self.measure(routed, Operation::Load, load).await {
+            Err(SessionError::NotFound | SessionError::Expired) => {}
+            result => return result,
+        }
+
+        // The percentage changed since the session was saved, so move it
+        let load = other_storage.load_with_metadata(id, ttl, cookie_jar);
+        let (data, ttl, metadata) = self.measure(other, Operation::Load, load).await?;
+        let duration = Duration::from_secs(ttl.into());
+        let save = routed_storage.save_with_metadata(id, data.clone(), duration, metadata.clone());
+        self.measure(routed, Operation::Save, save).await?;
+        let delete = other_storage.delete(id, data.clone());
+        self.measure(other, Operation::Delete, delete).await?;
+        self.metrics.record_moved();
+        Ok((data, ttl, metadata))
+    }
+
+    async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        let [(_, routed), (_, other)] = self.backends::<T>(id);
+        Ok(routed.exists(id, cookie_jar).await? || other.exists(id, cookie_jar).await?)
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let [(routed, storage), _] = self.backends::<T>(id);
+        self.measure(routed, Operation::Save, storage.save(id, data, ttl))
+            .await
+    }
+
+    async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+        let [(routed, storage), _] = self.backends::<T>(id);
+        let save = storage.save_with_duration(id, data, ttl);
+        self.measure(routed, Operation::Save, save).await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        let [(routed, storage), _] = self.backends::<T>(id);
+        let save = storage.save_with_metadata(id, data, ttl, metadata);
+        self.measure(routed, Operation::Save, save).await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        let [(routed, routed_storage), (other, other_storage)] = self.backends::<T>(id);
+        let delete = other_storage.delete(id, data.clone());
+        self.measure(other, Operation::Delete, delete).await?;
+        let delete = routed_storage.delete(id, data);
+        self.measure(routed, Operation::Delete, delete).await
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        for (backend, storage) in self.backends::<T>(id) {
+            let delete = storage.delete_unreadable(id);
+            self.measure(backend, Operation::Delete, delete).await?;
+        }
+        Ok(())
+    }
+
+    fn save_cookie(
+        &self,
+        id: &str,
+        data: Option<&T>,
+        ttl: u32,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<()> {
+        let [(_, storage), _] = self.backends::<T>(id);
+        storage.save_cookie(id, data, ttl, cookie_jar)
+    }
+
+    async fn record_activity(
+        &self,
+        id: &str,
+        entry: ActivityEntry,
+        limit: usize,
+    ) -> SessionResult<()> {
+        let [(_, storage), _] = self.backends::<T>(id);
+        storage.record_activity(id, entry, limit).await
+    }
+
+    async fn recent_activity(&self, id: &str) -> SessionResult<Vec<ActivityEntry>> {
+        let [(_, storage), _] = self.backends::<T>(id);
+        storage.recent_activity(id).await
+    }
+
+    async fn record_presence(&self, data: &T, last_seen: OffsetDateTime) -> SessionResult<()> {
+        self.current.record_presence(data, last_seen).await
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        self.current.recently_active(limit).await
+    }
+
+    async fn save_capability(
+        &self,
+        token: &str,
+        capability: Capability,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let [(_, storage), _] = self.backends::<T>(&capability.session_id);
+        storage.save_capability(token, capability, ttl).await
+    }
+
+    async fn load_capability(&self, token: &str) -> SessionResult<Capability> {
+        // The session of the token isn't known, so try both storages
+        match self.candidate.load_capability(token).await {
+            Err(SessionError::NotFound | SessionError::Unsupported(_)) => {
+                self.current.load_capability(token).await
+            }
+            result => result,
+        }
+    }
+
+    async fn delete_capability(&self, token: &str) -> SessionResult<()> {
+        let (current, candidate) = (
+            self.current.delete_capability(token).await,
+            self.candidate.delete_capability(token).await,
+        );
+        current.or(candidate)
+    }
+
+    async fn take_capability(&self, token: &str) -> SessionResult<Option<Capability>> {
+        // The session of the token isn't known, so try both storages
+        match self.candidate.take_capability(token).await {
+            Ok(None) | Err(SessionError::Unsupported(_)) => {
+                self.current.take_capability(token).await
+            }
+            result => result,
+        }
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let [(_, storage), _] = self.backends::<T>(id);
+        storage.save_once_value(id, key, value, ttl).await
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        let [(_, routed), (_, other)] = self.backends::<T>(id);
+        match routed.take_once_value(id, key).await? {
+            Some(value) => Ok(Some(value)),
+            None => other.take_once_value(id, key).await,
+        }
+    }
+
+    async fn save_lazy_field(
+        &self,
+        id: &str,
+        name: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let [(_, storage), _] = self.backends::<T>(id);
+        storage.save_lazy_field(id, name, value, ttl).await
+    }
+
+    async fn load_lazy_field(&self, id: &str, name: &str) -> SessionResult<Option<String>> {
+        let [(_, routed), (_, other)] = self.backends::<T>(id);
+        match routed.load_lazy_field(id, name).await? {
+            Some(value) => Ok(Some(value)),
+            None => other.load_lazy_field(id, name).await,
+        }
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.current.setup().await?;
+        self.candidate.setup().await
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        let (current, candidate) = (
+            self.current.shutdown().await,
+            self.candidate.shutdown().await,
+        );
+        current.and(candidate)
+    }
+}
+
+/// Counters of the operations of each storage of a [`SplitTrafficStorage`], to compare them during
+/// a rollout. Sessions that aren't found don't count as errors. Clones share the same counters.
+#[derive(Clone, Debug, Default)]
+pub struct SplitTrafficMetrics {
+    counters: Arc<SplitCounters>,
+}
+
+#[derive(Debug, Default)]
+struct SplitCounters {
+    backends: [BackendCounters; 2],
+    moved: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct BackendCounters {
+    operations: [Counter; 3],
+    total_latency_micros: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    calls: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// Index of each operation in the metrics counters
+#[derive(Clone, Copy)]
+enum Operation {
+    Load = 0,
+    Save = 1,
+    Delete = 2,
+}
+
+impl SplitTrafficMetrics {
+    /// Counts of session loads from the storage
+    pub fn load(&self, backend: SplitBackend) -> OperationCounts {
+        self.counts(backend, Operation::Load)
+    }
+
+    /// Counts of session saves to the storage
+    pub fn save(&self, backend: SplitBackend) -> OperationCounts {
+        self.counts(backend, Operation::Save)
+    }
+
+    /// Counts of session deletions from the storage
+    pub fn delete(&self, backend: SplitBackend) -> OperationCounts {
+        self.counts(backend, Operation::Delete)
+    }
+
+    /// Average latency of the loads, saves, and deletions of the storage
+    pub fn average_latency(&self, backend: SplitBackend) -> Option<Duration> {
+        let counters = &self.counters.backends[backend as usize];
+        let calls: u64 = (counters.operations.iter())
+            .map(|counter| counter.calls.load(Ordering::Relaxed))
+            .sum();
+        let total = counters.total_latency_micros.load(Ordering::Relaxed);
+        (calls > 0).then(|| Duration::from_micros(total / calls))
+    }
+
+    /// Number of sessions that were moved to the storage they're routed to after the
+    /// percentage changed
+    pub fn moved(&self) -> u64 {
+        self.counters.moved.load(Ordering::Relaxed)
+    }
+
+    fn counts(&self, backend: SplitBackend, operation: Operation) -> OperationCounts {
+        let counter = &self.counters.backends[backend as usize].operations[operation as usize];
+        OperationCounts {
+            calls: counter.calls.load(Ordering::Relaxed),
+            errors: counter.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record<R>(
+        &self,
+        backend: SplitBackend,
+        operation: Operation,
+        latency: Duration,
+        result: &SessionResult<R>,
+    ) {
+        let counters = &self.counters.backends[backend as usize];
+        let counter = &counters.operations[operation as usize];
+        counter.calls.fetch_add(1, Ordering::Relaxed);
+        if let Err(e) = result {
+            if !matches!(e, SessionError::NotFound | SessionError::Expired) {
+                counter.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let latency = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        (counters.total_latency_micros).fetch_add(latency, Ordering::Relaxed);
+    }
+
+    fn record_moved(&self) {
+        self.counters.moved.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,211 @@
+//! SQL generation shared by the SQL-based storage providers
+
+use super::sql_index::SqlIndexSchema;
+
+pub(crate) const ID_COLUMN: &str = "id";
+pub(crate) const DATA_COLUMN: &str = "data";
+pub(crate) const EXPIRES_COLUMN: &str = "expires";
+
+/// SQL dialect tweaks
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SqlDialect {
+    /// Standard SQL (Postgres and SQLite)
+    #[default]
+    Standard,
+    /// CockroachDB, optionally using follower reads for session listings
+    Cockroach { follower_reads: bool },
+}
+
+/// Append the optional creation time column to a list of columns
+fn with_created(columns: &str, created_column: Option<&str>) -> String {
+    match created_column {
+        Some(created_column) => format!("{columns}, {created_column}"),
+        None => columns.to_owned(),
+    }
+}
+
+/// Load session data. Bind session ID and current time
+pub(crate) fn load(table_name: &str, created_column: Option<&str>) -> String {
+    let columns = with_created(&format!("{DATA_COLUMN}, {EXPIRES_COLUMN}"), created_column);
+    format!(
+        "SELECT {columns} FROM \"{table_name}\" \
+        WHERE {ID_COLUMN} = $1 AND {EXPIRES_COLUMN} > $2"
+    )
+}
+
+/// Check whether a session exists. Bind session ID and current time
+pub(crate) fn exists(table_name: &str) -> String {
+    format!(
+        "SELECT 1 FROM \"{table_name}\" \
+        WHERE {ID_COLUMN} = $1 AND {EXPIRES_COLUMN} > $2"
+    )
+}
+
+/// Load session data and update TTL. Bind expiration, session ID, and current time
+pub(crate) fn load_and_update_ttl(table_name: &str, created_column: Option<&str>) -> String {
+    let columns = with_created(&format!("{DATA_COLUMN}, {EXPIRES_COLUMN}"), created_column);
+    format!(
+        "UPDATE \"{table_name}\" SET {EXPIRES_COLUMN} = $1 \
+        WHERE {ID_COLUMN} = $2 AND {EXPIRES_COLUMN} > $3 \
+        RETURNING {columns}",
+    )
+}
+
+/// Save session data. Bind the session ID, index, data, expiration, and the creation time
+/// if there's a creation time column. The creation time is only set for new sessions.
+pub(crate) fn save(
+    table_name: &str,
+    index_column: &str,
+    dialect: SqlDialect,
+    created_column: Option<&str>,
+) -> String {
+    let columns = with_created(
+        &format!("{ID_COLUMN}, {index_column}, {DATA_COLUMN}, {EXPIRES_COLUMN}"),
+        created_column,
+    );
+    let values = with_created("$1, $2, $3, $4", created_column.map(|_| "$5"));
+    // In CockroachDB mode, the index column of an existing session is updated as well. The
+    // creation time is never updated, so this isn't an `UPSERT`.
+    let index_update = match dialect {
+        SqlDialect::Cockroach { .. } => format!("{index_column} = EXCLUDED.{index_column}, "),
+        SqlDialect::Standard => String::new(),
+    };
+    format!(
+        "INSERT INTO \"{table_name}\" ({columns}) \
+        VALUES ({values}) \
+        ON CONFLICT ({ID_COLUMN}) DO UPDATE SET \
+            {index_update}\
+            {DATA_COLUMN} = EXCLUDED.{DATA_COLUMN}, \
+            {EXPIRES_COLUMN} = EXCLUDED.{EXPIRES_COLUMN}"
+    )
+}
+
+/// Delete session data. Bind the session ID
+pub(crate) fn delete(table_name: &str) -> String {
+    format!("DELETE FROM \"{table_name}\" WHERE {ID_COLUMN} = $1")
+}
+
+/// Get session IDs (and creation times, if there's a column) belonging to a user/identifier.
+/// Bind the identifier and current time
+pub(crate) fn all_session_ids(
+    table_name: &str,
+    index_column: &str,
+    dialect: SqlDialect,
+    created_column: Option<&str>,
+) -> String {
+    let mut columns = vec![ID_COLUMN];
+    columns.extend(created_column);
+    SqlIndexSchema::column(index_column).select_sessions(
+        table_name,
+        &columns,
+        as_of_system_time(dialect),
+    )
+}
+
+/// Get session data belonging to a user/identifier. Bind the identifier and current time
+pub(crate) fn all_session_data(
+    table_name: &str,
+    index_column: &str,
+    dialect: SqlDialect,
+) -> String {
+    SqlIndexSchema::column(index_column).select_sessions(
+        table_name,
+        &[ID_COLUMN, DATA_COLUMN, EXPIRES_COLUMN],
+        as_of_system_time(dialect),
+    )
+}
+
+/// Get the sessions with the latest expiration. Bind the current time and the limit
+pub(crate) fn recently_active(table_name: &str) -> String {
+    format!(
+        "SELECT {ID_COLUMN}, {DATA_COLUMN}, {EXPIRES_COLUMN} FROM \"{table_name}\" \
+        WHERE {EXPIRES_COLUMN} > $1 ORDER BY {EXPIRES_COLUMN} DESC LIMIT $2"
+    )
+}
+
+/// Clause for CockroachDB follower reads, if enabled
+fn as_of_system_time(dialect: SqlDialect) -> &'static str {
+    match dialect {
+        SqlDialect::Cockroach {
+            follower_reads: true,
+        } => " AS OF SYSTEM TIME follower_read_timestamp()",
+        _ => "",
+    }
+}
+
+/// Invalidate all sessions belonging to a user/identifier. Bind the identifier and the optional session ID to exclude
+pub(crate) fn invalidate_all(table_name: &str, index_column: &str, excluded_id: bool) -> String {
+    SqlIndexSchema::column(index_column).invalidate_sessions(table_name, excluded_id)
+}
+
+/// Delete expired sessions. Bind the current time, and the batch size if batched
+pub(crate) fn delete_expired(table_name: &str, batched: bool) -> String {
+    format!(
+        "DELETE FROM \"{table_name}\" WHERE {}",
+        expired_condition(table_name, batched)
+    )
+}
+
+/// Condition matching expired sessions, limited to a batch of session IDs if batched.
+/// Bind the current time, and the batch size if batched
+pub(crate) fn expired_condition(table_name: &str, batched: bool) -> String {
+    match batched {
+        true => format!(
+            "{ID_COLUMN} IN (SELECT {ID_COLUMN} FROM \"{table_name}\" WHERE {EXPIRES_COLUMN} < $1 LIMIT $2)"
+        ),
+        false => format!("{EXPIRES_COLUMN} < $1"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COCKROACH: SqlDialect = SqlDialect::Cockroach {
+        follower_reads: false,
+    };
+    const COCKROACH_FOLLOWER_READS: SqlDialect = SqlDialect::Cockroach {
+        follower_reads: true,
+    };
+
+    #[test]
+    fn cockroach_save_updates_index_column() {
+        for created_column in [None, Some("created")] {
+            let sql = save("sessions", "user_id", COCKROACH, created_column);
+            assert!(sql.starts_with("INSERT INTO \"sessions\""), "{sql}");
+            assert!(
+                sql.ends_with(
+                    "ON CONFLICT (id) DO UPDATE SET user_id = EXCLUDED.user_id, \
+                    data = EXCLUDED.data, expires = EXCLUDED.expires"
+                ),
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn standard_save_does_not_update_index_column() {
+        let sql = save("sessions", "user_id", SqlDialect::Standard, None);
+        assert!(sql.starts_with("INSERT INTO \"sessions\" (id, user_id, data, expires)"));
+        assert!(sql.ends_with(
+            "ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data, expires = EXCLUDED.expires"
+        ));
+    }
+
+    #[test]
+    fn cockroach_follower_reads() {
+        for sql in [
+            all_session_ids("sessions", "user_id", COCKROACH_FOLLOWER_READS, None),
+            all_session_data("sessions", "user_id", COCKROACH_FOLLOWER_READS),
+        ] {
+            assert!(
+                sql.contains("FROM \"sessions\" AS OF SYSTEM TIME follower_read_timestamp() WHERE"),
+                "{sql}"
+            );
+        }
+        for dialect in [SqlDialect::Standard, COCKROACH] {
+            let sql = all_session_ids("sessions", "user_id", dialect, None);
+            assert!(!sql.contains("AS OF SYSTEM TIME"), "{sql}");
+        }
+    }
+}
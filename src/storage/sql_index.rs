@@ -0,0 +1,198 @@
+//! Session index schema for SQL-based storage
+//!
+//! The built-in SQL storage providers index sessions with an identifier column in the sessions
+//! table. [`SqlIndexSchema`] generates the SQL for that design, as well as for a separate index table
+//! with a foreign key to the sessions table, which allows multiple identifiers per session (e.g. a user
+//! ID and an organization ID). You can use it when writing a custom SQL-based storage provider.
+//!
+//! # Example
+//! ```
+//! use rocket_flex_session::storage::sql_index::SqlIndexSchema;
+//!
+//! async fn setup(pool: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+//!     let schema = SqlIndexSchema::table("session_identifiers");
+//!     for statement in schema.create_index_table("sessions", "TEXT") {
+//!         sqlx::query(&statement).execute(pool).await?;
+//!     }
+//!
+//!     // After saving a session, add its identifiers to the index
+//!     if let Some(insert) = schema.insert_identifier() {
+//!         for identifier in ["user:1", "org:2"] {
+//!             sqlx::query(&insert)
+//!                 .bind("session_id")
+//!                 .bind(identifier)
+//!                 .execute(pool)
+//!                 .await?;
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use super::sql::{DATA_COLUMN, EXPIRES_COLUMN, ID_COLUMN};
+
+/// Design of the session index in a SQL database. Queries use numbered placeholders
+/// (`$1`, `$2`, ...), which are supported by Postgres and SQLite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SqlIndexSchema {
+    /// The identifier is stored in a column of the sessions table. This is the
+    /// design used by the built-in storage providers.
+    Column {
+        /// Name of the identifier column
+        column: String,
+    },
+    /// Identifiers are stored in a separate table, with a foreign key to the sessions table.
+    /// Deleting a session will also delete its index rows, via `ON DELETE CASCADE` (for SQLite,
+    /// make sure that foreign keys are enabled).
+    Table {
+        /// Name of the index table
+        table: String,
+        /// Name of the column referencing the session ID
+        session_id_column: String,
+        /// Name of the identifier column
+        identifier_column: String,
+    },
+}
+
+impl SqlIndexSchema {
+    /// Index sessions with the given identifier column in the sessions table
+    pub fn column(column: impl Into<String>) -> Self {
+        Self::Column {
+            column: column.into(),
+        }
+    }
+
+    /// Index sessions in a separate table, with `session_id` and `identifier` columns
+    pub fn table(table: impl Into<String>) -> Self {
+        Self::Table {
+            table: table.into(),
+            session_id_column: "session_id".to_owned(),
+            identifier_column: "identifier".to_owned(),
+        }
+    }
+
+    /// Statements to create the index table and its identifier index. Returns no statements for
+    /// the `Column` design, since the column should be created along with the sessions table.
+    pub fn create_index_table(&self, sessions_table: &str, identifier_type: &str) -> Vec<String> {
+        match self {
+            Self::Column { .. } => Vec::new(),
+            Self::Table {
+                table,
+                session_id_column,
+                identifier_column,
+            } => vec![
+                format!(
+                    "CREATE TABLE IF NOT EXISTS \"{table}\" (\
+                        {session_id_column} TEXT NOT NULL REFERENCES \"{sessions_table}\" ({ID_COLUMN}) ON DELETE CASCADE, \
+                        {identifier_column} {identifier_type} NOT NULL, \
+                        PRIMARY KEY ({session_id_column}, {identifier_column})\
+                    )"
+                ),
+                format!(
+                    "CREATE INDEX IF NOT EXISTS \"{table}_{identifier_column}_idx\" \
+                    ON \"{table}\" ({identifier_column})"
+                ),
+            ],
+        }
+    }
+
+    /// Add an identifier to a session. Bind the session ID and identifier.
+    /// Returns `None` for the `Column` design, where the identifier is saved with the session.
+    pub fn insert_identifier(&self) -> Option<String> {
+        match self {
+            Self::Column { .. } => None,
+            Self::Table {
+                table,
+                session_id_column,
+                identifier_column,
+            } => Some(format!(
+                "INSERT INTO \"{table}\" ({session_id_column}, {identifier_column}) \
+                VALUES ($1, $2) ON CONFLICT DO NOTHING"
+            )),
+        }
+    }
+
+    /// Remove all identifiers from a session. Bind the session ID.
+    /// Returns `None` for the `Column` design.
+    pub fn delete_identifiers(&self) -> Option<String> {
+        match self {
+            Self::Column { .. } => None,
+            Self::Table {
+                table,
+                session_id_column,
+                ..
+            } => Some(format!(
+                "DELETE FROM \"{table}\" WHERE {session_id_column} = $1"
+            )),
+        }
+    }
+
+    /// Get the IDs of active sessions belonging to an identifier. Bind the identifier and current time
+    pub fn session_ids(&self, sessions_table: &str) -> String {
+        self.select_sessions(sessions_table, &[ID_COLUMN], "")
+    }
+
+    /// Get the ID, data, and expiration of active sessions belonging to an identifier.
+    /// Bind the identifier and current time
+    pub fn sessions(&self, sessions_table: &str) -> String {
+        self.select_sessions(
+            sessions_table,
+            &[ID_COLUMN, DATA_COLUMN, EXPIRES_COLUMN],
+            "",
+        )
+    }
+
+    /// Delete all sessions belonging to an identifier. Bind the identifier, and the
+    /// session ID to exclude if `excluded_id` is true
+    pub fn invalidate_sessions(&self, sessions_table: &str, excluded_id: bool) -> String {
+        let mut sql = match self {
+            Self::Column { column } => {
+                format!("DELETE FROM \"{sessions_table}\" WHERE {column} = $1")
+            }
+            Self::Table {
+                table,
+                session_id_column,
+                identifier_column,
+            } => format!(
+                "DELETE FROM \"{sessions_table}\" WHERE {ID_COLUMN} IN \
+                (SELECT {session_id_column} FROM \"{table}\" WHERE {identifier_column} = $1)"
+            ),
+        };
+        if excluded_id {
+            sql.push_str(&format!(" AND {ID_COLUMN} != $2"));
+        }
+        sql
+    }
+
+    /// Select session columns belonging to an identifier, with an optional clause after the `FROM` clause
+    pub(crate) fn select_sessions(
+        &self,
+        sessions_table: &str,
+        columns: &[&str],
+        from_suffix: &str,
+    ) -> String {
+        match self {
+            Self::Column { column } => format!(
+                "SELECT {} FROM \"{sessions_table}\"{from_suffix} \
+                WHERE {column} = $1 AND {EXPIRES_COLUMN} > $2",
+                columns.join(", ")
+            ),
+            Self::Table {
+                table,
+                session_id_column,
+                identifier_column,
+            } => {
+                let columns = columns
+                    .iter()
+                    .map(|c| format!("s.{c}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "SELECT {columns} FROM \"{sessions_table}\" s \
+                    JOIN \"{table}\" i ON i.{session_id_column} = s.{ID_COLUMN}{from_suffix} \
+                    WHERE i.{identifier_column} = $1 AND s.{EXPIRES_COLUMN} > $2"
+                )
+            }
+        }
+    }
+}
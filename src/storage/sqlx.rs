@@ -1,6 +1,7 @@
 //! Session storage via sqlx
 
 mod base;
+pub use base::CleanupStats;
 use base::*;
 
 #[cfg(feature = "sqlx_postgres")]
@@ -13,6 +14,12 @@ mod sqlite;
 #[cfg(feature = "sqlx_sqlite")]
 pub use sqlite::SqlxSqliteStorage;
 
+mod throttle;
+#[cfg(feature = "sqlx_postgres")]
+pub use throttle::SqlxPostgresThrottle;
+#[cfg(feature = "sqlx_sqlite")]
+pub use throttle::SqlxSqliteThrottle;
+
 use crate::SessionIdentifier;
 
 /**
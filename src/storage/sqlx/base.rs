@@ -1,23 +1,95 @@
+use std::sync::{
+    atomic::{AtomicI64, AtomicU64, Ordering},
+    Arc,
+};
+
+use rand::Rng;
 use rocket::{
     time::{Duration, OffsetDateTime},
     tokio::{
-        sync::{oneshot, Mutex},
-        time::interval,
+        sync::Mutex,
+        time::{interval, sleep},
     },
 };
 
-use crate::error::{SessionError, SessionResult};
+use crate::{
+    error::SessionResult,
+    storage::{sql, TaskGuard},
+};
 
-pub(super) const ID_COLUMN: &str = "id";
-pub(super) const DATA_COLUMN: &str = "data";
-pub(super) const EXPIRES_COLUMN: &str = "expires";
+pub(super) use crate::storage::sql::{SqlDialect, DATA_COLUMN, EXPIRES_COLUMN, ID_COLUMN};
 
 /// Convert expiration time to TTL
-pub(super) fn expires_to_ttl(expires: &OffsetDateTime) -> u32 {
-    (*expires - OffsetDateTime::now_utc())
-        .whole_seconds()
-        .try_into()
-        .unwrap_or(0)
+pub(super) fn expires_to_ttl(expires: &OffsetDateTime, now: OffsetDateTime) -> u32 {
+    (*expires - now).whole_seconds().try_into().unwrap_or(0)
+}
+
+/// Marker for a clock skew that hasn't been measured yet
+const UNMEASURED_SKEW: i64 = i64::MIN;
+
+/// Interval to re-measure the clock skew in skew-corrected mode
+const SKEW_MEASURE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The current time used for TTL math. In skew-corrected mode, this is the app's time
+/// corrected by the last measured skew of the database clock.
+pub(super) struct DbClock {
+    skew_corrected: bool,
+    /// Skew of the database clock in milliseconds (database time - app time)
+    skew_ms: AtomicI64,
+}
+
+impl DbClock {
+    pub fn new(skew_corrected: bool) -> Self {
+        Self {
+            skew_corrected,
+            skew_ms: AtomicI64::new(UNMEASURED_SKEW),
+        }
+    }
+
+    /// Whether skew-corrected mode is enabled
+    pub fn is_skew_corrected(&self) -> bool {
+        self.skew_corrected
+    }
+
+    /// The current time
+    pub fn now(&self) -> OffsetDateTime {
+        let now = OffsetDateTime::now_utc();
+        match self.skew().filter(|_| self.skew_corrected) {
+            Some(skew) => now + skew,
+            None => now,
+        }
+    }
+
+    /// The last measured skew of the database clock, if measured
+    pub fn skew(&self) -> Option<Duration> {
+        match self.skew_ms.load(Ordering::Relaxed) {
+            UNMEASURED_SKEW => None,
+            skew_ms => Some(Duration::milliseconds(skew_ms)),
+        }
+    }
+
+    /// Measure the skew of the database clock, by comparing the time returned by the query
+    /// with the midpoint of the app's time before and after the query.
+    pub async fn measure<DB>(&self, pool: &sqlx::Pool<DB>, sql: &str) -> SessionResult<Duration>
+    where
+        DB: sqlx::Database,
+        for<'q> <DB as sqlx::Database>::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
+        for<'c> &'c mut <DB as sqlx::Database>::Connection: sqlx::Executor<'c, Database = DB>,
+        OffsetDateTime: for<'r> sqlx::Decode<'r, DB> + sqlx::Type<DB>,
+        (OffsetDateTime,): for<'r> sqlx::FromRow<'r, DB::Row>,
+    {
+        let before = OffsetDateTime::now_utc();
+        let db_now: OffsetDateTime = sqlx::query_scalar(sql).fetch_one(pool).await?;
+        let after = OffsetDateTime::now_utc();
+
+        let skew: Duration = db_now - (before + (after - before) / 2);
+        let skew_ms = i64::try_from(skew.whole_milliseconds()).unwrap_or(0);
+        self.skew_ms.store(skew_ms, Ordering::Relaxed);
+        if self.skew_corrected {
+            rocket::debug!("Measured database clock skew: {skew_ms}ms");
+        }
+        Ok(Duration::milliseconds(skew_ms))
+    }
 }
 
 /// Base struct for SQLx storage
@@ -25,6 +97,9 @@ pub(super) struct SqlxBase<DB: sqlx::Database> {
     pool: sqlx::Pool<DB>,
     table_name: String,
     index_column: String,
+    dialect: SqlDialect,
+    clock: Arc<DbClock>,
+    created_column: Option<String>,
 }
 
 impl<DB> SqlxBase<DB>
@@ -35,52 +110,114 @@ where
     OffsetDateTime: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
     String: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
 {
-    pub fn new(pool: sqlx::Pool<DB>, table_name: String, index_column: String) -> Self {
+    pub fn new(
+        pool: sqlx::Pool<DB>,
+        table_name: String,
+        index_column: String,
+        clock: Arc<DbClock>,
+    ) -> Self {
         SqlxBase {
             pool,
             table_name,
             index_column,
+            dialect: SqlDialect::default(),
+            clock,
+            created_column: None,
         }
     }
 
+    pub fn with_dialect(mut self, dialect: SqlDialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    pub fn with_created_column(mut self, created_column: Option<String>) -> Self {
+        self.created_column = created_column;
+        self
+    }
+
+    /// The column storing the creation time of sessions, if any
+    pub fn created_column(&self) -> Option<&str> {
+        self.created_column.as_deref()
+    }
+
+    /// Get the creation time of a session row, if there's a creation time column
+    pub fn created_at(&self, row: &DB::Row) -> Option<OffsetDateTime>
+    where
+        Option<OffsetDateTime>: for<'r> sqlx::Decode<'r, DB> + sqlx::Type<DB>,
+        for<'a> &'a str: sqlx::ColumnIndex<DB::Row>,
+    {
+        use sqlx::Row;
+        row.try_get(self.created_column()?).ok().flatten()
+    }
+
     pub async fn load(&self, id: &str, ttl: Option<u32>) -> Result<Option<DB::Row>, sqlx::Error> {
+        let now = self.clock.now();
         match ttl {
             Some(new_ttl) => {
-                sqlx::query(&sql::load_and_update_ttl(&self.table_name))
-                    .bind(OffsetDateTime::now_utc() + Duration::seconds(new_ttl.into()))
-                    .bind(id.to_owned())
-                    .bind(OffsetDateTime::now_utc())
-                    .fetch_optional(&self.pool)
-                    .await
+                sqlx::query(&sql::load_and_update_ttl(
+                    &self.table_name,
+                    self.created_column(),
+                ))
+                .bind(now + Duration::seconds(new_ttl.into()))
+                .bind(id.to_owned())
+                .bind(now)
+                .fetch_optional(&self.pool)
+                .await
             }
             None => {
-                sqlx::query(&sql::load(&self.table_name))
+                sqlx::query(&sql::load(&self.table_name, self.created_column()))
                     .bind(id.to_owned())
-                    .bind(OffsetDateTime::now_utc())
+                    .bind(now)
                     .fetch_optional(&self.pool)
                     .await
             }
         }
     }
 
+    pub async fn exists(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query(&sql::exists(&self.table_name))
+            .bind(id.to_owned())
+            .bind(self.clock.now())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Save a session. The creation time is set if the session isn't stored yet, to the given
+    /// creation time or the current time.
     pub async fn save<V, I>(
         &self,
         id: &str,
         value: V,
         index: Option<I>,
-        ttl: u32,
+        ttl: std::time::Duration,
+        created_at: Option<OffsetDateTime>,
     ) -> Result<DB::QueryResult, sqlx::Error>
     where
         V: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
         Option<I>: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
     {
-        sqlx::query(&sql::save(&self.table_name, &self.index_column))
+        let sql = sql::save(
+            &self.table_name,
+            &self.index_column,
+            self.dialect,
+            self.created_column(),
+        );
+        let now = self.clock.now();
+        let mut query = sqlx::query(&sql)
             .bind(id.to_owned())
             .bind(index)
             .bind(value)
-            .bind(OffsetDateTime::now_utc() + Duration::seconds(ttl.into()))
-            .execute(&self.pool)
-            .await
+            .bind(now + ttl);
+        if self.created_column.is_some() {
+            query = query.bind(created_at.unwrap_or(now));
+        }
+        query.execute(&self.pool).await
+    }
+
+    pub fn clock(&self) -> &DbClock {
+        &self.clock
     }
 
     pub async fn delete(&self, id: &str) -> Result<DB::QueryResult, sqlx::Error> {
@@ -97,11 +234,16 @@ where
     where
         I: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
     {
-        sqlx::query(&sql::all_session_ids(&self.table_name, &self.index_column))
-            .bind(identifier)
-            .bind(OffsetDateTime::now_utc())
-            .fetch_all(&self.pool)
-            .await
+        sqlx::query(&sql::all_session_ids(
+            &self.table_name,
+            &self.index_column,
+            self.dialect,
+            self.created_column(),
+        ))
+        .bind(identifier)
+        .bind(self.clock.now())
+        .fetch_all(&self.pool)
+        .await
     }
 
     pub async fn sessions_belonging_to<I>(
@@ -111,9 +253,24 @@ where
     where
         I: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
     {
-        sqlx::query(&sql::all_session_data(&self.table_name, &self.index_column))
-            .bind(identifier)
-            .bind(OffsetDateTime::now_utc())
+        sqlx::query(&sql::all_session_data(
+            &self.table_name,
+            &self.index_column,
+            self.dialect,
+        ))
+        .bind(identifier)
+        .bind(self.clock.now())
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn recently_active(&self, limit: usize) -> Result<Vec<DB::Row>, sqlx::Error>
+    where
+        i64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    {
+        sqlx::query(&sql::recently_active(&self.table_name))
+            .bind(self.clock.now())
+            .bind(i64::try_from(limit).unwrap_or(i64::MAX))
             .fetch_all(&self.pool)
             .await
     }
@@ -136,134 +293,318 @@ where
     }
 }
 
-/// SQL queries
-mod sql {
-    use super::*;
+/// Number of rows affected by a query
+pub(super) trait RowsAffected {
+    fn rows_affected(&self) -> u64;
+}
 
-    /// Load session data. Bind session ID and current time
-    pub fn load(table_name: &str) -> String {
-        format!(
-            "SELECT {DATA_COLUMN}, {EXPIRES_COLUMN} FROM \"{table_name}\" \
-            WHERE {ID_COLUMN} = $1 AND {EXPIRES_COLUMN} > $2"
-        )
+#[cfg(feature = "sqlx_postgres")]
+impl RowsAffected for sqlx::postgres::PgQueryResult {
+    fn rows_affected(&self) -> u64 {
+        self.rows_affected()
     }
+}
 
-    /// Load session data and update TTL. Bind expiration, session ID, and current time
-    pub fn load_and_update_ttl(table_name: &str) -> String {
-        format!(
-            "UPDATE \"{table_name}\" SET {EXPIRES_COLUMN} = $1 \
-            WHERE {ID_COLUMN} = $2 AND {EXPIRES_COLUMN} > $3 \
-            RETURNING {DATA_COLUMN}, {EXPIRES_COLUMN}",
-        )
+#[cfg(feature = "sqlx_sqlite")]
+impl RowsAffected for sqlx::sqlite::SqliteQueryResult {
+    fn rows_affected(&self) -> u64 {
+        self.rows_affected()
     }
+}
+
+/// Statistics of the cleanups of expired sessions, for monitoring. Clones share the same counters.
+#[derive(Clone, Debug, Default)]
+pub struct CleanupStats {
+    counters: Arc<CleanupCounters>,
+}
 
-    /// Save session data. Bind the session ID, index, data, and expiration
-    pub fn save(table_name: &str, index_column: &str) -> String {
-        format!(
-        "INSERT INTO \"{table_name}\" ({ID_COLUMN}, {index_column}, {DATA_COLUMN}, {EXPIRES_COLUMN}) \
-        VALUES ($1, $2, $3, $4) \
-        ON CONFLICT ({ID_COLUMN}) DO UPDATE SET \
-            {DATA_COLUMN} = EXCLUDED.{DATA_COLUMN}, \
-            {EXPIRES_COLUMN} = EXCLUDED.{EXPIRES_COLUMN}"
-    )
+#[derive(Debug, Default)]
+struct CleanupCounters {
+    runs: AtomicU64,
+    errors: AtomicU64,
+    deleted: AtomicU64,
+    last_deleted: AtomicU64,
+}
+
+impl CleanupStats {
+    /// Number of cleanup runs
+    pub fn runs(&self) -> u64 {
+        self.counters.runs.load(Ordering::Relaxed)
     }
 
-    /// Delete session data. Bind the session ID
-    pub fn delete(table_name: &str) -> String {
-        format!("DELETE FROM \"{table_name}\" WHERE {ID_COLUMN} = $1")
+    /// Number of cleanup runs that failed (possibly after deleting some batches)
+    pub fn errors(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
     }
 
-    /// Get session IDs belonging to a user/identifier. Bind the identifier and current time
-    pub fn all_session_ids(table_name: &str, index_column: &str) -> String {
-        format!(
-            "SELECT {ID_COLUMN} FROM \"{table_name}\" \
-            WHERE {index_column} = $1 AND {EXPIRES_COLUMN} > $2"
-        )
+    /// Total number of expired sessions deleted
+    pub fn deleted(&self) -> u64 {
+        self.counters.deleted.load(Ordering::Relaxed)
     }
 
-    /// Get session data belonging to a user/identifier. Bind the identifier and current time
-    pub fn all_session_data(table_name: &str, index_column: &str) -> String {
-        format!(
-            "SELECT {ID_COLUMN}, {DATA_COLUMN}, {EXPIRES_COLUMN} FROM \"{table_name}\" \
-            WHERE {index_column} = $1 AND {EXPIRES_COLUMN} > $2"
-        )
+    /// Number of expired sessions deleted by the last cleanup run
+    pub fn last_deleted(&self) -> u64 {
+        self.counters.last_deleted.load(Ordering::Relaxed)
     }
 
-    /// Invalidate all sessions belonging to a user/identifier. Bind the identifier and the optional session ID to exclude
-    pub fn invalidate_all(table_name: &str, index_column: &str, excluded_id: bool) -> String {
-        let mut sql = format!("DELETE FROM \"{table_name}\" WHERE {index_column} = $1");
-        if excluded_id {
-            sql.push_str(&format!(" AND {ID_COLUMN} != $2"));
+    fn record(&self, deleted: u64, failed: bool) {
+        let counters = &self.counters;
+        counters.runs.fetch_add(1, Ordering::Relaxed);
+        counters.deleted.fetch_add(deleted, Ordering::Relaxed);
+        counters.last_deleted.store(deleted, Ordering::Relaxed);
+        if failed {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
         }
-        sql
+    }
+}
+
+/// Limit on the number of sessions deleted by each query of a cleanup
+#[derive(Clone, Copy)]
+struct CleanupBatch {
+    size: u64,
+    /// Pause between batches
+    delay: Option<std::time::Duration>,
+}
+
+/// Query and settings to delete expired sessions
+#[derive(Clone)]
+struct CleanupQuery {
+    /// Bind the current time, and the batch size if batched
+    sql: String,
+    batch: Option<CleanupBatch>,
+    stats: CleanupStats,
+}
+
+impl CleanupQuery {
+    /// Delete expired sessions, in batches if configured, and return the number of deleted sessions
+    async fn run<DB>(&self, pool: &sqlx::Pool<DB>, clock: &DbClock) -> Result<u64, sqlx::Error>
+    where
+        DB: sqlx::Database,
+        DB::QueryResult: RowsAffected,
+        for<'q> <DB as sqlx::Database>::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
+        for<'c> &'c mut <DB as sqlx::Database>::Connection: sqlx::Executor<'c, Database = DB>,
+        OffsetDateTime: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        i64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    {
+        // All batches use the same cutoff, so that the cleanup ends
+        let now = clock.now();
+        let mut deleted = 0;
+        let result = loop {
+            let mut query = sqlx::query(&self.sql).bind(now);
+            if let Some(batch) = self.batch {
+                query = query.bind(i64::try_from(batch.size).unwrap_or(i64::MAX));
+            }
+            let rows = match query.execute(pool).await {
+                Ok(result) => result.rows_affected(),
+                Err(e) => break Err(e),
+            };
+            deleted += rows;
+            match self.batch {
+                Some(batch) if rows >= batch.size => {
+                    if let Some(delay) = batch.delay {
+                        sleep(delay).await;
+                    }
+                }
+                _ => break Ok(deleted),
+            }
+        };
+        self.stats.record(deleted, result.is_err());
+        result
+    }
+}
+
+/// Random delay of up to the given jitter
+fn jitter_delay(jitter: Option<std::time::Duration>) -> std::time::Duration {
+    match jitter {
+        Some(max) if !max.is_zero() => rand::rng().random_range(std::time::Duration::ZERO..=max),
+        _ => std::time::Duration::ZERO,
     }
 }
 
 /// Session cleanup task
-#[derive(Default)]
 pub(super) struct SqlxCleanupTask {
     interval: Option<std::time::Duration>,
-    shutdown_tx: Mutex<Option<oneshot::Sender<u8>>>,
+    jitter: Option<std::time::Duration>,
+    task: Mutex<Option<TaskGuard>>,
+    /// Task re-measuring the clock skew in skew-corrected mode
+    skew_task: Mutex<Option<TaskGuard>>,
     table_name: String,
+    /// Build the query to delete expired sessions, given the table name and whether it's batched
+    delete_sql: fn(&str, bool) -> String,
+    batch: Option<CleanupBatch>,
+    stats: CleanupStats,
+    clock: Arc<DbClock>,
+    /// Query for the current database time, to re-measure the clock skew in skew-corrected mode
+    now_sql: &'static str,
+    /// Lock held by the cleanup task while deleting, if the storage serializes its writes
+    write_lock: Option<Arc<Mutex<()>>>,
 }
 
 impl SqlxCleanupTask {
-    pub fn new(cleanup_interval: Option<std::time::Duration>, table_name: &str) -> Self {
+    pub fn new(
+        cleanup_interval: Option<std::time::Duration>,
+        table_name: &str,
+        clock: Arc<DbClock>,
+        now_sql: &'static str,
+    ) -> Self {
         Self {
             interval: cleanup_interval,
-            shutdown_tx: Mutex::default(),
+            jitter: None,
+            task: Mutex::default(),
+            skew_task: Mutex::default(),
             table_name: table_name.to_string(),
+            delete_sql: sql::delete_expired,
+            batch: None,
+            stats: CleanupStats::default(),
+            clock,
+            now_sql,
+            write_lock: None,
         }
     }
 
-    pub async fn setup<DB>(&self, pool: &sqlx::Pool<DB>) -> SessionResult<()>
+    /// Delay each cleanup by a random duration of up to `jitter`
+    pub fn with_jitter(mut self, jitter: Option<std::time::Duration>) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Delete at most `batch_size` sessions per query, pausing for `delay` between queries
+    pub fn with_batches(
+        mut self,
+        batch_size: Option<u64>,
+        delay: Option<std::time::Duration>,
+    ) -> Self {
+        self.batch = batch_size
+            .filter(|size| *size > 0)
+            .map(|size| CleanupBatch { size, delay });
+        self
+    }
+
+    /// Hold the storage's write lock while the cleanup task deletes expired sessions
+    pub fn with_write_lock(mut self, write_lock: Option<Arc<Mutex<()>>>) -> Self {
+        self.write_lock = write_lock;
+        self
+    }
+
+    /// Use a custom query to delete expired sessions
+    pub fn with_delete_sql(mut self, delete_sql: fn(&str, bool) -> String) -> Self {
+        self.delete_sql = delete_sql;
+        self
+    }
+
+    /// Statistics of the cleanup runs
+    pub fn stats(&self) -> &CleanupStats {
+        &self.stats
+    }
+
+    /// Whether the cleanup task is running
+    pub async fn is_running(&self) -> bool {
+        let task = self.task.lock().await;
+        task.as_ref().is_some_and(|task| !task.is_finished())
+    }
+
+    fn query(&self) -> CleanupQuery {
+        CleanupQuery {
+            sql: (self.delete_sql)(&self.table_name, self.batch.is_some()),
+            batch: self.batch,
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Delete expired sessions now, returning the number of deleted sessions
+    pub async fn delete_expired<DB>(&self, pool: &sqlx::Pool<DB>) -> SessionResult<u64>
     where
         DB: sqlx::Database,
+        DB::QueryResult: RowsAffected,
         for<'q> <DB as sqlx::Database>::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
         for<'c> &'c mut <DB as sqlx::Database>::Connection: sqlx::Executor<'c, Database = DB>,
         OffsetDateTime: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+        i64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
     {
+        Ok(self.query().run(pool, &self.clock).await?)
+    }
+
+    pub async fn setup<DB>(&self, pool: &sqlx::Pool<DB>) -> SessionResult<()>
+    where
+        DB: sqlx::Database,
+        DB::QueryResult: RowsAffected,
+        for<'q> <DB as sqlx::Database>::Arguments<'q>: sqlx::IntoArguments<'q, DB>,
+        for<'c> &'c mut <DB as sqlx::Database>::Connection: sqlx::Executor<'c, Database = DB>,
+        OffsetDateTime:
+            for<'q> sqlx::Encode<'q, DB> + for<'r> sqlx::Decode<'r, DB> + sqlx::Type<DB>,
+        (OffsetDateTime,): for<'r> sqlx::FromRow<'r, DB::Row>,
+        i64: for<'q> sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+    {
+        if self.clock.is_skew_corrected() {
+            self.clock.measure(pool, self.now_sql).await?;
+
+            // Re-measure on a timer of its own, so the skew stays fresh without cleanups
+            let pool = pool.clone();
+            let (clock, now_sql) = (self.clock.clone(), self.now_sql);
+            let skew_task = TaskGuard::spawn(|mut shutdown_rx| async move {
+                let mut interval = interval(SKEW_MEASURE_INTERVAL);
+                // The first tick completes immediately, and the skew was just measured
+                interval.tick().await;
+                loop {
+                    rocket::tokio::select! {
+                        _ = interval.tick() => {
+                            if let Err(e) = clock.measure(&pool, now_sql).await {
+                                rocket::warn!("Error measuring database clock skew: {e}");
+                            }
+                        }
+                        _ = &mut shutdown_rx => break,
+                    }
+                }
+            });
+            self.skew_task.lock().await.replace(skew_task);
+        }
         let Some(cleanup_interval) = self.interval else {
             return Ok(());
         };
 
-        let (tx, mut rx) = oneshot::channel();
-        self.shutdown_tx.lock().await.replace(tx);
-
         let pool = pool.clone();
-        let table_name = self.table_name.clone();
-        rocket::tokio::spawn(async move {
+        let (jitter, query) = (self.jitter, self.query());
+        let (clock, write_lock) = (self.clock.clone(), self.write_lock.clone());
+        let task = TaskGuard::spawn(|mut shutdown_rx| async move {
             rocket::info!("Starting session cleanup monitor");
             let mut interval = interval(cleanup_interval);
             loop {
+                // Instances started at the same time shouldn't all clean up at once
+                let next_cleanup = async {
+                    interval.tick().await;
+                    sleep(jitter_delay(jitter)).await;
+                };
                 rocket::tokio::select! {
-                    _ = interval.tick() => {
+                    _ = next_cleanup => {
                         rocket::debug!("Cleaning up expired sessions");
-                        if let Err(e) = sqlx::query(&format!(
-                            "DELETE FROM \"{table_name}\" WHERE {EXPIRES_COLUMN} < $1"
-                            ))
-                            .bind(OffsetDateTime::now_utc())
-                            .execute(&pool)
-                            .await
-                        {
-                            rocket::error!("Error deleting expired sessions: {e}");
+                        let _guard = match &write_lock {
+                            Some(lock) => Some(lock.lock().await),
+                            None => None,
+                        };
+                        match query.run(&pool, &clock).await {
+                            Ok(deleted) => rocket::debug!("Deleted {deleted} expired sessions"),
+                            Err(e) => rocket::error!("Error deleting expired sessions: {e}"),
                         }
                     }
-                    _ = &mut rx => {
+                    _ = &mut shutdown_rx => {
                         rocket::info!("Session cleanup monitor shutdown");
+                        break;
                     }
                 }
             }
         });
+        // Replacing a previous task stops it
+        self.task.lock().await.replace(task);
 
         Ok(())
     }
 
+    /// Stop the cleanup task, waiting for a cleanup in progress to finish
     pub async fn shutdown(&self) -> SessionResult<()> {
-        if let Some(tx) = self.shutdown_tx.lock().await.take() {
-            tx.send(0).map_err(|_| {
-                SessionError::SetupTeardown("Failed to send shutdown signal".to_string())
-            })?;
+        let skew_task = self.skew_task.lock().await.take();
+        let task = self.task.lock().await.take();
+        for task in [skew_task, task].into_iter().flatten() {
+            task.shutdown().await?;
         }
         Ok(())
     }
@@ -1,10 +1,23 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
 use bon::bon;
-use rocket::{async_trait, http::CookieJar};
-use sqlx::{postgres::PgRow, PgPool, Postgres, Row};
+use rocket::{
+    async_trait,
+    futures::{future, StreamExt},
+    time::{Duration, OffsetDateTime},
+};
+use sqlx::{
+    postgres::{PgListener, PgRow},
+    PgPool, Postgres, Row,
+};
 
 use crate::{
     error::{SessionError, SessionResult},
-    storage::{SessionStorage, SessionStorageIndexed},
+    security::session_id_eq,
+    storage::{
+        sql, SessionChange, SessionChangeStream, SessionContext, SessionMetadata, SessionStorage,
+        SessionStorageIndexed,
+    },
 };
 
 use super::*;
@@ -16,17 +29,87 @@ Session store using PostgreSQL via [sqlx](https://docs.rs/crate/sqlx).
 - You must pass in an initialized sqlx Postgres connection pool.
 - Your session data type must implement [`SessionSqlx`] to configure how to convert & store session data.
 - Your session data type must implement [`SessionIdentifier`]. The SessionIdentifier's
-[Id](`SessionIdentifier::Id`) type must be a type supported by sqlx.
+  [Id](`SessionIdentifier::Id`) type must be a type supported by sqlx.
 - Expects a table to already exist with the following columns:
 
 | Name | Type |
 |------|---------|
 | id   | `text` PRIMARY KEY |
-| data | `text` NOT NULL (or `jsonb`)  |
+| data | `text` NOT NULL (or `jsonb` / `bytea`)  |
 | user_id | SQL type of `SessionIdentifier::Id` |
 | expires | `timestamptz` NOT NULL |
 
 The name of the session index column ("user_id") can be customized when building the storage.
+The type of the data column should match [`SessionSqlx::Data`]: use `bytea` with `Vec<u8>`
+to store binary payloads (e.g. MessagePack or [compressed](crate::storage::compression) data)
+without encoding them as text.
+
+# CockroachDB
+This storage can also be used with [CockroachDB](https://www.cockroachlabs.com/) by enabling the
+`cockroach` option. In this mode, operations that fail due to transaction contention
+(SQLSTATE `40001`) are retried a few times, with an exponential backoff, before returning an error.
+
+Sessions are saved with the same `INSERT ... ON CONFLICT` statement as for Postgres, but while
+Postgres only updates the data and expiration of an existing session, CockroachDB mode also
+updates the index column on every save. This is intended: the index column always matches the
+current identifier of the session, e.g. after a user signs in without the session ID being
+rotated. The creation time of an existing session (see [below](#creation-time)) is never updated.
+
+You can additionally enable `follower_reads` to list sessions by identifier using
+`AS OF SYSTEM TIME follower_read_timestamp()`, which lowers latency in multi-region clusters at
+the cost of slightly stale results.
+
+# Large sessions
+To keep the sessions table small while still allowing occasional large sessions, set the
+`offload_table` option. Session data larger than `offload_threshold` bytes is then stored in the
+offload table, and the data column of the session row is set to `NULL` as a pointer to it.
+Loading an offloaded session takes an extra query. The size of the data is measured with
+`octet_length`, so the data column must be `text` or `bytea`, and it must be nullable.
+The offload table needs the following columns:
+
+| Name | Type |
+|------|---------|
+| id   | `text` PRIMARY KEY, referencing the sessions table's `id` with `ON DELETE CASCADE` |
+| data | same type as the data column of the sessions table, NOT NULL |
+
+Deleting a session (or cleaning up expired sessions) deletes its offloaded data via the cascade.
+
+# Single-use values
+To support [single-use values](crate::Session::take_once), set the name of a table for them with
+`once_table`. Values are taken with `DELETE ... RETURNING`, so only one request can take each
+value. The table needs the following columns:
+
+| Name | Type |
+|------|---------|
+| session_id | `text`, referencing the sessions table's `id` with `ON DELETE CASCADE` |
+| key  | `text` |
+| value | `text` NOT NULL |
+| expires | `timestamptz` NOT NULL |
+
+with a primary key of `(session_id, key)`. Expired values are ignored, and deleted when taken
+or along with their session.
+
+# Session changes
+If `notify_changes` is enabled, changes to indexed sessions are published with `pg_notify` to
+the `<table_name>_changes` channel, and can be subscribed to with
+[`SessionStorageIndexed::subscribe_identifier`]. Each subscription opens a new connection
+from the pool. Changes are published after they're stored, so a failure to publish is logged,
+without failing the operation.
+
+# Creation time
+To track the creation time of sessions, add a nullable column with the same type as the
+expiration column, and set its name with `created_column`. It's set when a session is first
+saved, and returned by [`Session::created_at`](crate::Session::created_at).
+
+# Clock skew
+Session expiration is calculated with the app's clock, and compared with the expiration stored
+in the database. If the clocks of your app servers drift apart, sessions may expire early or
+late depending on the server that handles the request. Enable `skew_corrected_time` to correct
+for this: the skew between the app's clock and `CURRENT_TIMESTAMP` is measured during setup (and
+again every minute), and all expiration times are calculated with the app's clock corrected by
+the last measured skew. This is an approximation of the database's clock, which can be off by
+the drift since the last measurement and by the latency of the measuring query. The measured
+skew is available with [`clock_skew`](Self::clock_skew), e.g. to monitor drift.
 
 # Session storage
 Sessions are stored in the table specified by `table_name`, along with the optional identifier
@@ -34,6 +117,16 @@ Sessions are stored in the table specified by `table_name`, along with the optio
 expired sessions by setting the `cleanup_interval` option. This storage provider does not
 create any table or index for you, so you'll need to do that in your existing migration flow.
 
+# Cleanup
+When several app instances share the sessions table, their cleanups may run at the same time
+and compete for the same rows. Set `cleanup_jitter` to spread them out with a random delay, and
+enable `cleanup_lock` to skip a cleanup while another instance holds a (transaction-level)
+advisory lock for the table. On large tables, set `cleanup_batch_size` to delete expired sessions
+in batches (optionally pausing for `cleanup_batch_delay` between them) instead of all at once.
+The number of deleted sessions is reported by [`cleanup_stats`](Self::cleanup_stats). To clean
+up from an external job instead (e.g. a cron job), leave `cleanup_interval` unset and call
+[`delete_expired`](Self::delete_expired) from the job.
+
 # Example
 Initialize the sqlx pool, then use the builder pattern to create a new instance of `SqlxPostgresStorage`:
 ```
@@ -58,8 +151,31 @@ pub struct SqlxPostgresStorage {
     pool: PgPool,
     base: SqlxBase<Postgres>,
     cleanup_task: SqlxCleanupTask,
+    max_retries: u32,
+    notify_channel: Option<String>,
+    offload: Option<OffloadTable>,
+    once_table: Option<String>,
+}
+
+/// Table that stores the data of large sessions
+struct OffloadTable {
+    name: String,
+    threshold: usize,
+    save_sql: String,
 }
 
+/// Default size in bytes beyond which session data is offloaded
+const DEFAULT_OFFLOAD_THRESHOLD: usize = 64 * 1024;
+
+/// Query for the current database time
+const PG_NOW_SQL: &str = "SELECT CURRENT_TIMESTAMP";
+
+/// Number of retries for serialization failures in CockroachDB mode
+const COCKROACH_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry of a serialization failure, doubled for each further retry
+const COCKROACH_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+
 #[bon]
 impl SqlxPostgresStorage {
     #[builder]
@@ -75,13 +191,354 @@ impl SqlxPostgresStorage {
         /// Interval to check for and delete expired sessions. If not set,
         /// expired sessions will not be cleaned up automatically.
         cleanup_interval: Option<std::time::Duration>,
+        /// Delay each cleanup by a random duration of up to this value, so that instances
+        /// started at the same time don't all clean up at once. See [Cleanup](#cleanup).
+        cleanup_jitter: Option<std::time::Duration>,
+        /// Delete expired sessions in batches of at most this many sessions, to avoid locking
+        /// large parts of the table during cleanup. If not set, all expired sessions are
+        /// deleted with a single query.
+        cleanup_batch_size: Option<u64>,
+        /// Pause between the batches of a cleanup, if `cleanup_batch_size` is set
+        cleanup_batch_delay: Option<std::time::Duration>,
+        /// Skip a cleanup if another instance is already cleaning up the same table, using an
+        /// advisory lock (default: `false`). Not supported in CockroachDB mode.
+        #[builder(default)]
+        cleanup_lock: bool,
+        /// Enable compatibility mode for CockroachDB (default: `false`)
+        #[builder(default)]
+        cockroach: bool,
+        /// In CockroachDB mode, use follower reads when listing sessions by identifier (default: `false`)
+        #[builder(default)]
+        follower_reads: bool,
+        /// Publish changes to indexed sessions via `pg_notify` (default: `false`)
+        #[builder(default)]
+        notify_changes: bool,
+        /// Store the data of large sessions in this table, instead of the sessions table.
+        /// See [Large sessions](#large-sessions).
+        #[builder(into)]
+        offload_table: Option<String>,
+        /// Size in bytes beyond which session data is stored in the `offload_table` (default: 64 KiB)
+        #[builder(default = DEFAULT_OFFLOAD_THRESHOLD)]
+        offload_threshold: usize,
+        /// Correct the app's clock by its measured skew from the database clock when
+        /// calculating session expiration (default: `false`). See [Clock skew](#clock-skew).
+        #[builder(default)]
+        skew_corrected_time: bool,
+        /// The name of a nullable column storing the creation time of sessions, with the same
+        /// type as the expiration column. If not set, creation times aren't tracked.
+        #[builder(into)]
+        created_column: Option<String>,
+        /// Store single-use values in this table. See [Single-use values](#single-use-values).
+        #[builder(into)]
+        once_table: Option<String>,
     ) -> Self {
+        let (dialect, max_retries) = match cockroach {
+            true => (
+                SqlDialect::Cockroach { follower_reads },
+                COCKROACH_MAX_RETRIES,
+            ),
+            false => (SqlDialect::Standard, 0),
+        };
+        let notify_channel = notify_changes.then(|| format!("{table_name}_changes"));
+        let offload = offload_table.map(|name| OffloadTable {
+            save_sql: save_offloaded_sql(
+                &table_name,
+                &index_column,
+                &name,
+                created_column.as_deref(),
+            ),
+            name,
+            threshold: offload_threshold,
+        });
+        let clock = Arc::new(DbClock::new(skew_corrected_time));
+        let mut cleanup_task =
+            SqlxCleanupTask::new(cleanup_interval, &table_name, clock.clone(), PG_NOW_SQL)
+                .with_jitter(cleanup_jitter)
+                .with_batches(cleanup_batch_size, cleanup_batch_delay);
+        if cleanup_lock && !cockroach {
+            cleanup_task = cleanup_task.with_delete_sql(delete_expired_locked_sql);
+        }
         Self {
-            cleanup_task: SqlxCleanupTask::new(cleanup_interval, &table_name),
-            base: SqlxBase::new(pool.clone(), table_name, index_column),
+            notify_channel,
+            offload,
+            once_table,
+            cleanup_task,
+            base: SqlxBase::new(pool.clone(), table_name, index_column, clock)
+                .with_dialect(dialect)
+                .with_created_column(created_column),
             pool,
+            max_retries,
         }
     }
+
+    /// The skew of the database clock (database time - app time), as last measured in
+    /// skew-corrected mode, or with [`measure_clock_skew`](Self::measure_clock_skew).
+    pub fn clock_skew(&self) -> Option<Duration> {
+        self.base.clock().skew()
+    }
+
+    /// Measure the skew of the database clock (database time - app time). In skew-corrected
+    /// mode, expiration times are corrected by the new measurement.
+    pub async fn measure_clock_skew(&self) -> SessionResult<Duration> {
+        self.base.clock().measure(&self.pool, PG_NOW_SQL).await
+    }
+
+    /// Statistics of the cleanups of expired sessions, e.g. to monitor the number of deleted sessions.
+    /// Includes cleanups by the cleanup task and by [`delete_expired`](Self::delete_expired).
+    pub fn cleanup_stats(&self) -> CleanupStats {
+        self.cleanup_task.stats().clone()
+    }
+
+    /// Whether the cleanup task is running. It's started by [`setup`](SessionStorage::setup)
+    /// if `cleanup_interval` is set, and stopped by [`shutdown`](SessionStorage::shutdown).
+    pub async fn is_cleanup_running(&self) -> bool {
+        self.cleanup_task.is_running().await
+    }
+
+    /// Delete all expired sessions, returning the number of deleted sessions. This is done
+    /// automatically if `cleanup_interval` is set, but can also be run by an external job.
+    pub async fn delete_expired(&self) -> SessionResult<u64> {
+        self.cleanup_task.delete_expired(&self.pool).await
+    }
+
+    /// Save the session data once, without retries
+    async fn save_once<T>(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+        created_at: Option<OffsetDateTime>,
+    ) -> SessionResult<()>
+    where
+        T: SessionSqlx<Postgres>,
+        <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+    {
+        let identifier = data.identifier();
+        let value = data
+            .into_sql()
+            .map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        match &self.offload {
+            Some(offload) => {
+                let now = self.base.clock().now();
+                let mut query = sqlx::query(&offload.save_sql)
+                    .bind(id.to_owned())
+                    .bind(identifier)
+                    .bind(value)
+                    .bind(now + ttl)
+                    .bind(i64::try_from(offload.threshold).unwrap_or(i64::MAX));
+                if self.base.created_column().is_some() {
+                    query = query.bind(created_at.unwrap_or(now));
+                }
+                query.execute(&self.pool).await?;
+            }
+            None => {
+                self.base
+                    .save(id, value, identifier, ttl, created_at)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Decode the session data value of a row. Returns `None` if the data was offloaded.
+    fn decode_value<T>(&self, row: &PgRow) -> SessionResult<Option<T::Data>>
+    where
+        T: SessionSqlx<Postgres>,
+        <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+    {
+        match self.offload {
+            Some(_) => Ok(row.try_get(DATA_COLUMN)?),
+            None => Ok(Some(row.try_get(DATA_COLUMN)?)),
+        }
+    }
+
+    /// Fetch the rows of offloaded session data for the given session IDs, keyed by session ID
+    async fn load_offloaded(&self, ids: &[String]) -> SessionResult<HashMap<String, PgRow>> {
+        let Some(offload) = self.offload.as_ref().filter(|_| !ids.is_empty()) else {
+            return Ok(HashMap::new());
+        };
+        let rows = sqlx::query(&load_offloaded_sql(&offload.name))
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await?;
+        let offloaded = rows
+            .into_iter()
+            .filter_map(|row| Some((row.try_get(ID_COLUMN).ok()?, row)))
+            .collect();
+
+        Ok(offloaded)
+    }
+
+    /// Parse rows with the session ID, data, and expiration, as used when listing sessions.
+    /// Offloaded data is fetched with a single query. Rows that can't be parsed are skipped
+    /// with a warning.
+    async fn parse_session_rows<T>(&self, rows: Vec<PgRow>) -> SessionResult<Vec<(String, T, u32)>>
+    where
+        T: SessionSqlx<Postgres>,
+        <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+    {
+        let offloaded_ids: Vec<String> = rows
+            .iter()
+            .filter(|row| matches!(self.decode_value::<T>(row), Ok(None)))
+            .filter_map(|row| row.try_get(ID_COLUMN).ok())
+            .collect();
+        let offloaded = self.load_offloaded(&offloaded_ids).await?;
+
+        let parsed_rows = rows
+            .into_iter()
+            .filter_map(|row| {
+                let id: String = row.try_get(ID_COLUMN).ok()?;
+                let value = self.decode_value::<T>(&row).and_then(|value| match value {
+                    Some(value) => Ok(value),
+                    None => offloaded_value::<T>(&offloaded, &id),
+                });
+                let data = match value.and_then(parse_value) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        rocket::warn!("Skipping session that failed to parse: {}", e.redacted());
+                        return None;
+                    }
+                };
+                let expires = row.try_get(EXPIRES_COLUMN).ok()?;
+                Some((id, data, expires_to_ttl(&expires, self.base.clock().now())))
+            })
+            .collect();
+
+        Ok(parsed_rows)
+    }
+
+    /// Notify listeners of a change to a session belonging to the identifier, if enabled.
+    /// The payload is the identifier and the encoded change, separated by a newline. This is
+    /// done after the change is stored, so a failure is logged instead of failing the operation.
+    async fn notify_change<I>(&self, identifier: &I, change: SessionChange)
+    where
+        I: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres> + Sync,
+    {
+        if let Some(channel) = &self.notify_channel {
+            let result = sqlx::query("SELECT pg_notify($1, CAST($2 AS TEXT) || E'\\n' || $3)")
+                .bind(channel)
+                .bind(identifier)
+                .bind(change.encode())
+                .execute(&self.pool)
+                .await;
+            if let Err(e) = result {
+                rocket::warn!("Error publishing session change: {e}");
+            }
+        }
+    }
+}
+
+/// Run the operation, retrying up to `max_retries` times with an exponential backoff if it
+/// fails with a serialization failure (SQLSTATE `40001`), as recommended by CockroachDB.
+async fn with_retries<F, Fut, R>(max_retries: u32, mut operation: F) -> SessionResult<R>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = SessionResult<R>>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation().await {
+            Err(SessionError::SqlxError(sqlx::Error::Database(e)))
+                if attempt < max_retries && e.code().as_deref() == Some("40001") =>
+            {
+                rocket::debug!("Retrying session operation after serialization failure: {e}");
+                rocket::tokio::time::sleep(COCKROACH_RETRY_BACKOFF * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Parse the session data value. The data column can be any type supported by sqlx,
+/// e.g. `text`, `jsonb`, or `bytea`, depending on [`SessionSqlx::Data`].
+fn parse_value<T>(value: T::Data) -> SessionResult<T>
+where
+    T: SessionSqlx<Postgres>,
+    <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+{
+    T::from_sql(value).map_err(|e| SessionError::Parsing(Box::new(e)))
+}
+
+/// Get the data value of an offloaded session from the fetched offload rows
+fn offloaded_value<T>(offloaded: &HashMap<String, PgRow>, id: &str) -> SessionResult<T::Data>
+where
+    T: SessionSqlx<Postgres>,
+    <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
+{
+    let row = offloaded.get(id).ok_or(SessionError::InvalidData)?;
+    Ok(row.try_get(DATA_COLUMN)?)
+}
+
+/// Save session data, storing data larger than the threshold in the offload table and
+/// setting the data column of the session row to NULL. Bind the session ID, index, data,
+/// expiration, threshold, and the creation time if there's a creation time column
+fn save_offloaded_sql(
+    table_name: &str,
+    index_column: &str,
+    offload_table: &str,
+    created_column: Option<&str>,
+) -> String {
+    let (created_column, created_value) = match created_column {
+        Some(column) => (format!(", {column}"), ", $6"),
+        None => (String::new(), ""),
+    };
+    format!(
+        "WITH session AS (\
+            INSERT INTO \"{table_name}\" ({ID_COLUMN}, {index_column}, {DATA_COLUMN}, {EXPIRES_COLUMN}{created_column}) \
+            VALUES ($1, $2, CASE WHEN octet_length($3) > $5 THEN NULL ELSE $3 END, $4{created_value}) \
+            ON CONFLICT ({ID_COLUMN}) DO UPDATE SET \
+                {DATA_COLUMN} = EXCLUDED.{DATA_COLUMN}, \
+                {EXPIRES_COLUMN} = EXCLUDED.{EXPIRES_COLUMN} \
+            RETURNING {ID_COLUMN}\
+        ), offloaded AS (\
+            INSERT INTO \"{offload_table}\" ({ID_COLUMN}, {DATA_COLUMN}) \
+            SELECT {ID_COLUMN}, $3 FROM session WHERE octet_length($3) > $5 \
+            ON CONFLICT ({ID_COLUMN}) DO UPDATE SET {DATA_COLUMN} = EXCLUDED.{DATA_COLUMN}\
+        ) \
+        DELETE FROM \"{offload_table}\" WHERE {ID_COLUMN} = $1 AND octet_length($3) <= $5"
+    )
+}
+
+/// Delete expired sessions if no other transaction holds the cleanup lock for the table,
+/// i.e. if another instance isn't cleaning up already. Bind the current time, and the batch
+/// size if batched
+fn delete_expired_locked_sql(table_name: &str, batched: bool) -> String {
+    let expired = sql::expired_condition(table_name, batched);
+    format!(
+        "WITH cleanup_lock AS (\
+            SELECT pg_try_advisory_xact_lock(hashtext('{table_name}')) AS acquired\
+        ) \
+        DELETE FROM \"{table_name}\" USING cleanup_lock \
+        WHERE cleanup_lock.acquired AND {expired}"
+    )
+}
+
+/// Save a single-use value, replacing an existing value with the same key. Bind the session ID,
+/// key, value, and expiration
+fn save_once_value_sql(once_table: &str) -> String {
+    format!(
+        "INSERT INTO \"{once_table}\" (session_id, key, value, {EXPIRES_COLUMN}) \
+        VALUES ($1, $2, $3, $4) \
+        ON CONFLICT (session_id, key) DO UPDATE SET \
+            value = EXCLUDED.value, \
+            {EXPIRES_COLUMN} = EXCLUDED.{EXPIRES_COLUMN}"
+    )
+}
+
+/// Delete a single-use value, returning it along with its expiration. Bind the session ID and key
+fn take_once_value_sql(once_table: &str) -> String {
+    format!(
+        "DELETE FROM \"{once_table}\" WHERE session_id = $1 AND key = $2 \
+        RETURNING value, {EXPIRES_COLUMN}"
+    )
+}
+
+/// Load offloaded session data. Bind the array of session IDs
+fn load_offloaded_sql(offload_table: &str) -> String {
+    format!(
+        "SELECT {ID_COLUMN}, {DATA_COLUMN} FROM \"{offload_table}\" WHERE {ID_COLUMN} = ANY($1)"
+    )
 }
 
 #[async_trait]
@@ -98,32 +555,174 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        _cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
-        let row: Option<PgRow> = self.base.load(id, ttl).await?;
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        let row: Option<PgRow> = with_retries(self.max_retries, || async move {
+            self.base
+                .load(id, ttl)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
         let row = row.ok_or(SessionError::NotFound)?;
 
-        let value = row.try_get(DATA_COLUMN)?;
-        let data = T::from_sql(value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
+        let value = match self.decode_value::<T>(&row)? {
+            Some(value) => value,
+            None => {
+                let offloaded = self.load_offloaded(&[id.to_owned()]).await?;
+                offloaded_value::<T>(&offloaded, id)?
+            }
+        };
+        let data = parse_value(value)?;
         let expires = row.try_get(EXPIRES_COLUMN)?;
+        let metadata = SessionMetadata::new(self.base.created_at(&row));
 
-        Ok((data, expires_to_ttl(&expires)))
+        Ok((
+            data,
+            expires_to_ttl(&expires, self.base.clock().now()),
+            metadata,
+        ))
+    }
+
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        with_retries(self.max_retries, || async move {
+            self.base.exists(id).await.map_err(SessionError::SqlxError)
+        })
+        .await
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        let rows = with_retries(self.max_retries, || async move {
+            self.base
+                .recently_active(limit)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
+        self.parse_session_rows(rows).await
     }
 
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
-        let identifier = data.identifier();
-        let value = data
-            .into_sql()
-            .map_err(|e| SessionError::Serialization(Box::new(e)))?;
-        self.base.save(id, value, identifier, ttl).await?;
+        self.save_with_duration(id, data, std::time::Duration::from_secs(ttl.into()))
+            .await
+    }
+
+    async fn save_with_duration(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+    ) -> SessionResult<()> {
+        self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
+        let identifier = match self.notify_channel {
+            Some(_) => data.identifier(),
+            None => None,
+        };
+        let created_at = metadata.created_at;
+        if self.max_retries == 0 {
+            self.save_once(id, data, ttl, created_at).await?;
+        } else {
+            let data = &data;
+            with_retries(self.max_retries, move || {
+                self.save_once(id, data.clone(), ttl, created_at)
+            })
+            .await?;
+        }
+        if let Some(identifier) = identifier {
+            self.notify_change(&identifier, SessionChange::Saved(id.to_owned()))
+                .await;
+        }
         Ok(())
     }
 
-    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
-        self.base.delete(id).await?;
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        with_retries(self.max_retries, || async move {
+            self.base.delete(id).await.map_err(SessionError::SqlxError)
+        })
+        .await?;
+        if let Some(identifier) = data.identifier() {
+            self.notify_change(&identifier, SessionChange::Deleted(id.to_owned()))
+                .await;
+        }
         Ok(())
     }
 
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        with_retries(self.max_retries, || async move {
+            self.base.delete(id).await.map_err(SessionError::SqlxError)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn save_once_value(
+        &self,
+        id: &str,
+        key: &str,
+        value: String,
+        ttl: u32,
+    ) -> SessionResult<()> {
+        let once_table =
+            (self.once_table.as_deref()).ok_or(SessionError::Unsupported("single-use values"))?;
+        let expires = self.base.clock().now() + Duration::seconds(ttl.into());
+        let sql = save_once_value_sql(once_table);
+        with_retries(self.max_retries, || async {
+            sqlx::query(&sql)
+                .bind(id)
+                .bind(key)
+                .bind(&value)
+                .bind(expires)
+                .execute(&self.pool)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
+        Ok(())
+    }
+
+    async fn take_once_value(&self, id: &str, key: &str) -> SessionResult<Option<String>> {
+        let once_table =
+            (self.once_table.as_deref()).ok_or(SessionError::Unsupported("single-use values"))?;
+        let sql = take_once_value_sql(once_table);
+        let row: Option<PgRow> = with_retries(self.max_retries, || async {
+            sqlx::query(&sql)
+                .bind(id)
+                .bind(key)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let expires: OffsetDateTime = row.try_get(EXPIRES_COLUMN)?;
+        if expires <= self.base.clock().now() {
+            return Ok(None);
+        }
+        Ok(Some(row.try_get("value")?))
+    }
+
     async fn setup(&self) -> SessionResult<()> {
         self.cleanup_task.setup(&self.pool).await
     }
@@ -140,7 +739,13 @@ where
     <T as SessionIdentifier>::Id: for<'q> sqlx::Encode<'q, Postgres> + sqlx::Type<Postgres>,
 {
     async fn get_session_ids_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<String>> {
-        let rows = self.base.session_ids_belonging_to(id).await?;
+        let rows = with_retries(self.max_retries, || async move {
+            self.base
+                .session_ids_belonging_to(id)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
         let session_ids = rows
             .into_iter()
             .filter_map(|row| row.try_get(ID_COLUMN).ok())
@@ -149,21 +754,37 @@ where
         Ok(session_ids)
     }
 
-    async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
-        let rows = self.base.sessions_belonging_to(id).await?;
-        let parsed_rows = rows
+    async fn get_session_metadata_by_identifier(
+        &self,
+        id: &T::Id,
+    ) -> SessionResult<Vec<(String, SessionMetadata)>> {
+        let rows = with_retries(self.max_retries, || async move {
+            self.base
+                .session_ids_belonging_to(id)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
+        let sessions = rows
             .into_iter()
             .filter_map(|row| {
-                let id = row.try_get(ID_COLUMN).ok()?;
-                let value = row.try_get(DATA_COLUMN).ok()?;
-                let data = T::from_sql(value).ok()?;
-                let expires = row.try_get(EXPIRES_COLUMN).ok()?;
-
-                Some((id, data, expires_to_ttl(&expires)))
+                let session_id = row.try_get(ID_COLUMN).ok()?;
+                Some((session_id, SessionMetadata::new(self.base.created_at(&row))))
             })
             .collect();
 
-        Ok(parsed_rows)
+        Ok(sessions)
+    }
+
+    async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
+        let rows = with_retries(self.max_retries, || async move {
+            self.base
+                .sessions_belonging_to(id)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
+        self.parse_session_rows(rows).await
     }
 
     async fn invalidate_sessions_by_identifier(
@@ -171,11 +792,140 @@ where
         id: &T::Id,
         excluded_session_id: Option<&str>,
     ) -> SessionResult<u64> {
-        let rows = self
-            .base
-            .invalidate_belonging_to(id, excluded_session_id)
-            .await?;
+        // Get the sessions to notify before they're deleted
+        let notified_ids = match self.notify_channel {
+            Some(_) => SessionStorageIndexed::<T>::get_session_ids_by_identifier(self, id).await?,
+            None => Vec::new(),
+        };
+        let rows = with_retries(self.max_retries, || async move {
+            self.base
+                .invalidate_belonging_to(id, excluded_session_id)
+                .await
+                .map_err(SessionError::SqlxError)
+        })
+        .await?;
 
+        for session_id in notified_ids {
+            if excluded_session_id.is_some_and(|excluded| session_id_eq(&session_id, excluded)) {
+                continue;
+            }
+            self.notify_change(id, SessionChange::Deleted(session_id))
+                .await;
+        }
         Ok(rows.rows_affected())
     }
+
+    async fn subscribe_identifier(&self, id: &T::Id) -> SessionResult<SessionChangeStream> {
+        let channel = self
+            .notify_channel
+            .as_ref()
+            .ok_or(SessionError::Unsupported("session change notifications"))?;
+        let identifier: String = sqlx::query_scalar("SELECT CAST($1 AS TEXT)")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(channel).await?;
+
+        let changes = listener.into_stream().filter_map(move |notification| {
+            let change = notification.ok().and_then(|notification| {
+                let (notified, change) = notification.payload().rsplit_once('\n')?;
+                match notified == identifier {
+                    true => SessionChange::decode(change),
+                    false => None,
+                }
+            });
+            future::ready(change)
+        });
+        Ok(Box::pin(changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        borrow::Cow,
+        error::Error,
+        fmt,
+        sync::atomic::{AtomicU32, Ordering},
+    };
+
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    use super::*;
+
+    /// Database error with the given SQLSTATE code
+    #[derive(Debug)]
+    struct CodeError(&'static str);
+
+    impl fmt::Display for CodeError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "database error {}", self.0)
+        }
+    }
+
+    impl Error for CodeError {}
+
+    impl DatabaseError for CodeError {
+        fn message(&self) -> &str {
+            "database error"
+        }
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+        fn as_error(&self) -> &(dyn Error + Send + Sync + 'static) {
+            self
+        }
+        fn as_error_mut(&mut self) -> &mut (dyn Error + Send + Sync + 'static) {
+            self
+        }
+        fn into_error(self: Box<Self>) -> Box<dyn Error + Send + Sync + 'static> {
+            self
+        }
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    /// Run an operation that fails with the given code a number of times before succeeding
+    async fn run(code: &'static str, failures: u32) -> (SessionResult<u32>, u32) {
+        let counter = AtomicU32::new(0);
+        let attempts = &counter;
+        let result = with_retries(COCKROACH_MAX_RETRIES, move || async move {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            match attempt < failures {
+                true => Err(SessionError::from(sqlx::Error::database(CodeError(code)))),
+                false => Ok(attempt),
+            }
+        })
+        .await;
+        (result, counter.load(Ordering::SeqCst))
+    }
+
+    #[rocket::async_test]
+    async fn retries_serialization_failures() {
+        let (result, attempts) = run("40001", 2).await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(attempts, 3);
+    }
+
+    #[rocket::async_test]
+    async fn retries_with_backoff_up_to_limit() {
+        let start = std::time::Instant::now();
+        let (result, attempts) = run("40001", u32::MAX).await;
+        assert!(matches!(
+            result,
+            Err(SessionError::SqlxError(sqlx::Error::Database(e))) if e.code().as_deref() == Some("40001")
+        ));
+        assert_eq!(attempts, COCKROACH_MAX_RETRIES + 1);
+        // 10ms + 20ms + 40ms
+        assert!(start.elapsed() >= COCKROACH_RETRY_BACKOFF * 7);
+    }
+
+    #[rocket::async_test]
+    async fn does_not_retry_other_errors() {
+        let (result, attempts) = run("23505", 1).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
 }
@@ -1,21 +1,30 @@
+use std::sync::Arc;
+
 use bon::bon;
-use rocket::{async_trait, http::CookieJar};
+use rocket::{
+    async_trait,
+    time::Duration,
+    tokio::sync::{Mutex, MutexGuard},
+};
 use sqlx::{sqlite::SqliteRow, Row, Sqlite, SqlitePool};
 
 use crate::{
     error::{SessionError, SessionResult},
-    storage::{SessionStorage, SessionStorageIndexed},
+    storage::{SessionContext, SessionMetadata, SessionStorage, SessionStorageIndexed},
 };
 
 use super::*;
 
+/// Query for the current database time, in a format that sqlx can decode with millisecond precision
+const SQLITE_NOW_SQL: &str = "SELECT strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
 /** Session store using SQLite via [sqlx](https://docs.rs/crate/sqlx).
 
 # Requirements
 - You must pass in an initialized sqlx SQLite connection pool.
 - Your session data type must implement [`SessionSqlx`] to configure how to convert & store session data.
 - Your session data type must implement [`SessionIdentifier`]. The SessionIdentifier's
-[Id](`SessionIdentifier::Id`) type must be a type supported by sqlx.
+  [Id](`SessionIdentifier::Id`) type must be a type supported by sqlx.
 - Expects a table to already exist with the following columns:
 
 | Name | Type |
@@ -26,12 +35,51 @@ use super::*;
 | expires | TEXT NOT NULL |
 
 The name of the session index column ("user_id") can be customized when building the storage.
+To track the creation time of sessions, add a nullable `TEXT` column and set its name with
+`created_column`.
+
+# Concurrency
+SQLite only allows a single writer at a time, so concurrent requests may fail with `SQLITE_BUSY`
+under load. To avoid this, you can:
+- enable `wal` to switch the database to write-ahead logging in `setup()`, so that reads
+  don't block writes. The journal mode is persisted in the database file.
+- set a busy timeout in the connect options of the pool (`SqliteConnectOptions::busy_timeout`),
+  to make SQLite wait for locks to be released instead of failing immediately. The storage
+  doesn't change the options of the pool it's given.
+- enable `serialize_writes` to queue all writes from this storage through an internal lock,
+  so that only one write is attempted at a time within the process. The cleanup task takes
+  the same lock while deleting expired sessions.
+
+# Clock skew
+If the database file is shared by several app servers (e.g. on a network volume), their clocks
+may drift apart, so sessions may expire early or late depending on the server that handles the
+request. Enable `skew_corrected_time` to correct expiration times by the measured skew of the
+app's clock compared to SQLite's `'now'`. See [`SqlxPostgresStorage`](super::SqlxPostgresStorage#clock-skew)
+for more info.
 
+```
+use rocket_flex_session::storage::sqlx::SqlxSqliteStorage;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+use std::{str::FromStr, time::Duration};
+
+fn create_storage() -> Result<SqlxSqliteStorage, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str("sqlite://sessions.db")?
+        .busy_timeout(Duration::from_secs(5));
+    Ok(SqlxSqliteStorage::builder()
+        .pool(SqlitePool::connect_lazy_with(options))
+        .table_name("sessions")
+        .wal(true)
+        .serialize_writes(true)
+        .build())
+}
+```
  */
 pub struct SqlxSqliteStorage {
     pool: SqlitePool,
     base: SqlxBase<Sqlite>,
     cleanup_task: SqlxCleanupTask,
+    wal: bool,
+    write_lock: Option<Arc<Mutex<()>>>,
 }
 
 #[bon]
@@ -49,11 +97,87 @@ impl SqlxSqliteStorage {
         /// Interval to check for and delete expired sessions. If not set,
         /// expired sessions will not be cleaned up automatically.
         cleanup_interval: Option<std::time::Duration>,
+        /// Delay each cleanup by a random duration of up to this value, so that instances
+        /// started at the same time don't all clean up at once.
+        cleanup_jitter: Option<std::time::Duration>,
+        /// Delete expired sessions in batches of at most this many sessions, to avoid locking
+        /// large parts of the table during cleanup. If not set, all expired sessions are
+        /// deleted with a single query.
+        cleanup_batch_size: Option<u64>,
+        /// Pause between the batches of a cleanup, if `cleanup_batch_size` is set
+        cleanup_batch_delay: Option<std::time::Duration>,
+        /// Enable write-ahead logging (`PRAGMA journal_mode = WAL`) during setup (default: `false`)
+        #[builder(default)]
+        wal: bool,
+        /// Serialize all writes from this storage through an internal queue (default: `false`)
+        #[builder(default)]
+        serialize_writes: bool,
+        /// Correct the app's clock by its measured skew from the database clock when
+        /// calculating session expiration (default: `false`). See [Clock skew](#clock-skew).
+        #[builder(default)]
+        skew_corrected_time: bool,
+        /// The name of a nullable column storing the creation time of sessions, with the same
+        /// type as the expiration column. If not set, creation times aren't tracked.
+        #[builder(into)]
+        created_column: Option<String>,
     ) -> Self {
+        let write_lock = serialize_writes.then(Arc::default);
+        let clock = Arc::new(DbClock::new(skew_corrected_time));
         Self {
-            cleanup_task: SqlxCleanupTask::new(cleanup_interval, &table_name),
-            base: SqlxBase::new(pool.clone(), table_name, index_column),
+            cleanup_task: SqlxCleanupTask::new(
+                cleanup_interval,
+                &table_name,
+                clock.clone(),
+                SQLITE_NOW_SQL,
+            )
+            .with_jitter(cleanup_jitter)
+            .with_batches(cleanup_batch_size, cleanup_batch_delay)
+            .with_write_lock(write_lock.clone()),
+            base: SqlxBase::new(pool.clone(), table_name, index_column, clock)
+                .with_created_column(created_column),
             pool,
+            wal,
+            write_lock,
+        }
+    }
+
+    /// The skew of the database clock (database time - app time), as last measured in
+    /// skew-corrected mode, or with [`measure_clock_skew`](Self::measure_clock_skew).
+    pub fn clock_skew(&self) -> Option<Duration> {
+        self.base.clock().skew()
+    }
+
+    /// Measure the skew of the database clock (database time - app time). In skew-corrected
+    /// mode, expiration times are corrected by the new measurement.
+    pub async fn measure_clock_skew(&self) -> SessionResult<Duration> {
+        self.base.clock().measure(&self.pool, SQLITE_NOW_SQL).await
+    }
+
+    /// Statistics of the cleanups of expired sessions, e.g. to monitor the number of deleted sessions.
+    /// Includes cleanups by the cleanup task and by [`delete_expired`](Self::delete_expired).
+    pub fn cleanup_stats(&self) -> CleanupStats {
+        self.cleanup_task.stats().clone()
+    }
+
+    /// Whether the cleanup task is running. It's started by [`setup`](SessionStorage::setup)
+    /// if `cleanup_interval` is set, and stopped by [`shutdown`](SessionStorage::shutdown).
+    pub async fn is_cleanup_running(&self) -> bool {
+        self.cleanup_task.is_running().await
+    }
+
+    /// Delete all expired sessions, returning the number of deleted sessions. This is done
+    /// automatically if `cleanup_interval` is set, but can also be run by an external job
+    /// (e.g. a cron job) with `cleanup_interval` unset.
+    pub async fn delete_expired(&self) -> SessionResult<u64> {
+        let _guard = self.lock_writes().await;
+        self.cleanup_task.delete_expired(&self.pool).await
+    }
+
+    /// Wait for our turn to write, if writes are serialized
+    async fn lock_writes(&self) -> Option<MutexGuard<'_, ()>> {
+        match &self.write_lock {
+            Some(lock) => Some(lock.lock().await),
+            None => None,
         }
     }
 }
@@ -72,33 +196,109 @@ where
         &self,
         id: &str,
         ttl: Option<u32>,
-        _cookie_jar: &CookieJar,
+        cookie_jar: &dyn SessionContext,
     ) -> SessionResult<(T, u32)> {
+        let (data, ttl, _) = self.load_with_metadata(id, ttl, cookie_jar).await?;
+        Ok((data, ttl))
+    }
+
+    async fn load_with_metadata(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32, SessionMetadata)> {
+        // Loading with a new TTL updates the expiration, so it counts as a write
+        let _guard = match ttl {
+            Some(_) => self.lock_writes().await,
+            None => None,
+        };
         let row: Option<SqliteRow> = self.base.load(id, ttl).await?;
         let row = row.ok_or(SessionError::NotFound)?;
 
         let value = row.try_get(DATA_COLUMN)?;
         let data = T::from_sql(value).map_err(|e| SessionError::Parsing(Box::new(e)))?;
         let expires = row.try_get(EXPIRES_COLUMN)?;
+        let metadata = SessionMetadata::new(self.base.created_at(&row));
+
+        Ok((
+            data,
+            expires_to_ttl(&expires, self.base.clock().now()),
+            metadata,
+        ))
+    }
 
-        Ok((data, expires_to_ttl(&expires)))
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        Ok(self.base.exists(id).await?)
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, T, u32)>> {
+        let rows = self.base.recently_active(limit).await?;
+        let parsed_rows = rows
+            .into_iter()
+            .filter_map(|row| {
+                let id = row.try_get(ID_COLUMN).ok()?;
+                let value = row.try_get(DATA_COLUMN).ok()?;
+                let data = T::from_sql(value).ok()?;
+                let expires = row.try_get(EXPIRES_COLUMN).ok()?;
+                Some((id, data, expires_to_ttl(&expires, self.base.clock().now())))
+            })
+            .collect();
+
+        Ok(parsed_rows)
     }
 
     async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.save_with_duration(id, data, std::time::Duration::from_secs(ttl.into()))
+            .await
+    }
+
+    async fn save_with_duration(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+    ) -> SessionResult<()> {
+        self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+            .await
+    }
+
+    async fn save_with_metadata(
+        &self,
+        id: &str,
+        data: T,
+        ttl: std::time::Duration,
+        metadata: SessionMetadata,
+    ) -> SessionResult<()> {
         let identifier = data.identifier();
         let value = data
             .into_sql()
             .map_err(|e| SessionError::Serialization(Box::new(e)))?;
-        self.base.save(id, value, identifier, ttl).await?;
+        let _guard = self.lock_writes().await;
+        self.base
+            .save(id, value, identifier, ttl, metadata.created_at)
+            .await?;
         Ok(())
     }
 
     async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        let _guard = self.lock_writes().await;
+        self.base.delete(id).await?;
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        let _guard = self.lock_writes().await;
         self.base.delete(id).await?;
         Ok(())
     }
 
     async fn setup(&self) -> SessionResult<()> {
+        if self.wal {
+            sqlx::query("PRAGMA journal_mode = WAL")
+                .execute(&self.pool)
+                .await?;
+        }
         self.cleanup_task.setup(&self.pool).await
     }
 
@@ -123,6 +323,22 @@ where
         Ok(session_ids)
     }
 
+    async fn get_session_metadata_by_identifier(
+        &self,
+        id: &T::Id,
+    ) -> SessionResult<Vec<(String, SessionMetadata)>> {
+        let rows = self.base.session_ids_belonging_to(id).await?;
+        let sessions = rows
+            .into_iter()
+            .filter_map(|row| {
+                let session_id = row.try_get(ID_COLUMN).ok()?;
+                Some((session_id, SessionMetadata::new(self.base.created_at(&row))))
+            })
+            .collect();
+
+        Ok(sessions)
+    }
+
     async fn get_sessions_by_identifier(&self, id: &T::Id) -> SessionResult<Vec<(String, T, u32)>> {
         let rows = self.base.sessions_belonging_to(id).await?;
         let parsed_rows = rows
@@ -132,7 +348,7 @@ where
                 let value = row.try_get(DATA_COLUMN).ok()?;
                 let data = T::from_sql(value).ok()?;
                 let expires = row.try_get(EXPIRES_COLUMN).ok()?;
-                Some((id, data, expires_to_ttl(&expires)))
+                Some((id, data, expires_to_ttl(&expires, self.base.clock().now())))
             })
             .collect();
 
@@ -144,6 +360,7 @@ where
         id: &T::Id,
         excluded_session_id: Option<&str>,
     ) -> SessionResult<u64> {
+        let _guard = self.lock_writes().await;
         let rows = self
             .base
             .invalidate_belonging_to(id, excluded_session_id)
@@ -0,0 +1,175 @@
+use bon::Builder;
+use rocket::{
+    async_trait,
+    time::{Duration, OffsetDateTime},
+};
+
+use crate::{
+    error::SessionResult,
+    throttle::{LoginThrottle, DEFAULT_MAX_ATTEMPTS, DEFAULT_WINDOW},
+};
+
+/**
+Postgres implementation of [`LoginThrottle`] via [sqlx](https://docs.rs/crate/sqlx).
+Expects a table to already exist with the following columns:
+
+| Name | Type |
+|------|---------|
+| identifier | `text` PRIMARY KEY |
+| attempts | `integer` NOT NULL |
+| expires | `timestamptz` NOT NULL |
+
+# Example
+```
+use rocket_flex_session::storage::sqlx::SqlxPostgresThrottle;
+
+fn create_throttle(pool: sqlx::PgPool) -> SqlxPostgresThrottle {
+    SqlxPostgresThrottle::builder()
+        .pool(pool)
+        .table_name("login_attempts")
+        .max_attempts(5)
+        .build()
+}
+```
+*/
+#[cfg(feature = "sqlx_postgres")]
+#[derive(Builder)]
+pub struct SqlxPostgresThrottle {
+    /// An initialized Postgres connection pool.
+    pool: sqlx::PgPool,
+    /// The name of the table used to count attempts (default: `"login_attempts"`)
+    #[builder(into, default = "login_attempts")]
+    table_name: String,
+    /// The maximum number of failed attempts before an identifier is locked out (default: 5)
+    #[builder(default = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u32,
+    /// The window in seconds in which failed attempts are counted (default: 15 minutes)
+    #[builder(default = DEFAULT_WINDOW)]
+    window: u32,
+}
+
+/**
+SQLite implementation of [`LoginThrottle`] via [sqlx](https://docs.rs/crate/sqlx).
+Expects a table to already exist with the following columns:
+
+| Name | Type |
+|------|---------|
+| identifier | TEXT NOT NULL PRIMARY KEY |
+| attempts | INTEGER NOT NULL |
+| expires | TEXT NOT NULL |
+*/
+#[cfg(feature = "sqlx_sqlite")]
+#[derive(Builder)]
+pub struct SqlxSqliteThrottle {
+    /// An initialized SQLite connection pool.
+    pool: sqlx::SqlitePool,
+    /// The name of the table used to count attempts (default: `"login_attempts"`)
+    #[builder(into, default = "login_attempts")]
+    table_name: String,
+    /// The maximum number of failed attempts before an identifier is locked out (default: 5)
+    #[builder(default = DEFAULT_MAX_ATTEMPTS)]
+    max_attempts: u32,
+    /// The window in seconds in which failed attempts are counted (default: 15 minutes)
+    #[builder(default = DEFAULT_WINDOW)]
+    window: u32,
+}
+
+#[cfg(feature = "sqlx_postgres")]
+#[async_trait]
+impl LoginThrottle for SqlxPostgresThrottle {
+    fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    async fn record_failed_login(&self, identifier: &str) -> SessionResult<u32> {
+        let now = OffsetDateTime::now_utc();
+        let attempts: i32 = sqlx::query_scalar(&sql::record_attempt(&self.table_name))
+            .bind(identifier)
+            .bind(now + Duration::seconds(self.window.into()))
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(attempts.try_into().unwrap_or(0))
+    }
+
+    async fn failed_attempts(&self, identifier: &str) -> SessionResult<u32> {
+        let attempts: Option<i32> = sqlx::query_scalar(&sql::get_attempts(&self.table_name))
+            .bind(identifier)
+            .bind(OffsetDateTime::now_utc())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(attempts.and_then(|a| a.try_into().ok()).unwrap_or(0))
+    }
+
+    async fn reset_failed_logins(&self, identifier: &str) -> SessionResult<()> {
+        sqlx::query(&sql::reset_attempts(&self.table_name))
+            .bind(identifier)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlx_sqlite")]
+#[async_trait]
+impl LoginThrottle for SqlxSqliteThrottle {
+    fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    async fn record_failed_login(&self, identifier: &str) -> SessionResult<u32> {
+        let now = OffsetDateTime::now_utc();
+        let attempts: i32 = sqlx::query_scalar(&sql::record_attempt(&self.table_name))
+            .bind(identifier)
+            .bind(now + Duration::seconds(self.window.into()))
+            .bind(now)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(attempts.try_into().unwrap_or(0))
+    }
+
+    async fn failed_attempts(&self, identifier: &str) -> SessionResult<u32> {
+        let attempts: Option<i32> = sqlx::query_scalar(&sql::get_attempts(&self.table_name))
+            .bind(identifier)
+            .bind(OffsetDateTime::now_utc())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(attempts.and_then(|a| a.try_into().ok()).unwrap_or(0))
+    }
+
+    async fn reset_failed_logins(&self, identifier: &str) -> SessionResult<()> {
+        sqlx::query(&sql::reset_attempts(&self.table_name))
+            .bind(identifier)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// SQL queries
+mod sql {
+    /// Record a failed attempt, starting a new window if the previous one expired.
+    /// Bind the identifier, new window expiration, and current time
+    pub fn record_attempt(table_name: &str) -> String {
+        format!(
+            "INSERT INTO \"{table_name}\" (identifier, attempts, expires) \
+            VALUES ($1, 1, $2) \
+            ON CONFLICT (identifier) DO UPDATE SET \
+                attempts = CASE WHEN \"{table_name}\".expires > $3 \
+                    THEN \"{table_name}\".attempts + 1 ELSE 1 END, \
+                expires = CASE WHEN \"{table_name}\".expires > $3 \
+                    THEN \"{table_name}\".expires ELSE EXCLUDED.expires END \
+            RETURNING attempts"
+        )
+    }
+
+    /// Get the attempts in the current window. Bind the identifier and current time
+    pub fn get_attempts(table_name: &str) -> String {
+        format!("SELECT attempts FROM \"{table_name}\" WHERE identifier = $1 AND expires > $2")
+    }
+
+    /// Reset the attempts. Bind the identifier
+    pub fn reset_attempts(table_name: &str) -> String {
+        format!("DELETE FROM \"{table_name}\" WHERE identifier = $1")
+    }
+}
@@ -0,0 +1,206 @@
+//! Minimal storage traits for third-party backends
+
+use rocket::async_trait;
+
+use crate::error::{SessionError, SessionResult};
+
+use super::{SessionContext, SessionStorage};
+
+/**
+Minimal trait for a session backend, without access to the request. This is all that's
+needed for most backends (e.g. a key-value store). Use it with the `store` method of the
+[fairing builder](crate::RocketFlexSession::builder).
+
+Implement the full [`SessionStorage`] trait instead for optional capabilities such as indexing,
+activity logs, or capability tokens. Backends that keep the session in a cookie should implement
+[`CookieStore`] as well.
+
+# Example
+```rust
+use std::{collections::HashMap, sync::Mutex};
+
+use rocket::async_trait;
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::SessionStore,
+    RocketFlexSession,
+};
+
+/// Sessions stored in a map, without expiration
+#[derive(Default)]
+struct MapStore(Mutex<HashMap<String, String>>);
+
+#[async_trait]
+impl SessionStore<String> for MapStore {
+    async fn load(&self, id: &str, ttl: Option<u32>) -> SessionResult<(String, u32)> {
+        let sessions = self.0.lock().unwrap();
+        let data = sessions.get(id).cloned().ok_or(SessionError::NotFound)?;
+        Ok((data, ttl.unwrap_or(u32::MAX)))
+    }
+
+    async fn save(&self, id: &str, data: String, _ttl: u32) -> SessionResult<()> {
+        self.0.lock().unwrap().insert(id.to_owned(), data);
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str, _data: String) -> SessionResult<()> {
+        self.0.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+let fairing = RocketFlexSession::<String>::builder()
+    .store(MapStore::default())
+    .build();
+```
+*/
+#[async_trait]
+pub trait SessionStore<T>: Send + Sync
+where
+    T: Send + Sync,
+{
+    /// Load session data and TTL (time-to-live in seconds). If a TTL value is provided, it should be
+    /// set upon retrieving the session. If the session is expired or otherwise invalid, a
+    /// [`SessionError`] should be returned instead.
+    async fn load(&self, id: &str, ttl: Option<u32>) -> SessionResult<(T, u32)>;
+
+    /// Save or update a session. This will be performed at the end of the request lifecycle.
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()>;
+
+    /// Delete a session. This will be performed at the end of the request lifecycle.
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()>;
+
+    /// Check whether a session exists. The default implementation loads the session.
+    async fn exists(&self, id: &str) -> SessionResult<bool> {
+        match self.load(id, None).await {
+            Ok(_) => Ok(true),
+            Err(SessionError::NotFound | SessionError::Expired) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Optional setup of resources that will be called on server startup
+    async fn setup(&self) -> SessionResult<()> {
+        Ok(()) // Default no-op
+    }
+
+    /// Optional teardown of resources that will be called on server shutdown
+    async fn shutdown(&self) -> SessionResult<()> {
+        Ok(()) // Default no-op
+    }
+}
+
+/// Extension of [`SessionStore`] for backends that keep the session in a cookie, with access to the
+/// [context](SessionContext) of the request. Use it with the `cookie_store` method of the
+/// [fairing builder](crate::RocketFlexSession::builder).
+#[async_trait]
+pub trait CookieStore<T>: SessionStore<T>
+where
+    T: Send + Sync,
+{
+    /// Load session data and TTL from the request, in the same way as [`SessionStore::load`].
+    /// The default implementation ignores the context and calls `load`.
+    #[allow(unused_variables, reason = "Public trait function with default")]
+    async fn load_with_context(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        context: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        self.load(id, ttl).await
+    }
+
+    /// Called when there's a pending change to the session data, to update the cookie during the
+    /// request. A `data` value of `None` indicates a deleted session.
+    fn save_cookie(
+        &self,
+        id: &str,
+        data: Option<&T>,
+        ttl: u32,
+        context: &dyn SessionContext,
+    ) -> SessionResult<()>;
+}
+
+/// Adapter to use a [`SessionStore`] as a [`SessionStorage`]
+pub(crate) struct StoreAdapter<S>(pub S);
+
+#[async_trait]
+impl<T, S> SessionStorage<T> for StoreAdapter<S>
+where
+    T: Send + Sync + 'static,
+    S: SessionStore<T>,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        self.0.load(id, ttl).await
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.0.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        self.0.delete(id, data).await
+    }
+
+    async fn exists(&self, id: &str, _cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+        self.0.exists(id).await
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.0.setup().await
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.0.shutdown().await
+    }
+}
+
+/// Adapter to use a [`CookieStore`] as a [`SessionStorage`]
+pub(crate) struct CookieStoreAdapter<S>(pub S);
+
+#[async_trait]
+impl<T, S> SessionStorage<T> for CookieStoreAdapter<S>
+where
+    T: Send + Sync + 'static,
+    S: CookieStore<T>,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        self.0.load_with_context(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        self.0.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+        self.0.delete(id, data).await
+    }
+
+    fn save_cookie(
+        &self,
+        id: &str,
+        data: Option<&T>,
+        ttl: u32,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<()> {
+        self.0.save_cookie(id, data, ttl, cookie_jar)
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.0.setup().await
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.0.shutdown().await
+    }
+}
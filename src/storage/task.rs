@@ -0,0 +1,74 @@
+//! Background tasks of storages
+
+use std::future::Future;
+
+use rocket::tokio::{spawn, sync::oneshot, task::JoinHandle};
+
+use crate::error::{SessionError, SessionResult};
+
+/// Guard for a background task of a storage (e.g. a cleanup task). The task is signaled to stop
+/// when the guard is shut down or dropped. Custom storages can use it to run their own background
+/// tasks, e.g. by spawning the task in [`setup`](super::SessionStorage::setup), and shutting it
+/// down in [`shutdown`](super::SessionStorage::shutdown).
+///
+/// ```rust
+/// use std::time::Duration;
+/// use rocket_flex_session::storage::TaskGuard;
+///
+/// async fn run_task() {
+///     let task = TaskGuard::spawn(|mut shutdown_rx| async move {
+///         let mut interval = rocket::tokio::time::interval(Duration::from_secs(60));
+///         loop {
+///             rocket::tokio::select! {
+///                 _ = interval.tick() => { /* do some work */ }
+///                 _ = &mut shutdown_rx => break,
+///             }
+///         }
+///     });
+///     assert!(!task.is_finished());
+///     task.shutdown().await.unwrap();
+/// }
+/// ```
+pub struct TaskGuard {
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl TaskGuard {
+    /// Spawn the task, passing it a receiver that resolves when the task should stop
+    pub fn spawn<F, Fut>(task: F) -> Self
+    where
+        F: FnOnce(oneshot::Receiver<()>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        Self {
+            shutdown_tx: Some(shutdown_tx),
+            handle: spawn(task(shutdown_rx)),
+        }
+    }
+
+    /// Whether the task has finished, e.g. because it was stopped or panicked
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Signal the task to stop, and wait for it to finish
+    pub async fn shutdown(mut self) -> SessionResult<()> {
+        if let Some(tx) = self.shutdown_tx.take() {
+            // The task may have already finished
+            let _ = tx.send(());
+        }
+        (&mut self.handle).await.map_err(|e| {
+            SessionError::SetupTeardown(format!("Background task didn't finish cleanly: {e}"))
+        })
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Tiered storage with a cache in front of a storage provider
+//!
+//! [`TieredLayer`] is a [storage layer](super::layer) that serves session loads from a
+//! [`SessionCache`], and only falls back to the inner storage on a cache miss. Saves and deletions
+//! are written through to the inner storage, and update the cache. This can be used to reduce
+//! round-trips to a remote database, with an in-process cache such as [`MokaCache`] (`moka` feature).
+//!
+//! Keep in mind that each server instance has its own cache: if the session is changed or
+//! deleted by another instance, or invalidated via indexed operations (which go directly to
+//! the inner storage), a cached session can be stale until its cache entry expires. Use a
+//! short cache TTL if this matters for your application. Session [metadata](super::SessionMetadata)
+//! (e.g. the creation time) isn't cached, so it's only available when the session is loaded from
+//! the inner storage.
+//!
+//! After a deploy, the cache starts out empty. To avoid a spike of loads from the inner storage,
+//! the cache can be [warmed up](TieredLayer::warmup) at startup with the most recently active
+//! sessions, if the inner storage supports [listing them](SessionStorage::recently_active)
+//! (currently the sqlx storages).
+//!
+//! # Example
+//! ```rust
+//! # #[cfg(all(feature = "moka", feature = "sqlx_postgres"))]
+//! # {
+//! use std::time::Duration;
+//! use rocket_flex_session::{
+//!     storage::{
+//!         layer::StorageStack,
+//!         sqlx::SqlxPostgresStorage,
+//!         tiered::{MokaCache, TieredLayer},
+//!     },
+//!     RocketFlexSession,
+//! };
+//! # use rocket_flex_session::{error::SessionError, storage::sqlx::SessionSqlx, SessionIdentifier};
+//! # #[derive(Clone)]
+//! # struct MySession { user_id: String }
+//! # impl SessionIdentifier for MySession {
+//! #     type Id = String;
+//! #     fn identifier(&self) -> Option<String> { Some(self.user_id.clone()) }
+//! # }
+//! # impl SessionSqlx<sqlx::Postgres> for MySession {
+//! #     type Error = SessionError;
+//! #     type Data = String;
+//! #     fn into_sql(self) -> Result<String, SessionError> { Ok(self.user_id) }
+//! #     fn from_sql(user_id: String) -> Result<Self, SessionError> { Ok(Self { user_id }) }
+//! # }
+//!
+//! fn create_fairing(pool: sqlx::PgPool) -> RocketFlexSession<MySession> {
+//!     let cache = MokaCache::builder()
+//!         .max_capacity(10_000)
+//!         .max_ttl(Duration::from_secs(30))
+//!         .build();
+//!     let inner = SqlxPostgresStorage::builder()
+//!         .pool(pool)
+//!         .table_name("sessions")
+//!         .build();
+//!     let storage = StorageStack::new(inner)
+//!         .layer(TieredLayer::new(cache).warmup(1_000))
+//!         .build();
+//!     RocketFlexSession::<MySession>::builder()
+//!         .storage(storage)
+//!         .build()
+//! }
+//! # }
+//! ```
+
+#[cfg(feature = "moka")]
+mod moka;
+#[cfg(feature = "moka")]
+pub use moka::MokaCache;
+
+use std::time::Duration;
+
+use rocket::async_trait;
+
+use crate::error::{SessionError, SessionResult};
+
+use super::{
+    forward_storage_impl, layer::StorageLayer, SessionContext, SessionMetadata, SessionStorage,
+    SessionStorageIndexed,
+};
+
+/// A key-value cache for sessions. Implement this to use your own cache with [`TieredLayer`].
+#[async_trait]
+pub trait SessionCache<T>: Send + Sync
+where
+    T: Send + Sync,
+{
+    /// Get the cached session data and its remaining TTL (in seconds), if the session
+    /// is cached and hasn't expired.
+    async fn get(&self, id: &str) -> Option<(T, u32)>;
+
+    /// Cache the session data with the given TTL (in seconds). The cache may
+    /// evict the entry before then.
+    async fn set(&self, id: &str, data: T, ttl: u32);
+
+    /// Remove the session from the cache
+    async fn invalidate(&self, id: &str);
+}
+
+/// Layer that adds a cache in front of a storage provider. See the [module docs](self).
+pub struct TieredLayer<C> {
+    cache: C,
+    warmup: Option<usize>,
+}
+
+impl<C> TieredLayer<C> {
+    /// Create a tiered layer using the given cache
+    pub fn new(cache: C) -> Self {
+        Self {
+            cache,
+            warmup: None,
+        }
+    }
+
+    /// Preload up to `limit` of the most recently active sessions into the cache during the
+    /// storage [setup](SessionStorage::setup), using [`SessionStorage::recently_active`]
+    /// of the inner storage. If the inner storage doesn't support it, the warmup is skipped
+    /// with a warning.
+    pub fn warmup(mut self, limit: usize) -> Self {
+        self.warmup = Some(limit);
+        self
+    }
+}
+
+impl<S, C> StorageLayer<S> for TieredLayer<C> {
+    type Storage = TieredStorage<S, C>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        TieredStorage {
+            inner,
+            cache: self.cache,
+            warmup: self.warmup,
+        }
+    }
+}
+
+/// Storage wrapped by a [`TieredLayer`]
+pub struct TieredStorage<S, C> {
+    inner: S,
+    cache: C,
+    warmup: Option<usize>,
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S, C> SessionStorage<T> for TieredStorage<S, C>
+    where
+        T: Clone + Send + Sync + 'static,
+        S: SessionStorage<T>,
+        C: SessionCache<T>,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            // A new TTL must be set in the inner storage, so only use the cache when there's no TTL
+            if ttl.is_none() {
+                if let Some(cached) = self.cache.get(id).await {
+                    return Ok(cached);
+                }
+            }
+            let (data, ttl) = self.inner.load(id, ttl, cookie_jar).await?;
+            self.cache.set(id, data.clone(), ttl).await;
+            Ok((data, ttl))
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            // Metadata isn't cached, so it's only returned on a cache miss
+            if ttl.is_none() {
+                if let Some((data, ttl)) = self.cache.get(id).await {
+                    return Ok((data, ttl, SessionMetadata::default()));
+                }
+            }
+            let (data, ttl, metadata) = self.inner.load_with_metadata(id, ttl, cookie_jar).await?;
+            self.cache.set(id, data.clone(), ttl).await;
+            Ok((data, ttl, metadata))
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            if self.cache.get(id).await.is_some() {
+                return Ok(true);
+            }
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            match self.inner.save(id, data.clone(), ttl).await {
+                Ok(()) => {
+                    self.cache.set(id, data, ttl).await;
+                    Ok(())
+                }
+                Err(e) => {
+                    self.cache.invalidate(id).await;
+                    Err(e)
+                }
+            }
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.save_with_metadata(id, data, ttl, SessionMetadata::default())
+                .await
+        }
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            let result = self.inner
+                .save_with_metadata(id, data.clone(), ttl, metadata)
+                .await;
+            // Round the cache TTL down, so that the cached session doesn't outlive the stored one
+            match u32::try_from(ttl.as_secs()).unwrap_or(u32::MAX) {
+                cache_ttl if cache_ttl > 0 && result.is_ok() => {
+                    self.cache.set(id, data, cache_ttl).await;
+                }
+                _ => self.cache.invalidate(id).await,
+            }
+            result
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.cache.invalidate(id).await;
+            self.inner.delete(id, data).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.cache.invalidate(id).await;
+            self.inner.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await?;
+            if let Some(limit) = self.warmup {
+                match self.inner.recently_active(limit).await {
+                    Ok(sessions) => {
+                        for (id, data, ttl) in sessions {
+                            self.cache.set(&id, data, ttl).await;
+                        }
+                    }
+                    Err(SessionError::Unsupported(_)) => {
+                        rocket::warn!(
+                            "Cache warmup skipped: the storage can't list recent sessions"
+                        );
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::time::{Duration, Instant};
+
+use bon::bon;
+use moka::future::Cache;
+use rocket::async_trait;
+
+use super::SessionCache;
+
+/// In-process [`SessionCache`] using the [moka](https://docs.rs/moka) crate.
+pub struct MokaCache<T> {
+    cache: Cache<String, (T, Instant)>,
+}
+
+#[bon]
+impl<T> MokaCache<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Create a new cache.
+    ///
+    /// # Parameters
+    /// * `max_capacity` - Maximum number of cached sessions (default: `10_000`)
+    /// * `max_ttl` - Maximum time that a session stays in the cache, even if its TTL is
+    ///   longer. This limits how long a stale session can be served. (default: 60 seconds)
+    #[builder]
+    pub fn new(
+        #[builder(default = 10_000)] max_capacity: u64,
+        #[builder(default = Duration::from_secs(60))] max_ttl: Duration,
+    ) -> Self {
+        Self {
+            cache: Cache::builder()
+                .max_capacity(max_capacity)
+                .time_to_live(max_ttl)
+                .build(),
+        }
+    }
+}
+
+impl<T> Default for MokaCache<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[async_trait]
+impl<T> SessionCache<T> for MokaCache<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    async fn get(&self, id: &str) -> Option<(T, u32)> {
+        let (data, expires) = self.cache.get(id).await?;
+        let remaining = expires.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            self.cache.invalidate(id).await;
+            return None;
+        }
+        Some((data, remaining.as_secs_f64().ceil() as u32))
+    }
+
+    async fn set(&self, id: &str, data: T, ttl: u32) {
+        let expires = Instant::now() + Duration::from_secs(ttl.into());
+        self.cache.insert(id.to_owned(), (data, expires)).await;
+    }
+
+    async fn invalidate(&self, id: &str) {
+        self.cache.invalidate(id).await;
+    }
+}
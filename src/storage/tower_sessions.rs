@@ -0,0 +1,200 @@
+//! Session storage over a tower-sessions store
+//!
+//! [`TowerSessionsStorage`] uses any [tower-sessions](https://docs.rs/crate/tower-sessions)
+//! [`SessionStore`] as the storage provider, so services using Axum (or another tower-based
+//! framework) and Rocket can share the same store implementation and session data.
+
+use std::{collections::HashMap, sync::Arc};
+
+use bon::Builder;
+use rocket::{
+    async_trait,
+    serde::{
+        de::DeserializeOwned,
+        json::serde_json::{self, Value},
+        Serialize,
+    },
+    time::Duration,
+};
+use tower_sessions_core::{
+    session::{Id, Record},
+    SessionStore,
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    error::{SessionError, SessionResult},
+    storage::{SessionContext, SessionStorage},
+};
+
+/**
+Session storage over a [tower-sessions](https://docs.rs/crate/tower-sessions) [`SessionStore`],
+e.g. one of the Redis, SQL, or MongoDB stores from the tower-sessions ecosystem. This storage
+doesn't support indexing. Your session data type must implement `serde::Serialize` and
+`serde::Deserialize`.
+
+# Session data
+tower-sessions stores a map of JSON values in each session record. By default, the fields of
+your session data are stored as the top-level keys of the record, so your data type must
+serialize to a JSON object. Set the `data_key` to store your data under a single key instead
+(e.g. to read it in Axum with `session.get::<User>("user")`). Other keys of the record, set by
+the other service, are then kept when the session is saved.
+
+# Session IDs
+tower-sessions uses 128-bit IDs, which are encoded as 22 base64 characters in its cookie.
+Session IDs in that format are used as-is, so a session created by the other service can be
+loaded by passing its ID to Rocket. Other session IDs (such as the IDs generated by this
+crate) are mapped to a 128-bit ID with a stable hash - see [`tower_session_id`].
+
+# Example
+```rust
+use rocket::serde::{Deserialize, Serialize};
+use rocket_flex_session::{storage::tower_sessions::TowerSessionsStorage, RocketFlexSession};
+use tower_sessions_core::SessionStore;
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct User {
+    id: String,
+}
+
+fn session_fairing(store: impl SessionStore) -> RocketFlexSession<User> {
+    let storage = TowerSessionsStorage::builder()
+        .store(store)
+        .data_key("user")
+        .build();
+    RocketFlexSession::builder().storage(storage).build()
+}
+```
+*/
+#[derive(Builder)]
+pub struct TowerSessionsStorage<S: SessionStore> {
+    /// The tower-sessions store
+    store: S,
+    /// The key of the session data in the tower-sessions record. If not set, the fields of the
+    /// session data are stored as the top-level keys of the record.
+    #[builder(into)]
+    data_key: Option<String>,
+    /// Set the clock used to calculate the expiration date of the records. The default is the system time.
+    #[builder(default = Arc::new(SystemClock), with = |clock: impl Clock + 'static| Arc::new(clock))]
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: SessionStore> TowerSessionsStorage<S> {
+    /// Get the underlying tower-sessions store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    async fn load_record(&self, id: &str) -> SessionResult<Record> {
+        let record = self
+            .store
+            .load(&tower_session_id(id))
+            .await?
+            .ok_or(SessionError::NotFound)?;
+        if record.expiry_date <= self.clock.now() {
+            return Err(SessionError::Expired);
+        }
+        Ok(record)
+    }
+
+    fn parse_record<T: DeserializeOwned>(&self, record: &Record) -> SessionResult<T> {
+        let value = match &self.data_key {
+            Some(key) => record
+                .data
+                .get(key)
+                .cloned()
+                .ok_or(SessionError::NotFound)?,
+            None => Value::Object(record.data.clone().into_iter().collect()),
+        };
+        serde_json::from_value(value).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+
+    fn expiry_date(&self, ttl: u32) -> rocket::time::OffsetDateTime {
+        self.clock.now() + Duration::seconds(ttl.into())
+    }
+}
+
+/// Get the tower-sessions ID for a session ID. IDs in the tower-sessions format are parsed,
+/// and other IDs are hashed with the 128-bit FNV-1a hash. Use this in the other service to find
+/// the sessions created by Rocket.
+pub fn tower_session_id(id: &str) -> Id {
+    id.parse().unwrap_or_else(|_| Id(fnv1a_128(id.as_bytes())))
+}
+
+fn fnv1a_128(bytes: &[u8]) -> i128 {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    let hash = bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u128::from(*byte)).wrapping_mul(PRIME)
+    });
+    hash as i128
+}
+
+#[async_trait]
+impl<T, S> SessionStorage<T> for TowerSessionsStorage<S>
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+    S: SessionStore,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let mut record = self.load_record(id).await?;
+        let data = self.parse_record(&record)?;
+
+        match ttl {
+            Some(new_ttl) => {
+                record.expiry_date = self.expiry_date(new_ttl);
+                self.store.save(&record).await?;
+                Ok((data, new_ttl))
+            }
+            None => {
+                let remaining_ttl = (record.expiry_date - self.clock.now()).whole_seconds();
+                Ok((data, remaining_ttl.try_into().unwrap_or(0)))
+            }
+        }
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let value =
+            serde_json::to_value(data).map_err(|e| SessionError::Serialization(Box::new(e)))?;
+        let data = match &self.data_key {
+            Some(key) => {
+                // Keep the other keys of the record
+                let mut data = match self.load_record(id).await {
+                    Ok(record) => record.data,
+                    Err(SessionError::NotFound | SessionError::Expired) => HashMap::new(),
+                    Err(e) => return Err(e),
+                };
+                data.insert(key.clone(), value);
+                data
+            }
+            None => match value {
+                Value::Object(fields) => fields.into_iter().collect(),
+                _ => return Err(SessionError::InvalidData),
+            },
+        };
+
+        let record = Record {
+            id: tower_session_id(id),
+            data,
+            expiry_date: self.expiry_date(ttl),
+        };
+        self.store.save(&record).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        self.store.delete(&tower_session_id(id)).await?;
+        Ok(())
+    }
+
+    async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+        self.store.delete(&tower_session_id(id)).await?;
+        Ok(())
+    }
+}
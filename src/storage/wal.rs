@@ -0,0 +1,477 @@
+//! Write-ahead log of session mutations
+//!
+//! A [`WalLayer`] appends a [`WalRecord`] to a [`WalSink`] for every session save and delete,
+//! before the operation is sent to the storage. Each record has the session ID, the operation, a
+//! timestamp, and a SHA-256 hash of the serialized session data - along with the data itself, so
+//! the log can be [replayed](WalLayer::replay) to reconstruct the sessions changed since a backup
+//! of the storage was taken. If the log can't be written, the operation fails and the storage
+//! isn't changed. Use [`include_data(false)`](WalLayer::include_data) to keep only the hashes,
+//! e.g. for an audit log that shouldn't contain personal information.
+//!
+//! [`FileWal`] appends the records to a local file. To keep the log somewhere else (e.g. a
+//! database table), implement [`WalSink`].
+//!
+//! # Example
+//! ```rust,no_run
+//! # #[cfg(feature = "json")]
+//! # async fn run() -> rocket_flex_session::error::SessionResult<()> {
+//! use rocket_flex_session::{
+//!     storage::{
+//!         envelope::EnvelopeCodec,
+//!         layer::StorageStack,
+//!         memory::MemoryStorage,
+//!         wal::{FileWal, WalLayer},
+//!     },
+//!     RocketFlexSession,
+//! };
+//!
+//! let wal = WalLayer::new(FileWal::new("sessions.wal"), EnvelopeCodec::default());
+//! let base_storage = MemoryStorage::<String>::default();
+//!
+//! // After restoring the storage from a backup, replay the sessions changed since then
+//! # let backup_time = rocket::time::OffsetDateTime::now_utc();
+//! let stats = wal.replay(&base_storage, Some(backup_time)).await?;
+//! println!("Restored {} sessions", stats.saved);
+//!
+//! let storage = StorageStack::new(base_storage).layer(wal).build();
+//! let fairing = RocketFlexSession::<String>::builder().storage(storage).build();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::HashMap, fmt::Write as _, path::PathBuf, sync::Arc, time::Duration};
+
+use rocket::{
+    async_trait,
+    time::OffsetDateTime,
+    tokio::{
+        fs::{self, File, OpenOptions},
+        io::AsyncWriteExt,
+        sync::Mutex,
+    },
+};
+use sha2::{Digest, Sha256};
+
+use crate::error::{SessionError, SessionResult};
+
+use super::{
+    erased::SessionCodec, forward_storage_impl, layer::StorageLayer, NoopSessionContext,
+    SessionContext, SessionMetadata, SessionStorage, SessionStorageIndexed,
+};
+
+/// A session mutation recorded in the write-ahead log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalOp {
+    /// The session was saved
+    Save,
+    /// The session was deleted
+    Delete,
+}
+
+impl WalOp {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Save => "save",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// A record of the write-ahead log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalRecord {
+    /// When the operation was started
+    pub timestamp: OffsetDateTime,
+    /// The operation
+    pub op: WalOp,
+    /// The session ID
+    pub id: String,
+    /// Expiration time of a saved session
+    pub expires: Option<OffsetDateTime>,
+    /// Hex-encoded SHA-256 hash of the serialized session data
+    pub hash: String,
+    /// The serialized session data of a saved session, if included in the log
+    pub data: Option<Vec<u8>>,
+}
+
+impl WalRecord {
+    /// Encode the record as a tab-separated line: timestamp (Unix nanoseconds), operation, session
+    /// ID, expiration (Unix seconds), hash, and hex-encoded data. Missing values are written as `-`.
+    fn to_line(&self) -> String {
+        let expires = (self.expires).map_or("-".to_owned(), |e| e.unix_timestamp().to_string());
+        let data = self.data.as_deref().map_or("-".to_owned(), hex);
+        format!(
+            "{}\t{}\t{}\t{expires}\t{}\t{data}\n",
+            self.timestamp.unix_timestamp_nanos(),
+            self.op.as_str(),
+            self.id,
+            self.hash,
+        )
+    }
+
+    fn from_line(line: &str) -> SessionResult<Self> {
+        let invalid =
+            || SessionError::Parsing(format!("Invalid write-ahead log record: {line}").into());
+        let mut fields = line.split('\t');
+        let mut next = || fields.next().ok_or_else(invalid);
+        let (timestamp, op, id, expires, hash, data) =
+            (next()?, next()?, next()?, next()?, next()?, next()?);
+        let timestamp = timestamp
+            .parse()
+            .ok()
+            .and_then(|nanos| OffsetDateTime::from_unix_timestamp_nanos(nanos).ok());
+        let op = match op {
+            "save" => WalOp::Save,
+            "delete" => WalOp::Delete,
+            _ => return Err(invalid()),
+        };
+        let expires = match expires {
+            "-" => None,
+            secs => Some(
+                secs.parse()
+                    .ok()
+                    .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+                    .ok_or_else(invalid)?,
+            ),
+        };
+        let data = match data {
+            "-" => None,
+            data => Some(unhex(data).ok_or_else(invalid)?),
+        };
+        Ok(Self {
+            timestamp: timestamp.ok_or_else(invalid)?,
+            op,
+            id: id.to_owned(),
+            expires,
+            hash: hash.to_owned(),
+            data,
+        })
+    }
+}
+
+/// Destination of the write-ahead log, e.g. a file or a database table
+#[async_trait]
+pub trait WalSink: Send + Sync {
+    /// Append a record to the log. The record should be written when this returns.
+    async fn append(&self, record: &WalRecord) -> SessionResult<()>;
+
+    /// Read all records of the log, in the order they were appended
+    async fn read(&self) -> SessionResult<Vec<WalRecord>>;
+}
+
+/// Write-ahead log in a local file, with one line per record
+pub struct FileWal {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+    sync: bool,
+}
+
+impl FileWal {
+    /// Append the records to the file at the given path, which is created if it doesn't exist
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: Mutex::default(),
+            sync: false,
+        }
+    }
+
+    /// Sync the file to disk after each record (default: `false`). This makes sure the record
+    /// survives a system crash, but makes saving sessions a lot slower.
+    pub fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+}
+
+#[async_trait]
+impl WalSink for FileWal {
+    async fn append(&self, record: &WalRecord) -> SessionResult<()> {
+        let mut file = self.file.lock().await;
+        if file.is_none() {
+            let opened = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await;
+            *file = Some(opened.map_err(|e| SessionError::Backend(e.into()))?);
+        }
+        let file = file.as_mut().expect("file should be open");
+        file.write_all(record.to_line().as_bytes())
+            .await
+            .map_err(|e| SessionError::Backend(e.into()))?;
+        file.flush()
+            .await
+            .map_err(|e| SessionError::Backend(e.into()))?;
+        if self.sync {
+            file.sync_data()
+                .await
+                .map_err(|e| SessionError::Backend(e.into()))?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self) -> SessionResult<Vec<WalRecord>> {
+        let contents = match fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(SessionError::Backend(e.into())),
+        };
+        contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(WalRecord::from_line)
+            .collect()
+    }
+}
+
+/// Counts of the sessions restored by [`WalLayer::replay`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayStats {
+    /// Sessions saved to the storage
+    pub saved: u64,
+    /// Sessions deleted from the storage
+    pub deleted: u64,
+    /// Sessions skipped because they have expired since
+    pub expired: u64,
+    /// Sessions skipped because their data isn't in the log, or doesn't match the hash
+    pub skipped: u64,
+}
+
+/// Layer that records session saves and deletes in a write-ahead log. See the [module docs](self).
+pub struct WalLayer<T> {
+    sink: Arc<dyn WalSink>,
+    codec: Arc<dyn SessionCodec<T>>,
+    include_data: bool,
+}
+
+impl<T> Clone for WalLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            sink: self.sink.clone(),
+            codec: self.codec.clone(),
+            include_data: self.include_data,
+        }
+    }
+}
+
+impl<T> WalLayer<T> {
+    /// Record the mutations in the given sink, serializing the session data with the codec
+    pub fn new(sink: impl WalSink + 'static, codec: impl SessionCodec<T> + 'static) -> Self {
+        Self {
+            sink: Arc::new(sink),
+            codec: Arc::new(codec),
+            include_data: true,
+        }
+    }
+
+    /// Include the serialized session data in the log (default: `true`). Without the data, only
+    /// deletes can be replayed.
+    pub fn include_data(mut self, include_data: bool) -> Self {
+        self.include_data = include_data;
+        self
+    }
+
+    /// Append a record of the operation to the log
+    async fn record(
+        &self,
+        op: WalOp,
+        id: &str,
+        data: &T,
+        ttl: Option<Duration>,
+    ) -> SessionResult<()> {
+        let bytes = self.codec.encode(data)?;
+        let timestamp = OffsetDateTime::now_utc();
+        let record = WalRecord {
+            timestamp,
+            op,
+            id: id.to_owned(),
+            expires: ttl.map(|ttl| timestamp + ttl),
+            hash: hex(&Sha256::digest(&bytes)),
+            data: (self.include_data && op == WalOp::Save).then_some(bytes),
+        };
+        self.sink.append(&record).await
+    }
+
+    /// Replay the log into the storage, e.g. after restoring it from a backup. Only the latest
+    /// record of each session is applied, and with `since`, only records from that time onwards
+    /// (e.g. the time of the backup). Saved sessions are restored with their remaining TTL, and
+    /// the storage should be set up before replaying. Replay into the base storage rather than the
+    /// one wrapped by this layer, which would append the records to the log again.
+    pub async fn replay<S>(
+        &self,
+        storage: &S,
+        since: Option<OffsetDateTime>,
+    ) -> SessionResult<ReplayStats>
+    where
+        T: Send + Sync,
+        S: SessionStorage<T> + ?Sized,
+    {
+        let records = self.sink.read().await?;
+        let mut latest: Vec<WalRecord> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for record in records {
+            if since.is_some_and(|since| record.timestamp < since) {
+                continue;
+            }
+            match positions.get(&record.id) {
+                Some(&position) => latest[position] = record,
+                None => {
+                    positions.insert(record.id.clone(), latest.len());
+                    latest.push(record);
+                }
+            }
+        }
+
+        let mut stats = ReplayStats::default();
+        let now = OffsetDateTime::now_utc();
+        for record in latest {
+            match record.op {
+                WalOp::Save => {
+                    let Some(bytes) = record
+                        .data
+                        .filter(|bytes| hex(&Sha256::digest(bytes)) == record.hash)
+                    else {
+                        stats.skipped += 1;
+                        continue;
+                    };
+                    let remaining = record.expires.map(|expires| expires - now);
+                    let ttl = remaining.and_then(|r| Duration::try_from(r).ok());
+                    let Some(ttl) = ttl.filter(|ttl| !ttl.is_zero()) else {
+                        stats.expired += 1;
+                        continue;
+                    };
+                    let data = self.codec.decode(&bytes)?;
+                    storage.save_with_duration(&record.id, data, ttl).await?;
+                    stats.saved += 1;
+                }
+                WalOp::Delete => match storage.load(&record.id, None, &NoopSessionContext).await {
+                    Ok((data, _)) => {
+                        storage.delete(&record.id, data).await?;
+                        stats.deleted += 1;
+                    }
+                    Err(SessionError::NotFound | SessionError::Expired) => {}
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+        Ok(stats)
+    }
+}
+
+impl<T, S> StorageLayer<S> for WalLayer<T> {
+    type Storage = WalStorage<S, T>;
+
+    fn layer(self, inner: S) -> Self::Storage {
+        WalStorage { inner, wal: self }
+    }
+}
+
+/// Storage wrapped by a [`WalLayer`]
+pub struct WalStorage<S, T> {
+    inner: S,
+    wal: WalLayer<T>,
+}
+
+forward_storage_impl! {
+    forward(inner): activity, presence, capabilities, once_values, lazy_fields;
+    impl<T, S> SessionStorage<T> for WalStorage<S, T>
+    where
+        T: Send + Sync,
+        S: SessionStorage<T>,
+    {
+        async fn load(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32)> {
+            self.inner.load(id, ttl, cookie_jar).await
+        }
+
+        async fn load_with_metadata(
+            &self,
+            id: &str,
+            ttl: Option<u32>,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<(T, u32, SessionMetadata)> {
+            self.inner.load_with_metadata(id, ttl, cookie_jar).await
+        }
+
+        async fn exists(&self, id: &str, cookie_jar: &dyn SessionContext) -> SessionResult<bool> {
+            self.inner.exists(id, cookie_jar).await
+        }
+
+        async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+            let duration = Duration::from_secs(ttl.into());
+            self.wal
+                .record(WalOp::Save, id, &data, Some(duration))
+                .await?;
+            self.inner.save(id, data, ttl).await
+        }
+
+        async fn save_with_duration(&self, id: &str, data: T, ttl: Duration) -> SessionResult<()> {
+            self.wal.record(WalOp::Save, id, &data, Some(ttl)).await?;
+            self.inner.save_with_duration(id, data, ttl).await
+        }
+
+        async fn save_with_metadata(
+            &self,
+            id: &str,
+            data: T,
+            ttl: Duration,
+            metadata: SessionMetadata,
+        ) -> SessionResult<()> {
+            self.wal.record(WalOp::Save, id, &data, Some(ttl)).await?;
+            self.inner.save_with_metadata(id, data, ttl, metadata).await
+        }
+
+        async fn delete(&self, id: &str, data: T) -> SessionResult<()> {
+            self.wal.record(WalOp::Delete, id, &data, None).await?;
+            self.inner.delete(id, data).await
+        }
+
+        async fn delete_unreadable(&self, id: &str) -> SessionResult<()> {
+            self.inner.delete_unreadable(id).await
+        }
+
+        fn save_cookie(
+            &self,
+            id: &str,
+            data: Option<&T>,
+            ttl: u32,
+            cookie_jar: &dyn SessionContext,
+        ) -> SessionResult<()> {
+            self.inner.save_cookie(id, data, ttl, cookie_jar)
+        }
+
+        fn as_indexed_storage(&self) -> Option<&dyn SessionStorageIndexed<T>> {
+            self.inner.as_indexed_storage()
+        }
+
+        async fn setup(&self) -> SessionResult<()> {
+            self.inner.setup().await
+        }
+
+        async fn shutdown(&self) -> SessionResult<()> {
+            self.inner.shutdown().await
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,72 @@
+//! Login throttling
+//!
+//! Helpers for counting failed login attempts per identifier (e.g. a username or email),
+//! so brute-force attempts can be locked out for a period of time. Implementations are
+//! available for the built-in storage backends:
+//!
+//! | Throttle | Feature Flag |
+//! |----------|-------------|
+//! | [`storage::memory::MemoryThrottle`](crate::storage::memory::MemoryThrottle) | Built-in |
+//! | `storage::redis::RedisFredThrottle` | `redis_fred` |
+//! | `storage::sqlx::SqlxPostgresThrottle` | `sqlx_postgres` |
+//! | `storage::sqlx::SqlxSqliteThrottle` | `sqlx_sqlite` |
+//!
+//! # Example
+//! ```rust
+//! use rocket::{http::Status, State};
+//! use rocket_flex_session::{storage::memory::MemoryThrottle, throttle::LoginThrottle};
+//!
+//! #[rocket::post("/login/<username>")]
+//! async fn login(username: &str, throttle: &State<MemoryThrottle>) -> Result<&'static str, Status> {
+//!     if throttle.is_locked_out(username).await.unwrap_or(false) {
+//!         return Err(Status::TooManyRequests);
+//!     }
+//!     let password_ok = false; // verify credentials...
+//!     if !password_ok {
+//!         let _ = throttle.record_failed_login(username).await;
+//!         return Err(Status::Unauthorized);
+//!     }
+//!     let _ = throttle.reset_failed_logins(username).await;
+//!     Ok("Logged in")
+//! }
+//!
+//! #[rocket::launch]
+//! fn rocket() -> _ {
+//!     let throttle = MemoryThrottle::builder().max_attempts(5).window(15 * 60).build();
+//!     rocket::build()
+//!         .manage(throttle)
+//!         .mount("/", rocket::routes![login])
+//! }
+//! ```
+
+use rocket::async_trait;
+
+use crate::error::SessionResult;
+
+/// Default maximum number of failed attempts before an identifier is locked out
+pub(crate) const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+/// Default window (in seconds) in which failed attempts are counted
+pub(crate) const DEFAULT_WINDOW: u32 = 15 * 60;
+
+/// Trait for counting failed login attempts by identifier. Attempts are counted within
+/// a fixed window that starts at the first failed attempt.
+#[async_trait]
+pub trait LoginThrottle: Send + Sync {
+    /// The maximum number of failed attempts before the identifier is locked out.
+    fn max_attempts(&self) -> u32;
+
+    /// Record a failed login attempt for the identifier. Returns the number of failed
+    /// attempts within the current window.
+    async fn record_failed_login(&self, identifier: &str) -> SessionResult<u32>;
+
+    /// Get the number of failed attempts for the identifier within the current window.
+    async fn failed_attempts(&self, identifier: &str) -> SessionResult<u32>;
+
+    /// Clear the failed attempts for the identifier (e.g. after a successful login).
+    async fn reset_failed_logins(&self, identifier: &str) -> SessionResult<()>;
+
+    /// Whether the identifier has reached the maximum number of failed attempts.
+    async fn is_locked_out(&self, identifier: &str) -> SessionResult<bool> {
+        Ok(self.failed_attempts(identifier).await? >= self.max_attempts())
+    }
+}
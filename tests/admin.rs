@@ -0,0 +1,92 @@
+use rocket_flex_session::{
+    admin::SessionAdmin,
+    storage::{memory::MemoryStorageIndexed, SessionStorage, SessionStorageIndexed},
+    RocketFlexSession, SessionIdentifier,
+};
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
+struct UserSession {
+    user_id: String,
+    theme: String,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+fn session(user_id: &str, theme: &str) -> UserSession {
+    UserSession {
+        user_id: user_id.to_owned(),
+        theme: theme.to_owned(),
+    }
+}
+
+#[rocket::async_test]
+async fn export_and_erase_sessions() {
+    let storage = MemoryStorageIndexed::<UserSession>::default();
+    storage
+        .save("session_id_1", session("alice", "dark"), 3600)
+        .await
+        .unwrap();
+    storage
+        .save("session_id_2", session("alice", "light"), 3600)
+        .await
+        .unwrap();
+    storage
+        .save("session_id_3", session("bob", "dark"), 3600)
+        .await
+        .unwrap();
+
+    let export = storage
+        .export_sessions_for_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    let sessions = export["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    for session in sessions {
+        assert_eq!(session["data"]["user_id"], "alice");
+        assert_eq!(session["id"].as_str().unwrap().len(), 8);
+        assert!(session["ttl"].as_u64().unwrap() <= 3600);
+    }
+
+    let erased = storage.erase_identifier(&"alice".to_owned()).await.unwrap();
+    assert_eq!(erased, 2);
+    let export = storage
+        .export_sessions_for_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert!(export["sessions"].as_array().unwrap().is_empty());
+
+    let bob_sessions = storage
+        .get_session_ids_by_identifier(&"bob".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(bob_sessions, vec!["session_id_3"]);
+}
+
+#[rocket::async_test]
+async fn admin_from_fairing() {
+    let fairing = RocketFlexSession::<UserSession>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .build();
+    let storage = fairing.indexed_storage().expect("should support indexing");
+    storage
+        .save("session_id_1", session("alice", "dark"), 3600)
+        .await
+        .unwrap();
+
+    let export = storage
+        .export_sessions_for_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(export["sessions"][0]["data"]["theme"], "dark");
+
+    assert!(RocketFlexSession::<UserSession>::default()
+        .indexed_storage()
+        .is_none());
+}
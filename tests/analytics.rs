@@ -0,0 +1,147 @@
+#[macro_use]
+extern crate rocket;
+
+use std::time::Duration;
+
+use rocket::{local::asynchronous::Client, time::OffsetDateTime};
+use rocket_flex_session::{
+    analytics::{SessionLifetimeMetrics, TerminationReason, LIFETIME_BUCKETS},
+    clock::MockClock,
+    storage::memory::MemoryStorageIndexed,
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[post("/login/<user>")]
+fn login(mut session: Session<User>, user: &str) {
+    session.set(User(user.to_owned()));
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<User>) {
+    session.delete();
+}
+
+#[get("/user")]
+fn user(session: Session<User>) -> String {
+    session.get().map(|user| user.0).unwrap_or_default()
+}
+
+/// Client that doesn't keep cookies, so each login creates a new session
+async fn create_client(clock: &MockClock, metrics: &SessionLifetimeMetrics) -> Client {
+    let fairing = RocketFlexSession::<User>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .lifetime_metrics(metrics.clone())
+        .clock(clock.clone())
+        .with_options(|opt| opt.presence_sample_rate = Some(1.0))
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, logout]);
+    Client::untracked(rocket).await.unwrap()
+}
+
+fn fairing(client: &Client) -> &RocketFlexSession<User> {
+    client.rocket().state().unwrap()
+}
+
+#[rocket::async_test]
+async fn tracks_created_and_deleted_sessions() {
+    let clock = MockClock::new(OffsetDateTime::now_utc());
+    let metrics = SessionLifetimeMetrics::default();
+    let client = create_client(&clock, &metrics).await;
+
+    let response = client.post("/login/alice").dispatch().await;
+    let cookie = response.cookies().get("rocket").unwrap().clone();
+    assert_eq!(metrics.created(), 1);
+
+    clock.advance(rocket::time::Duration::hours(2));
+    client.post("/logout").cookie(cookie).dispatch().await;
+    assert_eq!(metrics.terminated(TerminationReason::Deleted), 1);
+    assert_eq!(metrics.churn_rate(), 1.0);
+
+    let histogram = metrics.lifetime_histogram();
+    assert_eq!(histogram.len(), LIFETIME_BUCKETS.len() + 1);
+    assert_eq!(histogram[4], (Some(Duration::from_secs(4 * 60 * 60)), 1));
+    let average = metrics.average_lifetime().unwrap();
+    assert!(average > Duration::from_secs(60 * 60));
+    assert!(average <= Duration::from_secs(2 * 60 * 60));
+}
+
+#[rocket::async_test]
+async fn tracks_invalidated_and_expired_sessions() {
+    let clock = MockClock::new(OffsetDateTime::now_utc());
+    let metrics = SessionLifetimeMetrics::default();
+    let client = create_client(&clock, &metrics).await;
+
+    let response = client.post("/login/alice").dispatch().await;
+    let cookie = response.cookies().get("rocket").unwrap().clone();
+    let invalidated = fairing(&client)
+        .deprovision_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(invalidated, 1);
+    assert_eq!(metrics.terminated(TerminationReason::Invalidated), 1);
+
+    // The client still sends the cookie of the invalidated session
+    client.post("/logout").cookie(cookie).dispatch().await;
+    assert_eq!(metrics.terminated(TerminationReason::Expired), 1);
+    assert_eq!(metrics.terminated(TerminationReason::Deleted), 0);
+    assert_eq!(metrics.total_terminated(), 2);
+}
+
+#[rocket::async_test]
+async fn records_stale_session_once() {
+    let clock = MockClock::new(OffsetDateTime::now_utc());
+    let metrics = SessionLifetimeMetrics::default();
+    let session_fairing = RocketFlexSession::<User>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .lifetime_metrics(metrics.clone())
+        .clock(clock.clone())
+        .build();
+    let rocket = rocket::build()
+        .attach(session_fairing)
+        .mount("/", routes![login, user]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    client.post("/login/alice").dispatch().await;
+    fairing(&client)
+        .deprovision_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+
+    // The stale cookie is removed after the first request
+    client.get("/user").dispatch().await;
+    assert!(client.cookies().get("rocket").is_none());
+    client.get("/user").dispatch().await;
+    assert_eq!(metrics.terminated(TerminationReason::Expired), 1);
+}
+
+#[rocket::async_test]
+async fn computes_storage_analytics() {
+    let clock = MockClock::new(OffsetDateTime::now_utc());
+    let metrics = SessionLifetimeMetrics::default();
+    let client = create_client(&clock, &metrics).await;
+    for user in ["alice", "alice", "bob"] {
+        client.post(format!("/login/{user}")).dispatch().await;
+    }
+
+    let storage = fairing(&client).indexed_storage().unwrap();
+    let since = OffsetDateTime::now_utc() - rocket::time::Duration::days(1);
+    let analytics = storage.session_analytics(since).await.unwrap();
+    assert_eq!(analytics.sessions, 3);
+    assert_eq!(analytics.identifiers, 2);
+    assert_eq!(analytics.active_identifiers, 2);
+    assert_eq!(analytics.sessions_per_identifier(), 1.5);
+    assert!(analytics.average_age.is_some());
+}
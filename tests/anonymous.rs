@@ -0,0 +1,188 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use rocket::{error::ErrorKind, local::blocking::Client, routes, Build, Rocket};
+use rocket_flex_session::{RocketFlexSession, Session, SessionIdentifier};
+
+#[derive(Clone, Debug, PartialEq)]
+struct VisitorSession {
+    user_id: Option<String>,
+    visits: u32,
+}
+
+impl SessionIdentifier for VisitorSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        self.user_id.clone()
+    }
+}
+
+#[post("/visit")]
+fn visit(mut session: Session<VisitorSession>) -> String {
+    session.tap_mut(|data| {
+        let data = data.get_or_insert(VisitorSession {
+            user_id: None,
+            visits: 0,
+        });
+        data.visits += 1;
+        data.visits.to_string()
+    })
+}
+
+#[post("/login")]
+fn login(mut session: Session<VisitorSession>) -> &'static str {
+    session.set(VisitorSession {
+        user_id: Some("user1".to_owned()),
+        visits: 0,
+    });
+    "Logged in"
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<VisitorSession>) {
+    session.tap_mut(|data| {
+        if let Some(data) = data {
+            data.user_id = None;
+        }
+    });
+}
+
+#[get("/ttl")]
+fn ttl(session: Session<VisitorSession>) -> String {
+    session.ttl().to_string()
+}
+
+fn create_rocket(persist_anonymous: bool) -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<VisitorSession>::builder()
+                .with_options(|opt| opt.persist_anonymous = persist_anonymous)
+                .anonymous_without_identifier()
+                .build(),
+        )
+        .mount("/", routes![visit, login, logout])
+}
+
+#[test]
+fn test_anonymous_sessions_persisted_by_default() {
+    let client = Client::tracked(create_rocket(true)).unwrap();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "2");
+}
+
+#[test]
+fn test_anonymous_sessions_not_persisted() {
+    let client = Client::tracked(create_rocket(false)).unwrap();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+
+    // Authenticated sessions are still persisted
+    client.post("/login").dispatch();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "2");
+
+    // A stored session that becomes anonymous is deleted
+    client.post("/logout").dispatch();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+}
+
+#[test]
+fn test_becoming_anonymous_calls_delete_hook() {
+    let deleted = Arc::new(AtomicUsize::new(0));
+    let hook_deleted = deleted.clone();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<VisitorSession>::builder()
+                .with_options(|opt| opt.persist_anonymous = false)
+                .anonymous_without_identifier()
+                .on_delete(move |_id, _data| {
+                    hook_deleted.fetch_add(1, Ordering::Relaxed);
+                    Box::pin(async {})
+                })
+                .build(),
+        )
+        .mount("/", routes![visit, login, logout]);
+    let client = Client::tracked(rocket).unwrap();
+
+    client.post("/login").dispatch();
+    client.post("/logout").dispatch();
+    assert_eq!(deleted.load(Ordering::Relaxed), 1);
+}
+
+fn create_rocket_with_sample_rate(rate: f64) -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<VisitorSession>::builder()
+                .with_options(|opt| opt.anonymous_sample_rate = Some(rate))
+                .anonymous_without_identifier()
+                .build(),
+        )
+        .mount("/", routes![visit, login])
+}
+
+#[test]
+fn test_anonymous_sessions_sampled() {
+    let client = Client::tracked(create_rocket_with_sample_rate(0.0)).unwrap();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+
+    let client = Client::tracked(create_rocket_with_sample_rate(1.0)).unwrap();
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "2");
+}
+
+#[test]
+fn test_anonymous_and_authenticated_ttl() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<VisitorSession>::builder()
+                .with_options(|opt| {
+                    opt.anonymous_ttl = Some(60);
+                    opt.authenticated_ttl = Some(3600);
+                })
+                .anonymous_without_identifier()
+                .build(),
+        )
+        .mount("/", routes![visit, login, ttl]);
+    let client = Client::tracked(rocket).unwrap();
+    let get_ttl = || -> u32 {
+        client
+            .get("/ttl")
+            .dispatch()
+            .into_string()
+            .unwrap()
+            .parse()
+            .unwrap()
+    };
+
+    client.post("/visit").dispatch();
+    assert!((58..=60).contains(&get_ttl()));
+
+    // Logging in switches to the authenticated TTL
+    client.post("/login").dispatch();
+    assert!((3598..=3600).contains(&get_ttl()));
+
+    // Further updates keep the current TTL
+    client.post("/visit").dispatch();
+    assert!((3598..=3600).contains(&get_ttl()));
+}
+
+#[test]
+fn test_anonymous_ttl_requires_predicate() {
+    let rocket = rocket::build().attach(
+        RocketFlexSession::<VisitorSession>::builder()
+            .with_options(|opt| opt.anonymous_ttl = Some(60))
+            .build(),
+    );
+    match Client::tracked(rocket) {
+        Ok(_) => panic!("should fail to launch without the is_anonymous setting"),
+        Err(e) => assert!(matches!(e.kind(), ErrorKind::FailedFairings(_))),
+    }
+}
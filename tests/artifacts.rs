@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{Arc, Mutex};
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        memory::{MemoryStorage, MemoryStorageIndexed},
+        SessionStorage,
+    },
+    Capability, RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("user".to_owned());
+    session.id().unwrap()
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+#[test]
+fn on_delete_hook() {
+    let deleted = Arc::new(Mutex::new(Vec::new()));
+    let deleted_clone = deleted.clone();
+    let fairing = RocketFlexSession::<String>::builder()
+        .on_delete(move |id, data| {
+            let deleted = deleted_clone.clone();
+            let entry = (id.to_owned(), data.to_owned());
+            Box::pin(async move { deleted.lock().unwrap().push(entry) })
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, logout]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let id = client.post("/login").dispatch().into_string().unwrap();
+    assert!(deleted.lock().unwrap().is_empty());
+    client.post("/logout").dispatch();
+    assert_eq!(*deleted.lock().unwrap(), vec![(id, "user".to_owned())]);
+}
+
+fn capability(session_id: &str) -> Capability {
+    Capability {
+        session_id: session_id.to_owned(),
+        scope: "download".to_owned(),
+    }
+}
+
+#[rocket::async_test]
+async fn delete_removes_capabilities() {
+    let storage = MemoryStorage::<String>::default();
+    storage
+        .save("session", "user".to_owned(), 60)
+        .await
+        .unwrap();
+    storage
+        .save_capability("token", capability("session"), 60)
+        .await
+        .unwrap();
+    assert!(storage.load_capability("token").await.is_ok());
+
+    storage.delete("session", "user".to_owned()).await.unwrap();
+    let result = storage.load_capability("token").await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
+
+#[derive(Clone)]
+struct UserSession(String);
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[rocket::async_test]
+async fn invalidate_removes_capabilities() {
+    let storage = MemoryStorageIndexed::<UserSession>::default();
+    for id in ["a", "b"] {
+        storage
+            .save(id, UserSession("alice".to_owned()), 60)
+            .await
+            .unwrap();
+        storage
+            .save_capability(&format!("token-{id}"), capability(id), 60)
+            .await
+            .unwrap();
+    }
+
+    let indexed = SessionStorage::<UserSession>::as_indexed_storage(&storage).unwrap();
+    indexed
+        .invalidate_sessions_by_identifier(&"alice".to_owned(), Some("a"))
+        .await
+        .unwrap();
+    assert!(storage.load_capability("token-a").await.is_ok());
+    let result = storage.load_capability("token-b").await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
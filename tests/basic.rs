@@ -78,6 +78,28 @@ fn tap_session_delete(mut session: Session<User>) -> &'static str {
     "Session deleted"
 }
 
+#[post("/session_state/<action>")]
+fn session_state(mut session: Session<User>, action: &str) -> String {
+    let state = |session: &Session<User>| {
+        format!(
+            "dirty={},new={},deleted={}",
+            session.is_dirty(),
+            session.is_new(),
+            session.was_deleted()
+        )
+    };
+    let before = state(&session);
+    match action {
+        "set" => session.set(User {
+            id: "123".to_string(),
+            name: "Test User".to_string(),
+        }),
+        "delete" => session.delete(),
+        _ => {}
+    }
+    format!("{before};{}", state(&session))
+}
+
 #[get("/get_hash_session/<key>")]
 fn get_hash_session(session: Session<SessionHash>, key: &str) -> String {
     match session.get_key(key) {
@@ -113,6 +135,7 @@ fn create_rocket() -> Rocket<Build> {
                 delete_session,
                 tap_session_update,
                 tap_session_delete,
+                session_state,
                 get_hash_session,
                 set_hash_session,
             ],
@@ -158,6 +181,35 @@ fn test_set_and_get_session() {
     assert_eq!(set_response.status(), Status::Ok);
 }
 
+#[test]
+fn test_session_state() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    let state = |action: &str| {
+        client
+            .post(format!("/session_state/{action}"))
+            .dispatch()
+            .into_string()
+            .unwrap()
+    };
+
+    assert_eq!(
+        state("set"),
+        "dirty=false,new=false,deleted=false;dirty=true,new=true,deleted=false"
+    );
+    assert_eq!(
+        state("none"),
+        "dirty=false,new=false,deleted=false;dirty=false,new=false,deleted=false"
+    );
+    assert_eq!(
+        state("set"),
+        "dirty=false,new=false,deleted=false;dirty=true,new=false,deleted=false"
+    );
+    assert_eq!(
+        state("delete"),
+        "dirty=false,new=false,deleted=false;dirty=false,new=false,deleted=true"
+    );
+}
+
 #[test]
 fn test_delete_session() {
     let client = Client::tracked(create_rocket()).unwrap();
@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/login")]
+fn login(mut session: Session<String>) {
+    session.set("user".to_owned());
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+#[post("/ticket?<scope>")]
+async fn ticket(session: Session<'_, String>, scope: &str) -> Result<String, Status> {
+    let token = session.create_capability(scope, 60).await.unwrap();
+    token.ok_or(Status::Unauthorized)
+}
+
+#[get("/download?<token>")]
+async fn download(session: Session<'_, String>, token: &str) -> Status {
+    match session.verify_capability(token, "download").await.unwrap() {
+        Some(_) => Status::Ok,
+        None => Status::Forbidden,
+    }
+}
+
+#[get("/ws?<token>")]
+async fn ws(session: Session<'_, String>, token: &str) -> Status {
+    match session.redeem_capability(token, "ws").await.unwrap() {
+        Some(_) => Status::Ok,
+        None => Status::Forbidden,
+    }
+}
+
+#[test]
+fn capability_tokens() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![login, logout, ticket, download, ws]);
+    let client = Client::tracked(rocket).unwrap();
+    let get = |uri: String| client.get(uri).dispatch().status();
+
+    assert_eq!(
+        client.post("/ticket?scope=download").dispatch().status(),
+        Status::Unauthorized
+    );
+    client.post("/login").dispatch();
+    let download_token = client
+        .post("/ticket?scope=download")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    let ws_token = client
+        .post("/ticket?scope=ws")
+        .dispatch()
+        .into_string()
+        .unwrap();
+
+    // Tokens are only valid for their scope
+    assert_eq!(get(format!("/download?token={download_token}")), Status::Ok);
+    assert_eq!(get(format!("/download?token={download_token}")), Status::Ok);
+    assert_eq!(
+        get(format!("/download?token={ws_token}")),
+        Status::Forbidden
+    );
+    assert_eq!(get("/download?token=unknown".to_owned()), Status::Forbidden);
+
+    // Single-use tokens
+    assert_eq!(get(format!("/ws?token={ws_token}")), Status::Ok);
+    assert_eq!(get(format!("/ws?token={ws_token}")), Status::Forbidden);
+
+    // Tokens are revoked when the session ends
+    client.post("/logout").dispatch();
+    assert_eq!(
+        get(format!("/download?token={download_token}")),
+        Status::Forbidden
+    );
+}
+
+#[rocket::async_test]
+async fn single_use_token_concurrent_redeem() {
+    use rocket::{futures::future::join_all, local::asynchronous::Client};
+
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![login, ticket, ws]);
+    let client = Client::tracked(rocket).await.unwrap();
+    client.post("/login").dispatch().await;
+    let token = client
+        .post("/ticket?scope=ws")
+        .dispatch()
+        .await
+        .into_string()
+        .await
+        .unwrap();
+
+    // Only one of the concurrent requests redeems the token
+    let uri = format!("/ws?token={token}");
+    let statuses =
+        join_all((0..8).map(|_| async { client.get(uri.clone()).dispatch().await.status() })).await;
+    let redeemed = statuses.iter().filter(|s| **s == Status::Ok).count();
+    assert_eq!(redeemed, 1);
+}
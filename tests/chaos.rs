@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use rocket::tokio::time::sleep;
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        chaos::{ChaosLayer, ChaosStats},
+        layer::StorageStack,
+        memory::MemoryStorage,
+        NoopSessionContext, SessionStorage,
+    },
+};
+
+#[rocket::async_test]
+async fn injects_errors() {
+    let chaos = ChaosLayer::builder().error_rate(1.0).build();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(chaos.clone())
+        .build();
+
+    let result = storage.save("id", "alice".to_owned(), 60).await;
+    assert!(matches!(result, Err(SessionError::Backend(_))));
+    assert!(result.unwrap_err().is_transient());
+
+    chaos.set_enabled(false);
+    storage.save("id", "alice".to_owned(), 60).await.unwrap();
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "alice");
+    assert_eq!(
+        chaos.stats(),
+        ChaosStats {
+            errors: 1,
+            ..Default::default()
+        }
+    );
+}
+
+#[rocket::async_test]
+async fn defers_writes() {
+    let chaos = ChaosLayer::builder()
+        .reorder_rate(1.0)
+        .reorder_delay(Duration::from_millis(50))
+        .build();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(chaos.clone())
+        .build();
+
+    storage.save("id", "alice".to_owned(), 60).await.unwrap();
+    let result = storage.load("id", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+
+    sleep(Duration::from_millis(200)).await;
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "alice");
+    assert_eq!(chaos.stats().reorders, 1);
+}
+
+#[rocket::async_test]
+async fn injects_latency() {
+    let chaos = ChaosLayer::builder()
+        .latency_rate(1.0)
+        .max_latency(Duration::from_millis(20))
+        .build();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(chaos.clone())
+        .build();
+
+    for _ in 0..5 {
+        let result = storage.load("id", None, &NoopSessionContext).await;
+        assert!(matches!(result, Err(SessionError::NotFound)));
+    }
+    assert_eq!(chaos.stats().delays, 5);
+}
+
+#[rocket::async_test]
+async fn seeded_faults_are_repeatable() {
+    let mut outcomes = Vec::new();
+    for _ in 0..2 {
+        let chaos = ChaosLayer::builder().error_rate(0.5).seed(7).build();
+        let storage = StorageStack::new(MemoryStorage::<String>::default())
+            .layer(chaos)
+            .build();
+        let mut failed = Vec::new();
+        for _ in 0..20 {
+            let result = storage.load("id", None, &NoopSessionContext).await;
+            failed.push(matches!(result, Err(SessionError::Backend(_))));
+        }
+        outcomes.push(failed);
+    }
+    assert_eq!(outcomes[0], outcomes[1]);
+    assert!(outcomes[0].contains(&true) && outcomes[0].contains(&false));
+}
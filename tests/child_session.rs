@@ -0,0 +1,93 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{
+    parent_session_id, storage::memory::MemoryStorageIndexed, RocketFlexSession, Session,
+    SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct UserSession {
+    user_id: String,
+    scope: String,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+#[post("/login")]
+fn login(mut session: Session<UserSession>) -> String {
+    session.set(UserSession {
+        user_id: "alice".to_owned(),
+        scope: "full".to_owned(),
+    });
+    session.id().unwrap()
+}
+
+#[post("/checkout")]
+async fn checkout(session: Session<'_, UserSession>) -> Result<String, Status> {
+    let data = UserSession {
+        user_id: "alice".to_owned(),
+        scope: "checkout".to_owned(),
+    };
+    match session.spawn_child(data, "checkout", 600).await.unwrap() {
+        Some(child_id) => Ok(child_id),
+        None => Err(Status::Unauthorized),
+    }
+}
+
+#[get("/checkout")]
+async fn get_checkout(session: Session<'_, UserSession>) -> Result<String, Status> {
+    match session.get_child("checkout").await.unwrap() {
+        Some((_id, data)) => Ok(data.scope),
+        None => Err(Status::NotFound),
+    }
+}
+
+#[post("/revoke")]
+async fn revoke(session: Session<'_, UserSession>) -> String {
+    let revoked = session.revoke_children().await.unwrap();
+    revoked.unwrap_or_default().to_string()
+}
+
+#[test]
+fn spawn_and_revoke_child_session() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<UserSession>::builder()
+                .storage(MemoryStorageIndexed::default())
+                .build(),
+        )
+        .mount("/", routes![login, checkout, get_checkout, revoke]);
+    let client = Client::tracked(rocket).unwrap();
+
+    // No parent session
+    assert_eq!(
+        client.post("/checkout").dispatch().status(),
+        Status::Unauthorized
+    );
+
+    let parent_id = client.post("/login").dispatch().into_string().unwrap();
+    let child_id = client.post("/checkout").dispatch().into_string().unwrap();
+    assert_eq!(parent_session_id(&child_id), Some(parent_id.as_str()));
+    assert_eq!(parent_session_id(&parent_id), None);
+    assert_eq!(
+        client.get("/checkout").dispatch().into_string().unwrap(),
+        "checkout"
+    );
+
+    assert_eq!(
+        client.post("/revoke").dispatch().into_string().unwrap(),
+        "1"
+    );
+    assert_eq!(
+        client.get("/checkout").dispatch().status(),
+        Status::NotFound
+    );
+}
@@ -0,0 +1,106 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::Status,
+    local::blocking::Client,
+    time::{Duration, OffsetDateTime},
+    State,
+};
+use rocket_flex_session::{
+    clock::MockClock, storage::cookie::CookieStorage, RocketFlexSession, Session,
+};
+
+#[post("/set_session")]
+fn set_session(mut session: Session<String>) -> &'static str {
+    session.set("active".to_owned());
+    "Session set"
+}
+
+#[get("/get_session")]
+fn get_session(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+#[get("/expires")]
+fn expires(session: Session<String>) -> String {
+    session.expires().unix_timestamp().to_string()
+}
+
+#[get("/expires_during_request")]
+fn expires_during_request(session: Session<String>, clock: &State<MockClock>) -> String {
+    let (expires, remaining) = (session.expires_at(), session.remaining_ttl());
+    clock.advance(Duration::seconds(10));
+    assert_eq!(session.expires_at(), expires);
+    format!("{remaining},{}", session.remaining_ttl())
+}
+
+fn create_client(clock: &MockClock) -> Client {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .storage(CookieStorage::builder().clock(clock.clone()).build())
+                .clock(clock.clone())
+                .with_options(|opt| opt.max_age = 60)
+                .build(),
+        )
+        .manage(clock.clone())
+        .mount(
+            "/",
+            routes![set_session, get_session, expires, expires_during_request],
+        );
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn session_expires_with_mock_clock() {
+    let start = OffsetDateTime::now_utc();
+    let clock = MockClock::new(start);
+    let client = create_client(&clock);
+
+    client.post("/set_session").dispatch();
+    let response = client.get("/expires").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        (start + Duration::seconds(60)).unix_timestamp().to_string()
+    );
+
+    clock.advance(Duration::seconds(59));
+    assert_eq!(client.get("/get_session").dispatch().status(), Status::Ok);
+
+    // Time travel past the expiration, without waiting
+    clock.advance(Duration::seconds(2));
+    assert_eq!(
+        client.get("/get_session").dispatch().status(),
+        Status::Unauthorized
+    );
+}
+
+#[test]
+fn mock_clock_can_be_set() {
+    let start = OffsetDateTime::now_utc();
+    let clock = MockClock::new(start);
+    let client = create_client(&clock);
+
+    client.post("/set_session").dispatch();
+    clock.set(start + Duration::days(1));
+    assert_eq!(
+        client.get("/get_session").dispatch().status(),
+        Status::Unauthorized
+    );
+
+    // Going back in time makes the cookie valid again
+    clock.set(start);
+    assert_eq!(client.get("/get_session").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn expiration_is_consistent_during_request() {
+    let clock = MockClock::new(OffsetDateTime::now_utc());
+    let client = create_client(&clock);
+
+    client.post("/set_session").dispatch();
+    clock.advance(Duration::seconds(20));
+    let response = client.get("/expires_during_request").dispatch();
+    assert_eq!(response.into_string().unwrap(), "40,30");
+}
@@ -0,0 +1,30 @@
+use rocket_flex_session::{
+    error::SessionError,
+    storage::compression::{compress, decompress, Compression},
+};
+
+#[test]
+fn compress_roundtrip() {
+    let payload = format!("{{\"items\":[{}]}}", "\"item\",".repeat(1000));
+    for compression in [Compression::Zstd { level: 3 }, Compression::Lz4] {
+        let compressed = compress(payload.as_bytes(), compression).unwrap();
+        assert!(compressed.len() < payload.len() / 10);
+        assert_eq!(compressed[0], 0xFF);
+        assert_eq!(decompress(&compressed).unwrap(), payload.as_bytes());
+    }
+}
+
+#[test]
+fn uncompressed_payloads_are_unchanged() {
+    let payload = b"{\"user_id\":\"123\"}";
+    assert_eq!(decompress(payload).unwrap(), &payload[..]);
+    assert_eq!(decompress(b"").unwrap(), &b""[..]);
+}
+
+#[test]
+fn invalid_compressed_payloads() {
+    let result = decompress(&[0xFF, b'z', 1, 2, 3]);
+    assert!(matches!(result, Err(SessionError::Parsing(_))));
+    let result = decompress(&[0xFF, b'?', 1, 2, 3]);
+    assert!(matches!(result, Err(SessionError::Unsupported(_))));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        conformance, memory::MemoryStorageIndexed, sqlx::SessionSqlx, sqlx::SqlxSqliteStorage,
+    },
+    SessionIdentifier,
+};
+
+use crate::common::{setup_sqlite, teardown_sqlite};
+
+#[derive(Clone, Debug, PartialEq)]
+struct TestSession {
+    user_id: String,
+    counter: usize,
+}
+
+impl SessionIdentifier for TestSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+impl SessionSqlx<sqlx::Sqlite> for TestSession {
+    type Error = SessionError;
+    type Data = String;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(format!("{}:{}", self.user_id, self.counter))
+    }
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        let (user_id, counter) = value
+            .split_once(':')
+            .ok_or(SessionError::Parsing("missing separator".into()))?;
+        Ok(TestSession {
+            user_id: user_id.to_owned(),
+            counter: counter
+                .parse()
+                .map_err(|e| SessionError::Parsing(Box::new(e)))?,
+        })
+    }
+}
+
+fn sample(user_id: &str, counter: usize) -> TestSession {
+    TestSession {
+        user_id: user_id.to_owned(),
+        counter,
+    }
+}
+
+#[rocket::async_test]
+async fn memory_storage_conforms() {
+    let storage = MemoryStorageIndexed::<TestSession>::default();
+    conformance::run_storage_tests(&storage, |i| sample("user", i)).await;
+    conformance::run_indexed_storage_tests(
+        &storage,
+        |user_id: &String, i| sample(user_id, i),
+        ["user1".to_owned(), "user2".to_owned()],
+    )
+    .await;
+}
+
+#[rocket::async_test]
+async fn sqlite_storage_conforms() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .build();
+    conformance::run_storage_tests(&storage, |i| sample("user", i)).await;
+    conformance::run_indexed_storage_tests(
+        &storage,
+        |user_id: &String, i| sample(user_id, i),
+        ["user1".to_owned(), "user2".to_owned()],
+    )
+    .await;
+    teardown_sqlite(pool).await;
+}
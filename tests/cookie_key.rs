@@ -0,0 +1,71 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{edge::EdgeVerifier, RocketFlexSession, Session, SessionCookieKey};
+
+const SESSION_SECRET: [u8; 64] = [9; 64];
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("user".to_owned());
+    session.id().unwrap()
+}
+
+#[get("/user")]
+fn user(session: Session<String>) -> Option<String> {
+    session.tap(|data| data.cloned())
+}
+
+fn client(cookie_key: SessionCookieKey) -> Client {
+    let fairing = RocketFlexSession::<String>::builder()
+        .with_options(|opt| opt.cookie_key = cookie_key)
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, user]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn private_cookie_with_dedicated_key() {
+    let client = client(SessionCookieKey::private(&SESSION_SECRET).unwrap());
+    let response = client.post("/login").dispatch();
+    let session_id = response.into_string().unwrap();
+
+    // The cookie isn't encrypted with Rocket's secret key
+    assert!(client.cookies().get_private("rocket").is_none());
+    let cookies = client.cookies();
+    let cookie = cookies.get("rocket").unwrap();
+    let verifier = EdgeVerifier::new(&SESSION_SECRET, "rocket").unwrap();
+    assert_eq!(verifier.verify(cookie.value()), Some(session_id));
+
+    let response = client.get("/user").dispatch();
+    assert_eq!(response.into_string().unwrap(), "user");
+}
+
+#[test]
+fn signed_cookie_with_dedicated_key() {
+    let client = client(SessionCookieKey::signed(&SESSION_SECRET).unwrap());
+    let response = client.post("/login").dispatch();
+    let session_id = response.into_string().unwrap();
+
+    // The session ID is readable, but the cookie can't be tampered with
+    let cookies = client.cookies();
+    let cookie = cookies.get("rocket").unwrap();
+    assert!(cookie.value().ends_with(&session_id));
+    let response = client.get("/user").dispatch();
+    assert_eq!(response.into_string().unwrap(), "user");
+
+    let response = client
+        .get("/user")
+        .cookie(("rocket", format!("invalid-signature{session_id}")))
+        .dispatch();
+    assert_eq!(response.status(), rocket::http::Status::NotFound);
+}
+
+#[test]
+fn dedicated_key_must_be_long_enough() {
+    assert!(SessionCookieKey::private(&[1; 16]).is_none());
+    assert!(SessionCookieKey::signed(&[1; 32]).is_some());
+}
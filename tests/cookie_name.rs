@@ -0,0 +1,62 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{error::ErrorKind, local::asynchronous::Client, Config};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[derive(Clone)]
+struct MySession;
+
+#[derive(Clone)]
+struct AdminSession;
+
+#[post("/login")]
+fn login(mut session: Session<MySession>) {
+    session.set(MySession);
+}
+
+#[rocket::async_test]
+async fn duplicate_cookie_names_fail_to_launch() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<MySession>::default())
+        .attach(RocketFlexSession::<AdminSession>::default());
+    match rocket.ignite().await {
+        Ok(_) => panic!("should fail to launch with duplicate cookie names"),
+        Err(e) => assert!(matches!(e.kind(), ErrorKind::FailedFairings(_))),
+    }
+
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<MySession>::default())
+        .attach(
+            RocketFlexSession::<AdminSession>::builder()
+                .with_options(|opt| opt.cookie_name = "admin".to_owned())
+                .build(),
+        );
+    if let Err(e) = rocket.ignite().await {
+        panic!("failed to launch: {:?}", e.kind());
+    }
+}
+
+#[rocket::async_test]
+async fn cookie_name_template() {
+    let template = |opt: &mut rocket_flex_session::RocketFlexSessionOptions| {
+        opt.cookie_name = "{type}_{profile}".to_owned()
+    };
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<MySession>::builder()
+                .with_options(template)
+                .build(),
+        )
+        .attach(
+            RocketFlexSession::<AdminSession>::builder()
+                .with_options(template)
+                .build(),
+        )
+        .mount("/", routes![login]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let response = client.post("/login").dispatch().await;
+    let expected_name = format!("my_session_{}", Config::DEFAULT_PROFILE.as_str());
+    assert!(response.cookies().get(&expected_name).is_some());
+}
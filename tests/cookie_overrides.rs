@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{local::blocking::Client, time::Duration};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+const REMEMBER_ME: u32 = 30 * 24 * 60 * 60;
+
+#[post("/login?<remember>")]
+fn login(mut session: Session<String>, remember: bool) {
+    session.set("user".to_owned());
+    if remember {
+        session.with_cookie_overrides(|cookie| {
+            cookie.max_age = Some(REMEMBER_ME);
+            cookie.secure = Some(false);
+        });
+    }
+}
+
+#[post("/remember")]
+fn remember(mut session: Session<String>) {
+    session.with_cookie_overrides(|cookie| cookie.max_age = Some(REMEMBER_ME));
+}
+
+fn create_client() -> Client {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| opt.max_age = 3600)
+                .build(),
+        )
+        .mount("/", routes![login, remember]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn overrides_apply_to_response_only() {
+    let client = create_client();
+
+    let response = client.post("/login?remember=true").dispatch();
+    let cookie = response.cookies().get("rocket").unwrap();
+    assert_eq!(
+        cookie.max_age(),
+        Some(Duration::seconds(REMEMBER_ME.into()))
+    );
+    // The `Secure` attribute is left out of the response cookie
+    assert_eq!(cookie.secure(), None);
+
+    // Configured options are used for other responses
+    let client = create_client();
+    let response = client.post("/login?remember=false").dispatch();
+    let cookie = response.cookies().get("rocket").unwrap();
+    assert_eq!(cookie.max_age(), Some(Duration::seconds(3600)));
+    assert_eq!(cookie.secure(), Some(true));
+}
+
+#[test]
+fn overrides_reissue_existing_session_cookie() {
+    let client = create_client();
+    client.post("/login?remember=false").dispatch();
+    let id = client
+        .cookies()
+        .get_private("rocket")
+        .unwrap()
+        .value()
+        .to_owned();
+
+    let response = client.post("/remember").dispatch();
+    let cookie = response.cookies().get_private("rocket").unwrap();
+    assert_eq!(cookie.value(), id);
+    assert_eq!(
+        cookie.max_age(),
+        Some(Duration::seconds(REMEMBER_ME.into()))
+    );
+
+    // Without a session, no cookie is added
+    let client = create_client();
+    let response = client.post("/remember").dispatch();
+    assert!(response.cookies().get("rocket").is_none());
+}
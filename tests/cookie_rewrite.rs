@@ -0,0 +1,41 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/visit")]
+fn visit(mut session: Session<u32>) -> String {
+    for _ in 0..3 {
+        session.tap_mut(|visits| *visits.get_or_insert(0) += 1);
+    }
+    session.get().unwrap().to_string()
+}
+
+#[get("/visits")]
+fn visits(session: Session<u32>) -> Result<String, Status> {
+    session
+        .get()
+        .map(|v| v.to_string())
+        .ok_or(Status::Unauthorized)
+}
+
+#[test]
+fn unchanged_cookie_added_once() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<u32>::builder()
+                .with_options(|opt| opt.rewrite_unchanged_cookie = false)
+                .build(),
+        )
+        .mount("/", routes![visit, visits]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.post("/visit").dispatch();
+    let set_cookies: Vec<_> = response.headers().get("Set-Cookie").collect();
+    assert_eq!(set_cookies.len(), 1);
+    assert!(set_cookies[0].starts_with("rocket="));
+    assert_eq!(response.into_string().unwrap(), "3");
+
+    assert_eq!(client.get("/visits").dispatch().into_string().unwrap(), "3");
+}
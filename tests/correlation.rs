@@ -0,0 +1,75 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{LogIdFormat, RocketFlexSession, Session, SessionCorrelationId};
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("alice".to_owned());
+    session.id().unwrap()
+}
+
+#[get("/user")]
+fn user(session: Session<String>, correlation_id: CorrelationId) -> String {
+    format!("{}:{}", session.get().unwrap_or_default(), correlation_id.0)
+}
+
+/// Reads the correlation ID set by the request guard
+struct CorrelationId(String);
+
+#[rocket::async_trait]
+impl<'r> rocket::request::FromRequest<'r> for CorrelationId {
+    type Error = ();
+
+    async fn from_request(req: &'r rocket::Request<'_>) -> rocket::request::Outcome<Self, ()> {
+        let id = SessionCorrelationId::get(req).map(|id| id.to_string());
+        rocket::request::Outcome::Success(CorrelationId(id.unwrap_or_default()))
+    }
+}
+
+fn client() -> Client {
+    let fairing = RocketFlexSession::<String>::builder()
+        .with_options(|opt| {
+            opt.correlation_header = Some("X-Session-Hash".to_owned());
+            opt.correlation_local_cache = true;
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, user]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn adds_hashed_id_header() {
+    let client = client();
+    let response = client.post("/login").dispatch();
+    let header = response
+        .headers()
+        .get_one("X-Session-Hash")
+        .unwrap()
+        .to_owned();
+    let id = response.into_string().unwrap();
+    assert_eq!(header, LogIdFormat::Hashed.format(&id));
+    assert!(!header.contains(&id));
+}
+
+#[test]
+fn stores_hashed_id_in_local_cache() {
+    let client = client();
+    let id = client.post("/login").dispatch().into_string().unwrap();
+    let response = client.get("/user").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        format!("alice:{}", SessionCorrelationId::new(&id))
+    );
+}
+
+#[test]
+fn no_header_without_session() {
+    let client = client();
+    let response = client.get("/user").dispatch();
+    assert!(response.headers().get_one("X-Session-Hash").is_none());
+    assert_eq!(response.into_string().unwrap(), ":");
+}
@@ -0,0 +1,117 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::Status,
+    local::blocking::Client,
+    serde::{Deserialize, Serialize},
+    time::OffsetDateTime,
+};
+use rocket_flex_session::{
+    storage::{memory::MemoryStorageIndexed, SessionStorage, SessionStorageIndexed},
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct UserSession {
+    user_id: String,
+    visits: u32,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+#[post("/login/<user_id>")]
+fn login(mut session: Session<UserSession>, user_id: String) -> String {
+    session.set(UserSession { user_id, visits: 0 });
+    created_at(&session)
+}
+
+#[get("/visit")]
+fn visit(mut session: Session<UserSession>) -> Result<String, Status> {
+    session
+        .tap_mut(|data| {
+            let data = data.as_mut()?;
+            data.visits += 1;
+            Some(())
+        })
+        .ok_or(Status::Unauthorized)?;
+    Ok(created_at(&session))
+}
+
+fn created_at(session: &Session<UserSession>) -> String {
+    let created_at = session.created_at().expect("should have creation time");
+    created_at.unix_timestamp_nanos().to_string()
+}
+
+#[test]
+fn created_at_persists_across_requests() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<UserSession>::builder()
+                .storage(MemoryStorageIndexed::default())
+                .build(),
+        )
+        .mount("/", routes![login, visit]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let login_time: i128 = client
+        .post("/login/user1")
+        .dispatch()
+        .into_string()
+        .unwrap()
+        .parse()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    // The storage records the creation time when the new session is saved
+    let created_at = client.get("/visit").dispatch().into_string().unwrap();
+    assert!(created_at.parse::<i128>().unwrap() >= login_time);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let response = client.get("/visit").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), created_at);
+}
+
+#[rocket::async_test]
+async fn created_at_in_indexed_listings() {
+    let storage = MemoryStorageIndexed::<UserSession>::default();
+    let before = OffsetDateTime::now_utc();
+    let data = UserSession {
+        user_id: "user1".to_owned(),
+        visits: 0,
+    };
+    storage.save("session1", data.clone(), 60).await.unwrap();
+    storage.save("session2", data.clone(), 60).await.unwrap();
+
+    let metadata = storage
+        .get_session_metadata_by_identifier(&"user1".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(metadata.len(), 2);
+    let created_at = |id: &str| {
+        let (_, metadata) = metadata
+            .iter()
+            .find(|(session_id, _)| session_id == id)
+            .unwrap();
+        metadata.created_at.expect("should have creation time")
+    };
+    assert!(created_at("session1") >= before);
+
+    // Saving again keeps the original creation time
+    let original = created_at("session2");
+    storage.save("session2", data, 60).await.unwrap();
+    let metadata = storage
+        .get_session_metadata_by_identifier(&"user1".to_owned())
+        .await
+        .unwrap();
+    let (_, updated) = metadata.iter().find(|(id, _)| id == "session2").unwrap();
+    assert_eq!(updated.created_at, Some(original));
+}
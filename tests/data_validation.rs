@@ -0,0 +1,67 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/set/<value>")]
+fn set(mut session: Session<String>, value: &str) {
+    session.set(value.to_owned());
+}
+
+#[get("/get")]
+fn get(session: Session<String>) -> String {
+    match session.get() {
+        Some(data) => data,
+        None => session.error().map(|e| e.to_string()).unwrap_or_default(),
+    }
+}
+
+fn create_client() -> Client {
+    let fairing = RocketFlexSession::<String>::builder()
+        .validate(|data| match data.starts_with("legacy") {
+            true => Err(format!("legacy data: {data}").into()),
+            false => Ok(()),
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![set, get]);
+    Client::tracked(rocket).unwrap()
+}
+
+fn invalid_session_count(client: &Client) -> u64 {
+    client
+        .rocket()
+        .state::<RocketFlexSession<String>>()
+        .unwrap()
+        .invalid_session_count()
+}
+
+#[test]
+fn valid_session() {
+    let client = create_client();
+    client.post("/set/current").dispatch();
+
+    let response = client.get("/get").dispatch();
+    assert_eq!(response.into_string().unwrap(), "current");
+    assert_eq!(invalid_session_count(&client), 0);
+}
+
+#[test]
+fn invalid_session_is_deleted() {
+    let client = create_client();
+    client.post("/set/legacy-user").dispatch();
+
+    let response = client.get("/get").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        "Invalid session data: legacy data: legacy-user"
+    );
+    assert_eq!(invalid_session_count(&client), 1);
+
+    // Session was deleted from storage, so it's not validated again
+    let response = client.get("/get").dispatch();
+    assert_eq!(response.into_string().unwrap(), "Session not found");
+    assert_eq!(invalid_session_count(&client), 1);
+}
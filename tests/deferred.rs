@@ -0,0 +1,95 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{
+    deferred::{drain_deferred, DeferredSession},
+    storage::{memory::MemoryStorageIndexed, SessionStorage},
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Action {
+    SendEmail(String),
+    SyncProfile,
+}
+
+type UserSession = DeferredSession<User, Action>;
+
+#[post("/signup/<name>")]
+fn signup(mut session: Session<UserSession>, name: &str) -> String {
+    let deferred_without_session = session.defer(Action::SyncProfile);
+    session.set(DeferredSession::new(User(name.to_owned())));
+    session.defer(Action::SendEmail(format!("{name}@example.com")));
+    session.defer(Action::SyncProfile);
+    deferred_without_session.to_string()
+}
+
+#[post("/drain")]
+fn drain(mut session: Session<UserSession>) -> String {
+    format!("{:?}", session.take_deferred())
+}
+
+#[test]
+fn defer_and_take_actions() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<UserSession>::default())
+        .mount("/", routes![signup, drain]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.post("/signup/alice").dispatch();
+    assert_eq!(response.into_string().unwrap(), "false");
+
+    // Actions are saved with the session, and only drained once
+    let response = client.post("/drain").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        r#"[SendEmail("alice@example.com"), SyncProfile]"#
+    );
+    let response = client.post("/drain").dispatch();
+    assert_eq!(response.into_string().unwrap(), "[]");
+}
+
+#[rocket::async_test]
+async fn drain_actions_of_identifier() {
+    let storage = MemoryStorageIndexed::<UserSession>::default();
+    let session = DeferredSession {
+        data: User("alice".to_owned()),
+        actions: vec![Action::SyncProfile],
+    };
+    storage.save("id1", session.clone(), 60).await.unwrap();
+    storage
+        .save("id2", DeferredSession::new(User("alice".to_owned())), 60)
+        .await
+        .unwrap();
+    storage.save("id3", session, 60).await.unwrap();
+
+    let mut drained = drain_deferred::<User, Action>(&storage, &"alice".to_owned())
+        .await
+        .unwrap();
+    drained.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        drained,
+        vec![
+            ("id1".to_owned(), Action::SyncProfile),
+            ("id3".to_owned(), Action::SyncProfile)
+        ]
+    );
+    assert!(
+        drain_deferred::<User, Action>(&storage, &"alice".to_owned())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
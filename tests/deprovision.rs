@@ -0,0 +1,67 @@
+use std::sync::{Arc, Mutex};
+
+use rocket_flex_session::{
+    audit::AuditEvent, error::SessionError, storage::memory::MemoryStorageIndexed,
+    RocketFlexSession, SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct UserSession(String);
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[rocket::async_test]
+async fn deprovision_identifier() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let fairing = RocketFlexSession::<UserSession>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .audit({
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event)
+        })
+        .build();
+    let storage = fairing.indexed_storage().expect("should support indexing");
+    for (id, user) in [("id1", "alice"), ("id2", "alice"), ("id3", "bob")] {
+        storage
+            .save(id, UserSession(user.to_owned()), 3600)
+            .await
+            .unwrap();
+    }
+
+    let count = fairing
+        .deprovision_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+    let alice_ids = storage
+        .get_session_ids_by_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert!(alice_ids.is_empty());
+    let bob_ids = storage
+        .get_session_ids_by_identifier(&"bob".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(bob_ids, vec!["id3"]);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![AuditEvent::Deprovisioned {
+            identifier: "alice".to_owned(),
+            sessions_invalidated: 2,
+        }]
+    );
+}
+
+#[rocket::async_test]
+async fn deprovision_requires_indexing() {
+    let fairing = RocketFlexSession::<UserSession>::default();
+    let result = fairing.deprovision_identifier(&"alice".to_owned()).await;
+    assert!(matches!(result, Err(SessionError::NonIndexedStorage)));
+}
@@ -0,0 +1,138 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rocket::{
+    http::Status,
+    local::blocking::Client,
+    request::{FromRequest, Outcome},
+    Request, State,
+};
+use rocket_flex_session::{RocketFlexSession, Session, SessionDerived};
+
+/// Number of times the user was looked up
+#[derive(Default)]
+struct Lookups(AtomicUsize);
+
+#[derive(Clone)]
+struct CurrentUser {
+    name: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CurrentUser {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        let session = req.guard::<Session<String>>().await.unwrap();
+        let lookups = req.guard::<&State<Lookups>>().await.unwrap();
+        let user = SessionDerived::get_or_derive(req, &session, |name| async move {
+            lookups.0.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, ()>(CurrentUser { name })
+        });
+        match user.await {
+            Ok(Some(user)) => Outcome::Success(user),
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+/// Another guard that needs the current user
+struct Admin(CurrentUser);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        match req.guard::<CurrentUser>().await {
+            Outcome::Success(user) if user.name == "admin" => Outcome::Success(Admin(user)),
+            _ => Outcome::Error((Status::Forbidden, ())),
+        }
+    }
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: &str) {
+    session.set(name.to_owned());
+}
+
+#[get("/admin")]
+fn admin(user: CurrentUser, admin: Admin) -> String {
+    format!("{} {}", user.name, admin.0.name)
+}
+
+#[post("/rename")]
+fn rename(renamed: Renamed) -> String {
+    renamed.0
+}
+
+/// Renames the user to "bob", and gets the current user before and after invalidating it
+struct Renamed(String);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Renamed {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+        let user = req.guard::<CurrentUser>().await.succeeded().unwrap();
+        let mut session = req.guard::<Session<String>>().await.unwrap();
+        session.set("bob".to_owned());
+        let cached = current_user(req, &session).await;
+        SessionDerived::<CurrentUser>::invalidate(req, &session.id().unwrap());
+        let derived = current_user(req, &session).await;
+        Outcome::Success(Renamed(format!("{} {cached} {derived}", user.name)))
+    }
+}
+
+async fn current_user(req: &Request<'_>, session: &Session<'_, String>) -> String {
+    let user = SessionDerived::get_or_derive(req, session, |name| async move {
+        Ok::<_, ()>(CurrentUser { name })
+    });
+    user.await.unwrap().unwrap().name
+}
+
+fn client() -> Client {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .manage(Lookups::default())
+        .mount("/", routes![login, admin, rename]);
+    Client::tracked(rocket).unwrap()
+}
+
+fn lookups(client: &Client) -> usize {
+    client
+        .rocket()
+        .state::<Lookups>()
+        .unwrap()
+        .0
+        .load(Ordering::Relaxed)
+}
+
+#[test]
+fn derives_once_per_request() {
+    let client = client();
+    assert_eq!(
+        client.get("/admin").dispatch().status(),
+        Status::Unauthorized
+    );
+    assert_eq!(lookups(&client), 0);
+
+    client.post("/login/admin").dispatch();
+    let response = client.get("/admin").dispatch();
+    assert_eq!(response.into_string().unwrap(), "admin admin");
+    assert_eq!(lookups(&client), 1);
+
+    // Each request derives the object again
+    client.get("/admin").dispatch();
+    assert_eq!(lookups(&client), 2);
+}
+
+#[test]
+fn invalidates_derived_object() {
+    let client = client();
+    client.post("/login/admin").dispatch();
+    let response = client.post("/rename").dispatch();
+    assert_eq!(response.into_string().unwrap(), "admin admin bob");
+}
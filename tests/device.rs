@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Cookie, Header},
+    local::blocking::Client,
+};
+use rocket_flex_session::{
+    device::{DeviceInfo, DeviceSession, DeviceType},
+    storage::memory::MemoryStorageIndexed,
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+const CHROME_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36";
+const SAFARI_IPHONE: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 17_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.5 Mobile/15E148 Safari/604.1";
+
+#[test]
+fn parse_user_agents() {
+    let cases = [
+        (CHROME_WINDOWS, DeviceType::Desktop, "Chrome on Windows"),
+        (SAFARI_IPHONE, DeviceType::Mobile, "Safari on iOS"),
+        (
+            "Mozilla/5.0 (Macintosh; Intel Mac OS X 14.5; rv:127.0) Gecko/20100101 Firefox/127.0",
+            DeviceType::Desktop,
+            "Firefox on macOS",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Mobile Safari/537.36",
+            DeviceType::Mobile,
+            "Chrome on Android",
+        ),
+        (
+            "Mozilla/5.0 (Linux; Android 13; SM-X710) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36",
+            DeviceType::Tablet,
+            "Chrome on Android",
+        ),
+        (
+            "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/126.0.0.0 Safari/537.36 Edg/126.0.0.0",
+            DeviceType::Desktop,
+            "Edge on Windows",
+        ),
+        (
+            "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)",
+            DeviceType::Bot,
+            "Unknown device",
+        ),
+        ("something else", DeviceType::Unknown, "Unknown device"),
+    ];
+    for (user_agent, device_type, label) in cases {
+        let device = DeviceInfo::parse(user_agent);
+        assert_eq!(device.device_type, device_type, "{user_agent}");
+        assert_eq!(device.label(), label, "{user_agent}");
+    }
+}
+
+#[derive(Clone)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[post("/login/<user>")]
+fn login(device: DeviceInfo, mut session: Session<DeviceSession<User>>, user: &str) -> String {
+    session.set(DeviceSession::new(device, User(user.to_owned())));
+    session.id().unwrap()
+}
+
+#[get("/devices")]
+async fn devices(session: Session<'_, DeviceSession<User>>) -> String {
+    let devices = session.get_active_devices().await.unwrap().unwrap();
+    devices
+        .iter()
+        .map(|d| {
+            format!(
+                "{}:{}:{}",
+                d.device.label(),
+                d.session_ids.len(),
+                d.is_current
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[test]
+fn active_devices() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<DeviceSession<User>>::builder()
+                .storage(MemoryStorageIndexed::default())
+                .build(),
+        )
+        .mount("/", routes![login, devices]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let login = |user_agent: &'static str, user: &str| {
+        client
+            .post(format!("/login/{user}"))
+            .header(Header::new("User-Agent", user_agent))
+            .dispatch()
+            .into_string()
+            .unwrap()
+    };
+    login(CHROME_WINDOWS, "alice");
+    login(SAFARI_IPHONE, "alice");
+    login(SAFARI_IPHONE, "bob");
+    let current_id = login(CHROME_WINDOWS, "alice");
+
+    let response = client
+        .get("/devices")
+        .private_cookie(Cookie::new("rocket", current_id))
+        .dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        "Chrome on Windows:2:true,Safari on iOS:1:false"
+    );
+}
@@ -0,0 +1,75 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{config::SecretKey, local::blocking::Client, Config};
+use rocket_flex_session::{edge::EdgeVerifier, RocketFlexSession, Session, SessionCookieKey};
+
+const SECRET_KEY: [u8; 64] = [42; 64];
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("user".to_owned());
+    session.id().unwrap()
+}
+
+#[test]
+fn verify_session_cookie_outside_rocket() {
+    let config = Config {
+        secret_key: SecretKey::from(&SECRET_KEY),
+        ..Config::debug_default()
+    };
+    let rocket = rocket::custom(config)
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![login]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let response = client.post("/login").dispatch();
+    let set_cookie = response.headers().get_one("Set-Cookie").unwrap().to_owned();
+    let session_id = response.into_string().unwrap();
+    let encoded_value = set_cookie
+        .split(';')
+        .next()
+        .and_then(|cookie| cookie.strip_prefix("rocket="))
+        .unwrap();
+
+    let verifier = EdgeVerifier::new(&SECRET_KEY, "rocket").unwrap();
+    let header = format!("theme=dark; rocket={encoded_value}");
+    assert_eq!(verifier.verify_cookie_header(&header), Some(session_id));
+
+    // Cookies encrypted with another key, or with another name, are rejected
+    let other_verifier = EdgeVerifier::new(&[1; 64], "rocket").unwrap();
+    assert_eq!(other_verifier.verify_cookie_header(&header), None);
+    let other_name = EdgeVerifier::new(&SECRET_KEY, "session").unwrap();
+    assert_eq!(other_name.verify_cookie_header(&header), None);
+    assert_eq!(verifier.verify_cookie_header("rocket=invalid"), None);
+    assert!(EdgeVerifier::new(&[1; 16], "rocket").is_none());
+}
+
+#[test]
+fn verify_signed_session_cookie() {
+    let cookie_key = SessionCookieKey::signed(&SECRET_KEY).unwrap();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| opt.cookie_key = cookie_key.clone())
+                .build(),
+        )
+        .mount("/", routes![login]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let response = client.post("/login").dispatch();
+    let set_cookie = response.headers().get_one("Set-Cookie").unwrap().to_owned();
+    let session_id = response.into_string().unwrap();
+    let header = set_cookie.split(';').next().unwrap().to_owned();
+
+    let verifier = EdgeVerifier::with_cookie_key(cookie_key, "rocket").unwrap();
+    assert_eq!(verifier.verify_cookie_header(&header), Some(session_id));
+
+    // Signed with another key, or encrypted with the same secret
+    let other_key = SessionCookieKey::signed(&[1; 64]).unwrap();
+    let other_verifier = EdgeVerifier::with_cookie_key(other_key, "rocket").unwrap();
+    assert_eq!(other_verifier.verify_cookie_header(&header), None);
+    let private_verifier = EdgeVerifier::new(&SECRET_KEY, "rocket").unwrap();
+    assert_eq!(private_verifier.verify_cookie_header(&header), None);
+    assert!(EdgeVerifier::with_cookie_key(SessionCookieKey::RocketSecretKey, "rocket").is_none());
+}
@@ -0,0 +1,51 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Header, local::blocking::Client};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[derive(Clone, Debug)]
+struct UserSession {
+    region: Option<String>,
+}
+
+#[post("/login")]
+fn login(mut session: Session<UserSession>) {
+    session.set(UserSession { region: None });
+}
+
+#[get("/region")]
+fn region(session: Session<UserSession>) -> String {
+    session
+        .get()
+        .and_then(|data| data.region)
+        .unwrap_or_else(|| "none".to_owned())
+}
+
+#[test]
+fn enrich_session_before_save() {
+    let fairing = RocketFlexSession::<UserSession>::builder()
+        .enrich(|req, data| {
+            Box::pin(async move {
+                let region = req.headers().get_one("X-Region").map(str::to_owned);
+                data.region = region.or(data.region.take());
+            })
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, region]);
+    let client = Client::tracked(rocket).unwrap();
+
+    client
+        .post("/login")
+        .header(Header::new("X-Region", "eu-west"))
+        .dispatch();
+    let response = client
+        .get("/region")
+        .header(Header::new("X-Region", "us-east"))
+        .dispatch();
+
+    // Only called when the session is saved
+    assert_eq!(response.into_string().unwrap(), "eu-west");
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use rocket::serde::{
+    json::{serde_json, Value},
+    Deserialize, Serialize,
+};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{
+        compression::Compression,
+        envelope::{
+            envelope_version, EnvelopeCodec, EnvelopeFormat, ENVELOPE_VERSION, JSON_FORMAT_ID,
+            TAGGED_ENVELOPE_VERSION,
+        },
+        erased::{ErasedPayload, ErasedSessionStorage, SessionCodec},
+        memory::MemoryStorage,
+        NoopSessionContext, SessionStorage,
+    },
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct User {
+    id: u32,
+    name: String,
+}
+
+fn user() -> User {
+    User {
+        id: 1,
+        name: "alice".to_owned(),
+    }
+}
+
+#[test]
+fn encode_with_version_byte() {
+    let codec = EnvelopeCodec::default();
+    let encoded = codec.encode(&user()).unwrap();
+    assert_eq!(encoded[0], ENVELOPE_VERSION);
+    assert_eq!(&encoded[1..], br#"{"id":1,"name":"alice"}"#);
+    assert_eq!(envelope_version(&encoded), Some(1));
+    assert_eq!(codec.decode::<User>(&encoded).unwrap(), user());
+
+    let encoded_str = codec.encode_str(&user()).unwrap();
+    assert_eq!(encoded_str.as_bytes(), encoded.as_slice());
+    assert_eq!(codec.decode_str::<User>(&encoded_str).unwrap(), user());
+}
+
+#[test]
+fn decode_unversioned_and_unknown_versions() {
+    let legacy = r#"{"id":1,"name":"alice"}"#;
+    assert_eq!(envelope_version(legacy.as_bytes()), None);
+    assert_eq!(
+        EnvelopeCodec::default().decode_str::<User>(legacy).unwrap(),
+        user()
+    );
+
+    let strict = EnvelopeCodec::builder().accept_unversioned(false).build();
+    assert!(matches!(
+        strict.decode_str::<User>(legacy),
+        Err(SessionError::InvalidData)
+    ));
+
+    let mut future = EnvelopeCodec::default().encode(&user()).unwrap();
+    future[0] = 3;
+    assert!(matches!(
+        strict.decode::<User>(&future),
+        Err(SessionError::Unsupported(_))
+    ));
+    assert!(matches!(
+        strict.decode::<User>(b"\x01not json"),
+        Err(SessionError::Parsing(_))
+    ));
+}
+
+/// JSON with the bytes reversed, standing in for a binary format like MessagePack
+#[derive(Debug)]
+struct ReversedJson;
+
+impl EnvelopeFormat for ReversedJson {
+    fn id(&self) -> u8 {
+        0x80
+    }
+
+    fn serialize(&self, value: &Value) -> SessionResult<Vec<u8>> {
+        let mut bytes = serde_json::to_vec(value).unwrap();
+        bytes.reverse();
+        Ok(bytes)
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> SessionResult<Value> {
+        let mut bytes = bytes.to_vec();
+        bytes.reverse();
+        serde_json::from_slice(&bytes).map_err(|e| SessionError::Parsing(Box::new(e)))
+    }
+}
+
+#[test]
+fn tagged_envelope_with_custom_format() {
+    let codec = EnvelopeCodec::builder().format(ReversedJson).build();
+    let encoded = codec.encode(&user()).unwrap();
+    assert_eq!(&encoded[..3], &[TAGGED_ENVELOPE_VERSION, 0x80, 0]);
+    assert_eq!(&encoded[3..], br#"}"ecila":"eman",1:"di"{"#);
+    assert_eq!(codec.decode::<User>(&encoded).unwrap(), user());
+    assert!(matches!(
+        codec.encode_str(&user()),
+        Err(SessionError::Unsupported(_))
+    ));
+
+    // Plain records are still read, and the default codec can't read the custom format
+    let plain = EnvelopeCodec::default().encode(&user()).unwrap();
+    assert_eq!(codec.decode::<User>(&plain).unwrap(), user());
+    assert!(matches!(
+        EnvelopeCodec::default().decode::<User>(&encoded),
+        Err(SessionError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn reads_legacy_formats_and_upgrades() {
+    let legacy = EnvelopeCodec::builder().format(ReversedJson).build();
+    let old_record = legacy.encode(&user()).unwrap();
+
+    // Migrating back to JSON, with compression
+    let codec = EnvelopeCodec::builder()
+        .legacy_formats(vec![Arc::new(ReversedJson)])
+        .compression(Compression::Zstd { level: 3 })
+        .build();
+    assert!(codec.needs_upgrade(&old_record));
+    assert_eq!(codec.decode::<User>(&old_record).unwrap(), user());
+
+    let new_record = codec.encode(&user()).unwrap();
+    assert_eq!(
+        &new_record[..3],
+        &[TAGGED_ENVELOPE_VERSION, JSON_FORMAT_ID, 1]
+    );
+    assert!(!codec.needs_upgrade(&new_record));
+    assert_eq!(codec.decode::<User>(&new_record).unwrap(), user());
+    assert!(codec.needs_upgrade(br#"{"id":1,"name":"alice"}"#));
+
+    // Without the legacy format, the old record can't be read
+    let strict = EnvelopeCodec::builder()
+        .compression(Compression::Lz4)
+        .build();
+    assert!(matches!(
+        strict.decode::<User>(&old_record),
+        Err(SessionError::Unsupported(_))
+    ));
+    assert_eq!(strict.decode::<User>(&new_record).unwrap(), user());
+}
+
+/// Codec that exposes the stored bytes
+struct RawCodec;
+
+impl SessionCodec<Vec<u8>> for RawCodec {
+    fn encode(&self, data: &Vec<u8>) -> SessionResult<Vec<u8>> {
+        Ok(data.clone())
+    }
+
+    fn decode(&self, value: &[u8]) -> SessionResult<Vec<u8>> {
+        Ok(value.to_vec())
+    }
+}
+
+#[rocket::async_test]
+async fn erased_storage_upgrades_records_on_load() {
+    let raw = ErasedSessionStorage::new(MemoryStorage::<ErasedPayload>::default(), RawCodec);
+    let legacy = EnvelopeCodec::default().encode(&user()).unwrap();
+    raw.save("id", legacy, 60).await.unwrap();
+
+    let codec = EnvelopeCodec::builder().format(ReversedJson).build();
+    let storage: ErasedSessionStorage<User> = raw.with_codec(codec.clone());
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, user());
+
+    let (stored, _) = raw.load("id", None, &NoopSessionContext).await.unwrap();
+    assert!(!codec.needs_upgrade(&stored));
+    assert_eq!(codec.decode::<User>(&stored).unwrap(), user());
+}
@@ -0,0 +1,168 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use rocket::{
+    async_trait,
+    http::Status,
+    local::blocking::Client,
+    serde::{Deserialize, Serialize},
+};
+use rocket_flex_session::{
+    error::SessionResult,
+    storage::{
+        envelope::EnvelopeCodec,
+        erased::{ErasedPayload, ErasedSessionStorage},
+        memory::MemoryStorage,
+        SessionContext, SessionStorage,
+    },
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct UserSession {
+    user_id: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct AdminSession {
+    admin_id: String,
+    level: u8,
+}
+
+/// Memory storage that counts setups and shutdowns
+#[derive(Default)]
+struct CountingStorage {
+    inner: MemoryStorage<ErasedPayload>,
+    setups: Arc<AtomicU32>,
+    shutdowns: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl SessionStorage<ErasedPayload> for CountingStorage {
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(ErasedPayload, u32)> {
+        self.inner.load(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: ErasedPayload, ttl: u32) -> SessionResult<()> {
+        self.inner.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: ErasedPayload) -> SessionResult<()> {
+        self.inner.delete(id, data).await
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.setups.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.shutdowns.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[post("/user/<user_id>")]
+fn user_login(mut session: Session<UserSession>, user_id: String) {
+    session.set(UserSession { user_id });
+}
+
+#[get("/user")]
+fn user(session: Session<UserSession>) -> Result<String, Status> {
+    let data = session.get().ok_or(Status::Unauthorized)?;
+    Ok(data.user_id)
+}
+
+#[post("/admin/<admin_id>")]
+fn admin_login(mut session: Session<AdminSession>, admin_id: String) {
+    session.set(AdminSession { admin_id, level: 2 });
+}
+
+#[get("/admin")]
+fn admin(session: Session<AdminSession>) -> Result<String, Status> {
+    let data = session.get().ok_or(Status::Unauthorized)?;
+    Ok(format!("{}:{}", data.admin_id, data.level))
+}
+
+#[test]
+fn session_types_share_one_storage() {
+    let storage = CountingStorage::default();
+    let (setups, shutdowns) = (storage.setups.clone(), storage.shutdowns.clone());
+    let user_storage = ErasedSessionStorage::new(storage, EnvelopeCodec::default());
+    let admin_storage = user_storage.with_codec(EnvelopeCodec::default());
+
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<UserSession>::builder()
+                .storage(user_storage)
+                .with_options(|opt| opt.cookie_name = "user_session".to_owned())
+                .build(),
+        )
+        .attach(
+            RocketFlexSession::<AdminSession>::builder()
+                .storage(admin_storage)
+                .with_options(|opt| opt.cookie_name = "admin_session".to_owned())
+                .build(),
+        )
+        .mount("/", routes![user_login, user, admin_login, admin]);
+    let client = Client::tracked(rocket).unwrap();
+    assert_eq!(setups.load(Ordering::Relaxed), 1);
+
+    client.post("/user/alice").dispatch();
+    client.post("/admin/bob").dispatch();
+    assert_eq!(
+        client.get("/user").dispatch().into_string().unwrap(),
+        "alice"
+    );
+    assert_eq!(
+        client.get("/admin").dispatch().into_string().unwrap(),
+        "bob:2"
+    );
+
+    client.terminate();
+    assert_eq!(shutdowns.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn other_session_types_are_not_loaded() {
+    let user_storage =
+        ErasedSessionStorage::new(MemoryStorage::default(), EnvelopeCodec::default());
+    let admin_storage = user_storage.with_codec(EnvelopeCodec::default());
+
+    // The admin fairing reads the user session cookie
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<UserSession>::builder()
+                .storage(user_storage)
+                .with_options(|opt| opt.cookie_name = "shared_session".to_owned())
+                .build(),
+        )
+        .mount("/", routes![user_login]);
+    let user_client = Client::tracked(rocket).unwrap();
+    user_client.post("/user/alice").dispatch();
+    let cookie = user_client.cookies().get_private("shared_session").unwrap();
+
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<AdminSession>::builder()
+                .storage(admin_storage)
+                .with_options(|opt| opt.cookie_name = "shared_session".to_owned())
+                .build(),
+        )
+        .mount("/", routes![admin]);
+    let admin_client = Client::tracked(rocket).unwrap();
+    let response = admin_client.get("/admin").private_cookie(cookie).dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
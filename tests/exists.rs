@@ -0,0 +1,55 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Cookie, local::blocking::Client, routes};
+use rocket_flex_session::{RocketFlexSession, Session, SessionExists};
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("user".to_owned());
+    session.id().unwrap()
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+#[get("/logged_in")]
+fn logged_in(session: SessionExists<String>) -> &'static str {
+    match session.exists() {
+        true => "yes",
+        false => "no",
+    }
+}
+
+#[get("/session_exists")]
+async fn session_exists(session: Session<'_, String>) -> String {
+    session.exists().await.unwrap().to_string()
+}
+
+#[test]
+fn test_exists() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![login, logout, logged_in, session_exists]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let get = |path: &str| client.get(path).dispatch().into_string().unwrap();
+    assert_eq!(get("/logged_in"), "no");
+    assert_eq!(get("/session_exists"), "false");
+
+    let id = client.post("/login").dispatch().into_string().unwrap();
+    assert_eq!(get("/logged_in"), "yes");
+    assert_eq!(get("/session_exists"), "true");
+
+    client.post("/logout").dispatch();
+    assert_eq!(get("/logged_in"), "no");
+
+    // Stale cookie for a deleted session
+    let response = client
+        .get("/logged_in")
+        .private_cookie(Cookie::new("rocket", id))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), "no");
+}
@@ -1,8 +1,8 @@
 #[macro_use]
 extern crate rocket;
 
-use rocket::{http::Status, local::blocking::Client, routes, Build, Rocket};
-use rocket_flex_session::{RocketFlexSession, Session};
+use rocket::{http::Status, local::blocking::Client, routes, Build, Rocket, State};
+use rocket_flex_session::{clock::MockClock, RocketFlexSession, Session};
 
 #[post("/set_session")]
 fn set_session(mut session: Session<String>) -> &'static str {
@@ -62,3 +62,75 @@ fn test_session_expiry() {
         .dispatch();
     assert_eq!(response.status(), Status::Unauthorized);
 }
+
+#[test]
+fn test_rolling_expiry_header() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| {
+                    opt.rolling = true;
+                    opt.ttl = Some(60);
+                    opt.expiry_header = Some("X-Session-Expires".to_owned());
+                })
+                .build(),
+        )
+        .mount("/", routes![get_session, set_session]);
+    let client = Client::tracked(rocket).unwrap();
+    let get_expires = |response: &rocket::local::blocking::LocalResponse| {
+        response
+            .headers()
+            .get_one("X-Session-Expires")
+            .map(|expires| expires.parse::<i64>().unwrap())
+    };
+
+    // No header without a session
+    let response = client.get("/get_session").dispatch();
+    assert_eq!(get_expires(&response), None);
+
+    let response = client.post("/set_session").dispatch();
+    let now = time::OffsetDateTime::now_utc().unix_timestamp();
+    let expires = get_expires(&response).expect("should have expiry header");
+    assert!((now + 59..=now + 60).contains(&expires));
+
+    // Header is added when the session is extended, even if it wasn't modified
+    let response = client.get("/get_session").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let expires = get_expires(&response).expect("should have expiry header");
+    assert!((now + 59..=now + 61).contains(&expires));
+}
+
+#[post("/slow_request")]
+fn slow_request(mut session: Session<String>, clock: &State<MockClock>) -> String {
+    session.set("active".to_owned());
+    let expires = session.expires_at().unix_timestamp();
+    // Time passes before the response is sent
+    clock.advance(time::Duration::seconds(5));
+    expires.to_string()
+}
+
+#[test]
+fn test_rolling_expiry_header_matches_expires_at() {
+    let clock = MockClock::default();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| {
+                    opt.rolling = true;
+                    opt.ttl = Some(60);
+                    opt.expiry_header = Some("X-Session-Expires".to_owned());
+                })
+                .clock(clock.clone())
+                .build(),
+        )
+        .manage(clock)
+        .mount("/", routes![slow_request]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.post("/slow_request").dispatch();
+    let header = response
+        .headers()
+        .get_one("X-Session-Expires")
+        .map(str::to_owned);
+    assert_eq!(header, response.into_string());
+}
@@ -0,0 +1,47 @@
+use rocket::serde::{Deserialize, Serialize};
+use rocket_flex_session::storage::express::{ExpressCookie, ExpressSession};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct User {
+    user_id: u32,
+    name: String,
+}
+
+const CONNECT_REDIS_VALUE: &str = r#"{"cookie":{"originalMaxAge":86400000,"expires":"2025-01-01T00:00:00.000Z","secure":false,"httpOnly":true,"path":"/","sameSite":"lax"},"userId":1,"name":"alice"}"#;
+
+#[test]
+fn decode_connect_redis_session() {
+    let session = ExpressSession::<User>::decode(CONNECT_REDIS_VALUE).unwrap();
+    assert_eq!(
+        session.data,
+        User {
+            user_id: 1,
+            name: "alice".to_owned()
+        }
+    );
+    assert_eq!(session.cookie.original_max_age, Some(86_400_000));
+    assert_eq!(
+        session.cookie.expires.as_deref(),
+        Some("2025-01-01T00:00:00.000Z")
+    );
+    assert_eq!(session.cookie.http_only, Some(true));
+
+    // The cookie settings are kept when the session is encoded again
+    let encoded = session.encode().unwrap();
+    assert_eq!(ExpressSession::<User>::decode(&encoded).unwrap(), session);
+}
+
+#[test]
+fn encode_new_session() {
+    let session = ExpressSession::new(User {
+        user_id: 2,
+        name: "bob".to_owned(),
+    });
+    assert_eq!(session.cookie, ExpressCookie::default());
+    assert_eq!(
+        session.encode().unwrap(),
+        r#"{"cookie":{"originalMaxAge":null},"userId":2,"name":"bob"}"#
+    );
+    assert!(ExpressSession::<User>::decode(r#"{"cookie":{}}"#).is_err());
+}
@@ -0,0 +1,87 @@
+use rocket::serde::{json::serde_json::json, Deserialize};
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        django::DjangoCodec,
+        rails::{marshal_to_json, RailsCodec, RailsSerializer},
+    },
+};
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde")]
+struct DjangoSession {
+    #[serde(rename = "_auth_user_id")]
+    user_id: String,
+}
+
+const DJANGO_SECRET_KEY: &str = "django-insecure-secret";
+
+#[test]
+fn decode_django_session() {
+    let codec = DjangoCodec::builder().secret_key(DJANGO_SECRET_KEY).build();
+
+    let session_data = "eyJfYXV0aF91c2VyX2lkIjoiMSIsInRoZW1lIjoiZGFyayJ9:1tUvXo:TE7LtOH1vj4dsD8h0_PY5nd4IZ0O1e_I3mXp8mzlD1U";
+    let session: DjangoSession = codec.decode(session_data).unwrap();
+    assert_eq!(session.user_id, "1");
+
+    let compressed = ".eJyrVopPLC3JiC8tTi2Kz0xRslIyUtJRysgsLskvqlSyilbSz8jPTQUKDU46thYAG3U5MA:1tUvXo:zcslOBXZJzptf-UsbAzwEsEAJJbIcEg56Ikjh5JaF9E";
+    let value = codec.decode_value(compressed).unwrap();
+    assert_eq!(value["_auth_user_id"], "2");
+    assert_eq!(value["history"].as_array().unwrap().len(), 20);
+
+    // The signature is verified with the secret key
+    let other_key = DjangoCodec::builder().secret_key("other-secret").build();
+    assert!(matches!(
+        other_key.decode::<DjangoSession>(session_data),
+        Err(SessionError::InvalidData)
+    ));
+    let tampered = session_data.replacen("eyJf", "eyJg", 1);
+    assert!(codec.decode::<DjangoSession>(&tampered).is_err());
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde")]
+struct RailsSession {
+    user_id: u64,
+}
+
+#[test]
+fn decode_signed_rails_payload() {
+    let codec = RailsCodec::builder()
+        .secret(b"rails-signed-cookie-secret".to_vec())
+        .build();
+    let payload = b"eyJfcmFpbHMiOiB7Im1lc3NhZ2UiOiAiZXlKMWMyVnlYMmxrSWpvZ055d2dJbVpzWVhOb0lqb2diblZzYkgwPSIsICJleHAiOiBudWxsLCAicHVyIjogImNvb2tpZS5fYXBwX3Nlc3Npb24ifX0=--4cd0c3905bd62ed5bde82703708742616fc64d6e";
+    let session: RailsSession = codec.decode(payload).unwrap();
+    assert_eq!(session, RailsSession { user_id: 7 });
+
+    let mut tampered = payload.to_vec();
+    *tampered.last_mut().unwrap() = b'f';
+    assert!(matches!(
+        codec.decode::<RailsSession>(&tampered),
+        Err(SessionError::InvalidData)
+    ));
+}
+
+#[test]
+fn decode_rails_marshal_payload() {
+    // {"user_id"=>42, :name=>"alice", "ids"=>[300, -200], "again"=>(same string as :name)}
+    let payload = b"\x04\x08{\x09I\"\x0cuser_id\x06:\x06ETi\x2f:\x09nameI\"\x0aalice\x06;\x00TI\"\x08ids\x06;\x00T[\x07i\x02\x2c\x01i\xff\x38I\"\x0aagain\x06;\x00T@\x07";
+    assert_eq!(
+        marshal_to_json(payload).unwrap(),
+        json!({ "user_id": 42, "name": "alice", "ids": [300, -200], "again": "alice" })
+    );
+
+    let codec = RailsCodec::builder()
+        .serializer(RailsSerializer::Marshal)
+        .build();
+    let session: RailsSession = codec.decode(payload).unwrap();
+    assert_eq!(session.user_id, 42);
+    assert!(matches!(
+        marshal_to_json(b"\x04\x090"),
+        Err(SessionError::Unsupported(_))
+    ));
+    assert!(matches!(
+        marshal_to_json(b"\x04\x08[\x07i\x06"),
+        Err(SessionError::InvalidData)
+    ));
+}
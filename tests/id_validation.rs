@@ -0,0 +1,79 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Cookie, local::blocking::Client, routes};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/set_session")]
+fn set_session(mut session: Session<String>) -> String {
+    session.set("active".to_owned());
+    session.id().unwrap()
+}
+
+#[get("/get_session")]
+fn get_session(session: Session<String>) -> String {
+    match session.get() {
+        Some(data) => data,
+        None => session.error().map(|e| e.to_string()).unwrap_or_default(),
+    }
+}
+
+fn create_client(fairing: RocketFlexSession<String>) -> Client {
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![set_session, get_session]);
+    Client::untracked(rocket).unwrap()
+}
+
+fn get_with_id(client: &Client, id: impl Into<String>) -> String {
+    client
+        .get("/get_session")
+        .private_cookie(Cookie::new("rocket", id.into()))
+        .dispatch()
+        .into_string()
+        .unwrap()
+}
+
+#[test]
+fn test_default_id_validation() {
+    let client = create_client(RocketFlexSession::default());
+
+    let id = client
+        .post("/set_session")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert_eq!(get_with_id(&client, id), "active");
+
+    // Valid format, but not in storage
+    assert_eq!(get_with_id(&client, "abc-123_XYZ"), "Session not found");
+
+    // Malformed IDs are rejected before looking up storage
+    for id in [
+        "",
+        "has spaces",
+        "key:*",
+        "ünïcode",
+        "a\0b",
+        &"a".repeat(129),
+    ] {
+        assert_eq!(get_with_id(&client, id), "Invalid session ID");
+    }
+}
+
+#[test]
+fn test_custom_id_validation() {
+    let client = create_client(
+        RocketFlexSession::<String>::builder()
+            .validate_id(|id| id.len() == 20)
+            .build(),
+    );
+
+    let id = client
+        .post("/set_session")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert_eq!(get_with_id(&client, id), "active");
+    assert_eq!(get_with_id(&client, "abc-123_XYZ"), "Invalid session ID");
+}
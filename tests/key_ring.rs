@@ -0,0 +1,85 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{
+    edge::EdgeVerifier,
+    error::SessionError,
+    keys::{KeyProvider, KeyPurpose, KeyRing},
+    RocketFlexSession, Session,
+};
+
+const MASTER_SECRET: [u8; 48] = [5; 48];
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> String {
+    session.set("user".to_owned());
+    session.id().unwrap()
+}
+
+#[test]
+fn derive_keys_per_purpose() {
+    let key_ring = KeyRing::new(&MASTER_SECRET).unwrap();
+    let cookie_key = key_ring.derive(KeyPurpose::CookieEncryption);
+    let at_rest_key = key_ring.derive(KeyPurpose::AtRestEncryption);
+    let signing_key = key_ring.derive(KeyPurpose::TokenSigning);
+    assert_ne!(cookie_key, at_rest_key);
+    assert_ne!(at_rest_key, signing_key);
+    assert_ne!(
+        key_ring.derive(KeyPurpose::Custom("csrf")),
+        key_ring.derive(KeyPurpose::Custom("api"))
+    );
+    // Custom labels can't collide with the built-in purposes
+    assert_ne!(
+        key_ring.derive(KeyPurpose::Custom("cookie-encryption")),
+        cookie_key
+    );
+
+    // Keys are stable for the same master secret
+    let same_ring = KeyRing::new(&MASTER_SECRET).unwrap();
+    assert_eq!(same_ring.derive(KeyPurpose::CookieEncryption), cookie_key);
+    let other_ring = KeyRing::new(&[6; 48]).unwrap();
+    assert_ne!(other_ring.derive(KeyPurpose::CookieEncryption), cookie_key);
+
+    assert!(KeyRing::new(&[5; 16]).is_none());
+}
+
+#[test]
+fn encrypt_cookie_with_derived_key() {
+    let key_ring = KeyRing::new(&MASTER_SECRET).unwrap();
+    let cookie_key = key_ring.derive(KeyPurpose::CookieEncryption);
+    let fairing = RocketFlexSession::<String>::builder()
+        .key_ring(key_ring.clone())
+        .build();
+    assert!(fairing.key_ring().is_some());
+    let rocket = rocket::build().attach(fairing).mount("/", routes![login]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let session_id = client.post("/login").dispatch().into_string().unwrap();
+    assert!(client.cookies().get_private("rocket").is_none());
+    let cookies = client.cookies();
+    let cookie = cookies.get("rocket").unwrap();
+    let verifier = EdgeVerifier::new(&cookie_key, "rocket").unwrap();
+    assert_eq!(verifier.verify(cookie.value()), Some(session_id.clone()));
+    let verifier = EdgeVerifier::from_key_ring(&key_ring, "rocket").unwrap();
+    assert_eq!(verifier.verify(cookie.value()), Some(session_id));
+}
+
+#[rocket::async_test]
+async fn key_ring_as_key_provider() {
+    let key_ring = KeyRing::new(&MASTER_SECRET).unwrap();
+    let key = key_ring.current_key().await.unwrap();
+    assert_eq!(
+        key.material,
+        key_ring.derive(KeyPurpose::AtRestEncryption).to_vec()
+    );
+    assert_eq!(key_ring.key(&key.id).await.unwrap().material, key.material);
+    assert!(matches!(
+        key_ring.key("other").await,
+        Err(SessionError::NotFound)
+    ));
+    assert!(matches!(
+        key_ring.rotate().await,
+        Err(SessionError::Unsupported(_))
+    ));
+}
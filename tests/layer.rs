@@ -0,0 +1,282 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rocket::{async_trait, time::OffsetDateTime, tokio::time::sleep};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{
+        layer::{
+            ConcurrencyLimitLayer, ConcurrencyStats, MetricsLayer, RetryLayer, ShedPolicy,
+            StorageMetrics, StorageStack, TimeoutLayer,
+        },
+        memory::{MemoryStorage, MemoryStorageIndexed},
+        NoopSessionContext, SessionContext, SessionStorage,
+    },
+    ActivityEntry, Capability, SessionIdentifier,
+};
+
+/// Storage that fails the first few saves with a backend error, and takes a while to delete
+#[derive(Default)]
+struct FlakyStorage {
+    failures: u32,
+    /// Fail with an error that would happen again, instead of a connection error
+    permanent: bool,
+    attempts: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl SessionStorage<String> for FlakyStorage {
+    async fn load(
+        &self,
+        _id: &str,
+        _ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        Err(SessionError::NotFound)
+    }
+
+    async fn save(&self, _id: &str, _data: String, _ttl: u32) -> SessionResult<()> {
+        let attempt = self.attempts.fetch_add(1, Ordering::SeqCst);
+        match (attempt < self.failures, self.permanent) {
+            (true, false) => Err(SessionError::Backend(Box::new(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset",
+            )))),
+            (true, true) => Err(SessionError::Backend("unique constraint violated".into())),
+            (false, _) => Ok(()),
+        }
+    }
+
+    async fn delete(&self, _id: &str, _data: String) -> SessionResult<()> {
+        sleep(Duration::from_millis(200)).await;
+        Ok(())
+    }
+
+    async fn take_capability(&self, _token: &str) -> SessionResult<Option<Capability>> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Err(SessionError::Timeout)
+    }
+
+    async fn take_once_value(&self, _id: &str, _key: &str) -> SessionResult<Option<String>> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Err(SessionError::Timeout)
+    }
+
+    async fn record_activity(
+        &self,
+        _id: &str,
+        _entry: ActivityEntry,
+        _limit: usize,
+    ) -> SessionResult<()> {
+        self.attempts.fetch_add(1, Ordering::SeqCst);
+        Err(SessionError::Timeout)
+    }
+}
+
+fn flaky(failures: u32) -> FlakyStorage {
+    FlakyStorage {
+        failures,
+        ..Default::default()
+    }
+}
+
+#[rocket::async_test]
+async fn retry_transient_errors() {
+    let retry = || {
+        RetryLayer::builder()
+            .max_retries(2)
+            .backoff(Duration::from_millis(1))
+            .build()
+    };
+
+    let storage = StorageStack::new(flaky(2)).layer(retry()).build();
+    storage.save("id", "data".to_owned(), 60).await.unwrap();
+
+    let storage = StorageStack::new(flaky(3)).layer(retry()).build();
+    let result = storage.save("id", "data".to_owned(), 60).await;
+    assert!(matches!(result, Err(SessionError::Backend(_))));
+
+    // Non-transient errors aren't retried
+    let storage = StorageStack::new(flaky(0)).layer(retry()).build();
+    let result = storage.load("id", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
+
+#[rocket::async_test]
+async fn permanent_errors_not_retried() {
+    let flaky = FlakyStorage {
+        failures: 1,
+        permanent: true,
+        ..Default::default()
+    };
+    let attempts = flaky.attempts.clone();
+    let storage = StorageStack::new(flaky)
+        .layer(
+            RetryLayer::builder()
+                .backoff(Duration::from_millis(1))
+                .build(),
+        )
+        .build();
+    let result = storage.save("id", "data".to_owned(), 60).await;
+    assert!(matches!(result, Err(SessionError::Backend(_))));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+}
+
+#[rocket::async_test]
+async fn consuming_operations_not_retried() {
+    let flaky = flaky(0);
+    let attempts = flaky.attempts.clone();
+    let storage = StorageStack::new(flaky)
+        .layer(
+            RetryLayer::builder()
+                .backoff(Duration::from_millis(1))
+                .build(),
+        )
+        .build();
+    let result = storage.take_capability("token").await;
+    assert!(matches!(result, Err(SessionError::Timeout)));
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    let result = storage.take_once_value("id", "key").await;
+    assert!(matches!(result, Err(SessionError::Timeout)));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    let entry = ActivityEntry {
+        timestamp: OffsetDateTime::now_utc(),
+        path: "/login".to_owned(),
+        status: 200,
+    };
+    let result = storage.record_activity("id", entry, 10).await;
+    assert!(matches!(result, Err(SessionError::Timeout)));
+    assert_eq!(attempts.load(Ordering::SeqCst), 3);
+}
+
+#[cfg(feature = "sqlx_postgres")]
+#[test]
+fn sqlx_errors_transient() {
+    let decode = sqlx::Error::ColumnDecode {
+        index: "data".to_owned(),
+        source: "invalid UTF-8".into(),
+    };
+    for error in [sqlx::Error::RowNotFound, decode] {
+        assert!(!SessionError::SqlxError(error).is_transient());
+    }
+    assert!(SessionError::SqlxError(sqlx::Error::PoolTimedOut).is_transient());
+}
+
+#[rocket::async_test]
+async fn timeout_slow_operations() {
+    let storage = StorageStack::new(flaky(0))
+        .layer(TimeoutLayer::new(Duration::from_millis(50)))
+        .build();
+    storage.save("id", "data".to_owned(), 60).await.unwrap();
+    let result = storage.delete("id", "data".to_owned()).await;
+    assert!(matches!(result, Err(SessionError::Timeout)));
+    assert!(SessionError::Timeout.is_transient());
+}
+
+#[rocket::async_test]
+async fn limit_concurrent_writes() {
+    let limit = ConcurrencyLimitLayer::new(1, ShedPolicy::MaxWaiting(1));
+    let storage = StorageStack::new(flaky(0)).layer(limit.clone()).build();
+
+    let (first, second, third) = rocket::tokio::join!(
+        storage.delete("a", "data".to_owned()),
+        async {
+            sleep(Duration::from_millis(10)).await;
+            storage.delete("b", "data".to_owned()).await
+        },
+        async {
+            sleep(Duration::from_millis(20)).await;
+            let stats = limit.stats();
+            assert_eq!((stats.running, stats.waiting), (1, 1));
+            storage.delete("c", "data".to_owned()).await
+        },
+    );
+    assert!(first.is_ok());
+    assert!(second.is_ok());
+    assert!(matches!(third, Err(SessionError::Overloaded)));
+    assert_eq!(
+        limit.stats(),
+        ConcurrencyStats {
+            running: 0,
+            waiting: 0,
+            shed: 1
+        }
+    );
+
+    // Loads aren't limited
+    let result = storage.load("id", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
+
+#[rocket::async_test]
+async fn shed_writes_after_waiting() {
+    let limit = ConcurrencyLimitLayer::new(1, ShedPolicy::After(Duration::from_millis(50)));
+    let storage = StorageStack::new(flaky(0)).layer(limit.clone()).build();
+
+    let (first, second) = rocket::tokio::join!(storage.delete("a", "data".to_owned()), async {
+        sleep(Duration::from_millis(10)).await;
+        storage.delete("b", "data".to_owned()).await
+    });
+    assert!(first.is_ok());
+    assert!(matches!(second, Err(SessionError::Overloaded)));
+    assert_eq!(limit.stats().shed, 1);
+}
+
+#[rocket::async_test]
+async fn metrics_and_composition() {
+    let metrics = StorageMetrics::default();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(RetryLayer::default())
+        .layer(TimeoutLayer::new(Duration::from_secs(1)))
+        .layer(MetricsLayer::new(metrics.clone()))
+        .build();
+
+    storage.save("id", "data".to_owned(), 60).await.unwrap();
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "data");
+    storage.delete("id", data).await.unwrap();
+    assert!(storage.load("id", None, &NoopSessionContext).await.is_err());
+
+    assert_eq!(metrics.save().calls, 1);
+    assert_eq!(metrics.load().calls, 2);
+    assert_eq!(metrics.load().errors, 1);
+    assert_eq!(metrics.delete().calls, 1);
+    assert_eq!(metrics.delete().errors, 0);
+}
+
+#[derive(Clone)]
+struct UserSession(String);
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[rocket::async_test]
+async fn layers_forward_indexing() {
+    let storage = StorageStack::new(MemoryStorageIndexed::<UserSession>::default())
+        .layer(MetricsLayer::new(StorageMetrics::default()))
+        .build();
+    storage
+        .save("id", UserSession("alice".to_owned()), 60)
+        .await
+        .unwrap();
+
+    let indexed = SessionStorage::<UserSession>::as_indexed_storage(&storage)
+        .expect("should support indexing");
+    let ids = indexed
+        .get_session_ids_by_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(ids, vec!["id"]);
+}
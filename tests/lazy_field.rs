@@ -0,0 +1,129 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::Status,
+    local::asynchronous::Client,
+    serde::{Deserialize, Serialize},
+};
+use rocket_flex_session::{
+    lazy::LazyField,
+    storage::{memory::MemoryStorage, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Profile {
+    name: String,
+    history: LazyField<Vec<String>>,
+}
+
+#[post("/login/<name>")]
+async fn login(mut session: Session<'_, Profile>, name: &str) {
+    session.set(Profile {
+        name: name.to_owned(),
+        history: LazyField::new("history"),
+    });
+    let history = vec!["login".to_owned()];
+    session.set_lazy(|p| &p.history, history).await.unwrap();
+}
+
+#[post("/visit/<page>")]
+async fn visit(session: Session<'_, Profile>, page: &str) -> Status {
+    let history = session.load_lazy(|p| &p.history).await.unwrap();
+    let Some(mut history) = history else {
+        return Status::Unauthorized;
+    };
+    history.push(page.to_owned());
+    session.set_lazy(|p| &p.history, history).await.unwrap();
+    Status::Ok
+}
+
+#[get("/history")]
+async fn history(session: Session<'_, Profile>) -> Option<String> {
+    let history = session.load_lazy(|p| &p.history).await.unwrap()?;
+    // The second access uses the value loaded in this request
+    let again = session.load_lazy(|p| &p.history).await.unwrap()?;
+    assert_eq!(history, again);
+    Some(history.join(","))
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<Profile>) {
+    session.delete();
+}
+
+#[rocket::async_test]
+async fn loads_fields_on_demand() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<Profile>::default())
+        .mount("/", routes![login, visit, history, logout]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let status = client.post("/visit/home").dispatch().await.status();
+    assert_eq!(status, Status::Unauthorized);
+
+    client.post("/login/alice").dispatch().await;
+    client.post("/visit/home").dispatch().await;
+    client.post("/visit/about").dispatch().await;
+    let response = client.get("/history").dispatch().await;
+    assert_eq!(response.into_string().await.unwrap(), "login,home,about");
+
+    client.post("/logout").dispatch().await;
+    client.post("/login/bob").dispatch().await;
+    let response = client.get("/history").dispatch().await;
+    assert_eq!(response.into_string().await.unwrap(), "login");
+}
+
+#[test]
+fn only_field_name_is_serialized() {
+    let profile = Profile {
+        name: "alice".to_owned(),
+        history: LazyField::new("history"),
+    };
+    let json = serde_json::to_string(&profile).unwrap();
+    assert_eq!(json, r#"{"name":"alice","history":"history"}"#);
+
+    let profile: Profile = serde_json::from_str(&json).unwrap();
+    assert_eq!(profile.history, LazyField::new("history"));
+}
+
+#[rocket::async_test]
+async fn memory_storage_deletes_fields_with_session() {
+    let storage = MemoryStorage::<String>::default();
+    storage
+        .save("session", "data".to_owned(), 60)
+        .await
+        .unwrap();
+    storage
+        .save_lazy_field("session", "field", "1".to_owned(), 60)
+        .await
+        .unwrap();
+    let value = storage.load_lazy_field("session", "field").await.unwrap();
+    assert_eq!(value.as_deref(), Some("1"));
+    let missing = storage.load_lazy_field("session", "other").await.unwrap();
+    assert_eq!(missing, None);
+
+    storage.delete("session", "data".to_owned()).await.unwrap();
+    let value = storage.load_lazy_field("session", "field").await.unwrap();
+    assert_eq!(value, None);
+}
+
+#[rocket::async_test]
+async fn memory_storage_saves_fields_concurrently() {
+    let storage = MemoryStorage::<String>::default();
+    let names: Vec<_> = (0..10).map(|i| format!("field{i}")).collect();
+    let saves = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| storage.save_lazy_field("session", name, i.to_string(), 60));
+    for result in rocket::futures::future::join_all(saves).await {
+        result.unwrap();
+    }
+
+    for (i, name) in names.iter().enumerate() {
+        let value = storage.load_lazy_field("session", name).await.unwrap();
+        assert_eq!(value, Some(i.to_string()));
+    }
+}
@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Cookie, local::blocking::Client};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[get("/")]
+fn index(session: Session<String>) -> String {
+    session.get().unwrap_or_default()
+}
+
+#[test]
+fn expires_legacy_cookies() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| {
+                    opt.legacy_cookies = vec!["connect.sid".to_owned(), "old_session".to_owned()]
+                })
+                .build(),
+        )
+        .mount("/", routes![index]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let response = client
+        .get("/")
+        .cookie(Cookie::new("connect.sid", "legacy"))
+        .cookie(Cookie::new("other", "value"))
+        .dispatch();
+    let removed = response.cookies().get("connect.sid").unwrap();
+    assert_eq!(removed.value(), "");
+    assert!(response.cookies().get("old_session").is_none());
+    assert!(response.cookies().get("other").is_none());
+
+    // Nothing to expire
+    let response = client.get("/").dispatch();
+    assert!(response.cookies().get("connect.sid").is_none());
+}
@@ -0,0 +1,147 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{
+    lifecycle::{Lifecycle, SessionState},
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct User {
+    name: String,
+    visits: u32,
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<SessionState<User>>, name: &str) -> Status {
+    match session.lifecycle() {
+        Lifecycle::Anonymous(anonymous) => {
+            let user = User {
+                name: name.to_owned(),
+                visits: 0,
+            };
+            anonymous.authenticate(user);
+            Status::Ok
+        }
+        _ => Status::Conflict,
+    }
+}
+
+#[post("/visit")]
+fn visit(mut session: Session<SessionState<User>>) -> Result<String, Status> {
+    let visits = match session.lifecycle() {
+        Lifecycle::Authenticated(mut authenticated) => authenticated.update(|user| {
+            user.visits += 1;
+            user.visits
+        }),
+        Lifecycle::Elevated(mut elevated) => elevated.update(|user| {
+            user.visits += 1;
+            user.visits
+        }),
+        _ => return Err(Status::Unauthorized),
+    };
+    Ok(visits.to_string())
+}
+
+#[post("/elevate")]
+fn elevate(mut session: Session<SessionState<User>>) -> Status {
+    match session.lifecycle() {
+        Lifecycle::Authenticated(authenticated) => {
+            authenticated.elevate();
+            Status::Ok
+        }
+        _ => Status::Conflict,
+    }
+}
+
+#[post("/drop")]
+fn drop_privileges(mut session: Session<SessionState<User>>) -> Status {
+    match session.lifecycle() {
+        Lifecycle::Elevated(elevated) => {
+            elevated.drop_privileges();
+            Status::Ok
+        }
+        _ => Status::Conflict,
+    }
+}
+
+#[post("/revoke")]
+fn revoke(mut session: Session<SessionState<User>>) -> Status {
+    match session.lifecycle() {
+        Lifecycle::Authenticated(authenticated) => authenticated.revoke(),
+        Lifecycle::Elevated(elevated) => elevated.revoke(),
+        _ => return Status::Conflict,
+    };
+    Status::Ok
+}
+
+#[get("/state")]
+fn state(session: Session<SessionState<User>>) -> String {
+    match session.get() {
+        None => "none".to_owned(),
+        Some(SessionState::Anonymous) => "anonymous".to_owned(),
+        Some(SessionState::Authenticated(user)) => format!("authenticated:{}", user.name),
+        Some(SessionState::Elevated(user)) => format!("elevated:{}", user.name),
+        Some(SessionState::Revoked) => "revoked".to_owned(),
+    }
+}
+
+#[test]
+fn lifecycle_transitions() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<SessionState<User>>::default())
+        .mount(
+            "/",
+            routes![login, visit, elevate, drop_privileges, revoke, state],
+        );
+    let client = Client::tracked(rocket).unwrap();
+
+    // Anonymous session can't be elevated
+    assert_eq!(
+        client.post("/elevate").dispatch().status(),
+        Status::Conflict
+    );
+    assert_eq!(
+        client.post("/visit").dispatch().status(),
+        Status::Unauthorized
+    );
+
+    assert_eq!(client.post("/login/alice").dispatch().status(), Status::Ok);
+    assert_eq!(
+        client.get("/state").dispatch().into_string().unwrap(),
+        "authenticated:alice"
+    );
+    assert_eq!(
+        client.post("/login/bob").dispatch().status(),
+        Status::Conflict
+    );
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "1");
+
+    assert_eq!(client.post("/elevate").dispatch().status(), Status::Ok);
+    assert_eq!(
+        client.get("/state").dispatch().into_string().unwrap(),
+        "elevated:alice"
+    );
+    assert_eq!(client.post("/visit").dispatch().into_string().unwrap(), "2");
+    assert_eq!(client.post("/drop").dispatch().status(), Status::Ok);
+    assert_eq!(
+        client.get("/state").dispatch().into_string().unwrap(),
+        "authenticated:alice"
+    );
+
+    // Revoked session can't be used or authenticated again
+    assert_eq!(client.post("/revoke").dispatch().status(), Status::Ok);
+    assert_eq!(
+        client.get("/state").dispatch().into_string().unwrap(),
+        "revoked"
+    );
+    assert_eq!(
+        client.post("/visit").dispatch().status(),
+        Status::Unauthorized
+    );
+    assert_eq!(
+        client.post("/login/alice").dispatch().status(),
+        Status::Conflict
+    );
+}
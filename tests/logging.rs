@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{LogIdFormat, RocketFlexSession, Session, SessionLogLevel};
+
+const ID: &str = "a1B2c3D4e5F6g7H8i9J0";
+
+#[test]
+fn test_log_id_formats() {
+    assert_eq!(LogIdFormat::Full.format(ID), ID);
+    assert_eq!(LogIdFormat::Truncated.format(ID), "a1B2…");
+
+    let hashed = LogIdFormat::Hashed.format(ID);
+    assert!(hashed.starts_with('#'));
+    assert_eq!(hashed.len(), 17);
+    assert!(!hashed.contains(ID));
+    assert_eq!(
+        hashed,
+        LogIdFormat::Hashed.format(ID),
+        "hash should be stable"
+    );
+    assert_ne!(hashed, LogIdFormat::Hashed.format("a1B2c3D4e5F6g7H8i9J1"));
+}
+
+#[test]
+fn test_log_level_order() {
+    assert!(SessionLogLevel::Off < SessionLogLevel::Error);
+    assert!(SessionLogLevel::Error < SessionLogLevel::Warn);
+    assert!(SessionLogLevel::Info < SessionLogLevel::Debug);
+    assert_eq!(SessionLogLevel::default(), SessionLogLevel::Debug);
+}
+
+#[post("/set_session")]
+fn set_session(mut session: Session<String>) -> &'static str {
+    session.set("active".to_owned());
+    "Session set"
+}
+
+#[get("/get_session")]
+fn get_session(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+#[test]
+fn test_sessions_with_quiet_logs() {
+    let fairing = RocketFlexSession::<String>::builder()
+        .with_options(|opt| {
+            opt.log_ids = LogIdFormat::Hashed;
+            opt.log_level = SessionLogLevel::Off;
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![set_session, get_session]);
+    let client = Client::tracked(rocket).unwrap();
+
+    client.post("/set_session").dispatch();
+    let response = client.get("/get_session").dispatch();
+    assert_eq!(response.into_string().unwrap(), "active");
+}
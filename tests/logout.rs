@@ -0,0 +1,60 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Cookie, CookieJar},
+    local::blocking::Client,
+};
+use rocket_flex_session::{storage::cookie::CookieStorage, RocketFlexSession, Session};
+
+#[post("/login")]
+fn login(mut session: Session<String>, cookies: &CookieJar<'_>) {
+    session.set("alice".to_owned());
+    cookies.add(("csrf_token", "token"));
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.logout();
+}
+
+fn client() -> Client {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .storage(CookieStorage::default())
+                .with_options(|opt| opt.companion_cookies = vec!["csrf_token".to_owned()])
+                .build(),
+        )
+        .mount("/", routes![login, logout]);
+    Client::untracked(rocket).unwrap()
+}
+
+/// Whether the response removes the cookie with the given name
+fn is_removed(cookie: Option<&Cookie<'_>>) -> bool {
+    cookie.is_some_and(|cookie| cookie.value().is_empty())
+}
+
+#[test]
+fn logout_removes_all_cookies() {
+    let client = client();
+    let response = client.post("/login").dispatch();
+    let cookies: Vec<_> = response.cookies().iter().cloned().collect();
+    assert_eq!(cookies.len(), 3);
+
+    let response = client.post("/logout").cookies(cookies).dispatch();
+    assert!(is_removed(response.cookies().get("rocket")));
+    assert!(is_removed(response.cookies().get("rocket_session")));
+    assert!(is_removed(response.cookies().get("csrf_token")));
+}
+
+#[test]
+fn logout_removes_orphaned_storage_cookie() {
+    let client = client();
+    let response = client.post("/login").dispatch();
+    let data_cookie = response.cookies().get("rocket_session").unwrap().clone();
+
+    // The session cookie is missing, so no session is loaded
+    let response = client.post("/logout").cookie(data_cookie).dispatch();
+    assert!(is_removed(response.cookies().get("rocket_session")));
+}
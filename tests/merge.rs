@@ -0,0 +1,113 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::Arc;
+
+use rocket::{async_trait, local::blocking::Client, State};
+use rocket_flex_session::{
+    error::SessionResult,
+    merge::MergeStrategy,
+    storage::{memory::MemoryStorage, SessionContext, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+/// Memory storage that can be written to directly by the routes, to simulate a concurrent request
+struct SharedStorage(Arc<MemoryStorage<String>>);
+
+#[async_trait]
+impl SessionStorage<String> for SharedStorage {
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        self.0.load(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: String, ttl: u32) -> SessionResult<()> {
+        self.0.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: String) -> SessionResult<()> {
+        self.0.delete(id, data).await
+    }
+}
+
+#[post("/add/<item>")]
+fn add(mut session: Session<String>, item: &str) {
+    session.tap_mut(|data| add_item(data.get_or_insert_with(String::new), item));
+}
+
+/// Add an item, while another request adds a different item to the stored session
+#[post("/add/<item>/concurrent/<other>")]
+async fn add_concurrent(
+    mut session: Session<'_, String>,
+    storage: &State<Arc<MemoryStorage<String>>>,
+    item: &str,
+    other: &str,
+) {
+    let (id, mut stored) = (session.id().unwrap(), session.get().unwrap());
+    add_item(&mut stored, other);
+    storage.save(&id, stored, 60).await.unwrap();
+
+    session.tap_mut(|data| add_item(data.as_mut().unwrap(), item));
+}
+
+#[get("/items")]
+fn items(session: Session<String>) -> Option<String> {
+    session.get()
+}
+
+fn add_item(data: &mut String, item: &str) {
+    if !data.is_empty() {
+        data.push(',');
+    }
+    data.push_str(item);
+}
+
+fn client(merge: Option<MergeStrategy<String>>) -> Client {
+    let storage = Arc::new(MemoryStorage::default());
+    let fairing = RocketFlexSession::<String>::builder()
+        .storage(SharedStorage(storage.clone()))
+        .maybe_merge(merge)
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .manage(storage)
+        .mount("/", routes![add, add_concurrent, items]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn concurrent_changes_are_merged() {
+    let merge = MergeStrategy::new(|stored: String, mut mine: String| {
+        for item in stored.split(',') {
+            if !mine.split(',').any(|existing| existing == item) {
+                add_item(&mut mine, item);
+            }
+        }
+        mine
+    });
+    let client = client(Some(merge));
+    client.post("/add/a").dispatch();
+    client.post("/add/b/concurrent/c").dispatch();
+
+    let response = client.get("/items").dispatch();
+    assert_eq!(response.into_string().unwrap(), "a,b,c");
+
+    // Unchanged stored data isn't merged
+    client.post("/add/d").dispatch();
+    let response = client.get("/items").dispatch();
+    assert_eq!(response.into_string().unwrap(), "a,b,c,d");
+}
+
+#[test]
+fn last_write_wins_without_merge() {
+    let client = client(None);
+    client.post("/add/a").dispatch();
+    client.post("/add/b/concurrent/c").dispatch();
+
+    let response = client.get("/items").dispatch();
+    assert_eq!(response.into_string().unwrap(), "a,b");
+}
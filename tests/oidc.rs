@@ -0,0 +1,186 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{ContentType, Status},
+    local::blocking::Client,
+    time::OffsetDateTime,
+};
+use rocket_flex_session::{
+    clock::MockClock,
+    oidc::{BackChannelLogout, BACKCHANNEL_LOGOUT_EVENT},
+    storage::memory::MemoryStorageIndexed,
+    RocketFlexSession, Session, SessionIdentifier,
+};
+use serde_json::{json, Value};
+
+#[derive(Clone)]
+struct UserSession {
+    user_id: String,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+#[post("/login/<user_id>")]
+fn login(mut session: Session<UserSession>, user_id: &str) {
+    session.set(UserSession {
+        user_id: user_id.to_owned(),
+    });
+}
+
+#[get("/user")]
+fn user(session: Session<UserSession>) -> Option<String> {
+    session.tap(|data| data.map(|d| d.user_id.clone()))
+}
+
+/// The current time of the test clock (Unix timestamp)
+const NOW: i64 = 1700000000;
+
+fn create_client() -> Client {
+    create_client_with_storage(true)
+}
+
+fn create_client_with_storage(indexed: bool) -> Client {
+    let clock = MockClock::new(OffsetDateTime::from_unix_timestamp(NOW).unwrap());
+    let logout = BackChannelLogout::<UserSession>::builder()
+        .issuer("https://idp.example.com")
+        .audience("client")
+        // Test tokens are the JSON claims, without a signature
+        .verify(|token| {
+            Box::pin(async move { serde_json::from_str(&token).map_err(|e| e.to_string()) })
+        })
+        .map_subject(|sub| sub.strip_prefix("idp|").map(str::to_owned))
+        .build();
+    let fairing = match indexed {
+        true => RocketFlexSession::<UserSession>::builder()
+            .storage(MemoryStorageIndexed::default())
+            .clock(clock)
+            .build(),
+        false => RocketFlexSession::<UserSession>::builder()
+            .clock(clock)
+            .build(),
+    };
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, user])
+        .mount("/backchannel-logout", logout);
+    Client::tracked(rocket).unwrap()
+}
+
+fn claims(sub: &str) -> Value {
+    json!({
+        "iss": "https://idp.example.com",
+        "aud": ["client"],
+        "iat": NOW - 10,
+        "exp": NOW + 110,
+        "jti": "abc",
+        "sub": sub,
+        "events": { BACKCHANNEL_LOGOUT_EVENT: {} },
+    })
+}
+
+fn send_logout(client: &Client, claims: &Value) -> Status {
+    let body = format!(
+        "logout_token={}",
+        rocket::http::RawStr::new(&claims.to_string()).percent_encode()
+    );
+    let response = client
+        .post("/backchannel-logout")
+        .header(ContentType::Form)
+        .body(body)
+        .dispatch();
+    assert_eq!(
+        response.headers().get_one("Cache-Control"),
+        Some("no-store")
+    );
+    response.status()
+}
+
+#[test]
+fn logout_invalidates_sessions() {
+    let client = create_client();
+    client.post("/login/alice").dispatch();
+    assert_eq!(
+        client.get("/user").dispatch().into_string().as_deref(),
+        Some("alice")
+    );
+
+    // Another user's logout doesn't affect the session
+    assert_eq!(send_logout(&client, &claims("idp|bob")), Status::Ok);
+    assert_eq!(client.get("/user").dispatch().status(), Status::Ok);
+
+    assert_eq!(send_logout(&client, &claims("idp|alice")), Status::Ok);
+    assert_eq!(client.get("/user").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn invalid_logout_tokens() {
+    let client = create_client();
+    client.post("/login/alice").dispatch();
+
+    let mut wrong_issuer = claims("idp|alice");
+    wrong_issuer["iss"] = json!("https://evil.example.com");
+    let mut wrong_audience = claims("idp|alice");
+    wrong_audience["aud"] = json!("other-client");
+    let mut with_nonce = claims("idp|alice");
+    with_nonce["nonce"] = json!("123");
+    let mut without_event = claims("idp|alice");
+    without_event["events"] = json!({});
+    let mut without_sub = claims("idp|alice");
+    without_sub.as_object_mut().unwrap().remove("sub");
+
+    for claims in [
+        wrong_issuer,
+        wrong_audience,
+        with_nonce,
+        without_event,
+        without_sub,
+    ] {
+        assert_eq!(send_logout(&client, &claims), Status::BadRequest);
+    }
+    assert_eq!(client.get("/user").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn expired_and_stale_logout_tokens() {
+    let client = create_client();
+    client.post("/login/alice").dispatch();
+
+    let mut expired = claims("idp|alice");
+    expired["exp"] = json!(NOW - 60);
+    let mut without_exp = claims("idp|alice");
+    without_exp.as_object_mut().unwrap().remove("exp");
+    let mut stale = claims("idp|alice");
+    stale["iat"] = json!(NOW - 3600);
+    stale["exp"] = json!(NOW + 60);
+    let mut issued_in_future = claims("idp|alice");
+    issued_in_future["iat"] = json!(NOW + 600);
+
+    for claims in [expired, without_exp, stale, issued_in_future] {
+        assert_eq!(send_logout(&client, &claims), Status::BadRequest);
+    }
+    assert_eq!(client.get("/user").dispatch().status(), Status::Ok);
+
+    // A small clock skew is allowed
+    let mut just_expired = claims("idp|alice");
+    just_expired["exp"] = json!(NOW - 5);
+    assert_eq!(send_logout(&client, &just_expired), Status::Ok);
+    assert_eq!(client.get("/user").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn failed_logout_is_bad_request() {
+    // Sessions can't be invalidated without an indexed storage
+    let client = create_client_with_storage(false);
+    client.post("/login/alice").dispatch();
+    assert_eq!(
+        send_logout(&client, &claims("idp|alice")),
+        Status::BadRequest
+    );
+}
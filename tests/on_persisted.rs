@@ -0,0 +1,131 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{Arc, Mutex};
+
+use rocket::{async_trait, local::blocking::Client, State};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    queue::PersistQueue,
+    storage::{memory::MemoryStorage, SessionContext, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+/// Memory storage that fails to save sessions named "fail"
+#[derive(Default)]
+struct FailingStorage {
+    inner: MemoryStorage<String>,
+}
+
+#[async_trait]
+impl SessionStorage<String> for FailingStorage {
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        self.inner.load(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: String, ttl: u32) -> SessionResult<()> {
+        if data == "fail" {
+            return Err(SessionError::Backend("storage is down".into()));
+        }
+        self.inner.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: String) -> SessionResult<()> {
+        self.inner.delete(id, data).await
+    }
+}
+
+/// Results reported to the `on_persisted` callbacks, by request
+type Results = Arc<Mutex<Vec<(String, Result<(), String>)>>>;
+
+fn record(session: &Session<String>, results: &State<Results>, request: &str) {
+    let (results, request) = (results.inner().clone(), request.to_owned());
+    session.on_persisted(move |result| {
+        let result = result.map_err(|e| e.to_string());
+        results.lock().unwrap().push((request, result));
+    });
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: String, results: &State<Results>) {
+    session.set(name.clone());
+    record(&session, results, &name);
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>, results: &State<Results>) {
+    session.delete();
+    record(&session, results, "logout");
+}
+
+#[get("/")]
+fn index(session: Session<String>, results: &State<Results>) -> String {
+    record(&session, results, "index");
+    session.get().unwrap_or_default()
+}
+
+fn client(queue: Option<PersistQueue>) -> (Client, Results) {
+    let results = Results::default();
+    let builder = RocketFlexSession::<String>::builder().storage(FailingStorage::default());
+    let fairing = match queue {
+        Some(queue) => builder.persist_queue(queue).build(),
+        None => builder.build(),
+    };
+    let rocket = rocket::build()
+        .attach(fairing)
+        .manage(results.clone())
+        .mount("/", routes![login, logout, index]);
+    (Client::tracked(rocket).unwrap(), results)
+}
+
+#[test]
+fn reports_save_results() {
+    let (client, results) = client(None);
+
+    client.post("/login/alice").dispatch();
+    client.post("/login/fail").dispatch();
+
+    let results = results.lock().unwrap();
+    assert_eq!(results[0], ("alice".to_owned(), Ok(())));
+    assert_eq!(
+        results[1],
+        (
+            "fail".to_owned(),
+            Err("Storage backend error: storage is down".to_owned())
+        )
+    );
+}
+
+#[test]
+fn reports_deletes_but_not_unchanged_sessions() {
+    let (client, results) = client(None);
+
+    client.post("/login/alice").dispatch();
+    client.get("/").dispatch();
+    client.post("/logout").dispatch();
+
+    let results = results.lock().unwrap();
+    assert_eq!(
+        *results,
+        vec![("alice".to_owned(), Ok(())), ("logout".to_owned(), Ok(()))]
+    );
+}
+
+#[test]
+fn reports_queued_results() {
+    let (client, results) = client(Some(PersistQueue::default()));
+
+    client.post("/login/alice").dispatch();
+    client.post("/login/fail").dispatch();
+    client.terminate();
+
+    let results = results.lock().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0], ("alice".to_owned(), Ok(())));
+    assert!(results[1].1.is_err());
+}
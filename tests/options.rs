@@ -0,0 +1,102 @@
+use rocket::{
+    figment::{
+        providers::{Format, Toml},
+        Figment,
+    },
+    http::SameSite,
+};
+use rocket_flex_session::{
+    error::OptionsError, LogIdFormat, RocketFlexSessionOptions, SessionLogLevel,
+};
+
+#[test]
+fn builder_defaults_match_default_options() {
+    let built = RocketFlexSessionOptions::builder().build().unwrap();
+    let default = RocketFlexSessionOptions::default();
+    assert_eq!(built.cookie_name, default.cookie_name);
+    assert_eq!(built.max_age, default.max_age);
+    assert_eq!(built.path, "/");
+    assert!(built.http_only && built.secure && built.persist_anonymous);
+    assert_eq!(built.same_site, SameSite::Lax);
+}
+
+#[test]
+fn builder_validates_options() {
+    let options = RocketFlexSessionOptions::builder()
+        .cookie_name("my_session")
+        .max_age(3600)
+        .ttl(600)
+        .rolling(true)
+        .build()
+        .unwrap();
+    assert_eq!(options.cookie_name, "my_session");
+    assert_eq!(options.ttl, Some(600));
+
+    let invalid = [
+        (
+            RocketFlexSessionOptions::builder().max_age(0).build(),
+            OptionsError::ZeroMaxAge,
+        ),
+        (
+            RocketFlexSessionOptions::builder().ttl(0).build(),
+            OptionsError::ZeroTtl("ttl"),
+        ),
+        (
+            RocketFlexSessionOptions::builder()
+                .max_age(60)
+                .authenticated_ttl(3600)
+                .build(),
+            OptionsError::TtlExceedsMaxAge("authenticated_ttl", 3600, 60),
+        ),
+        (
+            RocketFlexSessionOptions::builder().path("app").build(),
+            OptionsError::InvalidPath("app".to_owned()),
+        ),
+        (
+            RocketFlexSessionOptions::builder()
+                .anonymous_sample_rate(1.5)
+                .build(),
+            OptionsError::InvalidSampleRate("anonymous_sample_rate", 1.5),
+        ),
+        (
+            RocketFlexSessionOptions::builder()
+                .activity_log_size(0)
+                .build(),
+            OptionsError::ZeroActivityLogSize,
+        ),
+    ];
+    for (result, expected) in invalid {
+        assert_eq!(result.unwrap_err(), expected);
+    }
+}
+
+#[test]
+fn deserialize_options() {
+    let figment = Figment::new().merge(Toml::string(
+        r#"
+        [session]
+        cookie_name = "my_session"
+        max_age = 3600
+        ttl = 600
+        same_site = "Strict"
+        log_ids = "hashed"
+        log_level = "warn"
+        trusted_origins = ["https://app.example.com"]
+        "#,
+    ));
+    let options = RocketFlexSessionOptions::from_figment(&figment, "session").unwrap();
+    assert_eq!(options.cookie_name, "my_session");
+    assert_eq!(options.ttl, Some(600));
+    assert_eq!(options.same_site, SameSite::Strict);
+    assert_eq!(options.log_ids, LogIdFormat::Hashed);
+    assert_eq!(options.log_level, SessionLogLevel::Warn);
+    assert_eq!(options.trusted_origins, vec!["https://app.example.com"]);
+    // Missing options use the defaults
+    assert_eq!(options.path, "/");
+    assert!(options.secure);
+
+    let invalid = Figment::new().merge(Toml::string("[session]\nmax_age = 60\nttl = 600"));
+    assert!(RocketFlexSessionOptions::from_figment(&invalid, "session").is_err());
+    let invalid = Figment::new().merge(Toml::string("[session]\nsame_site = \"sometimes\""));
+    assert!(RocketFlexSessionOptions::from_figment(&invalid, "session").is_err());
+}
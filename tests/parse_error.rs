@@ -0,0 +1,148 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    local::blocking::Client,
+    serde::{Deserialize, Serialize},
+    State,
+};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    policy::{ParseErrorMetrics, ParseErrorPolicy},
+    storage::{
+        envelope::EnvelopeCodec,
+        erased::{ErasedPayload, ErasedSessionStorage, SessionCodec},
+        memory::MemoryStorage,
+        NoopSessionContext, SessionStorage,
+    },
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct User {
+    name: String,
+}
+
+/// Codec that exposes the stored bytes
+struct RawCodec;
+
+impl SessionCodec<Vec<u8>> for RawCodec {
+    fn encode(&self, data: &Vec<u8>) -> SessionResult<Vec<u8>> {
+        Ok(data.clone())
+    }
+
+    fn decode(&self, value: &[u8]) -> SessionResult<Vec<u8>> {
+        Ok(value.to_vec())
+    }
+}
+
+type RawStorage = ErasedSessionStorage<Vec<u8>>;
+
+#[post("/login")]
+fn login(mut session: Session<User>) {
+    session.set(User {
+        name: "alice".to_owned(),
+    });
+}
+
+#[post("/corrupt")]
+async fn corrupt(session: Session<'_, User>, raw: &State<RawStorage>) {
+    let id = session.id().unwrap();
+    raw.save(&id, b"\x01not json".to_vec(), 60).await.unwrap();
+}
+
+#[get("/")]
+fn index(session: Session<User>) -> String {
+    let parse_error = matches!(session.error(), Some(SessionError::Parsing(_)));
+    let name = session.tap(|data| data.map(|user| user.name.clone()));
+    format!("{}:{parse_error}", name.unwrap_or_default())
+}
+
+/// Create a client, and corrupt the stored session. Returns the ID of the corrupt session.
+fn setup(policy: ParseErrorPolicy<User>, metrics: &ParseErrorMetrics) -> (Client, String) {
+    let raw = RawStorage::new(MemoryStorage::<ErasedPayload>::default(), RawCodec);
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<User>::builder()
+                .storage(raw.with_codec(EnvelopeCodec::default()))
+                .parse_error_policy(policy)
+                .parse_error_metrics(metrics.clone())
+                .build(),
+        )
+        .manage(raw)
+        .mount("/", routes![login, corrupt, index]);
+    let client = Client::tracked(rocket).unwrap();
+
+    client.post("/login").dispatch();
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        "alice:false"
+    );
+    client.post("/corrupt").dispatch();
+    let id = client
+        .cookies()
+        .get_private("rocket")
+        .unwrap()
+        .value()
+        .to_owned();
+    (client, id)
+}
+
+fn stored(client: &Client, id: &str) -> SessionResult<Vec<u8>> {
+    let raw = client.rocket().state::<RawStorage>().unwrap();
+    rocket::execute(raw.load(id, None, &NoopSessionContext)).map(|(data, _)| data)
+}
+
+#[test]
+fn keeps_corrupt_session_by_default() {
+    let metrics = ParseErrorMetrics::default();
+    let (client, id) = setup(ParseErrorPolicy::default(), &metrics);
+
+    assert_eq!(client.get("/").dispatch().into_string().unwrap(), ":true");
+    assert_eq!(stored(&client, &id).unwrap(), b"\x01not json");
+    assert_eq!(metrics.corrupted(), 1);
+    // Loaded before the session was corrupted, in the setup routes
+    assert_eq!(metrics.loaded(), 2);
+    assert_eq!(metrics.corruption_rate(), 1.0 / 3.0);
+}
+
+#[test]
+fn deletes_corrupt_session() {
+    let metrics = ParseErrorMetrics::default();
+    let (client, id) = setup(ParseErrorPolicy::Delete, &metrics);
+
+    assert_eq!(client.get("/").dispatch().into_string().unwrap(), ":true");
+    assert!(matches!(stored(&client, &id), Err(SessionError::NotFound)));
+    assert_eq!(metrics.deleted(), 1);
+
+    // The session is gone, so it isn't parsed again
+    assert_eq!(client.get("/").dispatch().into_string().unwrap(), ":false");
+    assert_eq!(metrics.corrupted(), 1);
+}
+
+#[test]
+fn recovers_corrupt_session() {
+    let metrics = ParseErrorMetrics::default();
+    let policy = ParseErrorPolicy::recover(|_id, error| {
+        assert!(matches!(error, SessionError::Parsing(_)));
+        Some(User {
+            name: "recovered".to_owned(),
+        })
+    });
+    let (client, id) = setup(policy, &metrics);
+
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        "recovered:false"
+    );
+    // The recovered data is saved over the corrupt data
+    let stored = stored(&client, &id).unwrap();
+    assert_eq!(&stored[1..], br#"{"name":"recovered"}"#);
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        "recovered:false"
+    );
+    assert_eq!(metrics.recovered(), 1);
+    assert_eq!(metrics.corrupted(), 1);
+}
@@ -0,0 +1,110 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use rocket::{async_trait, local::blocking::Client, tokio::sync::Semaphore};
+use rocket_flex_session::{
+    error::SessionResult,
+    queue::{OverflowPolicy, PersistQueue, PersistQueueStats},
+    storage::{memory::MemoryStorage, SessionContext, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+/// Storage that waits for a permit before each save, and counts the completed saves
+struct GatedStorage {
+    inner: MemoryStorage<String>,
+    gate: Arc<Semaphore>,
+    saves: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl SessionStorage<String> for GatedStorage {
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        self.inner.load(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: String, ttl: u32) -> SessionResult<()> {
+        let _permit = self.gate.acquire().await.unwrap();
+        self.inner.save(id, data, ttl).await?;
+        self.saves.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str, data: String) -> SessionResult<()> {
+        self.inner.delete(id, data).await
+    }
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: String) {
+    session.set(name);
+}
+
+fn client(queue: PersistQueue) -> (Client, Arc<Semaphore>, Arc<AtomicU32>) {
+    let (gate, saves) = (Arc::new(Semaphore::new(0)), Arc::new(AtomicU32::new(0)));
+    let storage = GatedStorage {
+        inner: MemoryStorage::default(),
+        gate: gate.clone(),
+        saves: saves.clone(),
+    };
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .storage(storage)
+                .persist_queue(queue)
+                .build(),
+        )
+        .mount("/", routes![login]);
+    (Client::tracked(rocket).unwrap(), gate, saves)
+}
+
+fn queue_stats(client: &Client) -> PersistQueueStats {
+    let fairing = client
+        .rocket()
+        .state::<RocketFlexSession<String>>()
+        .unwrap();
+    fairing.persist_queue_stats().expect("should have queue")
+}
+
+#[test]
+fn saves_in_background() {
+    let (client, gate, saves) = client(PersistQueue::default());
+
+    // The response doesn't wait for the save
+    client.post("/login/alice").dispatch();
+    assert_eq!(saves.load(Ordering::Relaxed), 0);
+
+    // Pending saves are completed on shutdown
+    gate.add_permits(10);
+    client.terminate();
+    assert_eq!(saves.load(Ordering::Relaxed), 1);
+}
+
+#[test]
+fn drops_oldest_when_full() {
+    let queue = PersistQueue::builder()
+        .capacity(1)
+        .overflow(OverflowPolicy::DropOldest)
+        .build();
+    let (client, gate, saves) = client(queue);
+
+    for name in ["alice", "bob", "carol"] {
+        client.post(format!("/login/{name}")).dispatch();
+    }
+    let stats = queue_stats(&client);
+    assert!(stats.dropped >= 1, "dropped: {}", stats.dropped);
+    assert!(stats.pending <= 1, "pending: {}", stats.pending);
+
+    gate.add_permits(10);
+    client.terminate();
+    assert_eq!(saves.load(Ordering::Relaxed) as u64, 3 - stats.dropped);
+}
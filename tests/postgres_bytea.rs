@@ -0,0 +1,101 @@
+mod common;
+
+use rocket::local::asynchronous::Client;
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        sqlx::{SessionSqlx, SqlxPostgresStorage},
+        SessionStorage, SessionStorageIndexed,
+    },
+    SessionIdentifier,
+};
+
+use crate::common::{setup_postgres, teardown_postgres, POSTGRES_URL};
+
+/// Session stored as raw bytes: the user ID, followed by a binary payload
+#[derive(Clone, Debug, PartialEq)]
+struct BinarySession {
+    user_id: String,
+    payload: Vec<u8>,
+}
+
+impl SessionIdentifier for BinarySession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+impl SessionSqlx<sqlx::Postgres> for BinarySession {
+    type Data = Vec<u8>;
+    type Error = SessionError;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        let mut data = vec![self.user_id.len() as u8];
+        data.extend_from_slice(self.user_id.as_bytes());
+        data.extend_from_slice(&self.payload);
+        Ok(data)
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        let (len, rest) = value.split_first().ok_or(SessionError::InvalidData)?;
+        let (user_id, payload) = rest.split_at(usize::from(*len));
+        Ok(Self {
+            user_id: String::from_utf8(user_id.to_vec()).map_err(|_| SessionError::InvalidData)?,
+            payload: payload.to_vec(),
+        })
+    }
+}
+
+#[rocket::async_test]
+async fn bytea_data_column() {
+    let (pool, db_name) = setup_postgres(POSTGRES_URL).await;
+    sqlx::query(
+        r#"CREATE TABLE binary_sessions (
+          id      TEXT PRIMARY KEY,
+          data    BYTEA NOT NULL,
+          user_id TEXT,
+          expires TIMESTAMPTZ NOT NULL
+      )"#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let storage = SqlxPostgresStorage::builder()
+        .pool(pool.clone())
+        .table_name("binary_sessions")
+        .build();
+    let client = Client::untracked(rocket::build()).await.unwrap();
+    let req = client.get("/");
+
+    let session = BinarySession {
+        user_id: "user1".to_owned(),
+        payload: vec![0xFF, 0x00, 0x80, 0x7F],
+    };
+    storage.save("id1", session.clone(), 60).await.unwrap();
+
+    let (loaded, _): (BinarySession, _) = storage
+        .load("id1", None, req.inner().cookies())
+        .await
+        .unwrap();
+    assert_eq!(loaded, session);
+
+    let sessions = SessionStorageIndexed::<BinarySession>::get_sessions_by_identifier(
+        &storage,
+        &"user1".to_owned(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].0, "id1");
+    assert_eq!(sessions[0].1, session);
+
+    let recent = SessionStorage::<BinarySession>::recently_active(&storage, 10)
+        .await
+        .unwrap();
+    assert_eq!(recent.len(), 1);
+    assert_eq!(recent[0].1, session);
+
+    teardown_postgres(pool, db_name).await;
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use rocket::local::asynchronous::Client;
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        sqlx::{SessionSqlx, SqlxPostgresStorage},
+        SessionStorage, SessionStorageIndexed,
+    },
+    SessionIdentifier,
+};
+
+use crate::common::{setup_postgres, teardown_postgres, POSTGRES_URL};
+
+#[derive(Clone, Debug, PartialEq)]
+struct LargeSession {
+    user_id: String,
+    payload: String,
+}
+
+impl SessionIdentifier for LargeSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+impl SessionSqlx<sqlx::Postgres> for LargeSession {
+    type Data = String;
+    type Error = SessionError;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(format!("{}:{}", self.user_id, self.payload))
+    }
+
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        let (user_id, payload) = value.split_once(':').ok_or(SessionError::InvalidData)?;
+        Ok(Self {
+            user_id: user_id.to_owned(),
+            payload: payload.to_owned(),
+        })
+    }
+}
+
+async fn stored_data(pool: &sqlx::PgPool, table: &str, id: &str) -> Option<Option<String>> {
+    sqlx::query_scalar(&format!("SELECT data FROM \"{table}\" WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn offload_large_sessions() {
+    let (pool, db_name) = setup_postgres(POSTGRES_URL).await;
+    sqlx::query(
+        r#"CREATE TABLE large_sessions (
+          id      TEXT PRIMARY KEY,
+          data    TEXT,
+          user_id TEXT,
+          expires TIMESTAMPTZ NOT NULL
+      )"#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        r#"CREATE TABLE large_session_data (
+          id   TEXT PRIMARY KEY REFERENCES large_sessions (id) ON DELETE CASCADE,
+          data TEXT NOT NULL
+      )"#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let storage = SqlxPostgresStorage::builder()
+        .pool(pool.clone())
+        .table_name("large_sessions")
+        .offload_table("large_session_data")
+        .offload_threshold(100)
+        .build();
+    let client = Client::untracked(rocket::build()).await.unwrap();
+    let req = client.get("/");
+
+    let small = LargeSession {
+        user_id: "user1".to_owned(),
+        payload: "small".to_owned(),
+    };
+    let large = LargeSession {
+        user_id: "user1".to_owned(),
+        payload: "x".repeat(1000),
+    };
+    storage.save("small", small.clone(), 60).await.unwrap();
+    storage.save("large", large.clone(), 60).await.unwrap();
+
+    assert_eq!(
+        stored_data(&pool, "large_sessions", "small").await,
+        Some(Some("user1:small".to_owned()))
+    );
+    assert_eq!(
+        stored_data(&pool, "large_sessions", "large").await,
+        Some(None)
+    );
+    assert_eq!(
+        stored_data(&pool, "large_session_data", "small").await,
+        None
+    );
+    assert!(stored_data(&pool, "large_session_data", "large")
+        .await
+        .is_some());
+
+    let (loaded, _): (LargeSession, _) = storage
+        .load("small", None, req.inner().cookies())
+        .await
+        .unwrap();
+    assert_eq!(loaded, small);
+    let (loaded, _): (LargeSession, _) = storage
+        .load("large", Some(120), req.inner().cookies())
+        .await
+        .unwrap();
+    assert_eq!(loaded, large);
+
+    let mut sessions = SessionStorageIndexed::<LargeSession>::get_sessions_by_identifier(
+        &storage,
+        &"user1".to_owned(),
+    )
+    .await
+    .unwrap();
+    sessions.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].1, large);
+    assert_eq!(sessions[1].1, small);
+
+    let recent = SessionStorage::<LargeSession>::recently_active(&storage, 10)
+        .await
+        .unwrap();
+    assert_eq!(recent.len(), 2);
+
+    // Shrinking a session moves its data back to the sessions table
+    storage.save("large", small.clone(), 60).await.unwrap();
+    assert_eq!(
+        stored_data(&pool, "large_session_data", "large").await,
+        None
+    );
+    let (loaded, _): (LargeSession, _) = storage
+        .load("large", None, req.inner().cookies())
+        .await
+        .unwrap();
+    assert_eq!(loaded, small);
+
+    // Deleting a session deletes its offloaded data
+    storage.save("large", large.clone(), 60).await.unwrap();
+    storage.delete("large", large).await.unwrap();
+    assert_eq!(
+        stored_data(&pool, "large_session_data", "large").await,
+        None
+    );
+
+    teardown_postgres(pool, db_name).await;
+}
@@ -0,0 +1,38 @@
+#[macro_use]
+extern crate rocket;
+
+use std::time::Duration;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/verify")]
+fn start_verification(mut session: Session<String>) -> String {
+    session.set("otp-pending".to_owned());
+    session.set_ttl_duration(Duration::from_millis(300));
+    format!("{},{}", session.ttl(), session.ttl_duration().as_millis())
+}
+
+#[get("/verify")]
+fn get_verification(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+#[test]
+fn session_expires_with_millisecond_precision() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![start_verification, get_verification]);
+    let client = Client::tracked(rocket).unwrap();
+
+    // Seconds accessor is rounded up
+    let response = client.post("/verify").dispatch();
+    assert_eq!(response.into_string().unwrap(), "1,300");
+    assert_eq!(client.get("/verify").dispatch().status(), Status::Ok);
+
+    std::thread::sleep(Duration::from_millis(400));
+    assert_eq!(
+        client.get("/verify").dispatch().status(),
+        Status::Unauthorized
+    );
+}
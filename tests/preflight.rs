@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Method, Status},
+    local::blocking::Client,
+    routes, Build, Rocket,
+};
+use rocket_flex_session::{PreflightMode, RocketFlexSession, Session};
+
+#[get("/set_session")]
+fn set_session(mut session: Session<String>) -> &'static str {
+    session.set("active".to_owned());
+    "Session set"
+}
+
+#[get("/get_session")]
+fn get_session(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+#[options("/get_session")]
+fn options_session(session: Session<String>) -> Status {
+    match session.get() {
+        Some(_) => Status::NoContent,
+        None => Status::Unauthorized,
+    }
+}
+
+fn create_rocket(preflight: Option<PreflightMode>) -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| {
+                    if let Some(preflight) = preflight {
+                        opt.preflight = preflight;
+                    }
+                })
+                .build(),
+        )
+        .mount("/", routes![set_session, get_session, options_session])
+}
+
+#[test]
+fn test_head_request_read_only_by_default() {
+    let client = Client::tracked(create_rocket(None)).unwrap();
+    let response = client.head("/set_session").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.cookies().get_private("rocket").is_none());
+
+    // Session is still loaded for HEAD and OPTIONS requests
+    client.get("/set_session").dispatch();
+    let response = client.head("/get_session").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.req(Method::Options, "/get_session").dispatch();
+    assert_eq!(response.status(), Status::NoContent);
+}
+
+#[test]
+fn test_head_request_skipped() {
+    let client = Client::tracked(create_rocket(Some(PreflightMode::Skip))).unwrap();
+    client.get("/set_session").dispatch();
+    let response = client.get("/get_session").dispatch();
+    assert_eq!(response.into_string().unwrap(), "active");
+
+    let response = client.head("/get_session").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let response = client.req(Method::Options, "/get_session").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
+
+#[test]
+fn test_head_request_normal() {
+    let client = Client::tracked(create_rocket(Some(PreflightMode::Normal))).unwrap();
+    let response = client.head("/set_session").dispatch();
+    assert!(response.cookies().get_private("rocket").is_some());
+
+    let response = client.get("/get_session").dispatch();
+    assert_eq!(response.into_string().unwrap(), "active");
+}
@@ -0,0 +1,99 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Header, Status},
+    local::blocking::Client,
+};
+use rocket_flex_session::{
+    prefs::{self, preferred_language, Locale, Preferred, PrefsSession, Theme, Timezone},
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Default)]
+struct User;
+
+#[post("/prefs?<locale>&<timezone>")]
+fn set_prefs(mut session: Session<PrefsSession<User>>, locale: &str, timezone: &str) {
+    session.set_prefs(|prefs| {
+        prefs.locale = Some(locale.to_owned());
+        prefs.timezone = Some(timezone.to_owned());
+    });
+}
+
+#[get("/locale")]
+fn locale(locale: Preferred<Locale>) -> String {
+    locale.0 .0
+}
+
+#[get("/timezone")]
+fn timezone(timezone: Option<Preferred<Timezone>>) -> String {
+    timezone.map_or_else(|| "none".to_owned(), |tz| tz.0 .0)
+}
+
+#[get("/theme")]
+fn theme(theme: Option<Preferred<Theme>>) -> String {
+    theme.map_or_else(|| "none".to_owned(), |theme| theme.0 .0)
+}
+
+fn client() -> Client {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<PrefsSession<User>>::default())
+        .attach(prefs::fairing::<User>())
+        .mount("/", routes![set_prefs, locale, timezone, theme]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn preferences_fall_back_to_headers() {
+    let client = client();
+
+    let response = client
+        .get("/locale")
+        .header(Header::new("Accept-Language", "fr;q=0.8, de-CH, en;q=0.9"))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), "de-CH");
+    assert_eq!(client.get("/locale").dispatch().status(), Status::NotFound);
+
+    let response = client
+        .get("/theme")
+        .header(Header::new("Sec-CH-Prefers-Color-Scheme", "\"dark\""))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), "dark");
+    assert_eq!(
+        client.get("/timezone").dispatch().into_string().unwrap(),
+        "none"
+    );
+}
+
+#[test]
+fn preferences_from_session() {
+    let client = client();
+    let response = client
+        .post("/prefs?locale=nl-NL&timezone=Europe/Amsterdam")
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .get("/locale")
+        .header(Header::new("Accept-Language", "en-US"))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), "nl-NL");
+    assert_eq!(
+        client.get("/timezone").dispatch().into_string().unwrap(),
+        "Europe/Amsterdam"
+    );
+    assert_eq!(
+        client.get("/theme").dispatch().into_string().unwrap(),
+        "none"
+    );
+}
+
+#[test]
+fn parse_accept_language() {
+    assert_eq!(preferred_language("en-US,en;q=0.5"), Some("en-US"));
+    assert_eq!(preferred_language("da, en-gb;q=0.8, en;q=0.7"), Some("da"));
+    assert_eq!(preferred_language("*;q=1, fr;q=0.5"), Some("fr"));
+    assert_eq!(preferred_language("es;q=0, *"), None);
+    assert_eq!(preferred_language(""), None);
+}
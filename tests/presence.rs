@@ -0,0 +1,81 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    local::blocking::Client,
+    time::{Duration, OffsetDateTime},
+};
+use rocket_flex_session::{
+    clock::MockClock, storage::memory::MemoryStorageIndexed, RocketFlexSession, Session,
+    SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[post("/login/<user>")]
+fn login(mut session: Session<User>, user: &str) {
+    session.set(User(user.to_owned()));
+}
+
+#[get("/online?<since>")]
+async fn online(session: Session<'_, User>, since: i64) -> String {
+    let since = OffsetDateTime::from_unix_timestamp(since).unwrap();
+    match session.online_identifiers(since).await {
+        Ok(identifiers) => identifiers.join(","),
+        Err(e) => e.to_string(),
+    }
+}
+
+fn create_client(clock: &MockClock, sample_rate: f64) -> Client {
+    let fairing = RocketFlexSession::<User>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .clock(clock.clone())
+        .with_options(|opt| opt.presence_sample_rate = Some(sample_rate))
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, online]);
+    Client::tracked(rocket).unwrap()
+}
+
+fn get_online(client: &Client, since: OffsetDateTime) -> String {
+    let uri = format!("/online?since={}", since.unix_timestamp());
+    client.get(uri).dispatch().into_string().unwrap()
+}
+
+#[test]
+fn online_identifiers() {
+    let start = OffsetDateTime::now_utc();
+    let clock = MockClock::new(start);
+    let client = create_client(&clock, 1.0);
+
+    client.post("/login/alice").dispatch();
+    clock.advance(Duration::minutes(10));
+    client.post("/login/bob").dispatch();
+
+    assert_eq!(get_online(&client, start), "bob,alice");
+    assert_eq!(get_online(&client, start + Duration::minutes(5)), "bob");
+
+    // Any request with the session updates the last seen time (after the response)
+    clock.advance(Duration::minutes(10));
+    get_online(&client, start);
+    assert_eq!(get_online(&client, start + Duration::minutes(15)), "bob");
+}
+
+#[test]
+fn presence_not_sampled() {
+    let start = OffsetDateTime::now_utc();
+    let client = create_client(&MockClock::new(start), 0.0);
+
+    client.post("/login/alice").dispatch();
+    assert_eq!(get_online(&client, start), "");
+}
@@ -0,0 +1,122 @@
+#[macro_use]
+extern crate rocket;
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use rocket::{http::Status, local::blocking::Client, Request, State};
+use rocket_flex_session::{
+    principal::{Loader, Principal},
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct UserSession {
+    user_id: u32,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = u32;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id)
+    }
+}
+
+#[derive(Clone)]
+struct User {
+    name: String,
+}
+
+#[derive(Default)]
+struct Users {
+    users: Mutex<HashMap<u32, User>>,
+    loads: AtomicUsize,
+}
+
+struct UserLoader;
+
+#[rocket::async_trait]
+impl Loader<UserSession> for UserLoader {
+    type Output = User;
+    type Error = String;
+
+    async fn load(req: &Request<'_>, user_id: &u32) -> Result<Option<User>, String> {
+        let users = req.guard::<&State<Users>>().await.succeeded().unwrap();
+        users.loads.fetch_add(1, Ordering::Relaxed);
+        if *user_id == 0 {
+            return Err("Database error".to_owned());
+        }
+        Ok(users.users.lock().unwrap().get(user_id).cloned())
+    }
+}
+
+type CurrentUser = Principal<UserSession, UserLoader>;
+
+#[post("/login/<user_id>")]
+fn login(mut session: Session<UserSession>, user_id: u32) {
+    session.set(UserSession { user_id });
+}
+
+#[get("/profile")]
+fn profile(user: CurrentUser, again: CurrentUser) -> String {
+    format!(
+        "{} {} {}",
+        user.data.user_id, user.principal.name, again.name
+    )
+}
+
+fn client() -> Client {
+    let users = Users::default();
+    let alice = User {
+        name: "alice".to_owned(),
+    };
+    users.users.lock().unwrap().insert(1, alice);
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<UserSession>::default())
+        .manage(users)
+        .mount("/", routes![login, profile]);
+    Client::tracked(rocket).unwrap()
+}
+
+fn loads(client: &Client) -> usize {
+    let users = client.rocket().state::<Users>().unwrap();
+    users.loads.load(Ordering::Relaxed)
+}
+
+#[test]
+fn loads_principal_once_per_request() {
+    let client = client();
+    client.post("/login/1").dispatch();
+    let response = client.get("/profile").dispatch();
+    assert_eq!(response.into_string().unwrap(), "1 alice alice");
+    assert_eq!(loads(&client), 1);
+}
+
+#[test]
+fn rejects_missing_principal() {
+    let client = client();
+    assert_eq!(
+        client.get("/profile").dispatch().status(),
+        Status::Unauthorized
+    );
+    assert_eq!(loads(&client), 0);
+
+    client.post("/login/2").dispatch();
+    assert_eq!(
+        client.get("/profile").dispatch().status(),
+        Status::Unauthorized
+    );
+    assert_eq!(loads(&client), 1);
+
+    client.post("/login/0").dispatch();
+    assert_eq!(
+        client.get("/profile").dispatch().status(),
+        Status::InternalServerError
+    );
+}
@@ -0,0 +1,59 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{
+    error::SessionError, quota::MaxSessions, storage::memory::MemoryStorageIndexed,
+    RocketFlexSession, Session, SessionIdentifier,
+};
+
+#[derive(Clone)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[post("/login/<user>")]
+async fn login(mut session: Session<'_, User>, user: &str) -> Result<(), Status> {
+    match session.try_set(User(user.to_owned())).await {
+        Ok(()) => Ok(()),
+        Err(SessionError::QuotaExceeded {
+            limit: 2,
+            active_sessions: 2,
+        }) => Err(Status::Conflict),
+        Err(_) => Err(Status::InternalServerError),
+    }
+}
+
+#[test]
+fn max_sessions_per_identifier() {
+    let fairing = RocketFlexSession::<User>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .quota(MaxSessions(2))
+        .build();
+    let rocket = rocket::build().attach(fairing).mount("/", routes![login]);
+    let client = Client::untracked(rocket).unwrap();
+
+    let first = client.post("/login/alice").dispatch();
+    assert_eq!(first.status(), Status::Ok);
+    let cookie = first.cookies().get_private("rocket").unwrap();
+    assert_eq!(client.post("/login/alice").dispatch().status(), Status::Ok);
+
+    // A third session is rejected, but other identifiers aren't affected
+    let third = client.post("/login/alice").dispatch();
+    assert_eq!(third.status(), Status::Conflict);
+    assert!(third.cookies().get_private("rocket").is_none());
+    assert_eq!(client.post("/login/bob").dispatch().status(), Status::Ok);
+
+    // Existing sessions of the identifier can still be updated
+    let response = client
+        .post("/login/alice")
+        .private_cookie(cookie)
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
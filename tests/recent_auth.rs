@@ -0,0 +1,89 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::Status,
+    local::blocking::Client,
+    time::{Duration, OffsetDateTime},
+    State,
+};
+use rocket_flex_session::{
+    clock::{Clock, MockClock},
+    RecentlyAuthenticated, RocketFlexSession, Session, SessionAuthTime,
+};
+
+#[derive(Clone)]
+struct MySession {
+    user_id: String,
+    logged_in_at: Option<OffsetDateTime>,
+}
+
+impl SessionAuthTime for MySession {
+    fn authenticated_at(&self) -> Option<OffsetDateTime> {
+        self.logged_in_at
+    }
+}
+
+#[post("/login")]
+fn login(mut session: Session<MySession>, clock: &State<MockClock>) {
+    session.set(MySession {
+        user_id: "alice".to_owned(),
+        logged_in_at: Some(clock.now()),
+    });
+}
+
+#[post("/guest")]
+fn guest(mut session: Session<MySession>) {
+    session.set(MySession {
+        user_id: "guest".to_owned(),
+        logged_in_at: None,
+    });
+}
+
+#[post("/change-email")]
+fn change_email(auth: RecentlyAuthenticated<MySession, 300>) -> String {
+    auth.data.user_id
+}
+
+fn client(clock: &MockClock) -> Client {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<MySession>::builder()
+                .clock(clock.clone())
+                .build(),
+        )
+        .manage(clock.clone())
+        .mount("/", routes![login, guest, change_email]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn requires_recent_login() {
+    let clock = MockClock::default();
+    let client = client(&clock);
+
+    let response = client.post("/change-email").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    client.post("/login").dispatch();
+    let response = client.post("/change-email").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), "alice");
+
+    clock.advance(Duration::minutes(6));
+    let response = client.post("/change-email").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    // Logging in again satisfies the guard
+    client.post("/login").dispatch();
+    let response = client.post("/change-email").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn rejects_unauthenticated_session() {
+    let client = client(&MockClock::default());
+    client.post("/guest").dispatch();
+    let response = client.post("/change-email").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
@@ -0,0 +1,54 @@
+use rocket_flex_session::{
+    error::SessionError, storage::memory::MemoryStorageIndexed, RocketFlexSession,
+    SessionIdentifier,
+};
+use serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
+struct UserSession {
+    user_id: String,
+    email: String,
+}
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.user_id.clone())
+    }
+}
+
+#[rocket::async_test]
+async fn export_uses_redacted_data() {
+    let fairing = RocketFlexSession::<UserSession>::builder()
+        .storage(MemoryStorageIndexed::default())
+        .redact(|data: &UserSession| format!("user {}", data.user_id))
+        .build();
+    let storage = fairing.indexed_storage().unwrap();
+    let session = UserSession {
+        user_id: "alice".to_owned(),
+        email: "alice@example.com".to_owned(),
+    };
+    storage.save("session_id_1", session, 3600).await.unwrap();
+
+    let export = fairing
+        .export_sessions_for_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert_eq!(export["sessions"][0]["data"], "user alice");
+    assert!(!export.to_string().contains("alice@example.com"));
+
+    assert_eq!(
+        fairing.erase_identifier(&"alice".to_owned()).await.unwrap(),
+        1
+    );
+}
+
+#[test]
+fn redacted_error_messages() {
+    let error = SessionError::Parsing("invalid email: alice@example.com".into());
+    assert!(error.to_string().contains("alice@example.com"));
+    assert_eq!(error.redacted(), "Failed to parse session");
+
+    assert_eq!(SessionError::NotFound.redacted(), "Session not found");
+}
@@ -93,7 +93,11 @@ fn admin_only_route(admin: Admin) -> String {
 fn create_rocket() -> Rocket<Build> {
     rocket::build()
         .attach(RocketFlexSession::<User>::default())
-        .attach(RocketFlexSession::<HashMap<String, String>>::default())
+        .attach(
+            RocketFlexSession::<HashMap<String, String>>::builder()
+                .with_options(|opt| opt.cookie_name = "hash_session".to_owned())
+                .build(),
+        )
         .mount("/", routes![get_user, admin_only_route, login, logout])
 }
 
@@ -0,0 +1,59 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client};
+use rocket_flex_session::{
+    responder::{SessionJson, SessionRedirect},
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone)]
+struct User {
+    name: String,
+}
+
+#[post("/login/<name>")]
+fn login(name: &str) -> SessionRedirect<User> {
+    let user = User {
+        name: name.to_owned(),
+    };
+    SessionRedirect::to("/user").with(move |session| session.set(user))
+}
+
+#[post("/rename/<name>")]
+fn rename(_session: Session<User>, name: &str) -> SessionJson<User, bool> {
+    let name = name.to_owned();
+    SessionJson::<User, _>::new(true).with(move |session| {
+        session.tap_mut(|user| {
+            if let Some(user) = user {
+                user.name = name;
+            }
+        })
+    })
+}
+
+#[get("/user")]
+fn user(session: Session<User>) -> Option<String> {
+    session.get().map(|user| user.name)
+}
+
+#[test]
+fn responders_update_session() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<User>::default())
+        .mount("/", routes![login, rename, user]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.post("/login/alice").dispatch();
+    assert_eq!(response.status(), Status::SeeOther);
+    assert_eq!(response.headers().get_one("Location"), Some("/user"));
+    assert_eq!(
+        client.get("/user").dispatch().into_string().unwrap(),
+        "alice"
+    );
+
+    // The existing session is updated if it was loaded by the request guard
+    let response = client.post("/rename/bob").dispatch();
+    assert_eq!(response.into_string().unwrap(), "true");
+    assert_eq!(client.get("/user").dispatch().into_string().unwrap(), "bob");
+}
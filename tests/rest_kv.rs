@@ -0,0 +1,197 @@
+#[macro_use]
+extern crate rocket;
+
+use std::{collections::HashMap, sync::Mutex};
+
+use rocket::{
+    http::Status,
+    local::asynchronous::Client,
+    request::{FromRequest, Outcome},
+    Request, State,
+};
+use rocket_flex_session::{
+    storage::rest_kv::{RestKvStorage, RestKvTtl, SessionRestKv},
+    RocketFlexSession, Session,
+};
+
+const TOKEN: &str = "test-token";
+
+/// Simple in-memory KV service, storing values and the last TTL received
+type KvStore = Mutex<HashMap<String, (String, u32)>>;
+
+/// Keys of all values saved to the KV service
+static PUT_KEYS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+struct Authorized;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Authorized {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.headers().get_one("Authorization") {
+            Some(auth) if auth == format!("Bearer {TOKEN}") => Outcome::Success(Authorized),
+            _ => Outcome::Error((Status::Unauthorized, ())),
+        }
+    }
+}
+
+#[get("/kv/<key>")]
+fn kv_get(_auth: Authorized, key: &str, store: &State<KvStore>) -> Option<String> {
+    let store = store.lock().unwrap();
+    store.get(key).map(|(value, _)| value.clone())
+}
+
+#[put("/kv/<key>?<expiration_ttl>", data = "<value>")]
+fn kv_put(
+    _auth: Authorized,
+    key: &str,
+    expiration_ttl: u32,
+    value: String,
+    store: &State<KvStore>,
+) {
+    PUT_KEYS.lock().unwrap().push(key.to_owned());
+    let mut store = store.lock().unwrap();
+    store.insert(key.to_owned(), (value, expiration_ttl));
+}
+
+#[delete("/kv/<key>")]
+fn kv_delete(_auth: Authorized, key: &str, store: &State<KvStore>) -> Status {
+    match store.lock().unwrap().remove(key) {
+        Some(_) => Status::Ok,
+        None => Status::NotFound,
+    }
+}
+
+#[derive(Clone)]
+struct SessionData {
+    user_id: String,
+}
+
+impl SessionRestKv for SessionData {
+    type Error = std::convert::Infallible;
+
+    fn into_kv(self) -> Result<String, Self::Error> {
+        Ok(self.user_id)
+    }
+
+    fn from_kv(value: String) -> Result<Self, Self::Error> {
+        Ok(Self { user_id: value })
+    }
+}
+
+#[post("/login")]
+fn login(mut session: Session<SessionData>) {
+    session.set(SessionData {
+        user_id: "123".to_owned(),
+    });
+}
+
+#[get("/user")]
+fn user(session: Session<SessionData>) -> Option<String> {
+    session.get().map(|data| data.user_id)
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<SessionData>) {
+    session.delete();
+}
+
+/// Launch the KV service on a free local port, and return the port
+async fn launch_kv_service() -> u16 {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let config = rocket::Config {
+        port,
+        log_level: rocket::config::LogLevel::Off,
+        ..rocket::Config::debug_default()
+    };
+    let kv_service = rocket::custom(config)
+        .manage(KvStore::default())
+        .mount("/", routes![kv_get, kv_put, kv_delete])
+        .ignite()
+        .await
+        .unwrap();
+    rocket::tokio::spawn(kv_service.launch());
+
+    // Wait for the service to start listening
+    for _ in 0..50 {
+        if rocket::tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_ok()
+        {
+            break;
+        }
+        rocket::tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    port
+}
+
+#[rocket::async_test]
+async fn test_rest_kv_storage() {
+    let port = launch_kv_service().await;
+    let storage = RestKvStorage::builder()
+        .get_url(format!("http://127.0.0.1:{port}/kv/{{key}}"))
+        .bearer_token(TOKEN)
+        .ttl_mode(RestKvTtl::QueryParam("expiration_ttl".to_owned()))
+        .min_ttl(60)
+        .build();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<SessionData>::builder()
+                .storage(storage)
+                .build(),
+        )
+        .mount("/", routes![login, user, logout]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let response = client.get("/user").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    client.post("/login").dispatch().await;
+    let response = client.get("/user").dispatch().await;
+    assert_eq!(response.into_string().await.unwrap(), "123");
+
+    client.post("/logout").dispatch().await;
+    let response = client.get("/user").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[rocket::async_test]
+async fn test_rest_kv_rolling_refresh() {
+    let port = launch_kv_service().await;
+    let storage = RestKvStorage::builder()
+        .get_url(format!("http://127.0.0.1:{port}/kv/{{key}}"))
+        .bearer_token(TOKEN)
+        .ttl_mode(RestKvTtl::QueryParam("expiration_ttl".to_owned()))
+        .prefix("rolling:")
+        .build();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<SessionData>::builder()
+                .storage(storage)
+                .with_options(|opt| opt.rolling = true)
+                .build(),
+        )
+        .mount("/", routes![login, user]);
+    let client = Client::tracked(rocket).await.unwrap();
+    let puts = || {
+        let keys = PUT_KEYS.lock().unwrap();
+        keys.iter()
+            .filter(|key| key.starts_with("rolling:"))
+            .count()
+    };
+
+    client.post("/login").dispatch().await;
+    assert_eq!(puts(), 1);
+
+    // The expiration is barely extended, so the session isn't saved again
+    for _ in 0..3 {
+        let response = client.get("/user").dispatch().await;
+        assert_eq!(response.into_string().await.unwrap(), "123");
+    }
+    assert_eq!(puts(), 1);
+}
@@ -0,0 +1,128 @@
+#[macro_use]
+extern crate rocket;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use rocket::{
+    async_trait,
+    http::{Cookie, SameSite},
+    local::blocking::Client,
+};
+use rocket_flex_session::{
+    storage::{rocket_session_store::from_rocket_session_store, SessionStore},
+    RocketFlexSession, Session,
+};
+use rocket_session_store::{SessionResult, Store};
+
+/// Store that keeps the values and durations in a shared map
+#[derive(Clone, Default)]
+struct TestStore(Arc<Mutex<HashMap<String, (String, Duration)>>>);
+
+#[async_trait]
+impl Store for TestStore {
+    type Value = String;
+
+    async fn get(&self, id: &str) -> SessionResult<Option<String>> {
+        Ok(self
+            .0
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|(value, _)| value.clone()))
+    }
+
+    async fn set(&self, id: &str, value: String, duration: Duration) -> SessionResult<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(id.to_owned(), (value, duration));
+        Ok(())
+    }
+
+    async fn touch(&self, id: &str, duration: Duration) -> SessionResult<()> {
+        if let Some((_, d)) = self.0.lock().unwrap().get_mut(id) {
+            *d = duration;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> SessionResult<()> {
+        self.0.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: &str) -> String {
+    session.set(name.to_owned());
+    session.id().unwrap()
+}
+
+#[get("/user")]
+fn user(session: Session<String>) -> Option<String> {
+    session.get()
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+#[test]
+fn migrates_session_store_fairing() {
+    let store = TestStore::default();
+    let session_store = rocket_session_store::SessionStore {
+        store: Box::new(store.clone()),
+        name: "token".into(),
+        duration: Duration::from_secs(3600),
+        cookie_builder: Cookie::build(("", ""))
+            .path("/app")
+            .same_site(SameSite::Strict),
+    };
+    let fairing = RocketFlexSession::<String>::builder()
+        .with_rocket_session_store(session_store)
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/app", routes![login, user, logout]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.post("/app/login/alice").dispatch();
+    let cookie = response.cookies().get_private("token").unwrap();
+    assert_eq!(cookie.path(), Some("/app"));
+    assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+    assert_eq!(cookie.max_age().map(|a| a.whole_seconds()), Some(3600));
+    let id = response.into_string().unwrap();
+    assert_eq!(
+        store.0.lock().unwrap().get(&id).cloned(),
+        Some(("alice".to_owned(), Duration::from_secs(3600)))
+    );
+
+    let response = client.get("/app/user").dispatch();
+    assert_eq!(response.into_string().unwrap(), "alice");
+
+    client.post("/app/logout").dispatch();
+    assert!(store.0.lock().unwrap().is_empty());
+}
+
+#[rocket::async_test]
+async fn wraps_existing_store() {
+    let store = TestStore::default();
+    store
+        .set("id", "bob".to_owned(), Duration::from_secs(60))
+        .await
+        .unwrap();
+    let adapter = from_rocket_session_store(store.clone()).ttl(60);
+
+    let (data, ttl) = adapter.load("id", None).await.unwrap();
+    assert_eq!((data.as_str(), ttl), ("bob", 60));
+    let (_, ttl) = adapter.load("id", Some(120)).await.unwrap();
+    assert_eq!(ttl, 120);
+    assert_eq!(store.0.lock().unwrap()["id"].1, Duration::from_secs(120));
+
+    assert!(!adapter.exists("other").await.unwrap());
+}
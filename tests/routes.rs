@@ -0,0 +1,109 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Header, Status},
+    local::blocking::Client,
+};
+use rocket_flex_session::{
+    routes::{session_routes, sync_routes},
+    RocketFlexSession, Session,
+};
+use serde_json::Value;
+
+#[post("/login")]
+fn login(mut session: Session<String>) {
+    session.set("user".to_owned());
+}
+
+#[post("/shorten")]
+fn shorten(mut session: Session<String>) {
+    session.set_ttl(10);
+}
+
+#[test]
+fn expiry_and_renew_routes() {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| opt.ttl = Some(60))
+                .build(),
+        )
+        .mount("/", routes![login, shorten])
+        .mount("/session", session_routes::<String>());
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.get("/session/expiry").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let response = client.post("/session/renew").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    client.post("/login").dispatch();
+    client.post("/shorten").dispatch();
+    let expiry: Value = client
+        .get("/session/expiry")
+        .dispatch()
+        .into_json()
+        .unwrap();
+    assert!(expiry["ttl"].as_u64().unwrap() <= 10);
+    assert!(expiry["expires"].as_i64().is_some());
+
+    let renewed: Value = client
+        .post("/session/renew")
+        .dispatch()
+        .into_json()
+        .unwrap();
+    assert_eq!(renewed["ttl"], 60);
+    let expiry: Value = client
+        .get("/session/expiry")
+        .dispatch()
+        .into_json()
+        .unwrap();
+    assert!(expiry["ttl"].as_u64().unwrap() > 10);
+}
+
+#[post("/rename/<name>")]
+fn rename(mut session: Session<String>, name: String) {
+    session.set(name);
+}
+
+#[test]
+fn sync_route() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![rename])
+        .mount("/session", sync_routes(|name: &String| name.to_uppercase()));
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.get("/session/sync").dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+
+    client.post("/rename/alice").dispatch();
+    let response = client.get("/session/sync").dispatch();
+    let etag = response.headers().get_one("ETag").unwrap().to_owned();
+    let sync: Value = response.into_json().unwrap();
+    assert_eq!(sync["data"], "ALICE");
+    let version = sync["version"].as_str().unwrap().to_owned();
+    assert_eq!(etag, format!("\"{version}\""));
+
+    // Unchanged since the client's version
+    let response = client
+        .get(format!("/session/sync?since={version}"))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+    let response = client
+        .get("/session/sync")
+        .header(Header::new("If-None-Match", etag))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+
+    // Changed since the client's version
+    client.post("/rename/bob").dispatch();
+    let response = client
+        .get(format!("/session/sync?since={version}"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let sync: Value = response.into_json().unwrap();
+    assert_eq!(sync["data"], "BOB");
+    assert_ne!(sync["version"], version.as_str());
+}
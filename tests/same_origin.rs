@@ -0,0 +1,124 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Header, Status},
+    local::blocking::Client,
+    routes, Build, Rocket,
+};
+use rocket_flex_session::{RocketFlexSession, SameOrigin, Session};
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> &'static str {
+    session.set("user".to_owned());
+    "Logged in"
+}
+
+#[post("/update")]
+fn update(_origin: SameOrigin<String>, mut session: Session<String>) -> &'static str {
+    session.set("updated".to_owned());
+    "Updated"
+}
+
+fn create_rocket() -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| {
+                    opt.trusted_origins = vec!["https://app.example.com".to_owned()];
+                })
+                .build(),
+        )
+        .mount("/", routes![login, update])
+}
+
+#[test]
+fn test_no_active_session() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    let response = client
+        .post("/update")
+        .header(Header::new("Origin", "https://evil.example.com"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn test_cross_site_request_rejected() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    client.post("/login").dispatch();
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Origin", "https://evil.example.com"))
+        .header(Header::new("Sec-Fetch-Site", "cross-site"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Sec-Fetch-Site", "cross-site"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}
+
+#[test]
+fn test_same_origin_request_accepted() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    client.post("/login").dispatch();
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Sec-Fetch-Site", "same-origin"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Host", "localhost:8000"))
+        .header(Header::new("Origin", "http://localhost:8000"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Origin", "https://app.example.com"))
+        .header(Header::new("Sec-Fetch-Site", "same-site"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // Non-browser clients don't send fetch metadata
+    let response = client.post("/update").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
+
+#[test]
+fn test_origin_scheme_must_match() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    client.post("/login").dispatch();
+
+    // An HTTP origin with the same host isn't the HTTPS site
+    let response = client
+        .post("/update")
+        .header(Header::new("Host", "example.com"))
+        .header(Header::new("X-Forwarded-Proto", "https"))
+        .header(Header::new("Origin", "http://example.com"))
+        .header(Header::new("Sec-Fetch-Site", "cross-site"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+
+    let response = client
+        .post("/update")
+        .header(Header::new("Host", "example.com"))
+        .header(Header::new("X-Forwarded-Proto", "https"))
+        .header(Header::new("Origin", "https://example.com"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    // Without TLS or a proxy header, the site is served over HTTP
+    let response = client
+        .post("/update")
+        .header(Header::new("Host", "example.com"))
+        .header(Header::new("Origin", "https://example.com"))
+        .dispatch();
+    assert_eq!(response.status(), Status::Forbidden);
+}
@@ -0,0 +1,119 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::Status,
+    local::blocking::Client,
+    time::{Duration, OffsetDateTime},
+};
+use rocket_flex_session::{clock::MockClock, scope::ScopedSession, RocketFlexSession, Session};
+
+#[post("/login/<user>")]
+fn login(mut session: Session<ScopedSession>, user: &str) -> Result<(), Status> {
+    session
+        .set_scope("auth", user, 3600)
+        .and_then(|_| session.set_scope("cart", vec!["book"], 60))
+        .map_err(|_| Status::InternalServerError)
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<ScopedSession>) {
+    session.remove_scope("auth");
+}
+
+#[post("/clear_cart")]
+fn clear_cart(mut session: Session<ScopedSession>) {
+    session.remove_scope("cart");
+}
+
+#[get("/")]
+fn index(session: Session<ScopedSession>) -> String {
+    let user: Option<String> = session.scope("auth");
+    let cart: Option<Vec<String>> = session.scope("cart");
+    format!(
+        "{}:{}:{}",
+        user.unwrap_or_default(),
+        cart.map_or(0, |cart| cart.len()),
+        session.id().is_some()
+    )
+}
+
+#[get("/expires")]
+fn expires(session: Session<ScopedSession>) -> String {
+    session.expires_at().unix_timestamp().to_string()
+}
+
+fn client(clock: &MockClock) -> Client {
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<ScopedSession>::builder()
+                .clock(clock.clone())
+                .build(),
+        )
+        .mount("/", routes![login, logout, clear_cart, index, expires]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn scopes_expire_independently() {
+    let start = OffsetDateTime::now_utc();
+    let clock = MockClock::new(start);
+    let client = client(&clock);
+
+    client.post("/login/alice").dispatch();
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        "alice:1:true"
+    );
+
+    // The session lasts as long as the auth scope
+    let expires: i64 = client
+        .get("/expires")
+        .dispatch()
+        .into_string()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((expires - (start.unix_timestamp() + 3600)).abs() <= 1);
+
+    // The cart expires, but the login doesn't
+    clock.advance(Duration::minutes(2));
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        "alice:0:true"
+    );
+}
+
+#[test]
+fn session_is_deleted_with_last_scope() {
+    let clock = MockClock::default();
+    let client = client(&clock);
+
+    client.post("/login/alice").dispatch();
+    client.post("/logout").dispatch();
+    assert_eq!(client.get("/").dispatch().into_string().unwrap(), ":1:true");
+
+    client.post("/clear_cart").dispatch();
+    assert_eq!(
+        client.get("/").dispatch().into_string().unwrap(),
+        ":0:false"
+    );
+}
+
+#[test]
+fn scoped_session_data() {
+    let now = OffsetDateTime::now_utc();
+    let mut data = ScopedSession::default();
+    data.insert("cart", "book".into(), 60, now);
+    data.insert("auth", "alice".into(), 3600, now);
+
+    assert_eq!(data.get("cart", now), Some(&"book".into()));
+    let later = now + Duration::minutes(2);
+    assert_eq!(data.get("cart", later), None);
+    assert_eq!(data.get("auth", later), Some(&"alice".into()));
+
+    data.remove_expired(later);
+    assert_eq!(data.expires("cart"), None);
+    data.remove("auth");
+    assert!(data.is_empty());
+}
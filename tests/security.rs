@@ -0,0 +1,19 @@
+use rocket_flex_session::security::{constant_time_eq, session_id_eq};
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"", b""));
+    assert!(constant_time_eq(b"abc", b"abc"));
+    assert!(!constant_time_eq(b"abc", b"abd"));
+    assert!(!constant_time_eq(b"abc", b"ab"));
+    assert!(!constant_time_eq(b"ab", b"abc"));
+}
+
+#[test]
+fn test_session_id_eq() {
+    let id = "a1B2c3D4e5F6g7H8i9J0";
+    assert!(session_id_eq(id, id));
+    assert!(!session_id_eq(id, "a1B2c3D4e5F6g7H8i9J1"));
+    assert!(!session_id_eq(id, "A1B2c3D4e5F6g7H8i9J0"));
+    assert!(!session_id_eq(id, ""));
+}
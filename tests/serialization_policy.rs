@@ -0,0 +1,110 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use rocket::{async_trait, http::Status, local::blocking::Client};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    policy::SerializationErrorPolicy,
+    storage::{memory::MemoryStorage, SessionContext, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+/// Storage that fails to serialize data containing "unserializable"
+#[derive(Default)]
+struct PickyStorage(MemoryStorage<String>);
+
+#[async_trait]
+impl SessionStorage<String> for PickyStorage {
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        self.0.load(id, ttl, cookie_jar).await
+    }
+
+    async fn save(&self, id: &str, data: String, ttl: u32) -> SessionResult<()> {
+        if data.contains("unserializable") {
+            return Err(SessionError::Serialization("invalid data".into()));
+        }
+        self.0.save(id, data, ttl).await
+    }
+
+    async fn delete(&self, id: &str, data: String) -> SessionResult<()> {
+        self.0.delete(id, data).await
+    }
+}
+
+#[post("/set/<value>")]
+fn set(mut session: Session<String>, value: &str) -> &'static str {
+    session.set(value.to_owned());
+    "ok"
+}
+
+#[get("/get")]
+fn get(session: Session<String>) -> Option<String> {
+    session.tap(|data| data.cloned())
+}
+
+fn client(policy: SerializationErrorPolicy<String>, errors: Arc<AtomicU32>) -> Client {
+    let fairing = RocketFlexSession::<String>::builder()
+        .storage(PickyStorage::default())
+        .serialization_error_policy(policy)
+        .on_save_error(move |_id, error| {
+            assert!(matches!(error, SessionError::Serialization(_)));
+            errors.fetch_add(1, Ordering::SeqCst);
+        })
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![set, get]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn keep_previous_data() {
+    let errors = Arc::new(AtomicU32::new(0));
+    let client = client(SerializationErrorPolicy::default(), errors.clone());
+    client.post("/set/first").dispatch();
+
+    let response = client.post("/set/unserializable").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+    let response = client.get("/get").dispatch();
+    assert_eq!(response.into_string().unwrap(), "first");
+}
+
+#[test]
+fn save_fallback_data() {
+    let errors = Arc::new(AtomicU32::new(0));
+    let policy = SerializationErrorPolicy::fallback(|data: &String| {
+        Some(data.replace("unserializable", "fallback"))
+    });
+    let client = client(policy, errors.clone());
+
+    let response = client.post("/set/unserializable").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(errors.load(Ordering::SeqCst), 0);
+    let response = client.get("/get").dispatch();
+    assert_eq!(response.into_string().unwrap(), "fallback");
+}
+
+#[test]
+fn fail_response() {
+    let errors = Arc::new(AtomicU32::new(0));
+    let client = client(SerializationErrorPolicy::Fail, errors.clone());
+
+    let response = client.post("/set/unserializable").dispatch();
+    assert_eq!(response.status(), Status::InternalServerError);
+    assert!(response.into_string().unwrap_or_default().is_empty());
+    assert_eq!(errors.load(Ordering::SeqCst), 1);
+
+    let response = client.post("/set/valid").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+}
@@ -0,0 +1,94 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use rocket::async_trait;
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{
+        erased::SessionCodec,
+        layer::StorageStack,
+        memory::MemoryStorage,
+        serialized::{PassthroughCodec, SerializeOnceLayer, SerializedSession},
+        tiered::{MokaCache, TieredLayer},
+        wal::{WalLayer, WalRecord, WalSink},
+        NoopSessionContext, SessionStorage,
+    },
+};
+
+/// UTF-8 codec that counts encodes and decodes
+#[derive(Clone, Default)]
+struct CountingCodec {
+    encodes: Arc<AtomicU32>,
+    decodes: Arc<AtomicU32>,
+}
+
+impl SessionCodec<String> for CountingCodec {
+    fn encode(&self, data: &String) -> SessionResult<Vec<u8>> {
+        self.encodes.fetch_add(1, Ordering::Relaxed);
+        Ok(data.as_bytes().to_vec())
+    }
+
+    fn decode(&self, value: &[u8]) -> SessionResult<String> {
+        self.decodes.fetch_add(1, Ordering::Relaxed);
+        String::from_utf8(value.to_vec()).map_err(|_| SessionError::InvalidData)
+    }
+}
+
+#[derive(Clone, Default)]
+struct MemoryWal(Arc<Mutex<Vec<WalRecord>>>);
+
+#[async_trait]
+impl WalSink for MemoryWal {
+    async fn append(&self, record: &WalRecord) -> SessionResult<()> {
+        self.0.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+
+    async fn read(&self) -> SessionResult<Vec<WalRecord>> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+}
+
+#[rocket::async_test]
+async fn serializes_once_for_all_layers() {
+    let codec = CountingCodec::default();
+    let wal = MemoryWal::default();
+    let storage = StorageStack::new(MemoryStorage::<SerializedSession<String>>::default())
+        .layer(WalLayer::new(wal.clone(), PassthroughCodec))
+        .layer(TieredLayer::new(MokaCache::default()))
+        .layer(SerializeOnceLayer::new(codec.clone()))
+        .build();
+
+    storage.save("id", "alice".to_owned(), 60).await.unwrap();
+    assert_eq!(codec.encodes.load(Ordering::Relaxed), 1);
+    let records = wal.read().await.unwrap();
+    assert_eq!(records[0].data.as_deref(), Some(b"alice".as_slice()));
+
+    // Cache hits use the data that was saved
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "alice");
+    assert_eq!(codec.decodes.load(Ordering::Relaxed), 0);
+}
+
+#[rocket::async_test]
+async fn decodes_bytes_from_storage() {
+    let codec = CountingCodec::default();
+    let base_storage = MemoryStorage::<SerializedSession<String>>::default();
+    base_storage
+        .save("id", SerializedSession::from_bytes(b"bob".as_slice()), 60)
+        .await
+        .unwrap();
+    let storage = StorageStack::new(base_storage)
+        .layer(SerializeOnceLayer::new(codec.clone()))
+        .build();
+
+    let (data, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "bob");
+    assert_eq!(codec.decodes.load(Ordering::Relaxed), 1);
+
+    storage.delete("id", data).await.unwrap();
+    let result = storage.load("id", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
@@ -0,0 +1,63 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{http::Status, local::blocking::Client, routes, Build, Rocket};
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[post("/login")]
+fn login(mut session: Session<String>) -> &'static str {
+    session.set("user".to_owned());
+    "Logged in"
+}
+
+#[get("/page/<name>")]
+fn page(session: Session<String>, name: &str) -> Result<String, Status> {
+    match session.get() {
+        Some(_) => Ok(format!("Page {name}")),
+        None => Err(Status::Unauthorized),
+    }
+}
+
+#[get("/activity")]
+async fn activity(session: Session<'_, String>) -> String {
+    let entries = session.recent_activity().await.unwrap();
+    entries
+        .iter()
+        .map(|entry| format!("{} {}", entry.status, entry.path))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn create_rocket() -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .with_options(|opt| opt.activity_log_size = Some(2))
+                .build(),
+        )
+        .mount("/", routes![login, page, activity])
+}
+
+#[test]
+fn test_no_activity_without_session() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    client.get("/page/home").dispatch();
+
+    let response = client.get("/activity").dispatch();
+    assert_eq!(response.into_string().unwrap(), "");
+}
+
+#[test]
+fn test_recent_activity() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    client.post("/login").dispatch();
+    client.get("/page/home").dispatch();
+    client.get("/page/settings").dispatch();
+
+    // Only the 2 most recent requests are kept, most recent first
+    let response = client.get("/activity").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        "200 /page/settings,200 /page/home"
+    );
+}
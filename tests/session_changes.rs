@@ -0,0 +1,182 @@
+mod common;
+
+use std::{future::Future, pin::Pin};
+
+use rocket::futures::{FutureExt, StreamExt};
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        memory::MemoryStorageIndexed,
+        redis::{RedisFormat, RedisFredStorage, RedisValue, SessionRedis},
+        sqlx::{SessionSqlx, SqlxPostgresStorage},
+        SessionChange, SessionStorageIndexed,
+    },
+    SessionIdentifier,
+};
+use test_case::test_case;
+
+use crate::common::{
+    setup_postgres, setup_redis_fred, teardown_postgres, teardown_redis_fred, POSTGRES_URL,
+};
+
+#[derive(Clone)]
+struct UserSession(String);
+
+impl SessionIdentifier for UserSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+
+    fn tags(&self) -> Vec<String> {
+        vec!["all".to_owned()]
+    }
+}
+
+impl SessionRedis for UserSession {
+    const REDIS_FORMAT: RedisFormat = RedisFormat::String;
+
+    type Error = SessionError;
+
+    fn into_redis(self) -> Result<RedisValue, Self::Error> {
+        Ok(RedisValue::String(self.0))
+    }
+
+    fn from_redis(value: RedisValue) -> Result<Self, Self::Error> {
+        let value = value.into_string().map_err(|_| SessionError::InvalidData)?;
+        Ok(Self(value))
+    }
+}
+
+impl SessionSqlx<sqlx::Postgres> for UserSession {
+    type Error = std::convert::Infallible;
+    type Data = String;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(Self(value))
+    }
+}
+
+fn user(name: &str) -> UserSession {
+    UserSession(name.to_owned())
+}
+
+async fn create_storage(
+    storage_case: &str,
+) -> (
+    Box<dyn SessionStorageIndexed<UserSession>>,
+    Option<Pin<Box<dyn Future<Output = ()>>>>,
+) {
+    match storage_case {
+        "memory" => {
+            let storage = MemoryStorageIndexed::<UserSession>::default();
+            (Box::new(storage), None)
+        }
+        "redis" => {
+            let (pool, prefix) = setup_redis_fred().await;
+            let storage = RedisFredStorage::builder()
+                .pool(pool.clone())
+                .prefix(&prefix)
+                .index_prefix(format!("{prefix}user:"))
+                .tag_prefix(format!("{prefix}tag:"))
+                .notify_changes(true)
+                .build();
+            let cleanup_task = teardown_redis_fred(pool, prefix).boxed();
+            (Box::new(storage), Some(cleanup_task))
+        }
+        "sqlx_postgres" => {
+            let (pool, db_name) = setup_postgres(POSTGRES_URL).await;
+            let storage = SqlxPostgresStorage::builder()
+                .pool(pool.clone())
+                .table_name("sessions")
+                .notify_changes(true)
+                .build();
+            let cleanup_task = teardown_postgres(pool, db_name).boxed();
+            (Box::new(storage), Some(cleanup_task))
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test_case("memory"; "Memory")]
+#[test_case("redis"; "Redis Fred")]
+#[test_case("sqlx_postgres"; "Sqlx Postgres")]
+#[rocket::async_test]
+async fn notifies_identifier_changes(storage_case: &str) {
+    let (storage, cleanup_task) = create_storage(storage_case).await;
+    storage.setup().await.unwrap();
+
+    let mut changes = storage
+        .subscribe_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+
+    storage.save("bob1", user("bob"), 60).await.unwrap();
+    storage.save("alice1", user("alice"), 60).await.unwrap();
+    storage.save("alice2", user("alice"), 60).await.unwrap();
+    storage.delete("alice1", user("alice")).await.unwrap();
+    storage.save("alice3", user("alice"), 60).await.unwrap();
+    storage
+        .invalidate_sessions_by_identifier(&"alice".to_owned(), Some("alice2"))
+        .await
+        .unwrap();
+
+    // Changes to other identifiers aren't included
+    let expected = [
+        SessionChange::Saved("alice1".to_owned()),
+        SessionChange::Saved("alice2".to_owned()),
+        SessionChange::Deleted("alice1".to_owned()),
+        SessionChange::Saved("alice3".to_owned()),
+        SessionChange::Deleted("alice3".to_owned()),
+    ];
+    for change in expected {
+        assert_eq!(changes.next().await, Some(change));
+    }
+
+    drop(changes);
+    storage.shutdown().await.unwrap();
+    if let Some(task) = cleanup_task {
+        task.await
+    }
+}
+
+#[test_case("memory"; "Memory")]
+#[test_case("redis"; "Redis Fred")]
+#[rocket::async_test]
+async fn notifies_sessions_invalidated_by_tag(storage_case: &str) {
+    let (storage, cleanup_task) = create_storage(storage_case).await;
+    storage.setup().await.unwrap();
+
+    let mut changes = storage
+        .subscribe_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+
+    storage.save("alice1", user("alice"), 60).await.unwrap();
+    storage.save("bob1", user("bob"), 60).await.unwrap();
+    let invalidated = storage.invalidate_sessions_by_tag("all").await.unwrap();
+    assert_eq!(invalidated, 2);
+
+    let expected = [
+        SessionChange::Saved("alice1".to_owned()),
+        SessionChange::Deleted("alice1".to_owned()),
+    ];
+    for change in expected {
+        assert_eq!(changes.next().await, Some(change));
+    }
+    let session_ids = storage
+        .get_session_ids_by_identifier(&"alice".to_owned())
+        .await
+        .unwrap();
+    assert!(session_ids.is_empty());
+
+    drop(changes);
+    storage.shutdown().await.unwrap();
+    if let Some(task) = cleanup_task {
+        task.await
+    }
+}
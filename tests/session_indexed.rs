@@ -25,21 +25,6 @@ impl SessionIdentifier for UserSession {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-struct AdminSession {
-    admin_id: String,
-    role: String,
-    permissions: Vec<String>,
-}
-
-impl SessionIdentifier for AdminSession {
-    type Id = String;
-
-    fn identifier(&self) -> Option<Self::Id> {
-        Some(self.admin_id.clone())
-    }
-}
-
 // Routes for testing user sessions
 #[get("/user/login/<user_id>/<username>")]
 async fn user_login(
@@ -96,6 +81,21 @@ async fn invalidate_other_user_sessions(session: Session<'_, UserSession>) -> St
     }
 }
 
+#[get("/user/invalidate-other-details")]
+async fn invalidate_other_user_sessions_with_details(session: Session<'_, UserSession>) -> String {
+    match session.invalidate_all_sessions_with_details(true).await {
+        Ok(Some(sessions)) => sessions
+            .iter()
+            .map(|(_, data, metadata)| {
+                format!("{}:{}", data.username, metadata.created_at.is_some())
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        Ok(None) => "No current session".to_string(),
+        Err(e) => format!("Error invalidating sessions: {e}"),
+    }
+}
+
 #[get("/user/invalidate-all/<user_id>")]
 async fn invalidate_sessions_for_user(
     session: Session<'_, UserSession>,
@@ -107,6 +107,21 @@ async fn invalidate_sessions_for_user(
     }
 }
 
+#[get("/user/rename/<username>")]
+async fn rename_user(mut session: Session<'_, UserSession>, username: String) -> String {
+    let result = session
+        .update_all_sessions(|data| UserSession {
+            username: username.clone(),
+            ..data
+        })
+        .await;
+    match result {
+        Ok(Some(n)) => format!("{n} other session(s) for current user updated."),
+        Ok(None) => "No current session".to_string(),
+        Err(e) => format!("Error updating sessions: {e}"),
+    }
+}
+
 #[get("/user/session-ids")]
 async fn get_user_session_ids(session: Session<'_, UserSession>) -> String {
     match session.get_all_session_ids().await {
@@ -145,7 +160,9 @@ fn rocket() -> Rocket<Build> {
             get_sessions_for_user,
             invalidate_all_user_sessions,
             invalidate_other_user_sessions,
+            invalidate_other_user_sessions_with_details,
             invalidate_sessions_for_user,
+            rename_user,
             get_user_session_ids,
             user_profile,
         ],
@@ -277,6 +294,86 @@ fn test_invalidate_other_sessions() {
         .contains("Profile for alice"));
 }
 
+#[test]
+fn test_invalidate_other_sessions_with_details() {
+    let client = create_test_client();
+
+    let response = client.get("/user/login/user1/alice").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    for username in ["alice-phone", "alice-tablet"] {
+        let response = client
+            .get(format!("/user/login/user1/{username}"))
+            .private_cookie("rocket")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = client.get("/user/invalidate-other-details").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_string().unwrap();
+    let mut invalidated: Vec<&str> = body.split(',').collect();
+    invalidated.sort();
+    assert_eq!(invalidated, ["alice-phone:true", "alice:true"]);
+
+    // The current session is still active, and the others are gone
+    let response = client.get("/user/profile").dispatch();
+    assert!(response
+        .into_string()
+        .unwrap()
+        .contains("Profile for alice-tablet"));
+    let response = client.get("/user/invalidate-other-details").dispatch();
+    assert_eq!(response.into_string().unwrap(), "");
+}
+
+#[test]
+fn test_update_all_sessions() {
+    let client = create_test_client();
+
+    for _ in 0..3 {
+        let response = client
+            .get("/user/login/user1/alice")
+            .private_cookie("rocket")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+    client
+        .get("/user/login/user2/bob")
+        .private_cookie("rocket")
+        .dispatch();
+    client
+        .get("/user/login/user1/alice")
+        .private_cookie("rocket")
+        .dispatch();
+
+    let response = client.get("/user/rename/alicia").dispatch();
+    assert_eq!(
+        response.into_string().unwrap(),
+        "3 other session(s) for current user updated."
+    );
+
+    // All sessions for the user are updated, including the current one
+    let response = client.get("/user/profile").dispatch();
+    assert!(response
+        .into_string()
+        .unwrap()
+        .contains("Profile for alicia"));
+    let body = client
+        .get("/user/sessions/user1")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert_eq!(body.matches("\"alicia\"").count(), 4);
+    assert!(!body.contains("\"alice\""));
+
+    // Other users' sessions aren't affected
+    let body = client
+        .get("/user/sessions/user2")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    assert!(body.contains("\"bob\""));
+}
+
 #[test]
 fn test_invalidate_sessions_by_user_id() {
     let client = create_test_client();
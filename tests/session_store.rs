@@ -0,0 +1,148 @@
+#[macro_use]
+extern crate rocket;
+
+use std::{collections::HashMap, sync::Mutex};
+
+use rocket::{
+    async_trait,
+    http::{Cookie, Status},
+    local::blocking::Client,
+};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{CookieStore, SessionContext, SessionStore},
+    RocketFlexSession, Session,
+};
+
+/// Store without expiration, keeping the data in a map
+#[derive(Default)]
+struct MapStore(Mutex<HashMap<String, String>>);
+
+#[async_trait]
+impl SessionStore<String> for MapStore {
+    async fn load(&self, id: &str, ttl: Option<u32>) -> SessionResult<(String, u32)> {
+        let sessions = self.0.lock().unwrap();
+        let data = sessions.get(id).cloned().ok_or(SessionError::NotFound)?;
+        Ok((data, ttl.unwrap_or(60)))
+    }
+
+    async fn save(&self, id: &str, data: String, _ttl: u32) -> SessionResult<()> {
+        self.0.lock().unwrap().insert(id.to_owned(), data);
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str, _data: String) -> SessionResult<()> {
+        self.0.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+const DATA_COOKIE: &str = "session_data";
+
+/// Store that keeps the data in a separate cookie
+struct DataCookieStore;
+
+#[async_trait]
+impl SessionStore<String> for DataCookieStore {
+    async fn load(&self, _id: &str, _ttl: Option<u32>) -> SessionResult<(String, u32)> {
+        Err(SessionError::NotFound)
+    }
+
+    async fn save(&self, _id: &str, _data: String, _ttl: u32) -> SessionResult<()> {
+        Ok(())
+    }
+
+    async fn delete(&self, _id: &str, _data: String) -> SessionResult<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CookieStore<String> for DataCookieStore {
+    async fn load_with_context(
+        &self,
+        _id: &str,
+        ttl: Option<u32>,
+        context: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        let cookie = context
+            .get_private(DATA_COOKIE)
+            .ok_or(SessionError::NotFound)?;
+        Ok((cookie.value().to_owned(), ttl.unwrap_or(60)))
+    }
+
+    fn save_cookie(
+        &self,
+        _id: &str,
+        data: Option<&String>,
+        _ttl: u32,
+        context: &dyn SessionContext,
+    ) -> SessionResult<()> {
+        match data {
+            Some(data) => context.add_private(Cookie::new(DATA_COOKIE, data.clone())),
+            None => context.remove_private(Cookie::from(DATA_COOKIE)),
+        }
+        Ok(())
+    }
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: String) {
+    session.set(name);
+}
+
+#[get("/user")]
+fn user(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+fn client(fairing: RocketFlexSession<String>) -> Client {
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![login, user, logout]);
+    Client::tracked(rocket).unwrap()
+}
+
+fn assert_login_and_logout(client: &Client) {
+    assert_eq!(
+        client.get("/user").dispatch().status(),
+        Status::Unauthorized
+    );
+    client.post("/login/alice").dispatch();
+    assert_eq!(
+        client.get("/user").dispatch().into_string().unwrap(),
+        "alice"
+    );
+    client.post("/logout").dispatch();
+    assert_eq!(
+        client.get("/user").dispatch().status(),
+        Status::Unauthorized
+    );
+}
+
+#[test]
+fn session_store() {
+    let fairing = RocketFlexSession::<String>::builder()
+        .store(MapStore::default())
+        .build();
+    assert_login_and_logout(&client(fairing));
+}
+
+#[test]
+fn cookie_store() {
+    let fairing = RocketFlexSession::<String>::builder()
+        .cookie_store(DataCookieStore)
+        .build();
+    let client = client(fairing);
+    client.post("/login/alice").dispatch();
+    assert!(client.cookies().get_private(DATA_COOKIE).is_some());
+    client.post("/logout").dispatch();
+    assert!(client.cookies().get_private(DATA_COOKIE).is_none());
+
+    assert_login_and_logout(&client);
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use rocket::tokio::time::{sleep, timeout};
+use rocket_flex_session::storage::{
+    layer::StorageStack,
+    memory::MemoryStorage,
+    shadow::{ShadowReadLayer, ShadowReadMetrics, ShadowReadStorage},
+    shared::SharedBackend,
+    NoopSessionContext, SessionStorage,
+};
+
+type Backend = SharedBackend<MemoryStorage<String>>;
+
+fn create_storage(
+    secondary: &Backend,
+    metrics: &ShadowReadMetrics,
+) -> ShadowReadStorage<MemoryStorage<String>, Backend> {
+    StorageStack::new(MemoryStorage::default())
+        .layer(ShadowReadLayer::new(secondary.clone()).metrics(metrics.clone()))
+        .build()
+}
+
+/// Wait for the background shadow reads to complete
+async fn wait_for(metrics: &ShadowReadMetrics, total: u64) {
+    let wait = async {
+        while metrics.total() < total {
+            sleep(Duration::from_millis(5)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), wait).await.unwrap();
+}
+
+async fn load(storage: &ShadowReadStorage<MemoryStorage<String>, Backend>, id: &str) {
+    let _: Result<(String, _), _> = storage.load(id, None, &NoopSessionContext).await;
+}
+
+#[rocket::async_test]
+async fn compares_loads_with_secondary() {
+    let secondary = SharedBackend::new(MemoryStorage::default());
+    let metrics = ShadowReadMetrics::default();
+    let storage = create_storage(&secondary, &metrics);
+
+    storage.save("same", "data".to_owned(), 60).await.unwrap();
+    secondary.save("same", "data".to_owned(), 60).await.unwrap();
+    load(&storage, "same").await;
+    load(&storage, "missing").await;
+    wait_for(&metrics, 2).await;
+    assert_eq!(metrics.matched(), 2);
+    assert_eq!(metrics.mismatched(), 0);
+
+    storage.save("stale", "new".to_owned(), 60).await.unwrap();
+    secondary.save("stale", "old".to_owned(), 60).await.unwrap();
+    storage
+        .save("primary", "data".to_owned(), 60)
+        .await
+        .unwrap();
+    secondary
+        .save("secondary", "data".to_owned(), 60)
+        .await
+        .unwrap();
+    for id in ["stale", "primary", "secondary"] {
+        load(&storage, id).await;
+    }
+    wait_for(&metrics, 5).await;
+    assert_eq!(metrics.mismatched(), 3);
+    assert_eq!(metrics.errors(), 0);
+}
+
+#[rocket::async_test]
+async fn serves_from_primary() {
+    let secondary = SharedBackend::new(MemoryStorage::default());
+    let metrics = ShadowReadMetrics::default();
+    let storage = create_storage(&secondary, &metrics);
+
+    storage.save("id", "primary".to_owned(), 60).await.unwrap();
+    secondary
+        .save("id", "secondary".to_owned(), 60)
+        .await
+        .unwrap();
+    let (data, _): (String, _) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "primary");
+    wait_for(&metrics, 1).await;
+    assert_eq!(metrics.mismatched(), 1);
+}
+
+#[rocket::async_test]
+async fn samples_sessions() {
+    let secondary = SharedBackend::new(MemoryStorage::default());
+    let metrics = ShadowReadMetrics::default();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(
+            ShadowReadLayer::new(secondary)
+                .sample_rate(0.0)
+                .metrics(metrics.clone()),
+        )
+        .build();
+
+    load(&storage, "id").await;
+    sleep(Duration::from_millis(50)).await;
+    assert_eq!(metrics.total(), 0);
+}
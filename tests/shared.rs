@@ -0,0 +1,137 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use rocket::{
+    async_trait,
+    http::Status,
+    local::blocking::Client,
+    serde::{de::DeserializeOwned, Deserialize, Serialize},
+};
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{memory::MemoryStorage, shared::SharedBackend, SessionContext, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct UserSession {
+    user_id: String,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct AdminSession {
+    admin_id: String,
+    level: u8,
+}
+
+/// Storage for any serializable session type (like the built-in Redis and sqlx storages),
+/// that counts setups and shutdowns
+#[derive(Default)]
+struct JsonStorage {
+    inner: MemoryStorage<String>,
+    setups: Arc<AtomicU32>,
+    shutdowns: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl<T> SessionStorage<T> for JsonStorage
+where
+    T: Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    async fn load(
+        &self,
+        id: &str,
+        ttl: Option<u32>,
+        cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(T, u32)> {
+        let (json, ttl) = self.inner.load(id, ttl, cookie_jar).await?;
+        let data = serde_json::from_str(&json).map_err(|_| SessionError::InvalidData)?;
+        Ok((data, ttl))
+    }
+
+    async fn save(&self, id: &str, data: T, ttl: u32) -> SessionResult<()> {
+        let json = serde_json::to_string(&data).map_err(|_| SessionError::InvalidData)?;
+        self.inner.save(id, json, ttl).await
+    }
+
+    async fn delete(&self, id: &str, _data: T) -> SessionResult<()> {
+        self.inner.delete(id, String::new()).await
+    }
+
+    async fn setup(&self) -> SessionResult<()> {
+        self.setups.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> SessionResult<()> {
+        self.shutdowns.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[post("/user/<user_id>")]
+fn user_login(mut session: Session<UserSession>, user_id: String) {
+    session.set(UserSession { user_id });
+}
+
+#[get("/user")]
+fn user(session: Session<UserSession>) -> Result<String, Status> {
+    let data = session.get().ok_or(Status::Unauthorized)?;
+    Ok(data.user_id)
+}
+
+#[post("/admin/<admin_id>")]
+fn admin_login(mut session: Session<AdminSession>, admin_id: String) {
+    session.set(AdminSession { admin_id, level: 2 });
+}
+
+#[get("/admin")]
+fn admin(session: Session<AdminSession>) -> Result<String, Status> {
+    let data = session.get().ok_or(Status::Unauthorized)?;
+    Ok(format!("{}:{}", data.admin_id, data.level))
+}
+
+#[test]
+fn fairings_share_one_backend() {
+    let storage = JsonStorage::default();
+    let (setups, shutdowns) = (storage.setups.clone(), storage.shutdowns.clone());
+    let backend = SharedBackend::new(storage);
+
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<UserSession>::builder()
+                .storage(backend.clone())
+                .with_options(|opt| opt.cookie_name = "user_session".to_owned())
+                .build(),
+        )
+        .attach(
+            RocketFlexSession::<AdminSession>::builder()
+                .storage(backend)
+                .with_options(|opt| opt.cookie_name = "admin_session".to_owned())
+                .build(),
+        )
+        .mount("/", routes![user_login, user, admin_login, admin]);
+    let client = Client::tracked(rocket).unwrap();
+    assert_eq!(setups.load(Ordering::Relaxed), 1);
+
+    client.post("/user/alice").dispatch();
+    client.post("/admin/bob").dispatch();
+    assert_eq!(
+        client.get("/user").dispatch().into_string().unwrap(),
+        "alice"
+    );
+    assert_eq!(
+        client.get("/admin").dispatch().into_string().unwrap(),
+        "bob:2"
+    );
+
+    client.terminate();
+    assert_eq!(shutdowns.load(Ordering::Relaxed), 1);
+}
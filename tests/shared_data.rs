@@ -0,0 +1,73 @@
+#[macro_use]
+extern crate rocket;
+
+use std::sync::Arc;
+
+use rocket::local::blocking::Client;
+use rocket_flex_session::{RocketFlexSession, Session};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Profile {
+    name: String,
+    permissions: Vec<String>,
+}
+
+#[post("/login")]
+fn login(mut session: Session<Profile>) {
+    let profile = Arc::new(Profile {
+        name: "alice".to_owned(),
+        permissions: vec!["read".to_owned(); 100],
+    });
+    session.set(profile);
+}
+
+#[get("/shared")]
+fn shared(session: Session<Profile>) -> String {
+    let first = session.get_arc().unwrap();
+    let second = session.get_arc().unwrap();
+    format!("{}:{}", first.name, Arc::ptr_eq(&first, &second))
+}
+
+#[post("/rename")]
+fn rename(mut session: Session<Profile>) -> String {
+    let before = session.get_arc().unwrap();
+    session.tap_mut(|data| {
+        if let Some(profile) = data {
+            profile.name = "bob".to_owned();
+        }
+    });
+    // The data is copied on write, so the earlier pointer is unchanged
+    let after = session.get_arc().unwrap();
+    format!("{}:{}", before.name, after.name)
+}
+
+fn client() -> Client {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<Profile>::default())
+        .mount("/", routes![login, shared, rename]);
+    Client::tracked(rocket).unwrap()
+}
+
+#[test]
+fn shares_data_without_cloning() {
+    let client = client();
+    client.post("/login").dispatch();
+    assert_eq!(
+        client.get("/shared").dispatch().into_string().unwrap(),
+        "alice:true"
+    );
+}
+
+#[test]
+fn copies_shared_data_on_write() {
+    let client = client();
+    client.post("/login").dispatch();
+    assert_eq!(
+        client.post("/rename").dispatch().into_string().unwrap(),
+        "alice:bob"
+    );
+    assert_eq!(
+        client.get("/shared").dispatch().into_string().unwrap(),
+        "bob:true"
+    );
+}
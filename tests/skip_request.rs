@@ -0,0 +1,67 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{
+    http::{Header, Status},
+    local::blocking::Client,
+    routes, Build, Rocket,
+};
+use rocket_flex_session::{bot::is_likely_bot, RocketFlexSession, Session};
+
+const BROWSER_UA: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0";
+const BOT_UA: &str = "Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)";
+
+#[post("/set_session")]
+fn set_session(mut session: Session<String>) -> &'static str {
+    session.set("active".to_owned());
+    "Session set"
+}
+
+#[get("/get_session")]
+fn get_session(session: Session<String>) -> Result<String, Status> {
+    session.get().ok_or(Status::Unauthorized)
+}
+
+fn create_rocket() -> Rocket<Build> {
+    rocket::build()
+        .attach(
+            RocketFlexSession::<String>::builder()
+                .skip_request(is_likely_bot)
+                .build(),
+        )
+        .mount("/", routes![set_session, get_session])
+}
+
+#[test]
+fn test_bot_request_skipped() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    let response = client
+        .post("/set_session")
+        .header(Header::new("User-Agent", BOT_UA))
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.cookies().get_private("rocket").is_none());
+}
+
+#[test]
+fn test_browser_request_not_skipped() {
+    let client = Client::tracked(create_rocket()).unwrap();
+    let response = client
+        .post("/set_session")
+        .header(Header::new("User-Agent", BROWSER_UA))
+        .dispatch();
+    assert!(response.cookies().get_private("rocket").is_some());
+
+    let response = client
+        .get("/get_session")
+        .header(Header::new("User-Agent", BROWSER_UA))
+        .dispatch();
+    assert_eq!(response.into_string().unwrap(), "active");
+
+    // Session isn't loaded for bots, even with a valid cookie
+    let response = client
+        .get("/get_session")
+        .header(Header::new("User-Agent", BOT_UA))
+        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+}
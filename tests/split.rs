@@ -0,0 +1,128 @@
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        memory::MemoryStorage,
+        shared::SharedBackend,
+        split::{SplitBackend, SplitTrafficMetrics, SplitTrafficStorage},
+        NoopSessionContext, SessionStorage,
+    },
+};
+
+type Backend = SharedBackend<MemoryStorage<String>>;
+
+fn backends() -> (Backend, Backend) {
+    (
+        SharedBackend::new(MemoryStorage::default()),
+        SharedBackend::new(MemoryStorage::default()),
+    )
+}
+
+fn split(
+    (current, candidate): &(Backend, Backend),
+    percentage: u8,
+) -> SplitTrafficStorage<Backend, Backend> {
+    SplitTrafficStorage::builder()
+        .current(current.clone())
+        .candidate(candidate.clone())
+        .percentage(percentage)
+        .build()
+}
+
+async fn exists(storage: &Backend, id: &str) -> bool {
+    SessionStorage::<String>::exists(storage, id, &NoopSessionContext)
+        .await
+        .unwrap()
+}
+
+#[rocket::async_test]
+async fn routes_percentage_of_sessions() {
+    let backends = backends();
+    let metrics = SplitTrafficMetrics::default();
+    let storage = SplitTrafficStorage::builder()
+        .current(backends.0.clone())
+        .candidate(backends.1.clone())
+        .percentage(30)
+        .metrics(metrics.clone())
+        .build();
+
+    let ids: Vec<_> = (0..1000).map(|i| format!("session-{i}")).collect();
+    for id in &ids {
+        storage.save(id, "data".to_owned(), 60).await.unwrap();
+    }
+    let candidate = metrics.save(SplitBackend::Candidate).calls;
+    assert_eq!(metrics.save(SplitBackend::Current).calls + candidate, 1000);
+    assert!((200..400).contains(&candidate), "{candidate} sessions");
+
+    // Each session is saved to (and loaded from) the storage it's routed to
+    for id in &ids {
+        let in_candidate = storage.route(id) == SplitBackend::Candidate;
+        assert_eq!(exists(&backends.1, id).await, in_candidate);
+        assert_eq!(exists(&backends.0, id).await, !in_candidate);
+        let (data, _): (String, _) = storage.load(id, None, &NoopSessionContext).await.unwrap();
+        assert_eq!(data, "data");
+    }
+    assert_eq!(metrics.load(SplitBackend::Candidate).calls, candidate);
+    assert_eq!(metrics.load(SplitBackend::Candidate).errors, 0);
+    assert!(metrics.average_latency(SplitBackend::Current).is_some());
+    assert_eq!(metrics.moved(), 0);
+}
+
+#[rocket::async_test]
+async fn moves_sessions_when_percentage_changes() {
+    let backends = backends();
+    let before = split(&backends, 0);
+    before.save("id", "data".to_owned(), 60).await.unwrap();
+    assert!(exists(&backends.0, "id").await);
+
+    let after = split(&backends, 100);
+    let (data, _): (String, _) = after.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "data");
+    assert!(!exists(&backends.0, "id").await);
+    assert!(exists(&backends.1, "id").await);
+    assert_eq!(after.metrics().moved(), 1);
+
+    // Rolling back moves the session back
+    let (data, _): (String, _) = before.load("id", None, &NoopSessionContext).await.unwrap();
+    assert_eq!(data, "data");
+    assert!(exists(&backends.0, "id").await);
+    assert!(!exists(&backends.1, "id").await);
+}
+
+#[rocket::async_test]
+async fn keeps_creation_time_when_moving() {
+    let backends = backends();
+    let before = split(&backends, 0);
+    before.save("id", "data".to_owned(), 60).await.unwrap();
+    let (_, _, metadata) =
+        SessionStorage::<String>::load_with_metadata(&backends.0, "id", None, &NoopSessionContext)
+            .await
+            .unwrap();
+    assert!(metadata.created_at.is_some());
+
+    let after = split(&backends, 100);
+    let (_, _, moved): (String, _, _) = after
+        .load_with_metadata("id", None, &NoopSessionContext)
+        .await
+        .unwrap();
+    assert_eq!(moved.created_at, metadata.created_at);
+    let (_, _, stored) =
+        SessionStorage::<String>::load_with_metadata(&backends.1, "id", None, &NoopSessionContext)
+            .await
+            .unwrap();
+    assert_eq!(stored.created_at, metadata.created_at);
+}
+
+#[rocket::async_test]
+async fn deletes_from_both_storages() {
+    let backends = backends();
+    split(&backends, 0)
+        .save("id", "data".to_owned(), 60)
+        .await
+        .unwrap();
+    let storage = split(&backends, 100);
+    storage.delete("id", "data".to_owned()).await.unwrap();
+    assert!(!exists(&backends.0, "id").await);
+
+    let result: Result<(String, _), _> = storage.load("id", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+}
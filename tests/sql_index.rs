@@ -0,0 +1,83 @@
+mod common;
+
+use rocket::time::{Duration, OffsetDateTime};
+use rocket_flex_session::storage::sql_index::SqlIndexSchema;
+use sqlx::Row;
+
+use crate::common::{setup_sqlite, teardown_sqlite};
+
+async fn insert_session(pool: &sqlx::SqlitePool, id: &str, identifiers: &[&str]) {
+    sqlx::query("INSERT INTO sessions (id, data, expires) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(format!("data for {id}"))
+        .bind(OffsetDateTime::now_utc() + Duration::hours(1))
+        .execute(pool)
+        .await
+        .unwrap();
+    let insert = SqlIndexSchema::table("session_identifiers")
+        .insert_identifier()
+        .unwrap();
+    for identifier in identifiers {
+        sqlx::query(&insert)
+            .bind(id)
+            .bind(identifier)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+}
+
+#[rocket::async_test]
+async fn index_table_with_multiple_identifiers() {
+    let pool = setup_sqlite().await;
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let schema = SqlIndexSchema::table("session_identifiers");
+    for statement in schema.create_index_table("sessions", "TEXT") {
+        sqlx::query(&statement).execute(&pool).await.unwrap();
+    }
+
+    insert_session(&pool, "sid1", &["user:1", "org:1"]).await;
+    insert_session(&pool, "sid2", &["user:2", "org:1"]).await;
+
+    let session_ids = |identifier: &'static str| {
+        let pool = pool.clone();
+        let sql = schema.session_ids("sessions");
+        async move {
+            let mut ids: Vec<String> = sqlx::query_scalar(&sql)
+                .bind(identifier)
+                .bind(OffsetDateTime::now_utc())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+            ids.sort();
+            ids
+        }
+    };
+    assert_eq!(session_ids("user:1").await, vec!["sid1"]);
+    assert_eq!(session_ids("org:1").await, vec!["sid1", "sid2"]);
+
+    let rows = sqlx::query(&schema.sessions("sessions"))
+        .bind("user:2")
+        .bind(OffsetDateTime::now_utc())
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<String, _>("id"), "sid2");
+    assert_eq!(rows[0].get::<String, _>("data"), "data for sid2");
+
+    // Invalidate all sessions of the organization except one
+    sqlx::query(&schema.invalidate_sessions("sessions", true))
+        .bind("org:1")
+        .bind("sid2")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(session_ids("user:1").await.is_empty());
+    assert_eq!(session_ids("org:1").await, vec!["sid2"]);
+
+    teardown_sqlite(pool).await;
+}
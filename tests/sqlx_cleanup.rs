@@ -0,0 +1,182 @@
+mod common;
+
+use std::time::Duration;
+
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        sqlx::{SessionSqlx, SqlxPostgresStorage, SqlxSqliteStorage},
+        NoopSessionContext, SessionStorage,
+    },
+    SessionIdentifier,
+};
+
+use crate::common::{
+    setup_postgres, setup_sqlite, teardown_postgres, teardown_sqlite, POSTGRES_URL,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+struct TestSession(String);
+
+impl SessionIdentifier for TestSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+impl SessionSqlx<sqlx::Sqlite> for TestSession {
+    type Error = SessionError;
+    type Data = String;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(TestSession(value))
+    }
+}
+
+impl SessionSqlx<sqlx::Postgres> for TestSession {
+    type Error = SessionError;
+    type Data = String;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(TestSession(value))
+    }
+}
+
+async fn save_sessions(storage: &impl SessionStorage<TestSession>) {
+    let data = TestSession("user".to_owned());
+    storage.save("expired", data.clone(), 0).await.unwrap();
+    storage.save("active", data, 60).await.unwrap();
+    rocket::tokio::time::sleep(Duration::from_millis(10)).await;
+}
+
+#[rocket::async_test]
+async fn sqlite_delete_expired() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .build();
+    save_sessions(&storage).await;
+
+    assert_eq!(storage.delete_expired().await.unwrap(), 1);
+    assert_eq!(storage.delete_expired().await.unwrap(), 0);
+    let exists = SessionStorage::<TestSession>::exists(&storage, "active", &NoopSessionContext);
+    assert!(exists.await.unwrap());
+
+    teardown_sqlite(pool).await;
+}
+
+#[rocket::async_test]
+async fn sqlite_delete_expired_in_batches() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .cleanup_batch_size(2)
+        .cleanup_batch_delay(Duration::from_millis(1))
+        .build();
+    for i in 0..5 {
+        let data = TestSession("user".to_owned());
+        storage.save(&format!("expired{i}"), data, 0).await.unwrap();
+    }
+    save_sessions(&storage).await;
+
+    assert_eq!(storage.delete_expired().await.unwrap(), 6);
+    let stats = storage.cleanup_stats();
+    assert_eq!(stats.runs(), 1);
+    assert_eq!(stats.last_deleted(), 6);
+
+    assert_eq!(storage.delete_expired().await.unwrap(), 0);
+    assert_eq!(stats.runs(), 2);
+    assert_eq!(stats.deleted(), 6);
+    assert_eq!(stats.last_deleted(), 0);
+    assert_eq!(stats.errors(), 0);
+
+    teardown_sqlite(pool).await;
+}
+
+#[rocket::async_test]
+async fn sqlite_cleanup_with_jitter() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .cleanup_interval(Duration::from_secs(60))
+        .cleanup_jitter(Duration::from_millis(50))
+        .build();
+    save_sessions(&storage).await;
+
+    // The first cleanup runs at startup, after the jitter delay
+    SessionStorage::<TestSession>::setup(&storage)
+        .await
+        .unwrap();
+    rocket::tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(storage.cleanup_stats().deleted(), 1);
+
+    SessionStorage::<TestSession>::shutdown(&storage)
+        .await
+        .unwrap();
+    teardown_sqlite(pool).await;
+}
+
+#[rocket::async_test]
+async fn postgres_cleanup_lock() {
+    let (pool, db_name) = setup_postgres(POSTGRES_URL).await;
+    let storage = SqlxPostgresStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .cleanup_lock(true)
+        .cleanup_batch_size(10)
+        .build();
+    save_sessions(&storage).await;
+
+    // Another instance holds the cleanup lock
+    let mut tx = pool.begin().await.unwrap();
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('sessions'))")
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+    assert_eq!(storage.delete_expired().await.unwrap(), 0);
+
+    tx.rollback().await.unwrap();
+    assert_eq!(storage.delete_expired().await.unwrap(), 1);
+
+    teardown_postgres(pool, db_name).await;
+}
+
+#[rocket::async_test]
+async fn sqlite_cleanup_stops_on_shutdown() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .cleanup_interval(Duration::from_millis(20))
+        .build();
+    assert!(!storage.is_cleanup_running().await);
+    SessionStorage::<TestSession>::setup(&storage)
+        .await
+        .unwrap();
+    assert!(storage.is_cleanup_running().await);
+    rocket::tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Shutdown waits for the task to finish, so no cleanups run afterwards
+    SessionStorage::<TestSession>::shutdown(&storage)
+        .await
+        .unwrap();
+    assert!(!storage.is_cleanup_running().await);
+    let stats = storage.cleanup_stats();
+    let runs = stats.runs();
+    assert!(runs > 0);
+    rocket::tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(stats.runs(), runs);
+
+    teardown_sqlite(pool).await;
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use rocket::time::Duration;
+use rocket_flex_session::{
+    error::SessionError,
+    storage::{
+        sqlx::{SessionSqlx, SqlxSqliteStorage},
+        NoopSessionContext, SessionStorage,
+    },
+    SessionIdentifier,
+};
+
+use crate::common::{setup_sqlite, teardown_sqlite};
+
+#[derive(Clone, Debug, PartialEq)]
+struct TestSession(String);
+
+impl SessionIdentifier for TestSession {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+impl SessionSqlx<sqlx::Sqlite> for TestSession {
+    type Error = SessionError;
+    type Data = String;
+
+    fn into_sql(self) -> Result<Self::Data, Self::Error> {
+        Ok(self.0)
+    }
+    fn from_sql(value: Self::Data) -> Result<Self, Self::Error> {
+        Ok(TestSession(value))
+    }
+}
+
+#[rocket::async_test]
+async fn measure_clock_skew() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .build();
+    assert_eq!(storage.clock_skew(), None);
+
+    // App and database share the same clock here
+    let skew = storage.measure_clock_skew().await.unwrap();
+    assert!(skew.abs() < Duration::seconds(1), "skew: {skew}");
+    assert_eq!(storage.clock_skew(), Some(skew));
+
+    teardown_sqlite(pool).await;
+}
+
+#[rocket::async_test]
+async fn skew_corrected_time() {
+    let pool = setup_sqlite().await;
+    let storage = SqlxSqliteStorage::builder()
+        .pool(pool.clone())
+        .table_name("sessions")
+        .skew_corrected_time(true)
+        .build();
+    SessionStorage::<TestSession>::setup(&storage)
+        .await
+        .unwrap();
+    assert!(storage.clock_skew().is_some());
+
+    storage
+        .save("session1", TestSession("user".to_owned()), 60)
+        .await
+        .unwrap();
+    let (data, ttl): (TestSession, _) = storage
+        .load("session1", None, &NoopSessionContext)
+        .await
+        .unwrap();
+    assert_eq!(data, TestSession("user".to_owned()));
+    assert!((59..=60).contains(&ttl), "ttl: {ttl}");
+
+    SessionStorage::<TestSession>::shutdown(&storage)
+        .await
+        .unwrap();
+    teardown_sqlite(pool).await;
+}
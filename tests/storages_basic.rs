@@ -13,6 +13,7 @@ use rocket_flex_session::{
     error::SessionError,
     storage::{
         cookie::CookieStorage,
+        libsql::{LibsqlStorage, SessionLibsql},
         redis::{RedisFormat, RedisFredStorage, RedisValue, SessionRedis},
         sqlx::{SessionSqlx, SqlxPostgresStorage, SqlxSqliteStorage},
     },
@@ -22,8 +23,8 @@ use serde::{Deserialize, Serialize};
 use test_case::test_case;
 
 use crate::common::{
-    setup_postgres, setup_redis_fred, setup_sqlite, teardown_postgres, teardown_redis_fred,
-    teardown_sqlite, POSTGRES_URL,
+    setup_libsql, setup_postgres, setup_redis_fred, setup_sqlite, teardown_libsql,
+    teardown_postgres, teardown_redis_fred, teardown_sqlite, POSTGRES_URL,
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -61,6 +62,16 @@ impl SessionSqlx<sqlx::Sqlite> for SessionData {
     }
 }
 
+impl SessionLibsql for SessionData {
+    type Error = SessionError;
+    fn into_sql(self) -> Result<String, Self::Error> {
+        Ok(self.user_id)
+    }
+    fn from_sql(value: String) -> Result<Self, Self::Error> {
+        Ok(Self { user_id: value })
+    }
+}
+
 impl SessionRedis for SessionData {
     const REDIS_FORMAT: RedisFormat = RedisFormat::Bytes;
     type Error = SessionError;
@@ -152,6 +163,32 @@ async fn create_rocket(
             let cleanup_task = teardown_sqlite(pool).boxed();
             (fairing, Some(cleanup_task))
         }
+        "sqlx_sqlite_tuned" => {
+            let pool = setup_sqlite().await;
+            let storage = SqlxSqliteStorage::builder()
+                .pool(pool.clone())
+                .table_name("sessions")
+                .wal(true)
+                .serialize_writes(true)
+                .build();
+            let fairing = RocketFlexSession::<SessionData>::builder()
+                .storage(storage)
+                .build();
+            let cleanup_task = teardown_sqlite(pool).boxed();
+            (fairing, Some(cleanup_task))
+        }
+        "libsql" => {
+            let (connection, table_name) = setup_libsql().await;
+            let storage = LibsqlStorage::builder()
+                .connection(connection.clone())
+                .table_name(&table_name)
+                .build();
+            let fairing = RocketFlexSession::<SessionData>::builder()
+                .storage(storage)
+                .build();
+            let cleanup_task = teardown_libsql(connection, table_name).boxed();
+            (fairing, Some(cleanup_task))
+        }
         _ => unimplemented!(),
     };
 
@@ -167,6 +204,8 @@ async fn create_rocket(
 #[test_case("redis"; "Redis Fred")]
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
+#[test_case("sqlx_sqlite_tuned"; "Sqlx SQLite tuned")]
+#[test_case("libsql"; "libSQL")]
 #[rocket::async_test]
 async fn test_storages(storage_case: &str) {
     let (rocket, cleanup_task) = create_rocket(storage_case).await;
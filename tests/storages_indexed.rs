@@ -6,6 +6,7 @@ use rocket::futures::FutureExt;
 use rocket_flex_session::{
     error::SessionError,
     storage::{
+        etcd::{EtcdStorage, SessionEtcd},
         memory::MemoryStorageIndexed,
         redis::{RedisFormat, RedisFredStorage, RedisValue, SessionRedis},
         sqlx::{SessionSqlx, SqlxPostgresStorage, SqlxSqliteStorage},
@@ -16,8 +17,8 @@ use rocket_flex_session::{
 use test_case::test_case;
 
 use crate::common::{
-    setup_postgres, setup_redis_fred, setup_sqlite, teardown_postgres, teardown_redis_fred,
-    teardown_sqlite, POSTGRES_URL,
+    setup_etcd, setup_postgres, setup_redis_fred, setup_sqlite, teardown_etcd, teardown_postgres,
+    teardown_redis_fred, teardown_sqlite, POSTGRES_URL,
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -32,6 +33,13 @@ impl SessionIdentifier for TestSession {
     fn identifier(&self) -> Option<Self::Id> {
         Some(self.user_id.clone())
     }
+
+    fn tags(&self) -> Vec<String> {
+        match self.data.starts_with("mobile") {
+            true => vec!["device-type=mobile".to_owned()],
+            false => Vec::new(),
+        }
+    }
 }
 
 impl SessionSqlx<sqlx::Postgres> for TestSession {
@@ -71,6 +79,26 @@ impl SessionSqlx<sqlx::Sqlite> for TestSession {
     }
 }
 
+impl SessionEtcd for TestSession {
+    type Error = std::io::Error;
+
+    fn into_etcd(self) -> Result<Vec<u8>, Self::Error> {
+        Ok(format!("{}:{}", self.user_id, self.data).into_bytes())
+    }
+    fn from_etcd(value: Vec<u8>) -> Result<Self, Self::Error> {
+        let value = String::from_utf8(value)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        let (user_id, data) = value.split_once(':').ok_or(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Invalid session format",
+        ))?;
+        Ok(TestSession {
+            user_id: user_id.to_string(),
+            data: data.to_string(),
+        })
+    }
+}
+
 impl SessionRedis for TestSession {
     const REDIS_FORMAT: RedisFormat = RedisFormat::Map;
 
@@ -118,6 +146,7 @@ async fn create_storage(
                 .pool(pool.clone())
                 .prefix(&prefix)
                 .index_prefix(format!("{prefix}user:"))
+                .tag_prefix(format!("{prefix}tag:"))
                 .build();
             let cleanup_task = teardown_redis_fred(pool, prefix).boxed();
             (Box::new(storage), Some(cleanup_task))
@@ -140,6 +169,16 @@ async fn create_storage(
             let cleanup_task = teardown_sqlite(pool).boxed();
             (Box::new(storage), Some(cleanup_task))
         }
+        "etcd" => {
+            let (client, prefix) = setup_etcd().await;
+            let storage = EtcdStorage::builder()
+                .client(client.clone())
+                .prefix(&prefix)
+                .index_prefix(format!("{prefix}user:"))
+                .build();
+            let cleanup_task = teardown_etcd(client, prefix).boxed();
+            (Box::new(storage), Some(cleanup_task))
+        }
         _ => unimplemented!(),
     }
 }
@@ -148,6 +187,7 @@ async fn create_storage(
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
 #[test_case("redis"; "Redis Fred")]
+#[test_case("etcd"; "etcd")]
 #[rocket::async_test]
 async fn basic_operations(storage_case: &str) {
     let (storage, cleanup_task) = create_storage(storage_case).await;
@@ -212,6 +252,7 @@ async fn basic_operations(storage_case: &str) {
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
 #[test_case("redis"; "Redis Fred")]
+#[test_case("etcd"; "etcd")]
 #[rocket::async_test]
 async fn invalidate_by_identifier(storage_case: &str) {
     let (storage, cleanup_task) = create_storage(storage_case).await;
@@ -283,6 +324,7 @@ async fn invalidate_by_identifier(storage_case: &str) {
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
 #[test_case("redis"; "Redis Fred")]
+#[test_case("etcd"; "etcd")]
 #[rocket::async_test]
 async fn invalidate_all_but_one_by_identifier(storage_case: &str) {
     let (storage, cleanup_task) = create_storage(storage_case).await;
@@ -344,6 +386,7 @@ async fn invalidate_all_but_one_by_identifier(storage_case: &str) {
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
 #[test_case("redis"; "Redis Fred")]
+#[test_case("etcd"; "etcd")]
 #[rocket::async_test]
 async fn delete_single_session(storage_case: &str) {
     let (storage, cleanup_task) = create_storage(storage_case).await;
@@ -395,6 +438,7 @@ async fn delete_single_session(storage_case: &str) {
 #[test_case("sqlx_postgres"; "Sqlx Postgres")]
 #[test_case("sqlx_sqlite"; "Sqlx SQLite")]
 #[test_case("redis"; "Redis Fred")]
+#[test_case("etcd"; "etcd")]
 #[rocket::async_test]
 async fn nonexistent_identifier(storage_case: &str) {
     let (storage, cleanup_task) = create_storage(storage_case).await;
@@ -428,3 +472,53 @@ async fn nonexistent_identifier(storage_case: &str) {
         task.await
     }
 }
+
+#[test_case("memory"; "Memory")]
+#[test_case("redis"; "Redis Fred")]
+#[rocket::async_test]
+async fn invalidate_by_tag(storage_case: &str) {
+    let (storage, cleanup_task) = create_storage(storage_case).await;
+    storage.setup().await.unwrap();
+
+    let sessions = [
+        ("sid1", "user1", "mobile_data"),
+        ("sid2", "user1", "desktop_data"),
+        ("sid3", "user2", "mobile_data"),
+    ];
+    for (id, user_id, data) in sessions {
+        let session = TestSession {
+            user_id: user_id.to_string(),
+            data: data.to_string(),
+        };
+        storage.save(id, session, 3600).await.unwrap();
+    }
+
+    let invalidated = storage
+        .invalidate_sessions_by_tag("device-type=mobile")
+        .await
+        .unwrap();
+    assert_eq!(invalidated, 2);
+
+    // Only the untagged session is left
+    let user1_session_ids = storage
+        .get_session_ids_by_identifier(&"user1".to_string())
+        .await
+        .unwrap();
+    assert_eq!(user1_session_ids, vec!["sid2".to_string()]);
+    let user2_session_ids = storage
+        .get_session_ids_by_identifier(&"user2".to_string())
+        .await
+        .unwrap();
+    assert!(user2_session_ids.is_empty());
+
+    let invalidated = storage
+        .invalidate_sessions_by_tag("device-type=mobile")
+        .await
+        .unwrap();
+    assert_eq!(invalidated, 0);
+
+    storage.shutdown().await.unwrap();
+    if let Some(task) = cleanup_task {
+        task.await
+    }
+}
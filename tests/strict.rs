@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{local::blocking::Client, response::stream::TextStream};
+use rocket_flex_session::{error::SessionError, RocketFlexSession, Session, SessionIdentifier};
+
+#[derive(Clone)]
+struct User(String);
+
+impl SessionIdentifier for User {
+    type Id = String;
+
+    fn identifier(&self) -> Option<Self::Id> {
+        Some(self.0.clone())
+    }
+}
+
+#[get("/stream")]
+fn stream(mut session: Session<'_, User>) -> TextStream![String + '_] {
+    TextStream! {
+        yield "start;".to_owned();
+        // The response has started, so the session has already been saved
+        match session.try_set(User("late".to_owned())).await {
+            Ok(()) => yield "saved".to_owned(),
+            Err(SessionError::ResponseStarted) => yield "denied".to_owned(),
+            Err(e) => yield e.to_string(),
+        }
+    }
+}
+
+#[get("/user")]
+fn user(session: Session<'_, User>) -> String {
+    session.get().map(|user| user.0).unwrap_or_default()
+}
+
+#[test]
+fn strict_mode_denies_writes_after_response() {
+    let fairing = RocketFlexSession::<User>::builder()
+        .with_options(|opt| opt.strict = true)
+        .build();
+    let rocket = rocket::build()
+        .attach(fairing)
+        .mount("/", routes![stream, user]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.get("/stream").dispatch();
+    assert_eq!(response.into_string().unwrap(), "start;denied");
+    assert_eq!(client.get("/user").dispatch().into_string().unwrap(), "");
+}
+
+#[test]
+fn writes_after_response_are_lost_without_strict_mode() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<User>::default())
+        .mount("/", routes![stream, user]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let response = client.get("/stream").dispatch();
+    assert_eq!(response.into_string().unwrap(), "start;saved");
+    assert_eq!(client.get("/user").dispatch().into_string().unwrap(), "");
+}
@@ -0,0 +1,89 @@
+#[macro_use]
+extern crate rocket;
+
+use rocket::{futures::future::join_all, http::Status, local::asynchronous::Client};
+use rocket_flex_session::{
+    storage::{memory::MemoryStorage, SessionStorage},
+    RocketFlexSession, Session,
+};
+
+#[post("/login")]
+fn login(mut session: Session<String>) {
+    session.set("user".to_owned());
+}
+
+#[post("/nonce/<nonce>")]
+async fn set_nonce(session: Session<'_, String>, nonce: &str) -> Status {
+    match session.set_once("email_nonce", nonce, 60).await.unwrap() {
+        true => Status::Ok,
+        false => Status::Unauthorized,
+    }
+}
+
+#[post("/verify/<nonce>")]
+async fn verify(session: Session<'_, String>, nonce: &str) -> Status {
+    match session.take_once("email_nonce").await.unwrap() {
+        Some(expected) if expected == nonce => Status::Ok,
+        _ => Status::Forbidden,
+    }
+}
+
+#[rocket::async_test]
+async fn values_can_only_be_taken_once() {
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::<String>::default())
+        .mount("/", routes![login, set_nonce, verify]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let status = client.post("/nonce/abc").dispatch().await.status();
+    assert_eq!(status, Status::Unauthorized);
+    client.post("/login").dispatch().await;
+    let status = client.post("/nonce/abc").dispatch().await.status();
+    assert_eq!(status, Status::Ok);
+
+    let requests = (0..10).map(|_| client.post("/verify/abc").dispatch());
+    let statuses: Vec<_> = join_all(requests)
+        .await
+        .iter()
+        .map(|response| response.status())
+        .collect();
+    let verified = statuses.iter().filter(|s| **s == Status::Ok).count();
+    assert_eq!(verified, 1);
+
+    // Setting the value again allows it to be taken again
+    client.post("/nonce/def").dispatch().await;
+    let status = client.post("/verify/def").dispatch().await.status();
+    assert_eq!(status, Status::Ok);
+}
+
+#[rocket::async_test]
+async fn memory_storage_take_is_atomic() {
+    let storage = MemoryStorage::<String>::default();
+    SessionStorage::<String>::save_once_value(&storage, "session", "key", "value".into(), 60)
+        .await
+        .unwrap();
+
+    let takes =
+        (0..20).map(|_| SessionStorage::<String>::take_once_value(&storage, "session", "key"));
+    let taken: Vec<_> = join_all(takes).await.into_iter().flatten().collect();
+    assert_eq!(taken.iter().filter(|value| value.is_some()).count(), 1);
+
+    let missing = SessionStorage::<String>::take_once_value(&storage, "other", "key").await;
+    assert_eq!(missing.unwrap(), None);
+}
+
+#[rocket::async_test]
+async fn memory_storage_deletes_values_with_session() {
+    let storage = MemoryStorage::<String>::default();
+    storage
+        .save("session", "user".to_owned(), 60)
+        .await
+        .unwrap();
+    SessionStorage::<String>::save_once_value(&storage, "session", "key", "value".into(), 60)
+        .await
+        .unwrap();
+
+    storage.delete("session", "user".to_owned()).await.unwrap();
+    let taken = SessionStorage::<String>::take_once_value(&storage, "session", "key").await;
+    assert_eq!(taken.unwrap(), None);
+}
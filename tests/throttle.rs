@@ -0,0 +1,101 @@
+mod common;
+
+use std::{future::Future, pin::Pin};
+
+use rocket::futures::FutureExt;
+use rocket_flex_session::{
+    storage::{
+        memory::MemoryThrottle,
+        redis::RedisFredThrottle,
+        sqlx::{SqlxPostgresThrottle, SqlxSqliteThrottle},
+    },
+    throttle::LoginThrottle,
+};
+use test_case::test_case;
+
+use crate::common::{
+    setup_postgres, setup_redis_fred, setup_sqlite, teardown_postgres, teardown_redis_fred,
+    teardown_sqlite, POSTGRES_URL,
+};
+
+async fn create_throttle(
+    throttle_case: &str,
+) -> (
+    Box<dyn LoginThrottle>,
+    Option<Pin<Box<dyn Future<Output = ()>>>>,
+) {
+    match throttle_case {
+        "memory" => (
+            Box::new(MemoryThrottle::builder().max_attempts(3).build()),
+            None,
+        ),
+        "redis" => {
+            let (pool, prefix) = setup_redis_fred().await;
+            let throttle = RedisFredThrottle::builder()
+                .pool(pool.clone())
+                .prefix(format!("{prefix}throttle:"))
+                .max_attempts(3)
+                .build();
+            let cleanup_task = teardown_redis_fred(pool, prefix).boxed();
+            (Box::new(throttle), Some(cleanup_task))
+        }
+        "sqlx_postgres" => {
+            let (pool, db_name) = setup_postgres(POSTGRES_URL).await;
+            sqlx::query(
+                "CREATE TABLE login_attempts (identifier TEXT PRIMARY KEY, attempts INTEGER NOT NULL, expires TIMESTAMPTZ NOT NULL)",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            let throttle = SqlxPostgresThrottle::builder()
+                .pool(pool.clone())
+                .max_attempts(3)
+                .build();
+            let cleanup_task = teardown_postgres(pool, db_name).boxed();
+            (Box::new(throttle), Some(cleanup_task))
+        }
+        "sqlx_sqlite" => {
+            let pool = setup_sqlite().await;
+            sqlx::query(
+                "CREATE TABLE login_attempts (identifier TEXT NOT NULL PRIMARY KEY, attempts INTEGER NOT NULL, expires TEXT NOT NULL)",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            let throttle = SqlxSqliteThrottle::builder()
+                .pool(pool.clone())
+                .max_attempts(3)
+                .build();
+            let cleanup_task = teardown_sqlite(pool).boxed();
+            (Box::new(throttle), Some(cleanup_task))
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test_case("memory"; "Memory")]
+#[test_case("redis"; "Redis Fred")]
+#[test_case("sqlx_postgres"; "Sqlx Postgres")]
+#[test_case("sqlx_sqlite"; "Sqlx SQLite")]
+#[rocket::async_test]
+async fn lockout_after_max_attempts(throttle_case: &str) {
+    let (throttle, cleanup_task) = create_throttle(throttle_case).await;
+
+    assert_eq!(throttle.failed_attempts("alice").await.unwrap(), 0);
+    assert!(!throttle.is_locked_out("alice").await.unwrap());
+
+    for expected in 1..=3 {
+        let attempts = throttle.record_failed_login("alice").await.unwrap();
+        assert_eq!(attempts, expected);
+    }
+    assert!(throttle.is_locked_out("alice").await.unwrap());
+    assert!(!throttle.is_locked_out("bob").await.unwrap());
+
+    throttle.reset_failed_logins("alice").await.unwrap();
+    assert_eq!(throttle.failed_attempts("alice").await.unwrap(), 0);
+    assert!(!throttle.is_locked_out("alice").await.unwrap());
+
+    if let Some(task) = cleanup_task {
+        task.await
+    }
+}
@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use rocket::async_trait;
+use rocket_flex_session::{
+    error::{SessionError, SessionResult},
+    storage::{
+        layer::{MetricsLayer, StorageMetrics, StorageStack},
+        memory::MemoryStorage,
+        tiered::{MokaCache, SessionCache, TieredLayer},
+        NoopSessionContext, SessionContext, SessionStorage,
+    },
+};
+
+#[rocket::async_test]
+async fn loads_are_served_from_cache() {
+    let metrics = StorageMetrics::default();
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(MetricsLayer::new(metrics.clone()))
+        .layer(TieredLayer::new(MokaCache::default()))
+        .build();
+
+    storage.save("id", "data".to_owned(), 60).await.unwrap();
+    for _ in 0..3 {
+        let (data, ttl) = storage.load("id", None, &NoopSessionContext).await.unwrap();
+        assert_eq!(data, "data");
+        assert!(ttl <= 60);
+    }
+    assert_eq!(metrics.load().calls, 0);
+
+    // Loading with a new TTL goes to the inner storage
+    let (_, ttl) = storage
+        .load("id", Some(120), &NoopSessionContext)
+        .await
+        .unwrap();
+    assert_eq!(ttl, 120);
+    assert_eq!(metrics.load().calls, 1);
+
+    storage.delete("id", "data".to_owned()).await.unwrap();
+    let result = storage.load("id", None, &NoopSessionContext).await;
+    assert!(result.is_err());
+    assert_eq!(metrics.load().calls, 2);
+}
+
+#[rocket::async_test]
+async fn moka_cache_expiration() {
+    let cache = MokaCache::<String>::builder()
+        .max_ttl(Duration::from_secs(60))
+        .build();
+    cache.set("id", "data".to_owned(), 1).await;
+    assert_eq!(cache.get("id").await, Some(("data".to_owned(), 1)));
+
+    rocket::tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(cache.get("id").await, None);
+
+    cache.set("id", "data".to_owned(), 60).await;
+    cache.invalidate("id").await;
+    assert_eq!(cache.get("id").await, None);
+}
+
+/// Storage with a fixed list of recently active sessions, that can't load sessions
+struct RecentStorage;
+
+#[async_trait]
+impl SessionStorage<String> for RecentStorage {
+    async fn load(
+        &self,
+        _id: &str,
+        _ttl: Option<u32>,
+        _cookie_jar: &dyn SessionContext,
+    ) -> SessionResult<(String, u32)> {
+        Err(SessionError::NotFound)
+    }
+
+    async fn save(&self, _id: &str, _data: String, _ttl: u32) -> SessionResult<()> {
+        Ok(())
+    }
+
+    async fn delete(&self, _id: &str, _data: String) -> SessionResult<()> {
+        Ok(())
+    }
+
+    async fn recently_active(&self, limit: usize) -> SessionResult<Vec<(String, String, u32)>> {
+        let sessions = (0..3).map(|i| (format!("id{i}"), format!("data{i}"), 60));
+        Ok(sessions.take(limit).collect())
+    }
+}
+
+#[rocket::async_test]
+async fn warmup_preloads_recent_sessions() {
+    let metrics = StorageMetrics::default();
+    let storage = StorageStack::new(RecentStorage)
+        .layer(MetricsLayer::new(metrics.clone()))
+        .layer(TieredLayer::new(MokaCache::default()).warmup(2))
+        .build();
+    storage.setup().await.unwrap();
+
+    for i in 0..2 {
+        let (data, _) = storage
+            .load(&format!("id{i}"), None, &NoopSessionContext)
+            .await
+            .unwrap();
+        assert_eq!(data, format!("data{i}"));
+    }
+    assert_eq!(metrics.load().calls, 0);
+
+    let result = storage.load("id2", None, &NoopSessionContext).await;
+    assert!(matches!(result, Err(SessionError::NotFound)));
+    assert_eq!(metrics.load().calls, 1);
+}
+
+#[rocket::async_test]
+async fn warmup_skipped_when_unsupported() {
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(TieredLayer::new(MokaCache::default()).warmup(2))
+        .build();
+    storage.setup().await.unwrap();
+}
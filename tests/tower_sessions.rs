@@ -0,0 +1,135 @@
+#[macro_use]
+extern crate rocket;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rocket::{
+    async_trait,
+    local::blocking::Client,
+    serde::{json::serde_json::json, Deserialize, Serialize},
+    time::{Duration, OffsetDateTime},
+};
+use rocket_flex_session::{
+    storage::{
+        tower_sessions::{tower_session_id, TowerSessionsStorage},
+        SessionStorage,
+    },
+    RocketFlexSession, Session,
+};
+use tower_sessions_core::{
+    session::{Id, Record},
+    session_store, SessionStore,
+};
+
+#[derive(Clone, Debug, Default)]
+struct TestStore(Arc<Mutex<HashMap<Id, Record>>>);
+
+#[async_trait]
+impl SessionStore for TestStore {
+    async fn save(&self, record: &Record) -> session_store::Result<()> {
+        self.0.lock().unwrap().insert(record.id, record.clone());
+        Ok(())
+    }
+
+    async fn load(&self, id: &Id) -> session_store::Result<Option<Record>> {
+        Ok(self.0.lock().unwrap().get(id).cloned())
+    }
+
+    async fn delete(&self, id: &Id) -> session_store::Result<()> {
+        self.0.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct User {
+    name: String,
+}
+
+#[post("/login/<name>")]
+fn login(mut session: Session<User>, name: &str) -> String {
+    session.set(User {
+        name: name.to_owned(),
+    });
+    session.id().unwrap()
+}
+
+#[get("/user")]
+fn user(session: Session<User>) -> Option<String> {
+    session.tap(|user| user.map(|u| u.name.clone()))
+}
+
+#[test]
+fn share_sessions_with_tower_store() {
+    let store = TestStore::default();
+    let storage = TowerSessionsStorage::builder()
+        .store(store.clone())
+        .data_key("user")
+        .build();
+    let rocket = rocket::build()
+        .attach(
+            RocketFlexSession::<User>::builder()
+                .storage(storage)
+                .build(),
+        )
+        .mount("/", routes![login, user]);
+    let client = Client::tracked(rocket).unwrap();
+
+    let session_id = client
+        .post("/login/alice")
+        .dispatch()
+        .into_string()
+        .unwrap();
+    let record = store.0.lock().unwrap()[&tower_session_id(&session_id)].clone();
+    assert_eq!(record.data["user"], json!({ "name": "alice" }));
+    assert!(record.expiry_date > OffsetDateTime::now_utc());
+
+    // Keys set by the other service are kept when Rocket saves the session
+    store
+        .0
+        .lock()
+        .unwrap()
+        .get_mut(&tower_session_id(&session_id))
+        .unwrap()
+        .data
+        .insert("cart".to_owned(), json!([1, 2]));
+    client.post("/login/bob").dispatch();
+    let record = store.0.lock().unwrap()[&tower_session_id(&session_id)].clone();
+    assert_eq!(record.data["user"], json!({ "name": "bob" }));
+    assert_eq!(record.data["cart"], json!([1, 2]));
+
+    let response = client.get("/user").dispatch();
+    assert_eq!(response.into_string().unwrap(), "bob");
+}
+
+#[rocket::async_test]
+async fn load_session_created_by_tower() {
+    let store = TestStore::default();
+    let id = Id::default();
+    store
+        .save(&Record {
+            id,
+            data: HashMap::from([("name".to_owned(), json!("carol"))]),
+            expiry_date: OffsetDateTime::now_utc() + Duration::hours(1),
+        })
+        .await
+        .unwrap();
+
+    // Without a data key, the fields of the session data are the keys of the record
+    let storage = TowerSessionsStorage::builder().store(store).build();
+    let client = rocket::local::asynchronous::Client::untracked(rocket::build())
+        .await
+        .unwrap();
+    let req = client.get("/");
+    let (user, ttl): (User, u32) = storage
+        .load(&id.to_string(), None, req.inner().cookies())
+        .await
+        .unwrap();
+    assert_eq!(user.name, "carol");
+    assert!(ttl > 3500 && ttl <= 3600);
+    assert_eq!(tower_session_id(&id.to_string()), id);
+}
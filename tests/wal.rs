@@ -0,0 +1,118 @@
+#[macro_use]
+extern crate rocket;
+
+use std::path::PathBuf;
+
+use rocket::local::asynchronous::Client;
+use rocket_flex_session::{
+    storage::{
+        envelope::EnvelopeCodec,
+        layer::StorageStack,
+        memory::MemoryStorage,
+        wal::{FileWal, ReplayStats, WalLayer, WalOp, WalSink},
+        NoopSessionContext, SessionStorage,
+    },
+    RocketFlexSession, Session,
+};
+
+#[post("/login/<name>")]
+fn login(mut session: Session<String>, name: String) {
+    session.set(name);
+}
+
+#[post("/logout")]
+fn logout(mut session: Session<String>) {
+    session.delete();
+}
+
+fn wal_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rfs-{name}-{}.wal", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+async fn client(wal: WalLayer<String>) -> Client {
+    let storage = StorageStack::new(MemoryStorage::<String>::default())
+        .layer(wal)
+        .build();
+    let rocket = rocket::build()
+        .attach(RocketFlexSession::builder().storage(storage).build())
+        .mount("/", routes![login, logout]);
+    Client::tracked(rocket).await.unwrap()
+}
+
+#[rocket::async_test]
+async fn records_and_replays_mutations() {
+    let path = wal_path("replay");
+    let wal = WalLayer::new(FileWal::new(&path), EnvelopeCodec::default());
+
+    let alice = client(wal.clone()).await;
+    alice.post("/login/alice").dispatch().await;
+    let bob = client(wal.clone()).await;
+    bob.post("/login/bob").dispatch().await;
+    bob.post("/logout").dispatch().await;
+
+    let records = FileWal::new(&path).read().await.unwrap();
+    let ops: Vec<_> = records.iter().map(|r| r.op).collect();
+    assert_eq!(ops, vec![WalOp::Save, WalOp::Save, WalOp::Delete]);
+    assert!(records[0].expires.is_some());
+    assert_eq!(records[0].hash.len(), 64);
+
+    // Replay into an empty storage, e.g. after restoring an older backup
+    let restored = MemoryStorage::<String>::default();
+    let stats = wal.replay(&restored, None).await.unwrap();
+    assert_eq!(
+        stats,
+        ReplayStats {
+            saved: 1,
+            ..Default::default()
+        }
+    );
+    let (data, _) = restored
+        .load(&records[0].id, None, &NoopSessionContext)
+        .await
+        .unwrap();
+    assert_eq!(data, "alice");
+    assert!(restored
+        .load(&records[1].id, None, &NoopSessionContext)
+        .await
+        .is_err());
+
+    // A restored session that was deleted later is deleted again
+    restored
+        .save(&records[1].id, "bob".to_owned(), 60)
+        .await
+        .unwrap();
+    let stats = wal.replay(&restored, None).await.unwrap();
+    assert_eq!(stats.deleted, 1);
+
+    // Records before the given time are ignored
+    let stats = wal
+        .replay(&restored, Some(rocket::time::OffsetDateTime::now_utc()))
+        .await
+        .unwrap();
+    assert_eq!(stats, ReplayStats::default());
+
+    let _ = std::fs::remove_file(path);
+}
+
+#[rocket::async_test]
+async fn hash_only_records_are_not_replayed() {
+    let path = wal_path("hash-only");
+    let wal = WalLayer::new(FileWal::new(&path), EnvelopeCodec::default()).include_data(false);
+
+    let client = client(wal.clone()).await;
+    client.post("/login/alice").dispatch().await;
+
+    let records = FileWal::new(&path).read().await.unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].data, None);
+
+    let stats = wal
+        .replay(&MemoryStorage::<String>::default(), None)
+        .await
+        .unwrap();
+    assert_eq!(stats.skipped, 1);
+
+    let _ = std::fs::remove_file(path);
+}